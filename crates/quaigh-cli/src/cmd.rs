@@ -0,0 +1,2616 @@
+//! Command line interface
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use quaigh::atpg::{
+    add_observation_points, diagnose, expose_dff, generate_comb_test_patterns,
+    generate_iddq_test_patterns, generate_random_seq_patterns, generate_transition_test_patterns,
+    report_comb_test_patterns, suggest_observation_points, FaultGrader, LaunchOnCapture,
+};
+use quaigh::equiv::{
+    align_black_boxes, check_equivalence_bounded, check_equivalence_bounded_by_output,
+    check_equivalence_bounded_with_learning, difference, normalize_miter, unroll, unrolled_size,
+    LearnReport, SatBackend, WitnessBundle,
+};
+use quaigh::io::{
+    create_pattern_file, is_tester_pattern_file, open_pattern_file, read_constraint_file,
+    read_delay_file, read_network_file_full, read_pattern_file, read_stimulus_file, write_dot_file,
+    write_network_file_with_names, write_network_file_with_provenance, write_tester_pattern_file,
+    BenchArity, BenchDialect, DotHighlight, Provenance, ReadOptions, ReadResult, TesterSignals,
+};
+use quaigh::network::area::AreaParameters;
+use quaigh::network::depth::DelayParameters;
+use quaigh::network::timing::Slacks;
+use quaigh::network::{
+    BusMap, GrowthLimitExceeded, HierarchyMap, NameTable, SourceMap, DEFAULT_MAX_NODES,
+};
+use quaigh::optim;
+use quaigh::sim::{
+    simulate, simulate_comb, simulate_stream, simulate_timing, simulate_with_clocks,
+    simulate_xprop_stream, Fault, FaultWeights,
+};
+use quaigh::util::format::{count, duration};
+use quaigh::util::rng::{SeededRng, DEFAULT_SEED};
+use quaigh::{Gate, Network, QuaighError, Signal};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::config::Config;
+
+/// Command line arguments
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+#[command(propagate_version = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Commands,
+
+    /// Print raw numbers, without thousands separators or units, for script consumers
+    #[arg(long, global = true)]
+    pub raw_numbers: bool,
+
+    /// Dialect of .bench files for flip-flops: iscas DFF(d), abc DFF(d, init), itc99 DFF(clk, d), or auto
+    #[arg(long, global = true, default_value_t = BenchDialect::Auto)]
+    pub dialect: BenchDialect,
+
+    /// Gates of .bench files with missing inputs: strict rejects them, pad ties the inputs to a constant
+    #[arg(long, global = true, default_value_t = BenchArity::Strict)]
+    pub arity: BenchArity,
+
+    /// Sat solver: kissat, cadical or builtin, a slower solver with no native dependency
+    #[arg(long, global = true, default_value_t = SatBackend::default())]
+    pub sat_backend: SatBackend,
+}
+
+impl Cli {
+    /// Record the options to read network files given on the command line
+    pub fn set_read_options(&self) {
+        let options = ReadOptions {
+            dialect: self.dialect,
+            arity: self.arity,
+        };
+        READ_OPTIONS.set(options).ok();
+    }
+}
+
+/// Options to read network files, set once from the command line
+static READ_OPTIONS: OnceLock<ReadOptions> = OnceLock::new();
+
+/// Read a network file with the options given on the command line
+fn read_file_full(path: &PathBuf) -> Result<ReadResult, QuaighError> {
+    let options = READ_OPTIONS.get().copied().unwrap_or_default();
+    read_network_file_full(path, &options)
+}
+
+/// Command line arguments
+#[derive(Subcommand)]
+pub enum Commands {
+    /// Show statistics about a logic network
+    ///
+    /// Will print statistics on the number of inputs, outputs and gates in the network.
+    #[clap()]
+    Show(ShowArgs),
+
+    /// Optimize a logic network
+    ///
+    /// At the moment this is simple constant propagation and deduplication,
+    /// but will grow in power over time.
+    #[clap(visible_alias = "opt")]
+    Optimize(OptArgs),
+
+    /// Simulate a logic network
+    ///
+    /// This uses the same test pattern format as Atalanta, with one bit per input:
+    ///    1: 00011101
+    ///    2: 01110000
+    #[clap(visible_alias = "sim")]
+    Simulate(SimulateArgs),
+
+    /// Test pattern generation for a logic network
+    ///
+    /// Generate patterns to find all possible faults in a design, assuming
+    /// that the primary inputs, outputs and flip-flops can be scanned.
+    /// Full fault coverage is achieved using a SAT solver.
+    ///
+    /// Fault types are:
+    ///   * Output stuck-at fault, where the output of the gate is stuck at a constant value
+    ///   * Input stuck-at fault, where the input of the gate is stuck at a constant value
+    ///   * With --transition, slow-to-rise and slow-to-fall faults on the outputs and inputs of
+    ///     gates, tested with two-cycle launch-on-capture patterns
+    ///
+    /// With --iddq, faults only need to be activated, for quiescent current measurements.
+    ///
+    /// Patterns are written in Atalanta format, or in STIL or WGL format with their expected
+    /// responses if the output file has a .stil or .wgl extension. Flip-flops are then accessed
+    /// through a mux-scan chain, in the order of the network.
+    ///
+    /// Patterns are compressed with a greedy algorithm. With --dynamic-compaction, each pattern
+    /// found by the SAT solver targets several faults; with --exact-compaction, a minimum set of
+    /// patterns is selected with the SAT solver.
+    #[clap()]
+    Atpg(AtpgArgs),
+
+    /// Analyze the results of test pattern generation
+    #[clap(hide = true, alias = "grade")]
+    AtpgReport(AtpgReportArgs),
+
+    /// Find the faults that explain the failures of a device
+    ///
+    /// Takes the test patterns applied to the device and the responses observed on its outputs,
+    /// in the same format as the simulation results, and ranks the stuck-at faults by how well
+    /// their simulated responses match the observed ones.
+    #[clap()]
+    Diagnose(DiagnoseArgs),
+
+    /// Check equivalence between two logic networks
+    ///
+    /// The command will fail if the two networks are not equivalent, and will output the
+    /// failing test pattern.
+    ///
+    /// Black boxes of .blif files are matched by instance: their outputs are shared between the
+    /// two networks, and their inputs must be equivalent.
+    #[clap(visible_alias = "equiv")]
+    CheckEquivalence(EquivArgs),
+
+    /// Check equivalence for many pairs of logic networks listed in a manifest
+    ///
+    /// Each line of the manifest gives an original and an optimized network, and optionally the
+    /// number of clock cycles, an output map and a time limit in seconds:
+    ///    original,optimized,cycles,output_map,time_limit
+    /// Results are printed as they complete. The command fails if any pair is not proven equivalent.
+    #[clap()]
+    EquivBatch(EquivBatchArgs),
+
+    /// Report the structural differences between two logic networks
+    ///
+    /// Lists the gates added or removed for each type, the outputs whose cone of logic has a
+    /// different structure, and the outputs that depend on different inputs, including through
+    /// flip-flops. Inputs and outputs are matched by index.
+    #[clap()]
+    Diff(DiffArgs),
+
+    /// Read a logic network and write it in another format
+    #[clap()]
+    Convert(ConvertArgs),
+
+    /// Map a logic network to a generic cell library
+    ///
+    /// The combinatorial logic is decomposed into the cells of a built-in library, without the
+    /// need for a Liberty file: two-input Nand gates only, two-input Nor gates only, or a set
+    /// of And-Or-Invert and Or-And-Invert cells. Flip-flops and latches are kept as is.
+    #[clap()]
+    Map(MapArgs),
+
+    /// Extract the logic feeding some outputs into a new network
+    ///
+    /// The new network only has the selected outputs, and the inputs and gates in their cone of
+    /// influence, including through flip-flops. Outputs are given by name, or as o3 for the fourth
+    /// output.
+    #[clap()]
+    Extract(ExtractArgs),
+
+    /// Run internal consistency checks
+    ///
+    /// At the moment this checks that multi-pattern sequential simulation, with and without faults,
+    /// matches single-pattern simulation on random designs.
+    #[clap()]
+    Selftest(SelftestArgs),
+
+    /// Explore a logic network interactively
+    ///
+    /// Opens a terminal interface with a searchable list of nodes and the details of the
+    /// selected one: gate, fanins and fanouts, logic level, and truth table for small supports.
+    /// Nodes can be marked and their cone exported to a file.
+    #[clap()]
+    Inspect(InspectArgs),
+
+    /// Replay a witness bundle written by a failed equivalence check
+    ///
+    /// The command will fail if the recorded failure cannot be reproduced.
+    #[clap()]
+    ReplayWitness(ReplayWitnessArgs),
+
+    /// Check whether two internal signals can differ, under constraints on the inputs
+    ///
+    /// Signals are given by name, or as x12 for nodes and i3 for inputs. Only their cones are
+    /// encoded, and flip-flops are free. Prints an assignment if the signals can differ.
+    #[clap()]
+    Query(QueryArgs),
+
+    /// Prove that a property holds at every clock cycle of a sequential network
+    ///
+    /// The property is an output that must always be 1, starting from the state where all
+    /// flip-flops are 0. The proof uses property directed reachability (IC3/PDR), so that it holds
+    /// for any number of cycles. The command fails with a counterexample if the property fails, and
+    /// with exit code 2 if no conclusion is reached.
+    #[clap()]
+    Prove(ProveArgs),
+
+    /// Compare optimization results on a suite of designs against a stored baseline
+    ///
+    /// Each design file of the suite is optimized twice, to check that the results are
+    /// deterministic. The command fails if the area, depth or runtime of a design regressed.
+    #[clap()]
+    BenchOpt(BenchOptArgs),
+
+    /// Check the runtime of the optimization passes against stored budgets
+    ///
+    /// A fixed set of synthetic designs goes through each pass. Budgets are scaled by the speed of
+    /// the machine, measured with a calibration loop. The command fails if a pass exceeds its budget.
+    #[clap()]
+    BenchPerf(BenchPerfArgs),
+
+    /// Manage configuration files
+    #[clap()]
+    Config(ConfigArgs),
+}
+
+/// Command arguments for equivalence checking
+#[derive(Args)]
+pub struct EquivArgs {
+    /// First network to compare
+    file1: PathBuf,
+    /// Second network to compare
+    file2: PathBuf,
+
+    /// Number of clock cycles considered
+    #[arg(short = 'c', long, default_value_t = 1)]
+    num_cycles: usize,
+
+    /// Use only the Sat solver, skipping internal optimizations
+    #[arg(long)]
+    sat_only: bool,
+
+    /// Run the full check even if both files carry the same fingerprint
+    #[arg(long)]
+    force_full_check: bool,
+
+    /// Write a replayable witness bundle if the networks are not equivalent
+    #[arg(long, value_name = "TAR")]
+    witness_bundle: Option<PathBuf>,
+
+    /// Learn constants and implications by simulation, and give them to the Sat solver
+    #[arg(long)]
+    learn: bool,
+
+    /// Report the reduction of the miter by normalization and, with --learn, the facts learned
+    /// and the time spent in the main Sat solve
+    #[arg(long)]
+    verbose: bool,
+
+    /// Maximum number of nodes of each unrolled network
+    #[arg(long, default_value_t = DEFAULT_MAX_NODES)]
+    max_nodes: usize,
+
+    /// Check each output separately, and report all mismatching outputs
+    #[arg(long)]
+    by_output: bool,
+
+    /// Number of threads checking outputs in parallel, with --by-output
+    #[arg(short = 'j', long, default_value_t = 1, requires = "by_output")]
+    jobs: usize,
+}
+
+impl EquivArgs {
+    /// Match the black boxes cut out of hierarchical .blif files, reordering the pins of the
+    /// second network
+    fn align_black_boxes(
+        aig1: &Network,
+        h1: &HierarchyMap,
+        aig2: Network,
+        h2: &HierarchyMap,
+    ) -> Network {
+        if h1.black_boxes().is_empty() && h2.black_boxes().is_empty() {
+            return aig2;
+        }
+        match align_black_boxes(aig1, h1.black_boxes(), &aig2, h2.black_boxes()) {
+            Ok(aligned) => {
+                println!("Matched {} black boxes", count(h1.black_boxes().len()));
+                aligned
+            }
+            Err(e) => {
+                println!("{}. Networks are not equivalent", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    /// Check the outputs one by one, reporting each mismatching output with a pattern
+    fn run_by_output(&self, aig1: &Network, aig2: &Network, names: &NameTable) -> ! {
+        if self.learn || self.witness_bundle.is_some() {
+            println!("--by-output cannot be used with --learn or --witness-bundle");
+            std::process::exit(1);
+        }
+        let start = Instant::now();
+        let mismatches =
+            check_equivalence_bounded_by_output(aig1, aig2, self.num_cycles, self.jobs);
+        let elapsed = duration(start.elapsed());
+        let is_comb = aig1.is_comb() && aig2.is_comb();
+        if mismatches.is_empty() {
+            if is_comb {
+                println!("Networks are equivalent");
+            } else {
+                println!("Networks are equivalent up to {} cycles", self.num_cycles);
+            }
+            println!("Checked in {}", elapsed);
+            std::process::exit(0);
+        }
+        let outputs = output_names(aig1, names);
+        println!(
+            "Networks are not equivalent: {} of {} outputs differ",
+            mismatches.len(),
+            aig1.nb_outputs()
+        );
+        for m in &mismatches {
+            if is_comb {
+                println!("Output {}:", outputs[m.output]);
+            } else {
+                println!("Output {} at cycle {}:", outputs[m.output], m.cycle);
+            }
+            print_pattern(names, &m.pattern);
+        }
+        println!("Checked in {}", elapsed);
+        std::process::exit(1);
+    }
+
+    pub fn run(&self) {
+        let ReadResult {
+            network: aig1,
+            names,
+            provenance: prov1,
+            hierarchy: h1,
+            ..
+        } = unwrap_file(read_file_full(&self.file1));
+        let ReadResult {
+            network: aig2,
+            provenance: prov2,
+            hierarchy: h2,
+            ..
+        } = unwrap_file(read_file_full(&self.file2));
+        if !self.force_full_check {
+            if let (Some(p1), Some(p2)) = (prov1, prov2) {
+                if p1.intact
+                    && p2.intact
+                    && p1.fingerprint.is_some()
+                    && p1.fingerprint == p2.fingerprint
+                {
+                    println!("Networks have identical fingerprints");
+                    std::process::exit(0);
+                }
+            }
+        }
+        let aig2 = Self::align_black_boxes(&aig1, &h1, aig2, &h2);
+        if aig1.nb_inputs() != aig2.nb_inputs() {
+            println!(
+                "Different number of inputs: {} vs {}. Networks are not equivalent",
+                aig1.nb_inputs(),
+                aig2.nb_inputs()
+            );
+            std::process::exit(1);
+        }
+        if aig1.nb_outputs() != aig2.nb_outputs() {
+            println!(
+                "Different number of outputs: {} vs {}. Networks are not equivalent",
+                aig1.nb_outputs(),
+                aig2.nb_outputs()
+            );
+            std::process::exit(1);
+        }
+        for aig in [&aig1, &aig2] {
+            let projected = unrolled_size(aig, self.num_cycles);
+            if let Err(e) = GrowthLimitExceeded::check("unroll", projected, self.max_nodes) {
+                exit_growth_limit(e);
+            }
+        }
+        if self.by_output {
+            self.run_by_output(&aig1, &aig2, &names);
+        }
+        if self.verbose && !self.sat_only {
+            let mut miter = difference(
+                &unroll(&aig1, self.num_cycles),
+                &unroll(&aig2, self.num_cycles),
+            );
+            println!("{}", normalize_miter(&mut miter));
+        }
+        let start = Instant::now();
+        let mut report = LearnReport::default();
+        let res = if self.learn {
+            check_equivalence_bounded_with_learning(
+                &aig1,
+                &aig2,
+                self.num_cycles,
+                !self.sat_only,
+                &mut report,
+            )
+        } else {
+            check_equivalence_bounded(&aig1, &aig2, self.num_cycles, !self.sat_only)
+        };
+        let elapsed = duration(start.elapsed());
+        if self.verbose && self.learn {
+            println!("{}", report);
+        }
+        let is_comb = aig1.is_comb() && aig2.is_comb();
+        match res {
+            Err(err) => {
+                println!("Networks are not equivalent");
+                println!("Test pattern:");
+                print_pattern(&names, &err);
+                println!("Checked in {}", elapsed);
+                if let Some(path) = &self.witness_bundle {
+                    let bundle = WitnessBundle::new(
+                        &aig1,
+                        &aig2,
+                        [
+                            self.file1.to_string_lossy().to_string(),
+                            self.file2.to_string_lossy().to_string(),
+                        ],
+                        err,
+                        self.num_cycles,
+                        self.sat_only,
+                    );
+                    let mut data = Vec::new();
+                    bundle.write(&mut data, &path.to_string_lossy());
+                    unwrap_file(std::fs::write(path, data).map_err(|e| QuaighError::io(path, e)));
+                    println!("Witness bundle written to {}", path.display());
+                }
+                std::process::exit(1);
+            }
+            Ok(()) => {
+                if is_comb {
+                    println!("Networks are equivalent");
+                } else {
+                    println!("Networks are equivalent up to {} cycles", self.num_cycles);
+                }
+                println!("Checked in {}", elapsed);
+                std::process::exit(0);
+            }
+        }
+    }
+}
+
+/// Command arguments for batch equivalence checking
+#[derive(Args)]
+pub struct EquivBatchArgs {
+    /// Manifest listing the pairs of networks, with paths relative to the manifest
+    manifest: PathBuf,
+
+    /// Number of pairs checked in parallel
+    #[arg(short = 'j', long, default_value_t = 1)]
+    jobs: usize,
+
+    /// Default time limit for each pair, in seconds
+    #[arg(long)]
+    time_limit: Option<f64>,
+
+    /// Directory where the failing patterns are written
+    #[arg(long, value_name = "DIR")]
+    counterexample_dir: Option<PathBuf>,
+
+    /// Use only the Sat solver, skipping internal optimizations
+    #[arg(long)]
+    sat_only: bool,
+
+    /// Maximum number of nodes of each unrolled network
+    #[arg(long, default_value_t = DEFAULT_MAX_NODES)]
+    max_nodes: usize,
+}
+
+impl EquivBatchArgs {
+    pub fn run(&self) {
+        use quaigh::equiv::{BatchEntry, BatchEquivRunner, BatchVerdict};
+        use std::time::Duration;
+
+        let text = unwrap_file(
+            std::fs::read_to_string(&self.manifest).map_err(|e| QuaighError::io(&self.manifest, e)),
+        );
+        let dir = self.manifest.parent().unwrap_or(Path::new(""));
+        let entries = match BatchEntry::parse_manifest(&text, dir) {
+            Ok(e) => e,
+            Err(e) => {
+                println!("Invalid manifest {}: {}", self.manifest.display(), e);
+                std::process::exit(1);
+            }
+        };
+        if let Some(d) = &self.counterexample_dir {
+            unwrap_file(std::fs::create_dir_all(d).map_err(|e| QuaighError::io(d, e)));
+        }
+        let mut runner = BatchEquivRunner::new();
+        runner.nb_jobs = self.jobs;
+        runner.time_limit = self.time_limit.map(Duration::from_secs_f64);
+        runner.optimize = !self.sat_only;
+        runner.counterexample_dir = self.counterexample_dir.clone();
+        runner.max_nodes = self.max_nodes;
+
+        let start = Instant::now();
+        println!("Pair  Verdict          Time  Networks");
+        let results = runner.run(&entries, |r| println!("{}", r));
+        let count = |f: fn(&BatchVerdict) -> bool| results.iter().filter(|r| f(&r.verdict)).count();
+        let nb_equivalent = count(|v| matches!(v, BatchVerdict::Equivalent));
+        let nb_different = count(|v| matches!(v, BatchVerdict::Different(_)));
+        let nb_unknown = count(|v| matches!(v, BatchVerdict::Unknown));
+        let nb_error = count(|v| matches!(v, BatchVerdict::Error(_)));
+        println!(
+            "Checked {} pairs in {}: {} equivalent, {} different, {} unknown, {} errors",
+            results.len(),
+            duration(start.elapsed()),
+            nb_equivalent,
+            nb_different,
+            nb_unknown,
+            nb_error
+        );
+        if nb_equivalent != results.len() {
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Command arguments for optimization
+#[derive(Args)]
+pub struct OptArgs {
+    /// Network to optimize
+    file: PathBuf,
+
+    /// Output file for optimized network
+    #[arg(short = 'o', long)]
+    output: PathBuf,
+
+    /// Configuration file; command line flags override its values
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Effort level, replacing the pass sequence of the configuration file [default: 1]
+    #[arg(long)]
+    effort: Option<u64>,
+
+    /// Seed for randomized algorithms; the result is identical for the same seed
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Do not write the provenance header, for reproducible output
+    #[arg(long)]
+    no_provenance: bool,
+
+    /// Explore randomized pass orderings, and write the Pareto front of area versus depth
+    ///
+    /// Each non-dominated result is written to the output file, suffixed with its index.
+    #[arg(long, value_name = "N")]
+    explore: Option<usize>,
+
+    /// Area model used to evaluate the results of exploration [default: vlsi]
+    #[arg(long, value_enum)]
+    area: Option<AreaModel>,
+
+    /// Number of threads used for optimization and exploration [default: 1]
+    ///
+    /// With several threads, the independent output cones of the network are optimized in
+    /// parallel, then merged again.
+    #[arg(short = 'j', long)]
+    jobs: Option<usize>,
+
+    /// Only optimize the parts of the design that changed since a previous run
+    ///
+    /// The design is split into independent components, and the optimized components are stored in
+    /// the cache directory to be reused by later runs.
+    #[arg(long)]
+    incremental: bool,
+
+    /// Cache directory for incremental optimization [default: .quaigh-cache]
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Check that cached results are equivalent to the components they replace
+    #[arg(long)]
+    verify: bool,
+
+    /// Maximum size of the network during the passes, in 2-input gates
+    #[arg(long, default_value_t = DEFAULT_MAX_NODES)]
+    max_nodes: usize,
+
+    /// Map the optimized network to Luts with at most this number of inputs, for FPGAs
+    #[arg(long, value_name = "K")]
+    lut_k: Option<usize>,
+
+    /// Re-express the nodes with existing signals after the passes (Boolean resubstitution)
+    #[arg(long)]
+    resub: bool,
+
+    /// Simplify the nodes with don't cares after the passes
+    ///
+    /// Changes that are not observable at the outputs are allowed, as well as changes where the
+    /// outputs are external don't cares, given by the .exdc section of a .blif file.
+    #[arg(long)]
+    dont_cares: bool,
+
+    /// Timing constraints, with the clock period and the input and output times
+    ///
+    /// Logic is decomposed again after the passes: gates on paths with negative slack are balanced
+    /// instead of shared. The worst slack is reported before and after.
+    #[arg(long, value_name = "FILE", conflicts_with = "explore")]
+    constraints: Option<PathBuf>,
+
+    /// Delay model used with --constraints
+    #[arg(long, value_enum, default_value_t = DelayModel::Unit)]
+    delays: DelayModel,
+
+    /// ABC script run after the passes, such as "dc2; rewrite", through an AIGER file exchange
+    ///
+    /// ABC must be installed separately. Flip-flops must be in a single clock domain.
+    #[cfg(feature = "abc")]
+    #[arg(long, value_name = "SCRIPT")]
+    abc: Option<String>,
+
+    /// ABC executable used with --abc
+    #[cfg(feature = "abc")]
+    #[arg(long, value_name = "FILE", default_value = optim::DEFAULT_ABC_BINARY)]
+    abc_binary: PathBuf,
+}
+
+/// Area models available from the command line
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AreaModel {
+    /// Standard cell design
+    Vlsi,
+    /// FPGA design
+    Fpga,
+    /// Sat solving complexity
+    Sat,
+}
+
+impl AreaModel {
+    /// Corresponding area parameters
+    pub fn parameters(&self) -> AreaParameters {
+        match self {
+            AreaModel::Vlsi => AreaParameters::vlsi(),
+            AreaModel::Fpga => AreaParameters::fpga(),
+            AreaModel::Sat => AreaParameters::sat(),
+        }
+    }
+}
+
+/// Delay models available from the command line
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DelayModel {
+    /// One unit per gate, to report the logic depth
+    Unit,
+    /// Standard cell design
+    Vlsi,
+}
+
+impl DelayModel {
+    /// Corresponding delay parameters
+    pub fn parameters(&self) -> DelayParameters {
+        match self {
+            DelayModel::Unit => DelayParameters::unit(),
+            DelayModel::Vlsi => DelayParameters::vlsi(),
+        }
+    }
+}
+
+/// Report a network that would grow too large, with possible remedies, and exit with an error
+fn exit_growth_limit(e: GrowthLimitExceeded) -> ! {
+    let remedy = match e.pass {
+        "unroll" => "use fewer clock cycles",
+        "flatten" => "use passes with a lower flattening limit, such as share(16)",
+        _ => "simplify the network",
+    };
+    println!("{}. Try to {}, or raise --max-nodes", e, remedy);
+    std::process::exit(1);
+}
+
+/// Read the configuration file and apply the command line flags, or exit with an error
+fn unwrap_config(config: Result<Config, String>) -> Config {
+    match config {
+        Ok(c) => c,
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Exit with a diagnostic, such as `design.bench:12: ...`, if a network file cannot be read or
+/// written
+fn unwrap_file<T>(res: Result<T, QuaighError>) -> T {
+    match res {
+        Ok(r) => r,
+        Err(e) => {
+            println!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+impl OptArgs {
+    /// Effective configuration, with the command line flags overriding the configuration file
+    pub fn effective_config(&self) -> Result<Config, String> {
+        let mut config = Config::load(self.config.as_deref())?;
+        let c = &mut config.optimize;
+        if let Some(effort) = self.effort {
+            c.effort = effort;
+            c.passes = None;
+        }
+        c.seed = self.seed.or(c.seed);
+        c.explore = self.explore.or(c.explore);
+        c.area = self.area.unwrap_or(c.area);
+        c.jobs = self.jobs.unwrap_or(c.jobs);
+        c.incremental |= self.incremental;
+        if let Some(dir) = &self.cache_dir {
+            c.cache_dir = dir.clone();
+        }
+        c.verify |= self.verify;
+        c.no_provenance |= self.no_provenance;
+        c.lut_k = self.lut_k.or(c.lut_k);
+        c.resub |= self.resub;
+        c.dont_cares |= self.dont_cares;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Decompose the logic again, balancing the paths that violate the timing constraints
+    fn optimize_timing(&self, aig: &mut Network, names: &NameTable, path: &Path) {
+        let constraints = match read_constraint_file(path, aig, names) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("Invalid constraints in {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        };
+        let delays = self.delays.parameters();
+        let worst_slack =
+            |aig: &Network| match Slacks::compute(aig, &delays.gate_delays(aig), &constraints)
+                .worst_slack()
+            {
+                Some(s) => s.to_string(),
+                None => "unconstrained".to_owned(),
+            };
+        let before = worst_slack(aig);
+        optim::share_logic_with_constraints(aig, 64, &delays, &constraints);
+        println!(
+            "Worst slack: {} before timing optimization, {} after",
+            before,
+            worst_slack(aig)
+        );
+    }
+
+    pub fn run(&self) {
+        let config = unwrap_config(self.effective_config()).optimize;
+        let ReadResult {
+            network: mut aig,
+            names,
+            exdc,
+            ..
+        } = unwrap_file(read_file_full(&self.file));
+        // Internal nodes are renumbered by the optimization: only keep the names of the interface
+        let opt_names = interface_names(&aig, &names);
+        if let Some(n) = config.explore {
+            let front = optim::explore(
+                &aig,
+                n,
+                config.effort,
+                config.seed.unwrap_or(DEFAULT_SEED),
+                &config.area.parameters(),
+                config.jobs,
+                self.max_nodes,
+            )
+            .unwrap_or_else(|e| exit_growth_limit(e));
+            let files: Vec<PathBuf> = (0..front.len())
+                .map(|i| optim::suffixed_path(&self.output, i))
+                .collect();
+            for (p, f) in front.iter().zip(files.iter()) {
+                let prov = provenance(config.no_provenance, &p.network, &self.file, &aig);
+                unwrap_file(write_network_file_with_names(
+                    f,
+                    &p.network,
+                    &opt_names,
+                    prov.as_ref(),
+                ));
+            }
+            print!(
+                "{}",
+                optim::ParetoTable {
+                    points: &front,
+                    files: &files
+                }
+            );
+            return;
+        }
+        let source = aig.clone();
+        // Already validated with the configuration
+        let pipeline = config.pipeline().unwrap();
+        if config.incremental {
+            match optim::optimize_incremental(&mut aig, &pipeline, &config.cache_dir, config.verify)
+            {
+                Ok(report) => println!("{}", report),
+                Err(e) => {
+                    println!("Incremental optimization failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        } else if config.jobs > 1 {
+            if let Err(e) =
+                optim::optimize_parallel(&mut aig, &pipeline, config.jobs, self.max_nodes)
+            {
+                exit_growth_limit(e);
+            }
+        } else if let Err(e) = pipeline.try_run(&mut aig, self.max_nodes) {
+            exit_growth_limit(e);
+        }
+        if let Some(path) = &self.constraints {
+            self.optimize_timing(&mut aig, &names, path);
+        }
+        #[cfg(feature = "abc")]
+        if let Some(script) = &self.abc {
+            if let Err(e) = optim::run_abc_with_binary(&mut aig, script, &self.abc_binary) {
+                println!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        let mut rng = SeededRng::new(config.seed.unwrap_or(DEFAULT_SEED));
+        if config.resub {
+            optim::resubstitute_with_rng(&mut aig, &mut rng);
+        }
+        if config.dont_cares {
+            optim::optimize_dont_cares_with_rng(&mut aig, exdc.as_ref(), &mut rng);
+        }
+        if let Some(k) = config.lut_k {
+            aig = optim::lut_map(&aig, k);
+        }
+        let prov = provenance(config.no_provenance, &aig, &self.file, &source);
+        unwrap_file(write_network_file_with_names(
+            &self.output,
+            &aig,
+            &opt_names,
+            prov.as_ref(),
+        ));
+    }
+}
+
+/// Command arguments for network informations
+#[derive(Args)]
+pub struct ShowArgs {
+    /// Network to show
+    file: PathBuf,
+
+    /// Delay model used to report the critical path
+    #[arg(long, value_enum, default_value_t = DelayModel::Unit)]
+    delays: DelayModel,
+
+    /// Find the outputs that are constant, with Sat proofs
+    #[arg(long)]
+    constant_outputs: bool,
+
+    /// Combinatorial network with the same inputs, whose outputs are assumed to be 1
+    #[arg(long, requires = "constant_outputs")]
+    assume: Option<PathBuf>,
+
+    /// Write the network with the constant outputs tied to their value
+    #[arg(long, requires = "constant_outputs")]
+    tie: Option<PathBuf>,
+
+    /// Do not write the provenance header, for reproducible output
+    #[arg(long)]
+    no_provenance: bool,
+
+    /// Write the network as a Graphviz graph
+    #[arg(long, value_name = "FILE")]
+    dot: Option<PathBuf>,
+
+    /// Highlight the critical path in the Graphviz graph
+    #[arg(long, requires = "dot")]
+    highlight_critical_path: bool,
+
+    /// Highlight a fault site in the Graphviz graph, such as `x12 stuck-at-1`
+    #[arg(long, requires = "dot", value_name = "FAULT")]
+    highlight_fault: Option<String>,
+
+    /// Estimate the switching activity of the nodes by simulating test patterns
+    #[arg(long, value_name = "PATTERNS")]
+    activity: Option<PathBuf>,
+
+    /// Report the number of gates in each instance of a hierarchical .blif file
+    #[arg(long)]
+    hierarchy: bool,
+
+    /// Report the slacks against timing constraints, with the delay model of --delays
+    #[arg(long, value_name = "FILE")]
+    constraints: Option<PathBuf>,
+}
+
+impl ShowArgs {
+    /// Write the network to a .dot file, with the requested highlights
+    fn write_dot(&self, path: &Path, file: &ReadResult) {
+        let (aig, names) = (&file.network, &file.names);
+        let mut highlight = DotHighlight::default();
+        if self.highlight_critical_path {
+            if let Some(p) = self.delays.parameters().critical_path(aig) {
+                highlight.path = p.path;
+            }
+        }
+        if let Some(desc) = &self.highlight_fault {
+            match Fault::parse(desc, aig, names) {
+                Ok(f) => highlight.fault = Some(f),
+                Err(e) => {
+                    println!("Invalid fault: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        unwrap_file(write_dot_file(path, aig, names, &highlight));
+    }
+
+    /// Report the number of gates of each instance of the design hierarchy
+    fn report_hierarchy(&self, file: &ReadResult) {
+        let (aig, hierarchy) = (&file.network, &file.hierarchy);
+        if hierarchy.is_empty() {
+            println!("No design hierarchy\n");
+            return;
+        }
+        let sizes = hierarchy.instance_sizes();
+        let nb_top = aig.nb_nodes() - sizes.iter().sum::<usize>();
+        println!("Design hierarchy:");
+        println!("  top: {} gates", count(nb_top));
+        for (i, size) in sizes.iter().enumerate() {
+            let inst = hierarchy.instance(i as u32);
+            println!("  {} ({}): {} gates", inst.path, inst.model, count(*size));
+        }
+        println!();
+    }
+
+    /// Report the slacks of the network against timing constraints
+    fn report_timing(&self, path: &Path, file: &ReadResult) {
+        let (aig, names) = (&file.network, &file.names);
+        if !aig.is_topo_sorted() {
+            println!("Timing analysis requires a topologically sorted network");
+            std::process::exit(1);
+        }
+        let constraints = match read_constraint_file(path, aig, names) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("Invalid constraints in {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        };
+        let delays = self.delays.parameters().gate_delays(aig);
+        let slacks = Slacks::compute(aig, &delays, &constraints);
+        println!("{}", slacks);
+        let outputs = output_names(aig, names);
+        let mut failing: Vec<(i64, usize)> = (0..aig.nb_outputs())
+            .filter_map(|o| slacks.output_slack(o).map(|s| (s, o)))
+            .filter(|(s, _)| *s < 0)
+            .collect();
+        failing.sort();
+        if !failing.is_empty() {
+            println!("Failing outputs:");
+            for (s, o) in failing.into_iter().take(10) {
+                println!("  {}: slack {}", outputs[o], s);
+            }
+            println!();
+        }
+    }
+
+    /// Report the switching activity of the network on test patterns
+    fn report_activity(&self, path: &PathBuf, file: &ReadResult) {
+        use quaigh::analysis::activity::Activity;
+        let (aig, names) = (&file.network, &file.names);
+        if !aig.is_topo_sorted() {
+            println!("Activity estimation requires a topologically sorted network");
+            std::process::exit(1);
+        }
+        let patterns = unwrap_file(read_pattern_file(path));
+        if let Some(p) = patterns
+            .iter()
+            .flatten()
+            .find(|p| p.len() != aig.nb_inputs())
+        {
+            println!(
+                "Patterns have {} values, but the network has {} inputs",
+                p.len(),
+                aig.nb_inputs()
+            );
+            std::process::exit(1);
+        }
+        let activity = Activity::compute(aig, &patterns);
+        println!("{}", activity);
+        println!("Most active nodes:");
+        for i in activity.most_active().into_iter().take(10) {
+            println!(
+                "  {}: toggle rate {:.3}, static probability {:.3}",
+                names.name_or_generated(aig.node(i)).0,
+                activity.toggle_rate(i),
+                activity.static_probability(i)
+            );
+        }
+        println!();
+    }
+
+    pub fn run(&self) {
+        use quaigh::analysis::testability::Testability;
+        use quaigh::analysis::{constant_outputs, tie_outputs};
+        use quaigh::network::stats::stats;
+        let file = unwrap_file(read_file_full(&self.file));
+        if let Some(path) = &self.dot {
+            self.write_dot(path, &file);
+        }
+        let aig = &file.network;
+        if let Some(p) = &file.provenance {
+            println!("{}", p);
+        }
+        println!("Network stats:\n{}\n", stats(aig));
+        if let Some(path) = self.delays.parameters().critical_path(aig) {
+            println!("{}\n\n", path);
+        }
+        if aig.is_topo_sorted() {
+            println!("{}", Testability::compute(aig));
+        }
+        if self.hierarchy {
+            self.report_hierarchy(&file);
+        }
+        if let Some(path) = &self.constraints {
+            self.report_timing(path, &file);
+        }
+        if let Some(path) = &self.activity {
+            self.report_activity(path, &file);
+        }
+        if !self.constant_outputs {
+            return;
+        }
+        let constraints = self
+            .assume
+            .as_ref()
+            .map(|f| unwrap_file(read_file_full(f)).network);
+        if let Some(c) = &constraints {
+            if !c.is_comb() || c.nb_inputs() != aig.nb_inputs() {
+                println!(
+                    "Assumptions must be a combinatorial network with {} inputs",
+                    aig.nb_inputs()
+                );
+                std::process::exit(1);
+            }
+        }
+        let constants = constant_outputs(aig, constraints.as_ref());
+        println!("Constant outputs: {}", constants.len());
+        for (o, v) in &constants {
+            println!("  Output {}: {}", o, *v as u8);
+        }
+        if let Some(dest) = &self.tie {
+            let mut tied = aig.clone();
+            tie_outputs(&mut tied, &constants);
+            let mut prov = provenance(self.no_provenance, &tied, &self.file, aig);
+            if let Some(a) = &self.assume {
+                let name = a
+                    .file_name()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                match prov {
+                    Some(p) => prov = Some(p.with_assumptions(&name)),
+                    None => println!(
+                        "Warning: the tied network is only equivalent under the assumptions in {}",
+                        a.display()
+                    ),
+                }
+            }
+            unwrap_file(write_network_file_with_provenance(
+                dest,
+                &tied,
+                prov.as_ref(),
+            ));
+        }
+    }
+}
+
+/// Command arguments for file conversion
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// Network to convert
+    file: PathBuf,
+
+    /// Destination file
+    destination: PathBuf,
+
+    /// Do not write the provenance header, for reproducible output
+    #[arg(long)]
+    no_provenance: bool,
+
+    /// Replace flip-flop resets by explicit logic, for tools without reset support
+    #[arg(long)]
+    expand_resets: bool,
+}
+
+impl ConvertArgs {
+    pub fn run(&self) {
+        let ReadResult {
+            network: mut aig,
+            names,
+            ..
+        } = unwrap_file(read_file_full(&self.file));
+        if self.expand_resets {
+            aig.expand_resets();
+        }
+        let prov = provenance(self.no_provenance, &aig, &self.file, &aig);
+        unwrap_file(write_network_file_with_names(
+            &self.destination,
+            &aig,
+            &names,
+            prov.as_ref(),
+        ));
+    }
+}
+
+/// Command arguments for structural comparison
+#[derive(Args)]
+pub struct DiffArgs {
+    /// First network to compare
+    file1: PathBuf,
+    /// Second network to compare
+    file2: PathBuf,
+}
+
+impl DiffArgs {
+    pub fn run(&self) {
+        use quaigh::network::diff::NetworkDiff;
+
+        let ReadResult {
+            network: aig1,
+            names: names1,
+            ..
+        } = unwrap_file(read_file_full(&self.file1));
+        let ReadResult {
+            network: aig2,
+            names: names2,
+            ..
+        } = unwrap_file(read_file_full(&self.file2));
+        let diff = NetworkDiff::compute(&aig1, &aig2);
+        println!("{}", diff);
+        let outputs = output_names(&aig1, &names1);
+        if !diff.changed_cones.is_empty() {
+            println!("Changed cones:");
+            for o in &diff.changed_cones {
+                println!("  {}", outputs[*o]);
+            }
+            println!();
+        }
+        if !diff.support_changes.is_empty() {
+            let input_list = |names: &NameTable, inputs: &[usize]| -> String {
+                inputs
+                    .iter()
+                    .map(|i| names.name_or_generated(Signal::from_input(*i as u32)).0)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            println!("Changed supports:");
+            for c in &diff.support_changes {
+                print!("  {}:", outputs[c.output]);
+                if !c.added.is_empty() {
+                    print!(" +[{}]", input_list(&names2, &c.added));
+                }
+                if !c.removed.is_empty() {
+                    print!(" -[{}]", input_list(&names1, &c.removed));
+                }
+                println!();
+            }
+            println!();
+        }
+    }
+}
+
+/// Generic cell libraries available from the command line
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum MapTarget {
+    /// Inverters and two-input Nand gates
+    Nand2,
+    /// Inverters and two-input Nor gates
+    Nor2,
+    /// Inverters, Nand, Nor, and AOI21/OAI21/AOI22/OAI22 cells
+    Aoi,
+}
+
+impl MapTarget {
+    /// Corresponding cell library
+    pub fn library(&self) -> optim::CellLibrary {
+        match self {
+            MapTarget::Nand2 => optim::CellLibrary::Nand2,
+            MapTarget::Nor2 => optim::CellLibrary::Nor2,
+            MapTarget::Aoi => optim::CellLibrary::AoiOai,
+        }
+    }
+}
+
+/// Command arguments for cell library mapping
+#[derive(Args)]
+pub struct MapArgs {
+    /// Network to map
+    file: PathBuf,
+
+    /// Destination file
+    #[arg(short = 'o', long)]
+    output: PathBuf,
+
+    /// Target cell library
+    #[arg(long, value_enum, default_value_t = MapTarget::Nand2)]
+    target: MapTarget,
+
+    /// Do not write the provenance header, for reproducible output
+    #[arg(long)]
+    no_provenance: bool,
+}
+
+impl MapArgs {
+    pub fn run(&self) {
+        let ReadResult {
+            network: aig,
+            names,
+            ..
+        } = unwrap_file(read_file_full(&self.file));
+        let library = self.target.library();
+        let mapped = optim::cell_map(&aig, library);
+        let mapped_names = interface_names(&aig, &names);
+        println!(
+            "Mapped {} gates to {} cells:",
+            count(aig.nb_nodes()),
+            count(mapped.iter_comb().count())
+        );
+        for (cell, nb) in library.cell_counts(&mapped) {
+            if nb != 0 {
+                println!("  {}: {}", cell, count(nb));
+            }
+        }
+        let prov = provenance(self.no_provenance, &mapped, &self.file, &aig);
+        unwrap_file(write_network_file_with_names(
+            &self.output,
+            &mapped,
+            &mapped_names,
+            prov.as_ref(),
+        ));
+    }
+}
+
+/// Command arguments for cone of influence extraction
+#[derive(Args)]
+pub struct ExtractArgs {
+    /// Network to extract from
+    file: PathBuf,
+
+    /// Outputs to keep, separated by commas
+    #[arg(long, value_delimiter = ',', required = true)]
+    outputs: Vec<String>,
+
+    /// Destination file
+    #[arg(short = 'o', long)]
+    output: PathBuf,
+
+    /// Do not write the provenance header, for reproducible output
+    #[arg(long)]
+    no_provenance: bool,
+}
+
+impl ExtractArgs {
+    /// Index of an output, by name or as o3
+    fn find_output(&self, aig: &Network, names: &[String], name: &str) -> usize {
+        if let Some(o) = names.iter().position(|n| n == name) {
+            return o;
+        }
+        match name.strip_prefix('o').and_then(|i| i.parse::<usize>().ok()) {
+            Some(o) if o < aig.nb_outputs() => o,
+            _ => {
+                println!("No output named {} in {}", name, self.file.display());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    pub fn run(&self) {
+        let ReadResult {
+            network: aig,
+            names,
+            ..
+        } = unwrap_file(read_file_full(&self.file));
+        let all_names = output_names(&aig, &names);
+        let outputs: Vec<usize> = self
+            .outputs
+            .iter()
+            .map(|n| self.find_output(&aig, &all_names, n))
+            .collect();
+        let (cone, mapping) = aig.extract_cone(&outputs);
+        let cone_names = mapping.names(&names);
+        println!(
+            "Extracted {} outputs, {} inputs and {} gates out of {} inputs and {} gates",
+            count(cone.nb_outputs()),
+            count(cone.nb_inputs()),
+            count(cone.nb_nodes()),
+            count(aig.nb_inputs()),
+            count(aig.nb_nodes())
+        );
+        let prov = provenance(self.no_provenance, &cone, &self.file, &aig);
+        unwrap_file(write_network_file_with_names(
+            &self.output,
+            &cone,
+            &cone_names,
+            prov.as_ref(),
+        ));
+    }
+}
+
+/// Command arguments for the self test
+#[derive(Args)]
+pub struct SelftestArgs {
+    /// Number of random designs to check
+    #[arg(long, default_value_t = 200)]
+    nb_designs: usize,
+
+    /// Seed for random design generation
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+}
+
+impl SelftestArgs {
+    pub fn run(&self) {
+        use quaigh::sim::selftest_lane_packing;
+        match selftest_lane_packing(self.nb_designs, self.seed) {
+            Ok(nb) => println!("Lane packing: {} simulations checked", nb),
+            Err(e) => {
+                println!("Lane packing: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Command arguments for the network inspector
+#[derive(Args)]
+pub struct InspectArgs {
+    /// Network to inspect
+    file: PathBuf,
+
+    /// Print the details of a node (x12 or i3) instead of opening the interactive interface
+    #[arg(long)]
+    node: Vec<String>,
+
+    /// File where the cone of the marked nodes is exported
+    #[cfg(feature = "tui")]
+    #[arg(long, default_value = "marked_cone.bench")]
+    export: PathBuf,
+}
+
+impl InspectArgs {
+    pub fn run(&self) {
+        use crate::inspect::Inspector;
+        let ReadResult {
+            network: aig,
+            locations,
+            ..
+        } = unwrap_file(read_file_full(&self.file));
+        let mut inspector = Inspector::new(aig).with_locations(locations);
+        if !self.node.is_empty() {
+            for name in &self.node {
+                let Some(s) = inspector.parse_signal(name) else {
+                    println!("No node named {} in the network", name);
+                    std::process::exit(1);
+                };
+                print!("{}", inspector.detail(s));
+            }
+            return;
+        }
+        #[cfg(feature = "tui")]
+        {
+            if let Err(e) = crate::inspect::tui::run(inspector, self.export.clone()) {
+                println!("Terminal error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        #[cfg(not(feature = "tui"))]
+        {
+            println!("The interactive inspector requires the tui feature; use --node instead");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Command arguments for witness replay
+#[derive(Args)]
+pub struct ReplayWitnessArgs {
+    /// Witness bundle to replay
+    bundle: PathBuf,
+}
+
+impl ReplayWitnessArgs {
+    pub fn run(&self) {
+        let f = unwrap_file(File::open(&self.bundle).map_err(|e| QuaighError::io(&self.bundle, e)));
+        let bundle = match WitnessBundle::read(f) {
+            Ok(b) => b,
+            Err(e) => {
+                println!("Invalid witness bundle: {}", e);
+                std::process::exit(1);
+            }
+        };
+        println!(
+            "Replaying witness for {} and {}, written by quaigh {}",
+            bundle.names[0], bundle.names[1], bundle.version
+        );
+        match bundle.replay() {
+            Ok(()) => {
+                println!("Confirmed: networks are not equivalent");
+                std::process::exit(0);
+            }
+            Err(e) => {
+                println!("Refuted: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Command arguments for signal queries
+#[derive(Args)]
+pub struct QueryArgs {
+    /// Network containing the signals
+    file: PathBuf,
+
+    /// First signal
+    #[arg(long)]
+    a: String,
+
+    /// Second signal
+    #[arg(long)]
+    b: String,
+
+    /// Network containing the second signal, with the same inputs, if different
+    #[arg(long)]
+    b_in: Option<PathBuf>,
+
+    /// Input assumed to have a constant value, as NAME=VALUE
+    #[arg(long)]
+    assume: Vec<String>,
+}
+
+/// Command arguments for model checking
+#[derive(Args)]
+pub struct ProveArgs {
+    /// Network to check
+    file: PathBuf,
+
+    /// Signal that must always be 1, by name [default: all outputs]
+    #[arg(long)]
+    property: Option<String>,
+
+    /// Signal assumed to be 1 at every clock cycle, by name
+    #[arg(long)]
+    assume: Vec<String>,
+
+    /// Maximum number of frames of the reachability analysis
+    #[arg(long, default_value_t = 100)]
+    max_frames: usize,
+}
+
+impl ProveArgs {
+    pub fn run(&self) {
+        use quaigh::mc::{check_invariant, CheckResult};
+        use quaigh::network::OutputKind;
+        let ReadResult {
+            network: mut aig,
+            names,
+            ..
+        } = unwrap_file(read_file_full(&self.file));
+        for name in &self.assume {
+            match (0..aig.nb_outputs()).find(|o| names.output_name(*o) == Some(name.as_str())) {
+                Some(o) => aig.set_output_kind(o, OutputKind::Assumption),
+                None => {
+                    let s = resolve_signal(&names, &aig, name, &self.file);
+                    aig.add_assumption(s);
+                }
+            }
+        }
+        let property = match &self.property {
+            Some(name) => resolve_signal(&names, &aig, name, &self.file),
+            None => {
+                let outputs: Vec<Signal> = (0..aig.nb_outputs())
+                    .filter(|o| aig.output_kind(*o) != OutputKind::Assumption)
+                    .map(|o| aig.output(o))
+                    .collect();
+                aig.add_canonical(Gate::andn(&outputs))
+            }
+        };
+        let start = Instant::now();
+        let res = check_invariant(&aig, property, self.max_frames);
+        let elapsed = duration(start.elapsed());
+        match res {
+            CheckResult::Proved => {
+                println!("Property holds");
+                println!("Checked in {}", elapsed);
+            }
+            CheckResult::Failed(trace) => {
+                println!("Property fails after {} cycles", trace.len());
+                println!("Counterexample:");
+                print_pattern(&names, &trace);
+                println!("Checked in {}", elapsed);
+                std::process::exit(1);
+            }
+            CheckResult::Unknown => {
+                println!(
+                    "Property could not be proved or disproved with {} frames",
+                    self.max_frames
+                );
+                println!("Checked in {}", elapsed);
+                std::process::exit(2);
+            }
+        }
+    }
+}
+
+/// Format the values of the inputs with their names, such as `a=1 b=0`
+fn format_inputs(names: &NameTable, values: &[bool]) -> String {
+    let inputs: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let (n, _) = names.name_or_generated(Signal::from_input(i as u32));
+            format!("{}={}", n, *v as u8)
+        })
+        .collect();
+    inputs.join(" ")
+}
+
+/// Print the values of the inputs at each cycle, with their names if the file has any
+fn print_pattern(names: &NameTable, pattern: &[Vec<bool>]) {
+    for v in pattern {
+        if names.is_empty() {
+            let bits: String = v.iter().map(|b| if *b { '1' } else { '0' }).collect();
+            println!("\t{}", bits);
+        } else {
+            println!("\t{}", format_inputs(names, v));
+        }
+    }
+}
+
+/// Resolve a signal by name, exiting with an error if it does not exist
+fn resolve_signal(names: &NameTable, aig: &Network, name: &str, file: &Path) -> Signal {
+    match names.resolve(name) {
+        Some(s) if s.is_input() && (s.input() as usize) < aig.nb_inputs() => s,
+        Some(s) if s.is_var() && (s.var() as usize) < aig.nb_nodes() => s,
+        Some(s) if s.is_constant() => s,
+        _ => {
+            println!("No signal named {} in {}", name, file.display());
+            std::process::exit(1);
+        }
+    }
+}
+
+impl QueryArgs {
+    pub fn run(&self) {
+        use quaigh::equiv::query_signals;
+        let ReadResult {
+            network: aig,
+            names,
+            ..
+        } = unwrap_file(read_file_full(&self.file));
+        let sa = resolve_signal(&names, &aig, &self.a, &self.file);
+        let other = self.b_in.as_ref().map(|f| unwrap_file(read_file_full(f)));
+        let sb = match (&other, &self.b_in) {
+            (
+                Some(ReadResult {
+                    network: b,
+                    names: b_names,
+                    ..
+                }),
+                Some(f),
+            ) => {
+                if b.nb_inputs() != aig.nb_inputs() {
+                    println!(
+                        "{} has {} inputs, but {} has {}",
+                        f.display(),
+                        b.nb_inputs(),
+                        self.file.display(),
+                        aig.nb_inputs()
+                    );
+                    std::process::exit(1);
+                }
+                resolve_signal(b_names, b, &self.b, f)
+            }
+            _ => resolve_signal(&names, &aig, &self.b, &self.file),
+        };
+        let mut constraints = vec![None; aig.nb_inputs()];
+        for a in &self.assume {
+            let Some((name, value)) = a.split_once('=') else {
+                println!("Expected NAME=VALUE, got {}", a);
+                std::process::exit(1);
+            };
+            let value = match value {
+                "0" => false,
+                "1" => true,
+                _ => {
+                    println!("Assumed value should be 0 or 1, got {}", value);
+                    std::process::exit(1);
+                }
+            };
+            let s = resolve_signal(&names, &aig, name, &self.file);
+            if !s.is_input() {
+                println!(
+                    "Assumptions apply to primary inputs, but {} is not one",
+                    name
+                );
+                std::process::exit(1);
+            }
+            constraints[s.input() as usize] = Some(value ^ s.is_inverted());
+        }
+
+        let b = other.as_ref().map(|r| &r.network);
+        match query_signals(&aig, sa, b, sb, &constraints) {
+            None => println!(
+                "{} and {} never differ under the assumptions",
+                self.a, self.b
+            ),
+            Some(diff) => {
+                println!(
+                    "{} and {} differ: {}={}, {}={}",
+                    self.a, self.b, self.a, diff.values[0] as u8, self.b, diff.values[1] as u8
+                );
+                println!("Inputs: {}", format_inputs(&names, &diff.inputs));
+                let b_names = other.as_ref().map(|r| &r.names).unwrap_or(&names);
+                for (state, names) in diff.state.iter().zip([&names, b_names]) {
+                    if state.is_empty() {
+                        continue;
+                    }
+                    let ffs: Vec<String> = state
+                        .iter()
+                        .map(|(i, v)| {
+                            let (n, _) = names.name_or_generated(Signal::from_var(*i as u32));
+                            format!("{}={}", n, *v as u8)
+                        })
+                        .collect();
+                    println!("Flip-flops: {}", ffs.join(" "));
+                }
+            }
+        }
+    }
+}
+
+/// Command arguments for optimization benchmarking
+#[derive(Args)]
+pub struct BenchOptArgs {
+    /// Directory containing the designs
+    #[arg(long)]
+    suite: PathBuf,
+
+    /// Baseline file, in JSON format
+    #[arg(long)]
+    baseline: PathBuf,
+
+    /// Write the results to the baseline file instead of comparing them
+    #[arg(long)]
+    update: bool,
+
+    /// Effort level
+    #[arg(long, default_value_t = 1)]
+    effort: u64,
+
+    /// Seed for randomized algorithms
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    /// Area model
+    #[arg(long, value_enum, default_value_t = AreaModel::Vlsi)]
+    area: AreaModel,
+
+    /// Time limit for the optimization of each design, in seconds
+    #[arg(long)]
+    time_limit: Option<f64>,
+
+    /// Relative runtime increase reported as a regression
+    #[arg(long, default_value_t = 0.5)]
+    runtime_tolerance: f64,
+}
+
+impl BenchOptArgs {
+    pub fn run(&self) {
+        use quaigh::benchmarking::{compare, run_suite, Baseline, Status};
+        use std::time::Duration;
+
+        let mut pipeline = optim::Pipeline::from_effort(self.effort);
+        pipeline.seed = Some(self.seed);
+        let time_limit = self.time_limit.map(Duration::from_secs_f64);
+        let current = match run_suite(&self.suite, &pipeline, &self.area.parameters(), time_limit) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        if self.update {
+            unwrap_file(
+                std::fs::write(&self.baseline, current.to_json())
+                    .map_err(|e| QuaighError::io(&self.baseline, e)),
+            );
+            println!(
+                "Baseline written to {} for {} designs",
+                self.baseline.display(),
+                current.designs.len()
+            );
+            return;
+        }
+        let baseline = std::fs::read_to_string(&self.baseline)
+            .map_err(|e| e.to_string())
+            .and_then(|s| Baseline::from_json(&s));
+        let baseline = match baseline {
+            Ok(b) => b,
+            Err(e) => {
+                println!("Cannot read baseline {}: {}", self.baseline.display(), e);
+                std::process::exit(1);
+            }
+        };
+        match compare(&baseline, &current, self.runtime_tolerance) {
+            Ok(cmp) => {
+                println!("{}", cmp);
+                if cmp.nb_with_status(Status::Regressed) != 0 {
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => {
+                println!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Command arguments for pass runtime benchmarking
+#[derive(Args)]
+pub struct BenchPerfArgs {
+    /// Budgets file, in text format
+    #[arg(long)]
+    budgets: PathBuf,
+
+    /// Write budgets from the measured runtimes instead of checking them
+    #[arg(long)]
+    update: bool,
+
+    /// Ratio between the budgets written and the measured runtimes
+    #[arg(long, default_value_t = 3.0)]
+    margin: f64,
+
+    /// Number of runs of each design, keeping the fastest
+    #[arg(long, default_value_t = 3)]
+    repeats: usize,
+
+    /// Use much smaller designs, to check the setup quickly
+    #[arg(long)]
+    fast: bool,
+}
+
+impl BenchPerfArgs {
+    pub fn run(&self) {
+        use quaigh::benchmarking::perf::{
+            calibrate, check_budgets, measure, perf_pipeline, workloads, Budgets,
+        };
+
+        let calibration = calibrate();
+        let timings = measure(&workloads(self.fast), &perf_pipeline(), self.repeats);
+        if self.update {
+            let budgets = Budgets::from_timings(&timings, calibration, self.margin);
+            unwrap_file(
+                std::fs::write(&self.budgets, budgets.to_string())
+                    .map_err(|e| QuaighError::io(&self.budgets, e)),
+            );
+            println!(
+                "Budgets written to {} for {} passes",
+                self.budgets.display(),
+                budgets.budgets.len()
+            );
+            return;
+        }
+        let budgets = std::fs::read_to_string(&self.budgets)
+            .map_err(|e| e.to_string())
+            .and_then(|s| Budgets::parse(&s));
+        let budgets = match budgets {
+            Ok(b) => b,
+            Err(e) => {
+                println!("Cannot read budgets {}: {}", self.budgets.display(), e);
+                std::process::exit(1);
+            }
+        };
+        let report = check_budgets(&budgets, &timings, calibration);
+        println!("{}", report);
+        if report.nb_over_budget() != 0 {
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Provenance header for a network derived from a source file
+fn provenance(
+    no_provenance: bool,
+    aig: &Network,
+    source_file: &Path,
+    source: &Network,
+) -> Option<Provenance> {
+    if no_provenance {
+        return None;
+    }
+    let name = source_file
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Some(Provenance::new(aig).with_source(&name, source))
+}
+
+/// Names of the inputs, outputs and clocks of a network, for a version whose internal nodes have
+/// been renumbered
+fn interface_names(aig: &Network, names: &NameTable) -> NameTable {
+    let mut ret = NameTable::new();
+    for i in 0..aig.nb_inputs() {
+        if let Some(n) = names.input_name(i) {
+            ret.set_input_name(i, n);
+        }
+    }
+    for o in 0..aig.nb_outputs() {
+        if let Some(n) = names.output_name(o) {
+            ret.set_output_name(o, n);
+        }
+    }
+    for c in aig.clock_domains() {
+        if let Some(n) = names.clock_name(c.index() as usize) {
+            ret.set_clock_name(c.index() as usize, n);
+        }
+    }
+    ret
+}
+
+/// Number of patterns read at once for simulation
+const SIMULATION_BATCH_SIZE: usize = 1024;
+
+/// Command arguments for simulation
+#[derive(Args)]
+pub struct SimulateArgs {
+    /// Network to simulate
+    network: PathBuf,
+
+    /// Input patterns file
+    ///
+    /// Files with a .stim extension are read as a stimulus for a sequential design, with signals
+    /// and clocks given by name, hold values, repeat counts and reset sequences.
+    #[arg(short = 'i', long)]
+    input: PathBuf,
+
+    /// Output file for output patterns
+    #[arg(short = 'o', long)]
+    output: PathBuf,
+
+    /// Expose flip-flops as primary inputs. Used after test pattern generation
+    #[arg(long)]
+    expose_ff: bool,
+
+    /// Explain the value of an output, given as output=N, for a single combinatorial pattern
+    ///
+    /// Prints the gates and the subset of the inputs that force the value of the output.
+    #[arg(long, value_name = "output=N", value_parser = parse_explain)]
+    explain: Vec<usize>,
+
+    /// Propagate unknown values, given as X in the input patterns
+    ///
+    /// Flip-flops start in an unknown state instead of 0, and outputs whose value depends on
+    /// unknown inputs or flip-flops are written as X.
+    #[arg(long, conflicts_with = "explain")]
+    x_prop: bool,
+
+    /// Annotate each pattern with the values of the inputs and outputs grouped by bus
+    ///
+    /// Bits named like a[3] in the network are grouped into buses, written most significant bit first.
+    #[arg(long, conflicts_with = "x_prop")]
+    by_bus: bool,
+
+    /// Simulate with a delay on each gate, and report the arrival times and glitches of each cycle
+    #[arg(long, conflicts_with_all = ["x_prop", "by_bus", "expose_ff", "explain"])]
+    timing: bool,
+
+    /// Delay model giving the default delay of the gates with --timing
+    #[arg(long, value_enum, default_value_t = DelayModel::Unit, requires = "timing")]
+    delay_model: DelayModel,
+
+    /// Delays of individual gates with --timing, as an .sdf file or a table of signals and delays
+    #[arg(long, value_name = "FILE", requires = "timing")]
+    delays: Option<PathBuf>,
+}
+
+fn parse_explain(s: &str) -> Result<usize, String> {
+    let index = s.strip_prefix("output=").unwrap_or(s);
+    index
+        .parse()
+        .map_err(|_| format!("Invalid output {}, expected output=N", s))
+}
+
+impl SimulateArgs {
+    fn explain(&self, aig: &Network, input_values: &[Vec<Vec<bool>>]) {
+        use quaigh::analysis::justify;
+        if input_values.len() != 1 || input_values[0].len() != 1 || !aig.is_comb() {
+            println!("Explanations require a single pattern on a combinatorial network; use --expose-ff for sequential networks");
+            std::process::exit(1);
+        }
+        for o in &self.explain {
+            if *o >= aig.nb_outputs() {
+                println!("Output {} does not exist", o);
+                std::process::exit(1);
+            }
+            println!("{}", justify(aig, &input_values[0][0], *o));
+        }
+    }
+
+    fn run_by_bus(&self, aig: &Network, names: &NameTable) {
+        let buses = BusMap::from_names(aig, names);
+        let mut writer = unwrap_file(create_pattern_file(&self.output));
+        for p in unwrap_file(read_pattern_file(&self.input)) {
+            let outputs = simulate(aig, &p);
+            writer.write(&outputs);
+            for (i, o) in p.iter().zip(outputs.iter()) {
+                writer.write_comment(&format!(
+                    "{} -> {}",
+                    buses.format_inputs(i),
+                    buses.format_outputs(o)
+                ));
+            }
+        }
+        writer.flush();
+    }
+
+    fn run_timing(&self, aig: &Network, names: &NameTable) {
+        let mut delays = self.delay_model.parameters().gate_delays(aig);
+        if let Some(path) = &self.delays {
+            if let Err(e) = read_delay_file(path, aig, names, &mut delays) {
+                println!("Invalid delay file {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+        let mut writer = unwrap_file(create_pattern_file(&self.output));
+        let (mut nb_transitions, mut nb_glitches, mut settle_time) = (0, 0, 0);
+        for (p, pattern) in unwrap_file(read_pattern_file(&self.input))
+            .iter()
+            .enumerate()
+        {
+            let timing = simulate_timing(aig, &delays, pattern);
+            let outputs: Vec<Vec<bool>> = timing.iter().map(|t| t.outputs.clone()).collect();
+            writer.write(&outputs);
+            for (c, t) in timing.iter().enumerate() {
+                println!("Pattern {} cycle {}: {}", p + 1, c, t);
+                nb_transitions += t.nb_transitions;
+                nb_glitches += t.nb_glitches;
+                settle_time = settle_time.max(t.settle_time);
+            }
+        }
+        writer.flush();
+        println!(
+            "Total: {} transitions, {} glitches, settled at {} at most",
+            nb_transitions, nb_glitches, settle_time
+        );
+    }
+
+    fn run_stimulus(&self, aig: &Network, names: &NameTable) {
+        if self.expose_ff || self.x_prop || self.by_bus || !self.explain.is_empty() {
+            println!(
+                "A stimulus file cannot be used with --expose-ff, --x-prop, --by-bus or --explain"
+            );
+            std::process::exit(1);
+        }
+        let resolved = read_stimulus_file(&self.input).and_then(|s| s.resolve(aig, names));
+        let (inputs, edges) = match resolved {
+            Ok(r) => r,
+            Err(e) => {
+                println!("Invalid stimulus file {}: {}", self.input.display(), e);
+                std::process::exit(1);
+            }
+        };
+        // The whole stimulus is a single pattern, with one timestep per cycle
+        let outputs = simulate_with_clocks(aig, &inputs, &edges);
+        let mut writer = unwrap_file(create_pattern_file(&self.output));
+        writer.write(&outputs);
+        writer.flush();
+    }
+
+    pub fn run(&self) {
+        let ReadResult {
+            network: mut aig,
+            names,
+            ..
+        } = unwrap_file(read_file_full(&self.network));
+        if self.input.extension().is_some_and(|s| s == "stim") {
+            self.run_stimulus(&aig, &names);
+            return;
+        }
+        if self.timing {
+            self.run_timing(&aig, &names);
+            return;
+        }
+        if self.expose_ff {
+            aig = expose_dff(&aig);
+        }
+        if !self.explain.is_empty() {
+            self.explain(&aig, &unwrap_file(read_pattern_file(&self.input)));
+        }
+        if self.by_bus {
+            self.run_by_bus(&aig, &names);
+            return;
+        }
+        let reader = unwrap_file(open_pattern_file(&self.input));
+        let mut writer = unwrap_file(create_pattern_file(&self.output));
+        let res = if self.x_prop {
+            simulate_xprop_stream(&aig, reader, &mut writer)
+        } else {
+            simulate_stream(&aig, reader, &mut writer, SIMULATION_BATCH_SIZE)
+        };
+        if let Err(e) = res {
+            println!("Invalid pattern file {}: {}", self.input.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Command arguments for test pattern generation
+#[derive(Args)]
+pub struct AtpgArgs {
+    /// Network to write test patterns for
+    network: PathBuf,
+
+    /// Output file for test patterns; .stil and .wgl files are written in tester formats
+    #[arg(short = 'o', long)]
+    output: PathBuf,
+
+    /// Configuration file; command line flags override its values
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Random seed for test pattern generation [default: 1]
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Attempt to generate sequential patterns (random only)
+    #[arg(short = 'c', long)]
+    num_cycles: Option<usize>,
+
+    /// Number of random patterns to generate
+    #[arg(short = 'r', long)]
+    num_random: Option<usize>,
+
+    /// Do not remove redundant faults beforehand
+    #[arg(long, default_value_t = false)]
+    with_redundant_faults: bool,
+
+    /// Input tied to a constant on the tester, for example i5=1; may be repeated
+    #[arg(long = "tie", value_name = "INPUT=VALUE", value_parser = parse_tie)]
+    ties: Vec<(usize, bool)>,
+
+    /// Suggest up to N internal signals to expose as outputs, to detect more faults
+    #[arg(long, value_name = "N")]
+    suggest_observe: Option<usize>,
+
+    /// Write the network with the suggested observation points added as outputs
+    #[arg(long, value_name = "FILE")]
+    observe_output: Option<PathBuf>,
+
+    /// Weights of the faults, to report weighted coverage and order patterns by weighted coverage
+    #[arg(long, value_name = "FILE")]
+    fault_weights: Option<PathBuf>,
+
+    /// Generate two-cycle launch-on-capture patterns for transition faults
+    #[arg(long, default_value_t = false)]
+    transition: bool,
+
+    /// Generate patterns that only activate the faults, without propagating them, for IDDQ testing
+    #[arg(long, default_value_t = false, conflicts_with = "transition")]
+    iddq: bool,
+
+    /// Target several undetected faults with each pattern generated by the SAT solver
+    #[arg(long, default_value_t = false)]
+    dynamic_compaction: bool,
+
+    /// Select a minimum set of patterns with the SAT solver, after greedy compression
+    #[arg(long, default_value_t = false)]
+    exact_compaction: bool,
+}
+
+/// Parse a tied input, of the form i5=1 or 5=1
+pub fn parse_tie(s: &str) -> Result<(usize, bool), String> {
+    let (input, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Expected INPUT=VALUE, got {}", s))?;
+    let input = input.strip_prefix('i').unwrap_or(input);
+    let input = input
+        .parse::<usize>()
+        .map_err(|_| format!("Invalid input index {}", input))?;
+    let value = match value {
+        "0" => false,
+        "1" => true,
+        _ => return Err(format!("Tied value should be 0 or 1, got {}", value)),
+    };
+    Ok((input, value))
+}
+
+impl AtpgArgs {
+    /// Effective configuration, with the command line flags overriding the configuration file
+    pub fn effective_config(&self) -> Result<Config, String> {
+        let mut config = Config::load(self.config.as_deref())?;
+        let c = &mut config.atpg;
+        c.seed = self.seed.unwrap_or(c.seed);
+        c.num_cycles = self.num_cycles.or(c.num_cycles);
+        c.num_random = self.num_random.or(c.num_random);
+        c.with_redundant_faults |= self.with_redundant_faults;
+        c.transition |= self.transition;
+        c.iddq |= self.iddq;
+        c.dynamic_compaction |= self.dynamic_compaction;
+        c.exact_compaction |= self.exact_compaction;
+        if !self.ties.is_empty() {
+            c.ties = self
+                .ties
+                .iter()
+                .map(|(i, v)| format!("i{}={}", i, i32::from(*v)))
+                .collect();
+        }
+        c.suggest_observe = self.suggest_observe.or(c.suggest_observe);
+        if self.observe_output.is_some() {
+            c.observe_output = self.observe_output.clone();
+        }
+        if self.fault_weights.is_some() {
+            c.fault_weights = self.fault_weights.clone();
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn run(&self) {
+        let config = unwrap_config(self.effective_config()).atpg;
+        // Already validated with the configuration
+        let ties = config.ties().unwrap();
+        let ReadResult {
+            network: mut aig,
+            names,
+            ..
+        } = unwrap_file(read_file_full(&self.network));
+        let mut input_names: Vec<String> = (0..aig.nb_inputs())
+            .map(|i| names.name_or_generated(aig.input(i)).0)
+            .collect();
+
+        if config.num_cycles.is_none() && config.num_random.is_none() {
+            // Network before exposing the flip-flops, for transition patterns and scan responses
+            let original = aig.clone();
+            if !aig.is_comb() {
+                println!("Exposing flip-flops for a sequential network");
+                // Exposed flip-flops become new inputs, in order
+                for i in 0..aig.nb_nodes() {
+                    if !aig.gate(i).is_comb() {
+                        input_names.push(names.name_or_generated(aig.node(i)).0);
+                    }
+                }
+                aig = expose_dff(&aig);
+            }
+            for (i, _) in &ties {
+                if *i >= aig.nb_inputs() {
+                    println!("Tied input i{} does not exist", i);
+                    std::process::exit(1);
+                }
+            }
+            let weights = config
+                .fault_weights
+                .as_ref()
+                .map(|path| read_fault_weights(path, &aig, &names));
+            if config.transition {
+                let patterns = generate_transition_test_patterns(
+                    &original,
+                    config.seed,
+                    config.with_redundant_faults,
+                    &ties,
+                    weights.as_ref(),
+                    config.compaction(),
+                );
+                write_scan_pattern_file(&self.output, &original, &names, &patterns, &input_names);
+                return;
+            }
+            let patterns = if config.iddq {
+                generate_iddq_test_patterns(
+                    &aig,
+                    config.seed,
+                    config.with_redundant_faults,
+                    &ties,
+                    weights.as_ref(),
+                    config.compaction(),
+                )
+            } else {
+                generate_comb_test_patterns(
+                    &aig,
+                    config.seed,
+                    config.with_redundant_faults,
+                    &ties,
+                    weights.as_ref(),
+                    config.compaction(),
+                )
+            };
+            let seq_patterns: Vec<_> = patterns.iter().map(|p| vec![p.clone()]).collect();
+            write_scan_pattern_file(&self.output, &original, &names, &seq_patterns, &input_names);
+            if let Some(nb_points) = config.suggest_observe {
+                let points = suggest_observation_points(
+                    &aig,
+                    &patterns,
+                    config.with_redundant_faults,
+                    &ties,
+                    nb_points,
+                    config.seed,
+                );
+                if let Some(path) = &config.observe_output {
+                    let observed = add_observation_points(&aig, &points);
+                    let prov = provenance(false, &observed, &self.network, &aig);
+                    unwrap_file(write_network_file_with_provenance(
+                        path,
+                        &observed,
+                        prov.as_ref(),
+                    ));
+                }
+            }
+        } else {
+            if config.suggest_observe.is_some() {
+                println!("Observation points are not supported for random sequential patterns");
+                std::process::exit(1);
+            }
+            if !ties.is_empty() {
+                println!("Tied inputs are not supported for random sequential patterns");
+                std::process::exit(1);
+            }
+            if config.fault_weights.is_some() {
+                println!("Fault weights are not supported for random sequential patterns");
+                std::process::exit(1);
+            }
+            println!("Generating only random patterns for multiple cycles");
+            let nb_timesteps = config.num_cycles.unwrap_or(1);
+            let nb_patterns = config.num_random.unwrap_or(4 * (aig.nb_inputs() + 1));
+            let seq_patterns = generate_random_seq_patterns(
+                aig.nb_inputs(),
+                nb_timesteps,
+                nb_patterns,
+                config.seed,
+            );
+            if is_tester_pattern_file(&self.output) {
+                // Functional patterns, applied from the initial state without scan
+                let signals =
+                    TesterSignals::new(input_names, output_names(&aig, &names), Vec::new());
+                let responses: Vec<_> = seq_patterns.iter().map(|p| simulate(&aig, p)).collect();
+                unwrap_file(write_tester_pattern_file(
+                    &self.output,
+                    &signals,
+                    &seq_patterns,
+                    &responses,
+                ));
+            } else {
+                write_named_pattern_file(&self.output, &seq_patterns, &input_names);
+            }
+        }
+    }
+}
+
+/// Names of the outputs of a network, generated if missing
+fn output_names(aig: &Network, names: &NameTable) -> Vec<String> {
+    (0..aig.nb_outputs())
+        .map(|i| match names.output_name(i) {
+            Some(n) => n.to_owned(),
+            None => names.name_or_generated(aig.output(i)).0,
+        })
+        .collect()
+}
+
+/// Write test patterns generated on a network with exposed flip-flops
+///
+/// In tester formats, the expected responses are written as well, and the flip-flops are accessed
+/// through a scan chain.
+fn write_scan_pattern_file(
+    path: &PathBuf,
+    aig: &Network,
+    names: &NameTable,
+    patterns: &[Vec<Vec<bool>>],
+    input_names: &[String],
+) {
+    if !is_tester_pattern_file(path) {
+        write_named_pattern_file(path, patterns, input_names);
+        return;
+    }
+    let loc = LaunchOnCapture::new(aig);
+    let (inputs, scan_cells) = input_names.split_at(aig.nb_inputs());
+    let signals = TesterSignals::new(
+        inputs.to_vec(),
+        output_names(aig, names),
+        scan_cells.to_vec(),
+    );
+    let responses: Vec<Vec<Vec<bool>>> = patterns
+        .iter()
+        .map(|p| {
+            p.iter()
+                .map(|v| {
+                    let mut r = simulate_comb(loc.exposed(), v);
+                    r.truncate(aig.nb_outputs());
+                    r.extend(loc.next_state(v));
+                    r
+                })
+                .collect()
+        })
+        .collect();
+    unwrap_file(write_tester_pattern_file(
+        path, &signals, patterns, &responses,
+    ));
+}
+
+/// Write a pattern file, with the names of the inputs in a comment
+fn write_named_pattern_file(path: &PathBuf, patterns: &[Vec<Vec<bool>>], input_names: &[String]) {
+    let mut writer = unwrap_file(create_pattern_file(path));
+    writer.write_input_names(input_names);
+    for p in patterns {
+        writer.write(p);
+    }
+    writer.flush();
+}
+
+/// Command arguments for test pattern generation report
+#[derive(Args)]
+pub struct AtpgReportArgs {
+    /// Network to analyze
+    network: PathBuf,
+
+    /// Test pattern file
+    patterns: PathBuf,
+
+    /// Do not remove redundant faults beforehand
+    #[arg(long, default_value_t = false)]
+    with_redundant_faults: bool,
+
+    /// Write the detection status of each fault to a CSV file, with faults located by net names
+    /// and source lines
+    #[arg(long)]
+    fault_report: Option<PathBuf>,
+
+    /// Weights of the faults, to report weighted coverage
+    #[arg(long, value_name = "FILE")]
+    fault_weights: Option<PathBuf>,
+}
+
+impl AtpgReportArgs {
+    pub fn run(&self) {
+        let ReadResult {
+            network: mut aig,
+            names,
+            locations,
+            ..
+        } = unwrap_file(read_file_full(&self.network));
+
+        if !aig.is_comb() {
+            println!("Exposing flip-flops for a sequential network");
+            aig = expose_dff(&aig);
+        }
+        // Patterns are streamed from the file, and only the first timestep is used
+        let patterns = || {
+            unwrap_file(open_pattern_file(&self.patterns)).map(|p| match p {
+                Ok(p) => p.into_iter().next().unwrap_or_default(),
+                Err(e) => {
+                    println!("Invalid pattern file {}: {}", self.patterns.display(), e);
+                    std::process::exit(1);
+                }
+            })
+        };
+        if let Some(path) = &self.fault_report {
+            let faults = if self.with_redundant_faults {
+                Fault::all(&aig)
+            } else {
+                Fault::all_unique(&aig)
+            };
+            let mut grader = FaultGrader::new(&aig, faults);
+            for p in patterns() {
+                grader.add_pattern(p);
+            }
+            write_fault_report(path, &aig, &names, &locations, &grader.finish());
+        }
+        let weights = self
+            .fault_weights
+            .as_ref()
+            .map(|path| read_fault_weights(path, &aig, &names));
+        report_comb_test_patterns(
+            &aig,
+            patterns(),
+            self.with_redundant_faults,
+            weights.as_ref(),
+        );
+    }
+}
+
+/// Command arguments for fault diagnosis
+#[derive(Args)]
+pub struct DiagnoseArgs {
+    /// Network of the device
+    network: PathBuf,
+
+    /// Test pattern file
+    patterns: PathBuf,
+
+    /// Responses observed on the device, one per pattern
+    responses: PathBuf,
+
+    /// Do not remove redundant faults beforehand
+    #[arg(long, default_value_t = false)]
+    with_redundant_faults: bool,
+
+    /// Maximum number of candidate faults to print
+    #[arg(short = 'n', long, default_value_t = 10)]
+    max_candidates: usize,
+}
+
+impl DiagnoseArgs {
+    pub fn run(&self) {
+        let ReadResult {
+            network: mut aig,
+            names,
+            ..
+        } = unwrap_file(read_file_full(&self.network));
+        if !aig.is_comb() {
+            println!("Exposing flip-flops for a sequential network");
+            aig = expose_dff(&aig);
+        }
+        // Only the first timestep of each pattern is used
+        let first_timestep = |path: &PathBuf| -> Vec<Vec<bool>> {
+            unwrap_file(read_pattern_file(path))
+                .into_iter()
+                .map(|p| p.into_iter().next().unwrap_or_default())
+                .collect()
+        };
+        let patterns = first_timestep(&self.patterns);
+        let responses = first_timestep(&self.responses);
+        if let Some(p) = patterns.iter().find(|p| p.len() != aig.nb_inputs()) {
+            println!(
+                "Expected patterns with {} inputs, got {}",
+                aig.nb_inputs(),
+                p.len()
+            );
+            std::process::exit(1);
+        }
+        let candidates = match diagnose(&aig, &patterns, &responses, self.with_redundant_faults) {
+            Ok(c) => c,
+            Err(e) => {
+                println!("Invalid responses {}: {}", self.responses.display(), e);
+                std::process::exit(1);
+            }
+        };
+        if candidates.is_empty() {
+            println!("No stuck-at fault explains the observed responses");
+            return;
+        }
+        let nb_failures = candidates[0].explained + candidates[0].unexplained;
+        println!(
+            "{} failing output bits over {} patterns, {} candidate faults",
+            count(nb_failures),
+            count(patterns.len()),
+            count(candidates.len())
+        );
+        println!("  Rank  Explained  Unexplained  Mispredicted  Fault");
+        for (i, c) in candidates.iter().take(self.max_candidates).enumerate() {
+            println!(
+                "{:>6}  {:>9}  {:>11}  {:>12}  {}{}",
+                i + 1,
+                count(c.explained),
+                count(c.unexplained),
+                count(c.mispredicted),
+                c.fault.describe(&aig, &names),
+                if c.is_exact() { " (exact)" } else { "" }
+            );
+        }
+    }
+}
+
+/// Read a fault weight file, or exit with an error
+fn read_fault_weights(path: &Path, aig: &Network, names: &NameTable) -> FaultWeights {
+    let text = unwrap_file(std::fs::read_to_string(path).map_err(|e| QuaighError::io(path, e)));
+    match FaultWeights::parse(&text, aig, names) {
+        Ok(w) => w,
+        Err(e) => {
+            println!("Invalid fault weights {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Write the detection status of each fault as CSV
+fn write_fault_report(
+    path: &Path,
+    aig: &Network,
+    names: &NameTable,
+    locations: &SourceMap,
+    detections: &[(Fault, bool)],
+) {
+    use std::io::Write;
+    let mut f = Vec::new();
+    writeln!(f, "fault,net,sink,pin,value,detected,location").unwrap();
+    let mut nb_generated = 0;
+    for (fault, detected) in detections {
+        let d = fault.describe_with_locations(aig, names, locations);
+        if d.generated {
+            nb_generated += 1;
+        }
+        let (sink, pin) = match &d.sink {
+            Some((sink, pin)) => (sink.clone(), pin.to_string()),
+            None => (String::new(), String::new()),
+        };
+        writeln!(
+            f,
+            "{},{},{},{},{},{},{}",
+            d,
+            d.net,
+            sink,
+            pin,
+            i32::from(d.value),
+            i32::from(*detected),
+            d.location.as_deref().unwrap_or_default()
+        )
+        .unwrap();
+    }
+    if nb_generated != 0 {
+        eprintln!(
+            "Warning: {} faults are located on unnamed nets and use generated names",
+            nb_generated
+        );
+    }
+    unwrap_file(std::fs::write(path, f).map_err(|e| QuaighError::io(path, e)));
+}
+
+/// Command arguments for configuration files
+#[derive(Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    command: ConfigCommand,
+}
+
+/// Commands on configuration files
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print the effective configuration, with the defaults merged with the file and flags
+    ///
+    /// Flags are taken from an optimize or atpg command line given after --, for example:
+    ///    quaigh config dump -- opt design.bench -o out.bench --config flow.toml --effort 2
+    #[clap()]
+    Dump(ConfigDumpArgs),
+}
+
+/// Command arguments to print the effective configuration
+#[derive(Args)]
+pub struct ConfigDumpArgs {
+    /// Configuration file to merge with the defaults
+    #[arg(long, value_name = "FILE", conflicts_with = "command")]
+    config: Option<PathBuf>,
+
+    /// Output file; the configuration is printed if omitted
+    #[arg(short = 'o', long)]
+    output: Option<PathBuf>,
+
+    /// Optimize or atpg command line whose flags are merged
+    #[arg(last = true)]
+    command: Vec<String>,
+}
+
+impl ConfigDumpArgs {
+    fn effective_config(&self) -> Result<Config, String> {
+        if self.command.is_empty() {
+            return Config::load(self.config.as_deref());
+        }
+        let args = std::iter::once("quaigh").chain(self.command.iter().map(|s| s.as_str()));
+        let cli = Cli::try_parse_from(args).map_err(|e| e.to_string())?;
+        match cli.command {
+            Commands::Optimize(a) => a.effective_config(),
+            Commands::Atpg(a) => a.effective_config(),
+            _ => Err("Only optimize and atpg commands use a configuration".to_owned()),
+        }
+    }
+}
+
+impl ConfigArgs {
+    pub fn run(&self) {
+        match &self.command {
+            ConfigCommand::Dump(a) => {
+                let config = unwrap_config(a.effective_config());
+                match &a.output {
+                    Some(path) => unwrap_file(
+                        std::fs::write(path, config.dump()).map_err(|e| QuaighError::io(path, e)),
+                    ),
+                    None => print!("{}", config.dump()),
+                }
+            }
+        }
+    }
+}
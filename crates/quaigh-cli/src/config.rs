@@ -0,0 +1,381 @@
+//! Configuration files for the optimization and test pattern generation commands
+//!
+//! A configuration file captures a whole flow, so that it can be archived with its results and
+//! reproduced later. Flags given on the command line override the values of the file, and
+//! `quaigh config dump` prints the effective configuration.
+//!
+//! All keys are optional, and unknown keys are rejected:
+//!
+//! ```toml
+//! [optimize]
+//! effort = 1                  # Effort level, used if no pass sequence is given
+//! passes = ["share(64)", "fraig", "xor-mux", "dffe", "share(64)", "rewrite", "balance(16)"]
+//! seed = 3                    # Seed for randomized algorithms
+//! area = "vlsi"               # Area model for exploration: vlsi, fpga or sat
+//! explore = 16                # Number of randomized pass orderings to explore
+//! jobs = 4                    # Number of threads used for optimization and exploration
+//! incremental = false         # Only optimize the components that changed
+//! cache_dir = ".quaigh-cache" # Cache directory for incremental optimization
+//! verify = false              # Check cached results for equivalence
+//! no_provenance = false       # Do not write the provenance header
+//! lut_k = 6                   # Map the result to Luts with at most this number of inputs
+//! resub = false               # Run Boolean resubstitution after the passes
+//! dont_cares = false          # Simplify with observability and external don't cares
+//!
+//! [atpg]
+//! seed = 1                    # Random seed for test pattern generation
+//! num_cycles = 4              # Number of cycles for random sequential patterns
+//! num_random = 100            # Number of random sequential patterns
+//! with_redundant_faults = false
+//! ties = ["i5=1", "i6=0"]     # Inputs tied to a constant on the tester
+//! suggest_observe = 10        # Number of observation points to suggest
+//! observe_output = "observed.bench"
+//! fault_weights = "weights.txt" # Relative likelihood of the faults
+//! transition = false          # Two-cycle patterns for transition faults
+//! iddq = false                # Patterns that only activate the faults, for IDDQ testing
+//! dynamic_compaction = false  # Target several faults with each Sat pattern
+//! exact_compaction = false    # Select a minimum set of patterns with the Sat solver
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use quaigh::atpg::Compaction;
+use quaigh::optim::{Pass, Pipeline, MAX_LUT_SIZE, MIN_LUT_SIZE};
+use serde::{Deserialize, Serialize};
+
+use crate::cmd::{parse_tie, AreaModel};
+
+/// Effective configuration of a flow
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Options of the optimization command
+    pub optimize: OptimizeConfig,
+    /// Options of the test pattern generation command
+    pub atpg: AtpgConfig,
+}
+
+/// Options of the optimization command
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OptimizeConfig {
+    /// Effort level, used if no pass sequence is given
+    pub effort: u64,
+    /// Sequence of passes, such as `share(64)`, `balance(16)`, `adders(8)`, `window(6)`,
+    /// `xor-mux`, `dffe`, `dffr`, `dedup-ff`, `rewrite`, `mig-rewrite`, `xor-blocks`,
+    /// `const-mult` or `fraig`
+    pub passes: Option<Vec<String>>,
+    /// Seed for randomized algorithms
+    pub seed: Option<u64>,
+    /// Area model used to evaluate the results of exploration
+    pub area: AreaModel,
+    /// Number of randomized pass orderings to explore
+    pub explore: Option<usize>,
+    /// Number of threads used for optimization and exploration
+    pub jobs: usize,
+    /// Only optimize the parts of the design that changed since a previous run
+    pub incremental: bool,
+    /// Cache directory for incremental optimization
+    pub cache_dir: PathBuf,
+    /// Check that cached results are equivalent to the components they replace
+    pub verify: bool,
+    /// Do not write the provenance header
+    pub no_provenance: bool,
+    /// Map the result to Luts with at most this number of inputs
+    pub lut_k: Option<usize>,
+    /// Run Boolean resubstitution after the passes
+    pub resub: bool,
+    /// Simplify the nodes with observability and external don't cares after the passes
+    pub dont_cares: bool,
+}
+
+impl Default for OptimizeConfig {
+    fn default() -> Self {
+        OptimizeConfig {
+            effort: 1,
+            passes: None,
+            seed: None,
+            area: AreaModel::Vlsi,
+            explore: None,
+            jobs: 1,
+            incremental: false,
+            cache_dir: PathBuf::from(".quaigh-cache"),
+            verify: false,
+            no_provenance: false,
+            lut_k: None,
+            resub: false,
+            dont_cares: false,
+        }
+    }
+}
+
+impl OptimizeConfig {
+    /// Pipeline to run, from the pass sequence or the effort level
+    pub fn pipeline(&self) -> Result<Pipeline, String> {
+        let mut pipeline = match &self.passes {
+            Some(passes) => {
+                let mut ret = Pipeline::default();
+                for (i, p) in passes.iter().enumerate() {
+                    let pass = p
+                        .parse::<Pass>()
+                        .map_err(|e| format!("optimize.passes[{}]: {}", i, e))?;
+                    ret.passes.push(pass);
+                }
+                ret
+            }
+            None => Pipeline::from_effort(self.effort),
+        };
+        pipeline.seed = self.seed;
+        Ok(pipeline)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.pipeline()?;
+        if self.jobs == 0 {
+            return Err("optimize.jobs: at least one thread is required".to_owned());
+        }
+        if self.explore.is_some() {
+            if self.incremental {
+                return Err("optimize.incremental cannot be used with optimize.explore".to_owned());
+            }
+            if self.passes.is_some() {
+                return Err("optimize.passes cannot be used with optimize.explore".to_owned());
+            }
+            if self.resub {
+                return Err("optimize.resub cannot be used with optimize.explore".to_owned());
+            }
+            if self.dont_cares {
+                return Err("optimize.dont_cares cannot be used with optimize.explore".to_owned());
+            }
+        }
+        if self.verify && !self.incremental {
+            return Err("optimize.verify requires optimize.incremental".to_owned());
+        }
+        if let Some(k) = self.lut_k {
+            if !(MIN_LUT_SIZE..=MAX_LUT_SIZE).contains(&k) {
+                return Err(format!(
+                    "optimize.lut_k: Lut size must be between {} and {}",
+                    MIN_LUT_SIZE, MAX_LUT_SIZE
+                ));
+            }
+            if self.explore.is_some() {
+                return Err("optimize.lut_k cannot be used with optimize.explore".to_owned());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Options of the test pattern generation command
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AtpgConfig {
+    /// Random seed for test pattern generation
+    pub seed: u64,
+    /// Number of cycles for random sequential patterns
+    pub num_cycles: Option<usize>,
+    /// Number of random sequential patterns
+    pub num_random: Option<usize>,
+    /// Do not remove redundant faults beforehand
+    pub with_redundant_faults: bool,
+    /// Inputs tied to a constant on the tester, such as `i5=1`
+    pub ties: Vec<String>,
+    /// Number of internal signals to suggest as observation points
+    pub suggest_observe: Option<usize>,
+    /// File where the network with the observation points is written
+    pub observe_output: Option<PathBuf>,
+    /// Weights of the faults, for weighted coverage
+    pub fault_weights: Option<PathBuf>,
+    /// Generate launch-on-capture patterns for transition faults instead of stuck-at faults
+    pub transition: bool,
+    /// Generate patterns that only activate the faults, for IDDQ testing
+    pub iddq: bool,
+    /// Target several undetected faults with each pattern generated by the Sat solver
+    pub dynamic_compaction: bool,
+    /// Select a minimum set of patterns with the Sat solver
+    pub exact_compaction: bool,
+}
+
+impl Default for AtpgConfig {
+    fn default() -> Self {
+        AtpgConfig {
+            seed: 1,
+            num_cycles: None,
+            num_random: None,
+            with_redundant_faults: false,
+            ties: Vec::new(),
+            suggest_observe: None,
+            observe_output: None,
+            fault_weights: None,
+            transition: false,
+            iddq: false,
+            dynamic_compaction: false,
+            exact_compaction: false,
+        }
+    }
+}
+
+impl AtpgConfig {
+    /// Tied inputs, as indices and values
+    pub fn ties(&self) -> Result<Vec<(usize, bool)>, String> {
+        self.ties
+            .iter()
+            .enumerate()
+            .map(|(i, t)| parse_tie(t).map_err(|e| format!("atpg.ties[{}]: {}", i, e)))
+            .collect()
+    }
+
+    /// Compaction strategies for test pattern generation
+    pub fn compaction(&self) -> Compaction {
+        Compaction {
+            dynamic: self.dynamic_compaction,
+            exact: self.exact_compaction,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.ties()?;
+        if self.observe_output.is_some() && self.suggest_observe.is_none() {
+            return Err("atpg.observe_output requires atpg.suggest_observe".to_owned());
+        }
+        if self.transition && (self.num_cycles.is_some() || self.num_random.is_some()) {
+            return Err(
+                "atpg.transition is not supported for random sequential patterns".to_owned(),
+            );
+        }
+        if self.transition && self.suggest_observe.is_some() {
+            return Err("atpg.transition is not supported with atpg.suggest_observe".to_owned());
+        }
+        if self.iddq && self.transition {
+            return Err("atpg.iddq and atpg.transition cannot be used together".to_owned());
+        }
+        if self.iddq && (self.num_cycles.is_some() || self.num_random.is_some()) {
+            return Err("atpg.iddq is not supported for random sequential patterns".to_owned());
+        }
+        if self.iddq && self.suggest_observe.is_some() {
+            return Err("atpg.iddq is not supported with atpg.suggest_observe".to_owned());
+        }
+        if (self.dynamic_compaction || self.exact_compaction)
+            && (self.num_cycles.is_some() || self.num_random.is_some())
+        {
+            return Err(
+                "atpg compaction is not supported for random sequential patterns".to_owned(),
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    /// Parse a configuration and check its values
+    pub fn parse(s: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(s).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Read a configuration file, or return the defaults if there is none
+    pub fn load(path: Option<&Path>) -> Result<Config, String> {
+        let Some(path) = path else {
+            return Ok(Config::default());
+        };
+        let s = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Config::parse(&s).map_err(|e| format!("Invalid configuration {}: {}", path.display(), e))
+    }
+
+    /// Check the values that cannot be checked while parsing
+    pub fn validate(&self) -> Result<(), String> {
+        self.optimize.validate()?;
+        self.atpg.validate()
+    }
+
+    /// Write the configuration as TOML
+    pub fn dump(&self) -> String {
+        toml::to_string(self).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::Config;
+    use crate::cmd::{Cli, Commands};
+
+    const FLOW: &str = r#"
+[optimize]
+passes = ["share(64)", "xor-mux", "balance(16)"]
+seed = 3
+area = "fpga"
+
+[atpg]
+seed = 7
+ties = ["i1=0", "2=1"]
+with_redundant_faults = true
+"#;
+
+    #[test]
+    fn test_dump_reload() {
+        let default = Config::default();
+        assert_eq!(Config::parse(&default.dump()), Ok(default));
+        let config = Config::parse(FLOW).unwrap();
+        assert_eq!(config.optimize.pipeline().unwrap().passes.len(), 3);
+        assert_eq!(config.atpg.ties(), Ok(vec![(1, false), (2, true)]));
+        assert_eq!(Config::parse(&config.dump()), Ok(config));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = Config::parse("[optimize]\nefort = 2\n").unwrap_err();
+        assert!(err.contains("efort"), "{}", err);
+        let err = Config::parse("[optimize]\narea = \"asic\"\n").unwrap_err();
+        assert!(err.contains("area"), "{}", err);
+        let err = Config::parse("[optimize]\npasses = [\"dffe\", \"refactor\"]\n").unwrap_err();
+        assert!(err.starts_with("optimize.passes[1]"), "{}", err);
+        let err = Config::parse("[atpg]\nties = [\"i1=2\"]\n").unwrap_err();
+        assert!(err.starts_with("atpg.ties[0]"), "{}", err);
+        assert!(Config::parse("[atpg]\ntransition = true\nnum_cycles = 2\n").is_err());
+        assert!(Config::parse("[atpg]\ntransition = true\niddq = true\n").is_err());
+        assert!(Config::parse("[atpg]\nexact_compaction = true\nnum_random = 4\n").is_err());
+        let err = Config::parse("[optimize]\nverify = true\n").unwrap_err();
+        assert!(err.contains("optimize.incremental"), "{}", err);
+        let err = Config::parse("[optimize]\nlut_k = 8\n").unwrap_err();
+        assert!(err.starts_with("optimize.lut_k"), "{}", err);
+        let err = Config::parse("[optimize]\nexplore = 4\nresub = true\n").unwrap_err();
+        assert!(err.starts_with("optimize.resub"), "{}", err);
+    }
+
+    #[test]
+    fn test_flags_override() {
+        let path = std::env::temp_dir().join(format!("quaigh-flow-{}.toml", std::process::id()));
+        std::fs::write(&path, FLOW).unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let cli = Cli::parse_from([
+            "quaigh", "opt", "a.bench", "-o", "b.bench", "--config", path_str, "--seed", "5",
+        ]);
+        let Commands::Optimize(args) = cli.command else {
+            panic!()
+        };
+        let config = args.effective_config().unwrap();
+        assert_eq!(config.optimize.seed, Some(5));
+        assert_eq!(config.optimize.passes.as_ref().unwrap().len(), 3);
+        assert_eq!(Config::parse(&config.dump()), Ok(config));
+
+        let cli = Cli::parse_from([
+            "quaigh", "atpg", "a.bench", "-o", "a.test", "--config", path_str, "--tie", "i4=1",
+        ]);
+        let Commands::Atpg(args) = cli.command else {
+            panic!()
+        };
+        let config = args.effective_config().unwrap();
+        assert_eq!(config.atpg.seed, 7);
+        assert_eq!(config.atpg.ties, vec!["i4=1"]);
+        assert!(config.atpg.with_redundant_faults);
+        assert_eq!(Config::parse(&config.dump()), Ok(config));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,492 @@
+//! Data provider for the network inspector
+//!
+//! All the queries of the interactive inspector go through [`Inspector`], so that they can be
+//! tested without a terminal. Fanouts and levels are only computed the first time they are
+//! needed, and searches refine the previous result when the query is extended.
+
+// Without the terminal interface, only the detail queries are used
+#![cfg_attr(not(feature = "tui"), allow(dead_code))]
+
+#[cfg(feature = "tui")]
+pub mod tui;
+
+use std::collections::{BTreeSet, HashMap};
+
+use quaigh::network::SourceMap;
+use quaigh::sim::simulate_nodes_multi;
+use quaigh::{Gate, Network, Signal};
+
+/// Maximum number of support signals for which a truth table is shown
+pub const MAX_TRUTH_TABLE_SUPPORT: usize = 6;
+
+/// Truth table of a node as a function of its combinatorial support
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TruthTable {
+    /// Support signals, from least to most significant bit of the table
+    pub support: Vec<Signal>,
+    /// Value of the node for each assignment of the support, one bit per assignment
+    pub bits: u64,
+}
+
+impl std::fmt::Display for TruthTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let nb_bits = 1usize << self.support.len();
+        let nb_digits = nb_bits.div_ceil(4);
+        write!(f, "0x{:0width$x} over (", self.bits, width = nb_digits)?;
+        for (i, s) in self.support.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{s}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Everything shown about a single signal in the detail pane
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeDetail {
+    /// The signal being shown
+    pub signal: Signal,
+    /// Gate driving the signal, if it is not a primary input
+    pub gate: Option<Gate>,
+    /// Signals used by the gate
+    pub fanins: Vec<Signal>,
+    /// Nodes using the signal
+    pub fanouts: Vec<Signal>,
+    /// Logic level of the signal
+    pub level: usize,
+    /// Primary outputs driven by the signal, with their polarity
+    pub outputs: Vec<(usize, bool)>,
+    /// Truth table, if the combinatorial support is small enough
+    pub truth_table: Option<TruthTable>,
+    /// Location of the gate in the source file, if known
+    pub location: Option<String>,
+}
+
+impl std::fmt::Display for NodeDetail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Node {}", self.signal)?;
+        match &self.gate {
+            Some(g) => writeln!(f, "  Gate: {g}")?,
+            None => writeln!(f, "  Primary input")?,
+        }
+        if let Some(l) = &self.location {
+            writeln!(f, "  Source: {l}")?;
+        }
+        writeln!(f, "  Level: {}", self.level)?;
+        writeln!(f, "  Fanins: {}", join(&self.fanins))?;
+        writeln!(f, "  Fanouts: {}", join(&self.fanouts))?;
+        if !self.outputs.is_empty() {
+            let outputs: Vec<String> = self
+                .outputs
+                .iter()
+                .map(|(o, inv)| format!("{}o{o}", if *inv { "!" } else { "" }))
+                .collect();
+            writeln!(f, "  Outputs: {}", outputs.join(", "))?;
+        }
+        if let Some(tt) = &self.truth_table {
+            writeln!(f, "  Truth table: {tt}")?;
+        }
+        Ok(())
+    }
+}
+
+fn join(signals: &[Signal]) -> String {
+    if signals.is_empty() {
+        return "-".to_owned();
+    }
+    let v: Vec<String> = signals.iter().map(|s| s.to_string()).collect();
+    v.join(", ")
+}
+
+/// Query layer over a network for the interactive inspector
+pub struct Inspector {
+    aig: Network,
+    /// Translation of the nodes, if the network had to be sorted
+    translation: Option<Box<[Signal]>>,
+    locations: SourceMap,
+    entries: Vec<Signal>,
+    fanouts: Option<HashMap<Signal, Vec<Signal>>>,
+    levels: Option<Vec<usize>>,
+    last_query: String,
+    last_results: Vec<Signal>,
+    marked: BTreeSet<Signal>,
+}
+
+impl Inspector {
+    /// Create an inspector for a network
+    pub fn new(aig: Network) -> Inspector {
+        let mut aig = aig;
+        let translation = if aig.is_topo_sorted() {
+            None
+        } else {
+            Some(aig.topo_sort())
+        };
+        let entries: Vec<Signal> = (0..aig.nb_inputs())
+            .map(|i| aig.input(i))
+            .chain((0..aig.nb_nodes()).map(|i| aig.node(i)))
+            .collect();
+        Inspector {
+            aig,
+            translation,
+            locations: SourceMap::new(),
+            last_results: entries.clone(),
+            entries,
+            fanouts: None,
+            levels: None,
+            last_query: String::new(),
+            marked: BTreeSet::new(),
+        }
+    }
+
+    /// Show the source locations of the gates, given for the network passed to [`new`](Inspector::new)
+    pub fn with_locations(mut self, locations: SourceMap) -> Inspector {
+        self.locations = locations;
+        if let Some(t) = &self.translation {
+            self.locations.remap(t);
+        }
+        self
+    }
+
+    /// All signals that can be inspected: primary inputs then nodes
+    pub fn entries(&self) -> &[Signal] {
+        &self.entries
+    }
+
+    /// Parse a signal name as shown by the inspector (x12, i3 or !x12)
+    pub fn parse_signal(&self, name: &str) -> Option<Signal> {
+        let name = name.trim();
+        let (inv, name) = match name.strip_prefix('!') {
+            Some(n) => (true, n),
+            None => (false, name),
+        };
+        let s = if let Some(i) = name.strip_prefix('x') {
+            let i: usize = i.parse().ok()?;
+            if i >= self.aig.nb_nodes() {
+                return None;
+            }
+            self.aig.node(i)
+        } else if let Some(i) = name.strip_prefix('i') {
+            let i: usize = i.parse().ok()?;
+            if i >= self.aig.nb_inputs() {
+                return None;
+            }
+            self.aig.input(i)
+        } else {
+            return None;
+        };
+        Some(s ^ inv)
+    }
+
+    /// Search signals whose name contains the query, case-insensitively
+    ///
+    /// A bare number matches by index. When the query extends the previous one, only the
+    /// previous results are filtered again.
+    pub fn search(&mut self, query: &str) -> &[Signal] {
+        let query = query.trim().to_lowercase();
+        let refine = query.starts_with(&self.last_query);
+        let candidates = if refine {
+            std::mem::take(&mut self.last_results)
+        } else {
+            self.entries.clone()
+        };
+        self.last_results = candidates
+            .into_iter()
+            .filter(|s| s.to_string().contains(&query))
+            .collect();
+        self.last_query = query;
+        &self.last_results
+    }
+
+    /// Best match for the last query: the signal named exactly like it if any, else the first result
+    pub fn best_match(&self) -> Option<Signal> {
+        let exact = self
+            .last_results
+            .iter()
+            .find(|s| s.to_string() == self.last_query);
+        exact.or(self.last_results.first()).copied()
+    }
+
+    /// Nodes using a signal
+    pub fn fanouts(&mut self, s: Signal) -> &[Signal] {
+        let aig = &self.aig;
+        let fanouts = self.fanouts.get_or_insert_with(|| {
+            let mut ret: HashMap<Signal, Vec<Signal>> = HashMap::new();
+            for i in 0..aig.nb_nodes() {
+                let mut deps: Vec<Signal> = aig
+                    .gate(i)
+                    .dependencies()
+                    .iter()
+                    .filter(|d| !d.is_constant())
+                    .map(|d| d.without_inversion())
+                    .collect();
+                deps.sort();
+                deps.dedup();
+                for d in deps {
+                    ret.entry(d).or_default().push(aig.node(i));
+                }
+            }
+            ret
+        });
+        fanouts
+            .get(&s.without_inversion())
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Logic level of a signal
+    pub fn level(&mut self, s: Signal) -> usize {
+        if !s.is_var() {
+            return 0;
+        }
+        let aig = &self.aig;
+        self.levels.get_or_insert_with(|| aig.levels())[s.var() as usize]
+    }
+
+    /// Truth table of a signal, if its combinatorial support has at most
+    /// [`MAX_TRUTH_TABLE_SUPPORT`] signals
+    ///
+    /// The support is made of primary inputs and flip-flop outputs.
+    pub fn truth_table(&self, s: Signal) -> Option<TruthTable> {
+        let mut support = BTreeSet::new();
+        let mut cone = BTreeSet::new();
+        let mut todo = vec![s.without_inversion()];
+        while let Some(t) = todo.pop() {
+            if t.is_constant() || support.contains(&t) || cone.contains(&t) {
+                continue;
+            }
+            if t.is_input() || !self.aig.gate(t.var() as usize).is_comb() {
+                support.insert(t);
+                if support.len() > MAX_TRUTH_TABLE_SUPPORT {
+                    return None;
+                }
+                continue;
+            }
+            cone.insert(t);
+            for d in self.aig.dependencies(t.var() as usize) {
+                todo.push(d.without_inversion());
+            }
+        }
+
+        // Extract the cone with the support as primary inputs, then simulate all assignments
+        let mut support: Vec<Signal> = support.into_iter().collect();
+        support.sort_by_key(|s| (s.is_var(), if s.is_var() { s.var() } else { s.input() }));
+        let mut sub = Network::new();
+        let mut translation = HashMap::new();
+        for s in &support {
+            translation.insert(*s, sub.add_input());
+        }
+        for t in &cone {
+            let g = self.aig.gate(t.var() as usize).remap(|d| {
+                if d.is_constant() {
+                    *d
+                } else {
+                    translation[&d.without_inversion()] ^ d.is_inverted()
+                }
+            });
+            translation.insert(*t, sub.add(g));
+        }
+        let patterns: Vec<u64> = (0..support.len()).map(input_pattern).collect();
+        let nb_bits = 1usize << support.len();
+        let mask = if nb_bits == 64 {
+            !0u64
+        } else {
+            (1u64 << nb_bits) - 1
+        };
+        let value = if s.is_constant() {
+            0
+        } else {
+            let t = translation[&s.without_inversion()];
+            if t.is_input() {
+                patterns[t.input() as usize]
+            } else {
+                simulate_nodes_multi(&sub, &patterns)[t.var() as usize]
+            }
+        };
+        let value = if s.is_inverted() { !value } else { value };
+        Some(TruthTable {
+            support,
+            bits: value & mask,
+        })
+    }
+
+    /// Assemble everything shown about a signal
+    pub fn detail(&mut self, s: Signal) -> NodeDetail {
+        let s = s.without_inversion();
+        let gate = if s.is_var() {
+            Some(self.aig.gate(s.var() as usize).clone())
+        } else {
+            None
+        };
+        let fanins = match &gate {
+            Some(g) => g.dependencies().to_vec(),
+            None => Vec::new(),
+        };
+        let outputs = (0..self.aig.nb_outputs())
+            .filter(|o| self.aig.output(*o).without_inversion() == s)
+            .map(|o| (o, self.aig.output(o).is_inverted()))
+            .collect();
+        NodeDetail {
+            signal: s,
+            gate,
+            fanins,
+            fanouts: self.fanouts(s).to_vec(),
+            level: self.level(s),
+            outputs,
+            truth_table: self.truth_table(s),
+            location: self
+                .locations
+                .signal_location(s)
+                .map(|l| self.locations.format(l)),
+        }
+    }
+
+    /// Mark or unmark a signal for export; return whether it is now marked
+    pub fn toggle_mark(&mut self, s: Signal) -> bool {
+        let s = s.without_inversion();
+        if !self.marked.remove(&s) {
+            self.marked.insert(s);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Signals currently marked
+    pub fn marked(&self) -> &BTreeSet<Signal> {
+        &self.marked
+    }
+
+    /// Extract the cone of the marked signals, with one output per marked signal
+    pub fn export_marked_cone(&self) -> Network {
+        let mut ret = Network::new();
+        ret.add_inputs(self.aig.nb_inputs());
+        for i in 0..self.aig.nb_nodes() {
+            ret.add(self.aig.gate(i).clone());
+        }
+        for s in &self.marked {
+            ret.add_output(*s);
+        }
+        ret.cleanup();
+        ret
+    }
+}
+
+/// Value of a support signal for all assignments, as in a truth table
+fn input_pattern(i: usize) -> u64 {
+    const PATTERNS: [u64; 6] = [
+        0xaaaa_aaaa_aaaa_aaaa,
+        0xcccc_cccc_cccc_cccc,
+        0xf0f0_f0f0_f0f0_f0f0,
+        0xff00_ff00_ff00_ff00,
+        0xffff_0000_ffff_0000,
+        0xffff_ffff_0000_0000,
+    ];
+    PATTERNS[i]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quaigh::network::SourceLocation;
+
+    /// x0 = i0 & i1, x1 = x0 ^ i2, x2 = Dff(x1), x3 = x2 & !i0
+    fn example() -> Network {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let x0 = aig.and(i0, i1);
+        let x1 = aig.xor(x0, i2);
+        let x2 = aig.dff(x1, Signal::one(), Signal::zero());
+        let x3 = aig.and(x2, !i0);
+        aig.add_output(x1);
+        aig.add_output(!x3);
+        aig
+    }
+
+    #[test]
+    fn test_search() {
+        let mut insp = Inspector::new(example());
+        assert_eq!(insp.search("").len(), 7);
+        assert_eq!(insp.search("x").len(), 4);
+        assert_eq!(insp.search("x1"), &[Signal::from_var(1)]);
+        assert_eq!(insp.best_match(), Some(Signal::from_var(1)));
+        // Refining then widening the query again
+        assert!(insp.search("x12").is_empty());
+        assert_eq!(insp.search("I").len(), 3);
+        assert_eq!(insp.search("2").len(), 2);
+        assert_eq!(insp.parse_signal("!x3"), Some(!Signal::from_var(3)));
+        assert_eq!(insp.parse_signal("x4"), None);
+        assert_eq!(insp.parse_signal("i2"), Some(Signal::from_input(2)));
+    }
+
+    #[test]
+    fn test_detail() {
+        let mut insp = Inspector::new(example());
+        let d = insp.detail(Signal::from_var(1));
+        assert_eq!(d.fanins, vec![Signal::from_var(0), Signal::from_input(2)]);
+        assert_eq!(d.fanouts, vec![Signal::from_var(2)]);
+        assert_eq!(d.level, 2);
+        assert_eq!(d.outputs, vec![(0, false)]);
+        let tt = d.truth_table.unwrap();
+        assert_eq!(
+            tt.support,
+            vec![
+                Signal::from_input(0),
+                Signal::from_input(1),
+                Signal::from_input(2)
+            ]
+        );
+        assert_eq!(tt.bits, 0b0111_1000);
+        assert_eq!(tt.to_string(), "0x78 over (i0, i1, i2)");
+
+        // The flip-flop output is part of the support
+        let d = insp.detail(Signal::from_var(3));
+        assert_eq!(d.level, 1);
+        assert_eq!(d.outputs, vec![(1, true)]);
+        let tt = d.truth_table.unwrap();
+        assert_eq!(tt.support, vec![Signal::from_input(0), Signal::from_var(2)]);
+        assert_eq!(tt.bits, 0b0100);
+
+        let d = insp.detail(Signal::from_input(0));
+        assert_eq!(d.gate, None);
+        assert_eq!(d.fanouts, vec![Signal::from_var(0), Signal::from_var(3)]);
+        assert_eq!(d.truth_table.unwrap().bits, 0b10);
+        assert_eq!(d.location, None);
+
+        let mut locations = SourceMap::new();
+        let file = locations.add_file("example.bench");
+        locations.set_node_location(1, SourceLocation { file, line: 4 });
+        let mut insp = Inspector::new(example()).with_locations(locations);
+        let d = insp.detail(!Signal::from_var(1));
+        assert_eq!(d.location.as_deref(), Some("example.bench:4"));
+        assert!(d.to_string().contains("Source: example.bench:4"));
+    }
+
+    #[test]
+    fn test_large_support() {
+        let mut aig = Network::new();
+        aig.add_inputs(8);
+        let v: Vec<Signal> = (0..8).map(|i| aig.input(i)).collect();
+        let x = aig.add(Gate::andn(&v));
+        aig.add_output(x);
+        let insp = Inspector::new(aig);
+        assert_eq!(insp.truth_table(x), None);
+    }
+
+    #[test]
+    fn test_export_marked() {
+        let mut insp = Inspector::new(example());
+        assert!(insp.toggle_mark(Signal::from_var(0)));
+        assert!(insp.toggle_mark(!Signal::from_var(1)));
+        assert!(!insp.toggle_mark(Signal::from_var(0)));
+        assert!(insp.toggle_mark(Signal::from_var(0)));
+        let cone = insp.export_marked_cone();
+        assert_eq!(cone.nb_inputs(), 3);
+        assert_eq!(cone.nb_outputs(), 2);
+        assert_eq!(cone.nb_nodes(), 2);
+        assert!(cone.is_comb());
+    }
+}
@@ -0,0 +1,302 @@
+//! Terminal interface for the network inspector
+
+use std::io;
+use std::path::PathBuf;
+
+use quaigh::io::write_network_file;
+use quaigh::Signal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use super::{Inspector, NodeDetail};
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum Focus {
+    List,
+    Search,
+    Detail,
+}
+
+struct App {
+    inspector: Inspector,
+    export: PathBuf,
+    focus: Focus,
+    query: String,
+    results: Vec<Signal>,
+    list_state: ListState,
+    /// Node shown in the detail pane, with its neighbours (fanins then fanouts)
+    detail: Option<(NodeDetail, Vec<Signal>)>,
+    neighbour_state: ListState,
+    history: Vec<Signal>,
+    status: String,
+}
+
+impl App {
+    fn new(inspector: Inspector, export: PathBuf) -> App {
+        let mut app = App {
+            results: inspector.entries().to_vec(),
+            inspector,
+            export,
+            focus: Focus::List,
+            query: String::new(),
+            list_state: ListState::default(),
+            detail: None,
+            neighbour_state: ListState::default(),
+            history: Vec::new(),
+            status: "/: search  Tab: switch pane  Enter: follow  Backspace: back  m: mark  e: export  q: quit".to_owned(),
+        };
+        app.select_in_list(0);
+        app
+    }
+
+    fn select_in_list(&mut self, i: usize) {
+        if self.results.is_empty() {
+            self.list_state.select(None);
+            self.detail = None;
+            return;
+        }
+        let i = i.min(self.results.len() - 1);
+        self.list_state.select(Some(i));
+        self.show(self.results[i]);
+    }
+
+    fn show(&mut self, s: Signal) {
+        let detail = self.inspector.detail(s);
+        let neighbours = detail
+            .fanins
+            .iter()
+            .chain(detail.fanouts.iter())
+            .filter(|n| !n.is_constant())
+            .map(|n| n.without_inversion())
+            .collect();
+        self.detail = Some((detail, neighbours));
+        self.neighbour_state.select(Some(0));
+    }
+
+    fn current(&self) -> Option<Signal> {
+        self.detail.as_ref().map(|(d, _)| d.signal)
+    }
+
+    fn update_search(&mut self) {
+        self.results = self.inspector.search(&self.query).to_vec();
+        let best = self.inspector.best_match();
+        let pos = best
+            .and_then(|b| self.results.iter().position(|s| *s == b))
+            .unwrap_or(0);
+        self.select_in_list(pos);
+    }
+
+    fn follow(&mut self) {
+        let Some((_, neighbours)) = &self.detail else {
+            return;
+        };
+        let Some(next) = self
+            .neighbour_state
+            .selected()
+            .and_then(|i| neighbours.get(i))
+        else {
+            return;
+        };
+        let next = *next;
+        if let Some(cur) = self.current() {
+            self.history.push(cur);
+        }
+        self.show(next);
+    }
+
+    fn back(&mut self) {
+        if let Some(prev) = self.history.pop() {
+            self.show(prev);
+        }
+    }
+
+    fn export(&mut self) {
+        if self.inspector.marked().is_empty() {
+            self.status = "No marked node to export".to_owned();
+            return;
+        }
+        let cone = self.inspector.export_marked_cone();
+        if let Err(e) = write_network_file(&self.export, &cone) {
+            self.status = format!("Cannot export the marked cone: {}", e);
+            return;
+        }
+        self.status = format!(
+            "Exported the cone of {} marked nodes to {}",
+            self.inspector.marked().len(),
+            self.export.display()
+        );
+    }
+
+    /// Handle a key press; return false to quit
+    fn handle_key(&mut self, key: KeyCode) -> bool {
+        if self.focus == Focus::Search {
+            match key {
+                KeyCode::Char(c) => {
+                    self.query.push(c);
+                    self.update_search();
+                }
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.update_search();
+                }
+                KeyCode::Enter | KeyCode::Esc | KeyCode::Tab => self.focus = Focus::List,
+                _ => (),
+            }
+            return true;
+        }
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('/') => self.focus = Focus::Search,
+            KeyCode::Tab => {
+                self.focus = if self.focus == Focus::List {
+                    Focus::Detail
+                } else {
+                    Focus::List
+                }
+            }
+            KeyCode::Char('m') => {
+                if let Some(s) = self.current() {
+                    let marked = self.inspector.toggle_mark(s);
+                    self.status = format!("{} {s}", if marked { "Marked" } else { "Unmarked" });
+                }
+            }
+            KeyCode::Char('e') => self.export(),
+            KeyCode::Backspace | KeyCode::Left => self.back(),
+            KeyCode::Enter | KeyCode::Right if self.focus == Focus::Detail => self.follow(),
+            KeyCode::Enter | KeyCode::Right => self.focus = Focus::Detail,
+            KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown => {
+                let delta: isize = match key {
+                    KeyCode::Up => -1,
+                    KeyCode::Down => 1,
+                    KeyCode::PageUp => -20,
+                    _ => 20,
+                };
+                if self.focus == Focus::List {
+                    let cur = self.list_state.selected().unwrap_or(0);
+                    self.select_in_list(cur.saturating_add_signed(delta));
+                } else if let Some((_, neighbours)) = &self.detail {
+                    if !neighbours.is_empty() {
+                        let cur = self.neighbour_state.selected().unwrap_or(0);
+                        let next = cur.saturating_add_signed(delta).min(neighbours.len() - 1);
+                        self.neighbour_state.select(Some(next));
+                    }
+                }
+            }
+            _ => (),
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(1),
+                Constraint::Length(1),
+            ])
+            .split(frame.area());
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+            .split(rows[1]);
+        let highlight = Style::default().add_modifier(Modifier::REVERSED);
+        let current_focus = self.focus;
+        let title = move |name: &str, focus: Focus| {
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .title(name.to_owned());
+            if current_focus == focus {
+                block.border_style(Style::default().add_modifier(Modifier::BOLD))
+            } else {
+                block
+            }
+        };
+
+        let search = Paragraph::new(self.query.as_str()).block(title("Search", Focus::Search));
+        frame.render_widget(search, rows[0]);
+
+        let marked = self.inspector.marked();
+        let items: Vec<ListItem> = self
+            .results
+            .iter()
+            .map(|s| {
+                let mark = if marked.contains(s) { "* " } else { "  " };
+                ListItem::new(format!("{mark}{s}"))
+            })
+            .collect();
+        let list_title = format!("Nodes ({})", self.results.len());
+        let list = List::new(items)
+            .block(title(&list_title, Focus::List))
+            .highlight_style(highlight);
+        frame.render_stateful_widget(list, panes[0], &mut self.list_state);
+
+        let detail_rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(10), Constraint::Min(1)])
+            .split(panes[1]);
+        let (summary, neighbours) = match &self.detail {
+            Some((d, n)) => {
+                let mut lines: Vec<Line> = d
+                    .to_string()
+                    .lines()
+                    .map(|l| Line::from(l.to_owned()))
+                    .collect();
+                if marked.contains(&d.signal) {
+                    lines.push(Line::from(Span::styled(
+                        "  Marked for export",
+                        Style::default().add_modifier(Modifier::ITALIC),
+                    )));
+                }
+                let nb_fanins = d.fanins.iter().filter(|s| !s.is_constant()).count();
+                let items: Vec<ListItem> = n
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| {
+                        let kind = if i < nb_fanins { "fanin " } else { "fanout" };
+                        ListItem::new(format!("{kind} {s}"))
+                    })
+                    .collect();
+                (lines, items)
+            }
+            None => (vec![Line::from("No node selected")], Vec::new()),
+        };
+        frame.render_widget(
+            Paragraph::new(summary)
+                .block(title("Detail", Focus::Detail))
+                .wrap(Wrap { trim: false }),
+            detail_rows[0],
+        );
+        let neighbours = List::new(neighbours)
+            .block(title("Neighbours", Focus::Detail))
+            .highlight_style(highlight);
+        frame.render_stateful_widget(neighbours, detail_rows[1], &mut self.neighbour_state);
+
+        frame.render_widget(Paragraph::new(self.status.as_str()), rows[2]);
+    }
+}
+
+fn run_app(terminal: &mut DefaultTerminal, mut app: App) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !app.handle_key(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Run the interactive inspector until the user quits
+///
+/// Marked cones are exported to the given file.
+pub fn run(inspector: Inspector, export: PathBuf) -> io::Result<()> {
+    let mut terminal = ratatui::init();
+    let ret = run_app(&mut terminal, App::new(inspector, export));
+    ratatui::restore();
+    ret
+}
@@ -0,0 +1,41 @@
+//! Binary for Quaigh
+
+#![warn(missing_docs)]
+
+mod cmd;
+mod config;
+mod inspect;
+
+use clap::Parser;
+use quaigh::{equiv, util};
+
+#[doc(hidden)]
+fn main() {
+    let cli = cmd::Cli::parse();
+    util::format::set_raw_numbers(cli.raw_numbers);
+    cli.set_read_options();
+    equiv::set_sat_backend(cli.sat_backend);
+
+    match cli.command {
+        cmd::Commands::CheckEquivalence(a) => a.run(),
+        cmd::Commands::EquivBatch(a) => a.run(),
+        cmd::Commands::Optimize(a) => a.run(),
+        cmd::Commands::Show(a) => a.run(),
+        cmd::Commands::Simulate(a) => a.run(),
+        cmd::Commands::Atpg(a) => a.run(),
+        cmd::Commands::AtpgReport(a) => a.run(),
+        cmd::Commands::Diagnose(a) => a.run(),
+        cmd::Commands::Diff(a) => a.run(),
+        cmd::Commands::Convert(a) => a.run(),
+        cmd::Commands::Map(a) => a.run(),
+        cmd::Commands::Extract(a) => a.run(),
+        cmd::Commands::Selftest(a) => a.run(),
+        cmd::Commands::Inspect(a) => a.run(),
+        cmd::Commands::ReplayWitness(a) => a.run(),
+        cmd::Commands::Query(a) => a.run(),
+        cmd::Commands::Prove(a) => a.run(),
+        cmd::Commands::BenchOpt(a) => a.run(),
+        cmd::Commands::BenchPerf(a) => a.run(),
+        cmd::Commands::Config(a) => a.run(),
+    }
+}
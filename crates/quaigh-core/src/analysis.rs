@@ -0,0 +1,270 @@
+//! Explanations of simulation results, for debugging, and exact analysis of small functions
+
+pub mod activity;
+pub mod bdd;
+pub mod testability;
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::network::{BinaryType, NaryType, TernaryType};
+use crate::sim::simulate_nodes_multi;
+use crate::{Gate, Network, Signal};
+
+/// Why a gate takes its value in a justification
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// One input has the controlling value of the gate, such as a 0 for an And
+    Controlling,
+    /// No input has the controlling value, so all of them are needed
+    NonControlling,
+    /// Parity gate, for which all inputs are needed
+    Parity,
+    /// Mux, determined by its select and the selected input
+    Select,
+    /// Majority gate, determined by two inputs with the same value
+    Majority,
+    /// Buffer or inverter
+    Copy,
+    /// Lookup table, for which all inputs are needed
+    Lut,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Controlling => write!(f, "controlled by"),
+            Reason::NonControlling => write!(f, "no controlling input among"),
+            Reason::Parity => write!(f, "parity of"),
+            Reason::Select => write!(f, "selected by"),
+            Reason::Majority => write!(f, "majority of"),
+            Reason::Copy => write!(f, "copy of"),
+            Reason::Lut => write!(f, "lookup of"),
+        }
+    }
+}
+
+/// A gate of a justification, with the inputs that determine its value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JustificationStep {
+    /// Index of the gate
+    pub gate: usize,
+    /// Value of the gate
+    pub value: bool,
+    /// Why the gate takes this value
+    pub reason: Reason,
+    /// Inputs of the gate that determine its value, with their values
+    pub fanins: Vec<(Signal, bool)>,
+}
+
+/// Explanation of the value of an output for a given input pattern
+///
+/// The primary inputs listed are sufficient to force the value of the output: it keeps the same
+/// value whatever the value of the other inputs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Justification {
+    /// Index of the output
+    pub output: usize,
+    /// Value of the output
+    pub value: bool,
+    /// Gates that force the value of the output, starting from the output
+    pub steps: Vec<JustificationStep>,
+    /// Primary inputs that force the value of the output, sorted by index, with their values
+    pub inputs: Vec<(usize, bool)>,
+}
+
+impl fmt::Display for Justification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Output {} is {}", self.output, self.value as u8)?;
+        for s in &self.steps {
+            let fanins: Vec<String> = s
+                .fanins
+                .iter()
+                .map(|(sig, v)| format!("{}={}", sig, *v as u8))
+                .collect();
+            writeln!(
+                f,
+                "  x{}={}: {} {}",
+                s.gate,
+                s.value as u8,
+                s.reason,
+                fanins.join(", ")
+            )?;
+        }
+        let inputs: Vec<String> = self
+            .inputs
+            .iter()
+            .map(|(i, v)| format!("i{}={}", i, *v as u8))
+            .collect();
+        write!(f, "Determined by inputs {}", inputs.join(", "))
+    }
+}
+
+/// Controlling input value of And-like gates, if any
+fn controlling_value(g: &Gate) -> Option<bool> {
+    match g {
+        Gate::Binary(_, BinaryType::And) | Gate::Ternary(_, TernaryType::And) => Some(false),
+        Gate::Nary(_, NaryType::And) | Gate::Nary(_, NaryType::Nand) => Some(false),
+        Gate::Nary(_, NaryType::Or) | Gate::Nary(_, NaryType::Nor) => Some(true),
+        _ => None,
+    }
+}
+
+/// Explain the value of an output of a combinatorial network for an input pattern
+///
+/// The explanation is obtained by tracing back from the output through the inputs that determine
+/// the value of each gate: a single input with a controlling value for And-like gates, all inputs
+/// for Xor gates, the select and the selected input for Mux gates, and two agreeing inputs for
+/// Maj gates. When several inputs could be chosen, constants and inputs already part of the
+/// explanation are preferred.
+pub fn justify(aig: &Network, pattern: &[bool], output: usize) -> Justification {
+    assert!(aig.is_comb());
+    assert_eq!(pattern.len(), aig.nb_inputs());
+    let words: Vec<u64> = pattern.iter().map(|b| *b as u64).collect();
+    let nodes = simulate_nodes_multi(aig, &words);
+    let value = |s: Signal| -> bool {
+        let v = if s.is_input() {
+            pattern[s.input() as usize]
+        } else if s.is_var() {
+            nodes[s.var() as usize] & 1 != 0
+        } else {
+            false
+        };
+        v ^ s.is_inverted()
+    };
+
+    let mut visited = HashSet::new();
+    let mut inputs = HashSet::new();
+    let mut steps = Vec::new();
+    let mut queue = Vec::new();
+    let mut next = 0;
+    let mut enqueue = |s: Signal, queue: &mut Vec<Signal>, visited: &mut HashSet<usize>| {
+        if s.is_input() {
+            inputs.insert(s.input() as usize);
+        } else if s.is_var() && visited.insert(s.var() as usize) {
+            queue.push(s);
+        }
+    };
+    let out = aig.output(output);
+    enqueue(out, &mut queue, &mut visited);
+    while next < queue.len() {
+        let i = queue[next].var() as usize;
+        next += 1;
+        let g = aig.gate(i);
+        let deps = g.dependencies();
+        let v = nodes[i] & 1 != 0;
+        // Constants and signals already in the explanation come first
+        let is_known =
+            |s: &Signal| s.is_constant() || s.is_input() || visited.contains(&(s.var() as usize));
+        let (reason, fanins): (Reason, Vec<Signal>) = match &g {
+            Gate::Buf(s) => (Reason::Copy, vec![*s]),
+            Gate::Ternary([s, a, b], TernaryType::Mux) => {
+                let selected = if value(*s) { *a } else { *b };
+                (Reason::Select, vec![*s, selected])
+            }
+            Gate::Ternary(_, TernaryType::Maj) => {
+                let mut agreeing: Vec<Signal> =
+                    deps.iter().copied().filter(|s| value(*s) == v).collect();
+                agreeing.sort_by_key(|s| !is_known(s));
+                agreeing.truncate(2);
+                (Reason::Majority, agreeing)
+            }
+            Gate::Lut(_) => (Reason::Lut, deps.to_vec()),
+            _ => match controlling_value(&g) {
+                Some(c) => {
+                    let mut controlling: Vec<Signal> =
+                        deps.iter().copied().filter(|s| value(*s) == c).collect();
+                    if controlling.is_empty() {
+                        (Reason::NonControlling, deps.to_vec())
+                    } else {
+                        controlling.sort_by_key(|s| !is_known(s));
+                        controlling.truncate(1);
+                        (Reason::Controlling, controlling)
+                    }
+                }
+                None => (Reason::Parity, deps.to_vec()),
+            },
+        };
+        let fanins: Vec<(Signal, bool)> = fanins
+            .into_iter()
+            .filter(|s| !s.is_constant())
+            .map(|s| (s, value(s)))
+            .collect();
+        for (s, _) in &fanins {
+            enqueue(*s, &mut queue, &mut visited);
+        }
+        steps.push(JustificationStep {
+            gate: i,
+            value: v,
+            reason,
+            fanins,
+        });
+    }
+
+    let mut inputs: Vec<(usize, bool)> = inputs.into_iter().map(|i| (i, pattern[i])).collect();
+    inputs.sort();
+    Justification {
+        output,
+        value: value(out),
+        steps,
+        inputs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::{justify, Reason};
+    use crate::network::generators::adder;
+    use crate::sim::simulate_comb;
+
+    #[test]
+    fn test_carry_chain() {
+        // Inputs are interleaved a0, b0, a1, b1, ...
+        let aig = adder::ripple_carry(4);
+        let (a, b) = (0b0110, 0b1010);
+        let pattern: Vec<bool> = (0..4)
+            .flat_map(|i| [(a >> i) & 1 != 0, (b >> i) & 1 != 0])
+            .collect();
+        let cout = 4;
+        let j = justify(&aig, &pattern, cout);
+        assert!(j.value);
+
+        // Generated at bit 1, propagated by a2 and b3
+        assert_eq!(j.inputs, vec![(2, true), (3, true), (4, true), (7, true)]);
+        assert_eq!(j.steps.len(), 3);
+        assert!(j.steps.iter().all(|s| s.reason == Reason::Majority));
+        assert!(j.to_string().starts_with("Output 4 is 1\n"));
+
+        // The other inputs do not matter
+        let mut rng = SmallRng::seed_from_u64(1);
+        for _ in 0..100 {
+            let mut p: Vec<bool> = (0..8).map(|_| rng.gen()).collect();
+            for (i, v) in &j.inputs {
+                p[*i] = *v;
+            }
+            assert!(simulate_comb(&aig, &p)[cout]);
+        }
+    }
+
+    #[test]
+    fn test_all_outputs() {
+        let aig = adder::ripple_carry(4);
+        let mut rng = SmallRng::seed_from_u64(2);
+        for _ in 0..20 {
+            let pattern: Vec<bool> = (0..8).map(|_| rng.gen()).collect();
+            let expected = simulate_comb(&aig, &pattern);
+            for (o, value) in expected.iter().enumerate() {
+                let j = justify(&aig, &pattern, o);
+                assert_eq!(j.value, *value);
+                let mut p: Vec<bool> = (0..8).map(|_| rng.gen()).collect();
+                for (i, v) in &j.inputs {
+                    p[*i] = *v;
+                }
+                assert_eq!(simulate_comb(&aig, &p)[o], *value);
+            }
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! Switching activity estimation, for power analysis
+//!
+//! The network is simulated on a set of patterns, and the value of each node is recorded at each
+//! cycle. The static probability of a node is the fraction of cycles where it is 1, and its toggle
+//! rate the fraction of cycles where its value differs from the previous cycle. The dynamic power
+//! of a design is roughly proportional to the toggle rates weighted by the load of each node,
+//! estimated here by its number of fanouts.
+//!
+//! ```
+//! # use quaigh_core::network::generators::adder;
+//! use quaigh_core::analysis::activity::Activity;
+//!
+//! let aig = adder::ripple_carry(4);
+//! let patterns = vec![vec![vec![false; 8], vec![true; 8], vec![false; 8]]];
+//! let activity = Activity::compute(&aig, &patterns);
+//! println!("Toggles per cycle: {:.2}", activity.switching_activity());
+//! ```
+
+use std::fmt;
+
+use crate::network::FanoutView;
+use crate::sim::simple_sim::SimpleSimulator;
+use crate::util::format::count;
+use crate::Network;
+
+/// Static probabilities and toggle rates of the nodes of a network
+#[derive(Clone, Debug)]
+pub struct Activity {
+    /// Number of cycles where each node is 1
+    nb_ones: Vec<u64>,
+    /// Number of cycles where each node changes value
+    nb_toggles: Vec<u64>,
+    /// Number of fanouts of each node
+    nb_fanouts: Vec<usize>,
+    /// Number of simulated cycles
+    nb_cycles: u64,
+    /// Number of pairs of consecutive cycles, where a toggle may happen
+    nb_transitions: u64,
+}
+
+impl Activity {
+    /// Simulate a topologically sorted network on the patterns, each a sequence of input values,
+    /// and record the activity of its nodes
+    ///
+    /// Patterns start with all flip-flops at 0 and are simulated 64 at a time. For a combinatorial
+    /// network, patterns are applied one after the other, and toggles between the last cycle of a
+    /// pattern and the first cycle of the next are counted too.
+    pub fn compute(aig: &Network, patterns: &[Vec<Vec<bool>>]) -> Activity {
+        assert!(aig.is_topo_sorted());
+        let mut ret = Activity::new(aig);
+        if aig.is_comb() {
+            ret.record_comb(aig, patterns);
+        } else {
+            ret.record_seq(aig, patterns);
+        }
+        ret
+    }
+
+    /// Activity without any simulated cycle
+    fn new(aig: &Network) -> Activity {
+        let fanouts = FanoutView::new(aig);
+        Activity {
+            nb_ones: vec![0; aig.nb_nodes()],
+            nb_toggles: vec![0; aig.nb_nodes()],
+            nb_fanouts: (0..aig.nb_nodes())
+                .map(|i| fanouts.fanouts(aig.node(i)).len())
+                .collect(),
+            nb_cycles: 0,
+            nb_transitions: 0,
+        }
+    }
+
+    /// Record the activity of a combinatorial network, with 64 consecutive cycles per lane group
+    fn record_comb(&mut self, aig: &Network, patterns: &[Vec<Vec<bool>>]) {
+        let cycles: Vec<&Vec<bool>> = patterns.iter().flatten().collect();
+        let mut sim = SimpleSimulator::from_aig(aig);
+        let mut last: Option<Vec<u64>> = None;
+        for group in cycles.chunks(64) {
+            let mut inputs = vec![0u64; aig.nb_inputs()];
+            for (lane, v) in group.iter().enumerate() {
+                assert_eq!(v.len(), aig.nb_inputs());
+                for (i, b) in v.iter().enumerate() {
+                    inputs[i] |= (*b as u64) << lane;
+                }
+            }
+            let mask = if group.len() == 64 {
+                !0
+            } else {
+                (1u64 << group.len()) - 1
+            };
+            sim.copy_inputs(&inputs);
+            sim.run_comb();
+            self.nb_cycles += group.len() as u64;
+            // Each lane is compared to the previous one, and the first to the previous group
+            let toggle_mask = if last.is_some() { mask } else { mask & !1 };
+            self.nb_transitions += toggle_mask.count_ones() as u64;
+            for (i, v) in sim.node_values.iter().enumerate() {
+                let carry = last.as_ref().map_or(0, |l| l[i] >> 63);
+                let shifted = (v << 1) | carry;
+                self.nb_ones[i] += (v & mask).count_ones() as u64;
+                self.nb_toggles[i] += ((v ^ shifted) & toggle_mask).count_ones() as u64;
+            }
+            last = Some(sim.node_values.clone());
+        }
+    }
+
+    /// Record the activity of a sequential network, with one pattern per lane
+    fn record_seq(&mut self, aig: &Network, patterns: &[Vec<Vec<bool>>]) {
+        let mut sim = SimpleSimulator::from_aig(aig);
+        for group in patterns.chunks(64) {
+            sim.reset();
+            let nb_steps = group.iter().map(|p| p.len()).max().unwrap_or(0);
+            let mut prev: Vec<u64> = Vec::new();
+            for t in 0..nb_steps {
+                // Lanes whose pattern is still running at this cycle
+                let mut mask = 0u64;
+                let mut inputs = vec![0u64; aig.nb_inputs()];
+                for (lane, p) in group.iter().enumerate() {
+                    let Some(v) = p.get(t) else {
+                        continue;
+                    };
+                    assert_eq!(v.len(), aig.nb_inputs());
+                    mask |= 1 << lane;
+                    for (i, b) in v.iter().enumerate() {
+                        inputs[i] |= (*b as u64) << lane;
+                    }
+                }
+                if t != 0 {
+                    sim.run_dff();
+                }
+                sim.copy_inputs(&inputs);
+                sim.run_comb();
+                self.nb_cycles += mask.count_ones() as u64;
+                if t != 0 {
+                    self.nb_transitions += mask.count_ones() as u64;
+                }
+                for (i, v) in sim.node_values.iter().enumerate() {
+                    self.nb_ones[i] += (v & mask).count_ones() as u64;
+                    if t != 0 {
+                        self.nb_toggles[i] += ((v ^ prev[i]) & mask).count_ones() as u64;
+                    }
+                }
+                prev.clone_from(&sim.node_values);
+            }
+        }
+    }
+
+    /// Number of nodes
+    pub fn nb_nodes(&self) -> usize {
+        self.nb_ones.len()
+    }
+
+    /// Number of simulated cycles, over all patterns
+    pub fn nb_cycles(&self) -> usize {
+        self.nb_cycles as usize
+    }
+
+    /// Fraction of the cycles where a node is 1
+    pub fn static_probability(&self, i: usize) -> f64 {
+        if self.nb_cycles == 0 {
+            0.0
+        } else {
+            self.nb_ones[i] as f64 / self.nb_cycles as f64
+        }
+    }
+
+    /// Fraction of the cycles where a node changes value compared to the previous cycle
+    pub fn toggle_rate(&self, i: usize) -> f64 {
+        if self.nb_transitions == 0 {
+            0.0
+        } else {
+            self.nb_toggles[i] as f64 / self.nb_transitions as f64
+        }
+    }
+
+    /// Estimated switching activity: average number of node toggles per cycle
+    pub fn switching_activity(&self) -> f64 {
+        (0..self.nb_nodes()).map(|i| self.toggle_rate(i)).sum()
+    }
+
+    /// Estimated switching activity weighted by the load of each node, using its number of fanouts
+    pub fn weighted_switching_activity(&self) -> f64 {
+        (0..self.nb_nodes())
+            .map(|i| self.toggle_rate(i) * self.nb_fanouts[i].max(1) as f64)
+            .sum()
+    }
+
+    /// Nodes by decreasing toggle rate, with the most active first
+    pub fn most_active(&self) -> Vec<usize> {
+        let mut ret: Vec<usize> = (0..self.nb_nodes()).collect();
+        ret.sort_by(|a, b| self.nb_toggles[*b].cmp(&self.nb_toggles[*a]));
+        ret
+    }
+}
+
+impl fmt::Display for Activity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let average = |v: f64| {
+            if self.nb_nodes() == 0 {
+                0.0
+            } else {
+                v / self.nb_nodes() as f64
+            }
+        };
+        let probability: f64 = (0..self.nb_nodes())
+            .map(|i| self.static_probability(i))
+            .sum();
+        let nb_constant = (0..self.nb_nodes())
+            .filter(|i| self.nb_toggles[*i] == 0)
+            .count();
+        writeln!(f, "Switching activity:")?;
+        writeln!(f, "  Cycles: {}", count(self.nb_cycles()))?;
+        writeln!(
+            f,
+            "  Static probability: average {:.3}",
+            average(probability)
+        )?;
+        writeln!(
+            f,
+            "  Toggle rate: average {:.3}",
+            average(self.switching_activity())
+        )?;
+        writeln!(
+            f,
+            "  Toggles per cycle: {:.2}, weighted by fanout {:.2}",
+            self.switching_activity(),
+            self.weighted_switching_activity()
+        )?;
+        if nb_constant != 0 {
+            writeln!(f, "  Nodes that never toggle: {}", count(nb_constant))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Activity;
+    use crate::{Network, Signal};
+
+    #[test]
+    fn test_activity() {
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let x = aig.and(a, b);
+        let y = aig.xor(a, b);
+        let d = aig.dff(a, Signal::one(), Signal::zero());
+        aig.add_output(x);
+        aig.add_output(y);
+        aig.add_output(d);
+
+        let p = |a: bool, b: bool| vec![a, b];
+        let patterns = vec![
+            vec![
+                p(false, false),
+                p(true, false),
+                p(true, true),
+                p(false, true),
+            ],
+            vec![p(true, true), p(true, true)],
+        ];
+        let act = Activity::compute(&aig, &patterns);
+        assert_eq!(act.nb_cycles(), 6);
+        // x is 1 in 3 cycles, and toggles twice in the first pattern
+        assert_eq!(act.static_probability(0), 0.5);
+        assert_eq!(act.toggle_rate(0), 0.5);
+        // y is 1 in 2 cycles, and toggles 3 times in the first pattern
+        assert_eq!(act.static_probability(1), 2.0 / 6.0);
+        assert_eq!(act.toggle_rate(1), 0.75);
+        // d is 0, 0, 1, 1 then 0, 1
+        assert_eq!(act.static_probability(2), 0.5);
+        assert_eq!(act.toggle_rate(2), 0.5);
+        assert_eq!(act.most_active(), vec![1, 0, 2]);
+        assert_eq!(act.switching_activity(), 1.75);
+    }
+
+    #[test]
+    fn test_comb() {
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let x = aig.and(a, !b);
+        aig.add_output(x);
+        // More than 64 cycles, in patterns of varying length, where a alternates at each cycle
+        let mut t = 0;
+        let patterns: Vec<Vec<Vec<bool>>> = (0..102)
+            .map(|i| {
+                (0..(i % 3 + 1))
+                    .map(|_| {
+                        t += 1;
+                        vec![t % 2 == 1, false]
+                    })
+                    .collect()
+            })
+            .collect();
+        let act = Activity::compute(&aig, &patterns);
+        assert_eq!(act.nb_cycles(), t);
+        assert_eq!(act.toggle_rate(0), 1.0);
+        assert_eq!(act.static_probability(0), 0.5);
+        assert!(act.to_string().contains("Toggles per cycle: 1.00"));
+    }
+}
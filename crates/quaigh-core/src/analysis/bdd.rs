@@ -0,0 +1,586 @@
+//! Binary decision diagrams for the exact analysis of small functions
+//!
+//! Reduced ordered BDDs are canonical: two functions are equal if and only if they are the same
+//! [`Bdd`]. This makes exact don't care computation, symmetry detection and equivalence
+//! detection straightforward, as long as the functions stay small. BDDs are built for a whole
+//! network with [`NetworkBdds::build`], which gives up if the number of BDD nodes exceeds a limit.
+//!
+//! ```
+//! # use quaigh_core::{Gate, Network};
+//! use quaigh_core::analysis::bdd::NetworkBdds;
+//!
+//! let mut aig = Network::new();
+//! aig.add_inputs(3);
+//! let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+//! let x = aig.add(Gate::maj(a, b, c));
+//! aig.add_output(x);
+//!
+//! let mut bdds = NetworkBdds::build(&aig, 1000).unwrap();
+//! let f = bdds.signal(x);
+//! assert_eq!(bdds.manager.symmetric_pairs(f), vec![(0, 1), (0, 2), (1, 2)]);
+//! ```
+
+use std::collections::HashMap;
+
+use volute::Lut;
+
+use crate::network::{BinaryType, NaryType, TernaryType};
+use crate::{Gate, Network, Signal};
+
+/// A function represented as a node of a [`BddManager`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Bdd(u32);
+
+impl Bdd {
+    /// Constant zero function
+    pub fn zero() -> Bdd {
+        Bdd(0)
+    }
+
+    /// Constant one function
+    pub fn one() -> Bdd {
+        Bdd(1)
+    }
+
+    /// Return whether the function is constant
+    pub fn is_constant(&self) -> bool {
+        self.0 < 2
+    }
+}
+
+/// Internal node of a BDD: if var then hi else lo
+#[derive(Clone, Copy, Debug)]
+struct BddNode {
+    var: u32,
+    lo: Bdd,
+    hi: Bdd,
+}
+
+/// Storage for BDDs sharing the same variable order, where variable 0 is at the top
+#[derive(Clone, Debug)]
+pub struct BddManager {
+    nodes: Vec<BddNode>,
+    unique: HashMap<(u32, Bdd, Bdd), Bdd>,
+    ite_cache: HashMap<(Bdd, Bdd, Bdd), Bdd>,
+}
+
+impl Default for BddManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BddManager {
+    /// Create a manager with only the constant functions
+    pub fn new() -> BddManager {
+        let terminal = |v| BddNode {
+            var: u32::MAX,
+            lo: Bdd(v),
+            hi: Bdd(v),
+        };
+        BddManager {
+            nodes: vec![terminal(0), terminal(1)],
+            unique: HashMap::new(),
+            ite_cache: HashMap::new(),
+        }
+    }
+
+    /// Number of nodes created so far, including the terminals
+    pub fn nb_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Number of nodes of a function, excluding the terminals
+    pub fn size(&self, f: Bdd) -> usize {
+        let mut visited = vec![f];
+        let mut i = 0;
+        while i < visited.len() {
+            let n = self.nodes[visited[i].0 as usize];
+            for c in [n.lo, n.hi] {
+                if !c.is_constant() && !visited.contains(&c) {
+                    visited.push(c);
+                }
+            }
+            i += 1;
+        }
+        visited.iter().filter(|b| !b.is_constant()).count()
+    }
+
+    /// Function of a single variable
+    pub fn var(&mut self, i: usize) -> Bdd {
+        self.mk(i as u32, Bdd::zero(), Bdd::one())
+    }
+
+    /// Find or create a node
+    fn mk(&mut self, var: u32, lo: Bdd, hi: Bdd) -> Bdd {
+        if lo == hi {
+            return lo;
+        }
+        if let Some(b) = self.unique.get(&(var, lo, hi)) {
+            return *b;
+        }
+        let b = Bdd(self.nodes.len() as u32);
+        self.nodes.push(BddNode { var, lo, hi });
+        self.unique.insert((var, lo, hi), b);
+        b
+    }
+
+    fn top_var(&self, f: Bdd) -> u32 {
+        self.nodes[f.0 as usize].var
+    }
+
+    /// Cofactors of a function with respect to a variable at or above its top variable
+    fn cofactors(&self, f: Bdd, var: u32) -> (Bdd, Bdd) {
+        let n = self.nodes[f.0 as usize];
+        if n.var == var {
+            (n.lo, n.hi)
+        } else {
+            (f, f)
+        }
+    }
+
+    /// If-then-else: f ? g : h
+    pub fn ite(&mut self, f: Bdd, g: Bdd, h: Bdd) -> Bdd {
+        if f == Bdd::one() || g == h {
+            return g;
+        }
+        if f == Bdd::zero() {
+            return h;
+        }
+        if g == Bdd::one() && h == Bdd::zero() {
+            return f;
+        }
+        if let Some(b) = self.ite_cache.get(&(f, g, h)) {
+            return *b;
+        }
+        let var = self.top_var(f).min(self.top_var(g)).min(self.top_var(h));
+        let (f0, f1) = self.cofactors(f, var);
+        let (g0, g1) = self.cofactors(g, var);
+        let (h0, h1) = self.cofactors(h, var);
+        let lo = self.ite(f0, g0, h0);
+        let hi = self.ite(f1, g1, h1);
+        let ret = self.mk(var, lo, hi);
+        self.ite_cache.insert((f, g, h), ret);
+        ret
+    }
+
+    /// Complement of a function
+    pub fn not(&mut self, f: Bdd) -> Bdd {
+        self.ite(f, Bdd::zero(), Bdd::one())
+    }
+
+    /// Conjunction of two functions
+    pub fn and(&mut self, f: Bdd, g: Bdd) -> Bdd {
+        self.ite(f, g, Bdd::zero())
+    }
+
+    /// Disjunction of two functions
+    pub fn or(&mut self, f: Bdd, g: Bdd) -> Bdd {
+        self.ite(f, Bdd::one(), g)
+    }
+
+    /// Exclusive or of two functions
+    pub fn xor(&mut self, f: Bdd, g: Bdd) -> Bdd {
+        let ng = self.not(g);
+        self.ite(f, ng, g)
+    }
+
+    /// Function with a variable set to a constant value
+    pub fn restrict(&mut self, f: Bdd, var: usize, value: bool) -> Bdd {
+        let mut cache = HashMap::new();
+        self.restrict_rec(f, var as u32, value, &mut cache)
+    }
+
+    fn restrict_rec(
+        &mut self,
+        f: Bdd,
+        var: u32,
+        value: bool,
+        cache: &mut HashMap<Bdd, Bdd>,
+    ) -> Bdd {
+        let n = self.nodes[f.0 as usize];
+        if f.is_constant() || n.var > var {
+            return f;
+        }
+        if n.var == var {
+            return if value { n.hi } else { n.lo };
+        }
+        if let Some(b) = cache.get(&f) {
+            return *b;
+        }
+        let lo = self.restrict_rec(n.lo, var, value, cache);
+        let hi = self.restrict_rec(n.hi, var, value, cache);
+        let ret = self.mk(n.var, lo, hi);
+        cache.insert(f, ret);
+        ret
+    }
+
+    /// Existential quantification of a variable
+    pub fn exists(&mut self, f: Bdd, var: usize) -> Bdd {
+        let f0 = self.restrict(f, var, false);
+        let f1 = self.restrict(f, var, true);
+        self.or(f0, f1)
+    }
+
+    /// Value of a function for an assignment of the variables
+    pub fn eval(&self, f: Bdd, values: &[bool]) -> bool {
+        let mut b = f;
+        while !b.is_constant() {
+            let n = self.nodes[b.0 as usize];
+            b = if values[n.var as usize] { n.hi } else { n.lo };
+        }
+        b == Bdd::one()
+    }
+
+    /// Variables that the function depends on, sorted
+    pub fn support(&self, f: Bdd) -> Vec<usize> {
+        let mut visited = vec![f];
+        let mut vars = Vec::new();
+        while let Some(b) = visited.pop() {
+            if b.is_constant() {
+                continue;
+            }
+            let n = self.nodes[b.0 as usize];
+            vars.push(n.var as usize);
+            visited.push(n.lo);
+            visited.push(n.hi);
+        }
+        vars.sort();
+        vars.dedup();
+        vars
+    }
+
+    /// Return whether the function is unchanged when two variables are swapped
+    pub fn is_symmetric(&mut self, f: Bdd, i: usize, j: usize) -> bool {
+        if i == j {
+            return true;
+        }
+        let f0 = self.restrict(f, i, false);
+        let f01 = self.restrict(f0, j, true);
+        let f1 = self.restrict(f, i, true);
+        let f10 = self.restrict(f1, j, false);
+        f01 == f10
+    }
+
+    /// Pairs of variables of the support that can be swapped without changing the function
+    pub fn symmetric_pairs(&mut self, f: Bdd) -> Vec<(usize, usize)> {
+        let support = self.support(f);
+        let mut ret = Vec::new();
+        for (k, i) in support.iter().enumerate() {
+            for j in &support[k + 1..] {
+                if self.is_symmetric(f, *i, *j) {
+                    ret.push((*i, *j));
+                }
+            }
+        }
+        ret
+    }
+
+    /// Function of a Lut, given the functions of its inputs
+    fn lut(&mut self, lut: &Lut, inputs: &[Bdd]) -> Bdd {
+        let mut ret = Bdd::zero();
+        for m in 0..1usize << inputs.len() {
+            if !lut.value(m) {
+                continue;
+            }
+            let mut term = Bdd::one();
+            for (i, x) in inputs.iter().enumerate() {
+                let l = if (m >> i) & 1 != 0 { *x } else { self.not(*x) };
+                term = self.and(term, l);
+            }
+            ret = self.or(ret, term);
+        }
+        ret
+    }
+}
+
+/// BDDs of all the nodes of a network
+///
+/// Primary input `i` is variable `i`, and flip-flop `j` is a free variable `nb_inputs + j`.
+#[derive(Clone, Debug)]
+pub struct NetworkBdds {
+    /// Manager holding the BDDs
+    pub manager: BddManager,
+    nb_inputs: usize,
+    nodes: Vec<Bdd>,
+}
+
+impl NetworkBdds {
+    /// Build the BDDs of all the nodes of a network
+    ///
+    /// Returns None if more than `max_nodes` BDD nodes are required.
+    pub fn build(aig: &Network, max_nodes: usize) -> Option<NetworkBdds> {
+        assert!(aig.is_topo_sorted());
+        let mut ret = NetworkBdds {
+            manager: BddManager::new(),
+            nb_inputs: aig.nb_inputs(),
+            nodes: Vec::new(),
+        };
+        for i in 0..aig.nb_nodes() {
+            let b = if aig.gate_ref(i).is_comb() {
+                ret.gate(&aig.gate(i))
+            } else {
+                ret.manager.var(aig.nb_inputs() + i)
+            };
+            ret.nodes.push(b);
+            if ret.manager.nb_nodes() > max_nodes {
+                return None;
+            }
+        }
+        Some(ret)
+    }
+
+    /// BDD of a node
+    pub fn node(&self, i: usize) -> Bdd {
+        self.nodes[i]
+    }
+
+    /// BDD of a signal
+    pub fn signal(&mut self, s: Signal) -> Bdd {
+        let b = if s.is_constant() {
+            Bdd::zero()
+        } else if s.is_input() {
+            self.manager.var(s.input() as usize)
+        } else {
+            self.nodes[s.var() as usize]
+        };
+        if s.is_inverted() {
+            self.manager.not(b)
+        } else {
+            b
+        }
+    }
+
+    /// BDD of a gate, from the BDDs of its dependencies
+    fn gate(&mut self, g: &Gate) -> Bdd {
+        use Gate::*;
+        let deps: Vec<Bdd> = g.dependencies().iter().map(|s| self.signal(*s)).collect();
+        let m = &mut self.manager;
+        match g {
+            Binary(_, BinaryType::And) => m.and(deps[0], deps[1]),
+            Binary(_, BinaryType::Xor) => m.xor(deps[0], deps[1]),
+            Ternary(_, TernaryType::And) => {
+                let x = m.and(deps[0], deps[1]);
+                m.and(x, deps[2])
+            }
+            Ternary(_, TernaryType::Xor) => {
+                let x = m.xor(deps[0], deps[1]);
+                m.xor(x, deps[2])
+            }
+            Ternary(_, TernaryType::Mux) => m.ite(deps[0], deps[1], deps[2]),
+            Ternary(_, TernaryType::Maj) => {
+                let x = m.or(deps[1], deps[2]);
+                let y = m.and(deps[1], deps[2]);
+                m.ite(deps[0], x, y)
+            }
+            Nary(_, tp) => {
+                let inv_in = matches!(tp, NaryType::Or | NaryType::Nor);
+                let inv_out = matches!(tp, NaryType::Or | NaryType::Nand | NaryType::Xnor);
+                let is_xor = matches!(tp, NaryType::Xor | NaryType::Xnor);
+                let mut ret = if is_xor { Bdd::zero() } else { Bdd::one() };
+                for d in deps {
+                    ret = if is_xor {
+                        m.xor(ret, d)
+                    } else if inv_in {
+                        let nd = m.not(d);
+                        m.and(ret, nd)
+                    } else {
+                        m.and(ret, d)
+                    };
+                }
+                if inv_out {
+                    m.not(ret)
+                } else {
+                    ret
+                }
+            }
+            Buf(_) => deps[0],
+            Lut(lut) => m.lut(&lut.lut, &deps),
+            Dff(..) | Latch(..) => unreachable!(),
+        }
+    }
+
+    /// Find the nodes whose function is identical to a constant, an input or an earlier node
+    ///
+    /// Returns each redundant node with the signal that can replace it. Buffers and flip-flops
+    /// are never replaced.
+    pub fn equivalences(&mut self, aig: &Network) -> Vec<(usize, Signal)> {
+        let mut known: HashMap<Bdd, Signal> = HashMap::new();
+        known.insert(Bdd::zero(), Signal::zero());
+        known.insert(Bdd::one(), Signal::one());
+        for i in 0..aig.nb_inputs() {
+            let b = self.manager.var(i);
+            known.insert(b, aig.input(i));
+        }
+        let mut ret = Vec::new();
+        for i in 0..aig.nb_nodes() {
+            let g = aig.gate(i);
+            if !g.is_comb() {
+                known.insert(self.nodes[i], aig.node(i));
+                continue;
+            }
+            if matches!(g, Gate::Buf(_)) {
+                continue;
+            }
+            let b = self.nodes[i];
+            let nb = self.manager.not(b);
+            if let Some(s) = known.get(&b) {
+                ret.push((i, *s));
+            } else if let Some(s) = known.get(&nb) {
+                ret.push((i, !*s));
+            } else {
+                known.insert(b, aig.node(i));
+            }
+        }
+        ret
+    }
+
+    /// Compute the exact observability don't cares of a node
+    ///
+    /// The result is the set of assignments of the inputs and flip-flops for which no output
+    /// depends on the value of the node.
+    pub fn dont_cares(&mut self, aig: &Network, node: usize) -> Bdd {
+        assert!(aig.gate_ref(node).is_comb());
+        // Replace the node by a fresh variable, below all others, and recompute its fanout
+        let fresh = self.nb_inputs + aig.nb_nodes();
+        let mut modified = NetworkBdds {
+            manager: std::mem::take(&mut self.manager),
+            nb_inputs: self.nb_inputs,
+            nodes: self.nodes.clone(),
+        };
+        modified.nodes[node] = modified.manager.var(fresh);
+        for i in node + 1..aig.nb_nodes() {
+            if aig.gate_ref(i).is_comb() {
+                modified.nodes[i] = modified.gate(&aig.gate(i));
+            }
+        }
+        let mut care = Bdd::zero();
+        for o in 0..aig.nb_outputs() {
+            let f = modified.signal(aig.output(o));
+            let m = &mut modified.manager;
+            let f0 = m.restrict(f, fresh, false);
+            let f1 = m.restrict(f, fresh, true);
+            let diff = m.xor(f0, f1);
+            care = m.or(care, diff);
+        }
+        self.manager = modified.manager;
+        self.manager.not(care)
+    }
+}
+
+/// Merge the nodes that are functionally equivalent to a constant, an input or another node
+///
+/// Equivalences are found exactly with BDDs. Returns the number of nodes merged, or None if the
+/// network is too large for the BDDs to be built with at most `max_nodes` nodes.
+pub fn remove_redundancies(aig: &mut Network, max_nodes: usize) -> Option<usize> {
+    let mut bdds = NetworkBdds::build(aig, max_nodes)?;
+    let equivalences = bdds.equivalences(aig);
+    for (i, s) in &equivalences {
+        aig.replace(*i, Gate::Buf(*s));
+    }
+    aig.make_canonical();
+    aig.cleanup();
+    Some(equivalences.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{remove_redundancies, Bdd, BddManager, NetworkBdds};
+    use crate::network::generators::adder;
+    use crate::sim::simulate_comb;
+    use crate::{Gate, Network};
+
+    #[test]
+    fn test_canonical() {
+        let mut m = BddManager::new();
+        let (a, b, c) = (m.var(0), m.var(1), m.var(2));
+        // De Morgan
+        let x = m.and(a, b);
+        let nx = m.not(x);
+        let (na, nb) = (m.not(a), m.not(b));
+        assert_eq!(nx, m.or(na, nb));
+        // Xor expressed with And/Or
+        let t1 = m.and(a, nb);
+        let t2 = m.and(na, b);
+        assert_eq!(m.or(t1, t2), m.xor(a, b));
+        assert_eq!(m.xor(a, a), Bdd::zero());
+        // Quantification and cofactors
+        let y = m.and(x, c);
+        assert_eq!(m.exists(y, 2), x);
+        assert_eq!(m.restrict(y, 0, false), Bdd::zero());
+        assert_eq!(m.support(y), vec![0, 1, 2]);
+        assert_eq!(m.size(y), 3);
+        assert!(m.eval(y, &[true, true, true]));
+        assert!(!m.eval(y, &[true, false, true]));
+    }
+
+    #[test]
+    fn test_symmetries() {
+        let mut m = BddManager::new();
+        let (a, b, c) = (m.var(0), m.var(1), m.var(2));
+        let nb = m.not(b);
+        let x = m.and(a, nb);
+        let f = m.or(x, c);
+        assert!(!m.is_symmetric(f, 0, 1));
+        assert!(!m.is_symmetric(f, 1, 2));
+        assert!(m.is_symmetric(f, 0, 0));
+        assert!(m.symmetric_pairs(f).is_empty());
+        let g = m.xor(a, b);
+        assert_eq!(m.symmetric_pairs(g), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn test_adder() {
+        let aig = adder::ripple_carry(3);
+        let mut bdds = NetworkBdds::build(&aig, 10000).unwrap();
+        for k in 0..64u32 {
+            let v: Vec<bool> = (0..6).map(|j| (k >> j) & 1 != 0).collect();
+            let expected = simulate_comb(&aig, &v);
+            for (o, e) in expected.iter().enumerate() {
+                let f = bdds.signal(aig.output(o));
+                assert_eq!(bdds.manager.eval(f, &v), *e);
+            }
+        }
+        assert!(NetworkBdds::build(&aig, 4).is_none());
+    }
+
+    #[test]
+    fn test_dont_cares() {
+        let mut aig = Network::new();
+        aig.add_inputs(2);
+        let (a, b) = (aig.input(0), aig.input(1));
+        let x = aig.add(Gate::xor(a, b));
+        let y = aig.add(Gate::and(x, a));
+        aig.add_output(y);
+        let mut bdds = NetworkBdds::build(&aig, 1000).unwrap();
+        // x is only observable when a is 1
+        let dc = bdds.dont_cares(&aig, x.var() as usize);
+        let na = bdds.signal(!a);
+        assert_eq!(dc, na);
+        // y is always observable
+        assert_eq!(bdds.dont_cares(&aig, y.var() as usize), Bdd::zero());
+    }
+
+    #[test]
+    fn test_remove_redundancies() {
+        // An Xor built with And gates, next to an Xor gate and a constant
+        let mut aig = Network::new();
+        aig.add_inputs(2);
+        let (a, b) = (aig.input(0), aig.input(1));
+        let x = aig.add(Gate::xor(a, b));
+        let t1 = aig.add(Gate::and(a, !b));
+        let t2 = aig.add(Gate::and(!a, b));
+        let y = aig.add(Gate::and(!t1, !t2));
+        let z = aig.add(Gate::and(x, y));
+        aig.add_output(x);
+        aig.add_output(y);
+        aig.add_output(z);
+        let orig = aig.clone();
+        assert_eq!(remove_redundancies(&mut aig, 1000), Some(2));
+        assert_eq!(aig.nb_nodes(), 1);
+        for k in 0..4u32 {
+            let v: Vec<bool> = (0..2).map(|j| (k >> j) & 1 != 0).collect();
+            assert_eq!(simulate_comb(&aig, &v), simulate_comb(&orig, &v));
+        }
+    }
+}
@@ -0,0 +1,474 @@
+//! SCOAP testability measures
+//!
+//! Controllability is the cost of setting a signal to 0 or to 1 from the primary inputs, and
+//! observability the cost of propagating its value to a primary output. Both are computed
+//! structurally: each gate costs 1, and each input or flip-flop costs 1 to control. Flip-flops
+//! are assumed to be scanned: their outputs are controllable and their inputs observable at no
+//! additional cost.
+//!
+//! ```
+//! # use quaigh_core::network::generators::adder;
+//! use quaigh_core::analysis::testability::Testability;
+//!
+//! let aig = adder::ripple_carry(8);
+//! let t = Testability::compute(&aig);
+//! let carry = aig.output(aig.nb_outputs() - 1);
+//! println!("Cost to set the carry to 1: {}", t.controllability(carry, true));
+//! ```
+
+use std::fmt;
+
+use crate::network::{BinaryType, GateRef, NaryType, TernaryType};
+use crate::sim::Fault;
+use crate::util::format::count;
+use crate::{Network, Signal};
+
+/// Cost of a signal that cannot be controlled or observed
+pub const UNTESTABLE: u32 = u32::MAX;
+
+/// Sum of costs, where any untestable term makes the sum untestable
+fn cost_sum<I: IntoIterator<Item = u32>>(costs: I) -> u32 {
+    costs.into_iter().fold(0, |a, b| a.saturating_add(b))
+}
+
+/// Controllability and observability of the signals of a network
+#[derive(Clone, Debug)]
+pub struct Testability {
+    /// Cost of setting each node to 0 and to 1
+    controllability: Vec<[u32; 2]>,
+    /// Cost of observing each node
+    observability: Vec<u32>,
+    /// Cost of observing each input
+    input_observability: Vec<u32>,
+}
+
+impl Testability {
+    /// Compute the testability measures of a topologically sorted network
+    pub fn compute(aig: &Network) -> Testability {
+        assert!(aig.is_topo_sorted());
+        let mut ret = Testability {
+            controllability: Vec::with_capacity(aig.nb_nodes()),
+            observability: vec![UNTESTABLE; aig.nb_nodes()],
+            input_observability: vec![UNTESTABLE; aig.nb_inputs()],
+        };
+        for i in 0..aig.nb_nodes() {
+            let g = aig.gate_ref(i);
+            let cc = [
+                ret.gate_controllability(g, false),
+                ret.gate_controllability(g, true),
+            ];
+            ret.controllability.push(cc);
+        }
+
+        for o in 0..aig.nb_outputs() {
+            ret.observe(aig.output(o), 0);
+        }
+        for i in (0..aig.nb_nodes()).rev() {
+            let g = aig.gate_ref(i);
+            for (k, s) in g.dependencies().iter().enumerate() {
+                let cost = if g.is_comb() {
+                    ret.pin_observability(aig, i, k)
+                } else {
+                    0
+                };
+                ret.observe(*s, cost);
+            }
+        }
+        ret
+    }
+
+    /// Update the observability of a signal with a new path
+    fn observe(&mut self, s: Signal, cost: u32) {
+        let obs = if s.is_input() {
+            &mut self.input_observability[s.input() as usize]
+        } else if s.is_var() {
+            &mut self.observability[s.var() as usize]
+        } else {
+            return;
+        };
+        *obs = (*obs).min(cost);
+    }
+
+    /// Cost of setting a signal to a given value
+    pub fn controllability(&self, s: Signal, value: bool) -> u32 {
+        if s.is_constant() {
+            if (s == Signal::one()) == value {
+                0
+            } else {
+                UNTESTABLE
+            }
+        } else if s.is_input() {
+            1
+        } else {
+            self.controllability[s.var() as usize][(value ^ s.is_inverted()) as usize]
+        }
+    }
+
+    /// Cost of observing a signal at the outputs
+    pub fn observability(&self, s: Signal) -> u32 {
+        if s.is_constant() {
+            UNTESTABLE
+        } else if s.is_input() {
+            self.input_observability[s.input() as usize]
+        } else {
+            self.observability[s.var() as usize]
+        }
+    }
+
+    /// Cost of observing the k-th input of a gate, through this gate
+    pub fn pin_observability(&self, aig: &Network, gate: usize, k: usize) -> u32 {
+        let g = aig.gate_ref(gate);
+        if !g.is_comb() {
+            return 0;
+        }
+        let sensitization = self.sensitization(g, k);
+        if matches!(g, GateRef::Buf(_)) {
+            sensitization.saturating_add(self.observability[gate])
+        } else {
+            cost_sum([sensitization, self.observability[gate], 1])
+        }
+    }
+
+    /// Cost of testing a stuck-at fault: setting the faulty signal to the opposite value and
+    /// observing it
+    ///
+    /// Transition faults additionally require the faulty signal to start from the stuck value in
+    /// the launch cycle.
+    pub fn fault_difficulty(&self, aig: &Network, fault: &Fault) -> u32 {
+        if fault.is_transition() {
+            let launch = self.controllability(fault.site(aig), fault.value());
+            return self
+                .fault_difficulty(aig, &fault.as_stuck_at())
+                .saturating_add(launch);
+        }
+        match *fault {
+            Fault::OutputStuckAtFault { gate, value } => {
+                let s = aig.node(gate);
+                self.controllability(s, !value)
+                    .saturating_add(self.observability(s))
+            }
+            Fault::InputStuckAtFault { gate, input, value } => {
+                let s = aig.dependencies(gate)[input];
+                self.controllability(s, !value)
+                    .saturating_add(self.pin_observability(aig, gate, input))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Cost of setting the output of a gate to a value, from the cost of its inputs
+    fn gate_controllability(&self, g: GateRef, value: bool) -> u32 {
+        use GateRef::*;
+        let cc = |s: &Signal, v: bool| self.controllability(*s, v);
+        let ret = match g {
+            Binary(v, BinaryType::And) => self.and_controllability(v, false, value),
+            Ternary(v, TernaryType::And) => self.and_controllability(v, false, value),
+            Binary(v, BinaryType::Xor) => self.xor_controllability(v, value),
+            Ternary(v, TernaryType::Xor) => self.xor_controllability(v, value),
+            Nary(v, tp) => match tp {
+                NaryType::And => self.and_controllability(v, false, value),
+                NaryType::Nand => self.and_controllability(v, false, !value),
+                NaryType::Or => self.and_controllability(v, true, !value),
+                NaryType::Nor => self.and_controllability(v, true, value),
+                NaryType::Xor => self.xor_controllability(v, value),
+                NaryType::Xnor => self.xor_controllability(v, !value),
+            },
+            Ternary([s, a, b], TernaryType::Mux) => {
+                cost_sum([cc(s, true), cc(a, value)]).min(cost_sum([cc(s, false), cc(b, value)]))
+            }
+            Ternary([a, b, c], TernaryType::Maj) => cost_sum([cc(a, value), cc(b, value)])
+                .min(cost_sum([cc(a, value), cc(c, value)]))
+                .min(cost_sum([cc(b, value), cc(c, value)])),
+            Buf(s) => return cc(s, value),
+            Dff(_, _) | Latch(_) => return 1,
+            Lut(v, lut) => (0..1 << v.len())
+                .filter(|m| lut.value(*m) == value)
+                .map(|m| self.minterm_controllability(v, m, None))
+                .min()
+                .unwrap_or(UNTESTABLE),
+        };
+        ret.saturating_add(1)
+    }
+
+    /// Cost of setting an And of the (optionally inverted) inputs to a value
+    fn and_controllability(&self, v: &[Signal], inv: bool, value: bool) -> u32 {
+        if value {
+            cost_sum(v.iter().map(|s| self.controllability(*s ^ inv, true)))
+        } else {
+            v.iter()
+                .map(|s| self.controllability(*s ^ inv, false))
+                .min()
+                .unwrap_or(UNTESTABLE)
+        }
+    }
+
+    /// Cost of setting a Xor of the inputs to a value
+    fn xor_controllability(&self, v: &[Signal], value: bool) -> u32 {
+        let mut parity = [0, UNTESTABLE];
+        for s in v {
+            let c0 = self.controllability(*s, false);
+            let c1 = self.controllability(*s, true);
+            parity = [
+                c0.saturating_add(parity[0])
+                    .min(c1.saturating_add(parity[1])),
+                c1.saturating_add(parity[0])
+                    .min(c0.saturating_add(parity[1])),
+            ];
+        }
+        parity[value as usize]
+    }
+
+    /// Cost of setting the inputs to a minterm, optionally ignoring one of them
+    fn minterm_controllability(&self, v: &[Signal], m: usize, ignored: Option<usize>) -> u32 {
+        cost_sum(
+            v.iter()
+                .enumerate()
+                .filter(|(k, _)| Some(*k) != ignored)
+                .map(|(k, s)| self.controllability(*s, (m >> k) & 1 != 0)),
+        )
+    }
+
+    /// Cost of setting the other inputs of a gate so that the k-th input is visible at its output
+    fn sensitization(&self, g: GateRef, k: usize) -> u32 {
+        use GateRef::*;
+        let v = g.dependencies();
+        let others = || v.iter().enumerate().filter(move |(j, _)| *j != k);
+        let cc = |s: &Signal, v: bool| self.controllability(*s, v);
+        let any = |s: &Signal| cc(s, false).min(cc(s, true));
+        match g {
+            Binary(_, BinaryType::And) | Ternary(_, TernaryType::And) => {
+                cost_sum(others().map(|(_, s)| cc(s, true)))
+            }
+            Nary(_, NaryType::And | NaryType::Nand) => cost_sum(others().map(|(_, s)| cc(s, true))),
+            Nary(_, NaryType::Or | NaryType::Nor) => cost_sum(others().map(|(_, s)| cc(s, false))),
+            Binary(_, BinaryType::Xor)
+            | Ternary(_, TernaryType::Xor)
+            | Nary(_, NaryType::Xor | NaryType::Xnor) => cost_sum(others().map(|(_, s)| any(s))),
+            Ternary([s, a, b], TernaryType::Mux) => match k {
+                0 => {
+                    cost_sum([cc(a, false), cc(b, true)]).min(cost_sum([cc(a, true), cc(b, false)]))
+                }
+                1 => cc(s, true),
+                _ => cc(s, false),
+            },
+            Ternary(_, TernaryType::Maj) => {
+                let mut o = others().map(|(_, s)| *s);
+                let (a, b) = (o.next().unwrap(), o.next().unwrap());
+                cost_sum([cc(&a, false), cc(&b, true)]).min(cost_sum([cc(&a, true), cc(&b, false)]))
+            }
+            Buf(_) => 0,
+            Dff(_, _) | Latch(_) => 0,
+            Lut(v, lut) => (0..1 << v.len())
+                .filter(|m| (m >> k) & 1 == 0 && lut.value(*m) != lut.value(m | 1 << k))
+                .map(|m| self.minterm_controllability(v, m, Some(k)))
+                .min()
+                .unwrap_or(UNTESTABLE),
+        }
+    }
+
+    /// Probability of setting each input to 1 in random patterns, to favour the hard-to-control
+    /// values of the nodes
+    ///
+    /// Each node whose costlier value is at least twice as hard to obtain as the other requests
+    /// this value. Requests are propagated to the inputs through And gates, where a 1 requires all
+    /// inputs to be 1, and through buffers. The probability of an input is the ratio of requests
+    /// for 1, clamped between 1/8 and 7/8.
+    pub fn input_bias(&self, aig: &Network) -> Vec<f64> {
+        let mut requests = vec![[0.0f64; 2]; aig.nb_nodes()];
+        let mut input_requests = vec![[0.0f64; 2]; aig.nb_inputs()];
+        for i in (0..aig.nb_nodes()).rev() {
+            let [c0, c1] = self.controllability[i];
+            if c0 != UNTESTABLE && c1 != UNTESTABLE {
+                if c1 >= 2 * c0 {
+                    requests[i][1] += 1.0;
+                } else if c0 >= 2 * c1 {
+                    requests[i][0] += 1.0;
+                }
+            }
+            let g = aig.gate_ref(i);
+            let r = requests[i];
+            let mut request = |s: Signal, value: bool, weight: f64| {
+                let v = (value ^ s.is_inverted()) as usize;
+                if s.is_input() {
+                    input_requests[s.input() as usize][v] += weight;
+                } else if s.is_var() {
+                    requests[s.var() as usize][v] += weight;
+                }
+            };
+            match g {
+                GateRef::Binary(_, BinaryType::And) | GateRef::Ternary(_, TernaryType::And) => {
+                    for s in g.dependencies() {
+                        request(*s, true, r[1]);
+                    }
+                }
+                GateRef::Nary(v, NaryType::And | NaryType::Nand | NaryType::Or | NaryType::Nor) => {
+                    let (inv, out) = match g {
+                        GateRef::Nary(_, NaryType::And) => (false, 1),
+                        GateRef::Nary(_, NaryType::Nand) => (false, 0),
+                        GateRef::Nary(_, NaryType::Or) => (true, 0),
+                        _ => (true, 1),
+                    };
+                    for s in v.iter() {
+                        request(*s, !inv, r[out]);
+                    }
+                }
+                GateRef::Buf(s) => {
+                    request(*s, false, r[0]);
+                    request(*s, true, r[1]);
+                }
+                _ => (),
+            }
+        }
+        input_requests
+            .iter()
+            .map(|[r0, r1]| ((1.0 + r1) / (2.0 + r0 + r1)).clamp(0.125, 0.875))
+            .collect()
+    }
+
+    /// Largest finite controllability among the nodes
+    fn max_controllability(&self) -> u32 {
+        self.controllability
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|c| *c != UNTESTABLE)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Largest finite observability among the nodes
+    fn max_observability(&self) -> u32 {
+        self.observability
+            .iter()
+            .copied()
+            .filter(|c| *c != UNTESTABLE)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl fmt::Display for Testability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let average = |v: Vec<u32>| {
+            if v.is_empty() {
+                0.0
+            } else {
+                v.iter().map(|c| *c as f64).sum::<f64>() / v.len() as f64
+            }
+        };
+        let cc: Vec<u32> = self
+            .controllability
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|c| *c != UNTESTABLE)
+            .collect();
+        let co: Vec<u32> = self
+            .observability
+            .iter()
+            .copied()
+            .filter(|c| *c != UNTESTABLE)
+            .collect();
+        let nb_unobservable = self.observability.len() - co.len();
+        writeln!(f, "Testability (SCOAP):")?;
+        writeln!(
+            f,
+            "  Controllability: max {}, average {:.1}",
+            count(self.max_controllability() as usize),
+            average(cc)
+        )?;
+        writeln!(
+            f,
+            "  Observability: max {}, average {:.1}",
+            count(self.max_observability() as usize),
+            average(co)
+        )?;
+        if nb_unobservable != 0 {
+            writeln!(f, "  Unobservable nodes: {}", count(nb_unobservable))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Testability, UNTESTABLE};
+    use crate::network::generators::adder;
+    use crate::sim::Fault;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_and() {
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let x = aig.and(aig.input(0), aig.input(1));
+        let y = aig.and(x, !aig.input(2));
+        aig.add_output(y);
+        aig.add_output(!aig.input(0));
+        let t = Testability::compute(&aig);
+        assert_eq!(t.controllability(x, true), 3);
+        assert_eq!(t.controllability(x, false), 2);
+        assert_eq!(t.controllability(y, true), 5);
+        assert_eq!(t.controllability(!y, true), 2);
+        assert_eq!(t.observability(y), 0);
+        assert_eq!(t.observability(x), 2);
+        // Input 1 is observed through x then y
+        assert_eq!(t.observability(aig.input(1)), 4);
+        // Input 0 is an output as well
+        assert_eq!(t.observability(aig.input(0)), 0);
+        assert_eq!(t.controllability(Signal::zero(), true), UNTESTABLE);
+        assert_eq!(t.controllability(Signal::one(), true), 0);
+
+        let fault = Fault::OutputStuckAtFault {
+            gate: y.var() as usize,
+            value: false,
+        };
+        assert_eq!(t.fault_difficulty(&aig, &fault), 5);
+        let fault = Fault::InputStuckAtFault {
+            gate: y.var() as usize,
+            input: 0,
+            value: true,
+        };
+        assert_eq!(t.fault_difficulty(&aig, &fault), 2 + 2);
+    }
+
+    #[test]
+    fn test_other_gates() {
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        let x = aig.add(Gate::xor(a, b));
+        let m = aig.add(Gate::mux(a, b, c));
+        let j = aig.add(Gate::maj(a, b, c));
+        aig.add_output(x);
+        aig.add_output(m);
+        aig.add_output(j);
+        let t = Testability::compute(&aig);
+        for s in [x, m, j] {
+            assert_eq!(t.controllability(s, false), 3);
+            assert_eq!(t.controllability(s, true), 3);
+            assert_eq!(t.observability(s), 0);
+        }
+        assert_eq!(t.observability(c), 2);
+    }
+
+    #[test]
+    fn test_bias() {
+        // A wide And is hard to set to 1, so its inputs are biased towards 1
+        let mut aig = Network::new();
+        aig.add_inputs(8);
+        let mut x = aig.input(0);
+        for i in 1..8 {
+            x = aig.and(x, aig.input(i));
+        }
+        aig.add_output(x);
+        let t = Testability::compute(&aig);
+        let bias = t.input_bias(&aig);
+        assert!(bias.iter().all(|p| *p > 0.5 && *p <= 0.875));
+
+        let aig = adder::ripple_carry(8);
+        let t = Testability::compute(&aig);
+        assert_eq!(t.input_bias(&aig).len(), aig.nb_inputs());
+        assert!(t.to_string().starts_with("Testability (SCOAP):"));
+    }
+}
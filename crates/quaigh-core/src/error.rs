@@ -0,0 +1,135 @@
+//! Errors reported when reading files or checking networks
+
+use std::fmt;
+use std::path::Path;
+
+/// Error of the fallible operations of quaigh, such as reading a network file
+///
+/// Errors are meant to be reported to the user: their display gives the file and the line when
+/// known, for example `design.bench:12: gate input x is not generated anywhere`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuaighError {
+    /// A file could not be opened, read or written
+    Io {
+        /// Path of the file
+        path: String,
+        /// Error reported by the system
+        message: String,
+    },
+    /// A file is malformed
+    Parse {
+        /// Path of the file, if known
+        file: Option<String>,
+        /// Line of the error, starting at 1, if known
+        line: Option<usize>,
+        /// Description of the error
+        message: String,
+    },
+    /// A file format or a construct is not supported
+    Unsupported(String),
+    /// A network is invalid, for example with a combinatorial loop or an out-of-bounds signal
+    InvalidNetwork(String),
+    /// An external program could not be run or failed
+    External {
+        /// Name of the program
+        program: String,
+        /// Description of the failure
+        message: String,
+    },
+}
+
+impl QuaighError {
+    /// Parse error from a message, without a line
+    pub fn parse(message: impl Into<String>) -> QuaighError {
+        QuaighError::Parse {
+            file: None,
+            line: None,
+            message: message.into(),
+        }
+    }
+
+    /// Parse error at a line of the file, starting at 1
+    pub fn parse_at(line: usize, message: impl Into<String>) -> QuaighError {
+        QuaighError::Parse {
+            file: None,
+            line: Some(line),
+            message: message.into(),
+        }
+    }
+
+    /// Error when accessing a file
+    pub fn io(path: &Path, error: std::io::Error) -> QuaighError {
+        QuaighError::Io {
+            path: path.display().to_string(),
+            message: error.to_string(),
+        }
+    }
+
+    /// Attach the path of the file to a parse error
+    pub fn with_file(self, path: &Path) -> QuaighError {
+        match self {
+            QuaighError::Parse { line, message, .. } => QuaighError::Parse {
+                file: Some(path.display().to_string()),
+                line,
+                message,
+            },
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for QuaighError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuaighError::Io { path, message } => write!(f, "Cannot access {}: {}", path, message),
+            QuaighError::Parse {
+                file,
+                line,
+                message,
+            } => match (file, line) {
+                (Some(file), Some(line)) => write!(f, "{}:{}: {}", file, line, message),
+                (Some(file), None) => write!(f, "{}: {}", file, message),
+                (None, Some(line)) => write!(f, "Line {}: {}", line, message),
+                (None, None) => write!(f, "{}", message),
+            },
+            QuaighError::Unsupported(message) => write!(f, "Unsupported: {}", message),
+            QuaighError::InvalidNetwork(message) => write!(f, "{}", message),
+            QuaighError::External { program, message } => {
+                write!(f, "{} failed: {}", program, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for QuaighError {}
+
+impl From<QuaighError> for String {
+    fn from(e: QuaighError) -> String {
+        e.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::QuaighError;
+
+    #[test]
+    fn test_display() {
+        let e = QuaighError::parse_at(12, "gate input x is not generated anywhere");
+        assert_eq!(
+            e.to_string(),
+            "Line 12: gate input x is not generated anywhere"
+        );
+        let e = e.with_file(Path::new("design.bench"));
+        assert_eq!(
+            e.to_string(),
+            "design.bench:12: gate input x is not generated anywhere"
+        );
+        let e = QuaighError::parse("Output o is not generated anywhere");
+        assert_eq!(e.to_string(), "Output o is not generated anywhere");
+        let e = QuaighError::Unsupported("latches in .bench files".to_owned());
+        assert_eq!(e.to_string(), "Unsupported: latches in .bench files");
+    }
+}
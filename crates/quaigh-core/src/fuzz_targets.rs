@@ -0,0 +1,201 @@
+//! Entry points for fuzzing
+//!
+//! These functions take raw bytes and never panic, whatever the input, so they can be
+//! plugged directly into [cargo-fuzz](https://github.com/rust-fuzz/cargo-fuzz) or AFL harnesses.
+//! A ready-made setup with a seed corpus is available in the `fuzz/` directory of the repository.
+//!
+//! ```
+//! # use quaigh_core::fuzz_targets::{canonicalize_roundtrip, parse_bench};
+//! assert!(parse_bench(b"INPUT(a)\nOUTPUT(a)\n").is_ok());
+//! assert!(parse_bench(b"x = AND(y)\n").is_err());
+//! assert!(canonicalize_roundtrip(b"INPUT(a)\nINPUT(b)\nOUTPUT(x)\nx = NAND(a, b)\n").is_ok());
+//! ```
+
+use std::fmt;
+use std::io;
+
+use crate::io::{read_bench, read_blif, read_patterns, write_bench};
+use crate::testing::compare_by_simulation;
+use crate::Network;
+
+/// Error returned by the fuzzing entry points
+#[derive(Debug)]
+pub enum Error {
+    /// The input is not a valid file; this is expected for most fuzzer inputs
+    Parse(io::Error),
+    /// The network read back after writing does not match the original; this is a bug
+    Mismatch(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "Parse error: {}", e),
+            Error::Mismatch(m) => write!(f, "Round-trip mismatch: {}", m),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Convert a parser error message to an IO error
+fn to_io_error(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Parse a .bench file from raw bytes
+pub fn parse_bench(data: &[u8]) -> Result<Network, io::Error> {
+    read_bench(data).map_err(to_io_error)
+}
+
+/// Parse a .blif file from raw bytes
+pub fn parse_blif(data: &[u8]) -> Result<Network, io::Error> {
+    read_blif(data).map_err(to_io_error)
+}
+
+/// Parse a test pattern file from raw bytes
+pub fn parse_patterns(data: &[u8]) -> Result<Vec<Vec<Vec<bool>>>, io::Error> {
+    read_patterns(data).map_err(to_io_error)
+}
+
+/// Parse a .bench file, canonicalize it, write it back, parse it again and compare
+///
+/// Returns [`Error::Parse`] if the input is not a valid .bench file, and [`Error::Mismatch`]
+/// if the network read back is not functionally identical to the canonicalized network.
+pub fn canonicalize_roundtrip(data: &[u8]) -> Result<(), Error> {
+    let mut aig = parse_bench(data).map_err(Error::Parse)?;
+    aig.make_canonical();
+    aig.cleanup();
+    let mut buf = Vec::new();
+    write_bench(&mut buf, &aig);
+    let reread = read_bench(buf.as_slice())
+        .map_err(|e| Error::Mismatch(format!("Unable to read back the network: {}", e)))?;
+    compare_by_simulation(&aig, &reread).map_err(Error::Mismatch)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{canonicalize_roundtrip, parse_bench, parse_blif, parse_patterns, Error};
+
+    fn corpus(target: &str) -> Vec<Vec<u8>> {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("..")
+            .join("fuzz")
+            .join("corpus")
+            .join(target);
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        paths.sort();
+        paths.iter().map(|p| fs::read(p).unwrap()).collect()
+    }
+
+    /// Inputs that exercise the error paths of the parsers
+    const NASTY_INPUTS: &[&str] = &[
+        "",
+        "\n\n\n",
+        "(",
+        "=",
+        "= =",
+        "x =",
+        "x = ()",
+        "INPUT(",
+        "INPUT()",
+        "INPUT(a)(b)",
+        "FOO(a)",
+        "INPUT(a)\nINPUT(a)",
+        "INPUT(a)\na = BUF(a)",
+        "x = AND(y)",
+        "x = AND(x)",
+        "x = NOT(y)\ny = NOT(x)",
+        "OUTPUT(x)",
+        "INPUT(a)\nx = DFF()",
+        "INPUT(a)\nx = DFF(a, a)",
+        "INPUT(a)\nx = DFFRSE(a)",
+        "INPUT(a)\nx = DFFRSE(a, a, a, a)",
+        "INPUT(a)\nx = DFFSR_NN_QN(a)",
+        "INPUT(a)\nx = DFFSR_PX(a, a, a)",
+        "INPUT(a)\nx = MUX(a, a)",
+        "INPUT(a)\nx = MAJ(a)",
+        "INPUT(a)\nx = VDD(a)",
+        "INPUT(a)\nx = FOO(a)",
+        "INPUT(a)\nx = LUT 0x(a)",
+        "INPUT(a)\nx = LUT 0xzz(a)",
+        "INPUT(a)\nx = LUT 0x12345(a)",
+        "INPUT(a)\nx = LUT 0x1(a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a, a)",
+        ".model",
+        ".latch",
+        ".latch a",
+        ".inputs a\n.latch b c",
+        ".names",
+        ".names a",
+        ".inputs a\n.names a b\n2 1",
+        ".inputs a\n.names a b\n1 2",
+        ".inputs a\n.names a b\n11 1",
+        ".inputs a\n.names a b\n1 1 1",
+        ".inputs a\n.names a b\n1 1\n0 0",
+        ".inputs a\n.names b a c\n1- 1\n.names c b\n1 1",
+        ".model a\n.model b",
+        ".end",
+        ".subckt foo",
+        "\\",
+        "a \\\n\\",
+        "1:",
+        "1: 0101 2",
+        "x: 0101",
+        "1: 0:1",
+        ":::",
+    ];
+
+    fn check_no_panic(data: &[u8]) {
+        let _ = parse_bench(data);
+        let _ = parse_blif(data);
+        let _ = parse_patterns(data);
+        if let Err(Error::Mismatch(m)) = canonicalize_roundtrip(data) {
+            panic!("Round-trip failure: {}", m);
+        }
+    }
+
+    #[test]
+    fn test_corpus() {
+        for data in corpus("parse_bench") {
+            parse_bench(&data).unwrap();
+            canonicalize_roundtrip(&data).unwrap();
+            check_no_panic(&data);
+        }
+        for data in corpus("parse_blif") {
+            parse_blif(&data).unwrap();
+            check_no_panic(&data);
+        }
+        for data in corpus("parse_patterns") {
+            parse_patterns(&data).unwrap();
+            check_no_panic(&data);
+        }
+    }
+
+    #[test]
+    fn test_nasty_inputs() {
+        for data in NASTY_INPUTS {
+            check_no_panic(data.as_bytes());
+        }
+        check_no_panic(&[0xff, 0xfe, 0x00, 0x80]);
+        check_no_panic(b"INPUT(\xff)\nOUTPUT(\xff)");
+    }
+
+    #[test]
+    fn test_truncated_corpus() {
+        // Truncate valid files at every position to hit partial statements
+        for target in ["parse_bench", "parse_blif", "parse_patterns"] {
+            for data in corpus(target) {
+                for i in 0..data.len() {
+                    check_no_panic(&data[..i]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,485 @@
+//! Read and write logic networks to files
+
+mod aiger;
+mod bench;
+mod blif;
+mod constraints;
+mod delays;
+mod dot;
+mod json;
+mod patterns;
+mod provenance;
+mod qgh;
+#[doc(hidden)]
+pub mod tar;
+mod tester;
+mod utils;
+mod verilog;
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+pub use aiger::{read_aiger, write_aiger};
+pub use bench::{
+    read_bench, read_bench_with_dialect, read_bench_with_locations, read_bench_with_names,
+    read_bench_with_names_and_dialect, read_bench_with_options, write_bench,
+    write_bench_with_names, write_bench_with_options, BenchArity, BenchDialect,
+};
+pub use blif::{
+    read_blif, read_blif_with_exdc, read_blif_with_hierarchy, read_blif_with_names, write_blif,
+    write_blif_with_names, write_blif_with_options,
+};
+pub use constraints::read_constraints;
+pub use delays::{read_delays, read_sdf_delays};
+pub use dot::{write_dot, DotHighlight};
+pub use json::{read_json, read_json_with_names, write_json, write_json_with_names};
+pub use patterns::{
+    read_patterns, read_stimulus, read_ternary_patterns, write_patterns, write_ternary_patterns,
+    PatternReader, PatternWriter, Stimulus,
+};
+pub use provenance::Provenance;
+pub use qgh::{read_quaigh, write_quaigh, QUAIGH_FORMAT_VERSION};
+pub use tester::{write_stil, write_wgl, TesterSignals};
+pub use verilog::read_verilog;
+
+use blif::read_blif_full;
+
+use crate::network::timing::TimingConstraints;
+use crate::network::{HierarchyMap, NameTable, SourceMap};
+use crate::{Network, QuaighError};
+
+/// Options to read network files
+///
+/// The default options are used by [`read_network_file`] and [`read_network`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Dialect of .bench files
+    pub dialect: BenchDialect,
+    /// Policy for gates with missing inputs in .bench files
+    pub arity: BenchArity,
+}
+
+/// Read the content of a file
+fn read_file(path: &PathBuf) -> Result<Vec<u8>, QuaighError> {
+    std::fs::read(path).map_err(|e| QuaighError::io(path, e))
+}
+
+/// A logic network read from a file, with everything else the file describes
+///
+/// Formats that do not carry some information leave it empty: names are only read from .bench,
+/// .blif and .json files, the hierarchy and the external don't care network from .blif files,
+/// and the location of the gates from .bench files.
+#[derive(Clone, Debug, Default)]
+pub struct ReadResult {
+    /// The logic network
+    pub network: Network,
+    /// Names of the signals
+    pub names: NameTable,
+    /// Provenance header, if present
+    pub provenance: Option<Provenance>,
+    /// Instance of the gates, for hierarchical .blif files
+    pub hierarchy: HierarchyMap,
+    /// Location of the gates in the file
+    pub locations: SourceMap,
+    /// External don't care network, from the .exdc section of .blif files
+    pub exdc: Option<Network>,
+}
+
+/// Extension of a network file, that gives its format
+fn file_format(path: &Path) -> Result<String, QuaighError> {
+    match path.extension() {
+        Some(ext) => Ok(ext.to_string_lossy().into_owned()),
+        None => Err(QuaighError::Unsupported(format!(
+            "no extension given for {}",
+            path.display()
+        ))),
+    }
+}
+
+/// Read a logic network from a file
+///
+/// .bench, .blif, .aag, .aig, .json (Yosys), .v and .qgh formats are supported, with limitations
+/// to the .blif, .json and .v format support
+pub fn read_network_file(path: &PathBuf) -> Result<Network, QuaighError> {
+    let format = file_format(path)?;
+    let data = read_file(path)?;
+    read_network(data.as_slice(), &format).map_err(|e| e.with_file(path))
+}
+
+/// Read a logic network from a file, with its names, provenance header, hierarchy, gate
+/// locations and external don't care network
+///
+/// The file is read and parsed once. The formats are the same as for [`read_network_file`].
+pub fn read_network_file_full(
+    path: &PathBuf,
+    options: &ReadOptions,
+) -> Result<ReadResult, QuaighError> {
+    let format = file_format(path)?;
+    let data = read_file(path)?;
+    let mut ret = match format.as_str() {
+        "bench" => read_bench_with_locations(
+            data.as_slice(),
+            &path.display().to_string(),
+            options.dialect,
+            options.arity,
+        )
+        .map(|(network, names, locations)| ReadResult {
+            network,
+            names,
+            locations,
+            ..ReadResult::default()
+        }),
+        "blif" => read_blif_full(data.as_slice()),
+        "json" => read_json_with_names(data.as_slice()).map(|(network, names)| ReadResult {
+            network,
+            names,
+            ..ReadResult::default()
+        }),
+        _ => {
+            read_network_with_options(data.as_slice(), &format, options).map(|network| ReadResult {
+                network,
+                ..ReadResult::default()
+            })
+        }
+    }
+    .map_err(|e| e.with_file(path))?;
+    ret.provenance = Provenance::parse_format(&data, &format);
+    Ok(ret)
+}
+
+/// Read a logic network from memory, in the format given by its usual file extension
+///
+/// This is [`read_network_file`] without a filesystem, for example to run in a browser.
+pub fn read_network(data: &[u8], format: &str) -> Result<Network, QuaighError> {
+    read_network_with_options(data, format, &ReadOptions::default())
+}
+
+/// Read a logic network from memory, with the given options
+pub fn read_network_with_options(
+    data: &[u8],
+    format: &str,
+    options: &ReadOptions,
+) -> Result<Network, QuaighError> {
+    match format {
+        "bench" => read_bench_with_options(data, options.dialect, options.arity).map(|r| r.0),
+        "blif" => read_blif(data),
+        "aag" | "aig" => read_aiger(data),
+        "json" => read_json(data),
+        "v" => read_verilog(data),
+        "qgh" => read_quaigh(data),
+        _ => Err(QuaighError::Unsupported(format!(
+            "unknown extension {}",
+            format
+        ))),
+    }
+}
+
+/// Write a logic network to a file
+///
+/// .bench, .blif, .aag, .aig, .json and .qgh formats are supported
+pub fn write_network_file(path: &PathBuf, aig: &Network) -> Result<(), QuaighError> {
+    write_network_file_with_provenance(path, aig, None)
+}
+
+/// Write a logic network to a file, with a provenance header
+///
+/// .bench, .blif, .aag, .aig, .json and .qgh formats are supported. The provenance is written where
+/// each format allows it, as described in [`Provenance`].
+pub fn write_network_file_with_provenance(
+    path: &PathBuf,
+    aig: &Network,
+    provenance: Option<&Provenance>,
+) -> Result<(), QuaighError> {
+    write_network_file_impl(path, aig, &NameTable::new(), provenance)
+}
+
+/// Write a logic network to a file, with the names of its signals and a provenance header
+///
+/// Names are only written to .bench, .blif and .json files: AIGER and .qgh files use the indices.
+pub fn write_network_file_with_names(
+    path: &PathBuf,
+    aig: &Network,
+    names: &NameTable,
+    provenance: Option<&Provenance>,
+) -> Result<(), QuaighError> {
+    write_network_file_impl(path, aig, names, provenance)
+}
+
+/// Write a logic network to memory, in the format given by its usual file extension
+///
+/// This is [`write_network_file`] without a filesystem, for example to run in a browser.
+pub fn write_network(aig: &Network, format: &str) -> Result<Vec<u8>, QuaighError> {
+    let mut body = Vec::new();
+    write_network_body(&mut body, aig, &NameTable::new(), format)?;
+    Ok(body)
+}
+
+/// Write a logic network in a given format
+fn write_network_body(
+    body: &mut Vec<u8>,
+    aig: &Network,
+    names: &NameTable,
+    format: &str,
+) -> Result<(), QuaighError> {
+    match format {
+        "bench" => write_bench_with_names(body, aig, names),
+        "blif" => write_blif_with_names(body, aig, names),
+        "aag" | "aig" => write_aiger(body, aig, format == "aig"),
+        "json" => write_json_with_names(body, aig, names),
+        "qgh" => write_quaigh(body, aig),
+        "v" => {
+            return Err(QuaighError::Unsupported(
+                "Verilog files can be read but not written".to_owned(),
+            ))
+        }
+        _ => {
+            return Err(QuaighError::Unsupported(format!(
+                "unknown extension {}",
+                format
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn write_network_file_impl(
+    path: &PathBuf,
+    aig: &Network,
+    names: &NameTable,
+    provenance: Option<&Provenance>,
+) -> Result<(), QuaighError> {
+    let format = file_format(path)?;
+    let mut body = Vec::new();
+    write_network_body(&mut body, aig, names, &format)?;
+    let data = match provenance {
+        Some(p) => {
+            let mut data = Vec::new();
+            p.write_format(&mut data, &body, &format)
+                .map_err(|e| QuaighError::io(path, e))?;
+            data
+        }
+        None => body,
+    };
+    std::fs::write(path, data).map_err(|e| QuaighError::io(path, e))
+}
+
+/// Read patterns from a file
+///
+/// Each pattern may contain multiple timesteps. For each timestep, the value of each circuit input is given.
+pub fn read_pattern_file(path: &PathBuf) -> Result<Vec<Vec<Vec<bool>>>, QuaighError> {
+    let f = File::open(path).map_err(|e| QuaighError::io(path, e))?;
+    read_patterns(f).map_err(|e| QuaighError::parse(e).with_file(path))
+}
+
+/// Read per-gate delays from a file, overriding the delays of the gates it annotates
+///
+/// .sdf files are read with [`read_sdf_delays`], other files as a table with [`read_delays`].
+pub fn read_delay_file(
+    path: &Path,
+    aig: &Network,
+    names: &NameTable,
+    delays: &mut [usize],
+) -> Result<(), String> {
+    let f = File::open(path).map_err(|e| e.to_string())?;
+    if path.extension().is_some_and(|s| s == "sdf") {
+        read_sdf_delays(f, aig, names, delays)
+    } else {
+        read_delays(f, aig, names, delays)
+    }
+}
+
+/// Read timing constraints from a file
+///
+/// See [`read_constraints`] for the format.
+pub fn read_constraint_file(
+    path: &Path,
+    aig: &Network,
+    names: &NameTable,
+) -> Result<TimingConstraints, String> {
+    let f = File::open(path).map_err(|e| e.to_string())?;
+    read_constraints(f, aig, names)
+}
+
+/// Read a stimulus for a sequential design from a file
+///
+/// See [`read_stimulus`] for the format.
+pub fn read_stimulus_file(path: &PathBuf) -> Result<Stimulus, String> {
+    let f = File::open(path).map_err(|e| e.to_string())?;
+    read_stimulus(f)
+}
+
+/// Write patterns to a file
+///
+/// Each pattern may contain multiple timesteps. For each timestep, the value of each circuit input is given.
+pub fn write_pattern_file(
+    path: &PathBuf,
+    patterns: &Vec<Vec<Vec<bool>>>,
+) -> Result<(), QuaighError> {
+    let mut data = Vec::new();
+    write_patterns(&mut data, patterns);
+    std::fs::write(path, data).map_err(|e| QuaighError::io(path, e))
+}
+
+/// Open a pattern file for streaming, reading one pattern at a time
+pub fn open_pattern_file(path: &PathBuf) -> Result<PatternReader<BufReader<File>>, QuaighError> {
+    let f = File::open(path).map_err(|e| QuaighError::io(path, e))?;
+    Ok(PatternReader::new(BufReader::new(f)))
+}
+
+/// Create a pattern file for streaming, writing one pattern at a time
+pub fn create_pattern_file(path: &PathBuf) -> Result<PatternWriter<BufWriter<File>>, QuaighError> {
+    let f = File::create(path).map_err(|e| QuaighError::io(path, e))?;
+    Ok(PatternWriter::new(BufWriter::new(f)))
+}
+
+/// Returns whether a pattern file uses a tester format, .stil or .wgl
+pub fn is_tester_pattern_file(path: &Path) -> bool {
+    path.extension().is_some_and(|s| s == "stil" || s == "wgl")
+}
+
+/// Write test patterns and their expected responses to a file in a tester format
+///
+/// .stil and .wgl formats are supported
+pub fn write_tester_pattern_file(
+    path: &PathBuf,
+    signals: &TesterSignals,
+    patterns: &[Vec<Vec<bool>>],
+    responses: &[Vec<Vec<bool>>],
+) -> Result<(), QuaighError> {
+    let mut data = Vec::new();
+    if path.extension().is_some_and(|s| s == "wgl") {
+        write_wgl(&mut data, signals, patterns, responses);
+    } else {
+        write_stil(&mut data, signals, patterns, responses);
+    }
+    std::fs::write(path, data).map_err(|e| QuaighError::io(path, e))
+}
+
+/// Write a network to a Graphviz .dot file, with the names of its signals
+pub fn write_dot_file(
+    path: &Path,
+    aig: &Network,
+    names: &NameTable,
+    highlight: &DotHighlight,
+) -> Result<(), QuaighError> {
+    let mut data = Vec::new();
+    write_dot(&mut data, aig, names, highlight);
+    std::fs::write(path, data).map_err(|e| QuaighError::io(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        create_pattern_file, open_pattern_file, read_network, read_network_file_full,
+        read_network_with_options, read_pattern_file, write_network, write_network_file,
+        write_pattern_file, BenchArity, ReadOptions,
+    };
+    use crate::network::generators::testcases::random_seq;
+    use crate::QuaighError;
+
+    #[test]
+    fn test_in_memory() {
+        let aig = random_seq(4, 2, 20, 2, 1);
+        for format in ["bench", "blif", "aag", "aig", "json", "qgh"] {
+            let data = write_network(&aig, format).unwrap();
+            let ret = read_network(&data, format).unwrap();
+            assert_eq!(ret.nb_inputs(), aig.nb_inputs(), "{}", format);
+            assert_eq!(ret.nb_outputs(), aig.nb_outputs(), "{}", format);
+        }
+        for format in ["v", "txt"] {
+            let err = write_network(&aig, format).unwrap_err();
+            assert!(matches!(err, QuaighError::Unsupported(_)), "{}", format);
+        }
+        let err = read_network(b"INPUT(a)\nOUTPUT(b)\nb = FOO(a)\n", "bench").unwrap_err();
+        assert!(
+            matches!(err, QuaighError::Parse { line: Some(3), .. }),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_read_options() {
+        let data = b"INPUT(s)\nINPUT(a)\nOUTPUT(x)\nx = MUX(s, a)\n";
+        assert!(read_network(data, "bench").is_err());
+        let options = ReadOptions {
+            arity: BenchArity::Pad,
+            ..ReadOptions::default()
+        };
+        let ret = read_network_with_options(data, "bench", &options).unwrap();
+        assert_eq!(ret.nb_inputs(), 2);
+        assert_eq!(ret.nb_outputs(), 1);
+    }
+
+    #[test]
+    fn test_read_full() {
+        let dir = std::env::temp_dir().join(format!("quaigh_read_full_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let blif = dir.join("t.blif");
+        let content =
+            ".model t\n.inputs a b\n.outputs y\n.names a b y\n11 1\n.exdc\n.names a y\n1 1\n.end\n";
+        std::fs::write(&blif, content).unwrap();
+        let ret = read_network_file_full(&blif, &ReadOptions::default()).unwrap();
+        assert_eq!(ret.network.nb_inputs(), 2);
+        assert_eq!(ret.names.input_name(1), Some("b"));
+        assert_eq!(ret.exdc.unwrap().nb_outputs(), 1);
+        assert!(ret.provenance.is_none());
+
+        let bench = dir.join("t.bench");
+        std::fs::write(&bench, "INPUT(a)\nINPUT(b)\nOUTPUT(y)\ny = AND(a, b)\n").unwrap();
+        let ret = read_network_file_full(&bench, &ReadOptions::default()).unwrap();
+        assert_eq!(ret.network.nb_nodes(), 1);
+        assert_eq!(ret.names.output_name(0), Some("y"));
+        assert_eq!(ret.locations.node_location(0).map(|l| l.line), Some(4));
+        assert!(ret.exdc.is_none());
+
+        let err = read_network_file_full(&dir.join("t"), &ReadOptions::default()).unwrap_err();
+        assert!(matches!(err, QuaighError::Unsupported(_)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_write_errors() {
+        let dir = std::env::temp_dir().join(format!("quaigh_write_errors_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let aig = random_seq(4, 2, 20, 2, 1);
+        for name in ["t.v", "t"] {
+            let err = write_network_file(&dir.join(name), &aig).unwrap_err();
+            assert!(matches!(err, QuaighError::Unsupported(_)), "{}", name);
+            assert!(!dir.join(name).exists());
+        }
+        let err = write_network_file(&dir.join("missing").join("t.bench"), &aig).unwrap_err();
+        assert!(matches!(err, QuaighError::Io { .. }));
+        write_network_file(&dir.join("t.bench"), &aig).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_pattern_file_errors() {
+        let dir =
+            std::env::temp_dir().join(format!("quaigh_pattern_errors_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let patterns = vec![vec![vec![true, false]], vec![vec![false, true]]];
+        let path = dir.join("t.test");
+        write_pattern_file(&path, &patterns).unwrap();
+        assert_eq!(read_pattern_file(&path).unwrap(), patterns);
+
+        let missing = dir.join("missing.test");
+        let err = read_pattern_file(&missing).unwrap_err();
+        assert!(matches!(err, QuaighError::Io { .. }));
+        assert!(open_pattern_file(&missing).is_err());
+        let err = write_pattern_file(&dir.join("missing").join("t.test"), &patterns).unwrap_err();
+        assert!(matches!(err, QuaighError::Io { .. }));
+        assert!(create_pattern_file(&dir.join("missing").join("t.test")).is_err());
+
+        std::fs::write(&path, "1: 01\n2: 0: 1\n").unwrap();
+        let err = read_pattern_file(&path).unwrap_err();
+        assert!(
+            err.to_string().starts_with(&path.display().to_string()),
+            "{}",
+            err
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
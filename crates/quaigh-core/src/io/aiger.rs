@@ -0,0 +1,483 @@
+//! Read and write AIGER files, in ASCII (.aag) or binary (.aig) format
+//!
+//! Latches are read as flip-flops without enable and reset, and must be initialized to zero.
+//! Bad state properties of AIGER 1.9 are read as additional outputs; other properties are not
+//! supported. Symbol tables and comments are ignored.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::network::{BinaryType, ClockId, NaryType, TernaryType};
+use crate::{Gate, Network, QuaighError, Signal};
+
+/// Parser for the content of an AIGER file
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Read the next line as a list of unsigned integers
+    fn read_numbers(&mut self, what: &str) -> Result<Vec<u32>, String> {
+        let end = self.data[self.pos..]
+            .iter()
+            .position(|c| *c == b'\n')
+            .map(|p| self.pos + p)
+            .ok_or_else(|| format!("Unexpected end of file while reading {}", what))?;
+        let line = std::str::from_utf8(&self.data[self.pos..end])
+            .map_err(|_| format!("Invalid {} line", what))?;
+        self.pos = end + 1;
+        line.split_whitespace()
+            .map(|t| {
+                t.parse::<u32>()
+                    .map_err(|_| format!("Invalid {} line: {}", what, line.trim()))
+            })
+            .collect()
+    }
+
+    /// Read a variable-length encoded delta of the binary format
+    fn read_delta(&mut self) -> Result<u32, String> {
+        let mut ret: u32 = 0;
+        let mut shift = 0;
+        loop {
+            let Some(b) = self.data.get(self.pos) else {
+                return Err("Unexpected end of file while reading And gates".to_owned());
+            };
+            self.pos += 1;
+            if shift > 28 {
+                return Err("Invalid delta encoding in And gates".to_owned());
+            }
+            ret |= ((b & 0x7f) as u32) << shift;
+            if b & 0x80 == 0 {
+                return Ok(ret);
+            }
+            shift += 7;
+        }
+    }
+}
+
+/// Read a network in AIGER format, either ASCII or binary
+pub fn read_aiger<R: std::io::Read>(r: R) -> Result<Network, QuaighError> {
+    parse_aiger(r).map_err(QuaighError::parse)
+}
+
+fn parse_aiger<R: std::io::Read>(mut r: R) -> Result<Network, String> {
+    let mut data = Vec::new();
+    r.read_to_end(&mut data).map_err(|e| e.to_string())?;
+    let mut p = Parser {
+        data: &data,
+        pos: 0,
+    };
+
+    // Header: aag M I L O A [B C J F]
+    let binary = if data.starts_with(b"aag ") {
+        false
+    } else if data.starts_with(b"aig ") {
+        true
+    } else {
+        return Err("Missing aag or aig header".to_owned());
+    };
+    p.pos = 4;
+    let header = p.read_numbers("header")?;
+    if header.len() < 5 || header.len() > 9 {
+        return Err("Invalid header, expected M I L O A".to_owned());
+    }
+    let [max_var, nb_inputs, nb_latches, nb_outputs, nb_ands] =
+        [0, 1, 2, 3, 4].map(|i| header[i] as usize);
+    let nb_bad = header.get(5).copied().unwrap_or(0) as usize;
+    if header.iter().skip(6).any(|n| *n != 0) {
+        return Err("Constraints, justice and fairness properties are not supported".to_owned());
+    }
+    if max_var < nb_inputs + nb_latches + nb_ands {
+        return Err(format!(
+            "Maximum variable {} is too small for {} inputs, {} latches and {} And gates",
+            max_var, nb_inputs, nb_latches, nb_ands
+        ));
+    }
+
+    // Variables of the file mapped to signals: latches and And gates are nodes in this order.
+    // The maximum variable is not trusted to allocate a table, as the file may be malformed.
+    let mut vars: HashMap<usize, Signal> = HashMap::new();
+    vars.insert(0, Signal::zero());
+    let mut define = |var: usize, s: Signal| -> Result<(), String> {
+        if var > max_var {
+            return Err(format!("Variable {} is larger than the maximum", var));
+        }
+        match vars.entry(var) {
+            Entry::Occupied(_) => Err(format!("Variable {} is defined twice", var)),
+            Entry::Vacant(e) => {
+                e.insert(s);
+                Ok(())
+            }
+        }
+    };
+    let lhs_var = |lit: u32, what: &str| -> Result<usize, String> {
+        if lit & 1 != 0 || lit < 2 {
+            Err(format!("Invalid {} literal {}", what, lit))
+        } else {
+            Ok((lit / 2) as usize)
+        }
+    };
+
+    let mut ret = Network::new();
+    ret.add_inputs(nb_inputs);
+    for i in 0..nb_inputs {
+        let var = if binary {
+            i + 1
+        } else {
+            let v = p.read_numbers("input")?;
+            if v.len() != 1 {
+                return Err("Invalid input line".to_owned());
+            }
+            lhs_var(v[0], "input")?
+        };
+        define(var, ret.input(i))?;
+    }
+    let mut latches = Vec::new();
+    for i in 0..nb_latches {
+        let v = p.read_numbers("latch")?;
+        let expected = if binary { 1..=2 } else { 2..=3 };
+        if !expected.contains(&v.len()) {
+            return Err("Invalid latch line".to_owned());
+        }
+        let (var, next, init) = if binary {
+            (nb_inputs + i + 1, v[0], v.get(1))
+        } else {
+            (lhs_var(v[0], "latch")?, v[1], v.get(2))
+        };
+        if init.is_some_and(|x| *x != 0) {
+            return Err(format!(
+                "Only a zero initial value is supported for latch {}",
+                i
+            ));
+        }
+        define(var, Signal::from_var(i as u32))?;
+        latches.push(next);
+    }
+    let mut outputs = Vec::new();
+    for _ in 0..nb_outputs + nb_bad {
+        let v = p.read_numbers("output")?;
+        if v.len() != 1 {
+            return Err("Invalid output line".to_owned());
+        }
+        outputs.push(v[0]);
+    }
+    let mut ands = Vec::new();
+    for i in 0..nb_ands {
+        let (lhs, a, b) = if binary {
+            let lhs = 2 * (nb_inputs + nb_latches + i + 1) as u32;
+            let a = lhs
+                .checked_sub(p.read_delta()?)
+                .ok_or("Invalid delta in And gates")?;
+            let b = a
+                .checked_sub(p.read_delta()?)
+                .ok_or("Invalid delta in And gates")?;
+            (lhs, a, b)
+        } else {
+            let v = p.read_numbers("And gate")?;
+            if v.len() != 3 {
+                return Err("Invalid And gate line".to_owned());
+            }
+            (v[0], v[1], v[2])
+        };
+        define(
+            lhs_var(lhs, "And gate")?,
+            Signal::from_var((nb_latches + i) as u32),
+        )?;
+        ands.push((a, b));
+    }
+
+    let lit = |l: u32| -> Result<Signal, String> {
+        vars.get(&((l / 2) as usize))
+            .copied()
+            .map(|s| s ^ (l & 1 != 0))
+            .ok_or_else(|| format!("Literal {} is not defined", l))
+    };
+    for next in latches {
+        ret.add(Gate::dff(lit(next)?, Signal::one(), Signal::zero()));
+    }
+    for (a, b) in ands {
+        ret.add(Gate::and(lit(a)?, lit(b)?));
+    }
+    for o in outputs {
+        ret.add_output(lit(o)?);
+    }
+    ret.try_topo_sort()?;
+    Ok(ret)
+}
+
+/// Builder for the And gates of an AIGER file, with literals as in the file
+struct AigerBuilder {
+    next_var: u32,
+    ands: Vec<(u32, u32, u32)>,
+}
+
+impl AigerBuilder {
+    fn and(&mut self, a: u32, b: u32) -> u32 {
+        if a == 0 || b == 0 || a == b ^ 1 {
+            0
+        } else if a == 1 || a == b {
+            b
+        } else if b == 1 {
+            a
+        } else {
+            let lhs = 2 * self.next_var;
+            self.next_var += 1;
+            self.ands.push((lhs, a.max(b), a.min(b)));
+            lhs
+        }
+    }
+
+    fn or(&mut self, a: u32, b: u32) -> u32 {
+        self.and(a ^ 1, b ^ 1) ^ 1
+    }
+
+    fn xor(&mut self, a: u32, b: u32) -> u32 {
+        let x = self.and(a, b ^ 1);
+        let y = self.and(a ^ 1, b);
+        self.or(x, y)
+    }
+
+    fn mux(&mut self, s: u32, a: u32, b: u32) -> u32 {
+        let x = self.and(s, a);
+        let y = self.and(s ^ 1, b);
+        self.or(x, y)
+    }
+
+    fn maj(&mut self, a: u32, b: u32, c: u32) -> u32 {
+        let ab = self.and(a, b);
+        let a_or_b = self.or(a, b);
+        let x = self.and(c, a_or_b);
+        self.or(ab, x)
+    }
+
+    fn and_all(&mut self, v: &[u32]) -> u32 {
+        v.iter().fold(1, |acc, x| self.and(acc, *x))
+    }
+
+    fn xor_all(&mut self, v: &[u32]) -> u32 {
+        v.iter().fold(0, |acc, x| self.xor(acc, *x))
+    }
+
+    /// Shannon expansion of a Lut, on its last variables first
+    fn lut(&mut self, v: &[u32], values: &[bool]) -> u32 {
+        match v.split_last() {
+            None => values[0] as u32,
+            Some((s, rest)) => {
+                let half = values.len() / 2;
+                let b = self.lut(rest, &values[..half]);
+                let a = self.lut(rest, &values[half..]);
+                self.mux(*s, a, b)
+            }
+        }
+    }
+}
+
+/// Write a network in AIGER format, binary or ASCII
+///
+/// All gates are decomposed into 2-input And gates. Flip-flops become latches, with their enable
+/// and reset implemented in the logic of the next state. AIGER has a single implicit clock, so
+/// all flip-flops must be in the default clock domain. Transparent latches are lowered to
+/// flip-flops with [`Network::lower_latches`].
+pub fn write_aiger<W: Write>(w: &mut W, aig: &Network, binary: bool) {
+    if aig.has_latches() {
+        let mut lowered = aig.clone();
+        lowered.lower_latches();
+        return write_aiger(w, &lowered, binary);
+    }
+    let dffs: Vec<usize> = (0..aig.nb_nodes())
+        .filter(|i| !aig.gate_ref(*i).is_comb())
+        .collect();
+    if dffs
+        .iter()
+        .any(|i| aig.gate(*i).clock() != Some(ClockId::default()))
+    {
+        panic!("AIGER files only support flip-flops on the rising edge of a single clock");
+    }
+    let nb_inputs = aig.nb_inputs() as u32;
+    let mut lits = vec![0; aig.nb_nodes()];
+    for (j, i) in dffs.iter().enumerate() {
+        lits[*i] = 2 * (nb_inputs + 1 + j as u32);
+    }
+    let mut b = AigerBuilder {
+        next_var: nb_inputs + dffs.len() as u32 + 1,
+        ands: Vec::new(),
+    };
+    let lit = |lits: &[u32], s: &Signal| -> u32 {
+        let l = if s.is_input() {
+            2 * (s.input() + 1)
+        } else if s.is_var() {
+            lits[s.var() as usize]
+        } else {
+            0
+        };
+        l ^ (s.is_inverted() as u32)
+    };
+    for i in 0..aig.nb_nodes() {
+        let g = aig.gate(i);
+        if !g.is_comb() {
+            continue;
+        }
+        let v: Vec<u32> = g.dependencies().iter().map(|s| lit(&lits, s)).collect();
+        lits[i] = match g {
+            Gate::Binary(_, BinaryType::And) | Gate::Ternary(_, TernaryType::And) => b.and_all(&v),
+            Gate::Binary(_, BinaryType::Xor) | Gate::Ternary(_, TernaryType::Xor) => b.xor_all(&v),
+            Gate::Ternary(_, TernaryType::Mux) => b.mux(v[0], v[1], v[2]),
+            Gate::Ternary(_, TernaryType::Maj) => b.maj(v[0], v[1], v[2]),
+            Gate::Nary(_, tp) => match tp {
+                NaryType::And => b.and_all(&v),
+                NaryType::Nand => b.and_all(&v) ^ 1,
+                NaryType::Or => {
+                    let inv: Vec<u32> = v.iter().map(|l| l ^ 1).collect();
+                    b.and_all(&inv) ^ 1
+                }
+                NaryType::Nor => {
+                    let inv: Vec<u32> = v.iter().map(|l| l ^ 1).collect();
+                    b.and_all(&inv)
+                }
+                NaryType::Xor => b.xor_all(&v),
+                NaryType::Xnor => b.xor_all(&v) ^ 1,
+            },
+            Gate::Buf(_) => v[0],
+            Gate::Lut(l) => {
+                let values: Vec<bool> = (0..l.lut.num_bits()).map(|m| l.lut.value(m)).collect();
+                b.lut(&v, &values)
+            }
+            Gate::Dff(..) | Gate::Latch(..) => unreachable!(),
+        };
+    }
+    // The next state is !res & (en ? d : q)
+    let mut next = Vec::new();
+    for i in &dffs {
+        let v: Vec<u32> = aig
+            .gate(*i)
+            .dependencies()
+            .iter()
+            .map(|s| lit(&lits, s))
+            .collect();
+        let x = b.mux(v[1], v[0], lits[*i]);
+        next.push(b.and(v[2] ^ 1, x));
+    }
+    let outputs: Vec<u32> = (0..aig.nb_outputs())
+        .map(|o| lit(&lits, &aig.output(o)))
+        .collect();
+
+    let max_var = b.next_var - 1;
+    let header = if binary { "aig" } else { "aag" };
+    writeln!(
+        w,
+        "{} {} {} {} {} {}",
+        header,
+        max_var,
+        nb_inputs,
+        dffs.len(),
+        outputs.len(),
+        b.ands.len()
+    )
+    .unwrap();
+    if !binary {
+        for i in 0..nb_inputs {
+            writeln!(w, "{}", 2 * (i + 1)).unwrap();
+        }
+    }
+    for (j, n) in next.iter().enumerate() {
+        if binary {
+            writeln!(w, "{}", n).unwrap();
+        } else {
+            writeln!(w, "{} {}", 2 * (nb_inputs + 1 + j as u32), n).unwrap();
+        }
+    }
+    for o in outputs {
+        writeln!(w, "{}", o).unwrap();
+    }
+    for (lhs, a, c) in b.ands {
+        if binary {
+            write_delta(w, lhs - a);
+            write_delta(w, a - c);
+        } else {
+            writeln!(w, "{} {} {}", lhs, a, c).unwrap();
+        }
+    }
+}
+
+/// Write a variable-length encoded delta of the binary format
+fn write_delta<W: Write>(w: &mut W, mut x: u32) {
+    while x & !0x7f != 0 {
+        w.write_all(&[(x & 0x7f) as u8 | 0x80]).unwrap();
+        x >>= 7;
+    }
+    w.write_all(&[x as u8]).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_aiger, write_aiger};
+    use crate::network::generators::adder;
+    use crate::network::NaryType;
+    use crate::sim::simulate;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_read_ascii() {
+        // Toggle flip-flop with an enable, from the AIGER documentation
+        let example = "aag 7 2 1 2 4\n2\n4\n6 14\n6\n7\n8 2 6\n10 3 7\n12 9 11\n14 4 12\n";
+        let aig = read_aiger(example.as_bytes()).unwrap();
+        assert_eq!(aig.nb_inputs(), 2);
+        assert_eq!(aig.nb_outputs(), 2);
+        assert_eq!(aig.nb_nodes(), 5);
+        assert!(!aig.is_comb());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(read_aiger("aag 1 1 0 1\n2\n2\n".as_bytes()).is_err());
+        assert!(read_aiger("abc 1 1 0 1 0\n2\n2\n".as_bytes()).is_err());
+        assert!(read_aiger("aag 1 1 0 1 0\n2\n4\n".as_bytes()).is_err());
+        assert!(read_aiger("aag 2 1 1 0 0\n2\n4 2 1\n".as_bytes()).is_err());
+        assert!(read_aiger("aag 3 1 0 1 1\n2\n6\n6 2 6\n".as_bytes()).is_err());
+        assert!(read_aiger("aig 3 2 0 1 1\n6\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut aig = Network::new();
+        let i: Vec<Signal> = (0..4).map(|_| aig.add_input()).collect();
+        let x = aig.add(Gate::mux(i[0], i[1], !i[2]));
+        let y = aig.add(Gate::maj(x, !i[3], i[1]));
+        let z = aig.add(Gate::xor3(y, i[0], i[2]));
+        let d = aig.add(Gate::dff(z, i[1], i[3]));
+        let e = aig.add(Gate::dff(!d, Signal::one(), Signal::zero()));
+        let n = aig.add(Gate::Nary([e, i[0], !d].into(), NaryType::Nor));
+        aig.add_output(n);
+        aig.add_output(!e);
+        aig.add_output(Signal::one());
+        aig.add_output(i[2]);
+        let adder = adder::ripple_carry(4);
+
+        let patterns: Vec<Vec<Vec<bool>>> = (0..32u64)
+            .map(|k| {
+                (0..6u64)
+                    .map(|t| {
+                        (0..16)
+                            .map(|j| ((k * 7919 + t * 31) >> j) & 1 != 0)
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        for binary in [false, true] {
+            for a in [&aig, &adder] {
+                let mut buf = Vec::new();
+                write_aiger(&mut buf, a, binary);
+                let b = read_aiger(buf.as_slice()).unwrap();
+                assert_eq!(b.nb_inputs(), a.nb_inputs());
+                assert_eq!(b.nb_outputs(), a.nb_outputs());
+                for p in &patterns {
+                    let p: Vec<Vec<bool>> = p.iter().map(|v| v[..a.nb_inputs()].to_vec()).collect();
+                    assert_eq!(simulate(a, &p), simulate(&b, &p));
+                }
+            }
+        }
+    }
+}
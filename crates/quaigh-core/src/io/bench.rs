@@ -0,0 +1,1421 @@
+//! IO for .bench (ISCAS) files
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::iter::zip;
+use std::str::FromStr;
+
+use volute::Lut;
+
+use crate::network::{BinaryType, NameTable, NaryType, SourceLocation, SourceMap, TernaryType};
+use crate::{Gate, Network, QuaighError, Signal};
+
+use super::utils::{get_inverted_signals, SignalNamer};
+
+fn build_name_to_sig(
+    statements: &Vec<Vec<String>>,
+    inputs: &Vec<String>,
+) -> Result<HashMap<String, Signal>, String> {
+    let mut ret = HashMap::new();
+    for (i, name) in inputs.iter().enumerate() {
+        let present = ret
+            .insert(name.clone(), Signal::from_input(i as u32))
+            .is_some();
+        if present {
+            return Err(format!("{} is defined twice", name));
+        }
+    }
+    for (i, s) in statements.iter().enumerate() {
+        let present = ret
+            .insert(s[0].to_string(), Signal::from_var(i as u32))
+            .is_some();
+        if present {
+            return Err(format!("{} is defined twice", s[0]));
+        }
+    }
+
+    // ABC-style naming for constant signals
+    if !ret.contains_key("vdd") {
+        ret.insert("vdd".to_string(), Signal::one());
+    }
+    if !ret.contains_key("gnd") {
+        ret.insert("gnd".to_string(), Signal::zero());
+    }
+    Ok(ret)
+}
+
+/// Check that the number of hexadecimal digits matches the number of Lut inputs
+///
+/// This is checked before building the Lut so that a malformed file cannot request huge allocations.
+fn check_lut_size(hex: &str, nb_inputs: usize) -> Result<(), String> {
+    let expected = if nb_inputs <= 2 {
+        Some(1)
+    } else if nb_inputs < usize::BITS as usize {
+        Some(1usize << (nb_inputs - 2))
+    } else {
+        None
+    };
+    if expected != Some(hex.len()) {
+        return Err(format!(
+            "Lut with {} inputs has an invalid truth table 0x{}",
+            nb_inputs, hex
+        ));
+    }
+    Ok(())
+}
+
+/// Dialect of .bench files, which differ in the way flip-flops are written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BenchDialect {
+    /// Guess the dialect from the file, with a warning when two-argument flip-flops are found
+    #[default]
+    Auto,
+    /// ISCAS style, with the data input only: `DFF(d)`
+    Iscas,
+    /// ABC style, with an optional initial value: `DFF(d, init)`
+    Abc,
+    /// ITC99 style, with an optional clock: `DFF(clk, d)`
+    Itc99,
+}
+
+impl fmt::Display for BenchDialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchDialect::Auto => write!(f, "auto"),
+            BenchDialect::Iscas => write!(f, "iscas"),
+            BenchDialect::Abc => write!(f, "abc"),
+            BenchDialect::Itc99 => write!(f, "itc99"),
+        }
+    }
+}
+
+impl FromStr for BenchDialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(BenchDialect::Auto),
+            "iscas" => Ok(BenchDialect::Iscas),
+            "abc" => Ok(BenchDialect::Abc),
+            "itc99" => Ok(BenchDialect::Itc99),
+            _ => Err(format!(
+                "Unknown .bench dialect {}, expected auto, iscas, abc or itc99",
+                s
+            )),
+        }
+    }
+}
+
+/// Policy for gates with fewer inputs than their type requires, such as `MUX(s, a)`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BenchArity {
+    /// Reject the file, reporting the line of the gate
+    #[default]
+    Strict,
+    /// Tie the missing inputs to a constant, with a warning
+    ///
+    /// Missing inputs are tied to zero, except for the enable of `DFFRSE` and the active-low set
+    /// and reset of `DFFSR` which are tied to one.
+    /// For example, `MUX(s, a)` becomes `MUX(s, a, gnd)`, `MAJ(a, b)` becomes `AND(a, b)`,
+    /// and `DFFRSE(d, r)` a flip-flop with reset and no enable.
+    Pad,
+}
+
+impl fmt::Display for BenchArity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BenchArity::Strict => write!(f, "strict"),
+            BenchArity::Pad => write!(f, "pad"),
+        }
+    }
+}
+
+impl FromStr for BenchArity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "strict" => Ok(BenchArity::Strict),
+            "pad" => Ok(BenchArity::Pad),
+            _ => Err(format!(
+                "Unknown arity policy {}, expected strict or pad",
+                s
+            )),
+        }
+    }
+}
+
+/// Flip-flop with set and reset, written `DFFSR(d, set, reset)`
+///
+/// The type may have a suffix for the polarities of the set and reset, such as `DFFSR_PN` for an
+/// active-high set and an active-low reset, and a `_QN` suffix for a complemented output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DffsrType {
+    /// Whether the set is active-low
+    set_low: bool,
+    /// Whether the reset is active-low
+    reset_low: bool,
+    /// Whether the output is complemented
+    qn: bool,
+}
+
+/// Parse a flip-flop type such as `DFFSR_NP_QN`; None if this is not a set/reset flip-flop
+fn parse_dffsr_type(tp: &str) -> Option<DffsrType> {
+    let rest = tp.strip_prefix("DFFSR")?;
+    let (polarities, qn) = match rest.strip_suffix("_QN") {
+        Some(r) => (r, true),
+        None => (rest, false),
+    };
+    let (set_low, reset_low) = match polarities {
+        "" | "_PP" => (false, false),
+        "_PN" => (false, true),
+        "_NP" => (true, false),
+        "_NN" => (true, true),
+        _ => return None,
+    };
+    Some(DffsrType {
+        set_low,
+        reset_low,
+        qn,
+    })
+}
+
+/// Number of inputs expected for a gate type, if fixed
+fn expected_arity(tp: &str) -> Option<usize> {
+    match tp {
+        "DFF" | "BUF" | "BUFF" | "NOT" => Some(1),
+        "VDD" | "VSS" | "GND" => Some(0),
+        "MUX" | "MAJ" => Some(3),
+        "DFFRSE" => Some(4),
+        _ if parse_dffsr_type(tp).is_some() => Some(3),
+        _ => None,
+    }
+}
+
+/// Constant used to pad a missing input of a gate
+fn padding_value(tp: &str, i: usize) -> Signal {
+    if tp == "DFFRSE" && i == 3 {
+        return Signal::one();
+    }
+    match parse_dffsr_type(tp) {
+        // Inactive set or reset
+        Some(t) if i == 1 => Signal::from(t.set_low),
+        Some(t) if i == 2 => Signal::from(t.reset_low),
+        _ => Signal::zero(),
+    }
+}
+
+/// Parse the initial value of a flip-flop; None if this is not an initial value
+///
+/// Unknown initial values (2, 3 or x, as written by ABC) are returned as None in the inner option.
+fn parse_init_value(s: &str) -> Option<Option<bool>> {
+    match s {
+        "0" | "gnd" | "vss" => Some(Some(false)),
+        "1" | "vdd" => Some(Some(true)),
+        "2" | "3" | "x" | "X" => Some(None),
+        _ => None,
+    }
+}
+
+/// Returns whether a signal is only used as the first argument of flip-flops that may have a clock
+fn is_clock_like(name: &str, statements: &[Vec<String>], outputs: &[String]) -> bool {
+    if outputs.iter().any(|o| o == name) {
+        return false;
+    }
+    statements.iter().all(|s| {
+        let deps = &s[2..];
+        let tp = s[1].to_uppercase();
+        let may_have_clock = (tp == "DFF" && deps.len() == 2)
+            || (tp == "DFFRSE" && deps.len() == 5)
+            || (parse_dffsr_type(&tp).is_some() && deps.len() == 4);
+        let data_deps = if may_have_clock { &deps[1..] } else { deps };
+        !data_deps.iter().any(|d| d == name)
+    })
+}
+
+/// Rewrite the flip-flops of the file to the ISCAS form, depending on the dialect
+///
+/// Initial values are returned for each statement, and clocks are checked to be a single global clock.
+fn resolve_flip_flops(
+    statements: &mut [Vec<String>],
+    inputs: &[String],
+    outputs: &[String],
+    dialect: BenchDialect,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<bool>, String> {
+    let mut init = vec![false; statements.len()];
+    let mut clock: Option<(String, String)> = None;
+    let mut warned = false;
+    for i in 0..statements.len() {
+        let tp = statements[i][1].to_uppercase();
+        let nb_deps = statements[i].len() - 2;
+        let has_clock = match (tp.as_str(), nb_deps) {
+            ("DFF", 2) => {
+                let (first, second) = (&statements[i][2], &statements[i][3]);
+                let is_init = parse_init_value(second).is_some();
+                let is_clock = match dialect {
+                    BenchDialect::Iscas => {
+                        return Err(format!(
+                            "DFF gate {} has 2 inputs, but the iscas dialect only allows DFF(d)",
+                            statements[i][0]
+                        ));
+                    }
+                    BenchDialect::Abc => {
+                        if !is_init {
+                            return Err(format!(
+                                "DFF gate {} has invalid initial value {}",
+                                statements[i][0], second
+                            ));
+                        }
+                        false
+                    }
+                    BenchDialect::Itc99 => true,
+                    BenchDialect::Auto => {
+                        let is_clock = if is_init {
+                            false
+                        } else if inputs.contains(first)
+                            && is_clock_like(first, statements, outputs)
+                        {
+                            true
+                        } else {
+                            return Err(format!(
+                                "Cannot tell whether DFF gate {} is DFF(d, init) or DFF(clk, d); specify the dialect",
+                                statements[i][0]
+                            ));
+                        };
+                        if !warned {
+                            warnings.push(if is_clock {
+                                "Interpreting two-input DFF gates as DFF(clk, d) (itc99 dialect)"
+                            } else {
+                                "Interpreting two-input DFF gates as DFF(d, init) (abc dialect)"
+                            }
+                            .to_owned());
+                            warned = true;
+                        }
+                        is_clock
+                    }
+                };
+                if !is_clock {
+                    match parse_init_value(second).unwrap() {
+                        Some(v) => init[i] = v,
+                        None => warnings.push(format!(
+                            "Unknown initial value {} for DFF gate {}, using 0",
+                            second, statements[i][0]
+                        )),
+                    }
+                    statements[i].truncate(3);
+                }
+                is_clock
+            }
+            (t, n) if (t == "DFFRSE" && n == 5) || (parse_dffsr_type(t).is_some() && n == 4) => {
+                if dialect == BenchDialect::Iscas || dialect == BenchDialect::Abc {
+                    return Err(format!(
+                        "{} gate {} has {} inputs, but the {} dialect does not allow a clock",
+                        statements[i][1], statements[i][0], nb_deps, dialect
+                    ));
+                }
+                true
+            }
+            _ => false,
+        };
+        if has_clock {
+            let name = statements[i][0].clone();
+            let clk = statements[i].remove(2);
+            if !inputs.contains(&clk) {
+                return Err(format!(
+                    "Clock {} of flip-flop {} is not a primary input",
+                    clk, name
+                ));
+            }
+            match &clock {
+                Some((other_clk, other_name)) if *other_clk != clk => {
+                    return Err(format!(
+                        "Flip-flops {} and {} use different clocks {} and {}; only a single global clock is supported",
+                        other_name, name, other_clk, clk
+                    ));
+                }
+                Some(_) => (),
+                None => clock = Some((clk, name)),
+            }
+        }
+    }
+    Ok(init)
+}
+
+fn check_statement(
+    statement: &[String],
+    line: usize,
+    name_to_sig: &HashMap<String, Signal>,
+    arity: BenchArity,
+    warnings: &mut Vec<String>,
+) -> Result<(), QuaighError> {
+    let deps = &statement[2..];
+    for dep in deps {
+        if !name_to_sig.contains_key(dep) {
+            return Err(QuaighError::parse_at(
+                line,
+                format!("gate input {dep} is not generated anywhere"),
+            ));
+        }
+    }
+    if let Some(nb) = expected_arity(statement[1].to_uppercase().as_str()) {
+        let msg = format!(
+            "{} gate {} has {} inputs, expected {}",
+            statement[1],
+            statement[0],
+            deps.len(),
+            nb
+        );
+        if deps.len() > nb || (deps.len() < nb && arity == BenchArity::Strict) {
+            return Err(QuaighError::parse_at(line, msg));
+        }
+        if deps.len() < nb {
+            warnings.push(format!(
+                "Line {}: {}; tying the missing inputs to a constant",
+                line, msg
+            ));
+        }
+    }
+    if statement[1].starts_with("LUT 0x") {
+        check_lut_size(&statement[1][6..], deps.len())
+            .map_err(|e| QuaighError::parse_at(line, e))?;
+    }
+    Ok(())
+}
+
+/// Signals of the gate inputs, with the missing ones padded with a constant
+fn gate_dependencies(
+    statement: &Vec<String>,
+    name_to_sig: &HashMap<String, Signal>,
+) -> Box<[Signal]> {
+    let tp = statement[1].to_uppercase();
+    let mut ret: Vec<Signal> = statement[2..].iter().map(|n| name_to_sig[n]).collect();
+    if let Some(nb) = expected_arity(&tp) {
+        for i in ret.len()..nb {
+            ret.push(padding_value(&tp, i));
+        }
+    }
+    ret.into()
+}
+
+/// Flip-flop with a synchronous set and reset, as a flip-flop with reset and additional gates
+///
+/// The reset takes priority over the set, and both over the enable: the set forces the data and
+/// the enable to one.
+fn dff_with_set(aig: &mut Network, d: Signal, en: Signal, set: Signal, reset: Signal) -> Gate {
+    let d = !aig.and(!d, !set);
+    let en = !aig.and(!en, !set);
+    Gate::dff(d, en, reset)
+}
+
+/// Gate for an Nary function, which is a constant if it has no input
+fn nary_gate(sigs: Box<[Signal]>, tp: NaryType) -> Gate {
+    if !sigs.is_empty() {
+        return Gate::Nary(sigs.into(), tp);
+    }
+    let one = matches!(tp, NaryType::And | NaryType::Nor | NaryType::Xnor);
+    Gate::Buf(Signal::from(one))
+}
+
+fn network_from_statements(
+    statements: &mut Vec<Vec<String>>,
+    lines: &[usize],
+    inputs: &Vec<String>,
+    outputs: &Vec<String>,
+    dialect: BenchDialect,
+    arity: BenchArity,
+    warnings: &mut Vec<String>,
+) -> Result<(Network, NameTable, Box<[Signal]>), QuaighError> {
+    let mut ret = Network::new();
+    ret.add_inputs(inputs.len());
+
+    // Bring flip-flops to a single form
+    let init = resolve_flip_flops(statements, inputs, outputs, dialect, warnings)
+        .map_err(QuaighError::parse)?;
+
+    // Compute a mapping between the two
+    let mut name_to_sig = build_name_to_sig(statements, inputs).map_err(QuaighError::parse)?;
+
+    // Flip-flops start at zero: those initialized at one are stored inverted, and complemented
+    // outputs are the inverted flip-flops
+    for (s, init) in zip(statements.iter(), init.iter()) {
+        let qn = parse_dffsr_type(&s[1].to_uppercase()).is_some_and(|t| t.qn);
+        if *init ^ qn {
+            let sig = name_to_sig[&s[0]];
+            name_to_sig.insert(s[0].clone(), !sig);
+        }
+    }
+
+    // Check everything
+    for (statement, line) in zip(statements.iter(), lines) {
+        check_statement(statement, *line, &name_to_sig, arity, warnings)?;
+    }
+    for output in outputs {
+        if !name_to_sig.contains_key(output) {
+            return Err(QuaighError::parse(format!(
+                "Output {output} is not generated anywhere"
+            )));
+        }
+    }
+
+    // Setup the variables based on the mapping
+    // Flip-flops with a set need additional gates, created once all statements have their node
+    let mut with_set = Vec::new();
+    for (i, (s, init)) in zip(statements.iter(), init.iter()).enumerate() {
+        let sigs: Box<[Signal]> = gate_dependencies(s, &name_to_sig);
+        let tp = s[1].to_uppercase();
+        if let Some(t) = parse_dffsr_type(&tp) {
+            let (set, reset) = (sigs[1] ^ t.set_low, sigs[2] ^ t.reset_low);
+            with_set.push((i, sigs[0], Signal::one(), set, reset));
+            ret.add(Gate::dff(Signal::zero(), Signal::one(), Signal::zero()));
+            continue;
+        }
+        match tp.as_str() {
+            "DFF" => {
+                ret.add(Gate::dff(sigs[0] ^ *init, Signal::one(), Signal::zero()));
+            }
+            "DFFRSE" => {
+                with_set.push((i, sigs[0], sigs[3], sigs[2], sigs[1]));
+                ret.add(Gate::dff(Signal::zero(), Signal::one(), Signal::zero()));
+            }
+            "BUF" | "BUFF" => {
+                ret.add(Gate::Buf(sigs[0]));
+            }
+            "NOT" => {
+                ret.add(Gate::Buf(!sigs[0]));
+            }
+            "VDD" => {
+                ret.add(Gate::Buf(Signal::one()));
+            }
+            "VSS" | "GND" => {
+                ret.add(Gate::Buf(Signal::zero()));
+            }
+            "AND" => {
+                ret.add(nary_gate(sigs, NaryType::And));
+            }
+            "NAND" => {
+                ret.add(nary_gate(sigs, NaryType::Nand));
+            }
+            "OR" => {
+                ret.add(nary_gate(sigs, NaryType::Or));
+            }
+            "NOR" => {
+                ret.add(nary_gate(sigs, NaryType::Nor));
+            }
+            "XOR" => {
+                ret.add(nary_gate(sigs, NaryType::Xor));
+            }
+            "XNOR" => {
+                ret.add(nary_gate(sigs, NaryType::Xnor));
+            }
+            "MUX" => {
+                ret.add(Gate::mux(sigs[0], sigs[1], sigs[2]));
+            }
+            "MAJ" => {
+                ret.add(Gate::maj(sigs[0], sigs[1], sigs[2]));
+            }
+            _ => {
+                if s[1].starts_with("LUT 0x") {
+                    let lut = Lut::from_hex_string(sigs.len(), &s[1][6..]).map_err(|_| {
+                        QuaighError::parse_at(lines[i], format!("invalid Lut truth table {}", s[1]))
+                    })?;
+                    ret.add(Gate::lut(sigs.as_ref(), lut));
+                } else {
+                    return Err(QuaighError::parse_at(
+                        lines[i],
+                        format!("unknown gate type {}", s[1]),
+                    ));
+                }
+            }
+        }
+    }
+    for (i, d, en, set, reset) in with_set {
+        let dff = dff_with_set(&mut ret, d, en, set, reset);
+        ret.replace(i, dff);
+    }
+    for o in outputs {
+        ret.add_output(name_to_sig[o]);
+    }
+    let translation = ret
+        .try_topo_sort()
+        .map_err(|e| match ret.find_comb_loop() {
+            // Nodes are still in the order of the statements
+            Some(l) => {
+                let gates: Vec<String> = l
+                    .iter()
+                    .rev()
+                    .filter(|i| **i < statements.len())
+                    .map(|i| format!("{} (line {})", statements[*i][0], lines[*i]))
+                    .collect();
+                QuaighError::parse(format!(
+                    "Combinatorial loop through gates {}",
+                    gates.join(", ")
+                ))
+            }
+            None => e,
+        })?;
+    ret.check();
+
+    let mut names = NameTable::new();
+    for (i, name) in inputs.iter().enumerate() {
+        names.set_input_name(i, name);
+    }
+    for (i, s) in statements.iter().enumerate() {
+        names.set_node_name(i, &s[0]);
+    }
+    for (i, name) in outputs.iter().enumerate() {
+        names.set_output_name(i, name);
+    }
+    names.remap(&translation);
+    Ok((ret, names, translation))
+}
+
+/// Read a network in .bench format, as used by the ISCAS benchmarks
+///
+/// These files describe the design with simple statements like:
+/// ```text
+///     # This is a comment
+///     INPUT(i0)
+///     INPUT(i1)
+///     x0 = AND(i0, i1)
+///     x1 = NAND(x0, i1)
+///     x2 = OR(x0, i0)
+///     x3 = NOR(i0, x1)
+///     x4 = XOR(x3, x2)
+///     x5 = BUF(x4)
+///     x6 = NOT(x5)
+///     x7 = gnd
+///     x8 = vdd
+///     OUTPUT(x0)
+/// ```
+///
+/// Flip-flops may be written with an initial value or a clock as a second argument, depending on the tool
+/// that wrote the file: the dialect is guessed with a warning. Use [`read_bench_with_dialect`] to choose it.
+///
+/// Flip-flops with reset and enable are written `DFFRSE(d, reset, set, enable)`, with synchronous
+/// reset and set taking priority over the enable, as for [`Gate::Dff`]. The reset takes priority
+/// over the set.
+///
+/// Flip-flops with set and reset are written `DFFSR(d, set, reset)`, with the same priorities.
+/// A suffix gives the polarities of the set and reset, for example `DFFSR_PN(d, set, reset_n)`
+/// for an active-low reset, and a `_QN` suffix denotes a complemented output, as in
+/// `qn = DFFSR_NN_QN(d, set_n, reset_n)`. They are mapped to [`Gate::Dff`] and additional gates.
+pub fn read_bench<R: Read>(r: R) -> Result<Network, QuaighError> {
+    read_bench_with_dialect(r, BenchDialect::Auto)
+}
+
+/// Read a network in .bench format, with a given dialect for flip-flops
+///
+/// Warnings about the interpretation of the file are printed on the standard error.
+pub fn read_bench_with_dialect<R: Read>(
+    r: R,
+    dialect: BenchDialect,
+) -> Result<Network, QuaighError> {
+    Ok(read_bench_with_names_and_dialect(r, dialect)?.0)
+}
+
+/// Read a network in .bench format, with the names of its signals
+pub fn read_bench_with_names<R: Read>(r: R) -> Result<(Network, NameTable), QuaighError> {
+    read_bench_with_names_and_dialect(r, BenchDialect::Auto)
+}
+
+/// Read a network in .bench format, with the names of its signals and a given dialect for flip-flops
+///
+/// Warnings about the interpretation of the file are printed on the standard error.
+pub fn read_bench_with_names_and_dialect<R: Read>(
+    r: R,
+    dialect: BenchDialect,
+) -> Result<(Network, NameTable), QuaighError> {
+    read_bench_with_options(r, dialect, BenchArity::Strict)
+}
+
+/// Read a network in .bench format, with the names of its signals, a given dialect for flip-flops
+/// and a given policy for gates with missing inputs
+///
+/// Gates without inputs are always accepted when their value is well-defined: `AND()` is one,
+/// and `OR()` is zero.
+/// Warnings about the interpretation of the file are printed on the standard error.
+pub fn read_bench_with_options<R: Read>(
+    r: R,
+    dialect: BenchDialect,
+    arity: BenchArity,
+) -> Result<(Network, NameTable), QuaighError> {
+    let mut warnings = Vec::new();
+    let ret = parse_bench(r, dialect, arity, None, &mut warnings);
+    for w in warnings {
+        eprintln!("Warning: {}", w);
+    }
+    ret.map(|(aig, names, _)| (aig, names))
+}
+
+/// Read a network in .bench format, with the names of its signals and the location of its gates
+///
+/// Each gate is associated with the line of the statement that created it in the file, recorded
+/// under the given file name. Options are the same as for [`read_bench_with_options`].
+pub fn read_bench_with_locations<R: Read>(
+    r: R,
+    file_name: &str,
+    dialect: BenchDialect,
+    arity: BenchArity,
+) -> Result<(Network, NameTable, SourceMap), QuaighError> {
+    let mut warnings = Vec::new();
+    let ret = parse_bench(r, dialect, arity, Some(file_name), &mut warnings);
+    for w in warnings {
+        eprintln!("Warning: {}", w);
+    }
+    let (aig, names, locations) = ret?;
+    Ok((aig, names, locations.unwrap()))
+}
+
+/// Parse a .bench file, collecting warnings
+///
+/// Gate locations are only recorded if a file name is given.
+fn parse_bench<R: Read>(
+    r: R,
+    dialect: BenchDialect,
+    arity: BenchArity,
+    file_name: Option<&str>,
+    warnings: &mut Vec<String>,
+) -> Result<(Network, NameTable, Option<SourceMap>), QuaighError> {
+    let mut statements = Vec::new();
+    let mut lines = Vec::new();
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    for (line, l) in BufReader::new(r).lines().enumerate() {
+        if let Ok(s) = l {
+            let t = s.trim().to_owned();
+            if t.is_empty() || t.starts_with('#') {
+                continue;
+            }
+            if !t.contains("=") {
+                let parts: Vec<_> = t
+                    .split(&['(', ')'])
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                if parts.len() != 2 {
+                    return Err(QuaighError::parse_at(
+                        line + 1,
+                        format!("invalid statement {}", t),
+                    ));
+                }
+                if ["INPUT", "PINPUT"].contains(&parts[0]) {
+                    inputs.push(parts[1].to_string());
+                } else if ["OUTPUT", "POUTPUT"].contains(&parts[0]) {
+                    outputs.push(parts[1].to_string());
+                } else {
+                    return Err(QuaighError::parse_at(
+                        line + 1,
+                        format!("unknown keyword {}", parts[0]),
+                    ));
+                }
+            } else {
+                let parts: Vec<_> = t
+                    .split(&['=', '(', ',', ')'])
+                    .map(|s| s.trim().to_owned())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                if parts.len() < 2 {
+                    return Err(QuaighError::parse_at(
+                        line + 1,
+                        format!("invalid statement {}", t),
+                    ));
+                }
+                statements.push(parts);
+                lines.push(line + 1);
+            }
+        } else {
+            return Err(QuaighError::parse_at(line + 1, "error during file IO"));
+        }
+    }
+    let (aig, names, translation) = network_from_statements(
+        &mut statements,
+        &lines,
+        &inputs,
+        &outputs,
+        dialect,
+        arity,
+        warnings,
+    )?;
+    let locations = file_name.map(|f| {
+        let mut locations = SourceMap::new();
+        let file = locations.add_file(f);
+        for (i, line) in lines.iter().enumerate() {
+            let line = *line as u32;
+            locations.set_node_location(i, SourceLocation { file, line });
+        }
+        locations.remap(&translation);
+        locations
+    });
+    Ok((aig, names, locations))
+}
+
+/// Write a network in .bench format, as used by the ISCAS benchmarks
+///
+/// These files describe the design with simple statements like:
+/// ```text
+///     # This is a comment
+///     INPUT(i0)
+///     INPUT(i1)
+///     x0 = AND(i0, i1)
+///     x1 = NAND(x0, i1)
+///     x2 = OR(x0, i0)
+///     x3 = NOR(i0, x1)
+///     x4 = XOR(x3, x2)
+///     x5 = BUF(x4)
+///     x6 = NOT(x5)
+///     x7 = gnd
+///     x8 = vdd
+///     OUTPUT(x0)
+/// ```
+pub fn write_bench<W: Write>(w: &mut W, aig: &Network) {
+    write_bench_with_names(w, aig, &NameTable::new());
+}
+
+/// Write a network in .bench format, with the names of its signals
+///
+/// Unnamed signals use generated names. Outputs whose name is not the name of their signal are
+/// driven by an additional buffer.
+pub fn write_bench_with_names<W: Write>(w: &mut W, aig: &Network, names: &NameTable) {
+    write_bench_with_options(w, aig, names, false);
+}
+
+/// Equivalent gate without inverted inputs, if it can be expressed in .bench format
+///
+/// Inversions are folded in Xor and Lut gates, and in And/Or gates whose inputs are all inverted.
+fn folded_gate(g: &Gate) -> Option<Gate> {
+    use Gate::*;
+    let deps = g.dependencies();
+    let is_inv = |s: &Signal| s.is_inverted() && !s.is_constant();
+    if !deps.iter().any(is_inv) {
+        return None;
+    }
+    let positive: Box<[Signal]> = deps
+        .iter()
+        .map(|s| if is_inv(s) { !*s } else { *s })
+        .collect();
+    let parity = deps.iter().filter(|s| is_inv(s)).count() % 2 != 0;
+    let all_inv = deps.iter().all(is_inv);
+    let tp = match g {
+        Binary(_, BinaryType::Xor) | Ternary(_, TernaryType::Xor) | Nary(_, NaryType::Xor) => {
+            if parity {
+                NaryType::Xnor
+            } else {
+                NaryType::Xor
+            }
+        }
+        Nary(_, NaryType::Xnor) => {
+            if parity {
+                NaryType::Xor
+            } else {
+                NaryType::Xnor
+            }
+        }
+        Binary(_, BinaryType::And) | Ternary(_, TernaryType::And) | Nary(_, NaryType::And)
+            if all_inv =>
+        {
+            NaryType::Nor
+        }
+        Nary(_, NaryType::Nand) if all_inv => NaryType::Or,
+        Nary(_, NaryType::Or) if all_inv => NaryType::Nand,
+        Nary(_, NaryType::Nor) if all_inv => NaryType::And,
+        Lut(lut) => {
+            let mask = deps
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| is_inv(s))
+                .fold(0, |m, (i, _)| m | (1 << i));
+            let mut folded = volute::Lut::zero(lut.lut.num_vars());
+            for m in 0..lut.lut.num_bits() {
+                if lut.lut.value(m ^ mask) {
+                    folded.set_bit(m);
+                }
+            }
+            return Some(Gate::lut(&positive, folded));
+        }
+        _ => return None,
+    };
+    Some(Nary(positive.into(), tp))
+}
+
+/// Write a network in .bench format, with the names of its signals and optionally folding the
+/// inversions into the gates
+///
+/// By default, each inverted signal is computed by an additional `NOT` gate. With
+/// `fold_inversions`, inverted inputs of Xor and Lut gates, and of And/Or gates whose inputs are
+/// all inverted, are folded into the gate instead.
+pub fn write_bench_with_options<W: Write>(
+    w: &mut W,
+    aig: &Network,
+    names: &NameTable,
+    fold_inversions: bool,
+) {
+    let namer = SignalNamer::new(aig, names);
+    let sig_to_string = |s: &Signal| namer.name(s);
+    let output_names = namer.output_names();
+    writeln!(w, "# .bench (ISCAS) file").unwrap();
+    writeln!(w, "# Generated by quaigh").unwrap();
+    for i in 0..aig.nb_inputs() {
+        writeln!(w, "INPUT({})", sig_to_string(&aig.input(i))).unwrap();
+    }
+    writeln!(w).unwrap();
+    for name in &output_names {
+        writeln!(w, "OUTPUT({})", name).unwrap();
+    }
+    writeln!(w).unwrap();
+    for i in 0..aig.nb_nodes() {
+        use Gate::*;
+        let gate = aig.gate(i);
+        let folded = if fold_inversions {
+            folded_gate(&gate)
+        } else {
+            None
+        };
+        let g = folded.as_ref().unwrap_or(&gate);
+        let rep = g
+            .dependencies()
+            .iter()
+            .map(sig_to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(w, "{} = ", sig_to_string(&aig.node(i))).unwrap();
+        match g {
+            Binary(_, BinaryType::And) | Ternary(_, TernaryType::And) => {
+                writeln!(w, "AND({})", rep).unwrap();
+            }
+            Binary(_, BinaryType::Xor) | Ternary(_, TernaryType::Xor) => {
+                writeln!(w, "XOR({})", rep).unwrap();
+            }
+            Nary(_, tp) => match tp {
+                NaryType::And => writeln!(w, "AND({})", rep).unwrap(),
+                NaryType::Or => writeln!(w, "OR({})", rep).unwrap(),
+                NaryType::Nand => writeln!(w, "NAND({})", rep).unwrap(),
+                NaryType::Nor => writeln!(w, "NOR({})", rep).unwrap(),
+                NaryType::Xor => writeln!(w, "XOR({})", rep).unwrap(),
+                NaryType::Xnor => writeln!(w, "XNOR({})", rep).unwrap(),
+            },
+            Dff([d, en, res], clock) => {
+                if !clock.is_default() {
+                    panic!("Flip-flops in several clock domains are not supported in .bench files");
+                }
+                if *en != Signal::one() || *res != Signal::zero() {
+                    // Same argument order and reset priority as the reader
+                    writeln!(
+                        w,
+                        "DFFRSE({}, {}, gnd, {})",
+                        sig_to_string(d),
+                        sig_to_string(res),
+                        sig_to_string(en)
+                    )
+                    .unwrap();
+                } else {
+                    writeln!(w, "DFF({})", sig_to_string(d)).unwrap();
+                }
+            }
+            Latch(_) => panic!("Transparent latches are not supported in .bench files"),
+            Ternary(_, TernaryType::Mux) => {
+                writeln!(w, "MUX({})", rep).unwrap();
+            }
+            Ternary(_, TernaryType::Maj) => {
+                writeln!(w, "MAJ({})", rep).unwrap();
+            }
+            Buf(s) => {
+                if s.is_constant() {
+                    writeln!(w, "{}", sig_to_string(s)).unwrap();
+                } else if s.is_inverted() {
+                    writeln!(w, "NOT({})", sig_to_string(&!s)).unwrap();
+                } else {
+                    writeln!(w, "BUF({})", rep).unwrap();
+                }
+            }
+            Lut(lut) => {
+                writeln!(w, "LUT 0x{}({})", lut.lut.to_hex_string(), rep).unwrap();
+            }
+        }
+    }
+
+    let signals_with_inv =
+        get_inverted_signals(aig, |g| fold_inversions && folded_gate(g).is_some());
+    for s in signals_with_inv {
+        writeln!(w, "{} = NOT({})", sig_to_string(&!s), sig_to_string(&s)).unwrap();
+    }
+
+    // Buffers for the output names
+    for (i, name) in output_names.iter().enumerate() {
+        let s = aig.output(i);
+        if *name == sig_to_string(&s) {
+            continue;
+        }
+        if s.is_constant() {
+            writeln!(w, "{} = {}", name, sig_to_string(&s)).unwrap();
+        } else {
+            writeln!(w, "{} = BUF({})", name, sig_to_string(&s)).unwrap();
+        }
+    }
+}
+
+mod test {
+    #[test]
+    fn test_basic_readwrite() {
+        use std::io::BufWriter;
+
+        let example = "# .bench (ISCAS) file
+# Generated by quaigh
+INPUT(i0)
+INPUT(i1)
+
+OUTPUT(x0)
+OUTPUT(x1)
+OUTPUT(x2)
+OUTPUT(x3)
+OUTPUT(x4)
+OUTPUT(x5)
+OUTPUT(x6)
+
+x0 = AND(i0, i1)
+x1 = NAND(i0, i1)
+x2 = OR(i0, i1)
+x3 = NOR(i0, i1)
+x4 = XOR(i0, i1)
+x5 = BUF(i0)
+x6 = NOT(i1)
+x7 = NOT(x2)
+x8 = gnd
+x9 = vdd
+x10 = XOR(  i0, i1 )
+x11   =  gnd 
+x12 = LUT 0x45fc (x0, x1, x2, x3)
+";
+        let aig = super::read_bench(example.as_bytes()).unwrap();
+        assert_eq!(aig.nb_inputs(), 2);
+        assert_eq!(aig.nb_outputs(), 7);
+        assert_eq!(aig.nb_nodes(), 13);
+        let mut buf = BufWriter::new(Vec::new());
+        super::write_bench(&mut buf, &aig);
+        String::from_utf8(buf.into_inner().unwrap()).unwrap();
+    }
+
+    #[cfg(test)]
+    fn parse(
+        example: &str,
+        dialect: super::BenchDialect,
+    ) -> Result<(crate::Network, Vec<String>), String> {
+        let mut warnings = Vec::new();
+        let (aig, _, _) = super::parse_bench(
+            example.as_bytes(),
+            dialect,
+            super::BenchArity::Strict,
+            None,
+            &mut warnings,
+        )?;
+        Ok((aig, warnings))
+    }
+
+    #[test]
+    fn test_dff_iscas() {
+        use super::BenchDialect;
+        use crate::{Gate, Signal};
+
+        let example = "INPUT(i0)
+OUTPUT(x0)
+x0 = DFF(i0)
+";
+        for dialect in [
+            BenchDialect::Auto,
+            BenchDialect::Iscas,
+            BenchDialect::Abc,
+            BenchDialect::Itc99,
+        ] {
+            let (aig, warnings) = parse(example, dialect).unwrap();
+            assert!(warnings.is_empty());
+            assert_eq!(
+                aig.gate(0),
+                Gate::dff(aig.input(0), Signal::one(), Signal::zero())
+            );
+        }
+        assert!(parse("INPUT(i0)\nx0 = DFF(i0, 1)\n", BenchDialect::Iscas).is_err());
+    }
+
+    #[test]
+    fn test_dff_abc() {
+        use super::BenchDialect;
+        use crate::sim::simulate;
+
+        let example = "INPUT(i0)
+OUTPUT(x0)
+OUTPUT(x1)
+OUTPUT(x2)
+x0 = DFF(i0, 0)
+x1 = DFF(i0, 1)
+x2 = DFF(i0, 2)
+";
+        let (aig, warnings) = parse(example, BenchDialect::Abc).unwrap();
+        assert_eq!(
+            warnings.len(),
+            1,
+            "Unknown initial value should be reported"
+        );
+        let sim = simulate(&aig, &vec![vec![false], vec![false], vec![true]]);
+        assert_eq!(
+            sim,
+            vec![
+                vec![false, true, false],
+                vec![false, false, false],
+                vec![false, false, false]
+            ]
+        );
+
+        // Guessed, with a warning
+        let (guessed, warnings) = parse(example, BenchDialect::Auto).unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("abc"));
+        assert_eq!(guessed.nb_nodes(), aig.nb_nodes());
+        assert!(parse(
+            "INPUT(i0)\nINPUT(i1)\nx0 = DFF(i0, i1)\n",
+            BenchDialect::Abc
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_dff_itc99() {
+        use super::BenchDialect;
+        use crate::{Gate, Signal};
+
+        let example = "INPUT(clk)
+INPUT(d)
+INPUT(r)
+INPUT(e)
+OUTPUT(x0)
+OUTPUT(x1)
+x0 = DFF(clk, d)
+x1 = DFFRSE(clk, x0, r, gnd, e)
+";
+        for dialect in [BenchDialect::Auto, BenchDialect::Itc99] {
+            let (aig, warnings) = parse(example, dialect).unwrap();
+            assert_eq!(warnings.len(), (dialect == BenchDialect::Auto) as usize);
+            assert_eq!(aig.nb_inputs(), 4);
+            assert_eq!(
+                aig.gate(0),
+                Gate::dff(aig.input(1), Signal::one(), Signal::zero())
+            );
+            assert_eq!(
+                aig.gate(1),
+                Gate::dff(aig.node(0), aig.input(3), aig.input(2))
+            );
+        }
+        assert!(parse(example, BenchDialect::Iscas).is_err());
+    }
+
+    #[test]
+    fn test_dff_ambiguous() {
+        use super::BenchDialect;
+
+        // d is used elsewhere, so it cannot be a clock
+        let example = "INPUT(a)
+INPUT(d)
+OUTPUT(x0)
+OUTPUT(x1)
+x0 = DFF(a, d)
+x1 = AND(a, d)
+";
+        assert!(parse(example, BenchDialect::Auto).is_err());
+        assert!(parse(example, BenchDialect::Itc99).is_ok());
+    }
+
+    #[test]
+    fn test_conflicting_clocks() {
+        use super::BenchDialect;
+
+        let example = "INPUT(clk1)
+INPUT(clk2)
+INPUT(d)
+OUTPUT(x1)
+x0 = DFF(clk1, d)
+x1 = DFF(clk2, x0)
+";
+        for dialect in [BenchDialect::Auto, BenchDialect::Itc99] {
+            let err = parse(example, dialect).unwrap_err();
+            assert!(err.contains("different clocks"), "{}", err);
+        }
+        // Clocks must be primary inputs
+        let example = "INPUT(clk)
+INPUT(d)
+OUTPUT(x1)
+g = NOT(clk)
+x1 = DFF(g, d)
+";
+        assert!(parse(example, BenchDialect::Itc99).is_err());
+    }
+
+    #[test]
+    fn test_missing_inputs() {
+        use super::{parse_bench, BenchArity, BenchDialect};
+        use crate::sim::simulate;
+        use crate::{Gate, Network, Signal};
+
+        let parse = |example: &str, arity| {
+            let mut warnings = Vec::new();
+            let (aig, _, _) = parse_bench(
+                example.as_bytes(),
+                BenchDialect::Iscas,
+                arity,
+                None,
+                &mut warnings,
+            )?;
+            Ok::<_, String>((aig, warnings))
+        };
+        let header = "INPUT(a)\nINPUT(b)\nOUTPUT(x)\n";
+        let cases = [
+            (
+                "MUX(a, b)",
+                Gate::and(Signal::from_input(0), Signal::from_input(1)),
+            ),
+            ("MUX(a)", Gate::Buf(Signal::zero())),
+            (
+                "MAJ(a, b)",
+                Gate::and(Signal::from_input(0), Signal::from_input(1)),
+            ),
+            ("MAJ(a)", Gate::Buf(Signal::zero())),
+            ("BUF()", Gate::Buf(Signal::zero())),
+            ("NOT()", Gate::Buf(Signal::one())),
+        ];
+        for (gate, expected) in cases {
+            let example = format!("{}x = {}\n", header, gate);
+            let err = parse(&example, BenchArity::Strict).unwrap_err();
+            assert!(err.starts_with("Line 4: "), "{}", err);
+
+            let (aig, warnings) = parse(&example, BenchArity::Pad).unwrap();
+            assert_eq!(warnings.len(), 1);
+            let mut reference = Network::new();
+            reference.add_inputs(2);
+            let x = reference.add(expected);
+            reference.add_output(x);
+            let patterns: Vec<Vec<bool>> = (0..4).map(|i| vec![i & 1 != 0, i & 2 != 0]).collect();
+            assert_eq!(
+                simulate(&aig, &patterns),
+                simulate(&reference, &patterns),
+                "{}",
+                gate
+            );
+        }
+
+        // Flip-flops: missing reset and set are tied to zero, missing enable to one
+        let example = format!("{}x = DFFRSE(a, b)\n", header);
+        assert!(parse(&example, BenchArity::Strict).is_err());
+        let (aig, _) = parse(&example, BenchArity::Pad).unwrap();
+        assert_eq!(
+            aig.gate(0),
+            Gate::dff(aig.input(0), Signal::one(), aig.input(1))
+        );
+        let (aig, _) = parse(&format!("{}x = DFF()\n", header), BenchArity::Pad).unwrap();
+        assert_eq!(
+            simulate(&aig, &vec![vec![true, true]; 2]),
+            vec![vec![false]; 2]
+        );
+        // Missing active-low set and reset are tied to one
+        let (aig, _) = parse(&format!("{}x = DFFSR_NN(a)\n", header), BenchArity::Pad).unwrap();
+        assert_eq!(
+            simulate(&aig, &vec![vec![true, false]; 2]),
+            vec![vec![false], vec![true]]
+        );
+
+        // Too many inputs are always an error
+        let example = format!("{}x = MUX(a, b, a, b)\n", header);
+        assert!(parse(&example, BenchArity::Pad).is_err());
+    }
+
+    #[test]
+    fn test_dffsr() {
+        use super::{read_bench, write_bench, BenchDialect};
+        use crate::sim::simulate;
+
+        let example = "INPUT(d)
+INPUT(s)
+INPUT(r)
+INPUT(e)
+OUTPUT(q0)
+OUTPUT(q1)
+OUTPUT(q2)
+OUTPUT(q3)
+sn = NOT(s)
+rn = NOT(r)
+q0 = DFFSR(d, s, r)
+q1 = DFFSR_NN_QN(d, sn, rn)
+q2 = DFFRSE(d, r, s, e)
+q3 = DFFSR_PN(q1, s, rn)
+";
+        let (aig, _) = parse(example, BenchDialect::Iscas).unwrap();
+        let patterns: Vec<Vec<bool>> = (0..32)
+            .map(|i| (0..4).map(|j| (i * 5 + j * 3) % 7 < 3).collect())
+            .collect();
+        // Reset takes priority over set, and both over enable
+        let mut expected = Vec::new();
+        let (mut q0, mut q2, mut q3) = (false, false, false);
+        for p in &patterns {
+            let (d, s, r, e) = (p[0], p[1], p[2], p[3]);
+            expected.push(vec![q0, !q0, q2, q3]);
+            let next = |en: bool, d: bool, q: bool| !r && (s || if en { d } else { q });
+            q3 = next(true, !q0, q3);
+            q0 = next(true, d, q0);
+            q2 = next(e, d, q2);
+        }
+        assert_eq!(simulate(&aig, &patterns), expected);
+
+        // The flip-flops are written with additional gates
+        let mut buf = Vec::new();
+        write_bench(&mut buf, &aig);
+        let aig2 = read_bench(buf.as_slice()).unwrap();
+        assert_eq!(simulate(&aig2, &patterns), expected);
+
+        // With a clock in the itc99 dialect
+        let example = "INPUT(clk)\nINPUT(d)\nINPUT(s)\nOUTPUT(q)\nq = DFFSR_NP(clk, d, s, gnd)\n";
+        assert!(parse(example, BenchDialect::Iscas).is_err());
+        let (aig, _) = parse(example, BenchDialect::Itc99).unwrap();
+        assert_eq!(aig.nb_inputs(), 3);
+        assert!(parse(
+            "INPUT(d)\nOUTPUT(q)\nq = DFFSR_XP(d, d, d)\n",
+            BenchDialect::Iscas
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_empty_nary() {
+        use super::BenchDialect;
+        use crate::sim::simulate;
+
+        let example = "OUTPUT(x0)
+OUTPUT(x1)
+OUTPUT(x2)
+OUTPUT(x3)
+OUTPUT(x4)
+OUTPUT(x5)
+x0 = AND()
+x1 = NAND()
+x2 = OR()
+x3 = NOR()
+x4 = XOR()
+x5 = XNOR()
+";
+        let (aig, warnings) = parse(example, BenchDialect::Auto).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(
+            simulate(&aig, &vec![vec![]]),
+            vec![vec![true, false, false, true, false, true]]
+        );
+    }
+
+    #[test]
+    fn test_locations() {
+        use super::{read_bench_with_locations, BenchArity, BenchDialect};
+
+        let example = "INPUT(a)
+INPUT(b)
+OUTPUT(o)
+
+n1 = NAND(a, b)
+# Duplicate of n1
+n2 = NAND(b, a)
+o = AND(n1, n2, a)
+";
+        let (mut aig, names, mut locations) = read_bench_with_locations(
+            example.as_bytes(),
+            "example.bench",
+            BenchDialect::Auto,
+            BenchArity::Strict,
+        )
+        .unwrap();
+        let n1 = names.find("n1").unwrap().var() as usize;
+        assert_eq!(
+            locations.describe_node(n1).as_deref(),
+            Some("example.bench:5")
+        );
+        let o = names.find("o").unwrap().var() as usize;
+        assert_eq!(
+            locations.describe_node(o).as_deref(),
+            Some("example.bench:8")
+        );
+
+        // The merged Nand is inverted and keeps the first line
+        let translation = aig.make_canonical();
+        locations.remap(&translation);
+        let nand = aig.output(0);
+        let deps = aig.dependencies(nand.var() as usize).to_vec();
+        let merged = deps.iter().find(|s| s.is_var()).unwrap();
+        assert_eq!(
+            locations.signal_location(*merged).unwrap().line,
+            5,
+            "{:?}",
+            locations
+        );
+    }
+
+    #[test]
+    fn test_loop_error() {
+        let example = "INPUT(a)
+OUTPUT(o)
+o = AND(a, n2)
+n1 = NOT(o)
+n2 = OR(n1, a)
+";
+        let err = super::read_bench(example.as_bytes())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("o (line 3)"), "{}", err);
+        assert!(err.contains("n1 (line 4)"), "{}", err);
+        assert!(err.contains("n2 (line 5)"), "{}", err);
+    }
+
+    #[test]
+    fn test_error_lines() {
+        use crate::QuaighError;
+
+        let example = "INPUT(a)
+OUTPUT(o)
+
+o = AND(a, b)
+";
+        let err = super::read_bench(example.as_bytes()).unwrap_err();
+        assert!(
+            matches!(err, QuaighError::Parse { line: Some(4), .. }),
+            "{:?}",
+            err
+        );
+        let err = super::read_bench("INPUT(a)\no = FOO(a)\n".as_bytes()).unwrap_err();
+        assert!(
+            matches!(err, QuaighError::Parse { line: Some(2), .. }),
+            "{:?}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_write_names() {
+        // Names are kept through a write and a read, and outputs are renamed by buffers
+        let example = "INPUT(a)
+INPUT(b)
+OUTPUT(o)
+OUTPUT(p)
+o = NAND(a, b)
+p = BUF(o)
+";
+        let (mut aig, mut names) = super::read_bench_with_names(example.as_bytes()).unwrap();
+        names.remap(&aig.make_canonical());
+        names.remap(&aig.cleanup());
+        let mut buf = Vec::new();
+        super::write_bench_with_names(&mut buf, &aig, &names);
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains("INPUT(a)"), "{}", text);
+        assert!(text.contains("OUTPUT(p)"), "{}", text);
+        assert!(text.contains("o = BUF(x0_n)"), "{}", text);
+        assert!(text.contains("p = BUF(x0_n)"), "{}", text);
+        let (aig2, names2) = super::read_bench_with_names(text.as_bytes()).unwrap();
+        assert_eq!(aig2.nb_outputs(), 2);
+        for i in 0..2 {
+            assert_eq!(names2.input_name(i), names.input_name(i));
+            assert_eq!(names2.output_name(i), names.output_name(i));
+        }
+    }
+}
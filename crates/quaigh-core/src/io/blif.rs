@@ -0,0 +1,1604 @@
+use core::panic;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::iter::zip;
+use std::rc::Rc;
+
+use fxhash::FxHashMap;
+
+use crate::network::{
+    BinaryType, BlackBox, ClockId, HierarchyMap, NameTable, NaryType, TernaryType, MAX_CLOCKS,
+};
+use crate::{Gate, Network, QuaighError, Signal};
+
+use super::utils::{get_inverted_signals, SignalNamer};
+use super::ReadResult;
+
+/// Index of an interned signal name
+type NameId = u32;
+
+/// Names of the signals of a .blif file
+///
+/// Each name is stored once, and statements refer to it by index, so that reading a large file
+/// does not allocate a string for every token.
+#[derive(Default)]
+struct Interner {
+    ids: FxHashMap<Rc<str>, NameId>,
+    names: Vec<Rc<str>>,
+}
+
+impl Interner {
+    fn intern(&mut self, name: &str) -> NameId {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = self.names.len() as NameId;
+        let name: Rc<str> = name.into();
+        self.names.push(name.clone());
+        self.ids.insert(name, id);
+        id
+    }
+
+    fn name(&self, id: NameId) -> &str {
+        &self.names[id as usize]
+    }
+
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+}
+
+/// Line of the truth table of a .names statement
+#[derive(Clone)]
+struct Cube {
+    /// Value of each input: 0, 1, or - if it does not matter
+    inputs: Box<[u8]>,
+    /// Value of the output
+    value: bool,
+}
+
+impl fmt::Display for Cube {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inputs = String::from_utf8_lossy(&self.inputs);
+        let value = if self.value { "1" } else { "0" };
+        if inputs.is_empty() {
+            write!(f, "{}", value)
+        } else {
+            write!(f, "{} {}", inputs, value)
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Statement {
+    Model(String),
+    End,
+    Exdc,
+    Inputs(Vec<NameId>),
+    Outputs(Vec<NameId>),
+    Latch {
+        input: NameId,
+        output: NameId,
+        enable: Option<NameId>,
+        reset: Option<NameId>,
+        clock: Option<NameId>,
+        /// Falling edge, or active low for a transparent latch
+        falling_edge: bool,
+        /// Level-sensitive latch, transparent while its enable is active
+        transparent: bool,
+    },
+    Name(Vec<NameId>),
+    Cube(Cube),
+    Subckt {
+        model: String,
+        connections: Vec<(NameId, NameId)>,
+    },
+    BlackBox,
+}
+
+/// Signal of each name, indexed by [`NameId`]
+fn build_name_to_sig(
+    statements: &Vec<Statement>,
+    names: &Interner,
+) -> Result<Vec<Option<Signal>>, String> {
+    let mut found_model = false;
+
+    let mut ret = vec![None; names.len()];
+    let mut define = |name: NameId, s: Signal| {
+        if ret[name as usize].replace(s).is_some() {
+            Err(format!("{} is defined twice", names.name(name)))
+        } else {
+            Ok(())
+        }
+    };
+    let mut var_index = 0;
+    let mut input_index = 0;
+    for statement in statements {
+        match statement {
+            Statement::Model(_) => {
+                if found_model {
+                    return Err("Multiple models in the same file are not supported".to_owned());
+                }
+                found_model = true;
+            }
+            Statement::End => {
+                if !found_model {
+                    return Err("End statement before the end of the model".to_owned());
+                }
+            }
+            Statement::Exdc => {
+                break;
+            }
+            Statement::Inputs(inputs) => {
+                for name in inputs {
+                    define(*name, Signal::from_input(input_index as u32))?;
+                    input_index += 1;
+                }
+            }
+            Statement::Outputs(_) => {}
+            Statement::Latch { output: name, .. } => {
+                define(*name, Signal::from_var(var_index as u32))?;
+                var_index += 1;
+            }
+            Statement::Name(names) => {
+                let Some(name) = names.last() else {
+                    return Err(".names statement with no output".to_owned());
+                };
+                define(*name, Signal::from_var(var_index as u32))?;
+                var_index += 1;
+            }
+            Statement::Cube(_) => (),
+            Statement::Subckt { .. } | Statement::BlackBox => {
+                unreachable!("Subcircuits are flattened before")
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Signal of a name, or an error if it is not defined
+fn lookup(
+    name_to_sig: &[Option<Signal>],
+    names: &Interner,
+    name: NameId,
+) -> Result<Signal, String> {
+    name_to_sig[name as usize].ok_or_else(|| format!("{} is not defined", names.name(name)))
+}
+
+/// Index of each named clock, in order of appearance
+///
+/// Latches without a clock use clock 0, as does the first named clock.
+fn build_clock_indices(statements: &Vec<Statement>) -> HashMap<NameId, u32> {
+    let mut ret = HashMap::new();
+    for statement in statements {
+        match statement {
+            Statement::Latch {
+                clock: Some(clock), ..
+            } => {
+                let index = ret.len() as u32;
+                ret.entry(*clock).or_insert(index);
+            }
+            Statement::Exdc => break,
+            _ => (),
+        }
+    }
+    ret
+}
+
+/// Network built from the statements
+struct Built {
+    aig: Network,
+    /// Translation applied by the topological sort
+    translation: Box<[Signal]>,
+    /// Index of the statement each node comes from, before the sort
+    origins: Vec<usize>,
+}
+
+fn build_network(
+    statements: &Vec<Statement>,
+    name_to_sig: &[Option<Signal>],
+    names: &Interner,
+) -> Result<Built, String> {
+    let mut ret: Network = Network::new();
+    let clock_indices = build_clock_indices(statements);
+    let get = |name: &NameId| lookup(name_to_sig, names, *name);
+
+    let mut names_to_process = Vec::new();
+    let mut origins = Vec::new();
+
+    for (i, statement) in statements.iter().enumerate() {
+        match statement {
+            Statement::Inputs(inputs) => ret.add_inputs(inputs.len()),
+            Statement::Outputs(outputs) => {
+                for name in outputs {
+                    ret.add_output(get(name)?);
+                }
+            }
+            Statement::Latch {
+                input,
+                enable,
+                reset,
+                clock,
+                falling_edge,
+                transparent,
+                ..
+            } => {
+                let d = get(input)?;
+                let en = enable.as_ref().map(get).transpose()?;
+                let res = reset.as_ref().map(get).transpose()?;
+                if *transparent {
+                    origins.push(i);
+                    ret.add(Gate::latch(
+                        d,
+                        en.unwrap_or(Signal::one()) ^ *falling_edge,
+                        res.unwrap_or(Signal::zero()),
+                    ));
+                    continue;
+                }
+                let index = clock.as_ref().map(|c| clock_indices[c]).unwrap_or(0);
+                if index as usize >= MAX_CLOCKS {
+                    return Err(format!(
+                        "Too many clocks: {}, at most {} are supported",
+                        clock_indices.len(),
+                        MAX_CLOCKS
+                    ));
+                }
+                let clock = ClockId::new(index, *falling_edge);
+                origins.push(i);
+                ret.add(Gate::dff_with_clock(
+                    d,
+                    en.unwrap_or(Signal::one()),
+                    res.unwrap_or(Signal::zero()),
+                    clock,
+                ));
+            }
+            Statement::Name(names) => {
+                let mut deps = Vec::new();
+                for name in names.iter().take(names.len() - 1) {
+                    deps.push(get(name)?);
+                }
+                names_to_process.push((i, ret.nb_nodes()));
+                origins.push(i);
+                ret.add(Gate::andn(&deps));
+            }
+            Statement::Cube(_) => (),
+            Statement::Model(_) => (),
+            Statement::Exdc => break,
+            Statement::End => (),
+            Statement::Subckt { .. } | Statement::BlackBox => {
+                unreachable!("Subcircuits are flattened before")
+            }
+        }
+    }
+
+    // Now that all gates have been added, we can process cubes that may require adding new gates
+    for (i, gate) in names_to_process {
+        let inputs = ret.dependencies(gate);
+        let mut cubes = Vec::new();
+        for j in (i + 1)..statements.len() {
+            if let Statement::Cube(c) = &statements[j] {
+                cubes.push(c);
+            } else {
+                break;
+            }
+        }
+        let mut cube_gates = Vec::new();
+        let mut polarities = Vec::new();
+        for c in cubes {
+            let mut deps = Vec::new();
+            if c.inputs.len() != inputs.len() {
+                return Err(format!(
+                    "Invalid cube: {} has {} inputs, expected {}",
+                    c,
+                    c.inputs.len(),
+                    inputs.len()
+                ));
+            }
+            for (v, s) in zip(c.inputs.iter(), inputs) {
+                match v {
+                    b'0' => deps.push(!s),
+                    b'1' => deps.push(*s),
+                    _ => (),
+                }
+            }
+            let pol = c.value;
+            polarities.push(pol);
+            let g = if pol {
+                if deps.len() == 0 {
+                    Gate::Buf(Signal::one())
+                } else if deps.len() == 1 {
+                    Gate::Buf(deps[0])
+                } else {
+                    Gate::andn(&deps)
+                }
+            } else {
+                if deps.len() == 0 {
+                    Gate::Buf(Signal::zero())
+                } else if deps.len() == 1 {
+                    Gate::Buf(!deps[0])
+                } else {
+                    Gate::Nary(deps.into(), NaryType::Nand)
+                }
+            };
+            cube_gates.push(g);
+        }
+        if cube_gates.is_empty() {
+            ret.replace(gate, Gate::Buf(Signal::zero()));
+        } else if cube_gates.len() == 1 {
+            ret.replace(gate, cube_gates[0].clone());
+        } else {
+            for p in &polarities {
+                if *p != polarities[0] {
+                    return Err("Inconsistent polarities in cubes".to_owned());
+                }
+            }
+            let mut deps = Vec::new();
+            for g in cube_gates {
+                origins.push(i);
+                deps.push(ret.add(g));
+            }
+            if polarities[0] {
+                ret.replace(gate, Gate::Nary(deps.into(), NaryType::Or));
+            } else {
+                ret.replace(gate, Gate::Nary(deps.into(), NaryType::Nand));
+            }
+        }
+    }
+    let translation = ret.try_topo_sort()?;
+    Ok(Built {
+        aig: ret,
+        translation,
+        origins,
+    })
+}
+
+/// Names of the inputs, nodes and outputs of the network built from the statements
+fn build_names(
+    statements: &Vec<Statement>,
+    name_to_sig: &[Option<Signal>],
+    interner: &Interner,
+    translation: &[Signal],
+) -> NameTable {
+    let mut names = NameTable::new();
+    for (id, s) in name_to_sig.iter().enumerate() {
+        let Some(s) = s else {
+            continue;
+        };
+        let name = interner.name(id as NameId);
+        if s.is_input() {
+            names.set_input_name(s.input() as usize, name);
+        } else {
+            names.set_node_name(s.var() as usize, name);
+        }
+    }
+    let mut output_index = 0;
+    for statement in statements {
+        match statement {
+            Statement::Outputs(outputs) => {
+                for name in outputs {
+                    names.set_output_name(output_index, interner.name(*name));
+                    output_index += 1;
+                }
+            }
+            Statement::Exdc => break,
+            _ => (),
+        }
+    }
+    for (name, index) in build_clock_indices(statements) {
+        names.set_clock_name(index as usize, interner.name(name));
+    }
+    names.remap(translation);
+    names
+}
+
+/// Read an ABC-style .flop statement
+///
+/// The reset is synchronous and takes priority over the enable, as for [`Gate::Dff`].
+/// The flip-flop is triggered on the rising edge of its clock, and only a zero initial value is
+/// supported.
+fn read_flop(tokens: &[&str], names: &mut Interner) -> Result<Statement, String> {
+    let mut input = None;
+    let mut output = None;
+    let mut enable = None;
+    let mut reset = None;
+    let mut clock = None;
+    for t in tokens {
+        let Some((key, value)) = t.split_once('=') else {
+            return Err(format!("Invalid .flop argument {}", t));
+        };
+        match key {
+            "D" => input = Some(names.intern(value)),
+            "Q" => output = Some(names.intern(value)),
+            "E" => enable = Some(names.intern(value)),
+            "R" => reset = Some(names.intern(value)),
+            "C" => clock = Some(names.intern(value)),
+            "init" => {
+                if value != "0" {
+                    return Err(format!("Unsupported .flop initial value {}", value));
+                }
+            }
+            _ => return Err(format!("Unsupported .flop argument {}", t)),
+        }
+    }
+    match (input, output) {
+        (Some(input), Some(output)) => Ok(Statement::Latch {
+            input,
+            output,
+            enable,
+            reset,
+            clock,
+            falling_edge: false,
+            transparent: false,
+        }),
+        _ => Err(".flop statement requires D and Q".to_owned()),
+    }
+}
+
+/// Read a line of the truth table of a .names statement
+fn read_cube(tokens: &[&str]) -> Result<Cube, String> {
+    let (inputs, value) = match tokens {
+        [inputs, value] => (*inputs, *value),
+        [value] => ("", *value),
+        _ => return Err(format!("Invalid cube: {}", tokens.join(" "))),
+    };
+    let value = match value {
+        "0" => false,
+        "1" => true,
+        _ => return Err(format!("Invalid cube: {}", tokens.join(" "))),
+    };
+    if !inputs.bytes().all(|c| matches!(c, b'0' | b'1' | b'-')) {
+        return Err(format!("Invalid cube: {}", tokens.join(" ")));
+    }
+    Ok(Cube {
+        inputs: inputs.as_bytes().into(),
+        value,
+    })
+}
+
+fn read_single_statement(tokens: &[&str], names: &mut Interner) -> Result<Statement, String> {
+    let mut intern_all = |tokens: &[&str]| tokens.iter().map(|s| names.intern(s)).collect();
+    match tokens[0] {
+        ".model" => Ok(Statement::Model(
+            tokens.get(1).map(|s| (*s).to_owned()).unwrap_or_default(),
+        )),
+        ".inputs" => Ok(Statement::Inputs(intern_all(&tokens[1..]))),
+        ".outputs" => Ok(Statement::Outputs(intern_all(&tokens[1..]))),
+        ".latch" => {
+            if tokens.len() < 3 {
+                return Err(".latch statement requires an input and an output".to_owned());
+            }
+            // Optional type and control, for example .latch d q fe clk 0
+            // Level-sensitive latches (ah or al) use their control as the enable
+            let (tp, control) = if tokens.len() >= 5 {
+                let control = match tokens[4] {
+                    "NIL" => None,
+                    c => Some(names.intern(c)),
+                };
+                (tokens[3], control)
+            } else {
+                ("re", None)
+            };
+            let (falling_edge, transparent) = match tp {
+                "re" => (false, false),
+                "fe" => (true, false),
+                "ah" => (false, true),
+                "al" => (true, true),
+                tp => return Err(format!("Unsupported .latch type {}", tp)),
+            };
+            let (clock, enable) = if transparent {
+                (None, control)
+            } else {
+                (control, None)
+            };
+            Ok(Statement::Latch {
+                input: names.intern(tokens[1]),
+                output: names.intern(tokens[2]),
+                enable,
+                reset: None,
+                clock,
+                falling_edge,
+                transparent,
+            })
+        }
+        ".flop" => read_flop(&tokens[1..], names),
+        ".names" => Ok(Statement::Name(intern_all(&tokens[1..]))),
+        ".subckt" => {
+            let Some(model) = tokens.get(1) else {
+                return Err(".subckt statement requires a model".to_owned());
+            };
+            let mut connections = Vec::new();
+            for t in &tokens[2..] {
+                let Some((formal, actual)) = t.split_once('=') else {
+                    return Err(format!("Invalid .subckt connection {}", t));
+                };
+                connections.push((names.intern(formal), names.intern(actual)));
+            }
+            Ok(Statement::Subckt {
+                model: (*model).to_owned(),
+                connections,
+            })
+        }
+        ".blackbox" => Ok(Statement::BlackBox),
+        ".end" => Ok(Statement::End),
+        ".exdc" => Ok(Statement::Exdc),
+        _ => {
+            if tokens[0].starts_with(".") {
+                Err(format!("{} construct is not supported", tokens[0]))
+            } else {
+                Ok(Statement::Cube(read_cube(tokens)?))
+            }
+        }
+    }
+}
+
+/// Read the statements of a .blif file in a single pass
+///
+/// The file is read line by line: only the current statement is kept as text, and the names it
+/// contains are interned.
+fn read_statements<R: std::io::Read>(
+    r: R,
+    names: &mut Interner,
+) -> Result<Vec<Statement>, QuaighError> {
+    let mut ret: Vec<Statement> = Vec::new();
+    let mut reader = BufReader::new(r);
+
+    // Buffers for the current line and for multi-line strings
+    let mut line = String::new();
+    let mut ss = String::new();
+
+    // Line of the end of the current statement, for error messages
+    let mut line_nb = 0;
+    let mut push_statement = |ss: &str, line_nb: usize| -> Result<(), QuaighError> {
+        let tokens: Vec<_> = ss.split_whitespace().collect();
+        if !tokens.is_empty() {
+            let statement = read_single_statement(&tokens, names)
+                .map_err(|e| QuaighError::parse_at(line_nb, e))?;
+            ret.push(statement);
+        }
+        Ok(())
+    };
+
+    loop {
+        line.clear();
+        let nb_read = reader
+            .read_line(&mut line)
+            .map_err(|e| QuaighError::parse(e.to_string()))?;
+        if nb_read == 0 {
+            break;
+        }
+        line_nb += 1;
+        let s = line.trim_end_matches(['\n', '\r']);
+
+        // TODO: parse comments properly, not just at the beginning of the line
+        let comment_pos = s.find('#');
+
+        // Extend multi-line buffers
+        ss += " ";
+        ss += &s[0..comment_pos.unwrap_or(s.len())];
+
+        let is_continuation = comment_pos.is_none() && ss.ends_with("\\");
+        if is_continuation {
+            ss.pop().unwrap();
+        }
+        if is_continuation || ss.is_empty() {
+            continue;
+        }
+
+        push_statement(&ss, line_nb)?;
+        ss.clear();
+    }
+
+    // Handle a line continuation at the end of the file
+    push_statement(&ss, line_nb)?;
+    Ok(ret)
+}
+
+/// Model of a .blif file: its name and its statements, without .model and .end
+struct Model {
+    name: String,
+    statements: Vec<Statement>,
+}
+
+/// Split the statements into models; the first one is the top-level model
+///
+/// Statements before the first .model belong to an unnamed top-level model, and statements after
+/// an .end to the previous model.
+fn split_models(statements: Vec<Statement>) -> Vec<Model> {
+    let mut ret: Vec<Model> = Vec::new();
+    for statement in statements {
+        match statement {
+            Statement::Model(name) => ret.push(Model {
+                name,
+                statements: Vec::new(),
+            }),
+            Statement::End => (),
+            s => {
+                if ret.is_empty() {
+                    ret.push(Model {
+                        name: String::new(),
+                        statements: Vec::new(),
+                    });
+                }
+                ret.last_mut().unwrap().statements.push(s);
+            }
+        }
+    }
+    ret
+}
+
+/// Flattening of the .subckt instances of the top-level model
+struct Flattener<'a> {
+    models: HashMap<&'a str, &'a Model>,
+    /// Names of the signals, extended with the names of the flattened signals
+    names: &'a mut Interner,
+    /// Statements of the flattened model
+    statements: Vec<Statement>,
+    /// Instance each flattened statement comes from, or None for the top-level model
+    instances: Vec<Option<u32>>,
+    hierarchy: HierarchyMap,
+    /// Models being instantiated, to detect recursion
+    stack: Vec<&'a str>,
+    /// Number of primary inputs and outputs, before the black box pins
+    nb_primary: (usize, usize),
+    /// Signals driven by the outputs of the black boxes, that become inputs of the network
+    black_box_outputs: Vec<NameId>,
+    /// Signals connected to the inputs of the black boxes, that become outputs of the network
+    black_box_inputs: Vec<NameId>,
+}
+
+impl<'a> Flattener<'a> {
+    fn push(&mut self, statement: Statement, instance: Option<u32>) {
+        self.statements.push(statement);
+        self.instances.push(instance);
+    }
+
+    /// Name of a signal of an instance, prefixed by its path
+    fn prefixed(&mut self, path: &str, name: NameId) -> NameId {
+        let prefixed = format!("{}/{}", path, self.names.name(name));
+        self.names.intern(&prefixed)
+    }
+
+    /// Rename a signal of a model in an instance
+    ///
+    /// Ports are renamed to the signals they are connected to, and other signals are prefixed by
+    /// the path of the instance.
+    fn rename(&mut self, name: NameId, path: &str, ports: &HashMap<NameId, NameId>) -> NameId {
+        match ports.get(&name) {
+            Some(actual) => *actual,
+            None if path.is_empty() => name,
+            None => self.prefixed(path, name),
+        }
+    }
+
+    /// Copy the statements of a model, with its signals renamed
+    fn expand(
+        &mut self,
+        model: &'a Model,
+        path: &str,
+        ports: &HashMap<NameId, NameId>,
+        instance: Option<u32>,
+    ) -> Result<(), String> {
+        let mut nb_instances: HashMap<&str, usize> = HashMap::new();
+        for statement in &model.statements {
+            match statement {
+                Statement::Inputs(_) | Statement::Outputs(_) | Statement::Exdc => {
+                    if instance.is_some() {
+                        if matches!(statement, Statement::Exdc) {
+                            return Err(format!(
+                                "Model {}: .exdc is only supported in the top-level model",
+                                model.name
+                            ));
+                        }
+                    } else {
+                        self.push(statement.clone(), instance);
+                    }
+                }
+                Statement::Latch {
+                    input,
+                    output,
+                    enable,
+                    reset,
+                    clock,
+                    falling_edge,
+                    transparent,
+                } => {
+                    let latch = Statement::Latch {
+                        input: self.rename(*input, path, ports),
+                        output: self.rename(*output, path, ports),
+                        enable: enable.map(|n| self.rename(n, path, ports)),
+                        reset: reset.map(|n| self.rename(n, path, ports)),
+                        clock: clock.map(|n| self.rename(n, path, ports)),
+                        falling_edge: *falling_edge,
+                        transparent: *transparent,
+                    };
+                    self.push(latch, instance);
+                }
+                Statement::Name(names) => {
+                    let names = names.iter().map(|n| self.rename(*n, path, ports)).collect();
+                    self.push(Statement::Name(names), instance);
+                }
+                Statement::Cube(c) => self.push(Statement::Cube(c.clone()), instance),
+                Statement::Subckt {
+                    model: sub,
+                    connections,
+                } => {
+                    let count = nb_instances.entry(sub.as_str()).or_insert(0);
+                    let local = format!("{}_{}", sub, count);
+                    *count += 1;
+                    let sub_path = if path.is_empty() {
+                        local
+                    } else {
+                        format!("{}/{}", path, local)
+                    };
+                    let connections: Vec<(NameId, NameId)> = connections
+                        .iter()
+                        .map(|(formal, actual)| (*formal, self.rename(*actual, path, ports)))
+                        .collect();
+                    self.instantiate(sub, &sub_path, &connections)?;
+                }
+                Statement::BlackBox => {
+                    return Err(format!(
+                        "Model {} is a black box and cannot be the top-level model",
+                        model.name
+                    ))
+                }
+                Statement::Model(_) | Statement::End => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Cut out an instance of a black box model
+    ///
+    /// Black box outputs that are not connected get a name prefixed by the path of the instance.
+    fn cut_black_box(
+        &mut self,
+        model: &Model,
+        path: &str,
+        ports: &HashMap<NameId, NameId>,
+        inputs: &[NameId],
+        outputs: &[NameId],
+    ) {
+        let mut black_box = BlackBox {
+            path: path.to_owned(),
+            model: model.name.clone(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        };
+        for i in inputs {
+            let index = self.nb_primary.1 + self.black_box_inputs.len();
+            black_box
+                .inputs
+                .push((self.names.name(*i).to_owned(), index));
+            self.black_box_inputs.push(ports[i]);
+        }
+        for o in outputs {
+            let actual = match ports.get(o) {
+                Some(a) => *a,
+                None => self.prefixed(path, *o),
+            };
+            let index = self.nb_primary.0 + self.black_box_outputs.len();
+            black_box
+                .outputs
+                .push((self.names.name(*o).to_owned(), index));
+            self.black_box_outputs.push(actual);
+        }
+        self.hierarchy.add_black_box(black_box);
+    }
+
+    /// Flatten an instance of a model
+    fn instantiate(
+        &mut self,
+        name: &str,
+        path: &str,
+        connections: &[(NameId, NameId)],
+    ) -> Result<(), String> {
+        let Some(model) = self.models.get(name).copied() else {
+            return Err(format!("Model {} is not defined", name));
+        };
+        if self.stack.contains(&model.name.as_str()) {
+            return Err(format!("Model {} instantiates itself", name));
+        }
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for statement in &model.statements {
+            match statement {
+                Statement::Inputs(v) => inputs.extend(v.iter().copied()),
+                Statement::Outputs(v) => outputs.extend(v.iter().copied()),
+                _ => (),
+            }
+        }
+        let mut ports = HashMap::new();
+        for (formal, actual) in connections {
+            if !inputs.contains(formal) && !outputs.contains(formal) {
+                return Err(format!(
+                    "Model {} has no port {}",
+                    name,
+                    self.names.name(*formal)
+                ));
+            }
+            if ports.insert(*formal, *actual).is_some() {
+                return Err(format!(
+                    "Port {} of model {} is connected twice",
+                    self.names.name(*formal),
+                    name
+                ));
+            }
+        }
+        if let Some(i) = inputs.iter().find(|i| !ports.contains_key(*i)) {
+            return Err(format!(
+                "Input {} of model {} is not connected",
+                self.names.name(*i),
+                name
+            ));
+        }
+        if model
+            .statements
+            .iter()
+            .any(|s| matches!(s, Statement::BlackBox))
+        {
+            self.cut_black_box(model, path, &ports, &inputs, &outputs);
+            return Ok(());
+        }
+        let instance = self.hierarchy.add_instance(path, name);
+        self.stack.push(&model.name);
+        self.expand(model, path, &ports, Some(instance))?;
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+/// Top-level model after flattening its .subckt instances
+#[derive(Default)]
+struct Flattened {
+    /// Statements of the flattened model
+    statements: Vec<Statement>,
+    /// Instance each statement comes from, or None for the top-level model
+    instances: Vec<Option<u32>>,
+    hierarchy: HierarchyMap,
+}
+
+/// Flatten the .subckt instances of the top-level model
+///
+/// Instances are named after their model, numbered in each parent.
+fn flatten(statements: Vec<Statement>, names: &mut Interner) -> Result<Flattened, String> {
+    let models = split_models(statements);
+    let Some(top) = models.first() else {
+        return Ok(Flattened::default());
+    };
+    let mut nb_primary = (0, 0);
+    for statement in &top.statements {
+        match statement {
+            Statement::Inputs(v) => nb_primary.0 += v.len(),
+            Statement::Outputs(v) => nb_primary.1 += v.len(),
+            Statement::Exdc => break,
+            _ => (),
+        }
+    }
+    let mut flattener = Flattener {
+        models: HashMap::new(),
+        names,
+        statements: Vec::new(),
+        instances: Vec::new(),
+        hierarchy: HierarchyMap::new(),
+        stack: vec![top.name.as_str()],
+        nb_primary,
+        black_box_outputs: Vec::new(),
+        black_box_inputs: Vec::new(),
+    };
+    for m in &models {
+        if flattener.models.insert(m.name.as_str(), m).is_some() {
+            return Err(format!("Model {} is defined twice", m.name));
+        }
+    }
+    flattener.push(Statement::Model(top.name.clone()), None);
+    flattener.expand(top, "", &HashMap::new(), None)?;
+
+    // Black box pins come after the primary inputs and outputs, before the .exdc section
+    if !flattener.hierarchy.black_boxes().is_empty() {
+        let pos = flattener
+            .statements
+            .iter()
+            .position(|s| matches!(s, Statement::Exdc))
+            .unwrap_or(flattener.statements.len());
+        let pins = [
+            Statement::Inputs(std::mem::take(&mut flattener.black_box_outputs)),
+            Statement::Outputs(std::mem::take(&mut flattener.black_box_inputs)),
+        ];
+        flattener.statements.splice(pos..pos, pins);
+        flattener.instances.splice(pos..pos, [None, None]);
+    }
+    flattener.push(Statement::End, None);
+    Ok(Flattened {
+        statements: flattener.statements,
+        instances: flattener.instances,
+        hierarchy: flattener.hierarchy,
+    })
+}
+
+/// Read a network in .blif format
+///
+/// The format specification is available [here](https://course.ece.cmu.edu/~ee760/760docs/blif.pdf),
+/// with extensions introduced by [ABC](https://people.eecs.berkeley.edu/~alanmi/publications/other/boxes01.pdf)
+/// and [Yosys](https://yosyshq.readthedocs.io/projects/yosys/en/latest/cmd/write_blif.html) and
+/// [VPR](https://docs.verilogtorouting.org/en/latest/vpr/file_formats/).
+///
+/// Quaigh only support a small subset. The first model is the top-level model, and the models
+/// it instantiates with .subckt are flattened into it. Instances of .blackbox models are cut out:
+/// their outputs become inputs of the network and their inputs become outputs, after the primary
+/// inputs and outputs. Clocks are numbered in order of appearance, and latches without a clock use
+/// the first one.
+///
+/// The file is streamed in a single pass, and each signal name is only stored once, so that the
+/// memory used grows with the size of the network rather than with the size of the file.
+pub fn read_blif<R: std::io::Read>(r: R) -> Result<Network, QuaighError> {
+    Ok(read_blif_with_names(r)?.0)
+}
+
+/// Read a network in .blif format, with the names of its signals
+///
+/// Signals inside an instance are named after its hierarchical path, such as `adder_0/fa_1/c`.
+pub fn read_blif_with_names<R: std::io::Read>(r: R) -> Result<(Network, NameTable), QuaighError> {
+    let (aig, names, _) = read_blif_with_hierarchy(r)?;
+    Ok((aig, names))
+}
+
+/// Read a network in .blif format, with the names of its signals and the instance of its gates
+///
+/// Instances of a model are named after the model, and numbered in the order of their .subckt
+/// statements in each parent model: the second instance of `fa` in the first instance of `adder`
+/// is `adder_0/fa_1`.
+pub fn read_blif_with_hierarchy<R: std::io::Read>(
+    r: R,
+) -> Result<(Network, NameTable, HierarchyMap), QuaighError> {
+    let ret = read_blif_full(r)?;
+    Ok((ret.network, ret.names, ret.hierarchy))
+}
+
+/// Read a network in .blif format, with everything the file describes: names, hierarchy and
+/// external don't care network
+pub(super) fn read_blif_full<R: std::io::Read>(r: R) -> Result<ReadResult, QuaighError> {
+    let mut interner = Interner::default();
+    let statements = read_statements(r, &mut interner)?;
+    let Flattened {
+        statements,
+        instances,
+        mut hierarchy,
+    } = flatten(statements, &mut interner).map_err(QuaighError::parse)?;
+    let name_to_sig = build_name_to_sig(&statements, &interner).map_err(QuaighError::parse)?;
+    let Built {
+        aig,
+        translation,
+        origins,
+    } = build_network(&statements, &name_to_sig, &interner).map_err(QuaighError::parse)?;
+    let names = build_names(&statements, &name_to_sig, &interner, &translation);
+    for (node, statement) in origins.iter().enumerate() {
+        if let Some(inst) = instances[*statement] {
+            hierarchy.set_node_instance(node, inst);
+        }
+    }
+    hierarchy.remap(&translation);
+    let exdc = match statements.iter().position(|s| matches!(s, Statement::Exdc)) {
+        Some(pos) => Some(
+            build_exdc(&statements[..pos], &statements[pos + 1..], &mut interner)
+                .map_err(QuaighError::parse)?,
+        ),
+        None => None,
+    };
+    Ok(ReadResult {
+        network: aig,
+        names,
+        hierarchy,
+        exdc,
+        ..ReadResult::default()
+    })
+}
+
+/// Build the external don't care network from the statements following .exdc
+///
+/// The network has the same inputs as the main model, and one output for each of its outputs.
+/// An output is a don't care when it is 1; outputs not described in the .exdc section are never
+/// don't cares.
+fn build_exdc(
+    main: &[Statement],
+    exdc: &[Statement],
+    interner: &mut Interner,
+) -> Result<Network, String> {
+    let mut statements = Vec::new();
+    let mut outputs = Vec::new();
+    for statement in main {
+        match statement {
+            Statement::Inputs(inputs) => statements.push(Statement::Inputs(inputs.clone())),
+            Statement::Outputs(names) => outputs.extend(names.iter().copied()),
+            _ => (),
+        }
+    }
+    let mut defined = HashSet::new();
+    for statement in exdc {
+        match statement {
+            Statement::Name(names) => {
+                if let Some(name) = names.last() {
+                    defined.insert(*name);
+                }
+                statements.push(Statement::Name(names.clone()));
+            }
+            Statement::Cube(c) => statements.push(Statement::Cube(c.clone())),
+            Statement::Latch { .. } => {
+                return Err("Latches are not supported in the .exdc network".to_owned())
+            }
+            Statement::Exdc => return Err("Multiple .exdc sections".to_owned()),
+            _ => (),
+        }
+    }
+    // Outputs without a don't care condition use a constant zero
+    let zero = interner.intern("$exdc_zero");
+    statements.push(Statement::Name(vec![zero]));
+    let outputs = outputs
+        .into_iter()
+        .map(|name| if defined.contains(&name) { name } else { zero })
+        .collect();
+    statements.push(Statement::Outputs(outputs));
+    let name_to_sig = build_name_to_sig(&statements, interner)?;
+    Ok(build_network(&statements, &name_to_sig, interner)?.aig)
+}
+
+/// Read a network in .blif format, with its external don't care network if any
+///
+/// The don't care network is described after the .exdc statement. It has the same inputs and
+/// outputs as the main network, and an output is 1 when the value of the corresponding output of
+/// the main network does not matter.
+pub fn read_blif_with_exdc<R: std::io::Read>(
+    r: R,
+) -> Result<(Network, Option<Network>), QuaighError> {
+    let ret = read_blif_full(r)?;
+    Ok((ret.network, ret.exdc))
+}
+
+pub fn write_blif_cube<W: Write>(w: &mut W, mask: usize, num_vars: usize, val: bool) {
+    for i in 0..num_vars {
+        let val_i = (mask >> i) & 1 != 0;
+        write!(w, "{}", if val_i { "1" } else { "0" }).unwrap();
+    }
+    writeln!(w, "{}", if val { " 1" } else { " 0" }).unwrap();
+}
+
+/// Write the cover of a combinatorial gate, without the .names line
+fn write_gate_cover<W: Write>(w: &mut W, g: &Gate) {
+    match g {
+        Gate::Binary(_, BinaryType::And) => {
+            writeln!(w, "11 1").unwrap();
+        }
+        Gate::Binary(_, BinaryType::Xor) => {
+            writeln!(w, "10 1").unwrap();
+            writeln!(w, "01 1").unwrap();
+        }
+        Gate::Ternary(_, TernaryType::And) => {
+            writeln!(w, "111 1").unwrap();
+        }
+        Gate::Ternary(_, TernaryType::Xor) => {
+            writeln!(w, "111 1").unwrap();
+            writeln!(w, "100 1").unwrap();
+            writeln!(w, "010 1").unwrap();
+            writeln!(w, "001 1").unwrap();
+        }
+        Gate::Ternary(_, TernaryType::Mux) => {
+            writeln!(w, "11- 1").unwrap();
+            writeln!(w, "0-1 1").unwrap();
+        }
+        Gate::Ternary(_, TernaryType::Maj) => {
+            writeln!(w, "11- 1").unwrap();
+            writeln!(w, "-11 1").unwrap();
+            writeln!(w, "1-1 1").unwrap();
+        }
+        Gate::Nary(v, tp) => {
+            if matches!(
+                tp,
+                NaryType::And | NaryType::Nand | NaryType::Nor | NaryType::Or
+            ) {
+                let input_inv = matches!(tp, NaryType::Nor | NaryType::Or);
+                let output_inv = matches!(tp, NaryType::Or | NaryType::Nand);
+                for _ in 0..v.len() {
+                    if input_inv {
+                        write!(w, "0").unwrap();
+                    } else {
+                        write!(w, "1").unwrap();
+                    }
+                }
+                if output_inv {
+                    writeln!(w, " 0").unwrap();
+                } else {
+                    writeln!(w, " 1").unwrap();
+                }
+            } else {
+                for mask in 0usize..(1 << v.len()) {
+                    let xor_val = mask.count_ones() % 2 != 0;
+                    let val = match tp {
+                        NaryType::Xor => xor_val,
+                        NaryType::Xnor => !xor_val,
+                        _ => unreachable!(),
+                    };
+                    if val {
+                        write_blif_cube(w, mask, v.len(), val);
+                    }
+                }
+            }
+        }
+        Gate::Buf(s) => {
+            if s.is_inverted() {
+                writeln!(w, "0 1").unwrap();
+            } else {
+                writeln!(w, "1 1").unwrap();
+            }
+        }
+        Gate::Lut(lut) => {
+            for mask in 0..lut.lut.num_bits() {
+                let val = lut.lut.value(mask);
+                if val {
+                    write_blif_cube(w, mask, lut.lut.num_vars(), val);
+                }
+            }
+        }
+        _ => panic!("Gate type not supported"),
+    }
+}
+
+/// Write a network in .blif format
+///
+/// The format specification is available [here](https://course.ece.cmu.edu/~ee760/760docs/blif.pdf),
+/// with extensions introduced by [ABC](https://people.eecs.berkeley.edu/~alanmi/publications/other/boxes01.pdf)
+/// and [Yosys](https://yosyshq.readthedocs.io/projects/yosys/en/latest/cmd/write_blif.html) and
+/// [VPR](https://docs.verilogtorouting.org/en/latest/vpr/file_formats/).
+///
+/// Quaigh only support a small subset, with a single module. Clocks are only written if the
+/// network has several clock domains or falling-edge flip-flops.
+pub fn write_blif<W: Write>(w: &mut W, aig: &Network) {
+    write_blif_with_names(w, aig, &NameTable::new());
+}
+
+/// Write a network in .blif format, with the names of its signals
+///
+/// Unnamed signals use generated names. Outputs whose name is not the name of their signal are
+/// driven by an additional buffer.
+pub fn write_blif_with_names<W: Write>(w: &mut W, aig: &Network, names: &NameTable) {
+    write_blif_with_options(w, aig, names, false);
+}
+
+/// Write a network in .blif format, with the names of its signals and optionally folding the
+/// inversions into the gates
+///
+/// By default, each inverted signal is computed by an additional inverter. With
+/// `fold_inversions`, the covers of the gates use their inputs inverted instead, and inverters are
+/// only written for outputs and flip-flops.
+pub fn write_blif_with_options<W: Write>(
+    w: &mut W,
+    aig: &Network,
+    names: &NameTable,
+    fold_inversions: bool,
+) {
+    let namer = SignalNamer::new(aig, names);
+    let sig_to_string = |s: &Signal| namer.name(s);
+    let output_names = namer.output_names();
+    writeln!(w, "# .blif file").unwrap();
+    writeln!(w, "# Generated by quaigh").unwrap();
+    writeln!(w).unwrap();
+    writeln!(w, ".model quaigh").unwrap();
+    writeln!(w).unwrap();
+
+    // Write input specifiers
+    write!(w, ".inputs").unwrap();
+    for i in 0..aig.nb_inputs() {
+        write!(w, " {}", sig_to_string(&aig.input(i))).unwrap();
+    }
+    writeln!(w).unwrap();
+    writeln!(w).unwrap();
+
+    // Write output specifiers
+    write!(w, ".outputs").unwrap();
+    for name in &output_names {
+        write!(w, " {}", name).unwrap();
+    }
+    writeln!(w).unwrap();
+    writeln!(w).unwrap();
+
+    // Write latches; clocks are only written for designs with several clock domains
+    let multi_clock = aig.clock_domains().iter().any(|c| !c.is_default());
+    let clock_name = |clock: &ClockId| match names.clock_name(clock.index() as usize) {
+        Some(n) => n.to_owned(),
+        None => format!("clk{}", clock.index()),
+    };
+    for i in 0..aig.nb_nodes() {
+        if let Gate::Latch([d, en, res]) = &aig.gate(i) {
+            if *res != Signal::zero() {
+                panic!("Transparent latches with reset are not supported in .blif files");
+            }
+            let tp = if en.is_inverted() { "al" } else { "ah" };
+            let control = if *en == Signal::one() {
+                "NIL".to_owned()
+            } else {
+                sig_to_string(&en.without_inversion())
+            };
+            writeln!(
+                w,
+                ".latch {} {} {} {} 0",
+                sig_to_string(d),
+                sig_to_string(&aig.node(i)),
+                tp,
+                control
+            )
+            .unwrap();
+        }
+        if let Gate::Dff([d, en, res], clock) = &aig.gate(i) {
+            if *en != Signal::one() || *res != Signal::zero() {
+                // ABC extension to blif; the reset is synchronous and takes priority over the enable
+                if clock.is_falling_edge() {
+                    panic!("Flip-flops with enable or reset on a falling edge are not supported in .blif files");
+                }
+                write!(
+                    w,
+                    ".flop D={} Q={} init=0",
+                    sig_to_string(d),
+                    sig_to_string(&aig.node(i))
+                )
+                .unwrap();
+                if *en != Signal::one() {
+                    write!(w, " E={}", sig_to_string(en)).unwrap();
+                }
+                if *res != Signal::zero() {
+                    write!(w, " R={}", sig_to_string(res)).unwrap();
+                }
+                if multi_clock {
+                    write!(w, " C={}", clock_name(clock)).unwrap();
+                }
+                writeln!(w).unwrap();
+            } else if multi_clock {
+                let tp = if clock.is_falling_edge() { "fe" } else { "re" };
+                writeln!(
+                    w,
+                    ".latch {} {} {} {} 0",
+                    sig_to_string(d),
+                    sig_to_string(&aig.node(i)),
+                    tp,
+                    clock_name(clock)
+                )
+                .unwrap();
+            } else {
+                writeln!(
+                    w,
+                    ".latch {} {} 0",
+                    sig_to_string(d),
+                    sig_to_string(&aig.node(i))
+                )
+                .unwrap();
+            }
+        }
+    }
+    writeln!(w).unwrap();
+
+    // Write gates
+    for i in 0..aig.nb_nodes() {
+        let g = aig.gate(i);
+        if !g.is_comb() {
+            continue;
+        }
+        write!(w, ".names").unwrap();
+        // Inverted inputs that are folded in the cover
+        let mut folded = vec![false; g.dependencies().len()];
+        if let Gate::Buf(s) = &g {
+            // Buffers handle the inversions themselves
+            write!(w, " {}", sig_to_string(&s.without_inversion())).unwrap();
+        } else {
+            // Other signals use a buffered signal for inverted inputs, unless folded
+            for (s, f) in zip(g.dependencies(), folded.iter_mut()) {
+                *f = fold_inversions && s.is_inverted() && !s.is_constant();
+                let s = if *f { !*s } else { *s };
+                write!(w, " {}", sig_to_string(&s)).unwrap();
+            }
+        }
+        writeln!(w, " {}", sig_to_string(&aig.node(i))).unwrap();
+
+        let mut cover = Vec::new();
+        write_gate_cover(&mut cover, &g);
+        for line in String::from_utf8(cover).unwrap().lines() {
+            let (cube, value) = line.split_at(folded.len());
+            let cube: String = zip(cube.chars(), &folded)
+                .map(|(c, f)| match (c, f) {
+                    ('0', true) => '1',
+                    ('1', true) => '0',
+                    _ => c,
+                })
+                .collect();
+            writeln!(w, "{}{}", cube, value).unwrap();
+        }
+    }
+
+    // Write inverters
+    let signals_with_inv = get_inverted_signals(aig, |g| fold_inversions && g.is_comb());
+    for s in signals_with_inv {
+        writeln!(w, ".names {} {}", sig_to_string(&s), sig_to_string(&!s)).unwrap();
+        writeln!(w, "0 1").unwrap();
+    }
+
+    // Write buffers for the output names
+    for (i, name) in output_names.iter().enumerate() {
+        let s = sig_to_string(&aig.output(i));
+        if *name != s {
+            writeln!(w, ".names {} {}", s, name).unwrap();
+            writeln!(w, "1 1").unwrap();
+        }
+    }
+
+    // Write constants
+    writeln!(w, ".names vdd").unwrap();
+    writeln!(w, "1").unwrap();
+    writeln!(w, ".names gnd").unwrap();
+}
+
+mod test {
+    #[test]
+    fn test_basic_readwrite() {
+        use std::io::BufWriter;
+
+        let example = "# .blif file
+  .model test_file # Comment
+ .inputs a b c
+ .outputs e \
+ f g # Comment # and more
+
+ .names a b e
+ 00 1  # Comment
+
+ .names c b \
+   f
+ 01 1
+
+ .names g \
+";
+        let aig = super::read_blif(example.as_bytes()).unwrap();
+        assert_eq!(aig.nb_inputs(), 3);
+        assert_eq!(aig.nb_outputs(), 3);
+        assert_eq!(aig.nb_nodes(), 3);
+        let mut buf = BufWriter::new(Vec::new());
+        super::write_blif(&mut buf, &aig);
+        String::from_utf8(buf.into_inner().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_crlf_and_cubes() {
+        let example =
+            ".model test\r\n.inputs a b\r\n.outputs x\r\n.names a b x\r\n1- 1\r\n-1 1\r\n.end\r\n";
+        let (aig, names) = super::read_blif_with_names(example.as_bytes()).unwrap();
+        assert_eq!(aig.nb_inputs(), 2);
+        assert_eq!(names.input_name(1), Some("b"));
+        assert_eq!(names.output_name(0), Some("x"));
+        for bad in ["1x 1", "11 2", "1 1 1", "111 1"] {
+            let text = example.replace("1- 1", bad);
+            assert!(super::read_blif(text.as_bytes()).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_flop() {
+        use crate::{Gate, Signal};
+
+        let example = ".model test
+.inputs d e r
+.outputs q q2
+.latch d q2 0
+.flop D=d Q=q C=clk E=e R=r init=0
+.end
+";
+        let aig = super::read_blif(example.as_bytes()).unwrap();
+        assert_eq!(aig.nb_nodes(), 2);
+        assert_eq!(
+            aig.gate(1),
+            Gate::dff(aig.input(0), aig.input(1), aig.input(2))
+        );
+        assert_eq!(
+            aig.gate(0),
+            Gate::dff(aig.input(0), Signal::one(), Signal::zero())
+        );
+        let bad = example.replace("init=0", "init=1");
+        assert!(super::read_blif(bad.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_exdc() {
+        use crate::sim::simulate_comb;
+
+        let example = ".model test
+.inputs a b
+.outputs x y
+.names a b x
+11 1
+.names a b y
+10 1
+.exdc
+.names a b x
+00 1
+.end
+";
+        let (aig, exdc) = super::read_blif_with_exdc(example.as_bytes()).unwrap();
+        assert_eq!(aig.nb_outputs(), 2);
+        let exdc = exdc.unwrap();
+        assert_eq!(exdc.nb_inputs(), 2);
+        assert_eq!(exdc.nb_outputs(), 2);
+        assert_eq!(simulate_comb(&exdc, &vec![false, false]), vec![true, false]);
+        assert_eq!(simulate_comb(&exdc, &vec![true, false]), vec![false, false]);
+        let (_, none) =
+            super::read_blif_with_exdc(".model t\n.inputs a\n.outputs a\n".as_bytes()).unwrap();
+        assert!(none.is_none());
+    }
+
+    #[test]
+    fn test_names() {
+        let example = ".model test
+.inputs a b
+.outputs x q
+.latch x q 0
+.names a b x
+11 1
+.end
+";
+        let (aig, names) = super::read_blif_with_names(example.as_bytes()).unwrap();
+        assert_eq!(names.input_name(1), Some("b"));
+        assert_eq!(names.output_name(0), Some("x"));
+        assert_eq!(names.signal_name(aig.output(0)), Some("x"));
+        assert_eq!(names.signal_name(aig.output(1)), Some("q"));
+
+        let mut buf = Vec::new();
+        super::write_blif_with_names(&mut buf, &aig, &names);
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains(".inputs a b"), "{}", text);
+        assert!(text.contains(".latch x q 0"), "{}", text);
+        let (aig2, names2) = super::read_blif_with_names(text.as_bytes()).unwrap();
+        for i in 0..2 {
+            assert_eq!(names2.input_name(i), names.input_name(i));
+            assert_eq!(names2.output_name(i), names.output_name(i));
+            assert_eq!(names2.signal_name(aig2.output(i)), names.output_name(i));
+        }
+    }
+
+    #[test]
+    fn test_clocks() {
+        use crate::network::ClockId;
+
+        let example = ".model test
+.inputs d clka clkb
+.outputs q1 q2 q3
+.latch d q1 re clka 0
+.latch d q2 fe clkb 0
+.flop D=d Q=q3 C=clkb
+.end
+";
+        let (aig, names) = super::read_blif_with_names(example.as_bytes()).unwrap();
+        assert_eq!(aig.gate(0).clock(), Some(ClockId::rising(0)));
+        assert_eq!(aig.gate(1).clock(), Some(ClockId::falling(1)));
+        assert_eq!(aig.gate(2).clock(), Some(ClockId::rising(1)));
+        assert_eq!(names.clock_name(1), Some("clkb"));
+        assert_eq!(
+            aig.clock_domains(),
+            vec![ClockId::rising(0), ClockId::rising(1), ClockId::falling(1)]
+        );
+
+        let mut buf = Vec::new();
+        super::write_blif_with_names(&mut buf, &aig, &names);
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains("fe clkb"), "{}", text);
+        let aig2 = super::read_blif(text.as_bytes()).unwrap();
+        assert_eq!(aig2.clock_domains(), aig.clock_domains());
+
+        let bad = example.replace(" fe ", " as ");
+        assert!(super::read_blif(bad.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_too_many_clocks() {
+        use crate::network::MAX_CLOCKS;
+
+        let mut example = String::from(".model test\n.inputs d\n.outputs q0\n");
+        for i in 0..=MAX_CLOCKS {
+            example += &format!(".latch d q{i} re clk{i} 0\n");
+        }
+        example += ".end\n";
+        let err = super::read_blif(example.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("Too many clocks"), "{}", err);
+    }
+
+    #[test]
+    fn test_latch() {
+        use crate::sim::simulate;
+
+        // Clock gating: the enable is latched while the clock is low
+        let example = ".model icg
+.inputs en clk
+.outputs l gclk
+.latch en l al clk 0
+.names l clk gclk
+11 1
+.end
+";
+        let aig = super::read_blif(example.as_bytes()).unwrap();
+        assert!(aig.has_latches());
+        assert!(aig.is_topo_sorted());
+        let pattern = vec![
+            vec![true, false],
+            vec![false, true],
+            vec![false, false],
+            vec![true, true],
+        ];
+        let expected = vec![
+            vec![true, false],
+            vec![true, true],
+            vec![false, false],
+            vec![false, false],
+        ];
+        assert_eq!(simulate(&aig, &pattern), expected);
+
+        let mut buf = Vec::new();
+        super::write_blif(&mut buf, &aig);
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains(" al "), "{}", text);
+        let aig2 = super::read_blif(text.as_bytes()).unwrap();
+        assert_eq!(simulate(&aig2, &pattern), expected);
+    }
+
+    #[test]
+    fn test_subckt() {
+        use crate::sim::simulate_comb;
+
+        let example = ".model top
+.inputs a b c
+.outputs s co
+.subckt fa x=a y=b z=c s=s c=co
+.end
+
+.model fa
+.inputs x y z
+.outputs s c
+.subckt ha a=x b=y s=s1 c=c1
+.subckt ha a=s1 b=z s=s c=c2
+.names c1 c2 c
+00 0
+.end
+
+.model ha
+.inputs a b
+.outputs s c
+.names a b s
+10 1
+01 1
+.names a b c
+11 1
+.end
+";
+        let (aig, names, hierarchy) = super::read_blif_with_hierarchy(example.as_bytes()).unwrap();
+        assert_eq!(aig.nb_inputs(), 3);
+        assert_eq!(aig.nb_outputs(), 2);
+        // Each Xor is built from two cubes
+        assert_eq!(aig.nb_nodes(), 9);
+        for i in 0..8 {
+            let v: Vec<bool> = (0..3).map(|j| (i >> j) & 1 != 0).collect();
+            let nb = v.iter().filter(|b| **b).count();
+            assert_eq!(simulate_comb(&aig, &v), vec![nb % 2 == 1, nb >= 2]);
+        }
+        assert_eq!(hierarchy.nb_instances(), 3);
+        assert_eq!(hierarchy.instance(2).path, "fa_0/ha_1");
+        assert_eq!(hierarchy.instance(2).model, "ha");
+        assert_eq!(hierarchy.instance_sizes(), vec![1, 4, 4]);
+        let s1 = names.find("fa_0/s1").unwrap();
+        assert_eq!(
+            hierarchy.describe_node(s1.var() as usize),
+            Some("fa_0/ha_0")
+        );
+        let co = aig.output(1);
+        assert_eq!(hierarchy.describe_node(co.var() as usize), Some("fa_0"));
+
+        let bad = example.replace("a=s1 b=z", "a=s1");
+        assert!(super::read_blif(bad.as_bytes()).is_err());
+        let bad = example.replace(".subckt ha a=x", ".subckt fa a=x");
+        assert!(super::read_blif(bad.as_bytes()).is_err());
+        let bad = example.replace(".subckt fa x=a", ".subckt adder x=a");
+        assert!(super::read_blif(bad.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,114 @@
+//! IO for timing constraints
+
+use std::io::{BufRead, BufReader, Read};
+
+use crate::network::timing::TimingConstraints;
+use crate::network::NameTable;
+use crate::Network;
+
+/// Index of a named input
+fn input_index(names: &NameTable, name: &str) -> Result<usize, String> {
+    match names.resolve(name) {
+        Some(s) if s.is_input() => Ok(s.input() as usize),
+        Some(_) => Err(format!("{} is not an input", name)),
+        None => Err(format!("Unknown input {}", name)),
+    }
+}
+
+/// Index of a named output, by its name or as `o3`
+fn output_index(aig: &Network, names: &NameTable, name: &str) -> Result<usize, String> {
+    if let Some(o) = (0..aig.nb_outputs()).find(|o| names.output_name(*o) == Some(name)) {
+        return Ok(o);
+    }
+    match name.strip_prefix('o').and_then(|i| i.parse::<usize>().ok()) {
+        Some(o) if o < aig.nb_outputs() => Ok(o),
+        _ => Err(format!("Unknown output {}", name)),
+    }
+}
+
+/// Read timing constraints for a network
+///
+/// Each line gives the clock period, the arrival time of an input or the required time of an
+/// output, as integers in the same unit as the gate delays:
+/// ```text
+///     # Comments start with # or *
+///     clock_period 10
+///     input_arrival a 2
+///     output_required sum 8
+/// ```
+/// Inputs arrive at time zero by default, and outputs without a required time are required at
+/// the clock period.
+pub fn read_constraints<R: Read>(
+    r: R,
+    aig: &Network,
+    names: &NameTable,
+) -> Result<TimingConstraints, String> {
+    let mut ret = TimingConstraints::new(aig);
+    for (i, line) in BufReader::new(r).lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let t = line.trim();
+        if t.is_empty() || t.starts_with('#') || t.starts_with('*') {
+            continue;
+        }
+        let tokens: Vec<&str> = t.split_whitespace().collect();
+        let parse_time = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|_| format!("Line {}: invalid time {}", i + 1, s))
+        };
+        match tokens[..] {
+            ["clock_period", period] => ret.clock_period = Some(parse_time(period)?),
+            ["input_arrival", name, time] => {
+                let input =
+                    input_index(names, name).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+                ret.input_arrival[input] = parse_time(time)?;
+            }
+            ["output_required", name, time] => {
+                let output =
+                    output_index(aig, names, name).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+                ret.output_required[output] = Some(parse_time(time)?);
+            }
+            _ => {
+                return Err(format!(
+                    "Line {}: expected clock_period, input_arrival or output_required",
+                    i + 1
+                ))
+            }
+        }
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read_constraints;
+    use crate::network::NameTable;
+    use crate::Network;
+
+    #[test]
+    fn test_constraints() {
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let x = aig.and(a, b);
+        aig.add_output(x);
+        aig.add_output(a);
+        let mut names = NameTable::new();
+        names.set_input_name(0, "a");
+        names.set_output_name(0, "sum");
+
+        let text = "# Constraints\nclock_period 10\n\ninput_arrival a 2\ninput_arrival i1 3\n\
+                    output_required sum 8\n";
+        let c = read_constraints(text.as_bytes(), &aig, &names).unwrap();
+        assert_eq!(c.clock_period, Some(10));
+        assert_eq!(c.input_arrival, vec![2, 3]);
+        assert_eq!(c.output_required, vec![Some(8), None]);
+        assert_eq!(c.required_time(1), Some(10));
+
+        let c = read_constraints("output_required o1 4".as_bytes(), &aig, &names).unwrap();
+        assert_eq!(c.output_required, vec![None, Some(4)]);
+        assert!(read_constraints("input_arrival x0 1".as_bytes(), &aig, &names).is_err());
+        assert!(read_constraints("output_required o2 1".as_bytes(), &aig, &names).is_err());
+        assert!(read_constraints("clock_period -1".as_bytes(), &aig, &names).is_err());
+        assert!(read_constraints("set_max_delay 2".as_bytes(), &aig, &names).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+//! IO for per-gate delay annotations
+
+use std::io::{BufRead, BufReader, Read};
+
+use crate::network::NameTable;
+use crate::Network;
+
+/// Index of the gate driving a named signal
+fn gate_index(aig: &Network, names: &NameTable, name: &str) -> Result<usize, String> {
+    match names.resolve(name) {
+        Some(s) if s.is_var() && (s.var() as usize) < aig.nb_nodes() => Ok(s.var() as usize),
+        Some(_) => Err(format!("{} is not driven by a gate", name)),
+        None => Err(format!("Unknown signal {}", name)),
+    }
+}
+
+/// Read a table of gate delays, overriding the delays of the named gates
+///
+/// Each line gives the name of the signal driven by a gate and its delay, as an integer:
+/// ```text
+///     # Comments start with # or *
+///     x12 3
+///     carry 5
+/// ```
+pub fn read_delays<R: Read>(
+    r: R,
+    aig: &Network,
+    names: &NameTable,
+    delays: &mut [usize],
+) -> Result<(), String> {
+    assert_eq!(delays.len(), aig.nb_nodes());
+    for (i, line) in BufReader::new(r).lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let t = line.trim();
+        if t.is_empty() || t.starts_with('#') || t.starts_with('*') {
+            continue;
+        }
+        let tokens: Vec<&str> = t.split_whitespace().collect();
+        let [name, delay] = tokens[..] else {
+            return Err(format!(
+                "Line {}: expected a signal name and a delay",
+                i + 1
+            ));
+        };
+        let delay = delay
+            .parse()
+            .map_err(|_| format!("Line {}: invalid delay {}", i + 1, delay))?;
+        delays[gate_index(aig, names, name)?] = delay;
+    }
+    Ok(())
+}
+
+/// Node of a parsed SDF file
+#[derive(Debug)]
+enum Sexp {
+    Atom(String),
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    /// Children of a list starting with the given keyword
+    fn keyword(&self, kw: &str) -> Option<&[Sexp]> {
+        match self {
+            Sexp::List(v) => match v.first() {
+                Some(Sexp::Atom(a)) if a.eq_ignore_ascii_case(kw) => Some(&v[1..]),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Children of the first list child starting with the given keyword
+    fn find(&self, kw: &str) -> Option<&[Sexp]> {
+        match self {
+            Sexp::List(v) => v.iter().find_map(|c| c.keyword(kw)),
+            _ => None,
+        }
+    }
+
+    fn atom(&self) -> Option<&str> {
+        match self {
+            Sexp::Atom(a) => Some(a),
+            _ => None,
+        }
+    }
+}
+
+/// Parse an SDF file as nested lists
+fn parse_sexp(text: &str) -> Result<Sexp, String> {
+    let mut stack: Vec<Vec<Sexp>> = vec![Vec::new()];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => stack.push(Vec::new()),
+            ')' => {
+                let l = stack.pop().unwrap();
+                let parent = stack.last_mut().ok_or("Unbalanced parentheses")?;
+                parent.push(Sexp::List(l));
+            }
+            '"' => {
+                let mut s = String::new();
+                for c in chars.by_ref() {
+                    if c == '"' {
+                        break;
+                    }
+                    s.push(c);
+                }
+                stack.last_mut().unwrap().push(Sexp::Atom(s));
+            }
+            _ if c.is_whitespace() => (),
+            _ => {
+                let mut s = String::from(c);
+                while let Some(&n) = chars.peek() {
+                    if n.is_whitespace() || n == '(' || n == ')' || n == '"' {
+                        break;
+                    }
+                    s.push(n);
+                    chars.next();
+                }
+                stack.last_mut().unwrap().push(Sexp::Atom(s));
+            }
+        }
+    }
+    if stack.len() != 1 {
+        return Err("Unbalanced parentheses".to_owned());
+    }
+    let mut top = stack.pop().unwrap();
+    match top.len() {
+        1 => Ok(top.pop().unwrap()),
+        _ => Err("Expected a single DELAYFILE".to_owned()),
+    }
+}
+
+/// Number of picoseconds in a time unit
+fn unit_in_ps(unit: &str) -> Result<f64, String> {
+    match unit.to_ascii_lowercase().as_str() {
+        "s" => Ok(1e12),
+        "ms" => Ok(1e9),
+        "us" => Ok(1e6),
+        "ns" => Ok(1e3),
+        "ps" => Ok(1.0),
+        "fs" => Ok(1e-3),
+        _ => Err(format!("Invalid time unit {}", unit)),
+    }
+}
+
+/// Parse the TIMESCALE of an SDF file, in picoseconds
+fn parse_timescale(args: &[Sexp]) -> Result<f64, String> {
+    let text: String = args.iter().filter_map(|a| a.atom()).collect();
+    let split = text
+        .find(|c: char| c.is_ascii_alphabetic())
+        .ok_or_else(|| format!("Invalid timescale {}", text))?;
+    let (value, unit) = text.split_at(split);
+    let value: f64 = if value.is_empty() {
+        1.0
+    } else {
+        value
+            .parse()
+            .map_err(|_| format!("Invalid timescale {}", text))?
+    };
+    Ok(value * unit_in_ps(unit)?)
+}
+
+/// Delay of a value list such as `(1.0:1.2:1.5)`, using the typical value; None if empty
+fn parse_delay_value(v: &Sexp) -> Result<Option<f64>, String> {
+    let Sexp::List(l) = v else {
+        return Err("Expected a delay value".to_owned());
+    };
+    let Some(text) = l.first().and_then(|a| a.atom()) else {
+        return Ok(None);
+    };
+    let parts: Vec<&str> = text.split(':').collect();
+    let typ = if parts.len() == 3 { parts[1] } else { parts[0] };
+    if typ.is_empty() {
+        return Ok(None);
+    }
+    typ.parse()
+        .map(Some)
+        .map_err(|_| format!("Invalid delay {}", text))
+}
+
+/// Read the gate delays of an SDF file, overriding the delays of the annotated gates
+///
+/// Only a subset of SDF is supported. Each CELL is a gate identified by its INSTANCE, which is the
+/// name of the signal it drives, and its delay is the largest of its IOPATH delays, using the
+/// typical value of rise and fall delays. Delays are converted to picoseconds using the
+/// TIMESCALE, 1ns by default, and rounded.
+pub fn read_sdf_delays<R: Read>(
+    mut r: R,
+    aig: &Network,
+    names: &NameTable,
+    delays: &mut [usize],
+) -> Result<(), String> {
+    assert_eq!(delays.len(), aig.nb_nodes());
+    let mut text = String::new();
+    r.read_to_string(&mut text).map_err(|e| e.to_string())?;
+    let sdf = parse_sexp(&text)?;
+    let Sexp::List(items) = &sdf else {
+        return Err("Expected a DELAYFILE".to_owned());
+    };
+    if sdf.keyword("DELAYFILE").is_none() {
+        return Err("Expected a DELAYFILE".to_owned());
+    }
+    let scale = match sdf.find("TIMESCALE") {
+        Some(args) => parse_timescale(args)?,
+        None => 1e3,
+    };
+    for cell in items.iter().filter(|c| c.keyword("CELL").is_some()) {
+        let instance = cell
+            .find("INSTANCE")
+            .and_then(|i| i.first())
+            .and_then(|i| i.atom())
+            .ok_or("CELL without an INSTANCE")?;
+        let mut delay: Option<f64> = None;
+        let Sexp::List(children) = cell else {
+            unreachable!()
+        };
+        for d in children.iter().filter_map(|c| c.keyword("DELAY")) {
+            for abs in d.iter().filter_map(|c| c.keyword("ABSOLUTE")) {
+                for path in abs.iter().filter_map(|c| c.keyword("IOPATH")) {
+                    for v in path.iter().skip(2) {
+                        if let Some(x) = parse_delay_value(v)? {
+                            delay = Some(delay.map_or(x, |d| d.max(x)));
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(d) = delay {
+            if d < 0.0 {
+                return Err(format!("Negative delay for {}", instance));
+            }
+            delays[gate_index(aig, names, instance)?] = (d * scale).round() as usize;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_delays, read_sdf_delays};
+    use crate::network::NameTable;
+    use crate::Network;
+
+    fn example() -> (Network, NameTable) {
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let x = aig.and(a, b);
+        let y = aig.xor(x, a);
+        aig.add_output(y);
+        let mut names = NameTable::new();
+        names.set_node_name(1, "sum");
+        (aig, names)
+    }
+
+    #[test]
+    fn test_table() {
+        let (aig, names) = example();
+        let mut delays = vec![1, 1];
+        let table = "# Delays\nx0 4\n\nsum 7\n";
+        read_delays(table.as_bytes(), &aig, &names, &mut delays).unwrap();
+        assert_eq!(delays, vec![4, 7]);
+        assert!(read_delays("i0 3".as_bytes(), &aig, &names, &mut delays).is_err());
+        assert!(read_delays("sum".as_bytes(), &aig, &names, &mut delays).is_err());
+    }
+
+    #[test]
+    fn test_sdf() {
+        let (aig, names) = example();
+        let mut delays = vec![1, 1];
+        let sdf = r#"
+            (DELAYFILE
+              (SDFVERSION "3.0")
+              (TIMESCALE 100ps)
+              (CELL (CELLTYPE "AND2") (INSTANCE x0)
+                (DELAY (ABSOLUTE (IOPATH A Y (0.1:0.2:0.3) (0.3:0.4:0.6)) (IOPATH B Y (0.1) ()))))
+              (CELL (CELLTYPE "XOR2") (INSTANCE "sum")
+                (DELAY (ABSOLUTE (IOPATH A Y (1.5::2.0)))))
+            )"#;
+        read_sdf_delays(sdf.as_bytes(), &aig, &names, &mut delays).unwrap();
+        assert_eq!(delays, vec![40, 1]);
+        assert!(read_sdf_delays("(DELAYFILE".as_bytes(), &aig, &names, &mut delays).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! Export to Graphviz .dot files, for visualization
+
+use std::io::Write;
+
+use crate::network::{BinaryType, NameTable, NaryType, TernaryType};
+use crate::sim::Fault;
+use crate::{Gate, Network, Signal};
+
+use super::utils::SignalNamer;
+
+/// Elements to highlight when exporting a network to .dot
+#[derive(Clone, Debug, Default)]
+pub struct DotHighlight {
+    /// Signals along a path, such as a critical path, whose nodes and edges are highlighted
+    pub path: Vec<Signal>,
+    /// Fault whose site is highlighted: the faulty gate, and the faulty edge for input faults
+    pub fault: Option<Fault>,
+}
+
+/// Identifier of the node driving a signal in the .dot graph
+fn node_id(s: Signal) -> String {
+    if s.is_constant() {
+        format!("c{}", s.is_inverted() as u8)
+    } else if s.is_input() {
+        format!("i{}", s.input())
+    } else {
+        format!("n{}", s.var())
+    }
+}
+
+/// Escape a name for use in a quoted .dot label
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Label and shape of a gate in the .dot graph
+fn gate_style(g: &Gate) -> (&'static str, &'static str) {
+    use Gate::*;
+    match g {
+        Binary(_, BinaryType::And) | Ternary(_, TernaryType::And) | Nary(_, NaryType::And) => {
+            ("AND", "ellipse")
+        }
+        Nary(_, NaryType::Nand) => ("NAND", "ellipse"),
+        Nary(_, NaryType::Or) => ("OR", "ellipse"),
+        Nary(_, NaryType::Nor) => ("NOR", "ellipse"),
+        Binary(_, BinaryType::Xor) | Ternary(_, TernaryType::Xor) | Nary(_, NaryType::Xor) => {
+            ("XOR", "diamond")
+        }
+        Nary(_, NaryType::Xnor) => ("XNOR", "diamond"),
+        Ternary(_, TernaryType::Maj) => ("MAJ", "hexagon"),
+        Ternary(_, TernaryType::Mux) => ("MUX", "trapezium"),
+        Buf(s) => {
+            if s.is_inverted() {
+                ("NOT", "circle")
+            } else {
+                ("BUF", "circle")
+            }
+        }
+        Dff(_, _) => ("DFF", "box"),
+        Latch(_) => ("LATCH", "box"),
+        Lut(_) => ("LUT", "box3d"),
+    }
+}
+
+/// Write a network as a Graphviz graph
+///
+/// Gates have a shape depending on their type, and inverted edges end with a dot. Signals use
+/// the names of the name table, or generated names if unnamed.
+pub fn write_dot<W: Write>(w: &mut W, aig: &Network, names: &NameTable, highlight: &DotHighlight) {
+    let namer = SignalNamer::new(aig, names);
+    let output_names = namer.output_names();
+    let on_path = |s: Signal| highlight.path.contains(&s.without_inversion());
+    let path_edge = |from: Signal, to: Signal| {
+        highlight
+            .path
+            .windows(2)
+            .any(|p| p[0].without_inversion() == from.without_inversion() && p[1] == to)
+    };
+    let style = |highlighted: bool| {
+        if highlighted {
+            ", color=red, penwidth=2"
+        } else {
+            ""
+        }
+    };
+    let fault_gate = highlight.fault.map(|f| f.gate());
+    let fault_input = highlight.fault.and_then(|f| f.input());
+
+    writeln!(w, "digraph network {{").unwrap();
+    writeln!(w, "  rankdir=LR;").unwrap();
+    for i in 0..aig.nb_inputs() {
+        let s = aig.input(i);
+        writeln!(
+            w,
+            "  {} [label=\"{}\", shape=triangle, orientation=270{}];",
+            node_id(s),
+            escape(&namer.name(&s)),
+            style(on_path(s))
+        )
+        .unwrap();
+    }
+    let mut used_constants = [false; 2];
+    for i in 0..aig.nb_nodes() {
+        for s in aig.dependencies(i) {
+            if s.is_constant() {
+                used_constants[s.is_inverted() as usize] = true;
+            }
+        }
+    }
+    for i in 0..aig.nb_outputs() {
+        if aig.output(i).is_constant() {
+            used_constants[aig.output(i).is_inverted() as usize] = true;
+        }
+    }
+    for (v, used) in used_constants.iter().enumerate() {
+        if *used {
+            writeln!(w, "  c{} [label=\"{}\", shape=plaintext];", v, v).unwrap();
+        }
+    }
+    for i in 0..aig.nb_nodes() {
+        let s = aig.node(i);
+        let (label, shape) = gate_style(&aig.gate(i));
+        let fault = if fault_gate == Some(i) {
+            ", style=filled, fillcolor=orange"
+        } else {
+            ""
+        };
+        writeln!(
+            w,
+            "  {} [label=\"{}\\n{}\", shape={}{}{}];",
+            node_id(s),
+            label,
+            escape(&namer.name(&s)),
+            shape,
+            style(on_path(s)),
+            fault
+        )
+        .unwrap();
+    }
+    for (i, name) in output_names.iter().enumerate() {
+        writeln!(
+            w,
+            "  o{} [label=\"{}\", shape=triangle, orientation=90];",
+            i,
+            escape(name)
+        )
+        .unwrap();
+    }
+
+    let edge = |w: &mut W, from: Signal, to: &str, attrs: &str| {
+        let inv = if from.is_inverted() && !from.is_constant() {
+            "arrowhead=odot"
+        } else {
+            "arrowhead=normal"
+        };
+        writeln!(w, "  {} -> {} [{}{}];", node_id(from), to, inv, attrs).unwrap();
+    };
+    for i in 0..aig.nb_nodes() {
+        let to = aig.node(i);
+        for (j, s) in aig.dependencies(i).iter().enumerate() {
+            let mut attrs = String::from(style(path_edge(*s, to)));
+            if fault_gate == Some(i) && fault_input == Some(j) {
+                attrs.push_str(", color=orange, penwidth=2");
+            }
+            edge(w, *s, &node_id(to), &attrs);
+        }
+    }
+    for i in 0..aig.nb_outputs() {
+        let s = aig.output(i);
+        let highlighted = highlight.path.last() == Some(&s);
+        edge(w, s, &format!("o{}", i), style(highlighted));
+    }
+    writeln!(w, "}}").unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_dot, DotHighlight};
+    use crate::network::NameTable;
+    use crate::sim::Fault;
+    use crate::{Network, Signal};
+
+    fn to_string(aig: &Network, highlight: &DotHighlight) -> String {
+        let mut buf = Vec::new();
+        write_dot(&mut buf, aig, &NameTable::new(), highlight);
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_dot() {
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let x = aig.and(a, !b);
+        let y = aig.xor(x, a);
+        let d = aig.dff(y, Signal::one(), Signal::zero());
+        aig.add_output(d);
+        aig.add_output(Signal::zero());
+
+        let s = to_string(&aig, &DotHighlight::default());
+        assert!(s.starts_with("digraph network {"));
+        assert!(s.trim_end().ends_with('}'));
+        assert!(s.contains("n0 [label=\"AND\\nx0\", shape=ellipse];"));
+        assert!(s.contains("n1 [label=\"XOR\\nx1\", shape=diamond];"));
+        assert!(s.contains("n2 [label=\"DFF\\nx2\", shape=box];"));
+        assert!(s.contains("i1 -> n0 [arrowhead=odot];"));
+        assert!(s.contains("i0 -> n0 [arrowhead=normal];"));
+        assert!(s.contains("c0 -> o1 [arrowhead=normal];"));
+        assert!(!s.contains("color"));
+
+        let highlight = DotHighlight {
+            path: vec![a, x, y],
+            fault: Some(Fault::InputStuckAtFault {
+                gate: 1,
+                input: 1,
+                value: false,
+            }),
+        };
+        let s = to_string(&aig, &highlight);
+        assert!(s.contains("i0 -> n0 [arrowhead=normal, color=red, penwidth=2];"));
+        assert!(s.contains("n0 -> n1 [arrowhead=normal, color=red, penwidth=2];"));
+        assert!(s.contains("i0 -> n1 [arrowhead=normal, color=orange, penwidth=2];"));
+        assert!(s.contains("fillcolor=orange"));
+        assert!(!s.contains("n1 -> n2 [arrowhead=normal, color=red"));
+    }
+}
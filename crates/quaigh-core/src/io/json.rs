@@ -0,0 +1,819 @@
+//! IO for Yosys JSON netlists
+//!
+//! Only the top module is read. Bitwise cells, reductions, multiplexers and Luts are supported,
+//! both as coarse-grain cells (`$and`, `$mux`, ...) and as gate-level cells (`$_AND_`, `$_MUX_`,
+//! ...), as well as flip-flops with an optional enable and synchronous reset to zero (`$dff`,
+//! `$dffe`, `$sdff`, `$sdffe` and their gate-level counterparts). The nets driving the clocks of
+//! the flip-flops become clocks of the network rather than inputs.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use serde_json::{json, Map, Value};
+use volute::Lut;
+
+use crate::network::{BinaryType, ClockId, NameTable, NaryType, TernaryType, MAX_CLOCKS};
+use crate::{Gate, Network, QuaighError, Signal};
+
+use super::utils::SignalNamer;
+
+/// Bit of a Yosys netlist, either a net or a constant
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Bit {
+    Net(u64),
+    Const(bool),
+}
+
+/// Parse an array of bits
+///
+/// Undefined and high-impedance bits are read as 0.
+fn parse_bits(v: &Value) -> Result<Vec<Bit>, String> {
+    let bits = v
+        .as_array()
+        .ok_or_else(|| format!("Expected an array of bits, got {}", v))?;
+    bits.iter()
+        .map(|b| match b {
+            Value::Number(n) => n
+                .as_u64()
+                .map(Bit::Net)
+                .ok_or_else(|| format!("Invalid bit {}", n)),
+            Value::String(s) => match s.as_str() {
+                "0" | "x" | "z" => Ok(Bit::Const(false)),
+                "1" => Ok(Bit::Const(true)),
+                _ => Err(format!("Invalid bit {}", s)),
+            },
+            _ => Err(format!("Invalid bit {}", b)),
+        })
+        .collect()
+}
+
+/// Parse a parameter or attribute value, as bits with the least significant first
+///
+/// Yosys writes them as binary strings, or occasionally as integers.
+fn parse_value(v: &Value) -> Result<Vec<bool>, String> {
+    match v {
+        Value::Number(n) => {
+            let n = n
+                .as_u64()
+                .ok_or_else(|| format!("Invalid parameter value {}", n))?;
+            Ok((0..64).map(|i| (n >> i) & 1 != 0).collect())
+        }
+        Value::String(s) => s
+            .chars()
+            .rev()
+            .map(|c| match c {
+                '0' | 'x' | 'z' => Ok(false),
+                '1' => Ok(true),
+                _ => Err(format!("Invalid parameter value {}", s)),
+            })
+            .collect(),
+        _ => Err(format!("Invalid parameter value {}", v)),
+    }
+}
+
+/// Name of the i-th bit of a multi-bit net or port
+fn bit_name(name: &str, i: usize, width: usize) -> String {
+    if width == 1 {
+        name.to_owned()
+    } else {
+        format!("{}[{}]", name, i)
+    }
+}
+
+/// Flip-flop cell, with the polarity of its clock and its optional enable and reset ports
+struct FlipFlop {
+    falling_edge: bool,
+    enable: Option<(&'static str, bool)>,
+    reset: Option<(&'static str, bool)>,
+}
+
+/// Polarity encoded by a character of a gate-level cell name
+fn polarity(c: u8) -> Result<bool, String> {
+    match c {
+        b'P' => Ok(true),
+        b'N' => Ok(false),
+        _ => Err(format!("Invalid polarity {}", c as char)),
+    }
+}
+
+/// Description of a flip-flop cell, or None if the cell is not a flip-flop
+fn flip_flop(tp: &str, cell: &Value) -> Result<Option<FlipFlop>, String> {
+    let param = |name: &str| -> Result<bool, String> {
+        match cell.get("parameters").and_then(|p| p.get(name)) {
+            Some(v) => Ok(parse_value(v)?.iter().any(|b| *b)),
+            None => Err(format!("Missing parameter {} for cell {}", name, tp)),
+        }
+    };
+    let zero_reset = || -> Result<(), String> {
+        let value = cell
+            .get("parameters")
+            .and_then(|p| p.get("SRST_VALUE"))
+            .map(parse_value)
+            .transpose()?
+            .unwrap_or_default();
+        if value.iter().any(|b| *b) {
+            Err("Only flip-flops with a reset to zero are supported".to_owned())
+        } else {
+            Ok(())
+        }
+    };
+    let b = tp.as_bytes();
+    let ret = match tp {
+        "$dff" | "$dffe" | "$sdff" | "$sdffe" => {
+            let enable = if tp.ends_with('e') {
+                Some(("EN", param("EN_POLARITY")?))
+            } else {
+                None
+            };
+            let reset = if tp.starts_with("$s") {
+                zero_reset()?;
+                Some(("SRST", param("SRST_POLARITY")?))
+            } else {
+                None
+            };
+            FlipFlop {
+                falling_edge: !param("CLK_POLARITY")?,
+                enable,
+                reset,
+            }
+        }
+        _ if tp.len() == 8 && tp.starts_with("$_DFF_") => FlipFlop {
+            falling_edge: !polarity(b[6])?,
+            enable: None,
+            reset: None,
+        },
+        _ if tp.len() == 10 && tp.starts_with("$_DFFE_") => FlipFlop {
+            falling_edge: !polarity(b[7])?,
+            enable: Some(("E", polarity(b[8])?)),
+            reset: None,
+        },
+        _ if tp.len() == 11 && tp.starts_with("$_SDFF_") && b[9] == b'0' => FlipFlop {
+            falling_edge: !polarity(b[7])?,
+            enable: None,
+            reset: Some(("R", polarity(b[8])?)),
+        },
+        _ if tp.len() == 13 && tp.starts_with("$_SDFFE_") && b[10] == b'0' => FlipFlop {
+            falling_edge: !polarity(b[8])?,
+            enable: Some(("E", polarity(b[11])?)),
+            reset: Some(("R", polarity(b[9])?)),
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(ret))
+}
+
+/// Name of the clock port of a flip-flop cell
+fn clock_port(tp: &str) -> &'static str {
+    if tp.starts_with("$_") {
+        "C"
+    } else {
+        "CLK"
+    }
+}
+
+/// Return whether an attribute value is set
+fn is_set(v: &Value) -> bool {
+    parse_value(v).is_ok_and(|b| b.iter().any(|x| *x))
+}
+
+/// Find the top module: the one with the `top` attribute, or the only module
+fn find_top(modules: &Map<String, Value>) -> Result<&Value, String> {
+    let mut tops = modules.values().filter(|m| {
+        m.get("attributes")
+            .and_then(|a| a.get("top"))
+            .is_some_and(is_set)
+    });
+    if let Some(m) = tops.next() {
+        return Ok(m);
+    }
+    if modules.len() == 1 {
+        Ok(modules.values().next().unwrap())
+    } else {
+        Err("Several modules without a top module".to_owned())
+    }
+}
+
+/// Read a network in Yosys JSON format, with the names of its signals
+pub fn read_json_with_names<R: Read>(r: R) -> Result<(Network, NameTable), QuaighError> {
+    parse_json(r).map_err(QuaighError::parse)
+}
+
+fn parse_json<R: Read>(r: R) -> Result<(Network, NameTable), String> {
+    let v: Value = serde_json::from_reader(r).map_err(|e| e.to_string())?;
+    let modules = v
+        .get("modules")
+        .and_then(|m| m.as_object())
+        .ok_or("No modules in the netlist")?;
+    let module = find_top(modules)?;
+    let empty = Map::new();
+    let section = |name: &str| {
+        module
+            .get(name)
+            .and_then(|s| s.as_object())
+            .unwrap_or(&empty)
+    };
+    let ports = section("ports");
+    let cells = section("cells");
+    let netnames = section("netnames");
+
+    let mut aig = Network::new();
+    let mut names = NameTable::new();
+    let mut net_to_sig: HashMap<u64, Signal> = HashMap::new();
+    let port = |cell_name: &str, cell: &Value, port: &str| -> Result<Vec<Bit>, String> {
+        let bits = cell
+            .get("connections")
+            .and_then(|c| c.get(port))
+            .ok_or_else(|| format!("Cell {} has no port {}", cell_name, port))?;
+        parse_bits(bits)
+    };
+
+    // Clocks, in order of appearance
+    let mut clocks: HashMap<u64, u32> = HashMap::new();
+    for (name, cell) in cells {
+        let tp = cell["type"].as_str().unwrap_or_default();
+        if flip_flop(tp, cell)?.is_none() {
+            continue;
+        }
+        for b in port(name, cell, clock_port(tp))? {
+            let Bit::Net(n) = b else {
+                return Err(format!("Cell {} has a constant clock", name));
+            };
+            let index = clocks.len() as u32;
+            clocks.entry(n).or_insert(index);
+        }
+    }
+    if clocks.len() > MAX_CLOCKS {
+        return Err(format!("Too many clocks: {}", clocks.len()));
+    }
+
+    // Inputs
+    for (name, p) in ports {
+        let bits = parse_bits(&p["bits"])?;
+        match p["direction"].as_str() {
+            Some("input") => (),
+            Some("output") => continue,
+            _ => return Err(format!("Port {} is not an input or an output", name)),
+        }
+        for (i, b) in bits.iter().enumerate() {
+            let Bit::Net(n) = b else {
+                return Err(format!("Input port {} has a constant bit", name));
+            };
+            if let Some(c) = clocks.get(n) {
+                names.set_clock_name(*c as usize, &bit_name(name, i, bits.len()));
+                continue;
+            }
+            let s = aig.add_input();
+            if net_to_sig.insert(*n, s).is_some() {
+                return Err(format!("Net {} is driven twice", n));
+            }
+            names.set_input_name(aig.nb_inputs() - 1, &bit_name(name, i, bits.len()));
+        }
+    }
+
+    // Create one node per cell output bit, so that cells can be read in any order
+    let mut cell_nodes = Vec::new();
+    for (name, cell) in cells {
+        let tp = cell["type"].as_str().unwrap_or_default();
+        let out = if flip_flop(tp, cell)?.is_some() {
+            "Q"
+        } else {
+            "Y"
+        };
+        cell_nodes.push(aig.nb_nodes());
+        for b in port(name, cell, out)? {
+            let Bit::Net(n) = b else {
+                return Err(format!("Cell {} drives a constant", name));
+            };
+            let s = aig.add(Gate::Buf(Signal::zero()));
+            if net_to_sig.insert(n, s).is_some() {
+                return Err(format!("Net {} is driven twice", n));
+            }
+        }
+    }
+
+    let sig = |b: &Bit| match b {
+        Bit::Const(v) => Ok(Signal::from(*v)),
+        Bit::Net(n) => net_to_sig
+            .get(n)
+            .copied()
+            .ok_or_else(|| format!("Net {} is not driven", n)),
+    };
+    let sigs = |bits: &[Bit]| {
+        bits.iter()
+            .map(sig)
+            .collect::<Result<Vec<Signal>, String>>()
+    };
+    for ((name, cell), first) in cells.iter().zip(cell_nodes) {
+        let tp = cell["type"].as_str().unwrap_or_default();
+        let bus = |p: &str| sigs(&port(name, cell, p)?);
+        let mut gates = Vec::new();
+        if let Some(ff) = flip_flop(tp, cell)? {
+            let clk = port(name, cell, clock_port(tp))?;
+            let d = bus("D")?;
+            let control = |c: Option<(&str, bool)>, default: Signal| -> Result<Signal, String> {
+                match c {
+                    Some((p, pol)) => Ok(bus(p)?[0] ^ !pol),
+                    None => Ok(default),
+                }
+            };
+            let en = control(ff.enable, Signal::one())?;
+            let res = control(ff.reset, Signal::zero())?;
+            for (d, c) in d.iter().zip(clk.iter().cycle()) {
+                let Bit::Net(c) = c else { unreachable!() };
+                let clock = ClockId::new(clocks[c], ff.falling_edge);
+                gates.push(Gate::dff_with_clock(*d, en, res, clock));
+            }
+        } else {
+            let y_width = port(name, cell, "Y")?.len();
+            let check_width = |v: &[Signal]| {
+                if v.len() == y_width {
+                    Ok(())
+                } else {
+                    Err(format!("Cell {}: width extension is not supported", name))
+                }
+            };
+            let bitwise = |f: &dyn Fn(Signal, Signal) -> Gate| -> Result<Vec<Gate>, String> {
+                let a = bus("A")?;
+                let b = bus("B")?;
+                check_width(&a)?;
+                check_width(&b)?;
+                Ok(a.iter().zip(b.iter()).map(|(a, b)| f(*a, *b)).collect())
+            };
+            let unary = |f: &dyn Fn(Signal) -> Gate| -> Result<Vec<Gate>, String> {
+                let a = bus("A")?;
+                check_width(&a)?;
+                Ok(a.iter().map(|a| f(*a)).collect())
+            };
+            let reduce = |tp: NaryType| -> Result<Vec<Gate>, String> {
+                if y_width != 1 {
+                    return Err(format!("Cell {}: width extension is not supported", name));
+                }
+                Ok(vec![Gate::Nary(bus("A")?.into(), tp)])
+            };
+            let nary = |a: Signal, b: Signal, tp: NaryType| Gate::Nary([a, b].into(), tp);
+            gates = match tp {
+                "$and" | "$_AND_" => bitwise(&|a, b| Gate::Binary([a, b], BinaryType::And))?,
+                "$or" | "$_OR_" => bitwise(&|a, b| nary(a, b, NaryType::Or))?,
+                "$xor" | "$_XOR_" => bitwise(&|a, b| Gate::Binary([a, b], BinaryType::Xor))?,
+                "$xnor" | "$_XNOR_" => bitwise(&|a, b| nary(a, b, NaryType::Xnor))?,
+                "$_NAND_" => bitwise(&|a, b| nary(a, b, NaryType::Nand))?,
+                "$_NOR_" => bitwise(&|a, b| nary(a, b, NaryType::Nor))?,
+                "$_ANDNOT_" => bitwise(&|a, b| Gate::Binary([a, !b], BinaryType::And))?,
+                "$_ORNOT_" => bitwise(&|a, b| nary(a, !b, NaryType::Or))?,
+                "$not" | "$_NOT_" => unary(&|a| Gate::Buf(!a))?,
+                "$pos" | "$_BUF_" => unary(&Gate::Buf)?,
+                "$mux" | "$_MUX_" => {
+                    let s = bus("S")?;
+                    if s.len() != 1 {
+                        return Err(format!("Cell {}: select must have a single bit", name));
+                    }
+                    bitwise(&|a, b| Gate::mux(s[0], b, a))?
+                }
+                "$reduce_and" => reduce(NaryType::And)?,
+                "$reduce_or" | "$reduce_bool" => reduce(NaryType::Or)?,
+                "$reduce_xor" => reduce(NaryType::Xor)?,
+                "$reduce_xnor" => reduce(NaryType::Xnor)?,
+                "$logic_not" => reduce(NaryType::Nor)?,
+                "$lut" => {
+                    let a = bus("A")?;
+                    let table = cell
+                        .get("parameters")
+                        .and_then(|p| p.get("LUT"))
+                        .ok_or_else(|| format!("Cell {} has no LUT parameter", name))?;
+                    let table = parse_value(table)?;
+                    let mut lut = Lut::zero(a.len());
+                    for m in 0..lut.num_bits() {
+                        if table.get(m).copied().unwrap_or(false) {
+                            lut.set_bit(m);
+                        }
+                    }
+                    if y_width != 1 {
+                        return Err(format!("Cell {}: Luts must have a single output", name));
+                    }
+                    vec![Gate::lut(&a, lut)]
+                }
+                _ => return Err(format!("Unsupported cell type {} for cell {}", tp, name)),
+            };
+        }
+        for (i, g) in gates.into_iter().enumerate() {
+            aig.replace(first + i, g);
+        }
+    }
+
+    // Outputs
+    for (name, p) in ports {
+        if p["direction"].as_str() != Some("output") {
+            continue;
+        }
+        let bits = parse_bits(&p["bits"])?;
+        for (i, b) in bits.iter().enumerate() {
+            aig.add_output(sig(b)?);
+            names.set_output_name(aig.nb_outputs() - 1, &bit_name(name, i, bits.len()));
+        }
+    }
+
+    // Names of the internal nets
+    for (name, n) in netnames {
+        if n.get("hide_name").is_some_and(is_set) {
+            continue;
+        }
+        let bits = parse_bits(&n["bits"])?;
+        for (i, b) in bits.iter().enumerate() {
+            if let Bit::Net(net) = b {
+                if let Some(s) = net_to_sig.get(net) {
+                    if s.is_var() && names.node_name(s.var() as usize).is_none() {
+                        names.set_node_name(s.var() as usize, &bit_name(name, i, bits.len()));
+                    }
+                }
+            }
+        }
+    }
+
+    let translation = aig.try_topo_sort()?;
+    names.remap(&translation);
+    Ok((aig, names))
+}
+
+/// Read a network in Yosys JSON format
+pub fn read_json<R: Read>(r: R) -> Result<Network, QuaighError> {
+    read_json_with_names(r).map(|(aig, _)| aig)
+}
+
+/// Builder for the cells and nets of a Yosys module
+struct JsonWriter {
+    next_net: u64,
+    cells: Map<String, Value>,
+    /// Net of each input and node
+    nets: HashMap<Signal, u64>,
+    /// Net of each inverted signal, driven by an additional inverter
+    inverted: HashMap<Signal, u64>,
+}
+
+impl JsonWriter {
+    fn new_net(&mut self) -> u64 {
+        let ret = self.next_net;
+        self.next_net += 1;
+        ret
+    }
+
+    /// Bit for a signal, adding an inverter if it is inverted
+    fn bit(&mut self, s: Signal) -> Value {
+        if s.is_constant() {
+            return json!(if s == Signal::one() { "1" } else { "0" });
+        }
+        let net = self.nets[&s.without_inversion()];
+        if !s.is_inverted() {
+            return json!(net);
+        }
+        if let Some(n) = self.inverted.get(&s) {
+            return json!(n);
+        }
+        let n = self.new_net();
+        self.inverted.insert(s, n);
+        self.add_cell("$_NOT_", json!({}), &[("A", json!([net]))], json!([n]));
+        json!(n)
+    }
+
+    /// Connection to a single signal
+    fn wire(&mut self, s: Signal) -> Value {
+        json!([self.bit(s)])
+    }
+
+    fn bits(&mut self, v: &[Signal]) -> Value {
+        Value::Array(v.iter().map(|s| self.bit(*s)).collect())
+    }
+
+    /// Add a cell whose output port is `Q` for flip-flops and `Y` otherwise
+    fn add_cell(&mut self, tp: &str, parameters: Value, inputs: &[(&str, Value)], output: Value) {
+        let is_ff = tp.contains("DFF");
+        let out = if is_ff { "Q" } else { "Y" };
+        let mut directions = Map::new();
+        let mut connections = Map::new();
+        for (p, v) in inputs {
+            directions.insert(p.to_string(), json!("input"));
+            connections.insert(p.to_string(), v.clone());
+        }
+        directions.insert(out.to_owned(), json!("output"));
+        connections.insert(out.to_owned(), output);
+        let name = format!("$quaigh${}", self.cells.len());
+        self.cells.insert(
+            name,
+            json!({
+                "hide_name": 1,
+                "type": tp,
+                "parameters": parameters,
+                "attributes": {},
+                "port_directions": directions,
+                "connections": connections,
+            }),
+        );
+    }
+}
+
+/// Binary string for a parameter, with the most significant bit first
+fn param(bits: impl DoubleEndedIterator<Item = bool>) -> Value {
+    json!(bits
+        .rev()
+        .map(|b| if b { '1' } else { '0' })
+        .collect::<String>())
+}
+
+/// Binary string for an integer parameter
+fn int_param(v: usize) -> Value {
+    param((0..32).map(|i| (v >> i) & 1 != 0))
+}
+
+/// Write a network in Yosys JSON format, with the names of its signals
+///
+/// Gates are written as gate-level cells, Majority gates as Luts, and additional inverters are
+/// added for inverted signals. Each clock of the network becomes an input port.
+pub fn write_json_with_names<W: Write>(w: &mut W, aig: &Network, names: &NameTable) {
+    let namer = SignalNamer::new(aig, names);
+    let mut writer = JsonWriter {
+        next_net: 2,
+        cells: Map::new(),
+        nets: HashMap::new(),
+        inverted: HashMap::new(),
+    };
+    let mut ports = Map::new();
+    let mut netnames = Map::new();
+    let mut add_netname = |name: String, net: u64, hidden: bool| {
+        netnames.insert(
+            name,
+            json!({"hide_name": hidden as u8, "bits": [net], "attributes": {}}),
+        );
+    };
+
+    for i in 0..aig.nb_inputs() {
+        let s = aig.input(i);
+        let net = writer.new_net();
+        writer.nets.insert(s, net);
+        let name = namer.name(&s);
+        ports.insert(name, json!({"direction": "input", "bits": [net]}));
+    }
+    let mut clock_nets = HashMap::new();
+    for clock in aig.clock_domains() {
+        if clock_nets.contains_key(&clock.index()) {
+            continue;
+        }
+        let net = writer.new_net();
+        clock_nets.insert(clock.index(), net);
+        let name = match names.clock_name(clock.index() as usize) {
+            Some(n) => n.to_owned(),
+            None => format!("clk{}", clock.index()),
+        };
+        ports.insert(name, json!({"direction": "input", "bits": [net]}));
+    }
+    for i in 0..aig.nb_nodes() {
+        let s = aig.node(i);
+        let net = writer.new_net();
+        writer.nets.insert(s, net);
+        let (name, generated) = names.name_or_generated(s);
+        add_netname(name, net, generated);
+    }
+
+    for i in 0..aig.nb_nodes() {
+        use Gate::*;
+        let y = json!([writer.nets[&aig.node(i)]]);
+        let no_params = json!({});
+        match &aig.gate(i) {
+            Binary([a, b], tp) => {
+                let t = match tp {
+                    BinaryType::And => "$_AND_",
+                    BinaryType::Xor => "$_XOR_",
+                };
+                let (a, b) = (writer.wire(*a), writer.wire(*b));
+                writer.add_cell(t, no_params, &[("A", a), ("B", b)], y);
+            }
+            Ternary([s, a, b], TernaryType::Mux) => {
+                let (s, a, b) = (writer.wire(*s), writer.wire(*a), writer.wire(*b));
+                writer.add_cell("$_MUX_", no_params, &[("A", b), ("B", a), ("S", s)], y);
+            }
+            Ternary(v, TernaryType::Maj) => {
+                let table = (0..8).map(|m: u32| m.count_ones() >= 2);
+                let params = json!({"WIDTH": int_param(3), "LUT": param(table)});
+                let a = writer.bits(v);
+                writer.add_cell("$lut", params, &[("A", a)], y);
+            }
+            Ternary(v, tp) => {
+                let t = match tp {
+                    TernaryType::And => "$reduce_and",
+                    _ => "$reduce_xor",
+                };
+                let params = json!({"A_SIGNED": int_param(0), "A_WIDTH": int_param(3), "Y_WIDTH": int_param(1)});
+                let a = writer.bits(v);
+                writer.add_cell(t, params, &[("A", a)], y);
+            }
+            Nary(v, tp) => {
+                let (t, inv) = match tp {
+                    NaryType::And => ("$reduce_and", false),
+                    NaryType::Or => ("$reduce_or", false),
+                    NaryType::Xor => ("$reduce_xor", false),
+                    NaryType::Nand => ("$reduce_and", true),
+                    NaryType::Nor => ("$reduce_or", true),
+                    NaryType::Xnor => ("$reduce_xor", true),
+                };
+                let params = json!({"A_SIGNED": int_param(0), "A_WIDTH": int_param(v.len()), "Y_WIDTH": int_param(1)});
+                let a = writer.bits(v);
+                if inv {
+                    let n = writer.new_net();
+                    writer.add_cell(t, params, &[("A", a)], json!([n]));
+                    writer.add_cell("$_NOT_", no_params, &[("A", json!([n]))], y);
+                } else {
+                    writer.add_cell(t, params, &[("A", a)], y);
+                }
+            }
+            Buf(s) => {
+                let t = if s.is_inverted() { "$_NOT_" } else { "$_BUF_" };
+                let a = writer.wire(s.without_inversion());
+                writer.add_cell(t, no_params, &[("A", a)], y);
+            }
+            Dff([d, en, res], clock) => {
+                let c = if clock.is_falling_edge() { 'N' } else { 'P' };
+                let mut inputs = vec![
+                    ("C", json!([clock_nets[&clock.index()]])),
+                    ("D", writer.wire(*d)),
+                ];
+                let t = match (*en == Signal::one(), *res == Signal::zero()) {
+                    (true, true) => format!("$_DFF_{}_", c),
+                    (false, true) => format!("$_DFFE_{}P_", c),
+                    (true, false) => format!("$_SDFF_{}P0_", c),
+                    (false, false) => format!("$_SDFFE_{}P0P_", c),
+                };
+                if *en != Signal::one() {
+                    inputs.push(("E", writer.wire(*en)));
+                }
+                if *res != Signal::zero() {
+                    inputs.push(("R", writer.wire(*res)));
+                }
+                writer.add_cell(&t, no_params, &inputs, y);
+            }
+            Latch(_) => panic!("Transparent latches are not supported in .json files"),
+            Lut(lut) => {
+                let n = lut.inputs.len();
+                let table = (0..lut.lut.num_bits()).map(|m| lut.lut.get_bit(m));
+                let params = json!({"WIDTH": int_param(n), "LUT": param(table)});
+                let a = writer.bits(&lut.inputs);
+                writer.add_cell("$lut", params, &[("A", a)], y);
+            }
+        }
+    }
+
+    for (i, name) in namer.output_names().into_iter().enumerate() {
+        let bit = writer.bit(aig.output(i));
+        ports.insert(name, json!({"direction": "output", "bits": [bit]}));
+    }
+
+    let top = json!({
+        "attributes": {"top": int_param(1)},
+        "ports": ports,
+        "cells": writer.cells,
+        "netnames": netnames,
+    });
+    let netlist = json!({
+        "creator": "quaigh",
+        "modules": {"top": top},
+    });
+    serde_json::to_writer_pretty(&mut *w, &netlist).unwrap();
+    writeln!(w).unwrap();
+}
+
+/// Write a network in Yosys JSON format
+pub fn write_json<W: Write>(w: &mut W, aig: &Network) {
+    write_json_with_names(w, aig, &NameTable::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_json, read_json_with_names, write_json, write_json_with_names};
+    use crate::network::ClockId;
+    use crate::sim::simulate;
+    use crate::{Gate, Network, Signal};
+
+    const EXAMPLE: &str = r#"{
+        "creator": "Yosys",
+        "modules": {
+            "helper": {"ports": {}, "cells": {}, "netnames": {}},
+            "counter": {
+                "attributes": {"top": "00000000000000000000000000000001"},
+                "ports": {
+                    "clk": {"direction": "input", "bits": [2]},
+                    "a": {"direction": "input", "bits": [3, 4]},
+                    "s": {"direction": "input", "bits": [5]},
+                    "y": {"direction": "output", "bits": [6, 7]},
+                    "q": {"direction": "output", "bits": [8, "1"]}
+                },
+                "cells": {
+                    "$mux$1": {
+                        "type": "$mux",
+                        "parameters": {"WIDTH": "00000000000000000000000000000010"},
+                        "connections": {"A": [3, 4], "B": [9, 3], "S": [5], "Y": [6, 7]}
+                    },
+                    "$not$2": {
+                        "type": "$_NOT_",
+                        "connections": {"A": [4], "Y": [9]}
+                    },
+                    "$dff$3": {
+                        "type": "$sdff",
+                        "parameters": {
+                            "CLK_POLARITY": "1",
+                            "SRST_POLARITY": "0",
+                            "SRST_VALUE": "0",
+                            "WIDTH": "00000000000000000000000000000001"
+                        },
+                        "connections": {"CLK": [2], "D": [6], "SRST": [5], "Q": [8]}
+                    }
+                },
+                "netnames": {
+                    "n": {"hide_name": 0, "bits": [9]},
+                    "$auto": {"hide_name": 1, "bits": [8]}
+                }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_read() {
+        let (aig, names) = read_json_with_names(EXAMPLE.as_bytes()).unwrap();
+        assert_eq!(aig.nb_inputs(), 3);
+        assert_eq!(aig.nb_outputs(), 4);
+        assert_eq!(names.input_name(0), Some("a[0]"));
+        assert_eq!(names.input_name(2), Some("s"));
+        assert_eq!(names.output_name(3), Some("q[1]"));
+        assert_eq!(names.clock_name(0), Some("clk"));
+        assert!(names.find("n").is_some());
+        assert_eq!(aig.output(3), Signal::one());
+        let ffs: Vec<Gate> = (0..aig.nb_nodes())
+            .map(|i| aig.gate(i))
+            .filter(|g| matches!(g, Gate::Dff(..)))
+            .collect();
+        assert_eq!(ffs.len(), 1);
+        assert_eq!(ffs[0].clock(), Some(ClockId::rising(0)));
+
+        // y = s ? {a[0], !a[1]} : a; q is y[0] delayed, reset when s is 0
+        let pattern = vec![
+            vec![true, true, true],
+            vec![true, false, true],
+            vec![false, true, true],
+            vec![true, false, false],
+            vec![false, false, true],
+        ];
+        let out = simulate(&aig, &pattern);
+        assert_eq!(out[0], vec![false, true, false, true]);
+        assert_eq!(out[1], vec![true, true, false, true]);
+        assert_eq!(out[2], vec![false, false, true, true]);
+        assert_eq!(out[3], vec![true, false, false, true]);
+        assert_eq!(out[4], vec![true, false, false, true]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let cell = |tp: &str| {
+            format!(
+                r#"{{"modules": {{"m": {{
+                    "ports": {{"a": {{"direction": "input", "bits": [2]}}}},
+                    "cells": {{"c": {{"type": "{}", "connections": {{"A": [2], "B": [2], "Y": [3]}}}}}}
+                }}}}}}"#,
+                tp
+            )
+        };
+        assert!(read_json(cell("$_AND_").as_bytes()).is_ok());
+        assert!(read_json(cell("$add").as_bytes()).is_err());
+        assert!(read_json("{}".as_bytes()).is_err());
+        assert!(read_json(r#"{"modules": {"a": {}, "b": {}}}"#.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        use volute::Lut;
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        let x = aig.and(a, !b);
+        let y = aig.xor(x, c);
+        let m = aig.add(Gate::maj(a, !b, c));
+        let u = aig.add(Gate::mux(a, b, !c));
+        let n = aig.add(Gate::Nary([a, b, c].into(), crate::network::NaryType::Nor));
+        let l = aig.add(Gate::lut(&[a, b], Lut::nth_var(2, 1)));
+        let d = aig.add(Gate::dff_with_clock(y, !a, b, ClockId::falling(1)));
+        for s in [y, m, u, n, l, d, !d, Signal::one()] {
+            aig.add_output(s);
+        }
+
+        let mut buf = Vec::new();
+        write_json(&mut buf, &aig);
+        let (read, names) = read_json_with_names(buf.as_slice()).unwrap();
+        assert_eq!(read.nb_inputs(), 3);
+        assert_eq!(read.nb_outputs(), 8);
+        assert_eq!(names.clock_name(0), Some("clk1"));
+        let patterns: Vec<Vec<bool>> = (0..8)
+            .map(|m| (0..3).map(|i| (m >> i) & 1 != 0).collect())
+            .collect();
+        assert_eq!(simulate(&aig, &patterns), simulate(&read, &patterns));
+
+        let mut buf2 = Vec::new();
+        write_json_with_names(&mut buf2, &read, &names);
+        let again = read_json(buf2.as_slice()).unwrap();
+        assert_eq!(simulate(&aig, &patterns), simulate(&again, &patterns));
+    }
+}
@@ -0,0 +1,601 @@
+//! IO for test patterns
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::iter::zip;
+
+use crate::network::{ClockId, NameTable};
+use crate::Network;
+
+/// Read test patterns in Atalanta format
+///
+/// Each pattern may contain multiple timesteps. For each timestep, the value of each circuit input is given.
+/// The patterns are formatted as follows:
+/// ```text
+///     * This is a comment
+///
+///     * Input pattern with five input values set to zero
+///     1: 00000
+///
+///     * The index increments at each pattern
+///     2: 00000
+///
+///     * A pattern that contains three timesteps
+///     3: 01110 00111 01000
+///
+///     * The index is optional when reading patterns
+///     01110 00111 01000
+/// ```
+pub fn read_patterns<R: Read>(r: R) -> Result<Vec<Vec<Vec<bool>>>, String> {
+    PatternReader::new(BufReader::new(r)).collect()
+}
+
+/// Read test patterns with unknown values in Atalanta format
+///
+/// The format is the same as for [`read_patterns`], with unknown values given as `x` or `X` and
+/// represented as None.
+pub fn read_ternary_patterns<R: Read>(r: R) -> Result<Vec<Vec<Vec<Option<bool>>>>, String> {
+    let mut reader = PatternReader::new(BufReader::new(r));
+    std::iter::from_fn(|| reader.next_ternary()).collect()
+}
+
+fn parse_bool(c: char) -> Option<bool> {
+    match c {
+        '0' => Some(false),
+        '1' => Some(true),
+        _ => None,
+    }
+}
+
+fn parse_ternary(c: char) -> Option<Option<bool>> {
+    match c {
+        'x' | 'X' => Some(None),
+        _ => parse_bool(c).map(Some),
+    }
+}
+
+/// Streaming reader for test patterns in Atalanta format
+///
+/// Patterns are parsed one at a time, so that large pattern files do not need to be loaded in memory.
+/// See [`read_patterns`] for the format.
+pub struct PatternReader<R: BufRead> {
+    reader: R,
+    line: String,
+    line_ind: usize,
+    pattern_ind: usize,
+}
+
+impl<R: BufRead> PatternReader<R> {
+    /// Create a reader for the patterns in a buffered stream
+    pub fn new(reader: R) -> PatternReader<R> {
+        PatternReader {
+            reader,
+            line: String::new(),
+            line_ind: 0,
+            pattern_ind: 1,
+        }
+    }
+
+    /// Read the next pattern with unknown values; see [`read_ternary_patterns`] for the format
+    pub fn next_ternary(&mut self) -> Option<Result<Vec<Vec<Option<bool>>>, String>> {
+        self.next_with(parse_ternary)
+    }
+
+    /// Read the next pattern, with a parser for the value characters
+    fn next_with<T>(
+        &mut self,
+        parse: fn(char) -> Option<T>,
+    ) -> Option<Result<Vec<Vec<T>>, String>> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => (),
+                Err(e) => return Some(Err(e.to_string())),
+            }
+            self.line_ind += 1;
+            if let Some(ret) = self.parse_line(parse) {
+                if ret.is_ok() {
+                    self.pattern_ind += 1;
+                }
+                return Some(ret);
+            }
+        }
+    }
+
+    /// Parse the current line; returns None if it does not contain a valid pattern
+    fn parse_line<T>(&self, parse: fn(char) -> Option<T>) -> Option<Result<Vec<Vec<T>>, String>> {
+        let t = self.line.trim();
+        if t.is_empty() || t.starts_with('*') {
+            return None;
+        }
+        let sp = t.split(':').collect::<Vec<_>>();
+        if sp.len() >= 3 || sp.is_empty() {
+            return Some(Err(
+                "Expected line of the form INDEX: TIMESTEP_1 TIMESTEP_2 ... TIMESTEP_N".to_owned(),
+            ));
+        }
+        if sp.len() == 2 {
+            let parse_ind = sp[0].trim().parse::<usize>();
+            if parse_ind.is_err() || parse_ind.unwrap() != self.pattern_ind {
+                println!(
+                    "Index {} on a line does not match expected {}",
+                    sp[0], self.pattern_ind
+                );
+            }
+        }
+        let patterns = if sp.len() == 2 {
+            sp[1].split_whitespace()
+        } else {
+            sp[0].split_whitespace()
+        };
+        let mut seq_ret = Vec::new();
+        for p in patterns {
+            let mut comb_ret = Vec::with_capacity(p.len());
+            for c in p.chars() {
+                match parse(c) {
+                    Some(v) => comb_ret.push(v),
+                    None => {
+                        println!("Ignoring line {} with invalid characters", self.line_ind);
+                        return None;
+                    }
+                }
+            }
+            seq_ret.push(comb_ret);
+        }
+        Some(Ok(seq_ret))
+    }
+}
+
+impl<R: BufRead> Iterator for PatternReader<R> {
+    type Item = Result<Vec<Vec<bool>>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with(parse_bool)
+    }
+}
+
+/// Write test patterns in Atalanta format
+///
+/// Each pattern may contain multiple timesteps. For each timestep, the value of each circuit input is given.
+/// The patterns are formatted as follows:
+/// ```text
+///     * This is a comment
+///
+///     * Input pattern with five input values set to zero
+///     1: 00000
+///
+///     * The index increments at each pattern
+///     2: 00000
+///
+///     * A pattern that contains three timesteps
+///     3: 01110 00111 01000
+/// ```
+pub fn write_patterns<W: Write>(w: &mut W, patterns: &Vec<Vec<Vec<bool>>>) {
+    let mut writer = PatternWriter::new(w);
+    for p in patterns {
+        writer.write(p);
+    }
+}
+
+/// Write test patterns with unknown values in Atalanta format
+///
+/// Unknown values, represented as None, are written as `X`. See [`write_patterns`] for the format.
+pub fn write_ternary_patterns<W: Write>(w: &mut W, patterns: &Vec<Vec<Vec<Option<bool>>>>) {
+    let mut writer = PatternWriter::new(w);
+    for p in patterns {
+        writer.write_ternary(p);
+    }
+}
+
+/// Streaming writer for test patterns in Atalanta format
+///
+/// Patterns are written one at a time, with consecutive indices. See [`write_patterns`] for the format.
+pub struct PatternWriter<W: Write> {
+    writer: W,
+    pattern_ind: usize,
+}
+
+impl<W: Write> PatternWriter<W> {
+    /// Create a writer, and write the header of the file
+    pub fn new(mut writer: W) -> PatternWriter<W> {
+        writeln!(writer, "* Test pattern file").unwrap();
+        writeln!(writer, "* generated by quaigh").unwrap();
+        PatternWriter {
+            writer,
+            pattern_ind: 1,
+        }
+    }
+
+    /// Write the names of the inputs, in the order of the values, as a comment
+    pub fn write_input_names(&mut self, names: &[String]) {
+        writeln!(self.writer, "* Inputs: {}", names.join(" ")).unwrap();
+    }
+
+    /// Write a comment line, for example the values of a pattern grouped by bus
+    pub fn write_comment(&mut self, text: &str) {
+        writeln!(self.writer, "* {}", text).unwrap();
+    }
+
+    /// Write the next pattern
+    pub fn write(&mut self, pattern: &[Vec<bool>]) {
+        self.write_with(pattern, |b| if *b { '1' } else { '0' });
+    }
+
+    /// Write the next pattern with unknown values, written as `X`
+    pub fn write_ternary(&mut self, pattern: &[Vec<Option<bool>>]) {
+        self.write_with(pattern, |b| match b {
+            None => 'X',
+            Some(true) => '1',
+            Some(false) => '0',
+        });
+    }
+
+    fn write_with<T>(&mut self, pattern: &[Vec<T>], to_char: fn(&T) -> char) {
+        let mut line = format!("{}:", self.pattern_ind);
+        for seq_pattern in pattern {
+            line.push(' ');
+            line.extend(seq_pattern.iter().map(to_char));
+        }
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).unwrap();
+        self.pattern_ind += 1;
+    }
+
+    /// Flush the underlying stream
+    pub fn flush(&mut self) {
+        self.writer.flush().unwrap();
+    }
+}
+
+/// Clock domains triggered at each cycle
+type ClockEdges = Vec<Vec<ClockId>>;
+
+/// Stimulus for the simulation of a sequential design, expanded cycle by cycle
+///
+/// Signals and clocks are referred to by name, and resolved against a network with
+/// [`Stimulus::resolve`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stimulus {
+    /// Names of the signals set by the stimulus, in order of appearance
+    pub signals: Vec<String>,
+    /// Names of the clocks controlled by the stimulus, in order of appearance
+    pub clocks: Vec<String>,
+    /// Value of each signal at each cycle
+    pub values: Vec<Vec<bool>>,
+    /// Whether each clock runs at the end of each cycle
+    pub clocks_enabled: Vec<Vec<bool>>,
+}
+
+impl Stimulus {
+    /// Number of cycles of the stimulus
+    pub fn nb_cycles(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Input values and clock edges to simulate a network with the stimulus
+    ///
+    /// Inputs that are not set by the stimulus are 0, and clocks that are not controlled by the
+    /// stimulus run at every cycle. The clock edges follow the convention of
+    /// [`simulate_with_clocks`](crate::sim::simulate_with_clocks).
+    pub fn resolve(
+        &self,
+        aig: &Network,
+        names: &NameTable,
+    ) -> Result<(Vec<Vec<bool>>, ClockEdges), String> {
+        let mut inputs = Vec::new();
+        for n in &self.signals {
+            match names.resolve(n) {
+                Some(s) if s.is_input() && !s.is_inverted() => inputs.push(s.input() as usize),
+                _ => return Err(format!("Unknown input {}", n)),
+            }
+        }
+        let domains = aig.clock_domains();
+        let mut clocks = Vec::new();
+        for n in &self.clocks {
+            let index = domains
+                .iter()
+                .map(|c| c.index())
+                .find(|i| {
+                    names.clock_name(*i as usize) == Some(n.as_str())
+                        || ClockId::rising(*i).to_string() == *n
+                })
+                .ok_or_else(|| format!("Unknown clock {}", n))?;
+            clocks.push(index);
+        }
+        let mut input_values = Vec::with_capacity(self.nb_cycles());
+        let mut edges = Vec::with_capacity(self.nb_cycles());
+        for (values, enabled) in self.values.iter().zip(&self.clocks_enabled) {
+            let mut v = vec![false; aig.nb_inputs()];
+            for (i, b) in inputs.iter().zip(values) {
+                v[*i] = *b;
+            }
+            input_values.push(v);
+            let stopped: Vec<u32> = zip(&clocks, enabled)
+                .filter(|(_, e)| !**e)
+                .map(|(c, _)| *c)
+                .collect();
+            edges.push(
+                domains
+                    .iter()
+                    .filter(|c| !stopped.contains(&c.index()))
+                    .copied()
+                    .collect(),
+            );
+        }
+        Ok((input_values, edges))
+    }
+
+    /// Add cycles with the given values and running clocks
+    fn add_cycles(&mut self, values: &[bool], enabled: &[bool], nb_cycles: usize) {
+        for _ in 0..nb_cycles {
+            self.values.push(values.to_vec());
+            self.clocks_enabled.push(enabled.to_vec());
+        }
+    }
+}
+
+/// Read a stimulus for a sequential design
+///
+/// Signals are set by name, and keep their value until they are set again. Each cycle applies the
+/// current values, then triggers the running clocks. The stimulus is formatted as follows:
+/// ```text
+///     * This is a comment, as is a line starting with #
+///
+///     * Set inputs by name; all inputs start at 0
+///     set en=1 data=0
+///
+///     * Set a reset input to 1 for 2 cycles, then to 0
+///     reset rst=1 2
+///
+///     * Stop a clock, then start it again; all clocks run by default
+///     clock clk off
+///     clock clk on
+///
+///     * Apply the current values for 10 cycles, then for a single cycle
+///     cycle 10
+///     cycle
+/// ```
+pub fn read_stimulus<R: Read>(r: R) -> Result<Stimulus, String> {
+    let mut ret = Stimulus::default();
+    let mut values: Vec<bool> = Vec::new();
+    let mut enabled: Vec<bool> = Vec::new();
+    let find_or_add =
+        |names: &mut Vec<String>, state: &mut Vec<bool>, name: &str, init: bool| match names
+            .iter()
+            .position(|n| n == name)
+        {
+            Some(i) => i,
+            None => {
+                names.push(name.to_owned());
+                state.push(init);
+                names.len() - 1
+            }
+        };
+    let parse_count = |s: Option<&str>| -> Result<usize, String> {
+        match s {
+            None => Ok(1),
+            Some(c) => c
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid cycle count {}", c)),
+        }
+    };
+    let parse_assignment = |s: &str| -> Result<(String, bool), String> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected NAME=VALUE, got {}", s))?;
+        let value = parse_bool(value.chars().next().unwrap_or(' '))
+            .filter(|_| value.len() == 1)
+            .ok_or_else(|| format!("Value should be 0 or 1, got {}", value))?;
+        Ok((name.to_owned(), value))
+    };
+
+    for (line_ind, line) in BufReader::new(r).lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let t = line.trim();
+        if t.is_empty() || t.starts_with('*') || t.starts_with('#') {
+            continue;
+        }
+        let err = |e: String| format!("Line {}: {}", line_ind + 1, e);
+        let mut tokens = t.split_whitespace();
+        let directive = tokens.next().unwrap();
+        let args: Vec<&str> = tokens.collect();
+        match directive {
+            "set" => {
+                for a in args {
+                    let (name, value) = parse_assignment(a).map_err(err)?;
+                    let i = find_or_add(&mut ret.signals, &mut values, &name, false);
+                    values[i] = value;
+                }
+            }
+            "reset" => {
+                if args.is_empty() || args.len() > 2 {
+                    return Err(err("Expected reset NAME=VALUE [CYCLES]".to_owned()));
+                }
+                let (name, value) = parse_assignment(args[0]).map_err(err)?;
+                let n = parse_count(args.get(1).copied()).map_err(err)?;
+                let i = find_or_add(&mut ret.signals, &mut values, &name, false);
+                values[i] = value;
+                ret.add_cycles(&values, &enabled, n);
+                values[i] = !value;
+            }
+            "clock" => {
+                let on = match args.get(1).copied() {
+                    Some("on") => true,
+                    Some("off") => false,
+                    _ => return Err(err("Expected clock NAME on|off".to_owned())),
+                };
+                if args.len() != 2 {
+                    return Err(err("Expected clock NAME on|off".to_owned()));
+                }
+                let i = find_or_add(&mut ret.clocks, &mut enabled, args[0], true);
+                enabled[i] = on;
+            }
+            "cycle" => {
+                if args.len() > 1 {
+                    return Err(err("Expected cycle [COUNT]".to_owned()));
+                }
+                let n = parse_count(args.first().copied()).map_err(err)?;
+                ret.add_cycles(&values, &enabled, n);
+            }
+            _ => return Err(err(format!("Unknown directive {}", directive))),
+        }
+    }
+    // Signals and clocks introduced later keep their initial value in earlier cycles
+    for v in &mut ret.values {
+        v.resize(ret.signals.len(), false);
+    }
+    for v in &mut ret.clocks_enabled {
+        v.resize(ret.clocks.len(), true);
+    }
+    Ok(ret)
+}
+
+mod test {
+    #[test]
+    fn test_read_pattern() {
+        let example = "  * comment1
+*comment2
+1: 00000 00000
+2: 01010  11111\t11111
+3:
+:00000
+*comment 3
+5: 00000
+00110";
+        let patterns = super::read_patterns(example.as_bytes()).unwrap();
+        assert_eq!(patterns.len(), 6);
+        assert_eq!(
+            patterns[0],
+            vec![
+                vec![false, false, false, false, false],
+                vec![false, false, false, false, false]
+            ]
+        );
+        assert_eq!(
+            patterns[1],
+            vec![
+                vec![false, true, false, true, false],
+                vec![true, true, true, true, true],
+                vec![true, true, true, true, true]
+            ]
+        );
+        assert_eq!(patterns[2], Vec::<Vec<bool>>::new());
+        assert_eq!(patterns[3], vec![vec![false, false, false, false, false],]);
+        assert_eq!(patterns[4], vec![vec![false, false, false, false, false],]);
+        assert_eq!(patterns[5], vec![vec![false, false, true, true, false],]);
+    }
+
+    #[test]
+    fn test_write_pattern() {
+        use std::io::BufWriter;
+
+        let example = vec![
+            vec![vec![false, true], vec![true, false]],
+            vec![vec![true, true]],
+        ];
+        let mut buf = BufWriter::new(Vec::new());
+        super::write_patterns(&mut buf, &example);
+        let s = String::from_utf8(buf.into_inner().unwrap()).unwrap();
+        assert_eq!(
+            s,
+            "* Test pattern file
+* generated by quaigh
+1: 01 10
+2: 11
+"
+        );
+    }
+
+    #[test]
+    fn test_ternary_pattern() {
+        let example = "1: 0x1 X10\n2: 01\n";
+        let patterns = super::read_ternary_patterns(example.as_bytes()).unwrap();
+        assert_eq!(
+            patterns,
+            vec![
+                vec![
+                    vec![Some(false), None, Some(true)],
+                    vec![None, Some(true), Some(false)]
+                ],
+                vec![vec![Some(false), Some(true)]]
+            ]
+        );
+        assert_eq!(super::read_patterns(example.as_bytes()).unwrap().len(), 1);
+
+        let mut buf = Vec::new();
+        super::write_ternary_patterns(&mut buf, &patterns);
+        let s = String::from_utf8(buf).unwrap();
+        assert!(s.ends_with("1: 0X1 X10\n2: 01\n"), "{}", s);
+    }
+
+    #[test]
+    fn test_read_stimulus() {
+        let example = "* comment
+# comment
+set a=1
+cycle 2
+reset rst=1
+clock clk off
+set a=0 b=1
+cycle
+clock clk on
+cycle";
+        let stim = super::read_stimulus(example.as_bytes()).unwrap();
+        assert_eq!(stim.signals, vec!["a", "rst", "b"]);
+        assert_eq!(stim.clocks, vec!["clk"]);
+        assert_eq!(stim.nb_cycles(), 5);
+        assert_eq!(
+            stim.values,
+            vec![
+                vec![true, false, false],
+                vec![true, false, false],
+                vec![true, true, false],
+                vec![false, false, true],
+                vec![false, false, true],
+            ]
+        );
+        assert_eq!(
+            stim.clocks_enabled,
+            vec![vec![true], vec![true], vec![true], vec![false], vec![true]]
+        );
+        for bad in ["set a", "set a=2", "cycle x", "clock clk", "jump 3"] {
+            let err = super::read_stimulus(bad.as_bytes()).unwrap_err();
+            assert!(err.starts_with("Line 1"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_resolve_stimulus() {
+        use crate::network::{ClockId, NameTable};
+        use crate::{Gate, Network, Signal};
+
+        let mut aig = Network::new();
+        let d = aig.add_input();
+        let res = aig.add_input();
+        let q = aig.add(Gate::dff_with_clock(
+            d,
+            Signal::one(),
+            res,
+            ClockId::falling(1),
+        ));
+        aig.add_output(q);
+        let mut names = NameTable::new();
+        names.set_input_name(1, "rst");
+        names.set_clock_name(1, "clk");
+
+        let stim = super::read_stimulus("set i0=1\nclock clk off\ncycle\n".as_bytes()).unwrap();
+        let (inputs, edges) = stim.resolve(&aig, &names).unwrap();
+        assert_eq!(inputs, vec![vec![true, false]]);
+        assert_eq!(edges, vec![Vec::<ClockId>::new()]);
+
+        let stim = super::read_stimulus("set rst=1\ncycle\n".as_bytes()).unwrap();
+        let (inputs, edges) = stim.resolve(&aig, &names).unwrap();
+        assert_eq!(inputs, vec![vec![false, true]]);
+        assert_eq!(edges, vec![vec![ClockId::falling(1)]]);
+
+        let stim = super::read_stimulus("set x=1\ncycle\n".as_bytes()).unwrap();
+        assert!(stim.resolve(&aig, &names).is_err());
+        let stim = super::read_stimulus("clock clk0 off\ncycle\n".as_bytes()).unwrap();
+        assert!(stim.resolve(&aig, &names).is_err());
+    }
+}
@@ -0,0 +1,441 @@
+//! Provenance headers embedded in the comments of written netlists
+//!
+//! The header records the version of quaigh, the fingerprint of the network, the source file it
+//! was derived from, the date, and a checksum of the rest of the file:
+//! ```text
+//!     # quaigh-version: 0.0.6
+//!     # fingerprint: 0x1a2b3c4d5e6f7a8b
+//!     # source: design.bench
+//!     # source-fingerprint: 0x0123456789abcdef
+//!     # assumptions: onehot.bench
+//!     # date: 2024-01-01T00:00:00Z
+//!     # checksum: 0x8b7a6f5e4d3c2b1a
+//! ```
+//! .bench and .blif files start with these lines, as comments. AIGER files have them at the
+//! end, in the comment section after a `c` line, and .qgh files in the comment of their header.
+//! JSON files have a top-level `provenance` object with the same keys. The checksum always covers
+//! the content of the file without the provenance.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use serde_json::{Map, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::qgh;
+use crate::network::fingerprint::{fingerprint, StableHasher};
+use crate::Network;
+
+/// Provenance information of a netlist file
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Provenance {
+    /// Version of quaigh that wrote the file
+    pub version: Option<String>,
+    /// Fingerprint of the network in the file
+    pub fingerprint: Option<u64>,
+    /// Name of the file the network was derived from
+    pub source: Option<String>,
+    /// Fingerprint of the network the file was derived from
+    pub source_fingerprint: Option<u64>,
+    /// Assumptions under which the network is equivalent to its source, if any
+    pub assumptions: Option<String>,
+    /// Date of writing, in UTC
+    pub date: Option<String>,
+    /// Whether the content of the file still matches the checksum in the header
+    ///
+    /// This is false when the file was edited after it was written, in which case the fingerprint cannot be trusted.
+    pub intact: bool,
+}
+
+/// Format a Unix timestamp as an ISO 8601 date in UTC
+fn format_date(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+    // Civil date from the number of days since 1970-01-01
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        y,
+        m,
+        d,
+        rem / 3600,
+        (rem / 60) % 60,
+        rem % 60
+    )
+}
+
+/// Parse a fingerprint written in hexadecimal
+fn parse_hex(s: &str) -> Option<u64> {
+    u64::from_str_radix(s.trim_start_matches("0x"), 16).ok()
+}
+
+/// Checksum of the body of a file
+fn checksum(body: &[u8]) -> u64 {
+    let mut h = StableHasher::new();
+    h.write(body);
+    h.finish()
+}
+
+impl Provenance {
+    /// Provenance of a network written now by this version of quaigh
+    pub fn new(aig: &Network) -> Provenance {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Provenance {
+            version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            fingerprint: Some(fingerprint(aig)),
+            source: None,
+            source_fingerprint: None,
+            assumptions: None,
+            date: Some(format_date(now)),
+            intact: true,
+        }
+    }
+
+    /// Record the file the network was derived from
+    pub fn with_source(mut self, name: &str, source: &Network) -> Provenance {
+        self.source = Some(name.to_string());
+        self.source_fingerprint = Some(fingerprint(source));
+        self
+    }
+
+    /// Record the assumptions under which the network is equivalent to its source
+    pub fn with_assumptions(mut self, assumptions: &str) -> Provenance {
+        self.assumptions = Some(assumptions.to_string());
+        self
+    }
+
+    /// Keys and values of the provenance, in the order they are written
+    fn fields(&self, checksum: u64) -> Vec<(&'static str, String)> {
+        let mut ret = Vec::new();
+        if let Some(v) = &self.version {
+            ret.push(("quaigh-version", v.clone()));
+        }
+        if let Some(f) = self.fingerprint {
+            ret.push(("fingerprint", format!("{:#018x}", f)));
+        }
+        if let Some(s) = &self.source {
+            ret.push(("source", s.clone()));
+        }
+        if let Some(f) = self.source_fingerprint {
+            ret.push(("source-fingerprint", format!("{:#018x}", f)));
+        }
+        if let Some(a) = &self.assumptions {
+            ret.push(("assumptions", a.clone()));
+        }
+        if let Some(d) = &self.date {
+            ret.push(("date", d.clone()));
+        }
+        ret.push(("checksum", format!("{:#018x}", checksum)));
+        ret
+    }
+
+    /// Record a field of the provenance, and return whether the key is known
+    fn set_field(&mut self, key: &str, value: &str, checksum: &mut Option<u64>) -> bool {
+        let value = value.trim();
+        match key.trim() {
+            "quaigh-version" => self.version = Some(value.to_string()),
+            "fingerprint" => self.fingerprint = parse_hex(value),
+            "source" => self.source = Some(value.to_string()),
+            "source-fingerprint" => self.source_fingerprint = parse_hex(value),
+            "assumptions" => self.assumptions = Some(value.to_string()),
+            "date" => self.date = Some(value.to_string()),
+            "checksum" => *checksum = parse_hex(value),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Parse a provenance comment line, and return whether it is one
+    fn parse_line(&mut self, line: &[u8], checksum: &mut Option<u64>) -> bool {
+        let Ok(s) = std::str::from_utf8(line) else {
+            return false;
+        };
+        let Some((key, value)) = s.trim().strip_prefix('#').and_then(|s| s.split_once(':')) else {
+            return false;
+        };
+        self.set_field(key, value, checksum)
+    }
+
+    /// Parse provenance comment lines that make up the whole data
+    fn parse_all_lines(data: &[u8]) -> Option<(Provenance, Option<u64>)> {
+        let mut ret = Provenance::default();
+        let mut found_checksum = None;
+        if data.is_empty() {
+            return None;
+        }
+        for line in data.split_inclusive(|c| *c == b'\n') {
+            if !ret.parse_line(line, &mut found_checksum) {
+                return None;
+            }
+        }
+        Some((ret, found_checksum))
+    }
+
+    /// Parse the provenance header at the start of a file, if present
+    ///
+    /// This is the header of .bench and .blif files: use [`Provenance::parse_format`] for the others.
+    pub fn parse(data: &[u8]) -> Option<Provenance> {
+        let mut ret = Provenance::default();
+        let mut found_checksum = None;
+        let mut header_len = 0;
+        for line in data.split_inclusive(|c| *c == b'\n') {
+            if !ret.parse_line(line, &mut found_checksum) {
+                break;
+            }
+            header_len += line.len();
+        }
+        if header_len == 0 {
+            return None;
+        }
+        ret.intact = found_checksum == Some(checksum(&data[header_len..]));
+        Some(ret)
+    }
+
+    /// Parse the provenance of a file in the format given by its usual file extension, if present
+    pub fn parse_format(data: &[u8], format: &str) -> Option<Provenance> {
+        match format {
+            "aag" | "aig" => Provenance::parse_aiger(data),
+            "json" => Provenance::parse_json(data),
+            "qgh" => {
+                let (comment, rest) = qgh::split_comment(data)?;
+                let (mut ret, found_checksum) = Provenance::parse_all_lines(comment)?;
+                ret.intact = found_checksum == Some(checksum(rest));
+                Some(ret)
+            }
+            _ => Provenance::parse(data),
+        }
+    }
+
+    /// Parse the provenance in the comment section at the end of an AIGER file
+    fn parse_aiger(data: &[u8]) -> Option<Provenance> {
+        const START: &[u8] = b"c\n#";
+        // The binary format may contain the start of the section by chance, before the actual one
+        let mut pos = 0;
+        while let Some(i) = data[pos..].windows(START.len()).position(|w| w == START) {
+            let start = pos + i;
+            if let Some((mut ret, found_checksum)) = Provenance::parse_all_lines(&data[start + 2..])
+            {
+                ret.intact = found_checksum == Some(checksum(&data[..start]));
+                return Some(ret);
+            }
+            pos = start + 1;
+        }
+        None
+    }
+
+    /// Parse the provenance object on the first line of a JSON file
+    fn parse_json(data: &[u8]) -> Option<Provenance> {
+        const START: &[u8] = b"{\n  \"provenance\": ";
+        let rest = data.strip_prefix(START)?;
+        let line_len = rest.iter().position(|c| *c == b'\n')?;
+        let line = rest[..line_len].strip_suffix(b",")?;
+        let fields: Map<String, Value> = serde_json::from_slice(line).ok()?;
+        let mut ret = Provenance::default();
+        let mut found_checksum = None;
+        for (key, value) in &fields {
+            ret.set_field(key, value.as_str()?, &mut found_checksum);
+        }
+        let mut body = b"{\n".to_vec();
+        body.extend_from_slice(&rest[line_len + 1..]);
+        ret.intact = found_checksum == Some(checksum(&body));
+        Some(ret)
+    }
+
+    /// Write the provenance comment lines, for the given checksum
+    fn write_lines<W: Write>(&self, w: &mut W, checksum: u64) -> io::Result<()> {
+        for (key, value) in self.fields(checksum) {
+            writeln!(w, "# {}: {}", key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Write the provenance header followed by the body of the file
+    ///
+    /// This is the header of .bench and .blif files: use [`Provenance::write_format`] for the others.
+    pub fn write_with_body<W: Write>(&self, w: &mut W, body: &[u8]) -> io::Result<()> {
+        self.write_lines(w, checksum(body))?;
+        w.write_all(body)
+    }
+
+    /// Write the body of a file in the format given by its usual file extension, with the provenance
+    pub fn write_format<W: Write>(&self, w: &mut W, body: &[u8], format: &str) -> io::Result<()> {
+        match format {
+            "aag" | "aig" => {
+                w.write_all(body)?;
+                writeln!(w, "c")?;
+                self.write_lines(w, checksum(body))
+            }
+            "json" => {
+                let Some(rest) = body.strip_prefix(b"{\n") else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "JSON content is not an object",
+                    ));
+                };
+                let fields: Map<String, Value> = self
+                    .fields(checksum(body))
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), Value::String(value)))
+                    .collect();
+                writeln!(w, "{{")?;
+                writeln!(w, "  \"provenance\": {},", Value::Object(fields))?;
+                w.write_all(rest)
+            }
+            "qgh" => {
+                let (_, rest) = qgh::split_comment(body).unwrap_or_default();
+                let mut comment = Vec::new();
+                self.write_lines(&mut comment, checksum(rest))?;
+                qgh::write_with_comment(w, body, &comment)
+            }
+            _ => self.write_with_body(w, body),
+        }
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Provenance:")?;
+        if let Some(v) = &self.version {
+            writeln!(f, "  Written by: quaigh {}", v)?;
+        }
+        if let Some(d) = &self.date {
+            writeln!(f, "  Date: {}", d)?;
+        }
+        if let Some(fp) = self.fingerprint {
+            writeln!(f, "  Fingerprint: {:#018x}", fp)?;
+        }
+        if let Some(s) = &self.source {
+            write!(f, "  Source: {}", s)?;
+            if let Some(fp) = self.source_fingerprint {
+                write!(f, " (fingerprint {:#018x})", fp)?;
+            }
+            writeln!(f)?;
+        }
+        if let Some(a) = &self.assumptions {
+            writeln!(f, "  Assuming: {}", a)?;
+        }
+        if !self.intact {
+            writeln!(f, "  Warning: the file was modified after it was written")?;
+        }
+        fmt::Result::Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_date, Provenance};
+    use crate::io::{read_bench, read_blif, read_network, write_bench, write_blif, write_network};
+    use crate::network::fingerprint::fingerprint;
+    use crate::network::generators::adder;
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_date(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_date(1704067199), "2023-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let src = adder::ripple_carry(3);
+        let mut aig = src.clone();
+        aig.make_canonical();
+        let prov = Provenance::new(&aig)
+            .with_source("adder.bench", &src)
+            .with_assumptions("onehot.bench");
+        for blif in [false, true] {
+            let mut body = Vec::new();
+            if blif {
+                write_blif(&mut body, &aig);
+            } else {
+                write_bench(&mut body, &aig);
+            }
+            let mut file = Vec::new();
+            prov.write_with_body(&mut file, &body).unwrap();
+
+            let parsed = Provenance::parse(&file).unwrap();
+            assert_eq!(parsed, prov);
+            assert!(parsed.intact);
+            assert_eq!(parsed.fingerprint, Some(fingerprint(&aig)));
+            assert_eq!(parsed.source_fingerprint, Some(fingerprint(&src)));
+
+            // The file is still readable
+            let read = if blif {
+                read_blif(file.as_slice()).unwrap()
+            } else {
+                read_bench(file.as_slice()).unwrap()
+            };
+            assert_eq!(read.nb_inputs(), aig.nb_inputs());
+            assert_eq!(read.nb_outputs(), aig.nb_outputs());
+
+            // Editing the file is detected
+            let mut edited = file.clone();
+            edited.extend(b"\n# edited\n");
+            assert!(!Provenance::parse(&edited).unwrap().intact);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_formats() {
+        let aig = adder::ripple_carry(3);
+        let prov = Provenance::new(&aig).with_source("adder.bench", &aig);
+        for format in ["aag", "aig", "json", "qgh"] {
+            let body = write_network(&aig, format).unwrap();
+            assert_eq!(Provenance::parse_format(&body, format), None);
+            let mut file = Vec::new();
+            prov.write_format(&mut file, &body, format).unwrap();
+
+            let parsed = Provenance::parse_format(&file, format).unwrap();
+            assert_eq!(parsed, prov, "{}", format);
+            assert!(parsed.intact);
+
+            // The file is still readable
+            let read = read_network(&file, format).unwrap();
+            assert_eq!(read.nb_inputs(), aig.nb_inputs());
+            assert_eq!(read.nb_outputs(), aig.nb_outputs());
+        }
+    }
+
+    #[test]
+    fn test_edited_formats() {
+        let aig = adder::ripple_carry(3);
+        let prov = Provenance::new(&aig);
+        let mut file = Vec::new();
+        let body = write_network(&aig, "aag").unwrap();
+        prov.write_format(&mut file, &body, "aag").unwrap();
+        file[4] = b'9';
+        assert!(!Provenance::parse_format(&file, "aag").unwrap().intact);
+
+        let mut file = Vec::new();
+        let body = write_network(&aig, "json").unwrap();
+        prov.write_format(&mut file, &body, "json").unwrap();
+        let edited = String::from_utf8(file)
+            .unwrap()
+            .replace("\"quaigh\"", "\"edited\"");
+        assert!(
+            !Provenance::parse_format(edited.as_bytes(), "json")
+                .unwrap()
+                .intact
+        );
+    }
+
+    #[test]
+    fn test_no_header() {
+        assert_eq!(
+            Provenance::parse(b"# .bench (ISCAS) file\nINPUT(a)\n"),
+            None
+        );
+        assert_eq!(Provenance::parse(b""), None);
+    }
+}
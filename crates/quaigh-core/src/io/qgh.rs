@@ -0,0 +1,535 @@
+//! Read and write networks in Quaigh's own binary format (.qgh)
+//!
+//! The format is meant for intermediate saves of large networks: it is compact and fast to read
+//! and write, and represents every gate exactly, including flip-flop clocks, latches, Luts and the
+//! kind of each output. Names are not saved.
+//!
+//! After the magic bytes `QGH` and the format version, all numbers are unsigned LEB128 varints.
+//! The header has a free-form comment, where the provenance of the file is written.
+//! Signals are numbered as in AIGER, with the constants first, then the inputs then the nodes,
+//! and twice the index plus one if inverted. They are stored as zigzag-encoded differences with the
+//! literal of the node being read, so that the local connections of a network take a single byte.
+//! As in AIGER, the second input of a 2-input gate is stored as a difference with the first one,
+//! with a flag in the tag if they were swapped.
+//!
+//! ```text
+//! QGH <version> <comment length> <comment bytes>
+//! <nb_inputs> <nb_nodes> <nb_outputs>
+//! <gate>*       tag, then the tag-specific data and the gate inputs
+//! <output>*     output signal, then output kind
+//! ```
+
+use std::io::{self, Read, Write};
+
+use crate::network::{BinaryType, ClockId, LutGate, NaryType, OutputKind, TernaryType, MAX_CLOCKS};
+use crate::{Gate, Network, QuaighError, Signal};
+
+/// Version of the .qgh format written by [`write_quaigh`]
+pub const QUAIGH_FORMAT_VERSION: u8 = 2;
+
+/// Oldest version of the .qgh format that can be read, without a comment in the header
+const OLDEST_FORMAT_VERSION: u8 = 1;
+
+const MAGIC: &[u8] = b"QGH";
+
+const TAG_AND2: u8 = 0;
+const TAG_XOR2: u8 = 1;
+const TAG_AND3: u8 = 2;
+const TAG_XOR3: u8 = 3;
+const TAG_MAJ: u8 = 4;
+const TAG_MUX: u8 = 5;
+const TAG_NARY: u8 = 6;
+const TAG_BUF: u8 = 12;
+const TAG_DFF: u8 = 13;
+const TAG_LATCH: u8 = 14;
+const TAG_LUT: u8 = 15;
+/// Flag of the tag of a 2-input gate, if its inputs are stored in reverse order
+const TAG_SWAPPED: u8 = 0x80;
+
+const NARY_TYPES: [NaryType; 6] = [
+    NaryType::And,
+    NaryType::Or,
+    NaryType::Nand,
+    NaryType::Nor,
+    NaryType::Xor,
+    NaryType::Xnor,
+];
+
+const OUTPUT_KINDS: [OutputKind; 3] = [
+    OutputKind::Output,
+    OutputKind::Assertion,
+    OutputKind::Assumption,
+];
+
+/// Conversion between signals and the literals of the format
+struct Literals {
+    nb_inputs: u64,
+}
+
+impl Literals {
+    fn literal(&self, s: Signal) -> u64 {
+        let ind = if s.is_constant() {
+            0
+        } else if s.is_input() {
+            1 + s.input() as u64
+        } else {
+            1 + self.nb_inputs + s.var() as u64
+        };
+        2 * ind + s.is_inverted() as u64
+    }
+
+    fn signal(&self, lit: u64) -> Signal {
+        let ind = lit >> 1;
+        let s = if ind == 0 {
+            Signal::zero()
+        } else if ind <= self.nb_inputs {
+            Signal::from_input((ind - 1) as u32)
+        } else {
+            Signal::from_var((ind - 1 - self.nb_inputs) as u32)
+        };
+        s ^ (lit & 1 != 0)
+    }
+}
+
+/// Encoder for the content of a .qgh file
+struct Encoder {
+    data: Vec<u8>,
+    literals: Literals,
+    /// Literal of the node being written
+    current: u64,
+}
+
+/// Append a varint to the content of a .qgh file
+fn push_number(data: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        data.push((v as u8 & 0x7f) | 0x80);
+        v >>= 7;
+    }
+    data.push(v as u8);
+}
+
+impl Encoder {
+    fn write_number(&mut self, v: u64) {
+        push_number(&mut self.data, v);
+    }
+
+    fn write_signal(&mut self, s: Signal) {
+        let delta = self.current as i64 - self.literals.literal(s) as i64;
+        self.write_number(((delta << 1) ^ (delta >> 63)) as u64);
+    }
+
+    fn write_signals(&mut self, signals: &[Signal]) {
+        for s in signals {
+            self.write_signal(*s);
+        }
+    }
+
+    fn write_gate(&mut self, g: &Gate) {
+        match g {
+            Gate::Binary([a, b], tp) => {
+                let tag = match tp {
+                    BinaryType::And => TAG_AND2,
+                    BinaryType::Xor => TAG_XOR2,
+                };
+                let (la, lb) = (self.literals.literal(*a), self.literals.literal(*b));
+                if la >= lb {
+                    self.data.push(tag);
+                    self.write_signal(*a);
+                    self.write_number(la - lb);
+                } else {
+                    self.data.push(tag | TAG_SWAPPED);
+                    self.write_signal(*b);
+                    self.write_number(lb - la);
+                }
+            }
+            Gate::Ternary(s, tp) => {
+                self.data.push(match tp {
+                    TernaryType::And => TAG_AND3,
+                    TernaryType::Xor => TAG_XOR3,
+                    TernaryType::Maj => TAG_MAJ,
+                    TernaryType::Mux => TAG_MUX,
+                });
+                self.write_signals(s);
+            }
+            Gate::Nary(s, tp) => {
+                let index = NARY_TYPES.iter().position(|t| t == tp).unwrap();
+                self.data.push(TAG_NARY + index as u8);
+                self.write_number(s.len() as u64);
+                self.write_signals(s);
+            }
+            Gate::Buf(s) => {
+                self.data.push(TAG_BUF);
+                self.write_signal(*s);
+            }
+            Gate::Dff(s, clock) => {
+                self.data.push(TAG_DFF);
+                let packed = (clock.index() as u64) << 1 | clock.is_falling_edge() as u64;
+                self.write_number(packed);
+                self.write_signals(s);
+            }
+            Gate::Latch(s) => {
+                self.data.push(TAG_LATCH);
+                self.write_signals(s);
+            }
+            Gate::Lut(lut) => {
+                self.data.push(TAG_LUT);
+                self.write_number(lut.inputs.len() as u64);
+                self.write_signals(&lut.inputs);
+                let table = lut.encode_truth_table();
+                self.write_number(table.len() as u64);
+                self.data.extend_from_slice(&table);
+            }
+        }
+    }
+}
+
+/// Write a network in .qgh format
+pub fn write_quaigh<W: Write>(w: &mut W, aig: &Network) {
+    let mut e = Encoder {
+        data: Vec::new(),
+        literals: Literals {
+            nb_inputs: aig.nb_inputs() as u64,
+        },
+        current: 0,
+    };
+    e.data.extend_from_slice(MAGIC);
+    e.data.push(QUAIGH_FORMAT_VERSION);
+    // Empty comment
+    e.write_number(0);
+    e.write_number(aig.nb_inputs() as u64);
+    e.write_number(aig.nb_nodes() as u64);
+    e.write_number(aig.nb_outputs() as u64);
+    for (s, g) in aig.iter_gates() {
+        e.current = e.literals.literal(s);
+        e.write_gate(&g);
+    }
+    e.current = 2 * (1 + aig.nb_inputs() + aig.nb_nodes()) as u64;
+    for o in 0..aig.nb_outputs() {
+        e.write_signal(aig.output(o));
+        let kind = OUTPUT_KINDS
+            .iter()
+            .position(|k| *k == aig.output_kind(o))
+            .unwrap();
+        e.data.push(kind as u8);
+    }
+    w.write_all(&e.data).unwrap();
+}
+
+/// Split the content of a .qgh file between the comment of its header and the data that follows
+pub(crate) fn split_comment(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    if !data.starts_with(MAGIC) {
+        return None;
+    }
+    let mut p = Parser {
+        data,
+        pos: MAGIC.len(),
+        literals: Literals { nb_inputs: 0 },
+        current: 0,
+        end: 0,
+    };
+    if p.read_byte().ok()? != QUAIGH_FORMAT_VERSION {
+        return None;
+    }
+    let comment = p.read_comment().ok()?;
+    Some((comment, &data[p.pos..]))
+}
+
+/// Write the content of a .qgh file, replacing the comment of its header
+pub(crate) fn write_with_comment<W: Write>(
+    w: &mut W,
+    data: &[u8],
+    comment: &[u8],
+) -> io::Result<()> {
+    let Some((_, rest)) = split_comment(data) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Invalid .qgh header",
+        ));
+    };
+    let mut header = MAGIC.to_vec();
+    header.push(QUAIGH_FORMAT_VERSION);
+    push_number(&mut header, comment.len() as u64);
+    w.write_all(&header)?;
+    w.write_all(comment)?;
+    w.write_all(rest)
+}
+
+/// Parser for the content of a .qgh file
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    literals: Literals,
+    /// Literal of the node being read
+    current: u64,
+    /// Bound on the valid literals
+    end: u64,
+}
+
+impl<'a> Parser<'a> {
+    fn read_byte(&mut self) -> Result<u8, String> {
+        let Some(b) = self.data.get(self.pos) else {
+            return Err("Unexpected end of file".to_owned());
+        };
+        self.pos += 1;
+        Ok(*b)
+    }
+
+    fn read_number(&mut self) -> Result<u64, String> {
+        let mut ret: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let b = self.read_byte()?;
+            if shift > 63 {
+                return Err("Invalid number encoding".to_owned());
+            }
+            ret |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(ret);
+            }
+            shift += 7;
+        }
+    }
+
+    /// Read a count of items, each taking at least one byte
+    fn read_count(&mut self, what: &str) -> Result<usize, String> {
+        let n = self.read_number()?;
+        if n > (self.data.len() - self.pos) as u64 {
+            return Err(format!("Invalid number of {}: {}", what, n));
+        }
+        Ok(n as usize)
+    }
+
+    fn read_comment(&mut self) -> Result<&'a [u8], String> {
+        let n = self.read_count("comment bytes")?;
+        self.pos += n;
+        Ok(&self.data[self.pos - n..self.pos])
+    }
+
+    fn read_literal(&mut self) -> Result<u64, String> {
+        let v = self.read_number()?;
+        let delta = (v >> 1) as i64 ^ -((v & 1) as i64);
+        let lit = (self.current as i64).wrapping_sub(delta);
+        if lit < 0 || lit as u64 >= self.end {
+            return Err(format!("Invalid signal at byte {}", self.pos));
+        }
+        Ok(lit as u64)
+    }
+
+    fn read_signal(&mut self) -> Result<Signal, String> {
+        let lit = self.read_literal()?;
+        Ok(self.literals.signal(lit))
+    }
+
+    /// Read the inputs of a 2-input gate
+    fn read_pair(&mut self, swapped: bool) -> Result<[Signal; 2], String> {
+        let hi = self.read_literal()?;
+        let diff = self.read_number()?;
+        if diff > hi {
+            return Err(format!("Invalid signal at byte {}", self.pos));
+        }
+        let (a, b) = (self.literals.signal(hi), self.literals.signal(hi - diff));
+        Ok(if swapped { [b, a] } else { [a, b] })
+    }
+
+    fn read_signals<const N: usize>(&mut self) -> Result<[Signal; N], String> {
+        let mut ret = [Signal::zero(); N];
+        for s in &mut ret {
+            *s = self.read_signal()?;
+        }
+        Ok(ret)
+    }
+
+    fn read_signal_vec(&mut self) -> Result<Vec<Signal>, String> {
+        let n = self.read_count("gate inputs")?;
+        (0..n).map(|_| self.read_signal()).collect()
+    }
+
+    fn read_gate(&mut self) -> Result<Gate, String> {
+        let tag = self.read_byte()?;
+        let swapped = tag & TAG_SWAPPED != 0;
+        let g = match tag & !TAG_SWAPPED {
+            TAG_AND2 => Gate::Binary(self.read_pair(swapped)?, BinaryType::And),
+            TAG_XOR2 => Gate::Binary(self.read_pair(swapped)?, BinaryType::Xor),
+            _ if swapped => return Err(format!("Invalid gate tag {}", tag)),
+            TAG_AND3 => Gate::Ternary(self.read_signals()?, TernaryType::And),
+            TAG_XOR3 => Gate::Ternary(self.read_signals()?, TernaryType::Xor),
+            TAG_MAJ => Gate::Ternary(self.read_signals()?, TernaryType::Maj),
+            TAG_MUX => Gate::Ternary(self.read_signals()?, TernaryType::Mux),
+            t if (TAG_NARY..TAG_BUF).contains(&t) => {
+                let tp = NARY_TYPES[(t - TAG_NARY) as usize];
+                Gate::Nary(self.read_signal_vec()?.into(), tp)
+            }
+            TAG_BUF => Gate::Buf(self.read_signal()?),
+            TAG_DFF => {
+                let packed = self.read_number()?;
+                if packed >> 1 >= MAX_CLOCKS as u64 {
+                    return Err(format!("Too many clocks: {}", (packed >> 1) + 1));
+                }
+                let clock = ClockId::new((packed >> 1) as u32, packed & 1 != 0);
+                Gate::Dff(self.read_signals()?, clock)
+            }
+            TAG_LATCH => Gate::Latch(self.read_signals()?),
+            TAG_LUT => {
+                let inputs = self.read_signal_vec()?;
+                let len = self.read_count("truth table bytes")?;
+                let lut = LutGate::decode_truth_table(&self.data[self.pos..self.pos + len])?;
+                self.pos += len;
+                if lut.num_vars() != inputs.len() {
+                    return Err(format!(
+                        "Lut with {} inputs has a truth table with {} inputs",
+                        inputs.len(),
+                        lut.num_vars()
+                    ));
+                }
+                Gate::lut(&inputs, lut)
+            }
+            t => return Err(format!("Invalid gate tag {}", t)),
+        };
+        Ok(g)
+    }
+}
+
+/// Read a network in .qgh format
+pub fn read_quaigh<R: Read>(r: R) -> Result<Network, QuaighError> {
+    parse_quaigh(r).map_err(QuaighError::parse)
+}
+
+fn parse_quaigh<R: Read>(mut r: R) -> Result<Network, String> {
+    let mut data = Vec::new();
+    r.read_to_end(&mut data).map_err(|e| e.to_string())?;
+    if !data.starts_with(MAGIC) {
+        return Err("Missing QGH header".to_owned());
+    }
+    let mut p = Parser {
+        data: &data,
+        pos: MAGIC.len(),
+        literals: Literals { nb_inputs: 0 },
+        current: 0,
+        end: 0,
+    };
+    let version = p.read_byte()?;
+    if !(OLDEST_FORMAT_VERSION..=QUAIGH_FORMAT_VERSION).contains(&version) {
+        return Err(format!("Unsupported .qgh format version {}", version));
+    }
+    if version >= 2 {
+        p.read_comment()?;
+    }
+    let nb_inputs = p.read_number()?;
+    let nb_nodes = p.read_count("nodes")?;
+    let nb_outputs = p.read_count("outputs")?;
+    if nb_inputs + nb_nodes as u64 >= 1 << 30 {
+        return Err("Too many inputs and nodes".to_owned());
+    }
+    p.literals.nb_inputs = nb_inputs;
+    p.end = 2 * (1 + nb_inputs + nb_nodes as u64);
+
+    let mut ret = Network::new();
+    ret.add_inputs(nb_inputs as usize);
+    for i in 0..nb_nodes {
+        p.current = p.literals.literal(Signal::from_var(i as u32));
+        let g = p.read_gate()?;
+        ret.add(g);
+    }
+    p.current = p.end;
+    for o in 0..nb_outputs {
+        let s = p.read_signal()?;
+        let kind = p.read_byte()?;
+        let Some(kind) = OUTPUT_KINDS.get(kind as usize) else {
+            return Err(format!("Invalid output kind {}", kind));
+        };
+        ret.add_output(s);
+        ret.set_output_kind(o, *kind);
+    }
+    if p.pos != data.len() {
+        return Err("Unexpected data at the end of the file".to_owned());
+    }
+    if !ret.is_topo_sorted() {
+        return Err("Nodes are not in topological order".to_owned());
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use volute::Lut;
+
+    use super::{read_quaigh, split_comment, write_quaigh, write_with_comment};
+    use crate::network::generators::adder;
+    use crate::network::{BinaryType, ClockId, NaryType, OutputKind};
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_roundtrip() {
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        let x = aig.add(Gate::and(a, !b));
+        let y = aig.add(Gate::mux(x, b, !c));
+        let z = aig.add(Gate::Nary([a, b, c, !y].into(), NaryType::Nor));
+        let l = aig.add(Gate::lut(&[a, z], Lut::nth_var(2, 1)));
+        let q = aig.add(Gate::dff_with_clock(
+            !l,
+            c,
+            Signal::zero(),
+            ClockId::falling(3),
+        ));
+        aig.add(Gate::latch(q, !a, b));
+        aig.add(Gate::Binary([!x, a], BinaryType::Xor));
+        aig.add(Gate::Binary([a, !x], BinaryType::And));
+        aig.add_output(q);
+        aig.add_output(!z);
+        aig.add_output(Signal::one());
+        aig.set_output_kind(1, OutputKind::Assertion);
+
+        let mut buf = Vec::new();
+        write_quaigh(&mut buf, &aig);
+        let read = read_quaigh(buf.as_slice()).unwrap();
+        assert_eq!(read.to_string(), aig.to_string());
+        assert_eq!(read.output_kind(1), OutputKind::Assertion);
+        assert_eq!(read.gate(4).clock(), Some(ClockId::falling(3)));
+
+        for len in 0..buf.len() {
+            assert!(read_quaigh(&buf[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_comment() {
+        let aig = adder::ripple_carry(4);
+        let mut buf = Vec::new();
+        write_quaigh(&mut buf, &aig);
+        assert_eq!(split_comment(&buf).unwrap().0, b"");
+
+        let mut commented = Vec::new();
+        write_with_comment(&mut commented, &buf, b"# comment\n").unwrap();
+        let (comment, rest) = split_comment(&commented).unwrap();
+        assert_eq!(comment, b"# comment\n");
+        assert_eq!(rest, split_comment(&buf).unwrap().1);
+        assert_eq!(
+            read_quaigh(commented.as_slice()).unwrap().to_string(),
+            aig.to_string()
+        );
+
+        // Files of the first version have no comment
+        let mut old = b"QGH\x01".to_vec();
+        old.extend_from_slice(rest);
+        assert_eq!(
+            read_quaigh(old.as_slice()).unwrap().to_string(),
+            aig.to_string()
+        );
+    }
+
+    #[test]
+    fn test_size() {
+        let aig = adder::ripple_carry(64);
+        let mut buf = Vec::new();
+        write_quaigh(&mut buf, &aig);
+        let mut aiger = Vec::new();
+        crate::io::write_aiger(&mut aiger, &aig, true);
+        assert!(buf.len() <= aiger.len(), "{} > {}", buf.len(), aiger.len());
+        assert_eq!(
+            read_quaigh(buf.as_slice()).unwrap().to_string(),
+            aig.to_string()
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Minimal reader and writer for uncompressed tar archives
+//!
+//! Only regular files are supported, which is all we need to bundle a few text files together.
+
+use std::io::Write;
+
+const BLOCK_SIZE: usize = 512;
+
+/// Write an octal number in a fixed-size, NUL-terminated field
+fn write_octal(field: &mut [u8], value: u64) {
+    let s = format!("{:0width$o}", value, width = field.len() - 1);
+    field[..s.len()].copy_from_slice(s.as_bytes());
+    field[s.len()] = 0;
+}
+
+/// Parse an octal number from a field, ignoring padding
+fn parse_octal(field: &[u8]) -> Result<u64, String> {
+    let s: String = field
+        .iter()
+        .take_while(|c| **c != 0)
+        .map(|c| *c as char)
+        .collect();
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(s, 8).map_err(|_| format!("Invalid octal field in tar header: {}", s))
+}
+
+/// Build the ustar header for a file
+fn header(name: &str, size: usize) -> [u8; BLOCK_SIZE] {
+    assert!(name.len() < 100, "File name {} is too long", name);
+    let mut h = [0u8; BLOCK_SIZE];
+    h[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut h[100..108], 0o644);
+    write_octal(&mut h[108..116], 0);
+    write_octal(&mut h[116..124], 0);
+    write_octal(&mut h[124..136], size as u64);
+    write_octal(&mut h[136..148], 0);
+    h[156] = b'0';
+    h[257..263].copy_from_slice(b"ustar\0");
+    h[263..265].copy_from_slice(b"00");
+    // The checksum is computed with the checksum field filled with spaces
+    h[148..156].copy_from_slice(b"        ");
+    let sum: u64 = h.iter().map(|c| *c as u64).sum();
+    write_octal(&mut h[148..155], sum);
+    h[155] = b' ';
+    h
+}
+
+/// Write files to a tar archive
+pub fn write_tar<W: Write>(w: &mut W, files: &[(&str, &[u8])]) {
+    for (name, content) in files {
+        w.write_all(&header(name, content.len())).unwrap();
+        w.write_all(content).unwrap();
+        let padding = (BLOCK_SIZE - content.len() % BLOCK_SIZE) % BLOCK_SIZE;
+        w.write_all(&vec![0u8; padding]).unwrap();
+    }
+    w.write_all(&[0u8; 2 * BLOCK_SIZE]).unwrap();
+}
+
+/// Read the regular files of a tar archive
+pub fn read_tar(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut ret = Vec::new();
+    let mut pos = 0;
+    while pos + BLOCK_SIZE <= data.len() {
+        let h = &data[pos..pos + BLOCK_SIZE];
+        if h.iter().all(|c| *c == 0) {
+            return Ok(ret);
+        }
+        let expected_sum = parse_octal(&h[148..156])?;
+        let sum: u64 = h
+            .iter()
+            .enumerate()
+            .map(|(i, c)| if (148..156).contains(&i) { b' ' } else { *c } as u64)
+            .sum();
+        if sum != expected_sum {
+            return Err("Invalid checksum in tar header".to_owned());
+        }
+        let name: String = h[..100]
+            .iter()
+            .take_while(|c| **c != 0)
+            .map(|c| *c as char)
+            .collect();
+        let size = parse_octal(&h[124..136])? as usize;
+        pos += BLOCK_SIZE;
+        if pos + size > data.len() {
+            return Err(format!("Truncated tar archive in file {}", name));
+        }
+        // Only keep regular files
+        if h[156] == b'0' || h[156] == 0 {
+            ret.push((name, data[pos..pos + size].to_vec()));
+        }
+        pos += size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+    }
+    Err("Tar archive ended without end-of-archive marker".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_tar, write_tar};
+
+    #[test]
+    fn test_roundtrip() {
+        let big = vec![b'x'; 1500];
+        let files: Vec<(&str, &[u8])> =
+            vec![("a.txt", b"hello\n"), ("empty", b""), ("big.bin", &big)];
+        let mut data = Vec::new();
+        write_tar(&mut data, &files);
+        assert_eq!(data.len() % 512, 0);
+        let read = read_tar(&data).unwrap();
+        assert_eq!(read.len(), 3);
+        for ((n1, c1), (n2, c2)) in files.iter().zip(read.iter()) {
+            assert_eq!(n1, n2);
+            assert_eq!(c1, &c2.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_corrupted() {
+        let mut data = Vec::new();
+        write_tar(&mut data, &[("a.txt", b"hello\n")]);
+        data[0] = b'b';
+        assert!(read_tar(&data).is_err());
+        assert!(read_tar(&data[..600]).is_err());
+    }
+}
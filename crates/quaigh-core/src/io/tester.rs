@@ -0,0 +1,457 @@
+//! Export of test patterns to tester formats
+//!
+//! Test patterns are applied on the network with exposed flip-flops: each timestep gives the
+//! primary inputs, followed by the values of the flip-flops. When the network has flip-flops, they
+//! are accessed through a single mux-scan chain, as inserted by `scan_insert`. The values of the
+//! flip-flops are loaded with the first timestep of each pattern, each timestep is applied as one
+//! capture cycle, and the next state of the flip-flops is unloaded after the last timestep while
+//! the next pattern is loaded.
+
+use std::io::Write;
+
+/// Values loaded in the scan cells, and values unloaded from them
+type ScanData<'a> = (Option<&'a [bool]>, Option<&'a [bool]>);
+
+/// Signals of the device under test, for export of test patterns
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TesterSignals {
+    /// Names of the primary inputs
+    pub inputs: Vec<String>,
+    /// Names of the primary outputs
+    pub outputs: Vec<String>,
+    /// Names of the scan cells, from the scan input to the scan output
+    pub scan_cells: Vec<String>,
+    /// Name of the scan enable input
+    pub scan_enable: String,
+    /// Name of the scan input
+    pub scan_in: String,
+    /// Name of the scan output
+    pub scan_out: String,
+}
+
+impl TesterSignals {
+    /// Create the signals of a device, with default names for the scan ports
+    pub fn new(
+        inputs: Vec<String>,
+        outputs: Vec<String>,
+        scan_cells: Vec<String>,
+    ) -> TesterSignals {
+        TesterSignals {
+            inputs,
+            outputs,
+            scan_cells,
+            scan_enable: "scan_en".to_owned(),
+            scan_in: "scan_in".to_owned(),
+            scan_out: "scan_out".to_owned(),
+        }
+    }
+
+    /// Returns whether the patterns are applied through a scan chain
+    pub fn has_scan(&self) -> bool {
+        !self.scan_cells.is_empty()
+    }
+
+    /// Check that the patterns and responses match the signals
+    fn check(&self, patterns: &[Vec<Vec<bool>>], responses: &[Vec<Vec<bool>>]) {
+        assert_eq!(patterns.len(), responses.len());
+        for (p, r) in patterns.iter().zip(responses) {
+            assert_eq!(p.len(), r.len());
+            for v in p {
+                assert_eq!(v.len(), self.inputs.len() + self.scan_cells.len());
+            }
+            for v in r {
+                assert_eq!(v.len(), self.outputs.len() + self.scan_cells.len());
+            }
+        }
+    }
+
+    /// Values loaded in and unloaded from the scan cells for each pattern
+    ///
+    /// Returns the values to load, then the values to unload from the previous pattern.
+    fn scan_data<'a>(
+        &self,
+        patterns: &'a [Vec<Vec<bool>>],
+        responses: &'a [Vec<Vec<bool>>],
+    ) -> Vec<ScanData<'a>> {
+        let nb_cells = self.scan_cells.len();
+        let load = |p: &'a Vec<Vec<bool>>| p.first().map(|v| &v[v.len() - nb_cells..]);
+        let unload = |r: &'a Vec<Vec<bool>>| r.last().map(|v| &v[v.len() - nb_cells..]);
+        let mut ret = Vec::new();
+        let mut prev = None;
+        for (p, r) in patterns.iter().zip(responses) {
+            ret.push((load(p), prev));
+            prev = unload(r);
+        }
+        ret.push((None, prev));
+        ret
+    }
+}
+
+fn quote_list(names: &[String], sep: &str) -> String {
+    names
+        .iter()
+        .map(|n| format!("\"{}\"", n))
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+fn drive(values: &[bool]) -> String {
+    values.iter().map(|b| if *b { '1' } else { '0' }).collect()
+}
+
+fn expect(values: &[bool]) -> String {
+    values.iter().map(|b| if *b { 'H' } else { 'L' }).collect()
+}
+
+/// Write test patterns in STIL format (IEEE 1450)
+///
+/// Each pattern gives the values of each timestep, and each response the expected primary outputs
+/// followed by the next state of the scan cells. Scan data is given in shift order: the first value
+/// shifted in ends up in the scan cell next to the scan output, and the first value shifted out
+/// comes from it.
+pub fn write_stil<W: Write>(
+    w: &mut W,
+    signals: &TesterSignals,
+    patterns: &[Vec<Vec<bool>>],
+    responses: &[Vec<Vec<bool>>],
+) {
+    signals.check(patterns, responses);
+    let nb_inputs = signals.inputs.len();
+    let nb_outputs = signals.outputs.len();
+    let nb_cells = signals.scan_cells.len();
+
+    writeln!(w, "STIL 1.0;\n").unwrap();
+    writeln!(w, "Header {{").unwrap();
+    writeln!(w, "    Title \"Test patterns\";").unwrap();
+    writeln!(w, "    Source \"generated by quaigh\";").unwrap();
+    writeln!(w, "}}\n").unwrap();
+
+    writeln!(w, "Signals {{").unwrap();
+    for n in &signals.inputs {
+        writeln!(w, "    \"{}\" In;", n).unwrap();
+    }
+    for n in &signals.outputs {
+        writeln!(w, "    \"{}\" Out;", n).unwrap();
+    }
+    if signals.has_scan() {
+        writeln!(w, "    \"{}\" In;", signals.scan_enable).unwrap();
+        writeln!(w, "    \"{}\" In {{ ScanIn; }}", signals.scan_in).unwrap();
+        writeln!(w, "    \"{}\" Out {{ ScanOut; }}", signals.scan_out).unwrap();
+    }
+    writeln!(w, "}}\n").unwrap();
+
+    writeln!(w, "SignalGroups {{").unwrap();
+    if nb_inputs > 0 {
+        writeln!(w, "    \"_pi\" = '{}';", quote_list(&signals.inputs, " + ")).unwrap();
+    }
+    if nb_outputs > 0 {
+        writeln!(
+            w,
+            "    \"_po\" = '{}';",
+            quote_list(&signals.outputs, " + ")
+        )
+        .unwrap();
+    }
+    writeln!(w, "}}\n").unwrap();
+
+    if signals.has_scan() {
+        writeln!(w, "ScanStructures {{").unwrap();
+        writeln!(w, "    ScanChain \"chain\" {{").unwrap();
+        writeln!(w, "        ScanLength {};", nb_cells).unwrap();
+        writeln!(w, "        ScanIn \"{}\";", signals.scan_in).unwrap();
+        writeln!(w, "        ScanOut \"{}\";", signals.scan_out).unwrap();
+        writeln!(w, "        ScanEnable \"{}\";", signals.scan_enable).unwrap();
+        writeln!(
+            w,
+            "        ScanCells {};",
+            quote_list(&signals.scan_cells, " ")
+        )
+        .unwrap();
+        writeln!(w, "    }}").unwrap();
+        writeln!(w, "}}\n").unwrap();
+    }
+
+    writeln!(w, "Timing {{").unwrap();
+    writeln!(w, "    WaveformTable \"_wft_\" {{").unwrap();
+    writeln!(w, "        Period '100ns';").unwrap();
+    writeln!(w, "        Waveforms {{").unwrap();
+    let mut drive_signals = Vec::new();
+    let mut strobe_signals = Vec::new();
+    if nb_inputs > 0 {
+        drive_signals.push("_pi");
+    }
+    if nb_outputs > 0 {
+        strobe_signals.push("_po");
+    }
+    if signals.has_scan() {
+        drive_signals.push(&signals.scan_enable);
+        drive_signals.push(&signals.scan_in);
+        strobe_signals.push(&signals.scan_out);
+    }
+    for s in drive_signals {
+        writeln!(w, "            \"{}\" {{ 01 {{ '0ns' D/U; }} }}", s).unwrap();
+    }
+    for s in strobe_signals {
+        writeln!(
+            w,
+            "            \"{}\" {{ LHX {{ '0ns' X; '80ns' L/H/X; }} }}",
+            s
+        )
+        .unwrap();
+    }
+    writeln!(w, "        }}").unwrap();
+    writeln!(w, "    }}").unwrap();
+    writeln!(w, "}}\n").unwrap();
+
+    writeln!(w, "PatternBurst \"_burst_\" {{").unwrap();
+    writeln!(w, "    PatList {{ \"_pattern_\"; }}").unwrap();
+    writeln!(w, "}}\n").unwrap();
+    writeln!(w, "PatternExec {{").unwrap();
+    writeln!(w, "    PatternBurst \"_burst_\";").unwrap();
+    writeln!(w, "}}\n").unwrap();
+
+    if signals.has_scan() {
+        writeln!(w, "Procedures {{").unwrap();
+        writeln!(w, "    \"load_unload\" {{").unwrap();
+        writeln!(w, "        W \"_wft_\";").unwrap();
+        writeln!(w, "        V {{ \"{}\" = 1; }}", signals.scan_enable).unwrap();
+        writeln!(
+            w,
+            "        Shift {{ V {{ \"{}\" = #; \"{}\" = #; }} }}",
+            signals.scan_in, signals.scan_out
+        )
+        .unwrap();
+        writeln!(w, "    }}").unwrap();
+        writeln!(w, "}}\n").unwrap();
+    }
+
+    writeln!(w, "Pattern \"_pattern_\" {{").unwrap();
+    writeln!(w, "    W \"_wft_\";").unwrap();
+    let scan_data = signals.scan_data(patterns, responses);
+    for (i, (p, r)) in patterns.iter().zip(responses).enumerate() {
+        let mut label = format!("\"pattern {}\": ", i + 1);
+        if signals.has_scan() {
+            let (load, unload) = scan_data[i];
+            write_stil_load_unload(w, signals, &label, load, unload);
+            label.clear();
+        }
+        for (v, o) in p.iter().zip(r) {
+            let mut stmt = String::new();
+            if signals.has_scan() {
+                stmt.push_str(&format!("\"{}\" = 0; ", signals.scan_enable));
+            }
+            if nb_inputs > 0 {
+                stmt.push_str(&format!("\"_pi\" = {}; ", drive(&v[..nb_inputs])));
+            }
+            if nb_outputs > 0 {
+                stmt.push_str(&format!("\"_po\" = {}; ", expect(&o[..nb_outputs])));
+            }
+            writeln!(w, "    {}V {{ {}}}", label, stmt).unwrap();
+            label.clear();
+        }
+    }
+    if signals.has_scan() {
+        let (load, unload) = scan_data[patterns.len()];
+        write_stil_load_unload(w, signals, "", load, unload);
+    }
+    writeln!(w, "}}").unwrap();
+}
+
+/// Write a call to the scan load/unload procedure, with values in shift order
+fn write_stil_load_unload<W: Write>(
+    w: &mut W,
+    signals: &TesterSignals,
+    label: &str,
+    load: Option<&[bool]>,
+    unload: Option<&[bool]>,
+) {
+    let nb_cells = signals.scan_cells.len();
+    let load: String = match load {
+        Some(v) => v.iter().rev().map(|b| if *b { '1' } else { '0' }).collect(),
+        None => "0".repeat(nb_cells),
+    };
+    let unload: String = match unload {
+        Some(v) => v.iter().rev().map(|b| if *b { 'H' } else { 'L' }).collect(),
+        None => "X".repeat(nb_cells),
+    };
+    writeln!(
+        w,
+        "    {}Call \"load_unload\" {{ \"{}\" = {}; \"{}\" = {}; }}",
+        label, signals.scan_in, load, signals.scan_out, unload
+    )
+    .unwrap();
+}
+
+/// Write test patterns in WGL format
+///
+/// The patterns and responses are given as for [`write_stil`]. Scan data is given in the order of
+/// the scan cells in the chain, starting next to the scan input.
+pub fn write_wgl<W: Write>(
+    w: &mut W,
+    signals: &TesterSignals,
+    patterns: &[Vec<Vec<bool>>],
+    responses: &[Vec<Vec<bool>>],
+) {
+    signals.check(patterns, responses);
+    let nb_inputs = signals.inputs.len();
+    let nb_outputs = signals.outputs.len();
+
+    let mut inputs = signals.inputs.clone();
+    let mut outputs = signals.outputs.clone();
+    if signals.has_scan() {
+        inputs.push(signals.scan_enable.clone());
+        inputs.push(signals.scan_in.clone());
+        outputs.push(signals.scan_out.clone());
+    }
+
+    writeln!(w, "waveform \"quaigh_patterns\"\n").unwrap();
+    writeln!(w, "signal").unwrap();
+    for n in &inputs {
+        writeln!(w, "    \"{}\" : input;", n).unwrap();
+    }
+    for n in &outputs {
+        writeln!(w, "    \"{}\" : output;", n).unwrap();
+    }
+    writeln!(w, "end\n").unwrap();
+
+    if signals.has_scan() {
+        writeln!(w, "scancell").unwrap();
+        for n in &signals.scan_cells {
+            writeln!(w, "    \"{}\";", n).unwrap();
+        }
+        writeln!(w, "end\n").unwrap();
+        writeln!(w, "scanchain").unwrap();
+        writeln!(
+            w,
+            "    \"chain\" [\"{}\", {}, \"{}\"];",
+            signals.scan_in,
+            quote_list(&signals.scan_cells, ", "),
+            signals.scan_out
+        )
+        .unwrap();
+        writeln!(w, "end\n").unwrap();
+    }
+
+    writeln!(w, "timeplate \"tp\" period 100ns").unwrap();
+    for n in &inputs {
+        writeln!(w, "    \"{}\" := input [0ns:S];", n).unwrap();
+    }
+    for n in &outputs {
+        writeln!(w, "    \"{}\" := output [0ns:X, 80ns:Q'edge];", n).unwrap();
+    }
+    writeln!(w, "end\n").unwrap();
+
+    let mut all = inputs.clone();
+    all.extend(outputs.iter().cloned());
+    writeln!(w, "pattern \"group_all\" ({})", quote_list(&all, ", ")).unwrap();
+    let scan_data = signals.scan_data(patterns, responses);
+    let write_scan = |w: &mut W, load: Option<&[bool]>, unload: Option<&[bool]>| {
+        let nb_cells = signals.scan_cells.len();
+        let load = match load {
+            Some(v) => drive(v),
+            None => "0".repeat(nb_cells),
+        };
+        let unload = match unload {
+            Some(v) => drive(v),
+            None => "X".repeat(nb_cells),
+        };
+        writeln!(
+            w,
+            "    scan(+, \"tp\") := [{}1-{}], input [\"chain\":\"{}\"], output [\"chain\":\"{}\"];",
+            "-".repeat(nb_inputs),
+            "X".repeat(nb_outputs + 1),
+            load,
+            unload
+        )
+        .unwrap();
+    };
+    for (i, (p, r)) in patterns.iter().zip(responses).enumerate() {
+        writeln!(w, "    {{pattern {}}}", i + 1).unwrap();
+        if signals.has_scan() {
+            let (load, unload) = scan_data[i];
+            write_scan(w, load, unload);
+        }
+        for (v, o) in p.iter().zip(r) {
+            let mut values = drive(&v[..nb_inputs]);
+            if signals.has_scan() {
+                values.push_str("00");
+            }
+            values.push_str(&drive(&o[..nb_outputs]));
+            if signals.has_scan() {
+                values.push('X');
+            }
+            writeln!(w, "    vector(+, \"tp\") := [{}];", values).unwrap();
+        }
+    }
+    if signals.has_scan() {
+        let (load, unload) = scan_data[patterns.len()];
+        write_scan(w, load, unload);
+    }
+    writeln!(w, "end\n").unwrap();
+    writeln!(w, "end").unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_stil, write_wgl, TesterSignals};
+
+    fn names(prefix: &str, n: usize) -> Vec<String> {
+        (0..n).map(|i| format!("{}{}", prefix, i)).collect()
+    }
+
+    #[test]
+    fn test_comb() {
+        let signals = TesterSignals::new(names("a", 2), names("z", 1), Vec::new());
+        let patterns = vec![vec![vec![false, true]], vec![vec![true, true]]];
+        let responses = vec![vec![vec![false]], vec![vec![true]]];
+        let mut buf = Vec::new();
+        write_stil(&mut buf, &signals, &patterns, &responses);
+        let s = String::from_utf8(buf).unwrap();
+        assert!(s.starts_with("STIL 1.0;"));
+        assert!(s.contains("\"_pi\" = '\"a0\" + \"a1\"';"));
+        assert!(s.contains("\"pattern 1\": V { \"_pi\" = 01; \"_po\" = L; }"));
+        assert!(s.contains("\"pattern 2\": V { \"_pi\" = 11; \"_po\" = H; }"));
+        assert!(!s.contains("ScanStructures"));
+
+        let mut buf = Vec::new();
+        write_wgl(&mut buf, &signals, &patterns, &responses);
+        let s = String::from_utf8(buf).unwrap();
+        assert!(s.contains("pattern \"group_all\" (\"a0\", \"a1\", \"z0\")"));
+        assert!(s.contains("vector(+, \"tp\") := [010];"));
+        assert!(s.contains("vector(+, \"tp\") := [111];"));
+        assert!(!s.contains("scanchain"));
+    }
+
+    #[test]
+    fn test_scan() {
+        let signals = TesterSignals::new(names("a", 1), names("z", 1), names("ff", 3));
+        let patterns = vec![
+            vec![vec![true, true, false, false]],
+            vec![vec![false, false, true, true]],
+        ];
+        let responses = vec![
+            vec![vec![true, false, false, true]],
+            vec![vec![false, true, true, false]],
+        ];
+        let mut buf = Vec::new();
+        write_stil(&mut buf, &signals, &patterns, &responses);
+        let s = String::from_utf8(buf).unwrap();
+        assert!(s.contains("ScanCells \"ff0\" \"ff1\" \"ff2\";"));
+        assert!(s.contains(
+            "\"pattern 1\": Call \"load_unload\" { \"scan_in\" = 001; \"scan_out\" = XXX; }"
+        ));
+        assert!(s.contains("V { \"scan_en\" = 0; \"_pi\" = 1; \"_po\" = H; }"));
+        assert!(s.contains(
+            "\"pattern 2\": Call \"load_unload\" { \"scan_in\" = 110; \"scan_out\" = HLL; }"
+        ));
+        assert!(s.contains("Call \"load_unload\" { \"scan_in\" = 000; \"scan_out\" = LHH; }"));
+
+        let mut buf = Vec::new();
+        write_wgl(&mut buf, &signals, &patterns, &responses);
+        let s = String::from_utf8(buf).unwrap();
+        assert!(s.contains("\"chain\" [\"scan_in\", \"ff0\", \"ff1\", \"ff2\", \"scan_out\"];"));
+        assert!(s.contains("input [\"chain\":\"100\"], output [\"chain\":\"XXX\"];"));
+        assert!(s.contains("vector(+, \"tp\") := [1001X];"));
+        assert!(s.contains("input [\"chain\":\"011\"], output [\"chain\":\"001\"];"));
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::network::NameTable;
+use crate::{Gate, Network, Signal};
+
+/// Find the set of signals that are used inverted
+///
+/// Inversions on the inputs of the gates for which `absorbs` returns true are ignored: the writer
+/// folds them into the gate.
+pub fn get_inverted_signals(aig: &Network, absorbs: impl Fn(&Gate) -> bool) -> Vec<Signal> {
+    // Generate signals where the inversion is required
+    let mut signals_with_inv = HashSet::new();
+    for o in 0..aig.nb_outputs() {
+        let s = aig.output(o);
+        if s.is_inverted() && !s.is_constant() {
+            signals_with_inv.insert(!s);
+        }
+    }
+    for i in 0..aig.nb_nodes() {
+        let g = aig.gate(i);
+        if matches!(g, Gate::Buf(_)) || absorbs(&g) {
+            // Buf(!x) is exported directly as a Not
+            continue;
+        }
+        for s in g.dependencies() {
+            if s.is_inverted() && !s.is_constant() {
+                signals_with_inv.insert(!s);
+            }
+        }
+    }
+    let mut signals_with_inv = signals_with_inv.into_iter().collect::<Vec<_>>();
+    signals_with_inv.sort();
+    signals_with_inv
+}
+
+/// Reserve a name that is not used yet, appending underscores to the base name if required
+fn fresh_name(used: &mut HashSet<String>, mut name: String) -> String {
+    while used.contains(&name) {
+        name.push('_');
+    }
+    used.insert(name.clone());
+    name
+}
+
+/// Names of the signals when writing a network, from a name table
+///
+/// Unnamed signals use generated names such as `x12` or `i3`, inverted signals use the name of
+/// the signal with an `_n` suffix, and constants are named `gnd` and `vdd`.
+///
+/// All names are allocated upfront and are guaranteed to be distinct: signals come first, then
+/// the names of the outputs, then inverted signals, and underscores are appended on conflicts.
+pub struct SignalNamer {
+    input_names: Vec<String>,
+    node_names: Vec<String>,
+    inverted_names: HashMap<Signal, String>,
+    output_names: Vec<String>,
+}
+
+impl SignalNamer {
+    pub fn new(aig: &Network, names: &NameTable) -> Self {
+        let mut used: HashSet<String> = ["gnd", "vdd"].iter().map(|s| s.to_string()).collect();
+        let input_names: Vec<String> = (0..aig.nb_inputs())
+            .map(|i| fresh_name(&mut used, names.name_or_generated(aig.input(i)).0))
+            .collect();
+        let node_names: Vec<String> = (0..aig.nb_nodes())
+            .map(|i| fresh_name(&mut used, names.name_or_generated(aig.node(i)).0))
+            .collect();
+
+        let mut namer = SignalNamer {
+            input_names,
+            node_names,
+            inverted_names: HashMap::new(),
+            output_names: Vec::new(),
+        };
+
+        // An output keeps its own name if it does not conflict with the name of a signal or of an
+        // earlier output. If it is the usual name of its inverted signal, the signal takes it.
+        let mut own_output_names = Vec::new();
+        for i in 0..aig.nb_outputs() {
+            let s = aig.output(i);
+            let own = match names.output_name(i) {
+                Some(n) if !used.contains(n) => {
+                    let n = fresh_name(&mut used, n.to_owned());
+                    if s.is_inverted()
+                        && !s.is_constant()
+                        && n == format!("{}_n", namer.name(&!s))
+                        && !namer.inverted_names.contains_key(&s)
+                    {
+                        namer.inverted_names.insert(s, n.clone());
+                    }
+                    Some(n)
+                }
+                _ => None,
+            };
+            own_output_names.push(own);
+        }
+        for s in get_inverted_signals(aig, |_| false) {
+            if namer.inverted_names.contains_key(&!s) {
+                continue;
+            }
+            let name = fresh_name(&mut used, format!("{}_n", namer.name(&s)));
+            namer.inverted_names.insert(!s, name);
+        }
+        namer.output_names = own_output_names
+            .into_iter()
+            .enumerate()
+            .map(|(i, n)| n.unwrap_or_else(|| namer.name(&aig.output(i))))
+            .collect();
+        namer
+    }
+
+    /// Name of a signal
+    pub fn name(&self, s: &Signal) -> String {
+        if *s == Signal::one() {
+            return "vdd".to_string();
+        }
+        if *s == Signal::zero() {
+            return "gnd".to_string();
+        }
+        if s.is_inverted() {
+            return match self.inverted_names.get(s) {
+                Some(n) => n.clone(),
+                None => format!("{}_n", self.name(&!s)),
+            };
+        }
+        if s.is_input() {
+            self.input_names[s.input() as usize].clone()
+        } else {
+            self.node_names[s.var() as usize].clone()
+        }
+    }
+
+    /// Names of the outputs
+    ///
+    /// An output keeps its own name if it does not conflict with the name of a signal or of an
+    /// earlier output; otherwise it uses the name of its signal.
+    pub fn output_names(&self) -> Vec<String> {
+        self.output_names.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use volute::Lut;
+
+    use crate::io::{
+        read_bench_with_names, read_blif_with_names, write_bench_with_options,
+        write_blif_with_options,
+    };
+    use crate::network::{BinaryType, NameTable, NaryType, TernaryType};
+    use crate::sim::simulate_comb_multi;
+    use crate::{Gate, Network};
+
+    /// Network whose names conflict with the usual names of constants and inverted signals
+    fn conflicting_names() -> (Network, NameTable) {
+        let mut aig = Network::new();
+        let mut names = NameTable::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        names.set_input_name(0, "vdd");
+        names.set_input_name(1, "b");
+        names.set_input_name(2, "vdd_n");
+        let x0 = aig.add(Gate::Binary([!a, b], BinaryType::Xor));
+        names.set_node_name(0, "b_n");
+        let x1 = aig.add(Gate::Nary([!b, !c].into(), NaryType::And));
+        let mut lut = Lut::zero(3);
+        for m in [1, 2, 4, 5] {
+            lut.set_bit(m);
+        }
+        let x2 = aig.add(Gate::lut(&[!a, b, !x0], lut));
+        let x3 = aig.add(Gate::Ternary([!x1, c, !x2], TernaryType::Mux));
+        aig.add_output(!x0);
+        names.set_output_name(0, "o");
+        aig.add_output(x3);
+        names.set_output_name(1, "b_n_");
+        aig.add_output(!x2);
+        names.set_output_name(2, "x2_n");
+        aig.add_output(x3);
+        names.set_output_name(3, "o");
+        (aig, names)
+    }
+
+    #[test]
+    fn test_fresh_names() {
+        let (aig, names) = conflicting_names();
+        let patterns: Vec<u64> = vec![0xf0f0, 0xcccc, 0xaaaa];
+        for fold in [false, true] {
+            let mut bench = Vec::new();
+            write_bench_with_options(&mut bench, &aig, &names, fold);
+            let mut blif = Vec::new();
+            write_blif_with_options(&mut blif, &aig, &names, fold);
+            let bench_text = String::from_utf8(bench).unwrap();
+            let blif_text = String::from_utf8(blif).unwrap();
+            assert!(bench_text.contains("INPUT(vdd_)"), "{}", bench_text);
+            assert!(bench_text.contains("OUTPUT(b_n_)"), "{}", bench_text);
+            // The output takes the name of the inverted signal, without a buffer
+            assert!(!bench_text.contains("x2_n = BUF"), "{}", bench_text);
+            assert_eq!(fold, !bench_text.contains("vdd__n = NOT"), "{}", bench_text);
+            assert_eq!(
+                fold,
+                !blif_text.contains(".names vdd_ vdd__n"),
+                "{}",
+                blif_text
+            );
+            let read = [
+                read_bench_with_names(bench_text.as_bytes()).unwrap(),
+                read_blif_with_names(blif_text.as_bytes()).unwrap(),
+            ];
+            for (aig2, names2) in read {
+                assert_eq!(aig2.nb_outputs(), 4);
+                assert_eq!(
+                    simulate_comb_multi(&aig, &patterns),
+                    simulate_comb_multi(&aig2, &patterns)
+                );
+                assert_eq!(names2.input_name(0), Some("vdd_"));
+                assert_eq!(names2.output_name(0), Some("o"));
+                assert_eq!(names2.output_name(1), Some("b_n_"));
+                assert_eq!(names2.output_name(2), Some("x2_n"));
+                assert_eq!(names2.output_name(3), Some("x3"));
+            }
+        }
+    }
+}
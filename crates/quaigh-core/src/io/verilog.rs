@@ -0,0 +1,1178 @@
+//! Read structural Verilog netlists
+//!
+//! A restricted subset is supported, as written by Yosys with `write_verilog -noattr` on a
+//! flattened design: a single module with input, output, wire and reg declarations, continuous
+//! assignments with bitwise and logical operators, reductions and multiplexers, and flip-flops.
+//!
+//! Flip-flops are either instances of a cell with `D` and `Q` ports, an optional active-high `E`
+//! enable and an optional active-high `R` synchronous reset to zero, or `always` blocks on a clock
+//! edge with non-blocking assignments, possibly under `if`/`else`. All flip-flops must use the
+//! same clock, which must be a primary input; it is kept as an unused input of the network.
+
+use std::collections::HashMap;
+
+use crate::{Gate, Network, QuaighError, Signal};
+
+/// Maximum width of a net, to reject malformed ranges before allocating
+const MAX_WIDTH: i64 = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Sym(&'static str),
+}
+
+/// Symbols, longest first so that they are matched greedily
+const SYMBOLS: [&str; 26] = [
+    "<=", "&&", "||", "~^", "^~", "(", ")", "[", "]", "{", "}", ":", ";", ",", "=", "&", "|", "^",
+    "~", "!", "?", ".", "@", "#", "*", "-",
+];
+
+/// Split the file into tokens, with their line numbers
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, QuaighError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut ret = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    let skip_until = |i: &mut usize, line: &mut usize, end: &str| -> Result<(), QuaighError> {
+        let end: Vec<char> = end.chars().collect();
+        let start_line = *line;
+        while *i < chars.len() {
+            if chars[*i..].starts_with(&end) {
+                *i += end.len();
+                return Ok(());
+            }
+            if chars[*i] == '\n' {
+                *line += 1;
+            }
+            *i += 1;
+        }
+        Err(QuaighError::parse_at(
+            start_line,
+            "unterminated comment or attribute",
+        ))
+    };
+    while i < chars.len() {
+        let c = chars[i];
+        let rest = &chars[i..];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if rest.starts_with(&['/', '/']) || c == '`' {
+            // Comments and compiler directives such as `timescale
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if rest.starts_with(&['/', '*']) {
+            skip_until(&mut i, &mut line, "*/")?;
+        } else if rest.starts_with(&['(', '*']) && !rest.starts_with(&['(', '*', ')']) {
+            skip_until(&mut i, &mut line, "*)")?;
+        } else if c == '\\' {
+            // Escaped identifier, up to the next whitespace
+            let start = i + 1;
+            while i < chars.len() && !chars[i].is_whitespace() {
+                i += 1;
+            }
+            ret.push((Token::Ident(chars[start..i].iter().collect()), line));
+        } else if c.is_ascii_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || "_$".contains(chars[i])) {
+                i += 1;
+            }
+            ret.push((Token::Ident(chars[start..i].iter().collect()), line));
+        } else if c.is_ascii_digit() || c == '\'' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '_') {
+                i += 1;
+            }
+            if i < chars.len() && chars[i] == '\'' {
+                i += 1;
+                if i < chars.len() && "sS".contains(chars[i]) {
+                    i += 1;
+                }
+                if i < chars.len() && "bBoOdDhH".contains(chars[i]) {
+                    i += 1;
+                }
+                while i < chars.len()
+                    && (chars[i].is_ascii_hexdigit() || "_xXzZ".contains(chars[i]))
+                {
+                    i += 1;
+                }
+            }
+            ret.push((Token::Number(chars[start..i].iter().collect()), line));
+        } else {
+            let Some(s) = SYMBOLS.iter().find(|s| {
+                let s: Vec<char> = s.chars().collect();
+                rest.starts_with(&s)
+            }) else {
+                return Err(QuaighError::parse_at(
+                    line,
+                    format!("unexpected character {}", c),
+                ));
+            };
+            i += s.len();
+            ret.push((Token::Sym(s), line));
+        }
+    }
+    Ok(ret)
+}
+
+/// Parse a number, and return its bits, least significant first
+fn parse_number(s: &str) -> Result<Vec<bool>, String> {
+    let s = s.replace('_', "");
+    let (width, value) = match s.split_once('\'') {
+        None => (None, format!("d{}", s)),
+        Some((w, v)) => {
+            let w = if w.is_empty() {
+                None
+            } else {
+                Some(
+                    w.parse::<usize>()
+                        .map_err(|_| format!("Invalid number {}", s))?,
+                )
+            };
+            (w, v.trim_start_matches(['s', 'S']).to_owned())
+        }
+    };
+    let invalid = || format!("Invalid number {}", s);
+    let mut chars = value.chars();
+    let base = chars.next().ok_or_else(invalid)?.to_ascii_lowercase();
+    let digits: String = chars.collect();
+    if digits.is_empty() {
+        return Err(invalid());
+    }
+    if digits.contains(['x', 'X', 'z', 'Z']) {
+        return Err(format!("Unknown values are not supported in number {}", s));
+    }
+    let mut bits = Vec::new();
+    match base {
+        'b' | 'o' | 'h' => {
+            let nb = match base {
+                'b' => 1,
+                'o' => 3,
+                _ => 4,
+            };
+            for c in digits.chars().rev() {
+                let d = c.to_digit(1 << nb).ok_or_else(invalid)?;
+                bits.extend((0..nb).map(|i| (d >> i) & 1 != 0));
+            }
+        }
+        'd' => {
+            let mut v = digits.parse::<u64>().map_err(|_| invalid())?;
+            while v != 0 {
+                bits.push(v & 1 != 0);
+                v >>= 1;
+            }
+        }
+        _ => return Err(invalid()),
+    }
+    let width = width.unwrap_or(std::cmp::max(bits.len(), 1));
+    if width as i64 > MAX_WIDTH {
+        return Err(invalid());
+    }
+    bits.resize(width, false);
+    Ok(bits)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NetKind {
+    Input,
+    Output,
+    Wire,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Net {
+    kind: NetKind,
+    msb: i64,
+    lsb: i64,
+}
+
+impl Net {
+    fn width(&self) -> usize {
+        (self.msb - self.lsb).unsigned_abs() as usize + 1
+    }
+
+    /// Offset of a bit from the least significant bit
+    fn offset(&self, i: i64) -> Option<usize> {
+        let (lo, hi) = (self.msb.min(self.lsb), self.msb.max(self.lsb));
+        if i < lo || i > hi {
+            None
+        } else {
+            Some((i - self.lsb).unsigned_abs() as usize)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Select {
+    All,
+    Bit(i64),
+    Range(i64, i64),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Ref(String, Select),
+    Const(Vec<bool>),
+    Not(Box<Expr>),
+    LogicNot(Box<Expr>),
+    Reduce(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Mux(Box<Expr>, Box<Expr>, Box<Expr>),
+    Concat(Vec<Expr>),
+}
+
+#[derive(Debug, Clone)]
+enum Stmt {
+    Assign(Expr, Expr),
+    If(Expr, Box<Stmt>, Option<Box<Stmt>>),
+    Block(Vec<Stmt>),
+}
+
+#[derive(Debug)]
+struct Module {
+    ports: Vec<String>,
+    nets: HashMap<String, Net>,
+    net_order: Vec<String>,
+    assigns: Vec<(Expr, Expr, usize)>,
+    instances: Vec<(Vec<(String, Expr)>, usize)>,
+    always: Vec<(String, Stmt, usize)>,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map(|t| t.1)
+            .unwrap_or(0)
+    }
+
+    fn error<T>(&self, msg: &str) -> Result<T, QuaighError> {
+        Err(QuaighError::parse_at(self.line(), msg))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.0)
+    }
+
+    fn is_sym(&self, s: &str) -> bool {
+        matches!(self.peek(), Some(Token::Sym(t)) if *t == s)
+    }
+
+    fn is_keyword(&self, s: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(t)) if t == s)
+    }
+
+    fn eat_sym(&mut self, s: &str) -> bool {
+        let ret = self.is_sym(s);
+        if ret {
+            self.pos += 1;
+        }
+        ret
+    }
+
+    fn eat_keyword(&mut self, s: &str) -> bool {
+        let ret = self.is_keyword(s);
+        if ret {
+            self.pos += 1;
+        }
+        ret
+    }
+
+    fn expect_sym(&mut self, s: &str) -> Result<(), QuaighError> {
+        if self.eat_sym(s) {
+            Ok(())
+        } else {
+            self.error(&format!("expected {}", s))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, QuaighError> {
+        match self.peek() {
+            Some(Token::Ident(s)) => {
+                let s = s.clone();
+                self.pos += 1;
+                Ok(s)
+            }
+            _ => self.error("expected an identifier"),
+        }
+    }
+
+    fn integer(&mut self) -> Result<i64, QuaighError> {
+        let negative = self.eat_sym("-");
+        match self.peek() {
+            Some(Token::Number(s)) if s.chars().all(|c| c.is_ascii_digit()) => {
+                let v: i64 = s
+                    .parse()
+                    .or_else(|_| self.error(&format!("Invalid index {}", s)))?;
+                self.pos += 1;
+                Ok(if negative { -v } else { v })
+            }
+            _ => self.error("expected an integer"),
+        }
+    }
+
+    /// Optional range of a declaration
+    fn range(&mut self) -> Result<(i64, i64), QuaighError> {
+        if !self.eat_sym("[") {
+            return Ok((0, 0));
+        }
+        let msb = self.integer()?;
+        self.expect_sym(":")?;
+        let lsb = self.integer()?;
+        self.expect_sym("]")?;
+        if (msb - lsb).abs() >= MAX_WIDTH {
+            return self.error("range is too large");
+        }
+        Ok((msb, lsb))
+    }
+
+    fn declare(&mut self, m: &mut Module, name: String, net: Net) -> Result<(), QuaighError> {
+        match m.nets.get_mut(&name) {
+            None => {
+                m.net_order.push(name.clone());
+                m.nets.insert(name, net);
+                Ok(())
+            }
+            Some(prev) => {
+                if (prev.msb, prev.lsb) != (net.msb, net.lsb) {
+                    return self.error(&format!("{} is declared with different ranges", name));
+                }
+                if prev.kind != NetKind::Wire && net.kind != NetKind::Wire {
+                    return self.error(&format!("{} is declared twice", name));
+                }
+                if net.kind != NetKind::Wire {
+                    prev.kind = net.kind;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Parse the kind of a declaration, if the next token starts one
+    fn decl_kind(&mut self) -> Result<Option<NetKind>, QuaighError> {
+        let kind = if self.eat_keyword("input") {
+            NetKind::Input
+        } else if self.eat_keyword("output") {
+            NetKind::Output
+        } else if self.eat_keyword("wire") || self.eat_keyword("reg") {
+            NetKind::Wire
+        } else if self.is_keyword("inout") {
+            return self.error("inout ports are not supported");
+        } else {
+            return Ok(None);
+        };
+        // Net types after the direction
+        while self.eat_keyword("wire") || self.eat_keyword("reg") || self.eat_keyword("signed") {}
+        Ok(Some(kind))
+    }
+
+    fn module(&mut self) -> Result<Module, QuaighError> {
+        let mut m = Module {
+            ports: Vec::new(),
+            nets: HashMap::new(),
+            net_order: Vec::new(),
+            assigns: Vec::new(),
+            instances: Vec::new(),
+            always: Vec::new(),
+        };
+        if !self.eat_keyword("module") {
+            return self.error("expected a module");
+        }
+        self.ident()?;
+        if self.is_sym("#") {
+            return self.error("parameters are not supported");
+        }
+        if self.eat_sym("(") && !self.eat_sym(")") {
+            // Port list, either names only or with ANSI-style declarations
+            let mut current: Option<Net> = None;
+            loop {
+                if let Some(kind) = self.decl_kind()? {
+                    let (msb, lsb) = self.range()?;
+                    current = Some(Net { kind, msb, lsb });
+                }
+                let name = self.ident()?;
+                if let Some(net) = current {
+                    self.declare(&mut m, name.clone(), net)?;
+                }
+                m.ports.push(name);
+                if self.eat_sym(")") {
+                    break;
+                }
+                self.expect_sym(",")?;
+            }
+        }
+        self.expect_sym(";")?;
+
+        loop {
+            let line = self.line();
+            if self.eat_keyword("endmodule") {
+                break;
+            } else if let Some(kind) = self.decl_kind()? {
+                let (msb, lsb) = self.range()?;
+                loop {
+                    let name = self.ident()?;
+                    self.declare(&mut m, name, Net { kind, msb, lsb })?;
+                    if self.is_sym("=") {
+                        return self.error("initial values are not supported");
+                    }
+                    if !self.eat_sym(",") {
+                        break;
+                    }
+                }
+                self.expect_sym(";")?;
+            } else if self.eat_keyword("assign") {
+                loop {
+                    let lhs = self.expr()?;
+                    self.expect_sym("=")?;
+                    let rhs = self.expr()?;
+                    m.assigns.push((lhs, rhs, line));
+                    if !self.eat_sym(",") {
+                        break;
+                    }
+                }
+                self.expect_sym(";")?;
+            } else if self.eat_keyword("always") {
+                self.expect_sym("@")?;
+                self.expect_sym("(")?;
+                if !self.eat_keyword("posedge") && !self.eat_keyword("negedge") {
+                    return self.error("only always blocks on a clock edge are supported");
+                }
+                let clock = self.ident()?;
+                if !self.is_sym(")") {
+                    return self.error("asynchronous resets are not supported");
+                }
+                self.expect_sym(")")?;
+                let stmt = self.stmt()?;
+                m.always.push((clock, stmt, line));
+            } else if self.is_keyword("initial") || self.is_keyword("function") {
+                return self.error("initial blocks and functions are not supported");
+            } else if self.is_keyword("module") {
+                return self.error("nested modules are not supported");
+            } else if self.peek().is_none() {
+                return self.error("missing endmodule");
+            } else {
+                // Cell instance with named port connections
+                self.ident()?;
+                if self.is_sym("#") {
+                    return self.error("parameters are not supported");
+                }
+                self.ident()?;
+                self.expect_sym("(")?;
+                let mut ports = Vec::new();
+                if !self.eat_sym(")") {
+                    loop {
+                        if !self.eat_sym(".") {
+                            return self.error("only named port connections are supported");
+                        }
+                        let port = self.ident()?;
+                        self.expect_sym("(")?;
+                        if !self.is_sym(")") {
+                            ports.push((port, self.expr()?));
+                        }
+                        self.expect_sym(")")?;
+                        if self.eat_sym(")") {
+                            break;
+                        }
+                        self.expect_sym(",")?;
+                    }
+                }
+                self.expect_sym(";")?;
+                m.instances.push((ports, line));
+            }
+        }
+        Ok(m)
+    }
+
+    fn stmt(&mut self) -> Result<Stmt, QuaighError> {
+        if self.eat_keyword("begin") {
+            let mut v = Vec::new();
+            while !self.eat_keyword("end") {
+                if self.peek().is_none() {
+                    return self.error("missing end");
+                }
+                v.push(self.stmt()?);
+            }
+            Ok(Stmt::Block(v))
+        } else if self.eat_keyword("if") {
+            self.expect_sym("(")?;
+            let cond = self.expr()?;
+            self.expect_sym(")")?;
+            let a = self.stmt()?;
+            let b = if self.eat_keyword("else") {
+                Some(Box::new(self.stmt()?))
+            } else {
+                None
+            };
+            Ok(Stmt::If(cond, Box::new(a), b))
+        } else if self.eat_sym(";") {
+            Ok(Stmt::Block(Vec::new()))
+        } else {
+            let lhs = self.expr()?;
+            if self.is_sym("=") {
+                return self.error("only non-blocking assignments are supported in always blocks");
+            }
+            self.expect_sym("<=")?;
+            let rhs = self.expr()?;
+            self.expect_sym(";")?;
+            Ok(Stmt::Assign(lhs, rhs))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, QuaighError> {
+        let cond = self.binary(0)?;
+        if self.eat_sym("?") {
+            let a = self.expr()?;
+            self.expect_sym(":")?;
+            let b = self.expr()?;
+            Ok(Expr::Mux(Box::new(cond), Box::new(a), Box::new(b)))
+        } else {
+            Ok(cond)
+        }
+    }
+
+    /// Binary operators, by increasing precedence
+    fn binary(&mut self, level: usize) -> Result<Expr, QuaighError> {
+        const LEVELS: [&[&str]; 5] = [&["||"], &["&&"], &["|"], &["^", "~^", "^~"], &["&"]];
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut ret = self.binary(level + 1)?;
+        loop {
+            let Some(op) = LEVELS[level].iter().find(|op| self.is_sym(op)) else {
+                return Ok(ret);
+            };
+            self.pos += 1;
+            let rhs = self.binary(level + 1)?;
+            ret = Expr::Binary(op, Box::new(ret), Box::new(rhs));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, QuaighError> {
+        if self.eat_sym("~") {
+            Ok(Expr::Not(Box::new(self.unary()?)))
+        } else if self.eat_sym("!") {
+            Ok(Expr::LogicNot(Box::new(self.unary()?)))
+        } else if let Some(op) = ["&", "|", "^"].into_iter().find(|op| self.is_sym(op)) {
+            self.pos += 1;
+            Ok(Expr::Reduce(op, Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, QuaighError> {
+        match self.peek().cloned() {
+            Some(Token::Number(s)) => {
+                self.pos += 1;
+                let bits = parse_number(&s).or_else(|e| self.error(&e))?;
+                Ok(Expr::Const(bits))
+            }
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                if !self.eat_sym("[") {
+                    return Ok(Expr::Ref(name, Select::All));
+                }
+                let a = self.integer()?;
+                let select = if self.eat_sym(":") {
+                    Select::Range(a, self.integer()?)
+                } else {
+                    Select::Bit(a)
+                };
+                self.expect_sym("]")?;
+                Ok(Expr::Ref(name, select))
+            }
+            Some(Token::Sym("(")) => {
+                self.pos += 1;
+                let e = self.expr()?;
+                self.expect_sym(")")?;
+                Ok(e)
+            }
+            Some(Token::Sym("{")) => {
+                self.pos += 1;
+                let mut v = Vec::new();
+                loop {
+                    v.push(self.expr()?);
+                    if self.is_sym("{") {
+                        return self.error("replications are not supported");
+                    }
+                    if self.eat_sym("}") {
+                        break;
+                    }
+                    self.expect_sym(",")?;
+                }
+                Ok(Expr::Concat(v))
+            }
+            _ => self.error("expected an expression"),
+        }
+    }
+}
+
+/// Construction of the network from the parsed module
+struct Builder<'a> {
+    m: &'a Module,
+    ret: Network,
+    bits: HashMap<&'a str, Vec<Signal>>,
+    driven: HashMap<&'a str, Vec<bool>>,
+}
+
+impl<'a> Builder<'a> {
+    fn net(&self, name: &str) -> Result<&'a Net, String> {
+        self.m
+            .nets
+            .get(name)
+            .ok_or_else(|| format!("{} is not declared", name))
+    }
+
+    /// Offsets of the bits selected in a net, least significant first
+    fn offsets(&self, name: &str, select: &Select) -> Result<Vec<usize>, String> {
+        let net = self.net(name)?;
+        let bit = |i: i64| {
+            net.offset(i)
+                .ok_or_else(|| format!("Index {} is out of the range of {}", i, name))
+        };
+        match select {
+            Select::All => Ok((0..net.width()).collect()),
+            Select::Bit(i) => Ok(vec![bit(*i)?]),
+            Select::Range(a, b) => {
+                let (a, b) = (bit(*a)?, bit(*b)?);
+                if a >= b {
+                    Ok((b..=a).collect())
+                } else {
+                    Ok((a..=b).rev().collect())
+                }
+            }
+        }
+    }
+
+    /// Bits assigned by the left-hand side of an assignment, least significant first
+    fn lvalue(&self, e: &Expr) -> Result<Vec<(&'a str, usize)>, String> {
+        match e {
+            Expr::Ref(name, select) => {
+                let (name, net) = self
+                    .m
+                    .nets
+                    .get_key_value(name)
+                    .ok_or_else(|| format!("{} is not declared", name))?;
+                if net.kind == NetKind::Input {
+                    return Err(format!("Input {} cannot be assigned", name));
+                }
+                Ok(self
+                    .offsets(name, select)?
+                    .into_iter()
+                    .map(|o| (name.as_str(), o))
+                    .collect())
+            }
+            Expr::Concat(v) => {
+                let mut ret = Vec::new();
+                for e in v.iter().rev() {
+                    ret.extend(self.lvalue(e)?);
+                }
+                Ok(ret)
+            }
+            _ => Err("Invalid left-hand side of assignment".to_owned()),
+        }
+    }
+
+    /// Mark a bit as driven, and return its placeholder node
+    fn drive(&mut self, name: &str, offset: usize) -> Result<usize, String> {
+        let driven = &mut self.driven.get_mut(name).unwrap()[offset];
+        if *driven {
+            return Err(format!("{} bit {} has multiple drivers", name, offset));
+        }
+        *driven = true;
+        Ok(self.bits[name][offset].var() as usize)
+    }
+
+    fn and(&mut self, a: Signal, b: Signal) -> Signal {
+        self.ret.add_canonical(Gate::and(a, b))
+    }
+
+    fn or(&mut self, a: Signal, b: Signal) -> Signal {
+        !self.and(!a, !b)
+    }
+
+    fn reduce(&mut self, op: &str, v: &[Signal]) -> Signal {
+        match op {
+            "&" => self.ret.add_canonical(Gate::andn(v)),
+            "^" => self.ret.add_canonical(Gate::xorn(v)),
+            _ => {
+                let inv: Vec<Signal> = v.iter().map(|s| !*s).collect();
+                !self.ret.add_canonical(Gate::andn(&inv))
+            }
+        }
+    }
+
+    /// Evaluate an expression, and return its bits, least significant first
+    fn eval(&mut self, e: &Expr) -> Result<Vec<Signal>, String> {
+        match e {
+            Expr::Ref(name, select) => {
+                let offsets = self.offsets(name, select)?;
+                Ok(offsets
+                    .iter()
+                    .map(|o| self.bits[name.as_str()][*o])
+                    .collect())
+            }
+            Expr::Const(bits) => Ok(bits.iter().map(|b| Signal::from(*b)).collect()),
+            Expr::Not(a) => Ok(self.eval(a)?.into_iter().map(|s| !s).collect()),
+            Expr::LogicNot(a) => {
+                let v = self.eval(a)?;
+                Ok(vec![!self.reduce("|", &v)])
+            }
+            Expr::Reduce(op, a) => {
+                let v = self.eval(a)?;
+                Ok(vec![self.reduce(op, &v)])
+            }
+            Expr::Binary(op, a, b) => {
+                let mut va = self.eval(a)?;
+                let mut vb = self.eval(b)?;
+                if *op == "&&" || *op == "||" {
+                    let x = self.reduce("|", &va);
+                    let y = self.reduce("|", &vb);
+                    return Ok(vec![if *op == "&&" {
+                        self.and(x, y)
+                    } else {
+                        self.or(x, y)
+                    }]);
+                }
+                // Operands are zero-extended to the same width
+                let width = va.len().max(vb.len());
+                va.resize(width, Signal::zero());
+                vb.resize(width, Signal::zero());
+                let mut ret = Vec::new();
+                for (x, y) in va.into_iter().zip(vb) {
+                    ret.push(match *op {
+                        "&" => self.and(x, y),
+                        "|" => self.or(x, y),
+                        "^" => self.ret.add_canonical(Gate::xor(x, y)),
+                        _ => !self.ret.add_canonical(Gate::xor(x, y)),
+                    });
+                }
+                Ok(ret)
+            }
+            Expr::Mux(c, a, b) => {
+                let vc = self.eval(c)?;
+                let s = self.reduce("|", &vc);
+                let mut va = self.eval(a)?;
+                let mut vb = self.eval(b)?;
+                let width = va.len().max(vb.len());
+                va.resize(width, Signal::zero());
+                vb.resize(width, Signal::zero());
+                Ok(va
+                    .into_iter()
+                    .zip(vb)
+                    .map(|(x, y)| self.ret.add_canonical(Gate::mux(s, x, y)))
+                    .collect())
+            }
+            Expr::Concat(v) => {
+                let mut ret = Vec::new();
+                for e in v.iter().rev() {
+                    ret.extend(self.eval(e)?);
+                }
+                Ok(ret)
+            }
+        }
+    }
+
+    /// Evaluate an assignment, resized to the width of the left-hand side
+    fn eval_assign(
+        &mut self,
+        lhs: &Expr,
+        rhs: &Expr,
+    ) -> Result<Vec<(&'a str, usize, Signal)>, String> {
+        let bits = self.lvalue(lhs)?;
+        let mut v = self.eval(rhs)?;
+        v.resize(bits.len(), Signal::zero());
+        Ok(bits
+            .into_iter()
+            .zip(v)
+            .map(|((name, o), s)| (name, o, s))
+            .collect())
+    }
+
+    /// Compute the next state of the registers assigned by a statement
+    ///
+    /// Registers that are not assigned on a path keep their value.
+    fn exec(
+        &mut self,
+        stmt: &Stmt,
+        next: &mut HashMap<(&'a str, usize), Signal>,
+    ) -> Result<(), String> {
+        match stmt {
+            Stmt::Assign(lhs, rhs) => {
+                for (name, o, s) in self.eval_assign(lhs, rhs)? {
+                    next.insert((name, o), s);
+                }
+            }
+            Stmt::Block(v) => {
+                for s in v {
+                    self.exec(s, next)?;
+                }
+            }
+            Stmt::If(cond, a, b) => {
+                let vc = self.eval(cond)?;
+                let c = self.reduce("|", &vc);
+                let mut next_a = next.clone();
+                self.exec(a, &mut next_a)?;
+                let mut next_b = next.clone();
+                if let Some(b) = b {
+                    self.exec(b, &mut next_b)?;
+                }
+                let mut keys: Vec<(&str, usize)> =
+                    next_a.keys().chain(next_b.keys()).copied().collect();
+                keys.sort();
+                keys.dedup();
+                for k in keys {
+                    let hold = self.bits[k.0][k.1];
+                    let x = next_a.get(&k).copied().unwrap_or(hold);
+                    let y = next_b.get(&k).copied().unwrap_or(hold);
+                    let s = self.ret.add_canonical(Gate::mux(c, x, y));
+                    next.insert(k, s);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read a network from a structural Verilog netlist
+///
+/// Inputs and outputs are ordered as in the port list, each bus from its least significant bit.
+pub fn read_verilog<R: std::io::Read>(mut r: R) -> Result<Network, QuaighError> {
+    let mut text = String::new();
+    r.read_to_string(&mut text)
+        .map_err(|e| QuaighError::parse(e.to_string()))?;
+    let mut p = Parser {
+        tokens: tokenize(&text)?,
+        pos: 0,
+    };
+    let m = p.module()?;
+    if p.peek().is_some() {
+        return p.error("only a single module is supported; flatten the design first");
+    }
+
+    for name in &m.ports {
+        if !m.nets.contains_key(name) {
+            return Err(QuaighError::parse(format!("Port {} is not declared", name)));
+        }
+    }
+    for name in &m.net_order {
+        if m.nets[name].kind != NetKind::Wire && !m.ports.contains(name) {
+            return Err(QuaighError::parse(format!(
+                "{} is not in the port list",
+                name
+            )));
+        }
+    }
+
+    let mut b = Builder {
+        m: &m,
+        ret: Network::new(),
+        bits: HashMap::new(),
+        driven: HashMap::new(),
+    };
+    for name in &m.ports {
+        let net = &m.nets[name];
+        if net.kind == NetKind::Input {
+            let bits = (0..net.width()).map(|_| b.ret.add_input()).collect();
+            b.bits.insert(name, bits);
+        }
+    }
+    // Placeholder nodes for all other nets, replaced once their driver is known
+    for name in &m.net_order {
+        let net = &m.nets[name];
+        if net.kind != NetKind::Input {
+            let bits = (0..net.width())
+                .map(|_| b.ret.add(Gate::Buf(Signal::zero())))
+                .collect();
+            b.bits.insert(name, bits);
+            b.driven.insert(name, vec![false; net.width()]);
+        }
+    }
+
+    let with_line = |line: usize| move |e: String| QuaighError::parse_at(line, e);
+    for (lhs, rhs, line) in &m.assigns {
+        for (name, o, s) in b.eval_assign(lhs, rhs).map_err(with_line(*line))? {
+            let node = b.drive(name, o).map_err(with_line(*line))?;
+            b.ret.replace(node, Gate::Buf(s));
+        }
+    }
+
+    let mut clock: Option<String> = None;
+    let mut check_clock = |clk: &str, line: usize| -> Result<(), QuaighError> {
+        if m.nets.get(clk).map(|n| (n.kind, n.width())) != Some((NetKind::Input, 1)) {
+            return Err(QuaighError::parse_at(
+                line,
+                format!("clock {} is not a 1-bit primary input", clk),
+            ));
+        }
+        match &clock {
+            Some(c) if c != clk => Err(QuaighError::parse_at(
+                line,
+                format!(
+                    "clocks {} and {} are used; only a single global clock is supported",
+                    c, clk
+                ),
+            )),
+            _ => {
+                clock = Some(clk.to_owned());
+                Ok(())
+            }
+        }
+    };
+    for (ports, line) in &m.instances {
+        let mut conn: HashMap<&str, &Expr> = HashMap::new();
+        for (port, e) in ports {
+            match port.as_str() {
+                "D" | "Q" | "E" | "EN" | "R" | "RST" | "C" | "CLK" => {
+                    conn.insert(port, e);
+                }
+                _ => {
+                    return Err(QuaighError::parse_at(
+                        *line,
+                        format!(
+                            "unsupported port {}; only flip-flops with D, Q, E, R and C ports are supported",
+                            port
+                        ),
+                    ))
+                }
+            }
+        }
+        let one_bit = |b: &mut Builder, port: &str, default: Signal| -> Result<Signal, String> {
+            let Some(e) = conn.get(port) else {
+                return Ok(default);
+            };
+            match b.eval(e)?.as_slice() {
+                [s] => Ok(*s),
+                _ => Err(format!("port {} must be connected to a single bit", port)),
+            }
+        };
+        let (Some(q), Some(_)) = (conn.get("Q"), conn.get("D")) else {
+            return Err(QuaighError::parse_at(
+                *line,
+                "flip-flops must have D and Q ports",
+            ));
+        };
+        let d = one_bit(&mut b, "D", Signal::zero()).map_err(with_line(*line))?;
+        let en = one_bit(&mut b, "E", Signal::one())
+            .and_then(|s| {
+                if conn.contains_key("EN") {
+                    one_bit(&mut b, "EN", Signal::one())
+                } else {
+                    Ok(s)
+                }
+            })
+            .map_err(with_line(*line))?;
+        let res = one_bit(&mut b, "R", Signal::zero())
+            .and_then(|s| {
+                if conn.contains_key("RST") {
+                    one_bit(&mut b, "RST", Signal::zero())
+                } else {
+                    Ok(s)
+                }
+            })
+            .map_err(with_line(*line))?;
+        for port in ["C", "CLK"] {
+            if let Some(Expr::Ref(clk, Select::All)) = conn.get(port) {
+                check_clock(clk, *line)?;
+            } else if conn.contains_key(port) {
+                return Err(QuaighError::parse_at(*line, "invalid clock connection"));
+            }
+        }
+        let q = b.lvalue(q).map_err(with_line(*line))?;
+        let [(name, o)] = q.as_slice() else {
+            return Err(QuaighError::parse_at(
+                *line,
+                "port Q must be connected to a single bit",
+            ));
+        };
+        let node = b.drive(name, *o).map_err(with_line(*line))?;
+        b.ret.replace(node, Gate::dff(d, en, res));
+    }
+
+    for (clk, stmt, line) in &m.always {
+        check_clock(clk, *line)?;
+        let mut next = HashMap::new();
+        b.exec(stmt, &mut next).map_err(with_line(*line))?;
+        let mut regs: Vec<((&str, usize), Signal)> = next.into_iter().collect();
+        regs.sort();
+        for ((name, o), s) in regs {
+            let node = b.drive(name, o).map_err(with_line(*line))?;
+            b.ret
+                .replace(node, Gate::dff(s, Signal::one(), Signal::zero()));
+        }
+    }
+
+    for name in &m.ports {
+        if m.nets[name].kind == NetKind::Output {
+            for s in b.bits[name.as_str()].clone() {
+                b.ret.add_output(s);
+            }
+        }
+    }
+
+    // Undriven nets are only allowed if they are not used
+    let mut used = vec![false; b.ret.nb_nodes()];
+    for i in 0..b.ret.nb_nodes() {
+        for v in b.ret.gate(i).vars() {
+            used[v as usize] = true;
+        }
+    }
+    for o in 0..b.ret.nb_outputs() {
+        let s = b.ret.output(o);
+        if s.is_var() {
+            used[s.var() as usize] = true;
+        }
+    }
+    for name in &m.net_order {
+        let Some(driven) = b.driven.get(name.as_str()) else {
+            continue;
+        };
+        for (o, d) in driven.iter().enumerate() {
+            if !d && used[b.bits[name.as_str()][o].var() as usize] {
+                return Err(QuaighError::parse(format!(
+                    "{} bit {} is used but not driven",
+                    name, o
+                )));
+            }
+        }
+    }
+    let mut ret = b.ret;
+    ret.try_topo_sort()?;
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_number, read_verilog};
+    use crate::network::generators::adder;
+    use crate::sim::simulate;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(parse_number("1'b0"), Ok(vec![false]));
+        assert_eq!(parse_number("1'h1"), Ok(vec![true]));
+        assert_eq!(parse_number("4'b1_01"), Ok(vec![true, false, true, false]));
+        assert_eq!(parse_number("3'o5"), Ok(vec![true, false, true]));
+        assert_eq!(parse_number("6"), Ok(vec![false, true, true]));
+        assert_eq!(
+            parse_number("8'hA5").unwrap(),
+            vec![true, false, true, false, false, true, false, true]
+        );
+        assert!(parse_number("2'bx1").is_err());
+        assert!(parse_number("2'b2").is_err());
+    }
+
+    #[test]
+    fn test_adder() {
+        // Ripple-carry adder written with vectors, selects and concatenations
+        let text = "
+            // 2-bit adder
+            module add2(a, b, s);
+              input [1:0] a;
+              input [1:0] b;
+              output [2:0] s;
+              wire c0;
+              (* keep *) wire \\c1 ;
+              assign s[0] = a[0] ^ b[0];
+              assign c0 = a[0] & b[0];
+              assign s[1] = a[1] ^ b[1] ^ c0, \\c1 = (a[1] & b[1]) | (c0 & (a[1] | b[1]));
+              assign s[2:2] = { \\c1 };
+            endmodule
+        ";
+        let aig = read_verilog(text.as_bytes()).unwrap();
+        let expected = adder::ripple_carry(2);
+        assert_eq!(aig.nb_inputs(), 4);
+        assert_eq!(aig.nb_outputs(), 3);
+        for k in 0..16 {
+            // Inputs of the generator are interleaved
+            let a = [k & 1 != 0, k & 2 != 0];
+            let b = [k & 4 != 0, k & 8 != 0];
+            let pattern = vec![vec![a[0], a[1], b[0], b[1]]];
+            let gen_pattern = vec![vec![a[0], b[0], a[1], b[1]]];
+            assert_eq!(simulate(&aig, &pattern), simulate(&expected, &gen_pattern));
+        }
+    }
+
+    #[test]
+    fn test_flops() {
+        // The same registers as an instance and as an always block
+        let text = "
+            module regs(input clk, input d, input en, input rst, output q1, output q2, output [1:0] q3);
+              reg q2;
+              reg [1:0] q3;
+              \\$_SDFFE_PP0P_ ff (.C(clk), .D(d), .E(en), .R(rst), .Q(q1));
+              always @(posedge clk)
+                if (rst) q2 <= 1'b0;
+                else if (en) q2 <= d;
+              always @(posedge clk) begin
+                q3 <= {q3[0], en ? !d : q2};
+              end
+            endmodule
+        ";
+        let aig = read_verilog(text.as_bytes()).unwrap();
+        assert_eq!(aig.nb_inputs(), 4);
+        assert_eq!(aig.nb_outputs(), 4);
+
+        let mut expected = Network::new();
+        expected.add_inputs(4);
+        let [_, d, en, rst] = [0, 1, 2, 3].map(|i| expected.input(i));
+        let q1 = expected.add(Gate::dff(d, en, rst));
+        let q2 = expected.add(Gate::dff(d, en, rst));
+        let x = expected.add(Gate::mux(en, !d, q2));
+        let q30 = expected.add(Gate::dff(x, Signal::one(), Signal::zero()));
+        let q31 = expected.add(Gate::dff(q30, Signal::one(), Signal::zero()));
+        for s in [q1, q2, q30, q31] {
+            expected.add_output(s);
+        }
+        let pattern: Vec<Vec<bool>> = (0..32u64)
+            .map(|t| {
+                (0..4)
+                    .map(|i| ((t * 0x9e37 + 13) >> (i + t % 5)) & 1 != 0)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(simulate(&aig, &pattern), simulate(&expected, &pattern));
+    }
+
+    #[test]
+    fn test_errors() {
+        let check = |body: &str, msg: &str| {
+            let text = format!(
+                "module m(input a, input b, input c, output y);\n{}\nendmodule",
+                body
+            );
+            let err = read_verilog(text.as_bytes()).unwrap_err().to_string();
+            assert!(err.contains(msg), "{} does not contain {}", err, msg);
+        };
+        check("assign y = a;\nassign y = b;", "multiple drivers");
+        check("assign y = w;", "w is not declared");
+        check("wire w;\nassign y = w;", "used but not driven");
+        check("wire w;\nassign y = w & a;\nassign w = y;", "loop");
+        check("assign a = b;", "cannot be assigned");
+        check("assign y = a[2];", "out of the range");
+        check("always @(posedge a or posedge b) y <= c;", "asynchronous");
+        check(
+            "reg r;\nalways @(posedge a) r <= c;\nalways @(posedge b) y <= r;",
+            "single global clock",
+        );
+        check("always @(posedge a) y = c;", "non-blocking");
+        check("AND2 g (.A(a), .B(b), .Y(y));", "unsupported port");
+        check("assign y = 2'bx0;", "Unknown values");
+        let err = read_verilog("module m(); endmodule module n(); endmodule".as_bytes())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("single module"));
+    }
+}
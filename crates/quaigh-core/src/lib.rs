@@ -0,0 +1,29 @@
+//! Core datastructures of quaigh: logic networks, simulation and file formats
+//!
+//! This crate has no dependency on a Sat solver or any native toolchain. Most users should depend on
+//! [quaigh](https://docs.rs/quaigh), which re-exports everything.
+//!
+//! ```
+//! # use quaigh_core::{Gate, Network};
+//! use quaigh_core::sim::simulate_comb;
+//! let mut net = Network::new();
+//! let i0 = net.add_input();
+//! let i1 = net.add_input();
+//! let x = net.add(Gate::xor(i0, i1));
+//! net.add_output(x);
+//! assert_eq!(simulate_comb(&net, &vec![true, false]), vec![true]);
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod analysis;
+pub mod error;
+pub mod fuzz_targets;
+pub mod io;
+pub mod network;
+pub mod sim;
+pub mod testing;
+pub mod util;
+
+pub use error::QuaighError;
+pub use network::{Gate, Network, Signal};
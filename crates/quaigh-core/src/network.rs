@@ -0,0 +1,44 @@
+//! Representation and handling of logic networks
+
+pub mod area;
+mod buses;
+mod cone;
+pub mod depth;
+pub mod diff;
+mod fanouts;
+pub mod fingerprint;
+mod gates;
+pub mod generators;
+mod hierarchy;
+mod iter;
+mod levelize;
+mod limits;
+mod locations;
+pub mod matcher;
+mod names;
+mod network;
+mod signal;
+mod splice;
+pub mod stats;
+pub mod timing;
+mod truth_table;
+mod window;
+
+pub use buses::{parse_bit_name, Bus, BusMap};
+pub use cone::Cone;
+pub use fanouts::FanoutView;
+pub use gates::{
+    BinaryType, ClockId, Gate, GateRef, LutGate, NaryType, Normalization, TernaryType, MAX_CLOCKS,
+    TRUTH_TABLE_FORMAT_VERSION,
+};
+pub use hierarchy::{BlackBox, HierarchyMap, Instance};
+pub use iter::Gates;
+pub use levelize::Levelization;
+pub use limits::{GrowthLimitExceeded, DEFAULT_MAX_NODES};
+pub use locations::{SourceLocation, SourceMap};
+pub use names::NameTable;
+pub use network::{Network, OutputKind};
+pub use signal::Signal;
+pub use splice::SpliceError;
+pub use truth_table::MAX_TRUTH_TABLE_INPUTS;
+pub use window::Window;
@@ -1,9 +1,9 @@
 //! Compute an approximation of the area or of the complexity of a network
 //!
 //! ```
-//! # use quaigh::Network;
+//! # use quaigh_core::Network;
 //! # let aig = Network::new();
-//! use quaigh::network::area::AreaParameters;
+//! use quaigh_core::network::area::AreaParameters;
 //!
 //! // To estimate area for VLSI designs
 //! println!("VLSI cost: {}", AreaParameters::vlsi().area(&aig));
@@ -17,12 +17,13 @@
 
 use std::fmt;
 
-use crate::network::gates::{BinaryType, NaryType, TernaryType};
-use crate::{Gate, Network};
+use crate::network::gates::{BinaryType, GateRef, NaryType, TernaryType};
+use crate::Network;
 
 /// Area estimation parameters for optimization
 ///
-/// Most gates have an area cost. N-ary gates are extrapolated and buffers are ignored.
+/// Most gates have an area cost. N-ary gates are extrapolated and buffers are ignored. Luts cost as
+/// much as the most expensive gate with the same number of inputs.
 /// This is obviously very inaccurate, and is meant to be used as an objective during logic optimization.
 #[derive(Clone, Copy, Debug)]
 pub struct AreaParameters {
@@ -107,10 +108,20 @@ impl AreaParameters {
         }
     }
 
-    /// Compute the area of a gate
-    pub fn gate_area(&self, g: &Gate) -> usize {
-        use Gate::*;
-        match g {
+    /// Cost of a Lut, as the most expensive gate with the same number of inputs
+    fn lut(&self, n: usize) -> usize {
+        match n {
+            0 | 1 => 0,
+            2 => self.and.max(self.xor),
+            3 => self.and3.max(self.xor3).max(self.mux).max(self.maj),
+            _ => self.andn(n).max(self.xorn(n)),
+        }
+    }
+
+    /// Compute the area of a gate, given as a [`Gate`](crate::Gate) or a [`GateRef`]
+    pub fn gate_area<'a>(&self, g: impl Into<GateRef<'a>>) -> usize {
+        use GateRef::*;
+        match g.into() {
             Binary(_, BinaryType::And) => self.and,
             Ternary(_, TernaryType::And) => self.and3,
             Binary(_, BinaryType::Xor) => self.xor,
@@ -119,11 +130,11 @@ impl AreaParameters {
                 NaryType::And | NaryType::Or | NaryType::Nand | NaryType::Nor => self.andn(v.len()),
                 NaryType::Xor | NaryType::Xnor => self.xorn(v.len()),
             },
-            Dff(_) => self.dff,
+            Dff(..) | Latch(..) => self.dff,
             Ternary(_, TernaryType::Mux) => self.mux,
             Ternary(_, TernaryType::Maj) => self.maj,
             Buf(_) => 0,
-            Lut(_) => todo!("LUT area not modeled"),
+            Lut(v, _) => self.lut(v.len()),
         }
     }
 
@@ -131,7 +142,7 @@ impl AreaParameters {
     pub fn area(&self, a: &Network) -> usize {
         let mut ret = 0;
         for i in 0..a.nb_nodes() {
-            ret += self.gate_area(a.gate(i));
+            ret += self.gate_area(a.gate_ref(i));
         }
         ret
     }
@@ -178,6 +189,8 @@ impl fmt::Display for AreaParameters {
 #[cfg(test)]
 mod tests {
     use super::AreaParameters;
+    use crate::network::{BinaryType, Gate, TernaryType};
+    use crate::Signal;
 
     #[test]
     fn test_consistent() {
@@ -185,4 +198,25 @@ mod tests {
         AreaParameters::fpga().check();
         AreaParameters::sat().check();
     }
+
+    #[test]
+    fn test_lut_area() {
+        let params = AreaParameters::vlsi();
+        let lut = |n: usize| {
+            let inputs: Vec<Signal> = (0..n as u32).map(Signal::from_input).collect();
+            Gate::lut(&inputs, volute::Lut::nth_var(n, 0))
+        };
+        assert_eq!(params.gate_area(&lut(1)), 0);
+        assert_eq!(
+            params.gate_area(&lut(2)),
+            params.gate_area(&Gate::Binary([Signal::zero(); 2], BinaryType::Xor))
+        );
+        assert_eq!(
+            params.gate_area(&lut(3)),
+            params.gate_area(&Gate::Ternary([Signal::zero(); 3], TernaryType::Xor))
+        );
+        assert!(params.gate_area(&lut(5)) > params.gate_area(&lut(4)));
+        // In FPGAs, the cost only depends on the number of inputs
+        assert_eq!(AreaParameters::fpga().gate_area(&lut(6)), 6);
+    }
 }
@@ -0,0 +1,229 @@
+//! Grouping of single-bit signals into named multi-bit buses
+
+use crate::network::NameTable;
+use crate::Network;
+
+/// Split a bit name such as `a[3]` into the bus name and the bit index
+///
+/// ```
+/// use quaigh_core::network::parse_bit_name;
+///
+/// assert_eq!(parse_bit_name("data[12]"), Some(("data", 12)));
+/// assert_eq!(parse_bit_name("data"), None);
+/// ```
+pub fn parse_bit_name(name: &str) -> Option<(&str, usize)> {
+    let (base, index) = name.strip_suffix(']')?.rsplit_once('[')?;
+    if base.is_empty() {
+        return None;
+    }
+    Some((base, index.trim().parse().ok()?))
+}
+
+/// A named group of primary inputs or primary outputs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bus {
+    /// Name of the bus, without the bit index
+    pub name: String,
+    /// Index of the input or output for each bit, least significant bit first
+    pub bits: Vec<usize>,
+}
+
+impl Bus {
+    /// Number of bits of the bus
+    pub fn width(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Format the value of the bus, most significant bit first, as `name=0110`
+    pub fn format(&self, values: &[bool]) -> String {
+        self.format_with(values, |b| if *b { '1' } else { '0' })
+    }
+
+    /// Format the value of the bus with unknown values, written as `X`
+    pub fn format_ternary(&self, values: &[Option<bool>]) -> String {
+        self.format_with(values, |b| match b {
+            None => 'X',
+            Some(true) => '1',
+            Some(false) => '0',
+        })
+    }
+
+    fn format_with<T>(&self, values: &[T], to_char: fn(&T) -> char) -> String {
+        let mut ret = format!("{}=", self.name);
+        ret.extend(self.bits.iter().rev().map(|i| to_char(&values[*i])));
+        ret
+    }
+}
+
+/// Buses formed by the primary inputs and outputs of a network
+///
+/// As for the [`NameTable`], the network itself does not store buses: this is an annotation
+/// layer used to print values per bus rather than per bit. Bits named like `a[3]` are grouped
+/// by bus name, in the order of their first bit. Other inputs and outputs form single-bit buses.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BusMap {
+    /// Buses of the primary inputs
+    pub inputs: Vec<Bus>,
+    /// Buses of the primary outputs
+    pub outputs: Vec<Bus>,
+}
+
+impl BusMap {
+    /// Create an empty bus map
+    pub fn new() -> BusMap {
+        BusMap::default()
+    }
+
+    /// Group the inputs and outputs of a network by name
+    ///
+    /// Unnamed inputs and outputs are named `i3` or `o3` from their index.
+    pub fn from_names(aig: &Network, names: &NameTable) -> BusMap {
+        let input_names: Vec<String> = (0..aig.nb_inputs())
+            .map(|i| names.name_or_generated(aig.input(i)).0)
+            .collect();
+        let output_names: Vec<String> = (0..aig.nb_outputs())
+            .map(|i| {
+                names
+                    .output_name(i)
+                    .map(|n| n.to_owned())
+                    .unwrap_or_else(|| format!("o{}", i))
+            })
+            .collect();
+        BusMap {
+            inputs: BusMap::group(&input_names),
+            outputs: BusMap::group(&output_names),
+        }
+    }
+
+    /// Group a list of bit names into buses
+    ///
+    /// Bits of a bus are sorted by index; missing indices are skipped.
+    pub fn group(names: &[String]) -> Vec<Bus> {
+        let mut buses: Vec<Bus> = Vec::new();
+        let mut indices: Vec<Vec<usize>> = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            let (base, index) = match parse_bit_name(name) {
+                Some((base, index)) => (base, Some(index)),
+                None => (name.as_str(), None),
+            };
+            let pos = match index {
+                Some(_) => buses.iter().position(|b| b.name == base),
+                None => None,
+            };
+            let pos = pos.unwrap_or_else(|| {
+                buses.push(Bus {
+                    name: base.to_owned(),
+                    bits: Vec::new(),
+                });
+                indices.push(Vec::new());
+                buses.len() - 1
+            });
+            buses[pos].bits.push(i);
+            indices[pos].push(index.unwrap_or(0));
+        }
+        for (bus, ind) in buses.iter_mut().zip(indices) {
+            let mut bits: Vec<(usize, usize)> =
+                ind.into_iter().zip(bus.bits.iter().copied()).collect();
+            bits.sort();
+            bus.bits = bits.into_iter().map(|(_, i)| i).collect();
+        }
+        buses
+    }
+
+    /// Return whether all buses have a single bit
+    pub fn is_trivial(&self) -> bool {
+        self.inputs
+            .iter()
+            .chain(self.outputs.iter())
+            .all(|b| b.width() <= 1)
+    }
+
+    /// Format the values of the inputs, bus by bus, as `a=0110 b=1`
+    pub fn format_inputs(&self, values: &[bool]) -> String {
+        format_buses(&self.inputs, values, Bus::format)
+    }
+
+    /// Format the values of the outputs, bus by bus, as `a=0110 b=1`
+    pub fn format_outputs(&self, values: &[bool]) -> String {
+        format_buses(&self.outputs, values, Bus::format)
+    }
+
+    /// Format the values of the outputs with unknown values, bus by bus
+    pub fn format_outputs_ternary(&self, values: &[Option<bool>]) -> String {
+        format_buses(&self.outputs, values, Bus::format_ternary)
+    }
+}
+
+fn format_buses<T>(buses: &[Bus], values: &[T], format: fn(&Bus, &[T]) -> String) -> String {
+    buses
+        .iter()
+        .map(|b| format(b, values))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_bit_name, Bus, BusMap};
+    use crate::network::NameTable;
+    use crate::{Gate, Network};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_bit_name("a[0]"), Some(("a", 0)));
+        assert_eq!(parse_bit_name("a[b][3]"), Some(("a[b]", 3)));
+        assert_eq!(parse_bit_name("[3]"), None);
+        assert_eq!(parse_bit_name("a[x]"), None);
+        assert_eq!(parse_bit_name("a3"), None);
+    }
+
+    #[test]
+    fn test_group() {
+        let names: Vec<String> = ["a[1]", "clk", "a[0]", "b[2]", "a[2]", "b[3]"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let buses = BusMap::group(&names);
+        assert_eq!(
+            buses,
+            vec![
+                Bus {
+                    name: "a".to_owned(),
+                    bits: vec![2, 0, 4]
+                },
+                Bus {
+                    name: "clk".to_owned(),
+                    bits: vec![1]
+                },
+                Bus {
+                    name: "b".to_owned(),
+                    bits: vec![3, 5]
+                },
+            ]
+        );
+        let values = [true, false, false, true, true, false];
+        assert_eq!(buses[0].format(&values), "a=110");
+        assert_eq!(buses[2].format(&values), "b=01");
+    }
+
+    #[test]
+    fn test_from_names() {
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let x = aig.add(Gate::and(aig.input(0), aig.input(1)));
+        aig.add_output(x);
+        aig.add_output(!x);
+        let mut names = NameTable::new();
+        names.set_input_name(0, "d[0]");
+        names.set_input_name(1, "d[1]");
+        names.set_output_name(1, "nx");
+        let buses = BusMap::from_names(&aig, &names);
+        assert!(!buses.is_trivial());
+        assert_eq!(buses.format_inputs(&[true, false, true]), "d=01 i2=1");
+        assert_eq!(buses.format_outputs(&[false, true]), "o0=0 nx=1");
+        assert_eq!(
+            buses.format_outputs_ternary(&[None, Some(true)]),
+            "o0=X nx=1"
+        );
+    }
+}
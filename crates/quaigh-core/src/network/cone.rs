@@ -0,0 +1,147 @@
+//! Extraction of the cone of influence of some outputs
+
+use crate::network::NameTable;
+use crate::{Network, Signal};
+
+/// Correspondence between a cone extracted with [`Network::extract_cone`] and its parent network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cone {
+    /// Inputs of the parent network corresponding to the inputs of the cone
+    pub inputs: Vec<usize>,
+    /// Nodes of the parent network corresponding to the nodes of the cone
+    pub nodes: Vec<usize>,
+    /// Outputs of the parent network corresponding to the outputs of the cone
+    pub outputs: Vec<usize>,
+}
+
+impl Cone {
+    /// Names of the signals of the cone, from the names of the parent network
+    pub fn names(&self, names: &NameTable) -> NameTable {
+        let mut ret = NameTable::new();
+        for (i, old) in self.inputs.iter().enumerate() {
+            if let Some(n) = names.input_name(*old) {
+                ret.set_input_name(i, n);
+            }
+        }
+        for (i, old) in self.nodes.iter().enumerate() {
+            if let Some(n) = names.node_name(*old) {
+                ret.set_node_name(i, n);
+            }
+        }
+        for (i, old) in self.outputs.iter().enumerate() {
+            if let Some(n) = names.output_name(*old) {
+                ret.set_output_name(i, n);
+            }
+        }
+        let mut clock = 0;
+        while let Some(n) = names.clock_name(clock) {
+            ret.set_clock_name(clock, n);
+            clock += 1;
+        }
+        ret
+    }
+}
+
+impl Network {
+    /// Extract the logic feeding some outputs, as a new network
+    ///
+    /// The new network has one output per selected output, in the same order, and only the inputs
+    /// and gates in their transitive fanin, including through flip-flops. Inputs and gates keep
+    /// their relative order, and outputs keep their kind.
+    pub fn cone_of(&self, outputs: &[usize]) -> Network {
+        self.extract_cone(outputs).0
+    }
+
+    /// Extract the logic feeding some outputs, as a new network; return the new network and its
+    /// correspondence with this one
+    ///
+    /// See [`Network::cone_of`].
+    pub fn extract_cone(&self, outputs: &[usize]) -> (Network, Cone) {
+        let mut used_inputs = vec![false; self.nb_inputs()];
+        let mut used_nodes = vec![false; self.nb_nodes()];
+        let mut to_visit: Vec<Signal> = outputs.iter().map(|o| self.output(*o)).collect();
+        while let Some(s) = to_visit.pop() {
+            if s.is_input() {
+                used_inputs[s.input() as usize] = true;
+            } else if s.is_var() && !used_nodes[s.var() as usize] {
+                used_nodes[s.var() as usize] = true;
+                to_visit.extend(self.dependencies(s.var() as usize));
+            }
+        }
+
+        let cone = Cone {
+            inputs: (0..self.nb_inputs()).filter(|i| used_inputs[*i]).collect(),
+            nodes: (0..self.nb_nodes()).filter(|i| used_nodes[*i]).collect(),
+            outputs: outputs.to_vec(),
+        };
+        let mut input_map = vec![Signal::zero(); self.nb_inputs()];
+        let mut node_map = vec![Signal::zero(); self.nb_nodes()];
+        let mut ret = Network::new();
+        for i in &cone.inputs {
+            input_map[*i] = ret.add_input();
+        }
+        for (new, old) in cone.nodes.iter().enumerate() {
+            node_map[*old] = Signal::from_var(new as u32);
+        }
+        let t = |s: &Signal| {
+            if s.is_input() {
+                input_map[s.input() as usize] ^ s.is_inverted()
+            } else {
+                s.remap_order(&node_map)
+            }
+        };
+        for i in &cone.nodes {
+            ret.add(self.gate(*i).remap(t));
+        }
+        for o in outputs {
+            ret.add_output(t(&self.output(*o)));
+            ret.set_output_kind(ret.nb_outputs() - 1, self.output_kind(*o));
+        }
+        (ret, cone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::network::NameTable;
+    use crate::{Network, Signal};
+
+    #[test]
+    fn test_cone() {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let x0 = aig.and(i0, i1);
+        let x1 = aig.xor(i1, !i2);
+        let x2 = aig.dff(x1, Signal::one(), Signal::zero());
+        let x3 = aig.and(x2, !i2);
+        aig.add_output(x0);
+        aig.add_output(!x3);
+        aig.add_output(Signal::one());
+
+        let (cone, mapping) = aig.extract_cone(&[1, 2]);
+        assert_eq!(mapping.inputs, vec![1, 2]);
+        assert_eq!(mapping.nodes, vec![1, 2, 3]);
+        assert_eq!(cone.nb_inputs(), 2);
+        assert_eq!(cone.nb_nodes(), 3);
+        assert_eq!(cone.nb_outputs(), 2);
+        assert_eq!(cone.output(0), !cone.node(2));
+        assert_eq!(cone.output(1), Signal::one());
+        assert!(cone.dependencies(0).iter().all(|s| s.is_input()));
+        assert!(cone.is_topo_sorted());
+
+        let mut names = NameTable::new();
+        names.set_input_name(2, "c");
+        names.set_node_name(3, "y");
+        names.set_output_name(1, "out");
+        let names = mapping.names(&names);
+        assert_eq!(names.input_name(1), Some("c"));
+        assert_eq!(names.node_name(2), Some("y"));
+        assert_eq!(names.output_name(0), Some("out"));
+
+        let cone = aig.cone_of(&[0]);
+        assert_eq!(cone.nb_inputs(), 2);
+        assert_eq!(cone.nb_nodes(), 1);
+    }
+}
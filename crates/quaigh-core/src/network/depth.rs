@@ -0,0 +1,245 @@
+//! Compute the logic depth of a network, and its critical path
+//!
+//! ```
+//! # use quaigh_core::network::generators::adder;
+//! use quaigh_core::network::depth::{depth, DelayParameters};
+//!
+//! let aig = adder::ripple_carry(4);
+//!
+//! // Number of gates on the longest path
+//! assert_eq!(depth(&aig), 4);
+//!
+//! // Longest path with per-gate delays
+//! let path = DelayParameters::vlsi().critical_path(&aig).unwrap();
+//! println!("{}", path);
+//! ```
+
+use std::fmt;
+
+use crate::network::gates::{BinaryType, NaryType, TernaryType};
+use crate::{Gate, Network, Signal};
+
+/// Delay parameters for timing analysis
+///
+/// Each combinatorial gate has a delay depending on its type; n-ary gates are given the delay of
+/// the corresponding 3-input gate. Buffers have no delay, and flip-flops start new paths.
+/// As for [`AreaParameters`](super::area::AreaParameters), this is a very rough model, meant to
+/// compare networks during logic optimization.
+#[derive(Clone, Copy, Debug)]
+pub struct DelayParameters {
+    /// Delay of And2
+    pub and: usize,
+    /// Delay of And3
+    pub and3: usize,
+    /// Delay of Xor2
+    pub xor: usize,
+    /// Delay of Xor3
+    pub xor3: usize,
+    /// Delay of Mux
+    pub mux: usize,
+    /// Delay of Maj
+    pub maj: usize,
+    /// Delay of Lut
+    pub lut: usize,
+}
+
+impl DelayParameters {
+    /// Unit delay for all gates, so that the arrival time is the number of gates on a path
+    pub fn unit() -> DelayParameters {
+        DelayParameters {
+            and: 1,
+            and3: 1,
+            xor: 1,
+            xor3: 1,
+            mux: 1,
+            maj: 1,
+            lut: 1,
+        }
+    }
+
+    /// Good default parameters for VLSI design
+    ///
+    /// Xor and Mux are roughly twice as slow as And gates in a standard cell library.
+    pub fn vlsi() -> DelayParameters {
+        DelayParameters {
+            and: 2,
+            and3: 3,
+            xor: 4,
+            xor3: 7,
+            mux: 4,
+            maj: 4,
+            lut: 4,
+        }
+    }
+
+    /// Compute the delay of a gate
+    pub fn gate_delay(&self, g: &Gate) -> usize {
+        use Gate::*;
+        match g {
+            Binary(_, BinaryType::And) => self.and,
+            Ternary(_, TernaryType::And) => self.and3,
+            Binary(_, BinaryType::Xor) => self.xor,
+            Ternary(_, TernaryType::Xor) => self.xor3,
+            Ternary(_, TernaryType::Mux) | Latch(_) => self.mux,
+            Ternary(_, TernaryType::Maj) => self.maj,
+            Nary(v, tp) => match (v.len(), tp) {
+                (0 | 1, _) => 0,
+                (2, NaryType::Xor | NaryType::Xnor) => self.xor,
+                (_, NaryType::Xor | NaryType::Xnor) => self.xor3,
+                (2, _) => self.and,
+                (_, _) => self.and3,
+            },
+            Lut(_) => self.lut,
+            Dff(..) | Buf(_) => 0,
+        }
+    }
+
+    /// Compute the delay of each node, for example for [`simulate_timing`](crate::sim::simulate_timing)
+    pub fn gate_delays(&self, aig: &Network) -> Vec<usize> {
+        (0..aig.nb_nodes())
+            .map(|i| self.gate_delay(&aig.gate(i)))
+            .collect()
+    }
+
+    /// Compute the arrival time of each node
+    ///
+    /// Inputs and flip-flops arrive at time zero.
+    pub fn arrival_times(&self, aig: &Network) -> Vec<usize> {
+        assert!(aig.is_topo_sorted());
+        let mut arrival = vec![0; aig.nb_nodes()];
+        for i in 0..aig.nb_nodes() {
+            let g = aig.gate(i);
+            if !g.is_comb() {
+                continue;
+            }
+            let max_input = g.vars().map(|v| arrival[v as usize]).max().unwrap_or(0);
+            arrival[i] = max_input + self.gate_delay(&g);
+        }
+        arrival
+    }
+
+    /// Compute the largest arrival time of the outputs
+    pub fn depth(&self, aig: &Network) -> usize {
+        self.critical_path(aig).map(|p| p.delay).unwrap_or(0)
+    }
+
+    /// Find the path with the largest delay, from an input or flip-flop to an output
+    ///
+    /// Ties are broken towards the first output and the first gate input.
+    /// Returns None if the network has no output.
+    pub fn critical_path(&self, aig: &Network) -> Option<CriticalPath> {
+        let arrival = self.arrival_times(aig);
+        let time = |s: &Signal| {
+            if s.is_var() {
+                arrival[s.var() as usize]
+            } else {
+                0
+            }
+        };
+        let output = (0..aig.nb_outputs())
+            .rev()
+            .max_by_key(|o| time(&aig.output(*o)))?;
+        let mut s = aig.output(output).without_inversion();
+        let mut path = vec![s];
+        while s.is_var() && aig.gate_ref(s.var() as usize).is_comb() {
+            let Some(prev) = aig
+                .dependencies(s.var() as usize)
+                .iter()
+                .rev()
+                .filter(|d| !d.is_constant())
+                .max_by_key(|d| time(d))
+            else {
+                break;
+            };
+            s = prev.without_inversion();
+            path.push(s);
+        }
+        path.reverse();
+        Some(CriticalPath {
+            output,
+            delay: time(&aig.output(output)),
+            path,
+        })
+    }
+}
+
+/// Longest path of a network, as returned by [`DelayParameters::critical_path`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CriticalPath {
+    /// Output at the end of the path
+    pub output: usize,
+    /// Arrival time of the output
+    pub delay: usize,
+    /// Signals on the path, starting from an input, a flip-flop or a constant gate
+    pub path: Vec<Signal>,
+}
+
+impl fmt::Display for CriticalPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Critical path (delay {}): ", self.delay)?;
+        for s in &self.path {
+            write!(f, "{} -> ", s)?;
+        }
+        write!(f, "o{}", self.output)
+    }
+}
+
+/// Compute the logic level of each node
+///
+/// Each combinatorial gate adds one level, except buffers. Inputs and flip-flops are at level zero.
+/// With unit delays, this is also the arrival time of each node.
+pub fn levels(aig: &Network) -> Vec<usize> {
+    DelayParameters::unit().arrival_times(aig)
+}
+
+/// Compute the logic depth of the network
+///
+/// Each combinatorial gate adds one level, except buffers. Inputs and flip-flops are at depth zero.
+pub fn depth(aig: &Network) -> usize {
+    DelayParameters::unit().depth(aig)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{depth, levels, DelayParameters};
+    use crate::network::generators::adder;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_depth() {
+        assert_eq!(depth(&adder::ripple_carry(1)), 1);
+        assert_eq!(depth(&adder::ripple_carry(4)), 4);
+    }
+
+    #[test]
+    fn test_critical_path() {
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        let x = aig.add(Gate::xor(a, b));
+        let y = aig.add(Gate::and(a, c));
+        let z = aig.add(Gate::and(!y, c));
+        let w = aig.add(Gate::Buf(!x));
+        let ff = aig.dff(z, Signal::one(), Signal::zero());
+        aig.add_output(w);
+        aig.add_output(z);
+        aig.add_output(ff);
+
+        assert_eq!(levels(&aig), vec![1, 1, 2, 1, 0]);
+        let path = DelayParameters::unit().critical_path(&aig).unwrap();
+        assert_eq!(path.output, 1);
+        assert_eq!(path.delay, 2);
+        assert_eq!(path.path, vec![a, y, z]);
+        assert_eq!(
+            path.to_string(),
+            "Critical path (delay 2): i0 -> x1 -> x2 -> o1"
+        );
+
+        // With Xor twice as slow as And, the path through the buffer is critical
+        let path = DelayParameters::vlsi().critical_path(&aig).unwrap();
+        assert_eq!(path.output, 0);
+        assert_eq!(path.delay, 4);
+        assert_eq!(path.path, vec![a, x, w]);
+        assert_eq!(DelayParameters::vlsi().arrival_times(&aig)[2], 4);
+    }
+}
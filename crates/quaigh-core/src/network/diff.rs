@@ -0,0 +1,386 @@
+//! Structural comparison of two networks
+//!
+//! Reports what changed between two versions of a design, for example before and after an
+//! optimization: the number of gates of each type, the outputs whose cone has a different
+//! structure, and the outputs that depend on different inputs. Inputs and outputs are matched by
+//! index, as for equivalence checking.
+//!
+//! ```
+//! # use quaigh_core::network::generators::adder;
+//! use quaigh_core::network::diff::NetworkDiff;
+//!
+//! let a = adder::ripple_carry(4);
+//! let b = adder::carry_lookahead(4);
+//! let diff = NetworkDiff::compute(&a, &b);
+//! assert!(diff.support_changes.is_empty());
+//! println!("{}", diff);
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::network::fingerprint::{gate_tag, StableHasher};
+use crate::network::gates::{BinaryType, TernaryType};
+use crate::util::format::count;
+use crate::{Gate, Network, Signal};
+
+/// Maximum number of rounds to propagate the structure of the cones through flip-flops
+const MAX_ROUNDS: usize = 64;
+
+/// Change of the inputs an output depends on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SupportChange {
+    /// Index of the output
+    pub output: usize,
+    /// Inputs in the support of the second network only
+    pub added: Vec<usize>,
+    /// Inputs in the support of the first network only
+    pub removed: Vec<usize>,
+}
+
+/// Structural differences between two networks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NetworkDiff {
+    /// Number of inputs of each network
+    pub nb_inputs: (usize, usize),
+    /// Number of outputs of each network
+    pub nb_outputs: (usize, usize),
+    /// Number of gates of each type in each network, for the types whose count changed
+    pub gate_counts: Vec<(String, usize, usize)>,
+    /// Outputs present in both networks whose cone has a different structure
+    pub changed_cones: Vec<usize>,
+    /// Outputs present in both networks whose support changed
+    pub support_changes: Vec<SupportChange>,
+}
+
+/// Name of the type of a gate, including its number of inputs for variable-size gates
+fn gate_type(g: &Gate) -> Option<String> {
+    use Gate::*;
+    let name = match g {
+        Binary(_, BinaryType::And) => "And2".to_owned(),
+        Binary(_, BinaryType::Xor) => "Xor2".to_owned(),
+        Ternary(_, TernaryType::And) => "And3".to_owned(),
+        Ternary(_, TernaryType::Xor) => "Xor3".to_owned(),
+        Ternary(_, TernaryType::Mux) => "Mux".to_owned(),
+        Ternary(_, TernaryType::Maj) => "Maj".to_owned(),
+        Nary(v, tp) => format!("{:?}{}", tp, v.len()),
+        // Do not count buffered constants that may be created for I/O
+        Buf(s) if s.is_constant() => return None,
+        Buf(s) if s.is_inverted() => "Not".to_owned(),
+        Buf(_) => "Buf".to_owned(),
+        Dff(..) => "Dff".to_owned(),
+        Latch(_) => "Latch".to_owned(),
+        Lut(lut) => format!("Lut{}", lut.inputs.len()),
+    };
+    Some(name)
+}
+
+/// Number of gates of each type
+fn gate_counts(aig: &Network) -> BTreeMap<String, usize> {
+    let mut ret = BTreeMap::new();
+    for (_, g) in aig.iter_gates() {
+        if let Some(t) = gate_type(&g) {
+            *ret.entry(t).or_insert(0) += 1;
+        }
+    }
+    ret
+}
+
+/// Whether the order of the inputs of a gate does not matter
+fn is_commutative(g: &Gate) -> bool {
+    match g {
+        Gate::Binary(..) | Gate::Nary(..) => true,
+        Gate::Ternary(_, tp) => *tp != TernaryType::Mux,
+        _ => false,
+    }
+}
+
+/// Structural hash of a signal, given the hashes of the nodes, and whether it is inverted
+type SignalHash = (u64, bool);
+
+/// Structural hash of a signal, given the hashes of the nodes
+fn signal_hash(hashes: &[SignalHash], s: &Signal) -> SignalHash {
+    let (base, inv) = if s.is_var() {
+        hashes[s.var() as usize]
+    } else {
+        let mut h = StableHasher::new();
+        if s.is_input() {
+            h.write(&[1]);
+            h.write_u32(s.input());
+        } else {
+            h.write(&[0]);
+        }
+        (h.finish(), false)
+    };
+    (base, inv ^ s.is_inverted())
+}
+
+/// Hash of the structure of each node, following flip-flops one more cycle back than the
+/// previous hashes
+///
+/// The hash only depends on the structure of the cone of the node, not on the indices of its
+/// gates, and buffers are transparent. Starting from zero, flip-flops are leaves in the first
+/// round.
+fn hash_round(aig: &Network, prev: &[SignalHash]) -> Vec<SignalHash> {
+    let mut hashes = prev.to_vec();
+    for i in 0..aig.nb_nodes() {
+        let g = aig.gate(i);
+        let seen = if g.is_comb() { &hashes } else { prev };
+        let hash = match &g {
+            Gate::Buf(s) => signal_hash(seen, s),
+            _ => {
+                let mut h = StableHasher::new();
+                h.write(&[gate_tag(&g)]);
+                if let Gate::Lut(lut) = &g {
+                    for w in lut.truth_table_words() {
+                        h.write_u64(w);
+                    }
+                }
+                let mut deps: Vec<SignalHash> = g
+                    .dependencies()
+                    .iter()
+                    .map(|s| signal_hash(seen, s))
+                    .collect();
+                if is_commutative(&g) {
+                    deps.sort();
+                }
+                for (base, inv) in deps {
+                    h.write_u64(base);
+                    h.write(&[inv as u8]);
+                }
+                (h.finish(), false)
+            }
+        };
+        hashes[i] = hash;
+    }
+    hashes
+}
+
+/// Number of distinct values
+fn nb_classes(hashes: &[SignalHash]) -> usize {
+    let mut v = hashes.to_vec();
+    v.sort();
+    v.dedup();
+    v.len()
+}
+
+/// Structural hashes of the nodes of two networks
+///
+/// Rounds are added until the hashes do not separate more nodes in either network. Both
+/// networks use the same number of rounds, so that their hashes are comparable.
+fn structural_hashes(a: &Network, b: &Network) -> (Vec<SignalHash>, Vec<SignalHash>) {
+    let mut ha = hash_round(a, &vec![(0, false); a.nb_nodes()]);
+    let mut hb = hash_round(b, &vec![(0, false); b.nb_nodes()]);
+    for _ in 1..MAX_ROUNDS {
+        let na = hash_round(a, &ha);
+        let nb = hash_round(b, &hb);
+        let converged = nb_classes(&na) == nb_classes(&ha) && nb_classes(&nb) == nb_classes(&hb);
+        ha = na;
+        hb = nb;
+        if converged {
+            break;
+        }
+    }
+    (ha, hb)
+}
+
+/// Inputs each node depends on, directly or through flip-flops, as bitsets
+fn supports(aig: &Network) -> Vec<Vec<u64>> {
+    let nb_words = aig.nb_inputs().div_ceil(64);
+    let mut ret = vec![vec![0u64; nb_words]; aig.nb_nodes()];
+    // Iterate until the support of flip-flops, which may depend on later nodes, is stable
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 0..aig.nb_nodes() {
+            let mut sup = ret[i].clone();
+            for s in aig.dependencies(i) {
+                if s.is_input() {
+                    let j = s.input() as usize;
+                    sup[j / 64] |= 1 << (j % 64);
+                } else if s.is_var() {
+                    for (w, d) in sup.iter_mut().zip(&ret[s.var() as usize]) {
+                        *w |= d;
+                    }
+                }
+            }
+            if sup != ret[i] {
+                ret[i] = sup;
+                changed = true;
+            }
+        }
+    }
+    ret
+}
+
+/// Inputs an output depends on
+fn output_support(aig: &Network, supports: &[Vec<u64>], o: usize) -> Vec<usize> {
+    let s = aig.output(o);
+    if s.is_input() {
+        vec![s.input() as usize]
+    } else if s.is_var() {
+        let sup = &supports[s.var() as usize];
+        (0..aig.nb_inputs())
+            .filter(|j| sup[j / 64] >> (j % 64) & 1 != 0)
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+impl NetworkDiff {
+    /// Compare two networks
+    pub fn compute(a: &Network, b: &Network) -> NetworkDiff {
+        let counts_a = gate_counts(a);
+        let counts_b = gate_counts(b);
+        let mut types: Vec<&String> = counts_a.keys().chain(counts_b.keys()).collect();
+        types.sort();
+        types.dedup();
+        let gate_counts = types
+            .into_iter()
+            .map(|t| {
+                let na = counts_a.get(t).copied().unwrap_or(0);
+                let nb = counts_b.get(t).copied().unwrap_or(0);
+                (t.clone(), na, nb)
+            })
+            .filter(|(_, na, nb)| na != nb)
+            .collect();
+
+        let (hashes_a, hashes_b) = structural_hashes(a, b);
+        let supports_a = supports(a);
+        let supports_b = supports(b);
+        let mut changed_cones = Vec::new();
+        let mut support_changes = Vec::new();
+        for o in 0..a.nb_outputs().min(b.nb_outputs()) {
+            if signal_hash(&hashes_a, &a.output(o)) != signal_hash(&hashes_b, &b.output(o)) {
+                changed_cones.push(o);
+            }
+            let sa = output_support(a, &supports_a, o);
+            let sb = output_support(b, &supports_b, o);
+            if sa != sb {
+                support_changes.push(SupportChange {
+                    output: o,
+                    added: sb.iter().filter(|i| !sa.contains(i)).copied().collect(),
+                    removed: sa.iter().filter(|i| !sb.contains(i)).copied().collect(),
+                });
+            }
+        }
+        NetworkDiff {
+            nb_inputs: (a.nb_inputs(), b.nb_inputs()),
+            nb_outputs: (a.nb_outputs(), b.nb_outputs()),
+            gate_counts,
+            changed_cones,
+            support_changes,
+        }
+    }
+
+    /// Whether the two networks have the same structure
+    pub fn is_empty(&self) -> bool {
+        self.nb_inputs.0 == self.nb_inputs.1
+            && self.nb_outputs.0 == self.nb_outputs.1
+            && self.gate_counts.is_empty()
+            && self.changed_cones.is_empty()
+            && self.support_changes.is_empty()
+    }
+}
+
+/// Signed difference between two counts
+fn delta(a: usize, b: usize) -> String {
+    if b >= a {
+        format!("+{}", count(b - a))
+    } else {
+        format!("-{}", count(a - b))
+    }
+}
+
+impl fmt::Display for NetworkDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Diff:")?;
+        let (ia, ib) = self.nb_inputs;
+        let (oa, ob) = self.nb_outputs;
+        writeln!(f, "  Inputs: {} -> {}", count(ia), count(ib))?;
+        writeln!(f, "  Outputs: {} -> {}", count(oa), count(ob))?;
+        for (t, na, nb) in &self.gate_counts {
+            writeln!(
+                f,
+                "  {}: {} -> {} ({})",
+                t,
+                count(*na),
+                count(*nb),
+                delta(*na, *nb)
+            )?;
+        }
+        writeln!(
+            f,
+            "  Changed cones: {} of {}",
+            count(self.changed_cones.len()),
+            count(oa.min(ob))
+        )?;
+        writeln!(
+            f,
+            "  Changed supports: {}",
+            count(self.support_changes.len())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NetworkDiff, SupportChange};
+    use crate::network::generators::adder;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_identical() {
+        let a = adder::ripple_carry(4);
+        let mut b = a.clone();
+        b.shuffle(3);
+        let diff = NetworkDiff::compute(&a, &b);
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn test_changes() {
+        let mut a = Network::new();
+        a.add_inputs(3);
+        let x = a.add(Gate::and(a.input(0), a.input(1)));
+        let q = a.add(Gate::dff(x, Signal::one(), Signal::zero()));
+        a.add_output(x);
+        a.add_output(q);
+        a.add_output(a.input(2));
+
+        // Same structure with an additional buffer and different gate indices
+        let mut b = Network::new();
+        b.add_inputs(3);
+        let buf = b.add(Gate::Buf(b.input(1)));
+        let x = b.add(Gate::and(b.input(0), buf));
+        let q = b.add(Gate::dff(x, Signal::one(), Signal::zero()));
+        b.add_output(x);
+        b.add_output(q);
+        b.add_output(b.input(2));
+        let diff = NetworkDiff::compute(&a, &b);
+        assert!(diff.changed_cones.is_empty());
+        assert_eq!(diff.gate_counts, vec![("Buf".to_owned(), 0, 1)]);
+
+        // The logic before the flip-flop changes, and its support too
+        let mut c = Network::new();
+        c.add_inputs(3);
+        let x = c.add(Gate::and(c.input(0), c.input(1)));
+        let y = c.add(Gate::xor(c.input(0), c.input(2)));
+        let q = c.add(Gate::dff(y, Signal::one(), Signal::zero()));
+        c.add_output(x);
+        c.add_output(q);
+        c.add_output(c.input(2));
+        let diff = NetworkDiff::compute(&a, &c);
+        assert_eq!(diff.changed_cones, vec![1]);
+        assert_eq!(
+            diff.support_changes,
+            vec![SupportChange {
+                output: 1,
+                added: vec![2],
+                removed: vec![1],
+            }]
+        );
+        assert_eq!(diff.gate_counts, vec![("Xor2".to_owned(), 0, 1)]);
+    }
+}
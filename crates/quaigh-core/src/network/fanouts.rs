@@ -0,0 +1,147 @@
+//! Fanouts of the signals of a network, maintained across modifications
+
+use crate::{Gate, Network, Signal};
+
+/// Nodes using each input and node of a network
+///
+/// The network does not store its fanouts: this side structure is built once, and must be kept
+/// up-to-date by adding and replacing gates through it, so that passes can query fanouts without
+/// rebuilding them. Fanouts are sorted by node index, and a node using a signal several times
+/// appears once.
+///
+/// ```
+/// # use quaigh_core::{Gate, Network, Signal};
+/// use quaigh_core::network::FanoutView;
+///
+/// let mut aig = Network::new();
+/// let a = aig.add_input();
+/// let b = aig.add_input();
+/// let x = aig.add(Gate::and(a, b));
+/// let mut fanouts = FanoutView::new(&aig);
+/// assert_eq!(fanouts.fanouts(a), &[0]);
+/// fanouts.replace(&mut aig, 0, Gate::and(!b, b));
+/// assert!(fanouts.fanouts(a).is_empty());
+/// fanouts.add(&mut aig, Gate::xor(x, a));
+/// assert_eq!(fanouts.fanouts(a), &[1]);
+/// assert_eq!(fanouts.fanouts(b), &[0]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FanoutView {
+    input_fanouts: Vec<Vec<usize>>,
+    node_fanouts: Vec<Vec<usize>>,
+}
+
+impl FanoutView {
+    /// Compute the fanouts of all signals of a network
+    pub fn new(aig: &Network) -> FanoutView {
+        let mut ret = FanoutView::default();
+        ret.add_new_nodes(aig);
+        ret
+    }
+
+    /// Nodes using a signal; inversions are ignored
+    pub fn fanouts(&self, s: Signal) -> &[usize] {
+        let v = if s.is_input() {
+            self.input_fanouts.get(s.input() as usize)
+        } else if s.is_var() {
+            self.node_fanouts.get(s.var() as usize)
+        } else {
+            None
+        };
+        v.map(|v| v.as_slice()).unwrap_or(&[])
+    }
+
+    /// Number of nodes using a signal
+    pub fn nb_fanouts(&self, s: Signal) -> usize {
+        self.fanouts(s).len()
+    }
+
+    /// Take into account the inputs and nodes added to the network since the last update
+    pub fn add_new_nodes(&mut self, aig: &Network) {
+        self.input_fanouts.resize(aig.nb_inputs(), Vec::new());
+        let first = self.node_fanouts.len();
+        self.node_fanouts.resize(aig.nb_nodes(), Vec::new());
+        for i in first..aig.nb_nodes() {
+            self.link(&aig.gate(i), i);
+        }
+    }
+
+    /// Add a gate to the network, and update the fanouts
+    pub fn add(&mut self, aig: &mut Network, gate: Gate) -> Signal {
+        let s = aig.add(gate);
+        self.add_new_nodes(aig);
+        s
+    }
+
+    /// Replace a gate of the network, and update the fanouts
+    pub fn replace(&mut self, aig: &mut Network, i: usize, gate: Gate) -> Signal {
+        self.add_new_nodes(aig);
+        self.unlink(&aig.gate(i).clone(), i);
+        self.link(&gate, i);
+        aig.replace(i, gate)
+    }
+
+    /// Fanout list of a signal, if it can have fanouts
+    fn list_mut(&mut self, s: Signal) -> Option<&mut Vec<usize>> {
+        if s.is_input() {
+            self.input_fanouts.get_mut(s.input() as usize)
+        } else if s.is_var() {
+            self.node_fanouts.get_mut(s.var() as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Add a node to the fanouts of its dependencies
+    fn link(&mut self, g: &Gate, n: usize) {
+        for s in g.dependencies() {
+            if let Some(v) = self.list_mut(*s) {
+                if let Err(pos) = v.binary_search(&n) {
+                    v.insert(pos, n);
+                }
+            }
+        }
+    }
+
+    /// Remove a node from the fanouts of its dependencies
+    fn unlink(&mut self, g: &Gate, n: usize) {
+        for s in g.dependencies() {
+            if let Some(v) = self.list_mut(*s) {
+                if let Ok(pos) = v.binary_search(&n) {
+                    v.remove(pos);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FanoutView;
+    use crate::network::generators::adder;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_incremental() {
+        let mut aig = adder::ripple_carry(4);
+        let mut view = FanoutView::new(&aig);
+        let a = aig.input(0);
+        let nb = view.nb_fanouts(a);
+        assert!(nb > 0);
+
+        // Replacing and adding gates gives the same result as rebuilding the view
+        let x = view.add(&mut aig, Gate::and(a, !a));
+        assert_eq!(view.fanouts(a).last(), Some(&(x.var() as usize)));
+        assert_eq!(view.nb_fanouts(a), nb + 1);
+        view.replace(&mut aig, 0, Gate::Buf(x));
+        assert_eq!(view.fanouts(x), &[0]);
+        aig.add_input();
+        let y = aig.add(Gate::xor(x, aig.input(8)));
+        view.add_new_nodes(&aig);
+        assert_eq!(view.fanouts(aig.input(8)), &[y.var() as usize]);
+        assert_eq!(view, FanoutView::new(&aig));
+
+        assert!(view.fanouts(Signal::one()).is_empty());
+        assert_eq!(FanoutView::new(&Network::new()).nb_fanouts(a), 0);
+    }
+}
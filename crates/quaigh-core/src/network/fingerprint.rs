@@ -0,0 +1,191 @@
+//! Stable fingerprints of logic networks
+//!
+//! The fingerprint only depends on the canonical form of the network, and is stable across
+//! platforms and versions of the compiler, so it can be stored in files. Lut gates are hashed
+//! through the values of their truth table, independently of the version of volute.
+//!
+//! ```
+//! # use quaigh_core::Network;
+//! use quaigh_core::network::fingerprint::fingerprint;
+//! let mut a = Network::new();
+//! let i0 = a.add_input();
+//! let i1 = a.add_input();
+//! let x = a.and(i0, i1);
+//! a.add_output(x);
+//! let b = a.clone();
+//! assert_eq!(fingerprint(&a), fingerprint(&b));
+//! ```
+
+use crate::{Gate, Network, Signal};
+
+/// Simple 64-bit FNV-1a hasher, whose result does not depend on the platform
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct StableHasher {
+    state: u64,
+}
+
+impl StableHasher {
+    /// Create a new hasher
+    pub fn new() -> StableHasher {
+        StableHasher {
+            state: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+
+    /// Hash a sequence of bytes
+    pub fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.state ^= *b as u64;
+            self.state = self.state.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    /// Hash a 32-bit integer
+    pub fn write_u32(&mut self, v: u32) {
+        self.write(&v.to_le_bytes());
+    }
+
+    /// Hash a 64-bit integer
+    pub fn write_u64(&mut self, v: u64) {
+        self.write(&v.to_le_bytes());
+    }
+
+    /// Hash a signal
+    pub fn write_signal(&mut self, s: Signal) {
+        self.write_u32(s.raw());
+    }
+
+    /// Return the hash value
+    pub fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+/// Tag identifying the type of a gate in hashes
+pub(crate) fn gate_tag(g: &Gate) -> u8 {
+    use crate::network::gates::{BinaryType, NaryType, TernaryType};
+    use Gate::*;
+    match g {
+        Binary(_, BinaryType::And) => 0,
+        Binary(_, BinaryType::Xor) => 1,
+        Ternary(_, TernaryType::And) => 2,
+        Ternary(_, TernaryType::Xor) => 3,
+        Ternary(_, TernaryType::Mux) => 4,
+        Ternary(_, TernaryType::Maj) => 5,
+        Nary(_, NaryType::And) => 6,
+        Nary(_, NaryType::Or) => 7,
+        Nary(_, NaryType::Nand) => 8,
+        Nary(_, NaryType::Nor) => 9,
+        Nary(_, NaryType::Xor) => 10,
+        Nary(_, NaryType::Xnor) => 11,
+        Buf(_) => 12,
+        Dff(..) => 13,
+        Lut(_) => 14,
+        Latch(_) => 15,
+    }
+}
+
+/// Hash a gate, with a tag for its type followed by its inputs
+fn hash_gate(h: &mut StableHasher, g: &Gate) {
+    h.write(&[gate_tag(g)]);
+    h.write_u32(g.dependencies().len() as u32);
+    for s in g.dependencies() {
+        h.write_signal(*s);
+    }
+    if let Gate::Lut(lut) = g {
+        for w in lut.truth_table_words() {
+            h.write_u64(w);
+        }
+    }
+    // Only non-default clock domains are hashed, so that single-clock fingerprints are unchanged
+    if let Gate::Dff(_, clock) = g {
+        if !clock.is_default() {
+            h.write_u32(clock.index());
+            h.write(&[clock.is_falling_edge() as u8]);
+        }
+    }
+}
+
+/// Compute the fingerprint of a network
+///
+/// The network is made canonical first, so that trivial differences such as buffers do not change the fingerprint.
+pub fn fingerprint(aig: &Network) -> u64 {
+    let mut a = aig.clone();
+    a.make_canonical();
+    a.cleanup();
+    let mut h = StableHasher::new();
+    h.write_u32(a.nb_inputs() as u32);
+    h.write_u32(a.nb_outputs() as u32);
+    h.write_u32(a.nb_nodes() as u32);
+    for i in 0..a.nb_nodes() {
+        hash_gate(&mut h, &a.gate(i));
+    }
+    for o in 0..a.nb_outputs() {
+        h.write_signal(a.output(o));
+    }
+    h.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fingerprint, StableHasher};
+    use crate::network::generators::adder;
+    use crate::{Gate, Network};
+
+    #[test]
+    fn test_stable_hasher() {
+        // Reference values for 64-bit FNV-1a
+        assert_eq!(StableHasher::new().finish(), 0xcbf29ce484222325);
+        let mut h = StableHasher::new();
+        h.write(b"a");
+        assert_eq!(h.finish(), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let a = adder::ripple_carry(4);
+        let b = adder::ripple_carry(4);
+        let c = adder::ripple_carry(5);
+        assert_eq!(fingerprint(&a), fingerprint(&b));
+        assert_ne!(fingerprint(&a), fingerprint(&c));
+
+        // Buffers are ignored
+        let mut d = Network::new();
+        d.add_inputs(2);
+        let x = d.add(Gate::Buf(d.input(0)));
+        let y = d.and(x, d.input(1));
+        d.add_output(y);
+        let mut e = Network::new();
+        e.add_inputs(2);
+        let y = e.and(e.input(0), e.input(1));
+        e.add_output(y);
+        assert_eq!(fingerprint(&d), fingerprint(&e));
+    }
+
+    /// Reference fingerprint for a 3-input Xor Lut
+    const FINGERPRINT_LUT: u64 = 0x1242_3c1f_837a_7294;
+
+    #[test]
+    fn test_fingerprint_lut() {
+        use volute::Lut;
+
+        let build = || {
+            let mut lut = Lut::zero(3);
+            for m in [1, 2, 4, 7] {
+                lut.set_bit(m);
+            }
+            let mut aig = Network::new();
+            aig.add_inputs(3);
+            let inputs: Vec<_> = (0..3).map(|i| aig.input(i)).collect();
+            let x = aig.add(Gate::lut(&inputs, lut));
+            aig.add_output(x);
+            aig
+        };
+        let f = fingerprint(&build());
+        for _ in 0..10 {
+            assert_eq!(fingerprint(&build()), f);
+        }
+        // Only depends on the values of the truth table
+        assert_eq!(f, FINGERPRINT_LUT);
+    }
+}
@@ -1,6 +1,8 @@
 use core::slice;
+use std::hash::{Hash, Hasher};
 use std::{cmp, fmt};
 
+use thin_vec::ThinVec;
 use volute::Lut;
 
 use crate::network::signal::Signal;
@@ -45,12 +47,103 @@ pub enum NaryType {
 }
 
 /// Lut gate
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+///
+/// Hashing and ordering only depend on the inputs and on the values of the truth table, as given
+/// by [`LutGate::truth_table_words`], and not on the internal representation of [`Lut`].
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct LutGate {
+    /// Inputs of the Lut, the first one being the least significant variable of the truth table
     pub inputs: Box<[Signal]>,
+    /// Truth table
     pub lut: Lut,
 }
 
+/// Version of the serialization of truth tables by [`LutGate::encode_truth_table`]
+pub const TRUTH_TABLE_FORMAT_VERSION: u8 = 1;
+
+impl LutGate {
+    /// Values of the truth table, 64 per word, with minterm 0 in the least significant bit of the
+    /// first word; unused bits of the last word are zero
+    pub fn truth_table_words(&self) -> Vec<u64> {
+        let nb_bits = self.lut.num_bits();
+        let mut ret = vec![0u64; nb_bits.div_ceil(64)];
+        for m in 0..nb_bits {
+            if self.lut.get_bit(m) {
+                ret[m / 64] |= 1 << (m % 64);
+            }
+        }
+        ret
+    }
+
+    /// Serialize the truth table: the format version, the number of inputs, then the words of
+    /// [`LutGate::truth_table_words`] in little-endian order
+    pub fn encode_truth_table(&self) -> Vec<u8> {
+        let mut ret = vec![TRUTH_TABLE_FORMAT_VERSION, self.lut.num_vars() as u8];
+        for w in self.truth_table_words() {
+            ret.extend_from_slice(&w.to_le_bytes());
+        }
+        ret
+    }
+
+    /// Read a truth table serialized by [`LutGate::encode_truth_table`]
+    pub fn decode_truth_table(bytes: &[u8]) -> Result<Lut, String> {
+        let [version, nb_vars, data @ ..] = bytes else {
+            return Err("Truth table is truncated".to_owned());
+        };
+        if *version != TRUTH_TABLE_FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported truth table format version {}",
+                version
+            ));
+        }
+        let nb_vars = *nb_vars as usize;
+        if nb_vars > 24 {
+            return Err(format!("Truth table with {} inputs is too large", nb_vars));
+        }
+        let nb_bits = 1usize << nb_vars;
+        if data.len() != 8 * nb_bits.div_ceil(64) {
+            return Err(format!(
+                "Truth table with {} inputs has {} bytes of data",
+                nb_vars,
+                data.len()
+            ));
+        }
+        let bit = |m: usize| (data[m / 8] >> (m % 8)) & 1 != 0;
+        if (nb_bits..8 * data.len()).any(bit) {
+            return Err("Truth table has values beyond its size".to_owned());
+        }
+        let mut lut = Lut::zero(nb_vars);
+        for m in (0..nb_bits).filter(|m| bit(*m)) {
+            lut.set_bit(m);
+        }
+        Ok(lut)
+    }
+}
+
+impl Hash for LutGate {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inputs.hash(state);
+        self.truth_table_words().hash(state);
+    }
+}
+
+impl PartialOrd for LutGate {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for LutGate {
+    /// Order by number of inputs, then inputs, then truth table values
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.inputs
+            .len()
+            .cmp(&other.inputs.len())
+            .then_with(|| self.inputs.cmp(&other.inputs))
+            .then_with(|| self.truth_table_words().cmp(&other.truth_table_words()))
+    }
+}
+
 /// Logic gate representation
 ///
 /// Logic gates have a canonical form.
@@ -60,10 +153,14 @@ pub struct LutGate {
 /// Canonical form includes:
 ///   * And gates (with optional negated inputs)
 ///   * Xor gates (no negated input)
-///   * Mux/Maj/Dff
+///   * Mux/Maj/Dff/Latch
 /// Or/Nor/Nand gates are replaced by And gates.
 /// Xnor gates are replaced by Xor gates.
 /// Buf/Not and trivial gates are omitted.
+///
+/// A gate owns its inputs, and N-input gates keep them behind a single thin pointer so that a gate
+/// stays small. Networks store the inputs of their N-input gates and Luts in an arena:
+/// [`Network::gate_ref`](crate::Network::gate_ref) gives access to them without a copy.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum Gate {
     /// Arbitrary 2-input gate (And/Xor)
@@ -71,15 +168,121 @@ pub enum Gate {
     /// Arbitrary 3-input gate (And/Xor/Mux/Maj)
     Ternary([Signal; 3], TernaryType),
     /// Arbitrary N-input gate (And/Or/Xor/Nand/Nor/Xnor)
-    Nary(Box<[Signal]>, NaryType),
+    Nary(ThinVec<Signal>, NaryType),
     /// Buf or Not
     Buf(Signal),
-    /// D flip-flop with enable and reset
-    Dff([Signal; 3]),
+    /// D flip-flop with enable and reset: `Dff([d, en, res], clock)`
+    ///
+    /// The reset is synchronous and takes priority over the enable. At each edge of its clock,
+    /// the next value is `!res & (en ? d : prev)`, so that a flip-flop with both `en` and `res`
+    /// active is reset. The initial value is 0.
+    Dff([Signal; 3], ClockId),
+    /// Transparent latch with enable and reset: `Latch([d, en, res])`
+    ///
+    /// The output follows `d` while `en` is 1, and keeps its value otherwise. The reset takes
+    /// priority over the enable. At each timestep the value is `!res & (en ? d : prev)`, where
+    /// `prev` is the value at the previous timestep. The initial value is 0.
+    ///
+    /// Unlike a flip-flop, a latch is transparent: its inputs must come before it in the
+    /// topological order, and it is part of combinatorial paths.
+    Latch([Signal; 3]),
     /// LUT
     Lut(Box<LutGate>),
 }
 
+/// Gate of a network, with its inputs borrowed from the network
+///
+/// It has the same variants as [`Gate`], and is returned by
+/// [`Network::gate_ref`](crate::Network::gate_ref) without copying the inputs of N-input gates
+/// and Luts.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GateRef<'a> {
+    /// Arbitrary 2-input gate (And/Xor)
+    Binary(&'a [Signal; 2], BinaryType),
+    /// Arbitrary 3-input gate (And/Xor/Mux/Maj)
+    Ternary(&'a [Signal; 3], TernaryType),
+    /// Arbitrary N-input gate (And/Or/Xor/Nand/Nor/Xnor)
+    Nary(&'a [Signal], NaryType),
+    /// Buf or Not
+    Buf(&'a Signal),
+    /// D flip-flop with enable and reset, as [`Gate::Dff`]
+    Dff(&'a [Signal; 3], ClockId),
+    /// Transparent latch with enable and reset, as [`Gate::Latch`]
+    Latch(&'a [Signal; 3]),
+    /// LUT, with its inputs and its truth table
+    Lut(&'a [Signal], &'a Lut),
+}
+
+/// Clock domain of a flip-flop
+///
+/// Clocks are implicit in the network, and identified by their index. A flip-flop is triggered
+/// either on the rising or on the falling edge of its clock. Most designs only use the default
+/// domain, the rising edge of clock 0.
+///
+/// The index and the edge are packed in 16 bits, so that flip-flops fit in the compact nodes of a
+/// [`Network`](crate::Network): there are at most [`MAX_CLOCKS`] clocks.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, Default)]
+pub struct ClockId {
+    /// Index of the clock, shifted by one, with the edge in the least significant bit
+    packed: u16,
+}
+
+/// Maximum number of clocks in a network
+pub const MAX_CLOCKS: usize = 1 << 15;
+
+impl ClockId {
+    /// Edge of a clock
+    pub fn new(index: u32, falling_edge: bool) -> ClockId {
+        assert!((index as usize) < MAX_CLOCKS, "Too many clocks");
+        ClockId {
+            packed: (index as u16) << 1 | falling_edge as u16,
+        }
+    }
+
+    /// Rising edge of a clock
+    pub fn rising(index: u32) -> ClockId {
+        ClockId::new(index, false)
+    }
+
+    /// Falling edge of a clock
+    pub fn falling(index: u32) -> ClockId {
+        ClockId::new(index, true)
+    }
+
+    /// Index of the clock
+    pub fn index(&self) -> u32 {
+        (self.packed >> 1) as u32
+    }
+
+    /// Whether the flip-flops are triggered on the falling edge of the clock
+    pub fn is_falling_edge(&self) -> bool {
+        self.packed & 1 != 0
+    }
+
+    /// Returns whether this is the default domain, the rising edge of clock 0
+    pub fn is_default(&self) -> bool {
+        *self == ClockId::default()
+    }
+}
+
+impl fmt::Debug for ClockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClockId")
+            .field("index", &self.index())
+            .field("falling_edge", &self.is_falling_edge())
+            .finish()
+    }
+}
+
+impl fmt::Display for ClockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_falling_edge() {
+            write!(f, "!")?;
+        }
+        write!(f, "clk{}", self.index())
+    }
+}
+
 /// Result of normalizing a logic gate
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum Normalization {
@@ -138,9 +341,27 @@ impl Gate {
         Gate::Ternary([a, b, c], TernaryType::Maj)
     }
 
-    /// Create a Dff
+    /// Create a Dff, with a synchronous reset taking priority over the enable
     pub fn dff(d: Signal, en: Signal, res: Signal) -> Gate {
-        Gate::Dff([d, en, res])
+        Gate::Dff([d, en, res], ClockId::default())
+    }
+
+    /// Create a Dff in a given clock domain
+    pub fn dff_with_clock(d: Signal, en: Signal, res: Signal, clock: ClockId) -> Gate {
+        Gate::Dff([d, en, res], clock)
+    }
+
+    /// Create a transparent latch, with a reset taking priority over the enable
+    pub fn latch(d: Signal, en: Signal, res: Signal) -> Gate {
+        Gate::Latch([d, en, res])
+    }
+
+    /// Clock domain of the gate, if it is a flip-flop
+    pub fn clock(&self) -> Option<ClockId> {
+        match self {
+            Gate::Dff(_, clock) => Some(*clock),
+            _ => None,
+        }
     }
 
     /// Returns whether the gate is in canonical form
@@ -173,13 +394,16 @@ impl Gate {
                 sorted_n(v) && v.len() > 3 && !v[0].is_constant() && no_inv_n(v)
             }
             Nary(_, _) => false,
-            Dff([d, en, res]) => {
+            Dff([d, en, res], _) => {
                 *en != Signal::zero() && *d != Signal::zero() && *res != Signal::one()
                 // TODO: handle synonyms in the inputs resulting in:
                 //   * const 0 (en == !d, en == res, res == d)
                 //   * remove enable (en == !res)
                 //   * remove data (d == res)
             }
+            Latch([d, en, res]) => {
+                !en.is_constant() && *d != Signal::zero() && *res != Signal::one()
+            }
             Buf(_) => false,
             Lut(_) => true,
         }
@@ -193,15 +417,7 @@ impl Gate {
 
     /// Obtain all signals feeding this gate
     pub fn dependencies(&self) -> &[Signal] {
-        use Gate::*;
-        match self {
-            Binary(s, _) => s,
-            Ternary(s, _) => s,
-            Nary(v, _) => v,
-            Dff(s) => s,
-            Buf(s) => slice::from_ref(s),
-            Lut(lut) => lut.inputs.as_ref(),
-        }
+        GateRef::from(self).dependencies()
     }
 
     /// Obtain all internal variables feeding this gate (not inputs or constants)
@@ -214,8 +430,23 @@ impl Gate {
     }
 
     /// Returns whether the gate is combinatorial
+    ///
+    /// Flip-flops and latches, that keep a state between timesteps, are not combinatorial.
     pub fn is_comb(&self) -> bool {
-        return !matches!(self, Gate::Dff(_));
+        !matches!(self, Gate::Dff(..) | Gate::Latch(..))
+    }
+
+    /// Returns whether the gate is a flip-flop
+    ///
+    /// Flip-flops are the only gates whose inputs do not need to come before them in the
+    /// topological order.
+    pub fn is_dff(&self) -> bool {
+        matches!(self, Gate::Dff(..))
+    }
+
+    /// Returns whether the gate is a transparent latch
+    pub fn is_latch(&self) -> bool {
+        matches!(self, Gate::Latch(..))
     }
 
     /// Returns whether the gate is an And of any arity
@@ -268,28 +499,18 @@ impl Gate {
     }
 
     /// Apply a remapping of the signals to the gate
-    pub(crate) fn remap<F: Fn(&Signal) -> Signal>(&self, t: F) -> Gate {
-        use Gate::*;
-        match self {
-            Binary([a, b], tp) => Binary([t(a), t(b)], *tp),
-            Ternary([a, b, c], tp) => Ternary([t(a), t(b), t(c)], *tp),
-            Dff([a, b, c]) => Dff([t(a), t(b), t(c)]),
-            Nary(v, tp) => Nary(v.iter().map(|s| t(s)).collect(), *tp),
-            Buf(s) => Buf(t(s)),
-            Lut(lut) => Lut(Box::new(LutGate {
-                inputs: lut.inputs.iter().map(|s| t(s)).collect(),
-                lut: lut.lut.clone(),
-            })),
-        }
+    pub fn remap<F: Fn(&Signal) -> Signal>(&self, t: F) -> Gate {
+        GateRef::from(self).remap(t)
     }
 
     /// Apply a remapping of the signals to the gate that takes the position as argument
-    pub(crate) fn remap_with_ind<F: Fn(&Signal, usize) -> Signal>(&self, t: F) -> Gate {
+    pub fn remap_with_ind<F: Fn(&Signal, usize) -> Signal>(&self, t: F) -> Gate {
         use Gate::*;
         match self {
             Binary([a, b], tp) => Binary([t(a, 0), t(b, 1)], *tp),
             Ternary([a, b, c], tp) => Ternary([t(a, 0), t(b, 1), t(c, 2)], *tp),
-            Dff([a, b, c]) => Dff([t(a, 0), t(b, 1), t(c, 2)]),
+            Dff([a, b, c], clock) => Dff([t(a, 0), t(b, 1), t(c, 2)], *clock),
+            Latch([a, b, c]) => Latch([t(a, 0), t(b, 1), t(c, 2)]),
             Nary(v, tp) => Nary(v.iter().enumerate().map(|(i, s)| t(s, i)).collect(), *tp),
             Buf(s) => Buf(t(s, 0)),
             Lut(lut) => Lut(Box::new(LutGate {
@@ -306,8 +527,79 @@ impl Gate {
 
     /// Apply a remapping of variable order to the gate
     pub(crate) fn remap_order(&self, t: &[Signal]) -> Gate {
-        let f = |s: &Signal| s.remap_order(t);
-        self.remap(f)
+        GateRef::from(self).remap_order(t)
+    }
+}
+
+impl<'a> From<&'a Gate> for GateRef<'a> {
+    fn from(g: &'a Gate) -> GateRef<'a> {
+        match g {
+            Gate::Binary(s, tp) => GateRef::Binary(s, *tp),
+            Gate::Ternary(s, tp) => GateRef::Ternary(s, *tp),
+            Gate::Nary(v, tp) => GateRef::Nary(v, *tp),
+            Gate::Buf(s) => GateRef::Buf(s),
+            Gate::Dff(s, clock) => GateRef::Dff(s, *clock),
+            Gate::Latch(s) => GateRef::Latch(s),
+            Gate::Lut(lut) => GateRef::Lut(&lut.inputs, &lut.lut),
+        }
+    }
+}
+
+impl<'a> GateRef<'a> {
+    /// Obtain all signals feeding this gate
+    pub fn dependencies(&self) -> &'a [Signal] {
+        use GateRef::*;
+        match *self {
+            Binary(s, _) => s,
+            Ternary(s, _) => s,
+            Nary(v, _) => v,
+            Dff(s, _) => s,
+            Latch(s) => s,
+            Buf(s) => slice::from_ref(s),
+            Lut(v, _) => v,
+        }
+    }
+
+    /// Returns whether the gate is combinatorial
+    pub fn is_comb(&self) -> bool {
+        !matches!(self, GateRef::Dff(..) | GateRef::Latch(..))
+    }
+
+    /// Returns whether the gate is a flip-flop
+    pub fn is_dff(&self) -> bool {
+        matches!(self, GateRef::Dff(..))
+    }
+
+    /// Returns whether the gate is a transparent latch
+    pub fn is_latch(&self) -> bool {
+        matches!(self, GateRef::Latch(..))
+    }
+
+    /// Copy the gate, with its inputs
+    pub fn to_gate(&self) -> Gate {
+        self.remap(|s| *s)
+    }
+
+    /// Copy the gate with a remapping of the signals
+    pub fn remap<F: Fn(&Signal) -> Signal>(&self, t: F) -> Gate {
+        use GateRef::*;
+        match *self {
+            Binary([a, b], tp) => Gate::Binary([t(a), t(b)], tp),
+            Ternary([a, b, c], tp) => Gate::Ternary([t(a), t(b), t(c)], tp),
+            Dff([a, b, c], clock) => Gate::Dff([t(a), t(b), t(c)], clock),
+            Latch([a, b, c]) => Gate::Latch([t(a), t(b), t(c)]),
+            Nary(v, tp) => Gate::Nary(v.iter().map(t).collect(), tp),
+            Buf(s) => Gate::Buf(t(s)),
+            Lut(v, lut) => Gate::Lut(Box::new(LutGate {
+                inputs: v.iter().map(t).collect(),
+                lut: lut.clone(),
+            })),
+        }
+    }
+
+    /// Copy the gate with a remapping of variable order
+    pub(crate) fn remap_order(&self, t: &[Signal]) -> Gate {
+        self.remap(|s| s.remap_order(t))
     }
 }
 
@@ -428,13 +720,27 @@ fn make_maj(a: Signal, b: Signal, c: Signal, inv: bool) -> Normalization {
 }
 
 /// Normalize a Dff
-fn make_dff(d: Signal, en: Signal, res: Signal, inv: bool) -> Normalization {
+fn make_dff(d: Signal, en: Signal, res: Signal, clock: ClockId, inv: bool) -> Normalization {
+    use Gate::*;
+    use Normalization::*;
+    if d == Signal::zero() || en == Signal::zero() || res == Signal::one() {
+        Copy(Signal::zero() ^ inv)
+    } else {
+        Node(Dff([d, en, res], clock), inv)
+    }
+}
+
+/// Normalize a Latch
+fn make_latch(d: Signal, en: Signal, res: Signal, inv: bool) -> Normalization {
     use Gate::*;
     use Normalization::*;
     if d == Signal::zero() || en == Signal::zero() || res == Signal::one() {
+        // Never loaded with a 1, so it keeps its initial value
         Copy(Signal::zero() ^ inv)
+    } else if en == Signal::one() {
+        make_and(d, !res, inv)
     } else {
-        Node(Dff([d, en, res]), inv)
+        Node(Latch([d, en, res]), inv)
     }
 }
 
@@ -532,7 +838,8 @@ impl Normalization {
                 Ternary([a, b, c], TernaryType::Xor) => make_xor3(*a, *b, *c, *inv),
                 Ternary([s, a, b], TernaryType::Mux) => make_mux(*s, *a, *b, *inv),
                 Ternary([a, b, c], TernaryType::Maj) => make_maj(*a, *b, *c, *inv),
-                Dff([d, en, res]) => make_dff(*d, *en, *res, *inv),
+                Dff([d, en, res], clock) => make_dff(*d, *en, *res, *clock, *inv),
+                Latch([d, en, res]) => make_latch(*d, *en, *res, *inv),
                 Nary(v, t) => {
                     let vi: Box<[Signal]> = v.iter().map(|s| !s).collect();
                     match t {
@@ -573,7 +880,7 @@ impl fmt::Display for Gate {
             Ternary([a, b, c], TernaryType::Maj) => {
                 write!(f, "Maj({a}, {b}, {c})")
             }
-            Dff([d, en, res]) => {
+            Dff([d, en, res], clock) => {
                 write!(f, "Dff({d}")?;
                 if *en != Signal::one() {
                     write!(f, ", en={en}")?;
@@ -581,6 +888,16 @@ impl fmt::Display for Gate {
                 if *res != Signal::zero() {
                     write!(f, ", res={res}")?;
                 }
+                if !clock.is_default() {
+                    write!(f, ", clk={clock}")?;
+                }
+                write!(f, ")")
+            }
+            Latch([d, en, res]) => {
+                write!(f, "Latch({d}, en={en}")?;
+                if *res != Signal::zero() {
+                    write!(f, ", res={res}")?;
+                }
                 write!(f, ")")
             }
             Nary(v, tp) => {
@@ -719,6 +1036,7 @@ mod tests {
                     check_canonization(Gate::and3(*i0, *i1, *i2));
                     check_canonization(Gate::xor3(*i0, *i1, *i2));
                     check_canonization(Gate::dff(*i0, *i1, *i2));
+                    check_canonization(Gate::latch(*i0, *i1, *i2));
                     for i3 in vars.iter() {
                         check_canonization(Nary(vec![*i0, *i1, *i2, *i3].into(), NaryType::And));
                         check_canonization(Nary(vec![*i0, *i1, *i2, *i3].into(), NaryType::Nand));
@@ -866,12 +1184,70 @@ mod tests {
     }
 
     /// Check that the size used for Gate does not increase
-    ///
-    /// This is currently too high due to the NAry variant, where the Box uses 16 bytes.
-    /// This could be made lower with another level of indirection, or with an ad-hoc type
-    /// to replace Box.
     #[test]
     fn test_representation_size() {
-        assert!(std::mem::size_of::<Gate>() <= 6 * std::mem::size_of::<Signal>());
+        assert!(std::mem::size_of::<Gate>() <= 4 * std::mem::size_of::<Signal>());
+        assert_eq!(std::mem::size_of::<ClockId>(), 2);
+    }
+
+    #[test]
+    fn test_lut_stable() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let hash = |g: &LutGate| {
+            let mut h = DefaultHasher::new();
+            g.hash(&mut h);
+            h.finish()
+        };
+        let inputs: Box<[Signal]> = (0..3).map(Signal::from_input).collect();
+        // Majority of three inputs: minterms 3, 5, 6 and 7
+        let mut maj = volute::Lut::zero(3);
+        for m in [3, 5, 6, 7] {
+            maj.set_bit(m);
+        }
+        let g = LutGate {
+            inputs: inputs.clone(),
+            lut: maj.clone(),
+        };
+        assert_eq!(g.truth_table_words(), vec![0xe8]);
+        assert_eq!(
+            g.encode_truth_table(),
+            vec![TRUTH_TABLE_FORMAT_VERSION, 3, 0xe8, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            LutGate::decode_truth_table(&g.encode_truth_table()),
+            Ok(maj)
+        );
+
+        let mut big = volute::Lut::zero(7);
+        big.set_bit(0);
+        big.set_bit(127);
+        let big = LutGate {
+            inputs: (0..7).map(Signal::from_input).collect(),
+            lut: big,
+        };
+        assert_eq!(big.truth_table_words(), vec![1, 1 << 63]);
+        assert_eq!(
+            LutGate::decode_truth_table(&big.encode_truth_table()),
+            Ok(big.lut.clone())
+        );
+
+        for bad in [
+            &[2, 3, 0xe8, 0, 0, 0, 0, 0, 0, 0][..],
+            &[1, 3, 0xe8],
+            &[1, 3, 0xe8, 1, 0, 0, 0, 0, 0, 0],
+        ] {
+            assert!(LutGate::decode_truth_table(bad).is_err());
+        }
+
+        // Equal gates hash the same, and the order is total
+        let h = g.clone();
+        assert_eq!(hash(&g), hash(&h));
+        let mut and = volute::Lut::zero(3);
+        and.set_bit(7);
+        let a = LutGate { inputs, lut: and };
+        assert_eq!(a.cmp(&g), cmp::Ordering::Less);
+        assert_eq!(g.cmp(&big), cmp::Ordering::Less);
+        assert_eq!(g.cmp(&h), cmp::Ordering::Equal);
     }
 }
@@ -0,0 +1,378 @@
+//! Network generators and templates
+
+/// Adder generators
+pub mod adder {
+    use crate::{Gate, Network, Signal};
+
+    /// A simple and slow ripple-carry adder
+    pub fn ripple_carry(len: usize) -> Network {
+        let mut ret = Network::new();
+        let mut c = Signal::zero();
+        for _ in 0..len {
+            let a = ret.add_input();
+            let b = ret.add_input();
+            let next_c = ret.add(Gate::maj(a, b, c));
+            let o = ret.add(Gate::xor3(a, b, c));
+            ret.add_output(o);
+            c = next_c;
+        }
+        ret.add_output(c);
+        ret.check();
+        ret
+    }
+
+    /// A carry-lookahead adder, with the same interface as [`ripple_carry`]
+    ///
+    /// Carries are computed with a Sklansky parallel prefix tree, for logarithmic depth.
+    pub fn carry_lookahead(len: usize) -> Network {
+        let mut ret = Network::new();
+        ret.add_inputs(2 * len);
+        let a: Vec<Signal> = (0..len).map(|i| ret.input(2 * i)).collect();
+        let b: Vec<Signal> = (0..len).map(|i| ret.input(2 * i + 1)).collect();
+        let (sum, carry) = add_carry_lookahead(&mut ret, &a, &b, Signal::zero());
+        for s in sum {
+            ret.add_output(s);
+        }
+        ret.add_output(carry);
+        ret.check();
+        ret
+    }
+
+    /// Add a carry-lookahead adder of two words to a network, least significant bit first
+    ///
+    /// Returns the sum and the carry out.
+    pub fn add_carry_lookahead(
+        aig: &mut Network,
+        a: &[Signal],
+        b: &[Signal],
+        carry_in: Signal,
+    ) -> (Vec<Signal>, Signal) {
+        assert_eq!(a.len(), b.len());
+        let len = a.len();
+        if len == 0 {
+            return (Vec::new(), carry_in);
+        }
+        let propagate: Vec<Signal> = (0..len).map(|i| aig.xor(a[i], b[i])).collect();
+        // Generate and propagate signals of the prefix [0, i], with the carry in folded in bit 0
+        let mut generate: Vec<Signal> = (0..len).map(|i| aig.and(a[i], b[i])).collect();
+        generate[0] = aig.add_canonical(Gate::maj(a[0], b[0], carry_in));
+        let mut prop = propagate.clone();
+        let mut level = 1;
+        while level < len {
+            for i in 0..len {
+                if i & level == 0 {
+                    continue;
+                }
+                let j = (i & !(level - 1)) - 1;
+                let t = aig.and(prop[i], generate[j]);
+                generate[i] = !aig.and(!generate[i], !t);
+                prop[i] = aig.and(prop[i], prop[j]);
+            }
+            level *= 2;
+        }
+        let sum = (0..len)
+            .map(|i| {
+                let c = if i == 0 { carry_in } else { generate[i - 1] };
+                aig.xor(propagate[i], c)
+            })
+            .collect();
+        (sum, generate[len - 1])
+    }
+}
+
+/// Multiplier generators
+pub mod multiplier {
+    use crate::{Gate, Network, Signal};
+
+    /// A carry-save array multiplier, followed by a ripple-carry adder
+    ///
+    /// The first operand is given by the first `len` inputs, the second by the next `len` inputs,
+    /// and the `2 * len` outputs are the product, least significant bit first.
+    pub fn array(len: usize) -> Network {
+        let mut ret = Network::new();
+        ret.add_inputs(2 * len);
+        let mut sum = vec![Signal::zero(); 2 * len];
+        let mut carry = vec![Signal::zero(); 2 * len];
+        for j in 0..len {
+            let b = ret.input(len + j);
+            let mut next_carry = vec![Signal::zero(); 2 * len];
+            for k in 0..2 * len {
+                let pp = if k >= j && k - j < len {
+                    ret.add(Gate::and(ret.input(k - j), b))
+                } else {
+                    Signal::zero()
+                };
+                if k + 1 < 2 * len {
+                    next_carry[k + 1] = ret.add(Gate::maj(sum[k], carry[k], pp));
+                }
+                sum[k] = ret.add(Gate::xor3(sum[k], carry[k], pp));
+            }
+            carry = next_carry;
+        }
+        let mut c = Signal::zero();
+        for k in 0..2 * len {
+            let next_c = ret.add(Gate::maj(sum[k], carry[k], c));
+            let o = ret.add(Gate::xor3(sum[k], carry[k], c));
+            ret.add_output(o);
+            c = next_c;
+        }
+        ret.check();
+        ret
+    }
+}
+
+/// Carry chain generators
+pub mod carry_chain {
+    use crate::{Network, Signal};
+
+    /// A simple and slow ripple-carry chain
+    pub fn ripple_carry(len: usize) -> Network {
+        let mut ret = Network::new();
+        let mut c = Signal::zero();
+        for _ in 0..len {
+            let propagate = ret.add_input();
+            let generate = ret.add_input();
+            let d = ret.and(propagate, c);
+            c = !ret.and(!generate, !d);
+            ret.add_output(c);
+        }
+        ret
+    }
+}
+
+/// Simple generators to test functionality
+pub mod testcases {
+    use rand::Rng;
+    use volute::Lut;
+
+    use crate::network::NaryType;
+    use crate::util::rng::SeededRng;
+    use crate::{Gate, Network, Signal};
+
+    /// A circular chain of Dffs with a Xor with input at the start; used to test topological sorting
+    pub fn toggle_chain(len: usize, has_en: bool, has_res: bool) -> Network {
+        assert!(len > 0);
+        let mut ret = Network::new();
+        let input = ret.add_input();
+        let en = if has_en {
+            ret.add_input()
+        } else {
+            Signal::one()
+        };
+        let res = if has_res {
+            ret.add_input()
+        } else {
+            Signal::zero()
+        };
+        let xor_output = Signal::from_var(len as u32);
+        let mut x = input;
+        for _ in 0..len {
+            x = ret.dff(x, en, res);
+        }
+        x = ret.xor(x, input);
+        ret.add_output(x);
+        assert_eq!(x, xor_output);
+        ret.check();
+        assert!(ret.is_topo_sorted());
+        ret
+    }
+
+    /// An expanding tree of Dffs, used to test deduplication
+    pub fn ff_tree(depth: usize, has_en: bool, has_res: bool, expansion: usize) -> Network {
+        let mut ret = Network::new();
+        let input = ret.add_input();
+        let en = if has_en {
+            ret.add_input()
+        } else {
+            Signal::one()
+        };
+        let res = if has_res {
+            ret.add_input()
+        } else {
+            Signal::zero()
+        };
+        let mut stage = vec![input];
+        for _ in 0..depth {
+            let mut next_stage = Vec::new();
+            for s in stage {
+                for _ in 0..expansion {
+                    next_stage.push(ret.dff(s, en, res));
+                }
+            }
+            stage = next_stage;
+        }
+        for s in stage {
+            ret.add_output(s);
+        }
+        ret.check();
+        ret
+    }
+
+    /// A random sequential network using all kinds of gates and flip-flops with enable and reset,
+    /// used to test simulation
+    pub fn random_seq(
+        nb_inputs: usize,
+        nb_dffs: usize,
+        nb_gates: usize,
+        nb_outputs: usize,
+        seed: u64,
+    ) -> Network {
+        let mut rng = SeededRng::new(seed);
+        let mut ret = Network::new();
+        ret.add_inputs(nb_inputs);
+        let mut signals: Vec<Signal> = (0..nb_inputs).map(|i| ret.input(i)).collect();
+        // Flip-flops come first and are connected at the end, so they can use any signal
+        for _ in 0..nb_dffs {
+            signals.push(ret.add(Gate::dff(Signal::zero(), Signal::one(), Signal::zero())));
+        }
+        let pick = |rng: &mut SeededRng, signals: &Vec<Signal>| -> Signal {
+            if signals.is_empty() || rng.gen_ratio(1, 20) {
+                Signal::from(rng.gen::<bool>())
+            } else {
+                signals[rng.gen_range(0..signals.len())] ^ rng.gen::<bool>()
+            }
+        };
+        for _ in 0..nb_gates {
+            let a = pick(&mut rng, &signals);
+            let b = pick(&mut rng, &signals);
+            let c = pick(&mut rng, &signals);
+            let v: Vec<Signal> = (0..rng.gen_range(1..6))
+                .map(|_| pick(&mut rng, &signals))
+                .collect();
+            let g = match rng.gen_range(0..10) {
+                0 => Gate::and(a, b),
+                1 => Gate::xor(a, b),
+                2 => Gate::and3(a, b, c),
+                3 => Gate::xor3(a, b, c),
+                4 => Gate::mux(a, b, c),
+                5 => Gate::maj(a, b, c),
+                6 => Gate::Buf(a),
+                7 => {
+                    let tp = [
+                        NaryType::And,
+                        NaryType::Or,
+                        NaryType::Nand,
+                        NaryType::Nor,
+                        NaryType::Xor,
+                        NaryType::Xnor,
+                    ][rng.gen_range(0..6)];
+                    Gate::Nary(v.into(), tp)
+                }
+                _ => {
+                    let mut lut = Lut::zero(3);
+                    for m in 0..8 {
+                        if rng.gen() {
+                            lut.set_bit(m);
+                        }
+                    }
+                    Gate::lut(&[a, b, c], lut)
+                }
+            };
+            signals.push(ret.add(g));
+        }
+        for i in 0..nb_dffs {
+            let d = pick(&mut rng, &signals);
+            let en = if rng.gen() {
+                pick(&mut rng, &signals)
+            } else {
+                Signal::one()
+            };
+            let res = if rng.gen() {
+                pick(&mut rng, &signals)
+            } else {
+                Signal::zero()
+            };
+            ret.replace(i, Gate::dff(d, en, res));
+        }
+        for _ in 0..nb_outputs {
+            let o = pick(&mut rng, &signals);
+            ret.add_output(o);
+        }
+        ret.check();
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{adder, carry_chain, testcases};
+    use crate::network::depth::depth;
+    use crate::sim::simulate_comb;
+
+    #[test]
+    fn test_adder() {
+        for i in [0, 1, 2, 4, 8, 16, 32, 64, 128] {
+            adder::ripple_carry(i);
+        }
+    }
+
+    #[test]
+    fn test_carry_lookahead() {
+        for len in [0, 1, 2, 3, 5] {
+            let cla = adder::carry_lookahead(len);
+            let rca = adder::ripple_carry(len);
+            for k in 0..1u32 << (2 * len) {
+                let v: Vec<bool> = (0..2 * len).map(|j| (k >> j) & 1 != 0).collect();
+                assert_eq!(simulate_comb(&cla, &v), simulate_comb(&rca, &v));
+            }
+        }
+        assert_eq!(depth(&adder::carry_lookahead(32)), 12);
+        assert_eq!(depth(&adder::ripple_carry(32)), 32);
+    }
+
+    #[test]
+    fn test_carry_chain() {
+        for i in [0, 1, 2, 4, 8, 16, 32, 64, 128] {
+            carry_chain::ripple_carry(i);
+        }
+    }
+
+    #[test]
+    fn test_toggle_chain() {
+        for i in [1, 2, 4, 8, 16, 32, 64, 128] {
+            for has_en in [false, true] {
+                for has_res in [false, true] {
+                    let mut aig = testcases::toggle_chain(i, has_en, has_res);
+                    assert_eq!(aig.nb_nodes(), i + 1);
+                    aig.cleanup();
+                    aig.make_canonical();
+                    assert_eq!(aig.nb_nodes(), i + 1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_seq() {
+        for seed in 0..10 {
+            let aig = testcases::random_seq(4, 3, 20, 5, seed);
+            assert_eq!(aig.nb_inputs(), 4);
+            assert_eq!(aig.nb_nodes(), 23);
+            assert_eq!(aig.nb_outputs(), 5);
+            assert!(aig.is_topo_sorted());
+            assert_eq!(
+                format!("{aig:?}"),
+                format!("{:?}", testcases::random_seq(4, 3, 20, 5, seed))
+            );
+        }
+    }
+
+    #[test]
+    fn test_ff_tree() {
+        for i in [0, 1, 2, 3, 4, 5] {
+            for expansion in [1, 2] {
+                for has_en in [false, true] {
+                    for has_res in [false, true] {
+                        let mut aig = testcases::ff_tree(i, has_en, has_res, expansion);
+                        aig.cleanup();
+                        for _ in 0..i {
+                            // Run dedup several times, once per Dff level
+                            aig.make_canonical();
+                        }
+                        assert_eq!(aig.nb_nodes(), i);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Instances of the original design hierarchy of a flattened network
+
+use crate::Signal;
+
+/// Instance of a model, flattened into the network
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instance {
+    /// Hierarchical path of the instance, such as `adder_0/fa_1`
+    pub path: String,
+    /// Name of the model instantiated
+    pub model: String,
+}
+
+/// Instance of a black box, whose logic is unknown, cut out of the network
+///
+/// The outputs of the black box are represented by inputs of the network, and its inputs by
+/// outputs of the network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlackBox {
+    /// Hierarchical path of the instance
+    pub path: String,
+    /// Name of the model instantiated
+    pub model: String,
+    /// Input ports of the black box, with the output of the network they are connected to
+    pub inputs: Vec<(String, usize)>,
+    /// Output ports of the black box, with the input of the network that represents them
+    pub outputs: Vec<(String, usize)>,
+}
+
+/// Instances of the nodes of a flattened network
+///
+/// As for the [`SourceMap`](crate::network::SourceMap), the network itself does not store the
+/// hierarchy: this side table is only filled by the readers on request, and must be updated with
+/// the translation returned by transformations that renumber nodes. Nodes of the top-level model
+/// do not belong to any instance.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HierarchyMap {
+    instances: Vec<Instance>,
+    nodes: Vec<Option<u32>>,
+    black_boxes: Vec<BlackBox>,
+}
+
+impl HierarchyMap {
+    /// Create an empty hierarchy map
+    pub fn new() -> HierarchyMap {
+        HierarchyMap::default()
+    }
+
+    /// Return whether the network has no instance and no black box
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty() && self.black_boxes.is_empty()
+    }
+
+    /// Number of instances
+    pub fn nb_instances(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Add an instance, and return its index
+    pub fn add_instance(&mut self, path: &str, model: &str) -> u32 {
+        self.instances.push(Instance {
+            path: path.to_owned(),
+            model: model.to_owned(),
+        });
+        (self.instances.len() - 1) as u32
+    }
+
+    /// Instance with a given index
+    pub fn instance(&self, i: u32) -> &Instance {
+        &self.instances[i as usize]
+    }
+
+    /// Add a black box
+    pub fn add_black_box(&mut self, black_box: BlackBox) {
+        self.black_boxes.push(black_box);
+    }
+
+    /// Black boxes cut out of the network
+    pub fn black_boxes(&self) -> &[BlackBox] {
+        &self.black_boxes
+    }
+
+    /// Instance of a node, if any
+    pub fn node_instance(&self, i: usize) -> Option<u32> {
+        self.nodes.get(i).copied().flatten()
+    }
+
+    /// Set the instance of a node
+    pub fn set_node_instance(&mut self, i: usize, instance: u32) {
+        if self.nodes.len() <= i {
+            self.nodes.resize(i + 1, None);
+        }
+        self.nodes[i] = Some(instance);
+    }
+
+    /// Instance of the gate driving a signal, if any
+    pub fn signal_instance(&self, s: Signal) -> Option<u32> {
+        if s.is_var() {
+            self.node_instance(s.var() as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Path of the instance of a node, if any
+    pub fn describe_node(&self, i: usize) -> Option<&str> {
+        self.node_instance(i)
+            .map(|inst| self.instance(inst).path.as_str())
+    }
+
+    /// Number of nodes directly in each instance, excluding its sub-instances
+    pub fn instance_sizes(&self) -> Vec<usize> {
+        let mut ret = vec![0; self.nb_instances()];
+        for inst in self.nodes.iter().flatten() {
+            ret[*inst as usize] += 1;
+        }
+        ret
+    }
+
+    /// Update the node instances after a transformation that returned a translation
+    ///
+    /// As for locations, instances are kept for nodes that are now inverted. When several nodes
+    /// are merged, the instance of the first one is kept.
+    pub fn remap(&mut self, translation: &[Signal]) {
+        let mut nodes: Vec<Option<u32>> = Vec::new();
+        for (old, s) in translation.iter().enumerate() {
+            let Some(inst) = self.node_instance(old) else {
+                continue;
+            };
+            if !s.is_var() {
+                continue;
+            }
+            let i = s.var() as usize;
+            if nodes.len() <= i {
+                nodes.resize(i + 1, None);
+            }
+            nodes[i].get_or_insert(inst);
+        }
+        self.nodes = nodes;
+    }
+}
@@ -0,0 +1,83 @@
+//! Iterators over the nodes of a network
+
+use std::iter::FusedIterator;
+
+use super::network::Node;
+use crate::{Gate, Network, Signal};
+
+/// Iterator over the nodes of a network, with their gates
+///
+/// Returned by [`Network::iter_gates`](crate::Network::iter_gates),
+/// [`Network::iter_comb`](crate::Network::iter_comb) and
+/// [`Network::iter_dffs`](crate::Network::iter_dffs). Nodes are visited in order, and the
+/// number of remaining nodes is always known.
+#[derive(Clone, Debug)]
+pub struct Gates<'a> {
+    aig: &'a Network,
+    filter: fn(&Node) -> bool,
+    front: usize,
+    back: usize,
+    len: usize,
+}
+
+impl<'a> Gates<'a> {
+    /// Iterate over the nodes whose gate satisfies the filter
+    ///
+    /// The matching nodes are counted upfront, unless all nodes are kept.
+    pub(super) fn new(aig: &'a Network, filter: Option<fn(&Node) -> bool>) -> Gates<'a> {
+        let nodes = &aig.nodes;
+        let (filter, len) = match filter {
+            Some(f) => (f, nodes.iter().filter(|g| f(g)).count()),
+            None => ((|_: &Node| true) as fn(&Node) -> bool, nodes.len()),
+        };
+        Gates {
+            aig,
+            filter,
+            front: 0,
+            back: nodes.len(),
+            len,
+        }
+    }
+
+    fn item(&self, i: usize) -> (Signal, Gate) {
+        (Signal::from_var(i as u32), self.aig.gate(i))
+    }
+}
+
+impl Iterator for Gates<'_> {
+    type Item = (Signal, Gate);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.front < self.back {
+            let i = self.front;
+            self.front += 1;
+            if (self.filter)(&self.aig.nodes[i]) {
+                self.len -= 1;
+                return Some(self.item(i));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl DoubleEndedIterator for Gates<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while self.front < self.back {
+            self.back -= 1;
+            let i = self.back;
+            if (self.filter)(&self.aig.nodes[i]) {
+                self.len -= 1;
+                return Some(self.item(i));
+            }
+        }
+        None
+    }
+}
+
+impl ExactSizeIterator for Gates<'_> {}
+
+impl FusedIterator for Gates<'_> {}
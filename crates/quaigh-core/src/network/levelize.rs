@@ -0,0 +1,174 @@
+//! Levelization of a network, to evaluate its gates one level at a time
+
+use std::ops::Range;
+
+use crate::Network;
+
+/// Nodes of a network grouped by level, each level being a contiguous range of nodes
+///
+/// Gates of a level only use primary inputs, flip-flops and the gates of the previous levels, so
+/// that all gates of a level can be evaluated independently, for example in parallel. The first
+/// level starts with the flip-flops, followed by the gates that only use inputs and flip-flops.
+/// Unlike [`depth::levels`](super::depth::levels), buffers are one level after their input.
+///
+/// It is obtained by [`Network::levelize`], that reorders the nodes by level.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Levelization {
+    /// Index of the first node of each level, followed by the number of nodes
+    starts: Vec<usize>,
+}
+
+impl Levelization {
+    /// Number of levels
+    pub fn nb_levels(&self) -> usize {
+        self.starts.len() - 1
+    }
+
+    /// Number of nodes in all levels
+    pub fn nb_nodes(&self) -> usize {
+        *self.starts.last().unwrap()
+    }
+
+    /// Range of the nodes of a level
+    pub fn level(&self, l: usize) -> Range<usize> {
+        self.starts[l]..self.starts[l + 1]
+    }
+
+    /// Iterate over the ranges of nodes of each level, in order
+    pub fn iter(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.starts.windows(2).map(|w| w[0]..w[1])
+    }
+
+    /// Level of a node
+    pub fn level_of(&self, node: usize) -> usize {
+        assert!(node < self.nb_nodes());
+        self.starts.partition_point(|s| *s <= node) - 1
+    }
+
+    /// Returns whether the levels are valid for a network: each gate only uses the gates of the
+    /// previous levels, and flip-flops are in the first level
+    pub fn is_valid(&self, aig: &Network) -> bool {
+        if self.nb_nodes() != aig.nb_nodes() {
+            return false;
+        }
+        for (l, r) in self.iter().enumerate() {
+            for i in r.clone() {
+                let g = aig.gate(i);
+                if g.is_dff() {
+                    if l != 0 {
+                        return false;
+                    }
+                } else if g.vars().any(|v| {
+                    let v = v as usize;
+                    v >= r.start && !aig.gate_ref(v).is_dff()
+                }) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Level of each node: flip-flops and gates that only use inputs and flip-flops are at level 0,
+/// and other gates one level after their latest input
+fn node_levels(aig: &Network) -> Vec<usize> {
+    assert!(aig.is_topo_sorted());
+    let mut ret = vec![0; aig.nb_nodes()];
+    for (s, g) in aig.iter_gates() {
+        if g.is_dff() {
+            continue;
+        }
+        ret[s.var() as usize] = g
+            .vars()
+            .filter(|v| !aig.gate_ref(*v as usize).is_dff())
+            .map(|v| ret[v as usize] + 1)
+            .max()
+            .unwrap_or(0);
+    }
+    ret
+}
+
+/// Order of the nodes by level, flip-flops first, and the corresponding levels
+pub(crate) fn level_order(aig: &Network) -> (Vec<u32>, Levelization) {
+    let levels = node_levels(aig);
+    let nb_levels = levels.iter().max().map_or(0, |l| l + 1);
+    let mut starts = vec![0; nb_levels + 1];
+    for l in &levels {
+        starts[l + 1] += 1;
+    }
+    for l in 0..nb_levels {
+        starts[l + 1] += starts[l];
+    }
+    let mut pos = starts.clone();
+    let mut order = vec![0; aig.nb_nodes()];
+    for i in (0..aig.nb_nodes())
+        .filter(|i| aig.gate_ref(*i).is_dff())
+        .chain((0..aig.nb_nodes()).filter(|i| !aig.gate_ref(*i).is_dff()))
+    {
+        order[pos[levels[i]]] = i as u32;
+        pos[levels[i]] += 1;
+    }
+    (order, Levelization { starts })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::network::generators::testcases::random_seq;
+    use crate::sim::simulate;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_levelize() {
+        let mut aig = Network::new();
+        aig.add_inputs(2);
+        let (a, b) = (aig.input(0), aig.input(1));
+        let x = aig.and(a, b);
+        let y = aig.add(Gate::Buf(!x));
+        let z = aig.xor(a, b);
+        let ff = aig.dff(y, Signal::one(), Signal::zero());
+        let w = aig.and(ff, z);
+        aig.add_output(w);
+        aig.add_output(y);
+
+        let (levels, translation) = aig.levelize();
+        assert_eq!(levels.nb_levels(), 2);
+        assert_eq!(levels.nb_nodes(), 5);
+        assert_eq!(levels.level(0), 0..3);
+        assert_eq!(levels.level(1), 3..5);
+        assert!(levels.is_valid(&aig));
+        assert!(aig.is_topo_sorted());
+        // The flip-flop comes first, then the gates that only use inputs and flip-flops
+        assert_eq!(translation[ff.var() as usize], Signal::from_var(0));
+        assert_eq!(
+            levels.level_of(translation[x.var() as usize].var() as usize),
+            0
+        );
+        assert_eq!(
+            levels.level_of(translation[y.var() as usize].var() as usize),
+            1
+        );
+        assert_eq!(
+            levels.level_of(translation[w.var() as usize].var() as usize),
+            1
+        );
+    }
+
+    #[test]
+    fn test_levelize_random() {
+        for seed in 0..4 {
+            let aig = random_seq(6, 4, 100, 5, seed);
+            let mut levelized = aig.clone();
+            let (levels, _) = levelized.levelize();
+            assert!(levels.is_valid(&levelized));
+            let patterns: Vec<Vec<bool>> = (0..5)
+                .map(|t| {
+                    (0..6)
+                        .map(|i| (i * 7 + t * 3 + seed as usize) % 4 == 1)
+                        .collect()
+                })
+                .collect();
+            assert_eq!(simulate(&aig, &patterns), simulate(&levelized, &patterns));
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! Limits on the size of the networks created by transformations
+//!
+//! Some transformations, such as unrolling or flattening, can create networks orders of
+//! magnitude larger than their input. They project the size of their result before allocating
+//! it, and fail with a [`GrowthLimitExceeded`] error if it is larger than the budget.
+
+use std::fmt;
+
+/// Default maximum number of nodes for transformations that can blow up
+pub const DEFAULT_MAX_NODES: usize = 1 << 27;
+
+/// Error returned when a transformation would create a network larger than the budget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrowthLimitExceeded {
+    /// Name of the transformation
+    pub pass: &'static str,
+    /// Projected number of nodes of the result
+    pub projected: usize,
+    /// Maximum number of nodes allowed
+    pub limit: usize,
+}
+
+impl GrowthLimitExceeded {
+    /// Check a projected size against the budget
+    pub fn check(pass: &'static str, projected: usize, limit: usize) -> Result<(), Self> {
+        if projected > limit {
+            Err(GrowthLimitExceeded {
+                pass,
+                projected,
+                limit,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl fmt::Display for GrowthLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} would create {} nodes, more than the limit of {}",
+            self.pass, self.projected, self.limit
+        )
+    }
+}
+
+impl std::error::Error for GrowthLimitExceeded {}
+
+#[cfg(test)]
+mod tests {
+    use super::GrowthLimitExceeded;
+
+    #[test]
+    fn test_check() {
+        assert!(GrowthLimitExceeded::check("unroll", 10, 10).is_ok());
+        let err = GrowthLimitExceeded::check("unroll", 11, 10).unwrap_err();
+        assert_eq!(
+            err,
+            GrowthLimitExceeded {
+                pass: "unroll",
+                projected: 11,
+                limit: 10
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "unroll would create 11 nodes, more than the limit of 10"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Locations of the gates of a network in the original design files
+
+use crate::Signal;
+
+/// Location of a gate in a design file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourceLocation {
+    /// Index of the file in the [`SourceMap`]
+    pub file: u32,
+    /// Line number, starting at 1
+    pub line: u32,
+}
+
+/// Source files and lines of the nodes of a network
+///
+/// As for the [`NameTable`](crate::network::NameTable), the network itself does not store
+/// locations: this side table is only filled by the readers on request, and must be updated with
+/// the translation returned by transformations that renumber nodes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    files: Vec<String>,
+    nodes: Vec<Option<SourceLocation>>,
+}
+
+impl SourceMap {
+    /// Create an empty source map
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// Return whether no location is defined
+    pub fn is_empty(&self) -> bool {
+        self.nodes.iter().all(|l| l.is_none())
+    }
+
+    /// Add a source file, or return the index of an existing one with the same name
+    pub fn add_file(&mut self, name: &str) -> u32 {
+        if let Some(i) = self.files.iter().position(|f| f == name) {
+            return i as u32;
+        }
+        self.files.push(name.to_owned());
+        (self.files.len() - 1) as u32
+    }
+
+    /// Name of a source file
+    pub fn file_name(&self, file: u32) -> &str {
+        &self.files[file as usize]
+    }
+
+    /// Location of the gate of a node, if any
+    pub fn node_location(&self, i: usize) -> Option<SourceLocation> {
+        self.nodes.get(i).copied().flatten()
+    }
+
+    /// Set the location of the gate of a node
+    pub fn set_node_location(&mut self, i: usize, loc: SourceLocation) {
+        if self.nodes.len() <= i {
+            self.nodes.resize(i + 1, None);
+        }
+        self.nodes[i] = Some(loc);
+    }
+
+    /// Location of the gate driving a signal, if any
+    ///
+    /// Inversions are ignored, as they are implicit in the network.
+    pub fn signal_location(&self, s: Signal) -> Option<SourceLocation> {
+        if s.is_var() {
+            self.node_location(s.var() as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Format a location as `file:line`
+    pub fn format(&self, loc: SourceLocation) -> String {
+        format!("{}:{}", self.file_name(loc.file), loc.line)
+    }
+
+    /// Location of the gate of a node formatted as `file:line`, if any
+    pub fn describe_node(&self, i: usize) -> Option<String> {
+        self.node_location(i).map(|l| self.format(l))
+    }
+
+    /// Update the node locations after a transformation that returned a translation
+    ///
+    /// Contrary to names, locations are kept for nodes that are now inverted. When several nodes
+    /// are merged, the location of the first one is kept. Locations of removed nodes are dropped.
+    pub fn remap(&mut self, translation: &[Signal]) {
+        let mut nodes: Vec<Option<SourceLocation>> = Vec::new();
+        for (old, s) in translation.iter().enumerate() {
+            let Some(loc) = self.node_location(old) else {
+                continue;
+            };
+            if !s.is_var() {
+                continue;
+            }
+            let i = s.var() as usize;
+            if nodes.len() <= i {
+                nodes.resize(i + 1, None);
+            }
+            nodes[i].get_or_insert(loc);
+        }
+        self.nodes = nodes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SourceLocation, SourceMap};
+    use crate::Signal;
+
+    #[test]
+    fn test_remap() {
+        let mut locs = SourceMap::new();
+        assert!(locs.is_empty());
+        let f = locs.add_file("a.bench");
+        assert_eq!(locs.add_file("a.bench"), f);
+        for i in 0..4 {
+            locs.set_node_location(
+                i,
+                SourceLocation {
+                    file: f,
+                    line: 10 + i as u32,
+                },
+            );
+        }
+        let translation = [
+            Signal::from_var(1),
+            Signal::from_var(0),
+            !Signal::from_var(2),
+            Signal::from_var(1),
+            Signal::zero(),
+        ];
+        locs.remap(&translation);
+        assert_eq!(locs.describe_node(0).as_deref(), Some("a.bench:11"));
+        assert_eq!(locs.describe_node(1).as_deref(), Some("a.bench:10"));
+        assert_eq!(locs.describe_node(2).as_deref(), Some("a.bench:12"));
+        assert_eq!(locs.node_location(3), None);
+    }
+}
@@ -71,7 +71,7 @@ impl<'a> Matcher<'a> {
             }
             let g_repr = self.pattern.gate(repr.var() as usize);
             let g = aig.gate(s.var() as usize);
-            if !Matcher::gate_type_matches(g_repr, g) {
+            if !Matcher::gate_type_matches(&g_repr, &g) {
                 return false;
             }
             for (&repr_r, &s_r) in zip(g_repr.dependencies(), g.dependencies()) {
@@ -96,7 +96,7 @@ impl<'a> Matcher<'a> {
             (Ternary(_, t1), Ternary(_, t2)) => t1 == t2,
             (Nary(v1, t1), Nary(v2, t2)) => t1 == t2 && v1.len() == v2.len(),
             (Buf(_), Buf(_)) => true,
-            (Dff(_), Dff(_)) => true,
+            (Dff(..), Dff(..)) => true,
             _ => false,
         }
     }
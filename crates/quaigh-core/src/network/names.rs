@@ -0,0 +1,243 @@
+//! Names of the signals of a network, as given in the original design files
+
+use crate::Signal;
+
+/// Names of the primary inputs, primary outputs, internal nets and clocks of a network
+///
+/// The network itself does not store names: this side table is filled by the readers and must be
+/// updated with the translation returned by transformations that renumber nodes.
+/// Unnamed signals get a deterministic generated name based on their index, such as `x12` or `i3`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameTable {
+    inputs: Vec<Option<String>>,
+    nodes: Vec<Option<String>>,
+    outputs: Vec<Option<String>>,
+    clocks: Vec<Option<String>>,
+}
+
+fn get_name(v: &[Option<String>], i: usize) -> Option<&str> {
+    v.get(i).and_then(|n| n.as_deref())
+}
+
+fn set_name(v: &mut Vec<Option<String>>, i: usize, name: &str) {
+    if v.len() <= i {
+        v.resize(i + 1, None);
+    }
+    v[i] = Some(name.to_owned());
+}
+
+impl NameTable {
+    /// Create an empty name table
+    pub fn new() -> NameTable {
+        NameTable::default()
+    }
+
+    /// Return whether no name is defined
+    pub fn is_empty(&self) -> bool {
+        self.inputs
+            .iter()
+            .chain(self.nodes.iter())
+            .chain(self.outputs.iter())
+            .chain(self.clocks.iter())
+            .all(|n| n.is_none())
+    }
+
+    /// Name of a primary input, if any
+    pub fn input_name(&self, i: usize) -> Option<&str> {
+        get_name(&self.inputs, i)
+    }
+
+    /// Name of the net driven by a node, if any
+    pub fn node_name(&self, i: usize) -> Option<&str> {
+        get_name(&self.nodes, i)
+    }
+
+    /// Name of a primary output, if any
+    pub fn output_name(&self, i: usize) -> Option<&str> {
+        get_name(&self.outputs, i)
+    }
+
+    /// Name of a clock, if any
+    pub fn clock_name(&self, i: usize) -> Option<&str> {
+        get_name(&self.clocks, i)
+    }
+
+    /// Set the name of a primary input
+    pub fn set_input_name(&mut self, i: usize, name: &str) {
+        set_name(&mut self.inputs, i, name);
+    }
+
+    /// Set the name of the net driven by a node
+    pub fn set_node_name(&mut self, i: usize, name: &str) {
+        set_name(&mut self.nodes, i, name);
+    }
+
+    /// Set the name of a primary output
+    pub fn set_output_name(&mut self, i: usize, name: &str) {
+        set_name(&mut self.outputs, i, name);
+    }
+
+    /// Set the name of a clock
+    pub fn set_clock_name(&mut self, i: usize, name: &str) {
+        set_name(&mut self.clocks, i, name);
+    }
+
+    /// Insert a primary output name at index i, to follow
+    /// [`Network::insert_output`](crate::Network::insert_output)
+    pub fn insert_output_name(&mut self, i: usize, name: Option<&str>) {
+        if self.outputs.len() < i {
+            self.outputs.resize(i, None);
+        }
+        self.outputs.insert(i, name.map(|n| n.to_owned()));
+    }
+
+    /// Remove the primary output name at index i, to follow
+    /// [`Network::remove_output`](crate::Network::remove_output)
+    pub fn remove_output_name(&mut self, i: usize) {
+        if i < self.outputs.len() {
+            self.outputs.remove(i);
+        }
+    }
+
+    /// Name of a signal, if it is a named input or node
+    ///
+    /// Inversions are ignored, as they are implicit in the network.
+    pub fn signal_name(&self, s: Signal) -> Option<&str> {
+        if s.is_input() {
+            self.input_name(s.input() as usize)
+        } else if s.is_var() {
+            self.node_name(s.var() as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Find the input or node with a given name
+    pub fn find(&self, name: &str) -> Option<Signal> {
+        if let Some(i) = self.inputs.iter().position(|n| n.as_deref() == Some(name)) {
+            return Some(Signal::from_input(i as u32));
+        }
+        if let Some(i) = self.nodes.iter().position(|n| n.as_deref() == Some(name)) {
+            return Some(Signal::from_var(i as u32));
+        }
+        None
+    }
+
+    /// Name of a signal, or a generated name if it is unnamed
+    ///
+    /// Generated names are the usual `x12` or `i3`, with underscores appended if this would
+    /// conflict with an existing name. Constants are named `gnd` and `vdd`.
+    /// Returns whether the name was generated.
+    pub fn name_or_generated(&self, s: Signal) -> (String, bool) {
+        let s = s.without_inversion();
+        if s == Signal::zero() {
+            return ("gnd".to_owned(), false);
+        }
+        if let Some(n) = self.signal_name(s) {
+            return (n.to_owned(), false);
+        }
+        let mut name = s.to_string();
+        while self.find(&name).is_some() {
+            name.push('_');
+        }
+        (name, true)
+    }
+
+    /// Resolve a name obtained from [`name_or_generated`](NameTable::name_or_generated)
+    pub fn resolve(&self, name: &str) -> Option<Signal> {
+        if let Some(s) = self.find(name) {
+            return Some(s);
+        }
+        if name == "gnd" {
+            return Some(Signal::zero());
+        }
+        if name == "vdd" {
+            return Some(Signal::one());
+        }
+        let base = name.trim_end_matches('_');
+        let s = if let Some(i) = base.strip_prefix('x') {
+            Signal::from_var(i.parse().ok()?)
+        } else if let Some(i) = base.strip_prefix('i') {
+            Signal::from_input(i.parse().ok()?)
+        } else {
+            return None;
+        };
+        let (generated, _) = self.name_or_generated(s);
+        if generated == name {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    /// Update the node names after a transformation that returned a translation
+    ///
+    /// Names of nodes that were removed, merged into an existing named node, or that are now
+    /// inverted are dropped.
+    pub fn remap(&mut self, translation: &[Signal]) {
+        let mut nodes = Vec::new();
+        for (old, s) in translation.iter().enumerate() {
+            let Some(name) = self.node_name(old) else {
+                continue;
+            };
+            if !s.is_var() || s.is_inverted() || get_name(&nodes, s.var() as usize).is_some() {
+                continue;
+            }
+            set_name(&mut nodes, s.var() as usize, name);
+        }
+        self.nodes = nodes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NameTable;
+    use crate::Signal;
+
+    #[test]
+    fn test_generated_names() {
+        let mut names = NameTable::new();
+        assert!(names.is_empty());
+        names.set_input_name(0, "a");
+        names.set_node_name(2, "x1");
+        assert_eq!(names.find("a"), Some(Signal::from_input(0)));
+        assert_eq!(names.find("x1"), Some(Signal::from_var(2)));
+        assert_eq!(names.find("x2"), None);
+
+        let cases = [
+            (Signal::from_input(0), "a", false),
+            (Signal::from_input(1), "i1", true),
+            (!Signal::from_var(2), "x1", false),
+            (Signal::from_var(1), "x1_", true),
+            (Signal::from_var(0), "x0", true),
+            (Signal::one(), "gnd", false),
+        ];
+        for (s, expected, generated) in cases {
+            let (name, gen) = names.name_or_generated(s);
+            assert_eq!(name, expected);
+            assert_eq!(gen, generated);
+            assert_eq!(names.resolve(&name), Some(s.without_inversion()));
+        }
+        assert_eq!(names.resolve("i0"), None);
+        assert_eq!(names.resolve("x2"), None);
+    }
+
+    #[test]
+    fn test_remap() {
+        let mut names = NameTable::new();
+        names.set_node_name(0, "a");
+        names.set_node_name(1, "b");
+        names.set_node_name(2, "c");
+        names.set_node_name(3, "d");
+        let translation = [
+            Signal::from_var(1),
+            Signal::from_var(0),
+            !Signal::from_var(2),
+            Signal::from_var(1),
+        ];
+        names.remap(&translation);
+        assert_eq!(names.node_name(0), Some("b"));
+        assert_eq!(names.node_name(1), Some("a"));
+        assert_eq!(names.node_name(2), None);
+    }
+}
@@ -0,0 +1,1207 @@
+use core::{fmt, slice};
+use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
+use std::ops::Range;
+
+use fxhash::{FxHashMap, FxHasher};
+use rand::seq::SliceRandom;
+use volute::Lut;
+
+use crate::network::gates::{
+    BinaryType, ClockId, Gate, GateRef, LutGate, NaryType, Normalization, TernaryType,
+};
+use crate::network::iter::Gates;
+use crate::network::levelize::{level_order, Levelization};
+use crate::network::signal::Signal;
+use crate::util::rng::SeededRng;
+use crate::QuaighError;
+
+/// Role of a primary output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OutputKind {
+    /// Ordinary output
+    #[default]
+    Output,
+    /// Property of the design, that must be 1 at every cycle
+    Assertion,
+    /// Constraint on the environment of the design, assumed to be 1 at every cycle
+    ///
+    /// Counterexamples and test patterns are only searched among the input values that satisfy
+    /// the assumptions, and assumptions are not compared during equivalence checking.
+    Assumption,
+}
+
+/// Gate as stored in a network
+///
+/// The inputs of N-input gates and Luts are a range of the input arena of the network, so that
+/// nodes stay small and their inputs need no allocation of their own. The number of inputs of a
+/// Lut is given by its truth table.
+#[derive(Debug, Clone)]
+pub(super) enum Node {
+    Binary([Signal; 2], BinaryType),
+    Ternary([Signal; 3], TernaryType),
+    Nary { start: u32, len: u32, tp: NaryType },
+    Buf(Signal),
+    Dff([Signal; 3], ClockId),
+    Latch([Signal; 3]),
+    Lut { start: u32, lut: Box<Lut> },
+}
+
+impl Node {
+    /// Returns whether the node is a flip-flop
+    pub(super) fn is_dff(&self) -> bool {
+        matches!(self, Node::Dff(..))
+    }
+
+    /// Returns whether the node is combinatorial
+    pub(super) fn is_comb(&self) -> bool {
+        !matches!(self, Node::Dff(..) | Node::Latch(..))
+    }
+
+    /// Range of the inputs of the node in the arena, if any
+    fn inputs_range(&self) -> Option<Range<usize>> {
+        match self {
+            Node::Nary { start, len, .. } => Some(*start as usize..*start as usize + *len as usize),
+            Node::Lut { start, lut } => Some(*start as usize..*start as usize + lut.num_vars()),
+            _ => None,
+        }
+    }
+}
+
+/// Representation of a logic network as a gate-inverter-graph, used as the main representation for all logic manipulations
+#[derive(Debug, Clone, Default)]
+pub struct Network {
+    nb_inputs: usize,
+    pub(super) nodes: Vec<Node>,
+    /// Inputs of the N-input gates and Luts, referenced by their nodes
+    ///
+    /// Replaced gates leave their inputs behind until the nodes are remapped, unless the inputs of
+    /// the new gate fit in place.
+    arena: Vec<Signal>,
+    outputs: Vec<Signal>,
+    output_kinds: Vec<OutputKind>,
+}
+
+impl Network {
+    /// Create a new network
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the number of primary inputs
+    pub fn nb_inputs(&self) -> usize {
+        self.nb_inputs
+    }
+
+    /// Return the number of primary outputs
+    pub fn nb_outputs(&self) -> usize {
+        self.outputs.len()
+    }
+
+    /// Return the number of nodes in the network
+    pub fn nb_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Get the input at index i
+    pub fn input(&self, i: usize) -> Signal {
+        assert!(i < self.nb_inputs());
+        Signal::from_input(i as u32)
+    }
+
+    /// Get the output at index i
+    pub fn output(&self, i: usize) -> Signal {
+        assert!(i < self.nb_outputs());
+        self.outputs[i]
+    }
+
+    /// Get the variable at index i
+    pub fn node(&self, i: usize) -> Signal {
+        Signal::from_var(i as u32)
+    }
+
+    /// Get a copy of the gate at index i
+    ///
+    /// The inputs of N-input gates and Luts are copied from the network: use
+    /// [`gate_ref`](Network::gate_ref) or [`dependencies`](Network::dependencies) to access them
+    /// without a copy.
+    pub fn gate(&self, i: usize) -> Gate {
+        self.gate_ref(i).to_gate()
+    }
+
+    /// Get the gate at index i, with its inputs borrowed from the network
+    pub fn gate_ref(&self, i: usize) -> GateRef<'_> {
+        match &self.nodes[i] {
+            Node::Binary(s, tp) => GateRef::Binary(s, *tp),
+            Node::Ternary(s, tp) => GateRef::Ternary(s, *tp),
+            Node::Nary { tp, .. } => GateRef::Nary(self.dependencies(i), *tp),
+            Node::Buf(s) => GateRef::Buf(s),
+            Node::Dff(s, clock) => GateRef::Dff(s, *clock),
+            Node::Latch(s) => GateRef::Latch(s),
+            Node::Lut { lut, .. } => GateRef::Lut(self.dependencies(i), lut),
+        }
+    }
+
+    /// Get the signals feeding the gate at index i
+    pub fn dependencies(&self, i: usize) -> &[Signal] {
+        match &self.nodes[i] {
+            Node::Binary(s, _) => s,
+            Node::Ternary(s, _) => s,
+            Node::Buf(s) => slice::from_ref(s),
+            Node::Dff(s, _) => s,
+            Node::Latch(s) => s,
+            n @ (Node::Nary { .. } | Node::Lut { .. }) => &self.arena[n.inputs_range().unwrap()],
+        }
+    }
+
+    /// Get the internal variables feeding the gate at index i (not inputs or constants)
+    fn vars(&self, i: usize) -> impl Iterator<Item = u32> + '_ {
+        self.dependencies(i)
+            .iter()
+            .filter(|s| s.is_var())
+            .map(|s| s.var())
+    }
+
+    /// Write the inputs of a gate to the arena, and return their start
+    ///
+    /// The inputs take the place of those of a replaced gate if they fit.
+    fn store_inputs(&mut self, v: &[Signal], replaced: Option<Range<usize>>) -> u32 {
+        let start = match replaced {
+            Some(r) if v.len() <= r.len() => {
+                self.arena[r.start..r.start + v.len()].copy_from_slice(v);
+                r.start
+            }
+            _ => {
+                let start = self.arena.len();
+                self.arena.extend_from_slice(v);
+                start
+            }
+        };
+        u32::try_from(start).expect("Too many gate inputs in the network")
+    }
+
+    /// Convert a gate to its stored form, with the inputs of N-input gates and Luts in the arena
+    fn store(&mut self, gate: Gate, replaced: Option<Range<usize>>) -> Node {
+        match gate {
+            Gate::Binary(s, tp) => Node::Binary(s, tp),
+            Gate::Ternary(s, tp) => Node::Ternary(s, tp),
+            Gate::Nary(v, tp) => Node::Nary {
+                start: self.store_inputs(&v, replaced),
+                len: u32::try_from(v.len()).expect("Too many inputs for a gate"),
+                tp,
+            },
+            Gate::Buf(s) => Node::Buf(s),
+            Gate::Dff(s, clock) => Node::Dff(s, clock),
+            Gate::Latch(s) => Node::Latch(s),
+            Gate::Lut(lut) => {
+                let LutGate { inputs, lut } = *lut;
+                assert_eq!(
+                    inputs.len(),
+                    lut.num_vars(),
+                    "A Lut must have one input per variable"
+                );
+                Node::Lut {
+                    start: self.store_inputs(&inputs, replaced),
+                    lut: Box::new(lut),
+                }
+            }
+        }
+    }
+
+    /// Replace all the gates, and reclaim the arena space of the previous ones
+    fn set_gates(&mut self, gates: Vec<Gate>) {
+        self.nodes.clear();
+        self.arena.clear();
+        for g in gates {
+            let node = self.store(g, None);
+            self.nodes.push(node);
+        }
+    }
+
+    /// Iterate over the nodes and their gates, in order
+    ///
+    /// ```
+    /// # use quaigh_core::network::generators::adder;
+    /// let aig = adder::ripple_carry(4);
+    /// for (s, g) in aig.iter_gates() {
+    ///     println!("{} = {}", s, g);
+    /// }
+    /// ```
+    pub fn iter_gates(&self) -> Gates<'_> {
+        Gates::new(self, None)
+    }
+
+    /// Iterate over the combinatorial nodes and their gates, in order
+    pub fn iter_comb(&self) -> Gates<'_> {
+        Gates::new(self, Some(Node::is_comb))
+    }
+
+    /// Iterate over the flip-flops and their gates, in order
+    pub fn iter_dffs(&self) -> Gates<'_> {
+        Gates::new(self, Some(Node::is_dff))
+    }
+
+    /// Iterate over the primary outputs, in order
+    pub fn outputs(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = Signal> + ExactSizeIterator + FusedIterator + '_ {
+        self.outputs.iter().copied()
+    }
+
+    /// Add a new primary input
+    pub fn add_input(&mut self) -> Signal {
+        self.nb_inputs += 1;
+        self.input(self.nb_inputs() - 1)
+    }
+
+    /// Add multiple primary inputs
+    pub fn add_inputs(&mut self, nb: usize) {
+        self.nb_inputs += nb;
+    }
+
+    /// Add a new primary output based on an existing literal
+    pub fn add_output(&mut self, l: Signal) {
+        self.outputs.push(l);
+        self.output_kinds.push(OutputKind::Output);
+    }
+
+    /// Add a new output, that must be 1 at every cycle
+    pub fn add_assertion(&mut self, l: Signal) {
+        self.add_output(l);
+        self.set_output_kind(self.nb_outputs() - 1, OutputKind::Assertion);
+    }
+
+    /// Add a new output, that is assumed to be 1 at every cycle
+    pub fn add_assumption(&mut self, l: Signal) {
+        self.add_output(l);
+        self.set_output_kind(self.nb_outputs() - 1, OutputKind::Assumption);
+    }
+
+    /// Get the role of the output at index i
+    pub fn output_kind(&self, i: usize) -> OutputKind {
+        self.output_kinds[i]
+    }
+
+    /// Change the role of the output at index i
+    pub fn set_output_kind(&mut self, i: usize, kind: OutputKind) {
+        self.output_kinds[i] = kind;
+    }
+
+    /// Indices of the outputs that are assertions
+    pub fn assertions(&self) -> Vec<usize> {
+        (0..self.nb_outputs())
+            .filter(|o| self.output_kind(*o) == OutputKind::Assertion)
+            .collect()
+    }
+
+    /// Indices of the outputs that are assumptions
+    pub fn assumptions(&self) -> Vec<usize> {
+        (0..self.nb_outputs())
+            .filter(|o| self.output_kind(*o) == OutputKind::Assumption)
+            .collect()
+    }
+
+    /// Return whether some outputs are assumptions
+    pub fn has_assumptions(&self) -> bool {
+        self.output_kinds.contains(&OutputKind::Assumption)
+    }
+
+    /// Add the logic computing whether all assumptions hold at the current cycle, and return its
+    /// signal
+    pub fn assumptions_hold(&mut self) -> Signal {
+        let assumptions: Vec<Signal> = self
+            .assumptions()
+            .into_iter()
+            .map(|o| self.output(o))
+            .collect();
+        self.add_canonical(Gate::andn(&assumptions))
+    }
+
+    /// Add the logic computing whether all assumptions held at every cycle so far, including the
+    /// current one, and return its signal
+    ///
+    /// A flip-flop records whether an assumption failed at a previous cycle. Properties that only
+    /// need to hold under the assumptions can be checked as `!held | property`.
+    pub fn assumptions_held(&mut self) -> Signal {
+        let hold = self.assumptions_hold();
+        if hold.is_constant() {
+            return hold;
+        }
+        let failed = self.add(Gate::dff(Signal::zero(), Signal::one(), Signal::zero()));
+        let failed_next = !self.and(!failed, hold);
+        self.replace(
+            failed.var() as usize,
+            Gate::dff(failed_next, Signal::one(), Signal::zero()),
+        );
+        self.and(hold, !failed)
+    }
+
+    /// Replace the primary output at index i
+    ///
+    /// As for [`add_output`](Network::add_output), the signal is only validated by
+    /// [`check`](Network::check); use [`is_valid`](Network::is_valid) beforehand if needed.
+    pub fn set_output(&mut self, i: usize, l: Signal) {
+        assert!(i < self.nb_outputs());
+        self.outputs[i] = l;
+    }
+
+    /// Insert a new primary output at index i
+    ///
+    /// The following outputs are shifted by one: output names, simulation results and expected
+    /// values must be shifted accordingly, for example with
+    /// [`NameTable::insert_output_name`](crate::network::NameTable::insert_output_name).
+    pub fn insert_output(&mut self, i: usize, l: Signal) {
+        assert!(i <= self.nb_outputs());
+        self.outputs.insert(i, l);
+        self.output_kinds.insert(i, OutputKind::Output);
+    }
+
+    /// Remove the primary output at index i, and return its signal
+    ///
+    /// The following outputs are shifted by one: output names, simulation results and expected
+    /// values must be shifted accordingly, for example with
+    /// [`NameTable::remove_output_name`](crate::network::NameTable::remove_output_name).
+    /// The logic driving the output is kept until the next [`cleanup`](Network::cleanup).
+    pub fn remove_output(&mut self, i: usize) -> Signal {
+        assert!(i < self.nb_outputs());
+        self.output_kinds.remove(i);
+        self.outputs.remove(i)
+    }
+
+    /// Create an And2 gate
+    pub fn and(&mut self, a: Signal, b: Signal) -> Signal {
+        self.add_canonical(Gate::and(a, b))
+    }
+
+    /// Create a Xor2 gate
+    pub fn xor(&mut self, a: Signal, b: Signal) -> Signal {
+        self.add_canonical(Gate::xor(a, b))
+    }
+
+    /// Create a Dff gate (flip flop)
+    pub fn dff(&mut self, data: Signal, enable: Signal, reset: Signal) -> Signal {
+        self.add_canonical(Gate::dff(data, enable, reset))
+    }
+
+    /// Create a transparent Latch gate
+    pub fn latch(&mut self, data: Signal, enable: Signal, reset: Signal) -> Signal {
+        self.add_canonical(Gate::latch(data, enable, reset))
+    }
+
+    /// Add a new gate, and make it canonical. The gate may be simplified immediately
+    pub fn add_canonical(&mut self, gate: Gate) -> Signal {
+        use Normalization::*;
+        let g = gate.make_canonical();
+        match g {
+            Copy(l) => l,
+            Node(g, inv) => self.add(g) ^ inv,
+        }
+    }
+
+    /// Add a new gate
+    pub fn add(&mut self, gate: Gate) -> Signal {
+        let l = Signal::from_var(self.nodes.len() as u32);
+        let node = self.store(gate, None);
+        self.nodes.push(node);
+        l
+    }
+
+    /// Replace an existing gate
+    pub fn replace(&mut self, i: usize, gate: Gate) -> Signal {
+        let l = Signal::from_var(i as u32);
+        let replaced = self.nodes[i].inputs_range();
+        self.nodes[i] = self.store(gate, replaced);
+        l
+    }
+
+    /// Return whether the network is purely combinatorial
+    pub fn is_comb(&self) -> bool {
+        self.nodes.iter().all(|g| g.is_comb())
+    }
+
+    /// Return whether the network contains transparent latches
+    pub fn has_latches(&self) -> bool {
+        self.nodes.iter().any(|g| matches!(g, Node::Latch(..)))
+    }
+
+    /// Replace each transparent latch by a flip-flop holding its previous value and the logic
+    /// selecting between the two; this will invalidate all signals if there are latches
+    ///
+    /// The behaviour is unchanged, and the result can be handled by all algorithms that only
+    /// support flip-flops. Returns the mapping of old variable indices to signals, if needed.
+    pub fn lower_latches(&mut self) -> Box<[Signal]> {
+        if !self.has_latches() {
+            return (0..self.nb_nodes())
+                .map(|i| Signal::from_var(i as u32))
+                .collect();
+        }
+        for i in 0..self.nb_nodes() {
+            if let Node::Latch([d, en, res]) = self.nodes[i] {
+                let prev = self.add(Gate::dff(self.node(i), Signal::one(), Signal::zero()));
+                let mx = self.add(Gate::mux(en, d, prev));
+                self.replace(i, Gate::and(mx, !res));
+            }
+        }
+        self.topo_sort()
+    }
+
+    /// Replace the reset of each flip-flop by explicit logic on its data and enable
+    ///
+    /// This is used before exporting to formats without reset support. The behaviour is
+    /// unchanged, and the new gates are added at the end of the network, so that existing signals
+    /// remain valid.
+    pub fn expand_resets(&mut self) {
+        for i in 0..self.nb_nodes() {
+            if let Node::Dff([d, en, res], clock) = self.nodes[i] {
+                if res == Signal::zero() {
+                    continue;
+                }
+                let d = self.and(d, !res);
+                let en = !self.and(!en, !res);
+                self.replace(i, Gate::dff_with_clock(d, en, Signal::zero(), clock));
+            }
+        }
+    }
+
+    /// Return the clock domains of the flip-flops, sorted
+    pub fn clock_domains(&self) -> Vec<ClockId> {
+        let mut ret: Vec<ClockId> = self
+            .nodes
+            .iter()
+            .filter_map(|g| match g {
+                Node::Dff(_, clock) => Some(*clock),
+                _ => None,
+            })
+            .collect();
+        ret.sort();
+        ret.dedup();
+        ret
+    }
+
+    /// Return the logic level of each node, with unit delays
+    ///
+    /// See [`depth::levels`](crate::network::depth::levels).
+    pub fn levels(&self) -> Vec<usize> {
+        crate::network::depth::levels(self)
+    }
+
+    /// Sort the nodes by level, so that each level is a contiguous range; this will invalidate
+    /// all signals
+    ///
+    /// See [`Levelization`](crate::network::Levelization). The network must be topologically sorted.
+    /// Returns the levels, and the mapping of old variable indices to signals, if needed.
+    pub fn levelize(&mut self) -> (Levelization, Box<[Signal]>) {
+        let (order, levels) = level_order(self);
+        let translation = self.remap(&order);
+        (levels, translation)
+    }
+
+    /// Return whether the network is already topologically sorted (except for flip-flops)
+    ///
+    /// Transparent latches must come after their inputs, as combinatorial gates.
+    pub fn is_topo_sorted(&self) -> bool {
+        for (i, g) in self.nodes.iter().enumerate() {
+            let ind = i as u32;
+            if !g.is_dff() {
+                for v in self.vars(i) {
+                    if v >= ind {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Remap nodes; there may be holes in the translation
+    fn remap(&mut self, order: &[u32]) -> Box<[Signal]> {
+        // Create the translation
+        let mut translation = vec![Signal::zero(); self.nb_nodes()];
+        for (new_i, old_i) in order.iter().enumerate() {
+            translation[*old_i as usize] = Signal::from_var(new_i as u32);
+        }
+
+        // Remap the nodes
+        let mut new_nodes = Vec::new();
+        for o in order {
+            let i = *o as usize;
+            let g = self.gate(i);
+            assert!(translation[i].is_var());
+            assert_eq!(translation[i].var(), new_nodes.len() as u32);
+            new_nodes.push(g.remap_order(translation.as_slice()));
+        }
+        self.set_gates(new_nodes);
+
+        // Remap the outputs
+        self.remap_outputs(&translation);
+        translation.into()
+    }
+
+    /// Shuffle the network randomly; this will invalidate all signals
+    ///
+    /// Returns the mapping of old variable indices to signals, if needed.
+    pub fn shuffle(&mut self, seed: u64) -> Box<[Signal]> {
+        let mut rng = SeededRng::new(seed);
+        let mut order: Vec<u32> = (0..self.nb_nodes() as u32).collect();
+        order.shuffle(&mut rng);
+        self.remap(&order);
+        self.topo_sort()
+    }
+
+    /// Remap outputs
+    fn remap_outputs(&mut self, translation: &[Signal]) {
+        let new_outputs = self
+            .outputs
+            .iter()
+            .map(|s| s.remap_order(translation))
+            .collect();
+        self.outputs = new_outputs;
+    }
+
+    /// Remove unused logic; this will invalidate all signals
+    ///
+    /// Returns the mapping of old variable indices to signals, if needed.
+    /// Removed signals are mapped to zero.
+    pub fn cleanup(&mut self) -> Box<[Signal]> {
+        // Mark unused logic
+        let mut visited = vec![false; self.nb_nodes()];
+        let mut to_visit = Vec::<u32>::new();
+        for o in 0..self.nb_outputs() {
+            let output = self.output(o);
+            if output.is_var() {
+                to_visit.push(output.var());
+            }
+        }
+        while !to_visit.is_empty() {
+            let node = to_visit.pop().unwrap() as usize;
+            if visited[node] {
+                continue;
+            }
+            visited[node] = true;
+            to_visit.extend(self.vars(node));
+        }
+
+        // Now compute a mapping for all nodes that are reachable
+        let mut order = Vec::new();
+        for (i, v) in visited.iter().enumerate() {
+            if *v {
+                order.push(i as u32);
+            }
+        }
+        self.remap(order.as_slice())
+    }
+
+    /// Remove duplicate logic and make all gates canonical; this will invalidate all signals
+    ///
+    /// Canonical gates are And, Xor, Mux, Maj and Lut. Everything else will be simplified.
+    /// Returns the mapping of old variable indices to signals, if needed.
+    pub fn make_canonical(&mut self) -> Box<[Signal]> {
+        self.dedup(true)
+    }
+
+    /// Remove duplicate logic; this will invalidate all signals
+    ///
+    /// Returns the mapping of old variable indices to signals, if needed.
+    pub fn deduplicate(&mut self) -> Box<[Signal]> {
+        self.dedup(false)
+    }
+
+    /// Remove duplicate logic. Optionally make all gates canonical
+    fn dedup(&mut self, make_canonical: bool) -> Box<[Signal]> {
+        // Replace each node, in turn, by a simplified version or an equivalent existing node
+        // We need the network to be topologically sorted, so that the gate inputs are already replaced
+        // Dff gates are an exception to the sorting, and are handled separately
+        assert!(self.is_topo_sorted());
+        let mut translation = (0..self.nb_nodes())
+            .map(|i| Signal::from_var(i as u32))
+            .collect::<Vec<Signal>>();
+
+        /// Core function for deduplication
+        fn dedup_node(
+            g: Gate,
+            h: &mut GateTable,
+            nodes: &mut Vec<Gate>,
+            make_canonical: bool,
+        ) -> Signal {
+            let normalized = if make_canonical {
+                g.make_canonical()
+            } else {
+                Normalization::Node(g, false)
+            };
+            match normalized {
+                Normalization::Copy(sig) => sig,
+                Normalization::Node(g, inv) => Signal::from_var(h.find_or_insert(g, nodes)) ^ inv,
+            }
+        }
+
+        let mut hsh = GateTable::with_capacity(self.nb_nodes());
+        let mut new_nodes = Vec::with_capacity(self.nb_nodes());
+
+        // Dedup flip flops
+        for i in 0..self.nb_nodes() {
+            if self.nodes[i].is_dff() {
+                let g = self.gate_ref(i).to_gate();
+                translation[i] = dedup_node(g, &mut hsh, &mut new_nodes, make_canonical);
+            }
+        }
+
+        // Remap and dedup combinatorial gates
+        for i in 0..self.nb_nodes() {
+            if !self.nodes[i].is_dff() {
+                let g = self.gate_ref(i).remap_order(translation.as_slice());
+                translation[i] = dedup_node(g, &mut hsh, &mut new_nodes, make_canonical);
+            }
+        }
+
+        // Remap flip flops
+        for i in 0..new_nodes.len() {
+            if new_nodes[i].is_dff() {
+                new_nodes[i] = new_nodes[i].remap_order(translation.as_slice());
+            }
+        }
+
+        self.set_gates(new_nodes);
+        self.remap_outputs(&translation);
+        self.check();
+        translation.into()
+    }
+
+    /// Topologically sort the network; this will invalidate all signals
+    ///
+    /// Ordering may be changed even if already sorted. Flip-flop ordering is kept as is.
+    /// Returns the mapping of old variable indices to signals, if needed.
+    pub fn topo_sort(&mut self) -> Box<[Signal]> {
+        match self.try_topo_sort() {
+            Ok(t) => t,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Topologically sort the network; this will invalidate all signals
+    ///
+    /// Returns an error instead of panicking if there is a combinatorial loop,
+    /// in which case the network is left untouched.
+    pub fn try_topo_sort(&mut self) -> Result<Box<[Signal]>, QuaighError> {
+        // Count the output dependencies of each gate
+        let mut count_deps = vec![0u32; self.nb_nodes()];
+        for (i, g) in self.nodes.iter().enumerate() {
+            if !g.is_dff() {
+                for v in self.vars(i) {
+                    count_deps[v as usize] += 1;
+                }
+            }
+        }
+
+        // Compute the topological sort
+        let mut rev_order: Vec<u32> = Vec::new();
+        let mut visited = vec![false; self.nb_nodes()];
+
+        // Handle Dff separately so they are not reordered
+        for i in 0..self.nb_nodes() {
+            if self.nodes[i].is_dff() {
+                visited[i] = true;
+            }
+        }
+
+        // Start with gates with no dependencies
+        let mut to_visit: Vec<u32> = (0..self.nb_nodes())
+            .filter(|v| count_deps[*v] == 0 && !visited[*v])
+            .map(|v| v as u32)
+            .collect();
+        while let Some(v) = to_visit.pop() {
+            // TODO: allow for some randomness here
+            // Visit the gate and mark the gates with satisfied dependencies
+            if visited[v as usize] {
+                continue;
+            }
+            visited[v as usize] = true;
+            rev_order.push(v);
+            if !self.nodes[v as usize].is_dff() {
+                for d in self.vars(v as usize) {
+                    count_deps[d as usize] -= 1;
+                    if count_deps[d as usize] == 0 {
+                        to_visit.push(d);
+                    }
+                }
+            }
+        }
+
+        // Add Dff first to the order (first, so last in the reversed order)
+        for i in (0..self.nb_nodes()).rev() {
+            if self.nodes[i].is_dff() {
+                rev_order.push(i as u32);
+            }
+        }
+
+        if rev_order.len() != self.nb_nodes() {
+            return Err(QuaighError::InvalidNetwork(
+                "Unable to find a valid topological sort: there must be a combinatorial loop"
+                    .to_owned(),
+            ));
+        }
+        rev_order.reverse();
+        let order = rev_order;
+
+        Ok(self.remap(order.as_slice()))
+    }
+
+    /// Find a combinatorial loop, if any
+    ///
+    /// Returns the nodes on the loop, each of them using the next one as input and the last one
+    /// using the first.
+    pub fn find_comb_loop(&self) -> Option<Vec<usize>> {
+        // 0 for unvisited nodes, 1 for nodes on the current path, 2 for finished nodes
+        let mut state = vec![0u8; self.nb_nodes()];
+        for root in 0..self.nb_nodes() {
+            if state[root] != 0 {
+                continue;
+            }
+            // Depth-first search, with the index of the next dependency to explore
+            let mut path: Vec<(usize, usize)> = vec![(root, 0)];
+            state[root] = 1;
+            while let Some((v, next)) = path.last_mut() {
+                let deps = if self.nodes[*v].is_dff() {
+                    &[]
+                } else {
+                    self.dependencies(*v)
+                };
+                let Some(i) = (*next..deps.len()).find(|i| deps[*i].is_var()) else {
+                    state[*v] = 2;
+                    path.pop();
+                    continue;
+                };
+                *next = i + 1;
+                let d = deps[i].var() as usize;
+                match state[d] {
+                    0 => {
+                        state[d] = 1;
+                        path.push((d, 0));
+                    }
+                    1 => {
+                        let start = path.iter().position(|(u, _)| *u == d).unwrap();
+                        return Some(path[start..].iter().map(|(u, _)| *u).collect());
+                    }
+                    _ => (),
+                }
+            }
+        }
+        None
+    }
+
+    /// Check consistency of the datastructure
+    pub fn check(&self) {
+        if let Err(e) = self.try_check() {
+            panic!("{}", e);
+        }
+    }
+
+    /// Check consistency of the datastructure
+    ///
+    /// Returns an error instead of panicking if a signal is out of bounds or if the network is not
+    /// topologically sorted.
+    pub fn try_check(&self) -> Result<(), QuaighError> {
+        for i in 0..self.nb_nodes() {
+            for v in self.dependencies(i) {
+                if !self.is_valid(*v) {
+                    return Err(QuaighError::InvalidNetwork(format!(
+                        "Invalid signal {v} in gate {i}"
+                    )));
+                }
+            }
+        }
+        for i in 0..self.nb_outputs() {
+            let v = self.output(i);
+            if !self.is_valid(v) {
+                return Err(QuaighError::InvalidNetwork(format!("Invalid output {v}")));
+            }
+        }
+        if !self.is_topo_sorted() {
+            return Err(QuaighError::InvalidNetwork(
+                "The network is not topologically sorted".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns whether a signal is valid (within bounds) in the network
+    pub fn is_valid(&self, s: Signal) -> bool {
+        if s.is_input() {
+            s.input() < self.nb_inputs() as u32
+        } else if s.is_var() {
+            s.var() < self.nb_nodes() as u32
+        } else {
+            true
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Network with {} inputs, {} outputs:",
+            self.nb_inputs(),
+            self.nb_outputs()
+        )?;
+        for (s, g) in self.iter_gates() {
+            writeln!(f, "\t{} = {}", s, g)?;
+        }
+        for i in 0..self.nb_outputs() {
+            match self.output_kind(i) {
+                OutputKind::Output => writeln!(f, "\to{} = {}", i, self.output(i))?,
+                OutputKind::Assertion => writeln!(f, "\to{} = {} (assertion)", i, self.output(i))?,
+                OutputKind::Assumption => {
+                    writeln!(f, "\to{} = {} (assumption)", i, self.output(i))?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Hash table of the gates of a network, used for deduplication
+///
+/// The gates are not owned by the table: it only stores their indices, so that each gate is hashed
+/// once and never cloned. Gates with the same hash are chained together.
+struct GateTable {
+    heads: FxHashMap<u64, u32>,
+    next: Vec<u32>,
+}
+
+impl GateTable {
+    const NONE: u32 = u32::MAX;
+
+    fn with_capacity(capacity: usize) -> GateTable {
+        GateTable {
+            heads: FxHashMap::with_capacity_and_hasher(capacity, Default::default()),
+            next: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Return the index of an identical gate, or push it to the nodes
+    fn find_or_insert(&mut self, g: Gate, nodes: &mut Vec<Gate>) -> u32 {
+        let mut hasher = FxHasher::default();
+        g.hash(&mut hasher);
+        let head = self.heads.entry(hasher.finish()).or_insert(Self::NONE);
+        let mut i = *head;
+        while i != Self::NONE {
+            if nodes[i as usize] == g {
+                return i;
+            }
+            i = self.next[i as usize];
+        }
+        let ret = nodes.len() as u32;
+        debug_assert_eq!(self.next.len(), nodes.len());
+        self.next.push(*head);
+        *head = ret;
+        nodes.push(g);
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use volute::Lut;
+
+    use super::{Node, OutputKind};
+    use crate::network::{GateRef, NameTable, NaryType};
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_basic() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x = aig.xor(i0, i1);
+        aig.add_output(x);
+
+        // Basic properties
+        assert_eq!(aig.nb_inputs(), 2);
+        assert_eq!(aig.nb_outputs(), 1);
+        assert_eq!(aig.nb_nodes(), 1);
+        assert!(aig.is_comb());
+        assert!(aig.is_topo_sorted());
+
+        // Access
+        assert_eq!(aig.input(0), i0);
+        assert_eq!(aig.input(1), i1);
+        assert_eq!(aig.output(0), x);
+    }
+
+    #[test]
+    fn test_iterators() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x = aig.and(i0, i1);
+        let d = aig.dff(x, Signal::one(), Signal::zero());
+        let y = aig.xor(d, i0);
+        aig.add_output(y);
+        aig.add_output(!d);
+
+        let gates: Vec<Signal> = aig.iter_gates().map(|(s, _)| s).collect();
+        assert_eq!(gates, vec![x, d, y]);
+        assert_eq!(aig.iter_gates().len(), 3);
+
+        let mut comb = aig.iter_comb();
+        assert_eq!(comb.len(), 2);
+        assert_eq!(comb.next_back().map(|(s, _)| s), Some(y));
+        assert_eq!(comb.len(), 1);
+        assert_eq!(comb.next().map(|(s, _)| s), Some(x));
+        assert_eq!(comb.next(), None);
+        assert_eq!(comb.len(), 0);
+
+        let dffs: Vec<_> = aig.iter_dffs().collect();
+        assert_eq!(dffs.len(), 1);
+        assert_eq!(dffs[0], (d, aig.gate(1)));
+
+        assert_eq!(aig.outputs().rev().collect::<Vec<_>>(), vec![!d, y]);
+        assert_eq!(aig.outputs().len(), 2);
+    }
+
+    /// Check that the size used for the nodes does not increase
+    #[test]
+    fn test_node_size() {
+        assert!(std::mem::size_of::<Node>() <= 4 * std::mem::size_of::<Signal>());
+    }
+
+    #[test]
+    fn test_nary_inputs() {
+        let mut aig = Network::default();
+        aig.add_inputs(4);
+        let v: Vec<Signal> = (0..4).map(|i| aig.input(i)).collect();
+        aig.add(Gate::Nary(v.clone().into(), NaryType::And));
+        let y = aig.add(Gate::Nary(v[1..].into(), NaryType::Xor));
+        aig.add_output(y);
+        assert_eq!(aig.dependencies(0), v.as_slice());
+        assert_eq!(aig.gate(1), Gate::Nary(v[1..].into(), NaryType::Xor));
+
+        // Smaller gates reuse the inputs of the gate they replace
+        aig.replace(0, Gate::Nary([v[0], !v[3]].into(), NaryType::Or));
+        assert_eq!(aig.dependencies(0), [v[0], !v[3]]);
+        assert_eq!(aig.dependencies(1), &v[1..]);
+        assert_eq!(aig.arena.len(), 7);
+
+        // Larger gates keep the previous inputs in the arena until the nodes are remapped
+        aig.replace(1, Gate::Nary(v.clone().into(), NaryType::Xor));
+        assert_eq!(aig.dependencies(0), [v[0], !v[3]]);
+        assert_eq!(aig.dependencies(1), v.as_slice());
+        assert_eq!(aig.arena.len(), 11);
+        aig.cleanup();
+        assert_eq!(aig.nb_nodes(), 1);
+        assert_eq!(aig.dependencies(0), v.as_slice());
+        assert_eq!(aig.arena.len(), 4);
+    }
+
+    #[test]
+    fn test_lut_inputs() {
+        let mut aig = Network::default();
+        aig.add_inputs(4);
+        let v: Vec<Signal> = (0..4).map(|i| aig.input(i)).collect();
+        let lut = Gate::lut(&v[..3], Lut::nth_var(3, 1));
+        aig.add(Gate::Nary(v.clone().into(), NaryType::And));
+        let y = aig.add(lut.clone());
+        aig.add_output(y);
+        assert_eq!(aig.dependencies(1), &v[..3]);
+        assert_eq!(aig.gate(1), lut);
+        assert!(matches!(aig.gate_ref(1), GateRef::Lut(d, _) if d == &v[..3]));
+        assert_eq!(aig.arena.len(), 7);
+
+        // Luts reuse the inputs of the gate they replace
+        let lut = Gate::lut(&[v[3], v[0]], Lut::nth_var(2, 0));
+        aig.replace(0, lut.clone());
+        assert_eq!(aig.gate(0), lut);
+        assert_eq!(aig.dependencies(1), &v[..3]);
+        assert_eq!(aig.arena.len(), 7);
+        aig.cleanup();
+        assert_eq!(aig.nb_nodes(), 1);
+        assert_eq!(aig.dependencies(0), &v[..3]);
+        assert_eq!(aig.arena.len(), 3);
+    }
+
+    #[test]
+    fn test_dff() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let c0 = Signal::zero();
+        let c1 = Signal::one();
+        // Useful Dff
+        assert_eq!(aig.dff(i0, i1, i2), Signal::from_var(0));
+        assert_eq!(aig.dff(i0, i1, c0), Signal::from_var(1));
+        // Dff that reduces to 0
+        assert_eq!(aig.dff(c0, i1, i2), c0);
+        assert_eq!(aig.dff(i0, c0, i2), c0);
+        assert_eq!(aig.dff(i0, i1, c1), c0);
+        assert!(!aig.is_comb());
+        assert!(aig.is_topo_sorted());
+    }
+
+    #[test]
+    fn test_sweep() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x0 = aig.and(i0, i1);
+        let x1 = !aig.and(!i0, !i1);
+        let _ = aig.and(x0, i1);
+        let x3 = !aig.and(!x1, !i1);
+        aig.add_output(x3);
+        let t = aig.cleanup();
+        assert_eq!(t.len(), 4);
+        assert_eq!(aig.nb_nodes(), 2);
+        assert_eq!(aig.nb_outputs(), 1);
+        assert_eq!(
+            t,
+            vec![
+                Signal::zero(),
+                Signal::from_var(0),
+                Signal::zero(),
+                Signal::from_var(1)
+            ]
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let x0 = aig.and(i0, i1);
+        let x0_s = aig.and(i0, i1);
+        let x1 = aig.and(x0, i2);
+        let x1_s = aig.and(x0_s, i2);
+        aig.add_output(x1);
+        aig.add_output(x1_s);
+        aig.make_canonical();
+        assert_eq!(aig.nb_nodes(), 2);
+    }
+
+    #[test]
+    fn test_topo_sort() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let x0 = Gate::dff(i2, Signal::one(), Signal::zero());
+        let x1 = Gate::dff(i1, Signal::one(), Signal::zero());
+        let x2 = Gate::dff(i0, Signal::one(), Signal::zero());
+        let x3 = Gate::dff(i2, i1, Signal::zero());
+        aig.add(x0.clone());
+        aig.add(x1.clone());
+        aig.add(x2.clone());
+        aig.add(x3.clone());
+        aig.topo_sort();
+        assert_eq!(aig.nb_nodes(), 4);
+        assert_eq!(aig.gate(0), x0);
+        assert_eq!(aig.gate(1), x1);
+        assert_eq!(aig.gate(2), x2);
+        assert_eq!(aig.gate(3), x3);
+    }
+
+    #[test]
+    fn test_edit_outputs() {
+        let mut aig = Network::default();
+        aig.add_inputs(4);
+        let mut names = NameTable::new();
+        for i in 0..4 {
+            aig.add_output(aig.input(i));
+            names.set_output_name(i, &format!("o{i}"));
+        }
+
+        // Removal in the middle shifts the following outputs and their names
+        assert_eq!(aig.remove_output(1), aig.input(1));
+        names.remove_output_name(1);
+        assert_eq!(aig.nb_outputs(), 3);
+        assert_eq!(aig.output(1), aig.input(2));
+        assert_eq!(names.output_name(1), Some("o2"));
+
+        // Insertion keeps the order of the other outputs
+        aig.insert_output(1, !aig.input(1));
+        names.insert_output_name(1, Some("o1_n"));
+        aig.insert_output(4, Signal::one());
+        names.insert_output_name(4, None);
+        let outputs: Vec<Signal> = (0..aig.nb_outputs()).map(|o| aig.output(o)).collect();
+        assert_eq!(
+            outputs,
+            vec![
+                aig.input(0),
+                !aig.input(1),
+                aig.input(2),
+                aig.input(3),
+                Signal::one()
+            ]
+        );
+        let output_names: Vec<Option<&str>> = (0..5).map(|o| names.output_name(o)).collect();
+        assert_eq!(
+            output_names,
+            vec![Some("o0"), Some("o1_n"), Some("o2"), Some("o3"), None]
+        );
+
+        aig.set_output(4, aig.input(3));
+        assert_eq!(aig.output(4), aig.input(3));
+        aig.check();
+    }
+
+    #[test]
+    fn test_output_kinds() {
+        let mut aig = Network::default();
+        aig.add_inputs(3);
+        aig.add_output(aig.input(0));
+        aig.add_assumption(aig.input(1));
+        aig.add_assertion(!aig.input(2));
+        assert_eq!(aig.assumptions(), vec![1]);
+        assert_eq!(aig.assertions(), vec![2]);
+        assert!(aig.to_string().contains("o1 = i1 (assumption)"));
+
+        // Kinds follow the outputs when they are edited
+        aig.insert_output(0, Signal::one());
+        assert_eq!(aig.output_kind(0), OutputKind::Output);
+        assert_eq!(aig.assumptions(), vec![2]);
+        aig.remove_output(2);
+        assert!(!aig.has_assumptions());
+        aig.set_output_kind(1, OutputKind::Assumption);
+        assert_eq!(aig.assumptions(), vec![1]);
+        assert_eq!(aig.assumptions_hold(), aig.input(0));
+
+        // The assumptions held so far are recorded in a flip-flop
+        let held = aig.assumptions_held();
+        assert_eq!(aig.nb_nodes(), 3);
+        assert!(!aig.gate_ref(0).is_comb());
+        assert!(held.is_var());
+        aig.check();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid output")]
+    fn test_set_invalid_output() {
+        let mut aig = Network::default();
+        aig.add_inputs(2);
+        aig.add_output(aig.input(0));
+        assert!(!aig.is_valid(Signal::from_input(2)));
+        aig.set_output(0, Signal::from_input(2));
+        aig.check();
+    }
+
+    #[test]
+    fn test_find_comb_loop() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let x0 = Signal::from_var(0);
+        let x1 = Signal::from_var(1);
+        let x2 = Signal::from_var(2);
+        aig.add(Gate::and(i0, x2));
+        aig.add(Gate::Buf(!x0));
+        aig.add(Gate::dff(x1, Signal::one(), Signal::zero()));
+        // Broken by the flip-flop
+        assert_eq!(aig.find_comb_loop(), None);
+        aig.add(Gate::and(x1, i0));
+        aig.replace(2, Gate::and(x0, Signal::from_var(3)));
+        let mut l = aig.find_comb_loop().unwrap();
+        l.sort();
+        assert_eq!(l, vec![0, 2]);
+    }
+}
@@ -74,7 +74,7 @@ impl Signal {
     }
 
     /// Clear the inversion, if set
-    pub(crate) fn without_inversion(&self) -> Signal {
+    pub fn without_inversion(&self) -> Signal {
         Signal { a: self.a & !1u32 }
     }
 
@@ -91,6 +91,11 @@ impl Signal {
         self.a
     }
 
+    /// Create a signal from its internal representation, as returned by [`Signal::raw`]
+    pub fn from_raw(a: u32) -> Signal {
+        Signal { a }
+    }
+
     /// Apply a remapping of variable order to the signal
     pub(crate) fn remap_order(&self, t: &[Signal]) -> Signal {
         if !self.is_var() {
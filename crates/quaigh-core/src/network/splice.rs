@@ -0,0 +1,266 @@
+//! Replacement of the logic of a network by another network
+
+use std::fmt;
+
+use crate::{Gate, Network, Signal};
+
+/// Error returned when a network cannot be spliced into another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpliceError {
+    /// The binding does not have one signal per input of the replacement
+    BindingLength {
+        /// Number of inputs of the replacement
+        expected: usize,
+        /// Number of signals in the binding
+        found: usize,
+    },
+    /// A signal of the binding is not valid in the network
+    InvalidSignal(Signal),
+    /// The root is not a node of the network
+    InvalidRoot(usize),
+    /// The replacement does not have exactly one output
+    OutputCount(usize),
+    /// The replacement does not have one output per root of the window
+    OutputLength {
+        /// Number of roots of the window
+        expected: usize,
+        /// Number of outputs of the replacement
+        found: usize,
+    },
+    /// A signal of the binding depends on the root, which would create a combinatorial loop
+    Loop(Signal),
+}
+
+impl fmt::Display for SpliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpliceError::BindingLength { expected, found } => write!(
+                f,
+                "Binding has {} signals, but the replacement has {} inputs",
+                found, expected
+            ),
+            SpliceError::InvalidSignal(s) => write!(f, "Invalid signal {} in the binding", s),
+            SpliceError::InvalidRoot(i) => write!(f, "Invalid root node {}", i),
+            SpliceError::OutputCount(n) => {
+                write!(f, "Replacement has {} outputs, expected exactly one", n)
+            }
+            SpliceError::OutputLength { expected, found } => write!(
+                f,
+                "Replacement has {} outputs, but the window has {} roots",
+                found, expected
+            ),
+            SpliceError::Loop(s) => write!(
+                f,
+                "Signal {} of the binding depends on the root, creating a loop",
+                s
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SpliceError {}
+
+impl Network {
+    /// Copy the gates of another network, with its inputs bound to signals of this network
+    ///
+    /// Returns the signals of the outputs of the other network. Gates are copied as is, so that
+    /// sequential networks can be inserted too: use [`make_canonical`](Network::make_canonical)
+    /// and [`cleanup`](Network::cleanup) once all the changes are done.
+    pub fn insert_network(
+        &mut self,
+        other: &Network,
+        input_binding: &[Signal],
+    ) -> Result<Vec<Signal>, SpliceError> {
+        if input_binding.len() != other.nb_inputs() {
+            return Err(SpliceError::BindingLength {
+                expected: other.nb_inputs(),
+                found: input_binding.len(),
+            });
+        }
+        if let Some(s) = input_binding.iter().find(|s| !self.is_valid(**s)) {
+            return Err(SpliceError::InvalidSignal(*s));
+        }
+        let offset = self.nb_nodes() as u32;
+        let t = |s: &Signal| -> Signal {
+            if s.is_input() {
+                input_binding[s.input() as usize] ^ s.is_inverted()
+            } else if s.is_var() {
+                Signal::from_var(offset + s.var()) ^ s.is_inverted()
+            } else {
+                *s
+            }
+        };
+        for i in 0..other.nb_nodes() {
+            self.add(other.gate(i).remap(t));
+        }
+        Ok((0..other.nb_outputs())
+            .map(|o| t(&other.output(o)))
+            .collect())
+    }
+
+    /// Replace the gate of a node by a single-output network, with its inputs bound to signals
+    /// of this network
+    ///
+    /// The binding may use signals of the cone of the root, but not signals that depend on the
+    /// root through combinatorial logic. The root becomes a buffer of the replacement, so that
+    /// its fanouts are preserved, and the signal of the replacement output is returned. Nothing
+    /// is modified on error.
+    ///
+    /// The network is not topologically sorted afterwards: use [`topo_sort`](Network::topo_sort)
+    /// once all the changes are done.
+    pub fn splice(
+        &mut self,
+        root: usize,
+        replacement: &Network,
+        input_binding: &[Signal],
+    ) -> Result<Signal, SpliceError> {
+        if root >= self.nb_nodes() {
+            return Err(SpliceError::InvalidRoot(root));
+        }
+        if replacement.nb_outputs() != 1 {
+            return Err(SpliceError::OutputCount(replacement.nb_outputs()));
+        }
+        if input_binding.len() != replacement.nb_inputs() {
+            return Err(SpliceError::BindingLength {
+                expected: replacement.nb_inputs(),
+                found: input_binding.len(),
+            });
+        }
+        if let Some(s) = input_binding.iter().find(|s| !self.is_valid(**s)) {
+            return Err(SpliceError::InvalidSignal(*s));
+        }
+        // Conservatively reject bindings in the combinatorial fanout of the root
+        for s in input_binding {
+            if s.is_var() && self.depends_on(s.var() as usize, root) {
+                return Err(SpliceError::Loop(*s));
+            }
+        }
+        let out = self.insert_network(replacement, input_binding)?[0];
+        self.replace(root, Gate::Buf(out));
+        Ok(out)
+    }
+
+    /// Return whether a node depends on another through combinatorial gates
+    pub(crate) fn depends_on(&self, node: usize, target: usize) -> bool {
+        let mut visited = vec![false; self.nb_nodes()];
+        let mut to_visit = vec![node];
+        while let Some(i) = to_visit.pop() {
+            if i == target {
+                return true;
+            }
+            if visited[i] || !self.gate_ref(i).is_comb() {
+                continue;
+            }
+            visited[i] = true;
+            to_visit.extend(self.gate(i).vars().map(|v| v as usize));
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpliceError;
+    use crate::sim::simulate_comb;
+    use crate::{Network, Signal};
+
+    /// Network computing (a & b) & c
+    fn and3() -> Network {
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let c = aig.add_input();
+        let x = aig.and(a, b);
+        let y = aig.and(x, c);
+        aig.add_output(y);
+        aig
+    }
+
+    /// Network computing !(i0 ^ i1)
+    fn xnor() -> Network {
+        let mut aig = Network::new();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x = aig.xor(i0, i1);
+        aig.add_output(!x);
+        aig
+    }
+
+    #[test]
+    fn test_splice() {
+        let mut aig = and3();
+        // Bind to a signal of the cone of the root, and to an inverted input
+        let x = aig.node(0);
+        let c = aig.input(2);
+        let s = aig.splice(1, &xnor(), &[x, !c]).unwrap();
+        assert_eq!(s, !aig.node(2));
+        aig.topo_sort();
+        aig.check();
+        for k in 0..8 {
+            let v: Vec<bool> = (0..3).map(|j| (k >> j) & 1 != 0).collect();
+            let expected = !((v[0] && v[1]) ^ !v[2]);
+            assert_eq!(simulate_comb(&aig, &v), vec![expected]);
+        }
+        aig.make_canonical();
+        aig.cleanup();
+        assert_eq!(aig.nb_nodes(), 2);
+    }
+
+    #[test]
+    fn test_insert_network() {
+        let mut aig = and3();
+        let a = aig.input(0);
+        let outputs = aig.insert_network(&xnor(), &[a, a]).unwrap();
+        assert_eq!(outputs, vec![!aig.node(2)]);
+        aig.set_output(0, outputs[0]);
+        aig.make_canonical();
+        aig.cleanup();
+        assert_eq!(aig.output(0), Signal::one());
+    }
+
+    #[test]
+    fn test_splice_errors() {
+        let mut aig = and3();
+        let x = aig.node(0);
+        let y = aig.node(1);
+        let a = aig.input(0);
+        assert_eq!(
+            aig.splice(1, &xnor(), &[x]),
+            Err(SpliceError::BindingLength {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            aig.splice(1, &xnor(), &[x, Signal::from_var(5)]),
+            Err(SpliceError::InvalidSignal(Signal::from_var(5)))
+        );
+        assert_eq!(
+            aig.splice(1, &xnor(), &[x, Signal::from_input(3)]),
+            Err(SpliceError::InvalidSignal(Signal::from_input(3)))
+        );
+        assert_eq!(
+            aig.splice(2, &xnor(), &[x, a]),
+            Err(SpliceError::InvalidRoot(2))
+        );
+        assert_eq!(
+            aig.splice(0, &and3(), &[x, a]),
+            Err(SpliceError::BindingLength {
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(aig.splice(0, &xnor(), &[!y, a]), Err(SpliceError::Loop(!y)));
+        assert_eq!(aig.splice(0, &xnor(), &[x, a]), Err(SpliceError::Loop(x)));
+        let mut two = xnor();
+        two.add_output(a);
+        assert_eq!(
+            aig.splice(0, &two, &[a, a]),
+            Err(SpliceError::OutputCount(2))
+        );
+
+        // Nothing was modified
+        assert_eq!(aig.nb_nodes(), 2);
+        aig.check();
+    }
+}
@@ -1,9 +1,9 @@
 //! Compute gate statistics
 //!
 //! ```
-//! # use quaigh::Network;
+//! # use quaigh_core::Network;
 //! # let aig = Network::new();
-//! use quaigh::network::stats::stats;
+//! use quaigh_core::network::stats::stats;
 //! let stats = stats(&aig);
 //!
 //! // Check that there is no Xor2 gate
@@ -16,6 +16,7 @@
 use std::fmt;
 
 use crate::network::gates::{BinaryType, NaryType, TernaryType};
+use crate::util::format::count;
 use crate::{Gate, Network};
 
 /// Number of inputs, outputs and gates in a network
@@ -51,12 +52,20 @@ pub struct NetworkStats {
     pub nb_dffe: usize,
     /// Number of Dff with reset
     pub nb_dffr: usize,
+    /// Number of transparent latches
+    pub nb_latch: usize,
 }
 
 impl NetworkStats {
-    /// Total number of gates, including Dff
+    /// Total number of gates, including Dff and latches
     pub fn nb_gates(&self) -> usize {
-        self.nb_and + self.nb_xor + self.nb_mux + self.nb_maj + self.nb_buf + self.nb_dff
+        self.nb_and
+            + self.nb_xor
+            + self.nb_mux
+            + self.nb_maj
+            + self.nb_buf
+            + self.nb_dff
+            + self.nb_latch
     }
 
     /// Record a new and
@@ -90,53 +99,56 @@ impl NetworkStats {
 impl fmt::Display for NetworkStats {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "Stats:")?;
-        writeln!(f, "  Inputs: {}", self.nb_inputs)?;
-        writeln!(f, "  Outputs: {}", self.nb_outputs)?;
-        writeln!(f, "  Gates: {}", self.nb_gates())?;
+        writeln!(f, "  Inputs: {}", count(self.nb_inputs))?;
+        writeln!(f, "  Outputs: {}", count(self.nb_outputs))?;
+        writeln!(f, "  Gates: {}", count(self.nb_gates()))?;
         if self.nb_dff != 0 {
-            writeln!(f, "  Dff: {}", self.nb_dff)?;
+            writeln!(f, "  Dff: {}", count(self.nb_dff))?;
             if self.nb_dffe != 0 {
-                writeln!(f, "      enable: {}", self.nb_dff)?;
+                writeln!(f, "      enable: {}", count(self.nb_dffe))?;
             }
             if self.nb_dffr != 0 {
-                writeln!(f, "      reset: {}", self.nb_dff)?;
+                writeln!(f, "      reset: {}", count(self.nb_dffr))?;
             }
         }
+        if self.nb_latch != 0 {
+            writeln!(f, "  Latch: {}", count(self.nb_latch))?;
+        }
         if self.nb_and != 0 {
-            writeln!(f, "  And: {}", self.nb_and)?;
+            writeln!(f, "  And: {}", count(self.nb_and))?;
             for (i, nb) in self.and_arity.iter().enumerate() {
                 if *nb != 0 {
-                    writeln!(f, "      {}: {}", i, nb)?;
+                    writeln!(f, "      {}: {}", i, count(*nb))?;
                 }
             }
         }
         if self.nb_xor != 0 {
-            writeln!(f, "  Xor: {}", self.nb_xor)?;
+            writeln!(f, "  Xor: {}", count(self.nb_xor))?;
             for (i, nb) in self.xor_arity.iter().enumerate() {
                 if *nb != 0 {
-                    writeln!(f, "      {}: {}", i, nb)?;
+                    writeln!(f, "      {}: {}", i, count(*nb))?;
                 }
             }
         }
         if self.nb_lut != 0 {
-            writeln!(f, "  Lut: {}", self.nb_lut)?;
+            writeln!(f, "  Lut: {}", count(self.nb_lut))?;
             for (i, nb) in self.lut_arity.iter().enumerate() {
                 if *nb != 0 {
-                    writeln!(f, "      {}: {}", i, nb)?;
+                    writeln!(f, "      {}: {}", i, count(*nb))?;
                 }
             }
         }
         if self.nb_mux != 0 {
-            writeln!(f, "  Mux: {}", self.nb_mux)?;
+            writeln!(f, "  Mux: {}", count(self.nb_mux))?;
         }
         if self.nb_maj != 0 {
-            writeln!(f, "  Maj: {}", self.nb_maj)?;
+            writeln!(f, "  Maj: {}", count(self.nb_maj))?;
         }
         if self.nb_not != 0 {
-            writeln!(f, "  Not: {}", self.nb_not)?;
+            writeln!(f, "  Not: {}", count(self.nb_not))?;
         }
         if self.nb_buf != 0 {
-            writeln!(f, "  Buf: {}", self.nb_buf)?;
+            writeln!(f, "  Buf: {}", count(self.nb_buf))?;
         }
         fmt::Result::Ok(())
     }
@@ -161,9 +173,10 @@ pub fn stats(a: &Network) -> NetworkStats {
         nb_dff: 0,
         nb_dffe: 0,
         nb_dffr: 0,
+        nb_latch: 0,
     };
-    for i in 0..a.nb_nodes() {
-        match a.gate(i) {
+    for (_, g) in a.iter_gates() {
+        match g {
             Binary(_, BinaryType::And) => ret.add_and(2),
             Ternary(_, TernaryType::And) => ret.add_and(3),
             Binary(_, BinaryType::Xor) => ret.add_xor(2),
@@ -180,7 +193,7 @@ pub fn stats(a: &Network) -> NetworkStats {
                     }
                 }
             }
-            Dff([_, en, res]) => {
+            Dff([_, en, res], _) => {
                 ret.nb_dff += 1;
                 if !en.is_constant() {
                     ret.nb_dffe += 1;
@@ -189,6 +202,7 @@ pub fn stats(a: &Network) -> NetworkStats {
                     ret.nb_dffr += 1;
                 }
             }
+            Latch(_) => ret.nb_latch += 1,
             Nary(v, tp) => match tp {
                 NaryType::And | NaryType::Or | NaryType::Nand | NaryType::Nor => {
                     ret.add_and(v.len());
@@ -244,3 +258,23 @@ pub fn gate_is_output(aig: &Network) -> Vec<bool> {
     }
     ret
 }
+
+#[cfg(test)]
+mod tests {
+    use super::stats;
+    use crate::network::generators::{adder, testcases};
+
+    #[test]
+    fn test_stats_display() {
+        let s = stats(&adder::ripple_carry(4)).to_string();
+        assert_eq!(
+            s,
+            "Stats:\n  Inputs: 8\n  Outputs: 5\n  Gates: 8\n  Xor: 4\n      3: 4\n  Maj: 4\n"
+        );
+        let s = stats(&testcases::ff_tree(3, true, true, 10)).to_string();
+        assert_eq!(
+            s,
+            "Stats:\n  Inputs: 3\n  Outputs: 1,000\n  Gates: 1,110\n  Dff: 1,110\n      enable: 1,110\n      reset: 1,110\n"
+        );
+    }
+}
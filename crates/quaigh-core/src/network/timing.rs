@@ -0,0 +1,264 @@
+//! Static timing analysis against timing constraints
+//!
+//! Constraints give the arrival time of each input, the time at which each output is required,
+//! and the clock period. Arrival times are propagated forward from the inputs and flip-flops, and
+//! required times backward from the outputs and flip-flop inputs. The slack of a node is the
+//! difference between the two: nodes with a negative slack are on paths that violate the
+//! constraints.
+//!
+//! ```
+//! # use quaigh_core::network::generators::adder;
+//! use quaigh_core::network::depth::DelayParameters;
+//! use quaigh_core::network::timing::{Slacks, TimingConstraints};
+//!
+//! let aig = adder::ripple_carry(4);
+//! let delays = DelayParameters::unit().gate_delays(&aig);
+//! let constraints = TimingConstraints::with_clock_period(&aig, 3);
+//! let slacks = Slacks::compute(&aig, &delays, &constraints);
+//! assert_eq!(slacks.worst_slack(), Some(-1));
+//! ```
+
+use std::fmt;
+
+use crate::util::format::count;
+use crate::{Network, Signal};
+
+/// Arrival and required times at the boundaries of a network
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TimingConstraints {
+    /// Clock period, required time of the flip-flop inputs and of the outputs without their own
+    pub clock_period: Option<usize>,
+    /// Arrival time of each input
+    pub input_arrival: Vec<usize>,
+    /// Required time of each output, if specified
+    pub output_required: Vec<Option<usize>>,
+}
+
+impl TimingConstraints {
+    /// Constraints for a network, where inputs arrive at time zero and nothing is required
+    pub fn new(aig: &Network) -> TimingConstraints {
+        TimingConstraints {
+            clock_period: None,
+            input_arrival: vec![0; aig.nb_inputs()],
+            output_required: vec![None; aig.nb_outputs()],
+        }
+    }
+
+    /// Constraints for a network with a clock period, where inputs arrive at time zero
+    pub fn with_clock_period(aig: &Network, period: usize) -> TimingConstraints {
+        TimingConstraints {
+            clock_period: Some(period),
+            ..TimingConstraints::new(aig)
+        }
+    }
+
+    /// Time at which an output is required, if constrained
+    pub fn required_time(&self, output: usize) -> Option<usize> {
+        self.output_required[output].or(self.clock_period)
+    }
+}
+
+/// Arrival times, required times and slacks of the nodes of a network
+#[derive(Clone, Debug)]
+pub struct Slacks {
+    /// Arrival time of each node
+    arrival: Vec<usize>,
+    /// Required time of each node, if it is in the fanin of a constrained output or flip-flop
+    required: Vec<Option<i64>>,
+    /// Slack of each output, if constrained
+    outputs: Vec<Option<i64>>,
+}
+
+impl Slacks {
+    /// Propagate the constraints through a topologically sorted network, with the delay of each
+    /// node
+    ///
+    /// Flip-flops launch new paths at time zero, and their inputs are required at the clock period.
+    pub fn compute(aig: &Network, delays: &[usize], constraints: &TimingConstraints) -> Slacks {
+        assert!(aig.is_topo_sorted());
+        assert_eq!(delays.len(), aig.nb_nodes());
+        assert_eq!(constraints.input_arrival.len(), aig.nb_inputs());
+        assert_eq!(constraints.output_required.len(), aig.nb_outputs());
+
+        let mut arrival = vec![0; aig.nb_nodes()];
+        let time = |arrival: &[usize], s: &Signal| {
+            if s.is_input() {
+                constraints.input_arrival[s.input() as usize]
+            } else if s.is_var() {
+                arrival[s.var() as usize]
+            } else {
+                0
+            }
+        };
+        for i in 0..aig.nb_nodes() {
+            let g = aig.gate(i);
+            if !g.is_comb() {
+                continue;
+            }
+            let max_input = g
+                .dependencies()
+                .iter()
+                .map(|s| time(&arrival, s))
+                .max()
+                .unwrap_or(0);
+            arrival[i] = max_input + delays[i];
+        }
+
+        let mut required: Vec<Option<i64>> = vec![None; aig.nb_nodes()];
+        let require = |required: &mut [Option<i64>], s: &Signal, t: i64| {
+            if s.is_var() {
+                let r = &mut required[s.var() as usize];
+                *r = Some(r.map_or(t, |r| r.min(t)));
+            }
+        };
+        for o in 0..aig.nb_outputs() {
+            if let Some(t) = constraints.required_time(o) {
+                require(&mut required, &aig.output(o), t as i64);
+            }
+        }
+        if let Some(period) = constraints.clock_period {
+            for i in 0..aig.nb_nodes() {
+                if !aig.gate_ref(i).is_comb() {
+                    for s in aig.dependencies(i) {
+                        require(&mut required, s, period as i64);
+                    }
+                }
+            }
+        }
+        for i in (0..aig.nb_nodes()).rev() {
+            let g = aig.gate(i);
+            let Some(t) = required[i] else {
+                continue;
+            };
+            if g.is_comb() {
+                for s in g.dependencies() {
+                    require(&mut required, s, t - delays[i] as i64);
+                }
+            }
+        }
+
+        let outputs = (0..aig.nb_outputs())
+            .map(|o| {
+                constraints
+                    .required_time(o)
+                    .map(|t| t as i64 - time(&arrival, &aig.output(o)) as i64)
+            })
+            .collect();
+        Slacks {
+            arrival,
+            required,
+            outputs,
+        }
+    }
+
+    /// Number of nodes
+    pub fn nb_nodes(&self) -> usize {
+        self.arrival.len()
+    }
+
+    /// Arrival time of a node
+    pub fn arrival(&self, i: usize) -> usize {
+        self.arrival[i]
+    }
+
+    /// Required time of a node, if it is constrained
+    pub fn required(&self, i: usize) -> Option<i64> {
+        self.required[i]
+    }
+
+    /// Slack of a node, if it is constrained
+    pub fn slack(&self, i: usize) -> Option<i64> {
+        self.required[i].map(|r| r - self.arrival[i] as i64)
+    }
+
+    /// Slack of an output, if it is constrained
+    pub fn output_slack(&self, o: usize) -> Option<i64> {
+        self.outputs[o]
+    }
+
+    /// Smallest slack of the outputs and nodes, if any is constrained
+    pub fn worst_slack(&self) -> Option<i64> {
+        let nodes = (0..self.nb_nodes()).filter_map(|i| self.slack(i));
+        self.outputs.iter().flatten().copied().chain(nodes).min()
+    }
+
+    /// Whether a node is on a path that violates the constraints
+    pub fn is_critical(&self, i: usize) -> bool {
+        self.slack(i).is_some_and(|s| s < 0)
+    }
+
+    /// Nodes on paths that violate the constraints
+    pub fn critical_nodes(&self) -> Vec<usize> {
+        (0..self.nb_nodes())
+            .filter(|i| self.is_critical(*i))
+            .collect()
+    }
+}
+
+impl fmt::Display for Slacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let constrained = self.outputs.iter().flatten();
+        let nb_failing = constrained.clone().filter(|s| **s < 0).count();
+        let total: i64 = constrained.clone().filter(|s| **s < 0).sum();
+        writeln!(f, "Timing:")?;
+        match self.worst_slack() {
+            Some(s) => writeln!(f, "  Worst slack: {}", s)?,
+            None => writeln!(f, "  Unconstrained")?,
+        }
+        writeln!(
+            f,
+            "  Failing outputs: {} of {}, total negative slack {}",
+            count(nb_failing),
+            count(constrained.count()),
+            total
+        )?;
+        writeln!(
+            f,
+            "  Critical nodes: {}",
+            count(self.critical_nodes().len())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Slacks, TimingConstraints};
+    use crate::{Network, Signal};
+
+    #[test]
+    fn test_slacks() {
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let c = aig.add_input();
+        let x = aig.and(a, b);
+        let y = aig.and(x, c);
+        let d = aig.dff(y, Signal::one(), Signal::zero());
+        let z = aig.xor(d, a);
+        aig.add_output(y);
+        aig.add_output(z);
+        let delays = vec![1, 2, 0, 3];
+
+        let mut constraints = TimingConstraints::new(&aig);
+        let slacks = Slacks::compute(&aig, &delays, &constraints);
+        assert_eq!(slacks.worst_slack(), None);
+        assert_eq!(slacks.arrival(1), 3);
+
+        // c arrives late, and y is required before the clock period
+        constraints.clock_period = Some(5);
+        constraints.input_arrival[2] = 2;
+        constraints.output_required[0] = Some(3);
+        let slacks = Slacks::compute(&aig, &delays, &constraints);
+        assert_eq!(slacks.arrival(1), 4);
+        assert_eq!(slacks.arrival(3), 3);
+        assert_eq!(slacks.output_slack(0), Some(-1));
+        assert_eq!(slacks.output_slack(1), Some(2));
+        assert_eq!(slacks.required(0), Some(1));
+        assert_eq!(slacks.slack(0), Some(0));
+        assert_eq!(slacks.slack(1), Some(-1));
+        assert_eq!(slacks.slack(2), Some(2));
+        assert_eq!(slacks.worst_slack(), Some(-1));
+        assert_eq!(slacks.critical_nodes(), vec![1]);
+        assert!(slacks.to_string().contains("Failing outputs: 1 of 2"));
+    }
+}
@@ -0,0 +1,215 @@
+//! Exact truth tables of small output cones
+
+use std::collections::{BTreeSet, HashMap};
+
+use volute::Lut;
+
+use crate::network::{BinaryType, NaryType, TernaryType};
+use crate::{Gate, Network, Signal};
+
+/// Maximum number of inputs for [`Network::truth_table`]
+pub const MAX_TRUTH_TABLE_INPUTS: usize = 16;
+
+impl Network {
+    /// Compute the truth table of an output
+    ///
+    /// The inputs of the cone are the primary inputs and flip-flops in the transitive fanin of the
+    /// output. They are returned primary inputs first, the first one being the least significant
+    /// variable of the truth table, as for [`LutGate`](super::LutGate). Returns None if the cone
+    /// has more than `max_inputs` inputs, which must be at most [`MAX_TRUTH_TABLE_INPUTS`].
+    ///
+    /// ```
+    /// # use quaigh_core::{Gate, Network};
+    /// let mut aig = Network::new();
+    /// aig.add_inputs(3);
+    /// let x = aig.add(Gate::and(aig.input(0), !aig.input(2)));
+    /// aig.add_output(x);
+    ///
+    /// let (lut, inputs) = aig.truth_table(0, 4).unwrap();
+    /// assert_eq!(inputs, vec![aig.input(0), aig.input(2)]);
+    /// assert_eq!(lut.to_hex_string(), "2");
+    /// ```
+    pub fn truth_table(&self, output: usize, max_inputs: usize) -> Option<(Lut, Vec<Signal>)> {
+        assert!(
+            max_inputs <= MAX_TRUTH_TABLE_INPUTS,
+            "Truth tables are limited to {} inputs",
+            MAX_TRUTH_TABLE_INPUTS
+        );
+        let o = self.output(output);
+        let (cone, leaves) = self.truth_table_cone(o);
+        let mut leaves: Vec<Signal> = leaves.into_iter().collect();
+        leaves.sort_by_key(|s| {
+            if s.is_input() {
+                (0, s.input())
+            } else {
+                (1, s.var())
+            }
+        });
+        if leaves.len() > max_inputs {
+            return None;
+        }
+        let nb_vars = leaves.len();
+        let mut values: HashMap<Signal, Lut> = leaves
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (*s, Lut::nth_var(nb_vars, i)))
+            .collect();
+        let value = |values: &HashMap<Signal, Lut>, s: &Signal| {
+            let v = if s.is_constant() {
+                Lut::zero(nb_vars)
+            } else {
+                values[&s.without_inversion()].clone()
+            };
+            if s.is_inverted() {
+                !v
+            } else {
+                v
+            }
+        };
+        for i in cone {
+            let g = self.gate(i);
+            let deps: Vec<Lut> = g.dependencies().iter().map(|s| value(&values, s)).collect();
+            values.insert(self.node(i), gate_truth_table(&g, &deps, nb_vars));
+        }
+        Some((value(&values, &o), leaves))
+    }
+
+    /// Combinatorial nodes in the cone of a signal, in topological order, and the inputs of the cone
+    fn truth_table_cone(&self, s: Signal) -> (Vec<usize>, BTreeSet<Signal>) {
+        let mut leaves = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        let mut cone = Vec::new();
+        // Iterative postorder traversal
+        let mut stack = vec![(s.without_inversion(), false)];
+        while let Some((s, expanded)) = stack.pop() {
+            if s.is_constant() {
+                continue;
+            }
+            if s.is_input() || !self.gate_ref(s.var() as usize).is_comb() {
+                leaves.insert(s);
+                continue;
+            }
+            let i = s.var() as usize;
+            if expanded {
+                cone.push(i);
+                continue;
+            }
+            if !visited.insert(i) {
+                continue;
+            }
+            stack.push((s, true));
+            for d in self.dependencies(i) {
+                stack.push((d.without_inversion(), false));
+            }
+        }
+        (cone, leaves)
+    }
+}
+
+/// Truth table of a gate, from the truth tables of its dependencies
+fn gate_truth_table(g: &Gate, deps: &[Lut], nb_vars: usize) -> Lut {
+    use Gate::*;
+    match g {
+        Binary(_, BinaryType::And) => &deps[0] & &deps[1],
+        Binary(_, BinaryType::Xor) => &deps[0] ^ &deps[1],
+        Ternary(_, TernaryType::And) => &(&deps[0] & &deps[1]) & &deps[2],
+        Ternary(_, TernaryType::Xor) => &(&deps[0] ^ &deps[1]) ^ &deps[2],
+        Ternary(_, TernaryType::Mux) => &(&deps[0] & &deps[1]) | &(&!&deps[0] & &deps[2]),
+        Ternary(_, TernaryType::Maj) => {
+            &(&(&deps[0] & &deps[1]) | &(&deps[0] & &deps[2])) | &(&deps[1] & &deps[2])
+        }
+        Nary(_, tp) => {
+            let inv_in = matches!(tp, NaryType::Or | NaryType::Nor);
+            let inv_out = matches!(tp, NaryType::Or | NaryType::Nand | NaryType::Xnor);
+            let ret = if matches!(tp, NaryType::Xor | NaryType::Xnor) {
+                deps.iter().fold(volute::Lut::zero(nb_vars), |a, b| &a ^ b)
+            } else if inv_in {
+                deps.iter().fold(volute::Lut::one(nb_vars), |a, b| &a & &!b)
+            } else {
+                deps.iter().fold(volute::Lut::one(nb_vars), |a, b| &a & b)
+            };
+            if inv_out {
+                !ret
+            } else {
+                ret
+            }
+        }
+        Buf(_) => deps[0].clone(),
+        Lut(lut) => {
+            let mut ret = volute::Lut::zero(nb_vars);
+            for m in 0..1 << deps.len() {
+                if !lut.lut.value(m) {
+                    continue;
+                }
+                let mut term = volute::Lut::one(nb_vars);
+                for (i, d) in deps.iter().enumerate() {
+                    term = if (m >> i) & 1 != 0 {
+                        &term & d
+                    } else {
+                        &term & &!d
+                    };
+                }
+                ret = &ret | &term;
+            }
+            ret
+        }
+        Dff(..) | Latch(..) => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use volute::Lut;
+
+    use crate::network::generators::adder;
+    use crate::sim::simulate_comb;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_adder() {
+        let aig = adder::ripple_carry(3);
+        for o in 0..aig.nb_outputs() {
+            let (lut, inputs) = aig.truth_table(o, 16).unwrap();
+            assert_eq!(lut.num_vars(), inputs.len());
+            for k in 0..64usize {
+                let v: Vec<bool> = (0..6).map(|j| (k >> j) & 1 != 0).collect();
+                let m: usize = inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| (v[s.input() as usize] as usize) << i)
+                    .sum();
+                assert_eq!(lut.value(m), simulate_comb(&aig, &v)[o]);
+            }
+        }
+        assert!(aig.truth_table(3, 5).is_none());
+    }
+
+    #[test]
+    fn test_gates() {
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        let ff = aig.dff(a, Signal::one(), Signal::zero());
+        let lut = Lut::nth_var(3, 0) & !Lut::nth_var(3, 2);
+        let x = aig.add(Gate::lut(&[b, ff, c], lut.clone()));
+        let y = aig.add(Gate::mux(a, b, !c));
+        let z = aig.add(Gate::maj(x, b, c));
+        aig.add_output(x);
+        aig.add_output(!y);
+        aig.add_output(z);
+        aig.add_output(Signal::one());
+
+        let (t, inputs) = aig.truth_table(0, 3).unwrap();
+        assert_eq!(inputs, vec![b, c, ff]);
+        assert_eq!(t, Lut::nth_var(3, 0) & !Lut::nth_var(3, 1));
+        let (t, _) = aig.truth_table(1, 3).unwrap();
+        let (va, vb, vc) = (Lut::nth_var(3, 0), Lut::nth_var(3, 1), Lut::nth_var(3, 2));
+        assert_eq!(t, !((&va & &vb) | (!&va & !&vc)));
+        let (t, inputs) = aig.truth_table(2, 3).unwrap();
+        assert_eq!(inputs.len(), 3);
+        assert_eq!(t, Lut::nth_var(3, 0));
+        let (t, inputs) = aig.truth_table(3, 3).unwrap();
+        assert!(inputs.is_empty());
+        assert_eq!(t, Lut::one(0));
+    }
+}
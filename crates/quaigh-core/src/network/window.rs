@@ -0,0 +1,218 @@
+//! Extraction of small windows of a network for local optimization
+
+use std::collections::BTreeSet;
+
+use crate::network::SpliceError;
+use crate::{Gate, Network, Signal};
+
+/// Boundary of a window extracted with [`Network::extract_window`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    /// Signals of the parent network bound to the inputs of the window
+    pub inputs: Vec<Signal>,
+    /// Nodes of the parent network corresponding to the outputs of the window
+    pub roots: Vec<usize>,
+    /// Nodes of the parent network copied in the window, in topological order
+    pub nodes: Vec<usize>,
+}
+
+impl Network {
+    /// Extract a combinatorial window around some roots
+    ///
+    /// The window contains the roots and part of their transitive fanin, limited to `max_size`
+    /// gates and `max_inputs` inputs. It is grown from the roots, by including first the
+    /// dependencies that add the fewest new inputs. Inputs, flip-flops and the roots' fanins that
+    /// do not fit become inputs of the window. The outputs of the window are the roots, in order.
+    ///
+    /// If the roots alone use more than `max_inputs` signals, the window is only grown where this
+    /// does not add inputs.
+    ///
+    /// Nodes of the window may still be used outside of it: after optimization, the window is
+    /// put back with [`stitch_back`](Network::stitch_back), which only replaces the roots.
+    pub fn extract_window(
+        &self,
+        roots: &[usize],
+        max_inputs: usize,
+        max_size: usize,
+    ) -> (Network, Window) {
+        assert!(self.is_topo_sorted());
+        for r in roots {
+            assert!(
+                self.gate_ref(*r).is_comb(),
+                "Window roots must be combinatorial"
+            );
+        }
+        let mut nodes: BTreeSet<usize> = roots.iter().copied().collect();
+        let mut leaves = self.window_leaves(&nodes);
+        while nodes.len() < max_size {
+            // Expand the leaf that adds the fewest new inputs
+            let best = leaves
+                .iter()
+                .rev()
+                .filter(|s| s.is_var() && self.gate_ref(s.var() as usize).is_comb())
+                .map(|s| {
+                    let i = s.var() as usize;
+                    let added = self
+                        .window_deps(i)
+                        .filter(|d| !leaves.contains(d))
+                        .filter(|d| !d.is_var() || !nodes.contains(&(d.var() as usize)))
+                        .count();
+                    (added, i)
+                })
+                .min_by_key(|(added, _)| *added);
+            let Some((added, i)) = best else {
+                break;
+            };
+            if leaves.len() - 1 + added > max_inputs.max(leaves.len()) {
+                break;
+            }
+            nodes.insert(i);
+            leaves = self.window_leaves(&nodes);
+        }
+
+        let inputs: Vec<Signal> = leaves.into_iter().collect();
+        let nodes: Vec<usize> = nodes.into_iter().collect();
+        let mut ret = Network::new();
+        ret.add_inputs(inputs.len());
+        let mut translation = vec![None; self.nb_nodes()];
+        let input_index = |s: &Signal| inputs.binary_search(&s.without_inversion()).ok();
+        for &i in &nodes {
+            let g = self.gate(i).remap(|s| {
+                let t = if let Some(j) = input_index(s) {
+                    ret.input(j)
+                } else if s.is_var() {
+                    translation[s.var() as usize].unwrap()
+                } else {
+                    s.without_inversion()
+                };
+                t ^ s.is_inverted()
+            });
+            translation[i] = Some(ret.add(g));
+        }
+        for r in roots {
+            ret.add_output(translation[*r].unwrap());
+        }
+        let window = Window {
+            inputs,
+            roots: roots.to_vec(),
+            nodes,
+        };
+        (ret, window)
+    }
+
+    /// Replace the roots of a window by the outputs of another network
+    ///
+    /// The replacement has one input per input of the window and one output per root, typically
+    /// obtained by optimizing the network returned by [`extract_window`](Network::extract_window).
+    /// The roots become buffers of the replacement outputs, which are returned. Nothing is
+    /// modified on error.
+    ///
+    /// As for [`splice`](Network::splice), the network is not topologically sorted afterwards.
+    pub fn stitch_back(
+        &mut self,
+        window: &Window,
+        replacement: &Network,
+    ) -> Result<Vec<Signal>, SpliceError> {
+        if let Some(r) = window.roots.iter().find(|r| **r >= self.nb_nodes()) {
+            return Err(SpliceError::InvalidRoot(*r));
+        }
+        if replacement.nb_outputs() != window.roots.len() {
+            return Err(SpliceError::OutputLength {
+                expected: window.roots.len(),
+                found: replacement.nb_outputs(),
+            });
+        }
+        // Conservatively reject windows whose inputs depend on a root
+        for s in &window.inputs {
+            if s.is_var()
+                && window
+                    .roots
+                    .iter()
+                    .any(|r| self.depends_on(s.var() as usize, *r))
+            {
+                return Err(SpliceError::Loop(*s));
+            }
+        }
+        let outputs = self.insert_network(replacement, &window.inputs)?;
+        for (r, o) in window.roots.iter().zip(outputs.iter()) {
+            self.replace(*r, Gate::Buf(*o));
+        }
+        Ok(outputs)
+    }
+
+    /// Non-constant dependencies of a node, without inversion
+    fn window_deps(&self, i: usize) -> impl Iterator<Item = Signal> + '_ {
+        self.dependencies(i)
+            .iter()
+            .filter(|s| !s.is_constant())
+            .map(|s| s.without_inversion())
+    }
+
+    /// Signals used by the nodes of a window but not computed in it
+    fn window_leaves(&self, nodes: &BTreeSet<usize>) -> BTreeSet<Signal> {
+        nodes
+            .iter()
+            .flat_map(|i| self.window_deps(*i))
+            .filter(|s| !(s.is_var() && nodes.contains(&(s.var() as usize))))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::network::generators::adder;
+    use crate::network::SpliceError;
+    use crate::sim::simulate_comb;
+    use crate::Network;
+
+    fn check_equivalent(a: &Network, b: &Network) {
+        for k in 0..256u32 {
+            let v: Vec<bool> = (0..a.nb_inputs()).map(|j| (k >> j) & 1 != 0).collect();
+            assert_eq!(simulate_comb(a, &v), simulate_comb(b, &v));
+        }
+    }
+
+    #[test]
+    fn test_extract() {
+        let aig = adder::ripple_carry(4);
+        let root = aig.output(3).var() as usize;
+        let (w, window) = aig.extract_window(&[root], 4, 3);
+        assert!(w.nb_inputs() <= 4);
+        assert!(w.nb_nodes() <= 3);
+        assert_eq!(w.nb_inputs(), window.inputs.len());
+        assert_eq!(w.nb_nodes(), window.nodes.len());
+        assert_eq!(w.nb_outputs(), 1);
+        assert!(window.nodes.contains(&root));
+        w.check();
+
+        // The whole cone fits in a large window
+        let (w, window) = aig.extract_window(&[root], 100, 100);
+        assert!(window.inputs.iter().all(|s| s.is_input()));
+        assert_eq!(w.nb_outputs(), 1);
+    }
+
+    #[test]
+    fn test_stitch_back() {
+        let aig = adder::ripple_carry(4);
+        let roots: Vec<usize> = (2..4).map(|o| aig.output(o).var() as usize).collect();
+        let (w, window) = aig.extract_window(&roots, 6, 6);
+
+        // Stitching back a copy of the window gives an equivalent network
+        let mut modified = aig.clone();
+        let outputs = modified.stitch_back(&window, &w).unwrap();
+        assert_eq!(outputs.len(), 2);
+        modified.topo_sort();
+        modified.check();
+        check_equivalent(&aig, &modified);
+
+        let mut bad = w.clone();
+        bad.add_output(bad.input(0));
+        assert_eq!(
+            modified.stitch_back(&window, &bad),
+            Err(SpliceError::OutputLength {
+                expected: 2,
+                found: 3
+            })
+        );
+    }
+}
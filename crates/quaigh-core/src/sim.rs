@@ -0,0 +1,1006 @@
+//! Simulation of a logic network. Faster, multi-pattern simulation methods process 64 patterns at once,
+//! or 256 and more with [`Wide`] words.
+
+mod cache;
+mod fault;
+mod incremental_sim;
+mod lane_check;
+pub(crate) mod simple_sim;
+mod timing_sim;
+mod weights;
+mod word;
+mod xprop_sim;
+
+use std::hash::Hash;
+use std::io::{BufRead, Write};
+
+use crate::io::{PatternReader, PatternWriter};
+use crate::network::ClockId;
+use crate::sim::incremental_sim::IncrementalSimulator;
+use crate::{Network, Signal};
+
+pub use cache::{SimCache, SimCacheStats};
+pub use fault::{Fault, FaultDescription};
+pub use lane_check::{check_lane_packing, selftest_lane_packing};
+pub use timing_sim::{simulate_timing, CycleTiming, TimingSimulator};
+pub use weights::{gate_type_name, FaultWeights};
+pub use word::{pack_lanes, SimWord, Wide, W256, W512};
+
+/// Simple conversion to 64b format
+fn bool_to_multi(values: &Vec<Vec<bool>>) -> Vec<Vec<u64>> {
+    let mut ret = Vec::<Vec<u64>>::new();
+    for v in values {
+        ret.push(v.iter().map(|b| if *b { !0 } else { 0 }).collect());
+    }
+    ret
+}
+
+/// Simple conversion from 64b format
+fn multi_to_bool(values: &Vec<Vec<u64>>) -> Vec<Vec<bool>> {
+    let mut ret = Vec::new();
+    for v in values {
+        ret.push(v.iter().map(|b| *b != 0).collect());
+    }
+    ret
+}
+
+/// Simulate a network over multiple timesteps; return the output values
+pub fn simulate(a: &Network, input_values: &Vec<Vec<bool>>) -> Vec<Vec<bool>> {
+    let multi_input = bool_to_multi(input_values);
+    let multi_ret = simulate_multi(a, &multi_input);
+    multi_to_bool(&multi_ret)
+}
+
+/// Simulate a network with several clock domains over multiple timesteps; return the output values
+///
+/// Between timesteps `i` and `i + 1`, only the flip-flops whose clock domain appears in `edges[i]`
+/// are updated. [`simulate`] corresponds to all clock domains triggering at each timestep.
+pub fn simulate_with_clocks(
+    a: &Network,
+    input_values: &Vec<Vec<bool>>,
+    edges: &[Vec<ClockId>],
+) -> Vec<Vec<bool>> {
+    use simple_sim::SimpleSimulator;
+    let multi_input = bool_to_multi(input_values);
+    let mut sim = SimpleSimulator::from_aig(a);
+    let multi_ret = sim.run_with_clocks(&multi_input, edges);
+    multi_to_bool(&multi_ret)
+}
+
+/// Simulate a network over multiple timesteps with unknown values; return the output values
+///
+/// Unknown values (X) are represented as None, and are propagated through the gates: the output
+/// of a gate is known only if it is the same for all possible values of its unknown inputs, which
+/// is checked gate by gate. Flip-flops start in an unknown state instead of 0, until they are reset
+/// or loaded with a known value.
+pub fn simulate_xprop(a: &Network, input_values: &[Vec<Option<bool>>]) -> Vec<Vec<Option<bool>>> {
+    use xprop_sim::{XPropSimulator, XValue};
+    let to_x = |v: &Option<bool>| match v {
+        None => XValue::unknown(),
+        Some(b) => XValue::known(if *b { !0 } else { 0 }),
+    };
+    let from_x = |v: &XValue| match (v.can0 & 1 != 0, v.can1 & 1 != 0) {
+        (true, false) => Some(false),
+        (false, true) => Some(true),
+        _ => None,
+    };
+    let multi_input: Vec<Vec<XValue>> = input_values
+        .iter()
+        .map(|v| v.iter().map(to_x).collect())
+        .collect();
+    let mut sim = XPropSimulator::from_aig(a);
+    sim.run(&multi_input)
+        .iter()
+        .map(|v| v.iter().map(from_x).collect())
+        .collect()
+}
+
+/// Simulate a combinatorial network; return the output values
+pub fn simulate_comb(a: &Network, input_values: &Vec<bool>) -> Vec<bool> {
+    assert!(a.is_comb());
+    let input = vec![input_values.clone()];
+    let output = simulate(a, &input);
+    output[0].clone()
+}
+
+/// Simulate a batch of patterns over multiple timesteps, 64 at a time; return the output values of each
+///
+/// Patterns may have different numbers of timesteps.
+pub fn simulate_batch(a: &Network, patterns: &[Vec<Vec<bool>>]) -> Vec<Vec<Vec<bool>>> {
+    let mut ret = Vec::with_capacity(patterns.len());
+    for chunk in patterns.chunks(64) {
+        // Shorter patterns are padded with zeros, which does not change their outputs
+        let nb_steps = chunk.iter().map(|p| p.len()).max().unwrap_or(0);
+        let mut input = vec![vec![0u64; a.nb_inputs()]; nb_steps];
+        for (lane, p) in chunk.iter().enumerate() {
+            for (step, values) in p.iter().enumerate() {
+                assert_eq!(values.len(), a.nb_inputs());
+                for (i, b) in values.iter().enumerate() {
+                    input[step][i] |= (*b as u64) << lane;
+                }
+            }
+        }
+        let output = simulate_multi(a, &input);
+        for (lane, p) in chunk.iter().enumerate() {
+            ret.push(
+                output[..p.len()]
+                    .iter()
+                    .map(|o| o.iter().map(|w| (w >> lane) & 1 != 0).collect())
+                    .collect(),
+            );
+        }
+    }
+    ret
+}
+
+/// Simulate a stream of patterns in batches, writing the output values as they are computed
+///
+/// Memory usage depends on the batch size, but not on the number of patterns.
+/// Returns the number of patterns simulated.
+pub fn simulate_stream<R: BufRead, W: Write>(
+    a: &Network,
+    reader: PatternReader<R>,
+    writer: &mut PatternWriter<W>,
+    batch_size: usize,
+) -> Result<usize, String> {
+    let mut nb_patterns = 0;
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut reader = reader.peekable();
+    while reader.peek().is_some() {
+        batch.clear();
+        for p in reader.by_ref().take(batch_size.max(1)) {
+            batch.push(p?);
+        }
+        for o in simulate_batch(a, &batch) {
+            writer.write(&o);
+        }
+        nb_patterns += batch.len();
+    }
+    writer.flush();
+    Ok(nb_patterns)
+}
+
+/// Simulate a stream of patterns with unknown values, writing the output values as they are computed
+///
+/// See [`simulate_xprop`] for the semantics. Returns the number of patterns simulated.
+pub fn simulate_xprop_stream<R: BufRead, W: Write>(
+    a: &Network,
+    mut reader: PatternReader<R>,
+    writer: &mut PatternWriter<W>,
+) -> Result<usize, String> {
+    let mut nb_patterns = 0;
+    while let Some(p) = reader.next_ternary() {
+        writer.write_ternary(&simulate_xprop(a, &p?));
+        nb_patterns += 1;
+    }
+    writer.flush();
+    Ok(nb_patterns)
+}
+
+/// Simulate a network over multiple timesteps, with faults injected; return the output values
+///
+/// Stuck-at faults may be located on any gate, including the inputs of flip-flops.
+pub fn simulate_with_faults(
+    a: &Network,
+    input_values: &Vec<Vec<bool>>,
+    faults: &Vec<Fault>,
+) -> Vec<Vec<bool>> {
+    let multi_input = bool_to_multi(input_values);
+    let multi_ret = simulate_multi_with_faults(a, &multi_input, faults);
+    multi_to_bool(&multi_ret)
+}
+
+/// Simulate a combinatorial network, with faults injected; return the output values
+pub fn simulate_comb_with_faults(
+    a: &Network,
+    input_values: &Vec<bool>,
+    faults: &Vec<Fault>,
+) -> Vec<bool> {
+    assert!(a.is_comb());
+    let input = vec![input_values.clone()];
+    let output = simulate_with_faults(a, &input, faults);
+    output[0].clone()
+}
+
+/// Simulate a network over multiple timesteps with 64b inputs; return the output values
+pub(crate) fn simulate_multi(a: &Network, input_values: &[Vec<u64>]) -> Vec<Vec<u64>> {
+    use simple_sim::SimpleSimulator;
+    let mut sim = SimpleSimulator::from_aig(a);
+    sim.run(input_values)
+}
+
+/// Simulate a combinatorial network with 64b inputs; return the output values
+pub fn simulate_comb_multi(a: &Network, input_values: &[u64]) -> Vec<u64> {
+    assert!(a.is_comb());
+    simulate_multi(a, &[input_values.to_vec()]).pop().unwrap()
+}
+
+/// Simulate a combinatorial network with 64b inputs, with faults injected; return the output values
+pub fn simulate_comb_multi_with_faults(
+    a: &Network,
+    input_values: &[u64],
+    faults: &[Fault],
+) -> Vec<u64> {
+    assert!(a.is_comb());
+    simulate_multi_with_faults(a, &vec![input_values.to_vec()], &faults.to_vec())
+        .pop()
+        .unwrap()
+}
+
+/// Simulate a combinatorial network with 64b inputs; return the values of all nodes
+pub fn simulate_nodes_multi(a: &Network, input_values: &[u64]) -> Vec<u64> {
+    use simple_sim::SimpleSimulator;
+    assert!(a.is_comb());
+    let mut sim = SimpleSimulator::from_aig(a);
+    sim.run(&[input_values.to_vec()]);
+    sim.node_values
+}
+
+/// Simulate a combinatorial network with inputs of any word width; return the output values
+///
+/// Each lane of the words is an independent pattern: with [`W256`] words, 256 patterns are
+/// simulated in a single pass over the network.
+pub fn simulate_comb_wide<W: SimWord>(a: &Network, input_values: &[W]) -> Vec<W> {
+    use simple_sim::SimpleSimulator;
+    assert!(a.is_comb());
+    let mut sim = SimpleSimulator::from_aig(a);
+    sim.run(&[input_values.to_vec()]).pop().unwrap()
+}
+
+/// Simulate a combinatorial network with 64b inputs on several threads; return the output values
+///
+/// A copy of the network is sorted by level, and the gates of each level are evaluated in
+/// parallel. This is only worthwhile for large networks, with thousands of gates per level.
+pub fn simulate_comb_parallel(a: &Network, input_values: &[u64], nb_threads: usize) -> Vec<u64> {
+    use simple_sim::SimpleSimulator;
+    assert!(a.is_comb());
+    let mut levelized = a.clone();
+    let (levels, _) = levelized.levelize();
+    let mut sim = SimpleSimulator::from_aig(&levelized);
+    sim.copy_inputs(input_values);
+    sim.run_comb_levels(&levels, nb_threads);
+    levelized.outputs().map(|o| sim.get_value(o)).collect()
+}
+
+/// Simulate a network over multiple timesteps with 64b inputs; return the output values
+///
+/// Each of the 64 lanes is an independent run, and the faults are injected in all of them.
+pub(crate) fn simulate_multi_with_faults(
+    a: &Network,
+    input_values: &Vec<Vec<u64>>,
+    faults: &Vec<Fault>,
+) -> Vec<Vec<u64>> {
+    use simple_sim::SimpleSimulator;
+    let mut sim = SimpleSimulator::from_aig(a);
+    sim.run_with_faults(input_values, faults)
+}
+
+/// Analyze which of a set of pattern detect a given fault
+pub fn detects_faults_multi(aig: &Network, pattern: &Vec<u64>, faults: &Vec<Fault>) -> Vec<u64> {
+    assert!(aig.is_comb());
+    assert!(aig.is_topo_sorted());
+    let mut incr_sim = IncrementalSimulator::from_aig(aig);
+    incr_sim.run_initial(pattern);
+    let mut detections = Vec::new();
+    for f in faults {
+        detections.push(incr_sim.detects_fault(*f));
+    }
+    detections
+}
+
+/// Analyze which of a set of patterns detect each fault, reusing the simulation of identical
+/// patterns from a cache
+pub fn detects_faults_cached<W: SimWord + Hash>(
+    cache: &mut SimCache<'_, W>,
+    pattern: &[W],
+    faults: &[Fault],
+) -> Vec<W> {
+    let aig = cache.aig();
+    let mut incr_sim = IncrementalSimulator::from_aig(aig);
+    incr_sim.run_initial_from_values(pattern, cache.node_values(pattern));
+    faults.iter().map(|f| incr_sim.detects_fault(*f)).collect()
+}
+
+/// Analyze which of a set of patterns detect each fault, with words of any width
+///
+/// Each lane of the words is a pattern. Wider words amortize the traversal of the network over
+/// more patterns, which speeds up the fault simulation of large pattern sets.
+pub fn detects_faults_wide<W: SimWord>(aig: &Network, pattern: &[W], faults: &[Fault]) -> Vec<W> {
+    assert!(aig.is_comb());
+    assert!(aig.is_topo_sorted());
+    let mut incr_sim = IncrementalSimulator::from_aig(aig);
+    incr_sim.run_initial(pattern);
+    faults.iter().map(|f| incr_sim.detects_fault(*f)).collect()
+}
+
+/// Analyze which of a set of patterns activate each fault, for IDDQ testing
+///
+/// A fault is activated when its site has the value opposite to the stuck value: the defect then
+/// causes a measurable quiescent current, whether or not its effect reaches an output.
+///
+/// Only the patterns that satisfy the assumptions of the network activate faults.
+pub fn activates_faults_multi(aig: &Network, pattern: &[u64], faults: &[Fault]) -> Vec<u64> {
+    let nodes = simulate_nodes_multi(aig, pattern);
+    let value = |s: Signal| {
+        let v = if s.is_constant() {
+            0
+        } else if s.is_input() {
+            pattern[s.input() as usize]
+        } else {
+            nodes[s.var() as usize]
+        };
+        if s.is_inverted() {
+            !v
+        } else {
+            v
+        }
+    };
+    let valid = aig
+        .assumptions()
+        .into_iter()
+        .fold(!0, |acc, o| acc & value(aig.output(o)));
+    faults
+        .iter()
+        .map(|f| {
+            let v = value(f.site(aig));
+            let v = if f.value() { !v } else { v };
+            v & valid
+        })
+        .collect()
+}
+
+/// Analyze which of a set of two-cycle patterns detect a given transition fault
+///
+/// The faults are launched by the first pattern and captured by the second one, in a combinatorial
+/// network. Stuck-at faults are only injected in the capture cycle.
+pub fn detects_transition_faults_multi(
+    aig: &Network,
+    launch: &[u64],
+    capture: &[u64],
+    faults: &[Fault],
+) -> Vec<u64> {
+    assert!(aig.is_comb());
+    assert!(aig.is_topo_sorted());
+    let mut incr_sim = IncrementalSimulator::from_aig(aig);
+    incr_sim.run_initial_transition(launch, capture);
+    faults.iter().map(|f| incr_sim.detects_fault(*f)).collect()
+}
+
+/// Analyze where the effect of each fault is visible for a set of patterns
+///
+/// For each fault, returns the gates whose value is modified by the fault for at least one pattern.
+/// Exposing any of them as an output would make the fault detectable.
+pub fn fault_effects_multi(aig: &Network, pattern: &[u64], faults: &[Fault]) -> Vec<Vec<usize>> {
+    assert!(aig.is_comb());
+    assert!(aig.is_topo_sorted());
+    let mut incr_sim = IncrementalSimulator::from_aig(aig);
+    incr_sim.run_initial(pattern);
+    faults.iter().map(|f| incr_sim.fault_effects(*f)).collect()
+}
+
+/// Analyze whether a pattern detects a given fault
+pub fn detects_faults(aig: &Network, pattern: &Vec<bool>, faults: &Vec<Fault>) -> Vec<bool> {
+    let multi_pattern = pattern
+        .iter()
+        .map(|b| if *b { !0u64 } else { 0u64 })
+        .collect();
+    let detections = detects_faults_multi(aig, &multi_pattern, faults);
+    detections
+        .iter()
+        .map(|d| {
+            debug_assert!(*d == 0u64 || *d == !0u64);
+            *d != 0
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::zip;
+
+    use volute::{Lut3, Lut5};
+
+    use crate::network::NaryType;
+    use crate::sim::{
+        activates_faults_multi, detects_faults_multi, detects_transition_faults_multi,
+        fault_effects_multi, simulate_multi, simulate_multi_with_faults, Fault,
+    };
+    use crate::{Gate, Network, Signal};
+
+    use super::simulate;
+
+    #[test]
+    fn test_basic() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let x1 = aig.xor(i0, i1);
+        let x2 = aig.and(i0, i2);
+        let x3 = aig.and(x2, !i1);
+        aig.add_output(x1);
+        aig.add_output(x3);
+
+        assert_eq!(
+            simulate(&aig, &vec![vec![false, false, false]]),
+            vec![vec![false, false]]
+        );
+        assert_eq!(
+            simulate(&aig, &vec![vec![true, false, false]]),
+            vec![vec![true, false]]
+        );
+        assert_eq!(
+            simulate(&aig, &vec![vec![true, false, true]]),
+            vec![vec![true, true]]
+        );
+        assert_eq!(
+            simulate(&aig, &vec![vec![true, true, true]]),
+            vec![vec![false, false]]
+        );
+    }
+
+    #[test]
+    fn test_dff() {
+        let mut aig = Network::default();
+        let d = aig.add_input();
+        let en = aig.add_input();
+        let res = aig.add_input();
+        let x = aig.dff(d, en, res);
+        aig.add_output(x);
+        let pattern = vec![
+            vec![false, false, false],
+            vec![false, true, false],
+            vec![true, true, false],
+            vec![true, false, false],
+            vec![true, false, true],
+            vec![false, false, false],
+        ];
+        let expected = vec![
+            vec![false],
+            vec![false],
+            vec![false],
+            vec![true],
+            vec![true],
+            vec![false],
+        ];
+        assert_eq!(simulate(&aig, &pattern), expected);
+    }
+
+    #[test]
+    fn test_clocks() {
+        use crate::network::ClockId;
+
+        let mut aig = Network::default();
+        let d = aig.add_input();
+        let (ca, cb) = (ClockId::rising(0), ClockId::falling(1));
+        let x = aig.add(Gate::dff_with_clock(d, Signal::one(), Signal::zero(), ca));
+        let y = aig.add(Gate::dff_with_clock(d, Signal::one(), Signal::zero(), cb));
+        aig.add_output(x);
+        aig.add_output(y);
+        let pattern = vec![vec![true], vec![false], vec![false], vec![true]];
+        let edges = vec![vec![ca], vec![cb], vec![ca, cb]];
+        let expected = vec![
+            vec![false, false],
+            vec![true, false],
+            vec![true, false],
+            vec![false, false],
+        ];
+        assert_eq!(
+            super::simulate_with_clocks(&aig, &pattern, &edges),
+            expected
+        );
+        let all = vec![vec![ca, cb]; 3];
+        assert_eq!(
+            super::simulate_with_clocks(&aig, &pattern, &all),
+            simulate(&aig, &pattern)
+        );
+    }
+
+    #[test]
+    fn test_xprop() {
+        let mut aig = Network::default();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let en = aig.add_input();
+        let x = aig.and(a, b);
+        let y = aig.xor(a, b);
+        let m = aig.add(Gate::mux(a, b, b));
+        let ff = aig.dff(a, en, Signal::zero());
+        aig.add_output(x);
+        aig.add_output(y);
+        aig.add_output(m);
+        aig.add_output(ff);
+        let pattern = vec![
+            vec![Some(false), None, Some(false)],
+            vec![None, Some(true), Some(true)],
+            vec![Some(true), Some(false), Some(false)],
+        ];
+        let expected = vec![
+            vec![Some(false), None, None, None],
+            vec![None, None, Some(true), None],
+            vec![Some(false), Some(true), Some(false), None],
+        ];
+        assert_eq!(super::simulate_xprop(&aig, &pattern), expected);
+
+        // Known inputs give the same result as the two-valued simulation once the flip-flops are set
+        let pattern = vec![vec![Some(true), Some(true), Some(true)]; 2];
+        let ret = super::simulate_xprop(&aig, &pattern);
+        assert_eq!(ret[0], vec![Some(true), Some(false), Some(true), None]);
+        assert_eq!(
+            ret[1],
+            vec![Some(true), Some(false), Some(true), Some(true)]
+        );
+    }
+
+    #[test]
+    fn test_xprop_lut() {
+        let mut aig = Network::default();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let c = aig.add_input();
+        // Majority as a Lut: known as soon as two inputs agree
+        let lut = Lut3::threshold(2);
+        let x = aig.add(Gate::lut(&[a, b, c], lut.into()));
+        aig.add_output(x);
+        let cases = [
+            ([Some(true), Some(true), None], Some(true)),
+            ([Some(false), None, Some(false)], Some(false)),
+            ([Some(false), None, Some(true)], None),
+            ([Some(true), Some(false), Some(true)], Some(true)),
+        ];
+        for (inputs, expected) in cases {
+            let ret = super::simulate_xprop(&aig, &[inputs.to_vec()]);
+            assert_eq!(ret, vec![vec![expected]]);
+        }
+    }
+
+    #[test]
+    fn test_nary() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let i3 = aig.add_input();
+        let x0 = aig.add(Gate::Nary([i0, i1, i2, i3].into(), NaryType::And));
+        aig.add_output(x0);
+        let x1 = aig.add(Gate::Nary([i0, i1, i2, i3].into(), NaryType::Xor));
+        aig.add_output(x1);
+        let x2 = aig.add(Gate::Nary([i0, i1, i2, i3].into(), NaryType::Or));
+        aig.add_output(x2);
+        let x3 = aig.add(Gate::Nary([i0, i1, i2, i3].into(), NaryType::Nand));
+        aig.add_output(x3);
+        let x4 = aig.add(Gate::Nary([i0, i1, i2, i3].into(), NaryType::Nor));
+        aig.add_output(x4);
+        let x5 = aig.add(Gate::Nary([i0, i1, i2, i3].into(), NaryType::Xnor));
+        aig.add_output(x5);
+
+        let pattern = vec![
+            vec![false, false, false, false],
+            vec![true, false, false, false],
+            vec![false, true, false, false],
+            vec![false, false, true, false],
+            vec![false, false, false, true],
+            vec![true, true, true, true],
+        ];
+        let expected = vec![
+            vec![false, false, false, true, true, true],
+            vec![false, true, true, true, false, false],
+            vec![false, true, true, true, false, false],
+            vec![false, true, true, true, false, false],
+            vec![false, true, true, true, false, false],
+            vec![true, false, true, false, false, true],
+        ];
+        assert_eq!(simulate(&aig, &pattern), expected);
+    }
+
+    #[test]
+    fn test_maj() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let x0 = aig.add(Gate::maj(i0, i1, i2));
+        aig.add_output(x0);
+        let pattern = vec![
+            vec![false, false, false],
+            vec![true, false, false],
+            vec![false, true, false],
+            vec![false, false, true],
+            vec![true, true, true],
+        ];
+        let expected = vec![
+            vec![false],
+            vec![false],
+            vec![false],
+            vec![false],
+            vec![true],
+        ];
+        assert_eq!(simulate(&aig, &pattern), expected);
+    }
+
+    #[test]
+    fn test_lfsr() {
+        let mut aig = Network::default();
+
+        // 3bit LFSR with seed 001, coefficients 101
+        // expected steps: 001 100 110 111 011 101 010 001
+        // expected outputs: 1 0 0 1 1 1 0 1
+        // expected network:
+        //
+        // x3x2x1 transform to x3'x2'x1' with:
+        //
+        // x3' = (x3 ^ x1)
+        // x2' = x3
+        // x1' = x2 | reset
+        //
+        // and output = x1'
+
+        let reset = aig.add_input();
+        let enable = aig.add_input();
+        let x3 = aig.dff(
+            Signal::placeholder(),
+            Signal::placeholder(),
+            Signal::placeholder(),
+        );
+        let x2 = aig.dff(
+            Signal::placeholder(),
+            Signal::placeholder(),
+            Signal::placeholder(),
+        );
+        let x1 = aig.dff(
+            Signal::placeholder(),
+            Signal::placeholder(),
+            Signal::placeholder(),
+        );
+
+        let x3_next = aig.xor(x3, x1);
+        let x2_next = x3;
+        let x1_next = aig.add(Gate::maj(x2, reset, Signal::one()));
+
+        let enable_on_reset = aig.add(Gate::maj(enable, reset, Signal::one()));
+
+        aig.replace(0, Gate::dff(x3_next, enable, reset));
+        aig.replace(1, Gate::dff(x2_next, enable, reset));
+        aig.replace(2, Gate::dff(x1_next, enable_on_reset, Signal::zero()));
+
+        aig.add_output(x1);
+
+        let pattern = vec![
+            vec![true, true], // step 0 reset to initial state
+            vec![false, true],
+            vec![false, true],
+            vec![false, true],
+            vec![false, true],
+            vec![false, true],
+            vec![false, true],
+            vec![false, true],
+            vec![false, true],
+        ];
+
+        // expected outputs: 0(uinit) 1(inited) 0 0 1 1 1 0 1
+        let expected: Vec<Vec<_>> = vec![0, 1, 0, 0, 1, 1, 1, 0, 1]
+            .into_iter()
+            .map(|b| vec![b == 1])
+            .collect();
+
+        assert_eq!(simulate(&aig, &pattern), expected);
+    }
+
+    #[test]
+    fn test_lut() {
+        let mut aig = Network::default();
+
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        let i3 = aig.add_input();
+        let i4 = aig.add_input();
+        let truth = Lut5::threshold(4);
+        let lut = Gate::lut(&[i0, i1, i2, i3, i4], truth.into());
+        let o0 = aig.add(lut);
+        aig.add_output(o0);
+
+        let pattern = vec![
+            vec![0, 0, 0, 0, 0],
+            vec![0b111110, 0b111100, 0b111000, 0b110000, 0b100000],
+        ];
+
+        let expected: Vec<Vec<_>> = vec![vec![0], vec![0b110000]];
+
+        assert_eq!(simulate_multi(&aig, &pattern), expected);
+    }
+
+    #[test]
+    fn test_lut_input_order() {
+        let mut aig = Network::default();
+
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+        for i in 0..3 {
+            let o = aig.add(Gate::lut(&[i0, i1, i2], Lut3::nth_var(i).into()));
+            aig.add_output(o);
+        }
+
+        let pattern = vec![vec![0b1110, 0b1100, 0b1000]];
+
+        let expected: Vec<Vec<_>> = vec![vec![0b1110, 0b1100, 0b1000]];
+
+        assert_eq!(simulate_multi(&aig, &pattern), expected);
+    }
+
+    #[test]
+    fn test_lut_bitsliced() {
+        let mut aig = Network::default();
+        let inputs: Vec<Signal> = (0..4).map(|_| aig.add_input()).collect();
+        let mut lut = volute::Lut::zero(4);
+        for m in [0, 3, 5, 6, 9, 14, 15] {
+            lut.set_bit(m);
+        }
+        let o = aig.add(Gate::lut(&inputs, lut.clone()));
+        aig.add_output(o);
+
+        let pattern = vec![vec![
+            0x0123456789abcdef,
+            0xfedcba9876543210,
+            0x5555aaaa3333cccc,
+            0x0f0f00ffff00f0f0,
+        ]];
+        let mut expected = 0u64;
+        for i in 0..64 {
+            let m = (0..4).fold(0, |m, k| m | (((pattern[0][k] >> i) & 1) << k));
+            expected |= (lut.value(m as usize) as u64) << i;
+        }
+        assert_eq!(simulate_multi(&aig, &pattern), vec![vec![expected]]);
+    }
+
+    #[test]
+    fn test_lut_faults() {
+        // The same multiplexer, as a Lut and as a gate
+        let mut lut_aig = Network::default();
+        let mut mux_aig = Network::default();
+        for aig in [&mut lut_aig, &mut mux_aig] {
+            aig.add_inputs(3);
+        }
+        let (s, a, b) = (lut_aig.input(0), lut_aig.input(1), lut_aig.input(2));
+        let v = |i| volute::Lut::nth_var(3, i);
+        let mux = (v(0) & v(1)) | (!v(0) & v(2));
+        let o = lut_aig.add(Gate::lut(&[s, a, b], mux));
+        lut_aig.add_output(o);
+        let o = mux_aig.add(Gate::mux(s, a, b));
+        mux_aig.add_output(o);
+
+        let pattern = vec![0b11110000, 0b11001100, 0b10101010];
+        for f in Fault::all(&lut_aig) {
+            assert_eq!(
+                detects_faults_multi(&lut_aig, &pattern, &vec![f]),
+                detects_faults_multi(&mux_aig, &pattern, &vec![f]),
+                "{}",
+                f
+            );
+        }
+        assert_eq!(Fault::all(&lut_aig).len(), 8);
+    }
+
+    #[test]
+    fn test_fault_effects() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x0 = aig.and(i0, i1);
+        let x1 = aig.and(x0, !i1);
+        let x2 = aig.xor(i0, i1);
+        aig.add_output(x1);
+        aig.add_output(x2);
+
+        let faults = vec![
+            Fault::OutputStuckAtFault {
+                gate: 0,
+                value: true,
+            },
+            Fault::OutputStuckAtFault {
+                gate: 2,
+                value: false,
+            },
+        ];
+        let pattern = vec![0b0110, 0b1010];
+        let effects = fault_effects_multi(&aig, &pattern, &faults);
+        // x0 is only visible on x1, when i1 is 0
+        assert_eq!(effects[0], vec![0, 1]);
+        assert_eq!(effects[1], vec![2]);
+        assert_eq!(
+            detects_faults_multi(&aig, &pattern, &faults),
+            vec![!0b1010, 0b1100]
+        );
+    }
+
+    #[test]
+    fn test_transition_faults() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x0 = aig.and(i0, i1);
+        let x1 = aig.xor(x0, i1);
+        aig.add_output(x0);
+        aig.add_output(x1);
+
+        let rise = Fault::OutputTransitionFault {
+            gate: 0,
+            slow_to_rise: true,
+        };
+        let fall = Fault::InputTransitionFault {
+            gate: 1,
+            input: 1,
+            slow_to_rise: false,
+        };
+        let faults = vec![rise, fall];
+        // Lanes: x0 rises in lane 0, falls in lane 1, stays at 1 in lane 2; i1 falls in lane 1
+        let launch = vec![0b0110, 0b0111];
+        let capture = vec![0b0101, 0b0101];
+        let detected = detects_transition_faults_multi(&aig, &launch, &capture, &faults);
+        assert_eq!(detected, vec![0b0001, 0b0010]);
+        // Without a launch cycle, transition faults are not detected
+        assert_eq!(detects_faults_multi(&aig, &capture, &faults), vec![0, 0]);
+
+        // Same result with a sequential simulation over the two cycles
+        let seq = vec![launch.clone(), capture.clone()];
+        let good = simulate_multi(&aig, &seq);
+        for (f, d) in zip(&faults, &detected) {
+            let faulty = simulate_multi_with_faults(&aig, &seq, &vec![*f]);
+            assert_eq!(faulty[0], good[0]);
+            let diff = zip(&faulty[1], &good[1]).fold(0, |acc, (a, b)| acc | (a ^ b));
+            assert_eq!(diff, *d);
+        }
+    }
+
+    #[test]
+    fn test_activation() {
+        let mut aig = Network::default();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x0 = aig.add(Gate::and(i0, !i1));
+        aig.add_output(x0);
+
+        let faults = vec![
+            Fault::OutputStuckAtFault {
+                gate: 0,
+                value: false,
+            },
+            Fault::InputStuckAtFault {
+                gate: 0,
+                input: 1,
+                value: false,
+            },
+        ];
+        let pattern = vec![0b0110, 0b1010];
+        assert_eq!(
+            activates_faults_multi(&aig, &pattern, &faults),
+            vec![0b0100, !0b1010]
+        );
+    }
+
+    /// Random sequential patterns with 1 to 3 timesteps, as a pattern file
+    fn pattern_file(nb_inputs: usize, nb_patterns: usize) -> Vec<u8> {
+        use rand::rngs::SmallRng;
+        use rand::{Rng, SeedableRng};
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut ret = Vec::new();
+        let mut w = crate::io::PatternWriter::new(&mut ret);
+        for _ in 0..nb_patterns {
+            let p: Vec<Vec<bool>> = (0..rng.gen_range(1..4))
+                .map(|_| (0..nb_inputs).map(|_| rng.gen()).collect())
+                .collect();
+            w.write(&p);
+        }
+        ret
+    }
+
+    #[test]
+    fn test_batch() {
+        use crate::network::generators::testcases::random_seq;
+        let aig = random_seq(8, 4, 40, 6, 1);
+        let data = pattern_file(8, 200);
+        let patterns = crate::io::read_patterns(data.as_slice()).unwrap();
+        let expected: Vec<Vec<Vec<bool>>> = patterns.iter().map(|p| simulate(&aig, p)).collect();
+        assert_eq!(super::simulate_batch(&aig, &patterns), expected);
+    }
+
+    #[test]
+    fn test_stream() {
+        use crate::io::{read_patterns, write_patterns, PatternReader, PatternWriter};
+        use crate::network::generators::testcases::random_seq;
+        let aig = random_seq(8, 4, 40, 6, 2);
+        let data = pattern_file(8, 20000);
+
+        let mut streamed = Vec::new();
+        let reader = PatternReader::new(data.as_slice());
+        let nb = super::simulate_stream(&aig, reader, &mut PatternWriter::new(&mut streamed), 7);
+        assert_eq!(nb, Ok(20000));
+
+        // Compare with the in-memory path on a prefix
+        let nb_prefix = 500;
+        let prefix: Vec<Vec<Vec<bool>>> = PatternReader::new(data.as_slice())
+            .take(nb_prefix)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let outputs: Vec<Vec<Vec<bool>>> = prefix.iter().map(|p| simulate(&aig, p)).collect();
+        let mut expected = Vec::new();
+        write_patterns(&mut expected, &outputs);
+        assert!(streamed.starts_with(&expected));
+        assert_eq!(read_patterns(streamed.as_slice()).unwrap().len(), 20000);
+    }
+
+    #[test]
+    fn test_wide() {
+        use super::{detects_faults_wide, simulate_comb_multi, simulate_comb_wide, SimWord, W256};
+        use crate::network::generators::testcases::random_seq;
+        use crate::util::rng::SeededRng;
+        use rand::Rng;
+
+        let aig = random_seq(8, 0, 60, 6, 3);
+        let faults = Fault::all(&aig);
+        let mut rng = SeededRng::new(1);
+        let pattern: Vec<W256> = (0..aig.nb_inputs())
+            .map(|_| super::Wide(rng.gen()))
+            .collect();
+        let outputs = simulate_comb_wide(&aig, &pattern);
+        let detections = detects_faults_wide(&aig, &pattern, &faults);
+        // Each 64b word of the wide simulation matches a 64b simulation
+        for k in 0..4 {
+            let narrow: Vec<u64> = pattern.iter().map(|w| w.0[k]).collect();
+            let expected = simulate_comb_multi(&aig, &narrow);
+            assert_eq!(expected, outputs.iter().map(|w| w.0[k]).collect::<Vec<_>>());
+            let expected = detects_faults_multi(&aig, &narrow, &faults);
+            assert_eq!(
+                expected,
+                detections.iter().map(|w| w.0[k]).collect::<Vec<_>>()
+            );
+        }
+        assert!(detections.iter().any(|d| d.count_ones() > 64));
+    }
+
+    #[test]
+    fn test_cached() {
+        use super::{detects_faults_cached, SimCache};
+        use crate::network::generators::testcases::random_seq;
+
+        let aig = random_seq(8, 0, 60, 6, 4);
+        let faults = Fault::all(&aig);
+        let mut cache = SimCache::new(&aig, 4);
+        let patterns: Vec<Vec<u64>> = (0..3u64)
+            .map(|k| {
+                (0..8)
+                    .map(|i| (i + k).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+                    .collect()
+            })
+            .collect();
+        for _ in 0..2 {
+            for p in &patterns {
+                assert_eq!(
+                    detects_faults_cached(&mut cache, p, &faults),
+                    detects_faults_multi(&aig, p, &faults)
+                );
+            }
+        }
+        assert_eq!(cache.stats().hits, 3);
+        assert_eq!(cache.stats().misses, 3);
+    }
+
+    #[test]
+    fn test_parallel() {
+        use super::{simulate_comb_multi, simulate_comb_parallel};
+        use crate::network::generators::testcases::random_seq;
+
+        let aig = random_seq(32, 0, 60000, 16, 5);
+        let mut levelized = aig.clone();
+        let (levels, _) = levelized.levelize();
+        assert!(levels.iter().any(|r| r.len() >= 4096));
+        let pattern: Vec<u64> = (0..32u64)
+            .map(|i| (i + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            .collect();
+        let expected = simulate_comb_multi(&aig, &pattern);
+        for nb_threads in [1, 4] {
+            assert_eq!(simulate_comb_parallel(&aig, &pattern, nb_threads), expected);
+        }
+    }
+}
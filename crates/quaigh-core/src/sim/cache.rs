@@ -0,0 +1,157 @@
+//! Caching of simulation results, for algorithms that simulate the same patterns repeatedly
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::Hash;
+
+use fxhash::FxHashMap;
+
+use crate::util::format::count;
+use crate::Network;
+
+use super::simple_sim::SimpleSimulator;
+use super::word::SimWord;
+
+/// Hit and miss counts of a [`SimCache`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SimCacheStats {
+    /// Number of lookups answered from the cache
+    pub hits: u64,
+    /// Number of lookups that required a simulation
+    pub misses: u64,
+    /// Number of entries removed to make room for new ones
+    pub evictions: u64,
+}
+
+impl SimCacheStats {
+    /// Fraction of the lookups answered from the cache, or 0 without any lookup
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+impl fmt::Display for SimCacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses ({:.1}% hit rate)",
+            count(self.hits as usize),
+            count(self.misses as usize),
+            100.0 * self.hit_rate()
+        )
+    }
+}
+
+/// Cache of the node values of a combinatorial network, keyed by the block of input values
+///
+/// Test pattern generation simulates the same patterns many times, for example when checking
+/// which additional faults a pattern detects. The values of all nodes are kept for the last
+/// blocks of patterns, and the oldest block is evicted once the capacity is reached.
+#[derive(Clone, Debug)]
+pub struct SimCache<'a, W: SimWord + Hash = u64> {
+    aig: &'a Network,
+    capacity: usize,
+    entries: FxHashMap<Box<[W]>, Box<[W]>>,
+    /// Cached input blocks, oldest first
+    order: VecDeque<Box<[W]>>,
+    stats: SimCacheStats,
+}
+
+impl<'a, W: SimWord + Hash> SimCache<'a, W> {
+    /// Create a cache holding the simulation results of at most `capacity` input blocks
+    pub fn new(aig: &'a Network, capacity: usize) -> SimCache<'a, W> {
+        assert!(aig.is_comb());
+        assert!(aig.is_topo_sorted());
+        assert!(capacity > 0);
+        SimCache {
+            aig,
+            capacity,
+            entries: FxHashMap::default(),
+            order: VecDeque::new(),
+            stats: SimCacheStats::default(),
+        }
+    }
+
+    /// Network being simulated
+    pub fn aig(&self) -> &'a Network {
+        self.aig
+    }
+
+    /// Values of all nodes for a block of input values, simulated if not in the cache
+    pub fn node_values(&mut self, input_values: &[W]) -> &[W] {
+        assert_eq!(input_values.len(), self.aig.nb_inputs());
+        if self.entries.contains_key(input_values) {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+            if self.order.len() == self.capacity {
+                let oldest = self.order.pop_front().unwrap();
+                self.entries.remove(&oldest);
+                self.stats.evictions += 1;
+            }
+            let mut sim = SimpleSimulator::from_aig(self.aig);
+            sim.copy_inputs(input_values);
+            sim.run_comb();
+            let key: Box<[W]> = input_values.into();
+            self.order.push_back(key.clone());
+            self.entries.insert(key, sim.node_values.into());
+        }
+        &self.entries[input_values]
+    }
+
+    /// Number of input blocks in the cache
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no input block is cached
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Remove all cached values, keeping the statistics
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    /// Hit and miss counts since the creation of the cache
+    pub fn stats(&self) -> SimCacheStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SimCache;
+    use crate::network::generators::adder;
+    use crate::sim::simulate_nodes_multi;
+
+    #[test]
+    fn test_cache() {
+        let aig = adder::ripple_carry(4);
+        let mut cache = SimCache::new(&aig, 2);
+        let a = vec![0b1010u64; 8];
+        let b = vec![0b0110u64; 8];
+        let c = vec![!0u64; 8];
+        assert_eq!(cache.node_values(&a), simulate_nodes_multi(&aig, &a));
+        assert_eq!(cache.node_values(&b), simulate_nodes_multi(&aig, &b));
+        assert_eq!(cache.node_values(&a), simulate_nodes_multi(&aig, &a));
+        assert_eq!(cache.stats().hits, 1);
+        assert_eq!(cache.stats().misses, 2);
+        // The oldest block is evicted first
+        cache.node_values(&c);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.stats().evictions, 1);
+        cache.node_values(&b);
+        cache.node_values(&a);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (2, 4, 2));
+        assert_eq!(stats.to_string(), "2 hits, 4 misses (33.3% hit rate)");
+    }
+}
@@ -0,0 +1,536 @@
+use std::fmt;
+
+use crate::network::{stats, NameTable, NaryType, SourceMap};
+use crate::{Gate, Network, Signal};
+
+/// Representation of a fault, with its type and location
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Fault {
+    /// Output stuck-at fault: the output of the given gate is stuck at a fixed value
+    OutputStuckAtFault {
+        /// Gate where the fault is located
+        gate: usize,
+        /// Fault value
+        value: bool,
+    },
+    /// Input stuck-at fault: the input of the given gate is stuck at a fixed value
+    InputStuckAtFault {
+        /// Gate where the fault is located
+        gate: usize,
+        /// Input where the fault is located
+        input: usize,
+        /// Fault value
+        value: bool,
+    },
+    /// Output transition fault: the output of the given gate is slow to rise or to fall
+    ///
+    /// The fault is tested with two cycles. In the capture cycle, the output behaves as if stuck
+    /// at the value it had in the launch cycle, if this is the initial value of the transition.
+    OutputTransitionFault {
+        /// Gate where the fault is located
+        gate: usize,
+        /// Whether the fault delays rising transitions rather than falling transitions
+        slow_to_rise: bool,
+    },
+    /// Input transition fault: the input of the given gate is slow to rise or to fall
+    InputTransitionFault {
+        /// Gate where the fault is located
+        gate: usize,
+        /// Input where the fault is located
+        input: usize,
+        /// Whether the fault delays rising transitions rather than falling transitions
+        slow_to_rise: bool,
+    },
+}
+
+/// Description of a fault location with net names, for correlation with layout tools
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct FaultDescription {
+    /// Net where the fault is located
+    pub net: String,
+    /// For input faults, the gate using the net, named after the net it drives, and the pin index
+    pub sink: Option<(String, usize)>,
+    /// Fault value; for transition faults, the initial value of the delayed transition
+    pub value: bool,
+    /// Whether this is a transition fault rather than a stuck-at fault
+    pub transition: bool,
+    /// Whether some of the names were generated because the nets are unnamed
+    pub generated: bool,
+    /// Source location of the faulty gate, as `file:line`, if known
+    ///
+    /// It is not part of the displayed description, which only uses net names.
+    pub location: Option<String>,
+}
+
+impl Fault {
+    /// Describe the fault location using net names
+    ///
+    /// Output faults are located on the net driven by the gate. Input faults are located on
+    /// the net connected to the pin, and also give the gate and pin index.
+    /// Unnamed nets fall back to a generated name, which is flagged in the result.
+    pub fn describe(&self, aig: &Network, names: &NameTable) -> FaultDescription {
+        let (gate_name, gate_generated) = names.name_or_generated(aig.node(self.gate()));
+        let value = self.as_stuck_at().value();
+        let transition = self.is_transition();
+        match self.input() {
+            None => FaultDescription {
+                net: gate_name,
+                sink: None,
+                value,
+                transition,
+                generated: gate_generated,
+                location: None,
+            },
+            Some(input) => {
+                let s = aig.dependencies(self.gate())[input];
+                let (net, net_generated) = names.name_or_generated(s);
+                FaultDescription {
+                    net,
+                    sink: Some((gate_name, input)),
+                    value,
+                    transition,
+                    generated: gate_generated || net_generated,
+                    location: None,
+                }
+            }
+        }
+    }
+
+    /// Describe the fault location using net names, with the source location of the gate
+    pub fn describe_with_locations(
+        &self,
+        aig: &Network,
+        names: &NameTable,
+        locations: &SourceMap,
+    ) -> FaultDescription {
+        FaultDescription {
+            location: locations.describe_node(self.gate()),
+            ..self.describe(aig, names)
+        }
+    }
+
+    /// Parse a fault from its description, as returned by [`describe`](Fault::describe)
+    ///
+    /// Generated names use node and input indices, so `x12 stuck-at-1` is always accepted for
+    /// unnamed nodes. Transition faults end with `slow-to-rise` or `slow-to-fall`.
+    pub fn parse(desc: &str, aig: &Network, names: &NameTable) -> Result<Fault, String> {
+        let parts: Vec<&str> = desc.split_whitespace().collect();
+        let (value, transition) = match parts.last() {
+            Some(&"stuck-at-0") => (false, false),
+            Some(&"stuck-at-1") => (true, false),
+            Some(&"slow-to-rise") => (false, true),
+            Some(&"slow-to-fall") => (true, true),
+            _ => {
+                return Err(format!(
+                "Fault {desc} should end with stuck-at-0, stuck-at-1, slow-to-rise or slow-to-fall"
+            ))
+            }
+        };
+        let resolve_gate = |name: &str| -> Result<usize, String> {
+            match names.resolve(name) {
+                Some(s) if s.is_var() && (s.var() as usize) < aig.nb_nodes() => {
+                    Ok(s.var() as usize)
+                }
+                _ => Err(format!("No gate drives net {name}")),
+            }
+        };
+        let fault = match parts.as_slice() {
+            [net, _] => Fault::OutputStuckAtFault {
+                gate: resolve_gate(net)?,
+                value,
+            },
+            [net, "->", sink, _] => {
+                let Some((sink, pin)) = sink.rsplit_once(':') else {
+                    return Err(format!("Fault {desc} should give the pin as gate:pin"));
+                };
+                let gate = resolve_gate(sink)?;
+                let input: usize = pin
+                    .parse()
+                    .map_err(|_| format!("Invalid pin {pin} in fault {desc}"))?;
+                let deps = aig.dependencies(gate);
+                if input >= deps.len() {
+                    return Err(format!("Gate {sink} has no pin {input}"));
+                }
+                if names.resolve(net) != Some(deps[input].without_inversion()) {
+                    return Err(format!("Pin {sink}:{input} is not connected to net {net}"));
+                }
+                Fault::InputStuckAtFault { gate, input, value }
+            }
+            _ => return Err(format!("Invalid fault description {desc}")),
+        };
+        Ok(if transition {
+            fault.to_transition()
+        } else {
+            fault
+        })
+    }
+
+    /// Get all possible faults in a network
+    pub fn all(aig: &Network) -> Vec<Fault> {
+        let mut ret = Vec::new();
+        for gate in 0..aig.nb_nodes() {
+            for value in [false, true] {
+                ret.push(Fault::OutputStuckAtFault { gate, value });
+            }
+            for input in 0..aig.dependencies(gate).len() {
+                for value in [false, true] {
+                    ret.push(Fault::InputStuckAtFault { gate, input, value });
+                }
+            }
+        }
+        ret
+    }
+
+    /// Get all possible transition faults in a network
+    pub fn all_transition(aig: &Network) -> Vec<Fault> {
+        Fault::all(aig)
+            .into_iter()
+            .map(|f| f.to_transition())
+            .collect()
+    }
+
+    /// Get all possible transition faults in a network, except those on single-use inputs
+    ///
+    /// Contrary to stuck-at faults, transition faults on the inputs of a gate are not covered by
+    /// faults on its output. Only transition faults on the input of a gate that is the single user
+    /// of its driver are redundant with the transition faults on the driver's output.
+    pub fn all_unique_transition(aig: &Network) -> Vec<Fault> {
+        let usage = stats::count_gate_usage(aig);
+        Fault::all_transition(aig)
+            .into_iter()
+            .filter(|f| match f.input() {
+                Some(input) => {
+                    let s = aig.dependencies(f.gate())[input];
+                    !(s.is_var() && usage[s.var() as usize] <= 1)
+                }
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Get all possible non-redundant faults in a network
+    pub fn all_unique(aig: &Network) -> Vec<Fault> {
+        let mut ret = Fault::all(aig);
+        let redundant = Fault::redundant_faults(aig);
+        ret.retain(|f| !redundant.binary_search(f).is_ok());
+        ret
+    }
+
+    /// List the redundant faults in a network
+    ///
+    /// A fault is redundant if it is covered by other faults.
+    /// The redundancy found here must be acyclic, so that we do not discard a group of equivalent faults.
+    /// When determining redundancy, we always keep the output stuck-at fault, and if equivalent
+    /// faults are the same type we keep the later one.
+    pub fn redundant_faults(aig: &Network) -> Vec<Fault> {
+        let usage = stats::count_gate_usage(aig);
+        // Returns whether the signal is a variable that is used once, so that its input stuck-at fault and output stuck-at fault are equivalent
+        let is_single_use = |s: &Signal| -> bool { s.is_var() && usage[s.var() as usize] <= 1 };
+        let mut ret = Vec::new();
+        for gate in 0..aig.nb_nodes() {
+            let g = aig.gate(gate);
+            for (input, s) in g.dependencies().iter().enumerate() {
+                for value in [false, true] {
+                    if is_single_use(s) {
+                        // Fault covered by a previous output stuck-at fault, because the output is used only once
+                        ret.push(Fault::InputStuckAtFault { gate, input, value });
+                    }
+                    if g.is_xor_like() || g.is_buf_like() {
+                        // Fault redundant because this is a Xor-like gate: it is equivalent to faults on the output
+                        ret.push(Fault::InputStuckAtFault { gate, input, value });
+                        if is_single_use(s) {
+                            ret.push(Fault::OutputStuckAtFault {
+                                gate: s.var() as usize,
+                                value,
+                            });
+                        }
+                    }
+                    if g.is_and_like() {
+                        // Some faults are redundant because this is an And-like gate: one of the values forces the gate
+                        let input_inv = matches!(
+                            g,
+                            Gate::Nary(_, NaryType::Or) | Gate::Nary(_, NaryType::Nor)
+                        );
+                        if value == input_inv {
+                            ret.push(Fault::InputStuckAtFault { gate, input, value });
+                            if is_single_use(s) {
+                                ret.push(Fault::OutputStuckAtFault {
+                                    gate: s.var() as usize,
+                                    value,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        ret.sort();
+        ret.dedup();
+        ret
+    }
+
+    /// Gate where the fault is located
+    pub fn gate(&self) -> usize {
+        match self {
+            Fault::OutputStuckAtFault { gate, .. } => *gate,
+            Fault::InputStuckAtFault { gate, .. } => *gate,
+            Fault::OutputTransitionFault { gate, .. } => *gate,
+            Fault::InputTransitionFault { gate, .. } => *gate,
+        }
+    }
+
+    /// Input of the gate where the fault is located, for input faults
+    pub fn input(&self) -> Option<usize> {
+        match self {
+            Fault::InputStuckAtFault { input, .. } | Fault::InputTransitionFault { input, .. } => {
+                Some(*input)
+            }
+            _ => None,
+        }
+    }
+
+    /// Signal where the fault is located: the output of the gate or the signal on its input
+    pub fn site(&self, aig: &Network) -> Signal {
+        match self.input() {
+            Some(input) => aig.dependencies(self.gate())[input],
+            None => aig.node(self.gate()),
+        }
+    }
+
+    /// Returns whether this is a transition fault
+    pub fn is_transition(&self) -> bool {
+        matches!(
+            self,
+            Fault::OutputTransitionFault { .. } | Fault::InputTransitionFault { .. }
+        )
+    }
+
+    /// Stuck-at fault with the same location that a transition fault behaves as in the capture
+    /// cycle: slow-to-rise faults are stuck at 0, and slow-to-fall faults at 1
+    pub fn as_stuck_at(&self) -> Fault {
+        match *self {
+            Fault::OutputTransitionFault { gate, slow_to_rise } => Fault::OutputStuckAtFault {
+                gate,
+                value: !slow_to_rise,
+            },
+            Fault::InputTransitionFault {
+                gate,
+                input,
+                slow_to_rise,
+            } => Fault::InputStuckAtFault {
+                gate,
+                input,
+                value: !slow_to_rise,
+            },
+            f => f,
+        }
+    }
+
+    /// Transition fault with the same location that behaves as a stuck-at fault in the capture cycle
+    pub fn to_transition(&self) -> Fault {
+        match *self {
+            Fault::OutputStuckAtFault { gate, value } => Fault::OutputTransitionFault {
+                gate,
+                slow_to_rise: !value,
+            },
+            Fault::InputStuckAtFault { gate, input, value } => Fault::InputTransitionFault {
+                gate,
+                input,
+                slow_to_rise: !value,
+            },
+            f => f,
+        }
+    }
+
+    /// Value of a stuck-at fault; for transition faults, the initial value of the transition
+    pub fn value(&self) -> bool {
+        match *self {
+            Fault::OutputStuckAtFault { value, .. } | Fault::InputStuckAtFault { value, .. } => {
+                value
+            }
+            Fault::OutputTransitionFault { slow_to_rise, .. }
+            | Fault::InputTransitionFault { slow_to_rise, .. } => !slow_to_rise,
+        }
+    }
+
+    /// Return true if there are two faults with the same gate in the vector
+    pub fn has_duplicate_gate(faults: &[Fault]) -> bool {
+        let mut gates: Vec<usize> = faults.iter().map(|f| f.gate()).collect();
+        gates.sort();
+        for i in 1..gates.len() {
+            if gates[i - 1] == gates[i] {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl fmt::Display for FaultDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.net)?;
+        if let Some((sink, pin)) = &self.sink {
+            write!(f, " -> {}:{}", sink, pin)?;
+        }
+        if self.transition {
+            write!(
+                f,
+                " {}",
+                if self.value {
+                    "slow-to-fall"
+                } else {
+                    "slow-to-rise"
+                }
+            )
+        } else {
+            write!(f, " stuck-at-{}", i32::from(self.value))
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::OutputStuckAtFault { gate, value } => {
+                write!(f, "Gate {} output stuck at {}", gate, i32::from(*value))
+            }
+            Fault::InputStuckAtFault { gate, input, value } => {
+                write!(
+                    f,
+                    "Gate {} input {} stuck at {}",
+                    gate,
+                    input,
+                    i32::from(*value)
+                )
+            }
+            Fault::OutputTransitionFault { gate, slow_to_rise } => {
+                write!(
+                    f,
+                    "Gate {} output slow to {}",
+                    gate,
+                    if *slow_to_rise { "rise" } else { "fall" }
+                )
+            }
+            Fault::InputTransitionFault {
+                gate,
+                input,
+                slow_to_rise,
+            } => {
+                write!(
+                    f,
+                    "Gate {} input {} slow to {}",
+                    gate,
+                    input,
+                    if *slow_to_rise { "rise" } else { "fall" }
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fault;
+    use crate::io::{read_bench_with_locations, read_bench_with_names, BenchArity, BenchDialect};
+    use crate::network::NameTable;
+    use crate::Network;
+
+    #[test]
+    fn test_describe() {
+        let example = "INPUT(a)\nINPUT(b)\nOUTPUT(n2)\nn1 = NAND(a, b)\nn2 = AND(n1, a, gnd)\n";
+        let (aig, names) = read_bench_with_names(example.as_bytes()).unwrap();
+        let n2 = names.find("n2").unwrap().var() as usize;
+        let d = Fault::OutputStuckAtFault {
+            gate: n2,
+            value: true,
+        }
+        .describe(&aig, &names);
+        assert_eq!(d.to_string(), "n2 stuck-at-1");
+        assert!(!d.generated);
+        let d = Fault::InputStuckAtFault {
+            gate: n2,
+            input: 1,
+            value: false,
+        }
+        .describe(&aig, &names);
+        assert_eq!(d.to_string(), "a -> n2:1 stuck-at-0");
+        let d = Fault::InputStuckAtFault {
+            gate: n2,
+            input: 2,
+            value: true,
+        }
+        .describe(&aig, &names);
+        assert_eq!(d.to_string(), "gnd -> n2:2 stuck-at-1");
+        assert_eq!(d.location, None);
+        assert!(Fault::parse("n3 stuck-at-1", &aig, &names).is_err());
+        assert!(Fault::parse("b -> n2:1 stuck-at-1", &aig, &names).is_err());
+        assert!(Fault::parse("a -> n2:3 stuck-at-1", &aig, &names).is_err());
+        assert!(Fault::parse("n2 stuck-at-2", &aig, &names).is_err());
+        let d = Fault::InputTransitionFault {
+            gate: n2,
+            input: 1,
+            slow_to_rise: true,
+        }
+        .describe(&aig, &names);
+        assert_eq!(d.to_string(), "a -> n2:1 slow-to-rise");
+        assert_eq!(
+            Fault::parse("n2 slow-to-fall", &aig, &names),
+            Ok(Fault::OutputTransitionFault {
+                gate: n2,
+                slow_to_rise: false
+            })
+        );
+
+        let (aig, names, locations) = read_bench_with_locations(
+            example.as_bytes(),
+            "example.bench",
+            BenchDialect::Auto,
+            BenchArity::Strict,
+        )
+        .unwrap();
+        let d = Fault::InputStuckAtFault {
+            gate: n2,
+            input: 1,
+            value: false,
+        }
+        .describe_with_locations(&aig, &names, &locations);
+        assert_eq!(d.to_string(), "a -> n2:1 stuck-at-0");
+        assert_eq!(d.location.as_deref(), Some("example.bench:5"));
+    }
+
+    fn check_roundtrip(aig: &Network, names: &NameTable) {
+        let mut faults = Fault::all(aig);
+        faults.extend(Fault::all_transition(aig));
+        let mut descriptions = Vec::new();
+        for f in &faults {
+            let d = f.describe(aig, names).to_string();
+            assert_eq!(Fault::parse(&d, aig, names), Ok(*f), "{}", d);
+            descriptions.push(d);
+        }
+        descriptions.sort();
+        descriptions.dedup();
+        assert_eq!(descriptions.len(), faults.len());
+    }
+
+    #[test]
+    fn test_describe_roundtrip() {
+        let example = "INPUT(a)\nINPUT(b)\nINPUT(x0)\nOUTPUT(o)\n\
+            o = XOR(m, x0)\n\
+            m = MUX(a, b, x0)\n\
+            q = DFF(o)\n\
+            x1 = NOT(q)\n\
+            r = OR(x1, a)\n\
+            OUTPUT(r)\n";
+        let (mut aig, mut names) = read_bench_with_names(example.as_bytes()).unwrap();
+        assert_eq!(names.input_name(2), Some("x0"));
+        check_roundtrip(&aig, &names);
+
+        // Without names, and after optimization with some nodes unnamed
+        check_roundtrip(&aig, &NameTable::new());
+        aig.add(crate::Gate::and(aig.input(0), aig.input(2)));
+        check_roundtrip(&aig, &names);
+        let translation = aig.make_canonical();
+        names.remap(&translation);
+        check_roundtrip(&aig, &names);
+    }
+}
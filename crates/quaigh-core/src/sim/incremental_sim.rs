@@ -0,0 +1,205 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::network::{stats, OutputKind};
+use crate::{Network, Signal};
+
+use super::simple_sim::{transition_value, SimpleSimulator};
+use super::word::SimWord;
+use super::Fault;
+
+/// Structure for simulation that only touches the values that were modified
+pub struct IncrementalSimulator<'a, W: SimWord = u64> {
+    /// Whether a gate is an output, other than an assumption
+    is_output: Vec<bool>,
+    /// Outputs that are assumptions
+    assumptions: Vec<Signal>,
+    /// Patterns that satisfy the assumptions
+    valid: W,
+    /// Gates that use each gate
+    gate_users: Vec<Vec<usize>>,
+    /// Simple simulator for the initial simulation
+    sim: SimpleSimulator<'a, W>,
+    /// Simulator that will be updated incrementally
+    incr_sim: SimpleSimulator<'a, W>,
+    /// Simulator for the launch cycle of transition faults, if any
+    launch_sim: Option<SimpleSimulator<'a, W>>,
+    /// Queue of nodes to update, lowest index first
+    update_queue: BinaryHeap<Reverse<usize>>,
+    /// List of modified value
+    touched_gates: Vec<usize>,
+    /// Whether each value is on the queue
+    is_touched: Vec<bool>,
+}
+
+impl<'a, W: SimWord> IncrementalSimulator<'a, W> {
+    /// Build a simulator by capturing a network
+    pub fn from_aig(aig: &'a Network) -> IncrementalSimulator<'a, W> {
+        assert!(aig.is_topo_sorted());
+        let sim = SimpleSimulator::from_aig(aig);
+        let incr_sim = sim.clone();
+        let mut is_output = vec![false; aig.nb_nodes()];
+        let mut assumptions = Vec::new();
+        for o in 0..aig.nb_outputs() {
+            let s = aig.output(o);
+            if aig.output_kind(o) == OutputKind::Assumption {
+                assumptions.push(s);
+            } else if s.is_var() {
+                is_output[s.var() as usize] = true;
+            }
+        }
+        IncrementalSimulator {
+            is_output,
+            assumptions,
+            valid: W::splat(true),
+            gate_users: stats::gate_users(aig),
+            sim,
+            incr_sim,
+            launch_sim: None,
+            update_queue: BinaryHeap::new(),
+            touched_gates: Vec::new(),
+            is_touched: vec![false; aig.nb_nodes()],
+        }
+    }
+
+    /// Reset the state of the simulator
+    fn reset(&mut self) {
+        for v in &self.touched_gates {
+            self.incr_sim.node_values[*v] = self.sim.node_values[*v];
+            self.is_touched[*v] = false;
+        }
+        assert!(self.update_queue.is_empty());
+        self.touched_gates.clear();
+    }
+
+    /// Run the simulation from a fault
+    ///
+    /// Faults are only detected by the patterns that satisfy the assumptions of the network.
+    pub fn run_initial(&mut self, input_values: &[W]) {
+        self.sim.reset();
+        self.sim.copy_inputs(input_values);
+        self.sim.run_comb();
+        self.finish_initial();
+    }
+
+    /// Start from the values of all nodes, already simulated for the given input values
+    pub fn run_initial_from_values(&mut self, input_values: &[W], node_values: &[W]) {
+        self.sim.reset();
+        self.sim.copy_inputs(input_values);
+        self.sim.node_values.copy_from_slice(node_values);
+        self.finish_initial();
+    }
+
+    /// Prepare the incremental simulation once the initial values are known
+    fn finish_initial(&mut self) {
+        self.incr_sim = self.sim.clone();
+        self.launch_sim = None;
+        self.valid = self
+            .assumptions
+            .iter()
+            .fold(W::splat(true), |acc, s| acc & self.sim.get_value(*s));
+    }
+
+    /// Run the simulation of a launch cycle and a capture cycle, to analyze transition faults
+    ///
+    /// Faults are then injected in the capture cycle. Without a launch cycle, transition faults are
+    /// never detected.
+    pub fn run_initial_transition(&mut self, launch_values: &[W], capture_values: &[W]) {
+        self.run_initial(launch_values);
+        let launch_sim = self.sim.clone();
+        let launch_valid = self.valid;
+        self.run_initial(capture_values);
+        self.launch_sim = Some(launch_sim);
+        self.valid = self.valid & launch_valid;
+    }
+
+    /// Update a single gate
+    fn update_gate(&mut self, i: usize, value: W) {
+        let old_val = self.incr_sim.node_values[i];
+        if old_val == value {
+            return;
+        }
+        if !self.is_touched[i] {
+            // Check it explicitly for the first gate
+            self.is_touched[i] = true;
+            self.touched_gates.push(i);
+        }
+        self.incr_sim.node_values[i] = value;
+        for &j in &self.gate_users[i] {
+            if !self.is_touched[j] {
+                self.is_touched[j] = true;
+                self.update_queue.push(Reverse(j));
+                self.touched_gates.push(j);
+            }
+        }
+    }
+
+    /// Run the simulation from a fault
+    fn run_incremental(&mut self, fault: Fault) {
+        match fault {
+            Fault::OutputStuckAtFault { gate, value } => {
+                self.update_gate(gate, W::splat(value));
+            }
+            Fault::InputStuckAtFault { gate, input, value } => {
+                let value = self.incr_sim.run_gate_with_input_stuck(gate, input, value);
+                self.update_gate(gate, value);
+            }
+            Fault::OutputTransitionFault { gate, .. } => {
+                if let Some(launch) = &self.launch_sim {
+                    let prev = launch.node_values[gate];
+                    let value = transition_value(&fault, Some(prev), self.sim.node_values[gate]);
+                    self.update_gate(gate, value);
+                }
+            }
+            Fault::InputTransitionFault { gate, input, .. } => {
+                if let Some(launch) = &self.launch_sim {
+                    let site = fault.site(self.sim.aig());
+                    let prev = launch.get_value(site);
+                    let site_value = transition_value(&fault, Some(prev), self.sim.get_value(site));
+                    let value = self
+                        .incr_sim
+                        .run_gate_with_input_value(gate, input, site_value);
+                    self.update_gate(gate, value);
+                }
+            }
+        }
+        while let Some(Reverse(i)) = self.update_queue.pop() {
+            let v = self.incr_sim.run_gate(i);
+            self.update_gate(i, v);
+        }
+    }
+
+    /// Whether an output has been modified by the incremental run
+    fn output_modified(&self) -> W {
+        let mut ret = W::splat(false);
+        for i in &self.touched_gates {
+            if self.is_output[*i] {
+                ret = ret | (self.incr_sim.node_values[*i] ^ self.sim.node_values[*i]);
+            }
+        }
+        ret
+    }
+
+    /// Whether the given fault is detected by the pattern
+    pub fn detects_fault(&mut self, fault: Fault) -> W {
+        self.run_incremental(fault);
+        let ret = self.output_modified() & self.valid;
+        self.reset();
+        ret
+    }
+
+    /// Gates whose value is modified by the given fault for at least one pattern
+    pub fn fault_effects(&mut self, fault: Fault) -> Vec<usize> {
+        self.run_incremental(fault);
+        let mut ret: Vec<usize> = self
+            .touched_gates
+            .iter()
+            .copied()
+            .filter(|i| self.incr_sim.node_values[*i] != self.sim.node_values[*i])
+            .collect();
+        ret.sort();
+        ret.dedup();
+        self.reset();
+        ret
+    }
+}
@@ -0,0 +1,120 @@
+//! Verification of multi-pattern simulation against single-pattern runs
+
+use rand::Rng;
+
+use crate::network::generators::testcases;
+use crate::util::rng::SeededRng;
+use crate::Network;
+
+use super::{simulate_multi_with_faults, Fault};
+
+/// Check that simulating 64 lanes at once gives the same result as simulating each lane alone
+///
+/// Each lane of the input words is an independent run. It is simulated alone, with its values
+/// broadcast to all lanes, and the outputs are compared bit-exactly with the packed simulation.
+/// Faults are injected in every lane. Returns a description of the first mismatch.
+pub fn check_lane_packing(
+    aig: &Network,
+    input_values: &Vec<Vec<u64>>,
+    faults: &Vec<Fault>,
+) -> Result<(), String> {
+    let packed = simulate_multi_with_faults(aig, input_values, faults);
+    for lane in 0..64 {
+        let scalar_input = input_values
+            .iter()
+            .map(|v| {
+                v.iter()
+                    .map(|w| 0u64.wrapping_sub((w >> lane) & 1))
+                    .collect()
+            })
+            .collect();
+        let scalar = simulate_multi_with_faults(aig, &scalar_input, faults);
+        for (t, (p, s)) in packed.iter().zip(scalar.iter()).enumerate() {
+            for (o, (pv, sv)) in p.iter().zip(s.iter()).enumerate() {
+                if *sv != 0 && *sv != !0 {
+                    return Err(format!(
+                        "Lane {lane} leaks into other lanes on output {o} at cycle {t}"
+                    ));
+                }
+                if (pv >> lane) & 1 != sv & 1 {
+                    return Err(format!(
+                        "Lane {lane} differs from its packed simulation on output {o} at cycle {t}"
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run [`check_lane_packing`] on random sequential designs, with and without faults
+///
+/// Returns the number of simulations checked, or a description of the first mismatch.
+pub fn selftest_lane_packing(nb_designs: usize, seed: u64) -> Result<usize, String> {
+    let mut rng = SeededRng::new(seed);
+    let mut nb_checked = 0;
+    for _ in 0..nb_designs {
+        let design_seed = rng.gen();
+        let aig = testcases::random_seq(6, 4, 40, 6, design_seed);
+        let nb_cycles = rng.gen_range(1..12);
+        let input_values: Vec<Vec<u64>> = (0..nb_cycles)
+            .map(|_| (0..aig.nb_inputs()).map(|_| rng.gen()).collect())
+            .collect();
+
+        // A few faults on distinct gates, including the flip-flops
+        let mut faults = Vec::new();
+        for f in Fault::all(&aig) {
+            if rng.gen_ratio(1, 20) && faults.iter().all(|g: &Fault| g.gate() != f.gate()) {
+                faults.push(f);
+            }
+        }
+        for faults in [Vec::new(), faults] {
+            check_lane_packing(&aig, &input_values, &faults)
+                .map_err(|e| format!("{e} (design seed {design_seed}, faults {faults:?})"))?;
+            nb_checked += 1;
+        }
+    }
+    Ok(nb_checked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_lane_packing, selftest_lane_packing};
+    use crate::sim::{simulate_with_faults, Fault};
+    use crate::{Network, Signal};
+
+    #[test]
+    fn test_selftest() {
+        assert_eq!(selftest_lane_packing(50, 1), Ok(100));
+    }
+
+    #[test]
+    fn test_dff_input_fault() {
+        let mut aig = Network::new();
+        let d = aig.add_input();
+        let en = aig.add_input();
+        let x = aig.dff(d, en, Signal::zero());
+        aig.add_output(x);
+        let pattern = vec![vec![true, true], vec![false, true], vec![false, true]];
+        let faults = vec![Fault::InputStuckAtFault {
+            gate: 0,
+            input: 0,
+            value: true,
+        }];
+        assert_eq!(
+            simulate_with_faults(&aig, &pattern, &faults),
+            vec![vec![false], vec![true], vec![true]]
+        );
+        let faults = vec![Fault::InputStuckAtFault {
+            gate: 0,
+            input: 1,
+            value: false,
+        }];
+        assert_eq!(
+            simulate_with_faults(&aig, &pattern, &faults),
+            vec![vec![false], vec![false], vec![false]]
+        );
+        let input_values = vec![vec![0x00ff_00ff, 0x0f0f_0f0f], vec![0x3333, !0]];
+        assert!(check_lane_packing(&aig, &input_values, &faults).is_ok());
+    }
+}
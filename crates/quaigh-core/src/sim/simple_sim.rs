@@ -0,0 +1,551 @@
+use volute::Lut;
+
+use crate::network::{BinaryType, ClockId, Levelization, NaryType, TernaryType};
+use crate::{Network, Signal};
+
+use super::word::SimWord;
+use super::Fault;
+
+/// Structure for simulation based directly on the network representation
+///
+/// This is simple to write and relatively efficient, but could be greatly improved
+/// with a regular and- or mux-based structure.
+///
+/// Each bit of a word is an independent lane: all operations are bitwise, and lanes never
+/// interact. Faults are injected in every lane, so that each lane is a run of the same faulty
+/// design. Words are 64b by default, and [`Wide`](super::Wide) words simulate more patterns in
+/// each pass.
+///
+/// Transparent latches are evaluated with the combinatorial gates, from the value they held at
+/// the previous timestep.
+#[derive(Clone, Debug)]
+pub struct SimpleSimulator<'a, W: SimWord = u64> {
+    aig: &'a Network,
+    pub input_values: Vec<W>,
+    pub node_values: Vec<W>,
+    /// Values of the latches at the previous timestep, empty if there are none
+    latch_values: Vec<W>,
+}
+
+/// Convert the inversion to a word for bitwise operations
+fn pol_to_word<W: SimWord>(s: Signal) -> W {
+    W::splat(s.is_inverted())
+}
+
+/// Value of a stuck-at fault, identical in all lanes
+fn broadcast<W: SimWord>(value: bool) -> W {
+    W::splat(value)
+}
+
+/// Value at the site of a transition fault, given the value in the previous cycle
+///
+/// A slow-to-rise site stays at 0 if it was 0 in the previous cycle, and a slow-to-fall site stays
+/// at 1. Without a previous cycle, the fault has no effect.
+pub(crate) fn transition_value<W: SimWord>(fault: &Fault, prev: Option<W>, good: W) -> W {
+    match prev {
+        None => good,
+        Some(p) => {
+            if fault.value() {
+                p | good
+            } else {
+                p & good
+            }
+        }
+    }
+}
+
+/// Initial values of the latches of a network, only allocated if there are any
+fn latch_values<W: SimWord>(aig: &Network) -> Vec<W> {
+    if aig.has_latches() {
+        vec![W::splat(false); aig.nb_nodes()]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Minimum number of gates evaluated by each thread in level-by-level simulation
+const MIN_PARALLEL_CHUNK: usize = 1024;
+
+/// Majority function
+fn maj<W: SimWord>(a: W, b: W, c: W) -> W {
+    (b & c) | (a & (b | c))
+}
+
+/// Multiplexer function
+fn mux<W: SimWord>(s: W, a: W, b: W) -> W {
+    (s & a) | (!s & b)
+}
+
+impl<'a, W: SimWord> SimpleSimulator<'a, W> {
+    /// Build a simulator by capturing a network
+    pub fn from_aig(aig: &'a Network) -> SimpleSimulator<'a, W> {
+        assert!(aig.is_topo_sorted());
+        SimpleSimulator {
+            aig,
+            input_values: vec![W::splat(false); aig.nb_inputs()],
+            node_values: vec![W::splat(false); aig.nb_nodes()],
+            latch_values: latch_values(aig),
+        }
+    }
+
+    /// Network being simulated
+    pub(crate) fn aig(&self) -> &'a Network {
+        self.aig
+    }
+
+    /// Run the simulation
+    pub fn run(&mut self, input_values: &[Vec<W>]) -> Vec<Vec<W>> {
+        self.check();
+        self.reset();
+        let mut ret = Vec::new();
+        for (i, v) in input_values.iter().enumerate() {
+            if i != 0 {
+                self.run_dff();
+            }
+            self.copy_inputs(v.as_slice());
+            self.run_comb();
+            ret.push(self.get_output_values());
+        }
+        ret
+    }
+
+    /// Run the simulation with several clock domains
+    ///
+    /// Between timesteps `i` and `i + 1`, only the flip-flops whose clock domain appears in
+    /// `edges[i]` are updated.
+    pub fn run_with_clocks(
+        &mut self,
+        input_values: &[Vec<W>],
+        edges: &[Vec<ClockId>],
+    ) -> Vec<Vec<W>> {
+        assert!(edges.len() + 1 >= input_values.len());
+        self.check();
+        self.reset();
+        let mut ret = Vec::new();
+        for (i, v) in input_values.iter().enumerate() {
+            if i != 0 {
+                self.update_dff(&[], &[], Some(&edges[i - 1]));
+            }
+            self.copy_inputs(v.as_slice());
+            self.run_comb();
+            ret.push(self.get_output_values());
+        }
+        ret
+    }
+
+    /// Run the simulation with a list of stuck-at-fault or transition fault errors
+    ///
+    /// Transition faults have no effect on the first timestep.
+    pub fn run_with_faults(
+        &mut self,
+        input_values: &Vec<Vec<W>>,
+        faults: &Vec<Fault>,
+    ) -> Vec<Vec<W>> {
+        self.check();
+        self.reset();
+        let mut ret = Vec::new();
+        let mut sites: Option<Vec<W>> = None;
+        for v in input_values {
+            if let Some(s) = &sites {
+                self.update_dff(faults, s, None);
+            }
+            self.copy_inputs(v.as_slice());
+            sites = Some(self.run_comb_with_faults_after(faults, sites.as_deref()));
+            ret.push(self.get_output_values());
+        }
+        ret
+    }
+
+    pub fn reset(&mut self) {
+        self.input_values = vec![W::splat(false); self.aig.nb_inputs()];
+        self.node_values = vec![W::splat(false); self.aig.nb_nodes()];
+        self.latch_values = latch_values(self.aig);
+    }
+
+    fn check(&self) {
+        assert!(self.aig.is_topo_sorted());
+        assert_eq!(self.input_values.len(), self.aig.nb_inputs());
+        assert_eq!(self.node_values.len(), self.aig.nb_nodes());
+    }
+
+    // Get the value of a signal in the current state
+    pub(crate) fn get_value(&self, s: Signal) -> W {
+        if s == Signal::zero() {
+            W::splat(false)
+        } else if s == Signal::one() {
+            W::splat(true)
+        } else if s.is_input() {
+            self.input_values[s.input() as usize] ^ pol_to_word(s)
+        } else {
+            debug_assert!(s.is_var());
+            self.node_values[s.var() as usize] ^ pol_to_word(s)
+        }
+    }
+
+    // Copy the values of the inputs to the internal state
+    pub fn copy_inputs(&mut self, inputs: &[W]) {
+        assert_eq!(inputs.len(), self.input_values.len());
+        self.input_values.copy_from_slice(inputs);
+    }
+
+    // Copy the values of the flip-flops for the next cycle
+    pub fn run_dff(&mut self) {
+        self.run_dff_with_faults(&[]);
+    }
+
+    // Copy the values of the flip-flops for the next cycle, with stuck-at faults on their inputs
+    pub fn run_dff_with_faults(&mut self, faults: &[Fault]) {
+        self.update_dff(faults, &[], None);
+    }
+
+    // Copy the values of the flip-flops of some clock domains, or of all flip-flops if None
+    //
+    // Transition faults on their inputs use the site values of the current cycle, if given.
+    // Latches keep their value, whatever the clock domain.
+    fn update_dff(&mut self, faults: &[Fault], sites: &[W], edges: Option<&[ClockId]>) {
+        use crate::network::GateRef::*;
+        let mut next_values = self.node_values.clone();
+        for i in 0..self.aig.nb_nodes() {
+            let g = self.aig.gate_ref(i);
+            if g.is_latch() {
+                self.latch_values[i] = self.node_values[i];
+            }
+            if let Dff(deps, clock) = g {
+                if edges.is_some_and(|e| !e.contains(&clock)) {
+                    continue;
+                }
+                let mut v = deps.map(|s| self.get_value(s));
+                for (k, f) in faults.iter().enumerate() {
+                    match f {
+                        Fault::InputStuckAtFault { gate, input, value } if *gate == i => {
+                            v[*input] = broadcast(*value);
+                        }
+                        Fault::InputTransitionFault { gate, input, .. } if *gate == i => {
+                            if let Some(site) = sites.get(k) {
+                                v[*input] = *site;
+                            }
+                        }
+                        _ => (),
+                    }
+                }
+                let [dv, env, resv] = v;
+                let prevv = self.node_values[i];
+                let val = !resv & ((env & dv) | (!env & prevv));
+                next_values[i] = val;
+            }
+        }
+        self.node_values = next_values;
+    }
+
+    /// Return the result of a single gate
+    pub fn run_gate(&self, i: usize) -> W {
+        use crate::network::GateRef::*;
+        let g = self.aig.gate_ref(i);
+        match g {
+            Binary([a, b], tp) => {
+                let va = self.get_value(*a);
+                let vb = self.get_value(*b);
+                match tp {
+                    BinaryType::And => va & vb,
+                    BinaryType::Xor => va ^ vb,
+                }
+            }
+            Ternary([a, b, c], tp) => {
+                let va = self.get_value(*a);
+                let vb = self.get_value(*b);
+                let vc = self.get_value(*c);
+                match tp {
+                    TernaryType::And => va & vb & vc,
+                    TernaryType::Xor => va ^ vb ^ vc,
+                    TernaryType::Maj => maj(va, vb, vc),
+                    TernaryType::Mux => mux(va, vb, vc),
+                }
+            }
+            Dff(..) => self.node_values[i],
+            Latch([d, en, res]) => {
+                let (vd, ven, vres) = (
+                    self.get_value(*d),
+                    self.get_value(*en),
+                    self.get_value(*res),
+                );
+                !vres & mux(ven, vd, self.latch_values[i])
+            }
+            Nary(v, tp) => match tp {
+                NaryType::And => self.compute_andn(v, false, false),
+                NaryType::Or => self.compute_andn(v, true, true),
+                NaryType::Nand => self.compute_andn(v, false, true),
+                NaryType::Nor => self.compute_andn(v, true, false),
+                NaryType::Xor => self.compute_xorn(v, false),
+                NaryType::Xnor => self.compute_xorn(v, true),
+            },
+            Buf(s) => self.get_value(*s),
+            Lut(inputs, lut) => self.compute_lut(lut, inputs),
+        }
+    }
+
+    /// Return the result of a single gate with a fault on an input
+    pub fn run_gate_with_input_stuck(&self, i: usize, input: usize, value: bool) -> W {
+        // TODO: this is an ugly duplication but I don't see how to make it cleaner
+        assert!(input < self.aig.dependencies(i).len());
+        let v = broadcast(value);
+        use crate::network::GateRef::*;
+        let g = self.aig.gate_ref(i);
+        match g {
+            Binary([a, b], tp) => {
+                let va = if input == 0 { v } else { self.get_value(*a) };
+                let vb = if input == 1 { v } else { self.get_value(*b) };
+                match tp {
+                    BinaryType::And => va & vb,
+                    BinaryType::Xor => va ^ vb,
+                }
+            }
+            Ternary([a, b, c], tp) => {
+                let va = if input == 0 { v } else { self.get_value(*a) };
+                let vb = if input == 1 { v } else { self.get_value(*b) };
+                let vc = if input == 2 { v } else { self.get_value(*c) };
+                match tp {
+                    TernaryType::And => va & vb & vc,
+                    TernaryType::Xor => va ^ vb ^ vc,
+                    TernaryType::Maj => maj(va, vb, vc),
+                    TernaryType::Mux => mux(va, vb, vc),
+                }
+            }
+            Dff(..) => self.node_values[i],
+            Latch([d, en, res]) => {
+                let vd = if input == 0 { v } else { self.get_value(*d) };
+                let ven = if input == 1 { v } else { self.get_value(*en) };
+                let vres = if input == 2 { v } else { self.get_value(*res) };
+                !vres & mux(ven, vd, self.latch_values[i])
+            }
+            Nary(v, tp) => match tp {
+                NaryType::And => self.compute_andn_with_input_stuck(v, false, false, input, value),
+                NaryType::Or => self.compute_andn_with_input_stuck(v, true, true, input, value),
+                NaryType::Nand => self.compute_andn_with_input_stuck(v, false, true, input, value),
+                NaryType::Nor => self.compute_andn_with_input_stuck(v, true, false, input, value),
+                NaryType::Xor => self.compute_xorn_with_input_stuck(v, false, input, value),
+                NaryType::Xnor => self.compute_xorn_with_input_stuck(v, true, input, value),
+            },
+            Buf(_) => v,
+            Lut(inputs, lut) => self.compute_lut_with_input_stuck(lut, inputs, input, value),
+        }
+    }
+
+    /// Return the result of a single gate with an input forced to the given value
+    pub fn run_gate_with_input_value(&self, i: usize, input: usize, value: W) -> W {
+        let v0 = self.run_gate_with_input_stuck(i, input, false);
+        let v1 = self.run_gate_with_input_stuck(i, input, true);
+        mux(value, v1, v0)
+    }
+
+    /// Run the combinatorial part of the design with a list of stuck-at-fault or transition fault errors
+    ///
+    /// `prev_sites` are the values at the site of each fault in the previous cycle, as returned by
+    /// the previous call; without them, transition faults have no effect. Returns the values at
+    /// the site of each transition fault for this cycle.
+    pub fn run_comb_with_faults_after(
+        &mut self,
+        faults: &[Fault],
+        prev_sites: Option<&[W]>,
+    ) -> Vec<W> {
+        assert!(!Fault::has_duplicate_gate(faults));
+        let prev = |k: usize| prev_sites.map(|p| p[k]);
+        let mut sites = vec![W::splat(false); faults.len()];
+        for i in 0..self.aig.nb_nodes() {
+            self.node_values[i] = self.run_gate(i);
+            for (k, f) in faults.iter().enumerate() {
+                if f.gate() != i {
+                    continue;
+                }
+                match f {
+                    Fault::OutputStuckAtFault { value, .. } => {
+                        self.node_values[i] = broadcast(*value);
+                    }
+                    Fault::InputStuckAtFault { input, value, .. } => {
+                        self.node_values[i] = self.run_gate_with_input_stuck(i, *input, *value);
+                    }
+                    Fault::OutputTransitionFault { .. } => {
+                        sites[k] = transition_value(f, prev(k), self.node_values[i]);
+                        self.node_values[i] = sites[k];
+                    }
+                    Fault::InputTransitionFault { input, .. } => {
+                        if !self.aig.gate_ref(i).is_dff() {
+                            let good = self.get_value(f.site(self.aig));
+                            sites[k] = transition_value(f, prev(k), good);
+                            self.node_values[i] =
+                                self.run_gate_with_input_value(i, *input, sites[k]);
+                        }
+                    }
+                }
+            }
+        }
+        // Flip-flop inputs are only known once all gates have been simulated
+        for (k, f) in faults.iter().enumerate() {
+            if let Fault::InputTransitionFault { gate, .. } = f {
+                if self.aig.gate_ref(*gate).is_dff() {
+                    let good = self.get_value(f.site(self.aig));
+                    sites[k] = transition_value(f, prev(k), good);
+                }
+            }
+        }
+        sites
+    }
+
+    /// Run the combinatorial part of the design
+    pub fn run_comb(&mut self) {
+        for i in 0..self.aig.nb_nodes() {
+            self.node_values[i] = self.run_gate(i);
+        }
+    }
+
+    /// Run the combinatorial part of the design level by level, on several threads
+    ///
+    /// The network must have been sorted by [`Network::levelize`]. The gates of a level are
+    /// independent, and large levels are split between the threads.
+    pub fn run_comb_levels(&mut self, levels: &Levelization, nb_threads: usize) {
+        assert_eq!(levels.nb_nodes(), self.aig.nb_nodes());
+        debug_assert!(levels.is_valid(self.aig));
+        for r in levels.iter() {
+            if nb_threads <= 1 || r.len() < 2 * MIN_PARALLEL_CHUNK {
+                for i in r {
+                    self.node_values[i] = self.run_gate(i);
+                }
+                continue;
+            }
+            let chunk = r.len().div_ceil(nb_threads).max(MIN_PARALLEL_CHUNK);
+            let sim = &*self;
+            let values: Vec<Vec<W>> = std::thread::scope(|scope| {
+                let handles: Vec<_> = r
+                    .clone()
+                    .step_by(chunk)
+                    .map(|start| {
+                        let end = (start + chunk).min(r.end);
+                        scope.spawn(move || (start..end).map(|i| sim.run_gate(i)).collect())
+                    })
+                    .collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect()
+            });
+            for (k, v) in values.iter().enumerate() {
+                let start = r.start + k * chunk;
+                self.node_values[start..start + v.len()].copy_from_slice(v);
+            }
+        }
+    }
+
+    fn compute_andn(&self, v: &[Signal], inv_in: bool, inv_out: bool) -> W {
+        let mut ret = W::splat(true);
+        for s in v {
+            ret = ret & self.get_value(s ^ inv_in);
+        }
+        if inv_out {
+            !ret
+        } else {
+            ret
+        }
+    }
+
+    fn compute_xorn(&self, v: &[Signal], inv_out: bool) -> W {
+        let mut ret = W::splat(false);
+        for s in v {
+            ret = ret ^ self.get_value(*s);
+        }
+        if inv_out {
+            !ret
+        } else {
+            ret
+        }
+    }
+
+    fn compute_andn_with_input_stuck(
+        &self,
+        v: &[Signal],
+        inv_in: bool,
+        inv_out: bool,
+        input: usize,
+        value: bool,
+    ) -> W {
+        let val = broadcast(value ^ inv_in);
+        let mut ret = W::splat(true);
+        for (i, s) in v.iter().enumerate() {
+            ret = ret
+                & if i == input {
+                    val
+                } else {
+                    self.get_value(s ^ inv_in)
+                };
+        }
+        if inv_out {
+            !ret
+        } else {
+            ret
+        }
+    }
+
+    fn compute_xorn_with_input_stuck(
+        &self,
+        v: &[Signal],
+        inv_out: bool,
+        input: usize,
+        value: bool,
+    ) -> W {
+        let val = broadcast(value);
+        let mut ret = W::splat(false);
+        for (i, s) in v.iter().enumerate() {
+            ret = ret ^ if i == input { val } else { self.get_value(*s) };
+        }
+        if inv_out {
+            !ret
+        } else {
+            ret
+        }
+    }
+
+    fn get_output_values(&self) -> Vec<W> {
+        let mut ret = Vec::new();
+        for o in 0..self.aig.nb_outputs() {
+            ret.push(self.get_value(self.aig.output(o)));
+        }
+        ret
+    }
+
+    fn compute_lut_with_input_stuck(
+        &self,
+        lut: &Lut,
+        signals: &[Signal],
+        input: usize,
+        value: bool,
+    ) -> W {
+        let val = broadcast(value);
+        let signals = signals
+            .iter()
+            .enumerate()
+            .map(|(i, s)| if i == input { val } else { self.get_value(*s) })
+            .collect::<Vec<_>>();
+        compute_lut(lut, &signals)
+    }
+
+    fn compute_lut(&self, lut: &Lut, signals: &[Signal]) -> W {
+        let signals: Vec<_> = signals.iter().map(|s| self.get_value(*s)).collect();
+
+        compute_lut(lut, &signals)
+    }
+}
+
+/// Evaluate a Lut on all the lanes of a word at once
+///
+/// The truth table is reduced one variable at a time, with a bitwise multiplexer between the
+/// cofactors, so that the cost does not depend on the number of patterns.
+#[inline]
+fn compute_lut<W: SimWord>(lut: &Lut, signals: &[W]) -> W {
+    assert_eq!(lut.num_vars(), signals.len());
+    let mut values: Vec<W> = (0..lut.num_bits())
+        .map(|m| broadcast(lut.value(m)))
+        .collect();
+    let mut len = values.len();
+    for s in signals {
+        len /= 2;
+        for i in 0..len {
+            values[i] = mux(*s, values[2 * i + 1], values[2 * i]);
+        }
+    }
+    values[0]
+}
@@ -0,0 +1,253 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+
+use crate::network::FanoutView;
+use crate::sim::simple_sim::SimpleSimulator;
+use crate::{Network, Signal};
+
+/// Timing of the signals during one clock cycle of a timing simulation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CycleTiming {
+    /// Values of the outputs once the network is stable
+    pub outputs: Vec<bool>,
+    /// Time of the last transition of each output in the cycle, or 0 if it does not change
+    pub arrival: Vec<usize>,
+    /// Time of the last transition of any gate in the cycle
+    pub settle_time: usize,
+    /// Number of transitions of the gates in the cycle
+    pub nb_transitions: usize,
+    /// Number of glitches: transitions in addition to the one needed to reach the final value
+    pub nb_glitches: usize,
+}
+
+impl fmt::Display for CycleTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "settled at {}, output arrival {}, {} transitions, {} glitches",
+            self.settle_time,
+            self.arrival.iter().max().copied().unwrap_or(0),
+            self.nb_transitions,
+            self.nb_glitches
+        )
+    }
+}
+
+/// Event-driven simulator with a transport delay on each gate
+///
+/// Values are kept in a [`SimpleSimulator`], using a single lane.
+pub struct TimingSimulator<'a> {
+    sim: SimpleSimulator<'a>,
+    delays: &'a [usize],
+    fanouts: FanoutView,
+    /// Pending value changes, ordered by time then by creation
+    events: BinaryHeap<Reverse<(usize, usize, usize, u64)>>,
+    nb_events: usize,
+}
+
+impl<'a> TimingSimulator<'a> {
+    /// Build a timing simulator, with the delay of each node
+    pub fn new(aig: &'a Network, delays: &'a [usize]) -> TimingSimulator<'a> {
+        assert_eq!(delays.len(), aig.nb_nodes());
+        let mut sim = SimpleSimulator::from_aig(aig);
+        sim.run_comb();
+        TimingSimulator {
+            sim,
+            delays,
+            fanouts: FanoutView::new(aig),
+            events: BinaryHeap::new(),
+            nb_events: 0,
+        }
+    }
+
+    /// Schedule the evaluation of the combinatorial gates using the given nodes or inputs
+    ///
+    /// Each gate is evaluated once with the current values, and its new value is applied after
+    /// its delay.
+    fn schedule_fanouts(&mut self, changed: &[Signal], time: usize) {
+        let aig = self.sim.aig();
+        let mut gates: Vec<usize> = changed
+            .iter()
+            .flat_map(|s| self.fanouts.fanouts(*s))
+            .copied()
+            .filter(|i| aig.gate_ref(*i).is_comb())
+            .collect();
+        gates.sort();
+        gates.dedup();
+        for i in gates {
+            let v = self.sim.run_gate(i);
+            self.events
+                .push(Reverse((time + self.delays[i], self.nb_events, i, v)));
+            self.nb_events += 1;
+        }
+    }
+
+    /// Run one clock cycle: the inputs and flip-flops change at time 0, then the changes
+    /// propagate through the gates
+    ///
+    /// Flip-flops are not updated for the first cycle.
+    pub fn run_cycle(&mut self, inputs: &[bool], first: bool) -> CycleTiming {
+        let aig = self.sim.aig();
+        let start = self.sim.node_values.clone();
+        let mut last_change = vec![0; aig.nb_nodes()];
+        let mut nb_changes = vec![0; aig.nb_nodes()];
+        let mut changed = Vec::new();
+
+        if !first {
+            self.sim.run_dff();
+        }
+        for (i, v) in inputs.iter().enumerate() {
+            let v = if *v { !0 } else { 0 };
+            if self.sim.input_values[i] != v {
+                self.sim.input_values[i] = v;
+                changed.push(aig.input(i));
+            }
+        }
+        for i in 0..aig.nb_nodes() {
+            if self.sim.node_values[i] != start[i] {
+                nb_changes[i] += 1;
+                changed.push(aig.node(i));
+            }
+        }
+        self.schedule_fanouts(&changed, 0);
+
+        // Apply all changes happening at the same time before evaluating the gates again, so that
+        // simultaneous changes do not create glitches
+        while let Some(Reverse((time, ..))) = self.events.peek().copied() {
+            let mut touched = Vec::new();
+            while let Some(Reverse((t, _, i, v))) = self.events.peek().copied() {
+                if t != time {
+                    break;
+                }
+                self.events.pop();
+                touched.push((i, self.sim.node_values[i]));
+                self.sim.node_values[i] = v;
+            }
+            touched.sort_by_key(|(i, _)| *i);
+            touched.dedup_by_key(|(i, _)| *i);
+            changed.clear();
+            for (i, prev) in touched {
+                if self.sim.node_values[i] != prev {
+                    last_change[i] = time;
+                    nb_changes[i] += 1;
+                    changed.push(aig.node(i));
+                }
+            }
+            self.schedule_fanouts(&changed, time);
+        }
+
+        let outputs: Vec<Signal> = (0..aig.nb_outputs()).map(|o| aig.output(o)).collect();
+        let arrival = outputs
+            .iter()
+            .map(|s| {
+                if s.is_var() {
+                    last_change[s.var() as usize]
+                } else {
+                    0
+                }
+            })
+            .collect();
+        let nb_transitions = nb_changes.iter().sum();
+        let nb_needed = (0..aig.nb_nodes())
+            .filter(|i| self.sim.node_values[*i] != start[*i])
+            .count();
+        CycleTiming {
+            outputs: outputs
+                .iter()
+                .map(|s| self.sim.get_value(*s) & 1 != 0)
+                .collect(),
+            arrival,
+            settle_time: last_change.iter().max().copied().unwrap_or(0),
+            nb_transitions,
+            nb_glitches: nb_transitions - nb_needed,
+        }
+    }
+}
+
+/// Simulate a network over multiple timesteps with a delay on each gate; return the timing of
+/// each cycle
+///
+/// The network starts stable, with all inputs and flip-flops at 0. At each cycle, the inputs and
+/// flip-flops change at time 0, and each gate reacts to a change of its inputs after its delay.
+/// Glitches, when a gate changes several times before reaching its final value, are counted.
+/// The values of the outputs are the same as with [`simulate`](super::simulate).
+pub fn simulate_timing(
+    a: &Network,
+    delays: &[usize],
+    input_values: &[Vec<bool>],
+) -> Vec<CycleTiming> {
+    let mut sim = TimingSimulator::new(a, delays);
+    input_values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| sim.run_cycle(v, i == 0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simulate_timing;
+    use crate::network::depth::DelayParameters;
+    use crate::network::generators::adder;
+    use crate::sim::simulate;
+    use crate::{Network, Signal};
+
+    #[test]
+    fn test_glitch() {
+        // x = a ^ !a is constant, but the inverter delays one of the paths
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let n = aig.add(crate::Gate::Buf(!a));
+        let b = aig.add(crate::Gate::Buf(n));
+        let x = aig.xor(a, b);
+        aig.add_output(x);
+        let delays = vec![1, 2, 1];
+
+        let timing = simulate_timing(&aig, &delays, &[vec![false], vec![true], vec![true]]);
+        assert_eq!(timing[0].outputs, vec![true]);
+        assert_eq!(timing[0].nb_transitions, 0);
+        // a rises at 0, x falls at 1 then rises again at 4 when b falls at 3
+        assert_eq!(timing[1].outputs, vec![true]);
+        assert_eq!(timing[1].arrival, vec![4]);
+        assert_eq!(timing[1].settle_time, 4);
+        assert_eq!(timing[1].nb_transitions, 4);
+        assert_eq!(timing[1].nb_glitches, 2);
+        assert_eq!(timing[2].nb_transitions, 0);
+    }
+
+    #[test]
+    fn test_values() {
+        let aig = adder::ripple_carry(4);
+        let delays = DelayParameters::vlsi().gate_delays(&aig);
+        let patterns: Vec<Vec<bool>> = (0..20)
+            .map(|i| {
+                (0..aig.nb_inputs())
+                    .map(|j| (i * 7 + j * 3) % 5 < 2)
+                    .collect()
+            })
+            .collect();
+        let timing = simulate_timing(&aig, &delays, &patterns);
+        let expected = simulate(&aig, &patterns);
+        let depth = DelayParameters::vlsi().depth(&aig);
+        for (t, e) in timing.iter().zip(expected.iter()) {
+            assert_eq!(&t.outputs, e);
+            assert!(t.settle_time <= depth);
+        }
+    }
+
+    #[test]
+    fn test_dff() {
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let d = aig.dff(a, Signal::one(), Signal::zero());
+        let x = aig.and(d, a);
+        aig.add_output(x);
+        let delays = vec![0, 3];
+        let timing = simulate_timing(&aig, &delays, &[vec![true], vec![true]]);
+        assert_eq!(timing[0].outputs, vec![false]);
+        assert_eq!(timing[1].outputs, vec![true]);
+        assert_eq!(timing[1].arrival, vec![3]);
+        assert_eq!(timing[1].nb_glitches, 0);
+    }
+}
@@ -0,0 +1,160 @@
+//! Relative likelihood of faults, for weighted fault coverage
+
+use std::collections::HashMap;
+
+use crate::network::{BinaryType, NameTable, NaryType, TernaryType};
+use crate::{Gate, Network};
+
+use super::Fault;
+
+/// Relative likelihood of each fault, for example from foundry defect density data
+///
+/// Weights are read from a text file with one weight per line, followed by what it applies to:
+/// ```text
+/// # Faults in the carry chain are more likely
+/// 10 c3 stuck-at-0
+/// 10 a -> c3:1 stuck-at-1
+/// # Default weight for the faults of all Xor gates
+/// 2 type XOR
+/// # Default weight for all other faults, 1 if not given
+/// 0.5 default
+/// ```
+/// Faults use the same descriptions as fault reports. A weight given for a fault takes precedence
+/// over the weight of its gate type, which takes precedence over the default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaultWeights {
+    default: f64,
+    gate_types: HashMap<String, f64>,
+    faults: HashMap<Fault, f64>,
+}
+
+impl Default for FaultWeights {
+    fn default() -> Self {
+        FaultWeights {
+            default: 1.0,
+            gate_types: HashMap::new(),
+            faults: HashMap::new(),
+        }
+    }
+}
+
+/// Name of the type of a gate, as used in .bench files
+pub fn gate_type_name(g: &Gate) -> &'static str {
+    match g {
+        Gate::Binary(_, BinaryType::And) | Gate::Ternary(_, TernaryType::And) => "AND",
+        Gate::Binary(_, BinaryType::Xor) | Gate::Ternary(_, TernaryType::Xor) => "XOR",
+        Gate::Ternary(_, TernaryType::Mux) => "MUX",
+        Gate::Ternary(_, TernaryType::Maj) => "MAJ",
+        Gate::Nary(_, NaryType::And) => "AND",
+        Gate::Nary(_, NaryType::Or) => "OR",
+        Gate::Nary(_, NaryType::Nand) => "NAND",
+        Gate::Nary(_, NaryType::Nor) => "NOR",
+        Gate::Nary(_, NaryType::Xor) => "XOR",
+        Gate::Nary(_, NaryType::Xnor) => "XNOR",
+        Gate::Buf(s) if s.is_inverted() => "NOT",
+        Gate::Buf(_) => "BUF",
+        Gate::Dff(..) => "DFF",
+        Gate::Latch(..) => "LATCH",
+        Gate::Lut(_) => "LUT",
+    }
+}
+
+impl FaultWeights {
+    /// Parse a weight file, resolving fault descriptions in the network
+    pub fn parse(text: &str, aig: &Network, names: &NameTable) -> Result<FaultWeights, String> {
+        let mut ret = FaultWeights::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let err = |e: String| format!("Line {}: {}", i + 1, e);
+            let (weight, target) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let weight: f64 = weight
+                .parse()
+                .map_err(|_| err(format!("Invalid weight {}", weight)))?;
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(err(format!("Weight {} should be non-negative", weight)));
+            }
+            let target = target.trim();
+            if target == "default" {
+                ret.default = weight;
+            } else if let Some(tp) = target.strip_prefix("type ") {
+                ret.gate_types.insert(tp.trim().to_uppercase(), weight);
+            } else {
+                let fault = Fault::parse(target, aig, names).map_err(err)?;
+                ret.faults.insert(fault, weight);
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Weight of a fault
+    pub fn weight(&self, aig: &Network, fault: &Fault) -> f64 {
+        if let Some(w) = self.faults.get(fault) {
+            return *w;
+        }
+        let tp = gate_type_name(&aig.gate(fault.gate()));
+        *self.gate_types.get(tp).unwrap_or(&self.default)
+    }
+
+    /// Weights of a list of faults
+    pub fn weights(&self, aig: &Network, faults: &[Fault]) -> Vec<f64> {
+        faults.iter().map(|f| self.weight(aig, f)).collect()
+    }
+
+    /// Weighted coverage, as the weight of the detected faults and the total weight
+    pub fn coverage(&self, aig: &Network, detections: &[(Fault, bool)]) -> (f64, f64) {
+        let mut detected = 0.0;
+        let mut total = 0.0;
+        for (f, d) in detections {
+            let w = self.weight(aig, f);
+            total += w;
+            if *d {
+                detected += w;
+            }
+        }
+        (detected, total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FaultWeights;
+    use crate::io::read_bench_with_names;
+    use crate::sim::Fault;
+
+    #[test]
+    fn test_parse() {
+        let example = "INPUT(a)
+INPUT(b)
+OUTPUT(n2)
+n1 = AND(a, b)
+n2 = XOR(n1, a)
+";
+        let (aig, names) = read_bench_with_names(example.as_bytes()).unwrap();
+        let weights = "# Comment
+10 n1 stuck-at-0
+3 a -> n2:1 stuck-at-1  # Trailing comment
+2 type xor
+0.5 default
+";
+        let w = FaultWeights::parse(weights, &aig, &names).unwrap();
+        let n1 = names.find("n1").unwrap().var() as usize;
+        let n2 = names.find("n2").unwrap().var() as usize;
+        let out = |gate, value| Fault::OutputStuckAtFault { gate, value };
+        assert_eq!(w.weight(&aig, &out(n1, false)), 10.0);
+        assert_eq!(w.weight(&aig, &out(n1, true)), 0.5);
+        assert_eq!(w.weight(&aig, &out(n2, false)), 2.0);
+        let pin = Fault::parse("a -> n2:1 stuck-at-1", &aig, &names).unwrap();
+        assert_eq!(w.weight(&aig, &pin), 3.0);
+
+        let detections = [(out(n1, false), true), (out(n1, true), false), (pin, true)];
+        assert_eq!(w.coverage(&aig, &detections), (13.0, 13.5));
+
+        for bad in ["x n1 stuck-at-0", "-1 default", "1 n3 stuck-at-0", "1 n1"] {
+            let err = FaultWeights::parse(bad, &aig, &names).unwrap_err();
+            assert!(err.starts_with("Line 1: "), "{}", err);
+        }
+    }
+}
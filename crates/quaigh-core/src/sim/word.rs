@@ -0,0 +1,184 @@
+//! Words of simulation lanes, for bit-parallel multi-pattern simulation
+
+use std::fmt::Debug;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
+
+/// A word of independent simulation lanes
+///
+/// All operations are bitwise, so that each lane is an independent run of the simulation.
+/// Wider words simulate more patterns in each pass over the network: [`u64`] has 64 lanes, and
+/// [`Wide`] words are made of several of them, that the compiler can vectorize.
+pub trait SimWord:
+    Copy
+    + Send
+    + Sync
+    + Debug
+    + Eq
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitXor<Output = Self>
+    + Not<Output = Self>
+{
+    /// Number of lanes in the word
+    const LANES: usize;
+
+    /// Word with the same value in all lanes
+    fn splat(value: bool) -> Self;
+
+    /// Value of a single lane
+    fn lane(&self, i: usize) -> bool;
+
+    /// Set the value of a single lane
+    fn set_lane(&mut self, i: usize, value: bool);
+
+    /// Number of lanes at 1
+    fn count_ones(&self) -> usize;
+
+    /// Returns true if all lanes are at 0
+    fn is_zero(&self) -> bool {
+        *self == Self::splat(false)
+    }
+}
+
+impl SimWord for u64 {
+    const LANES: usize = 64;
+
+    fn splat(value: bool) -> Self {
+        0u64.wrapping_sub(value as u64)
+    }
+
+    fn lane(&self, i: usize) -> bool {
+        (self >> i) & 1 != 0
+    }
+
+    fn set_lane(&mut self, i: usize, value: bool) {
+        *self = (*self & !(1 << i)) | ((value as u64) << i);
+    }
+
+    fn count_ones(&self) -> usize {
+        u64::count_ones(*self) as usize
+    }
+}
+
+/// A word of N x 64 lanes
+///
+/// Lane `i` is bit `i % 64` of the `i / 64`-th 64b word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Wide<const N: usize>(pub [u64; N]);
+
+/// Word of 256 lanes
+pub type W256 = Wide<4>;
+
+/// Word of 512 lanes
+pub type W512 = Wide<8>;
+
+impl<const N: usize> Default for Wide<N> {
+    fn default() -> Self {
+        Wide([0; N])
+    }
+}
+
+impl<const N: usize> BitAnd for Wide<N> {
+    type Output = Self;
+
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self {
+        Wide(std::array::from_fn(|i| self.0[i] & rhs.0[i]))
+    }
+}
+
+impl<const N: usize> BitOr for Wide<N> {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Wide(std::array::from_fn(|i| self.0[i] | rhs.0[i]))
+    }
+}
+
+impl<const N: usize> BitXor for Wide<N> {
+    type Output = Self;
+
+    #[inline]
+    fn bitxor(self, rhs: Self) -> Self {
+        Wide(std::array::from_fn(|i| self.0[i] ^ rhs.0[i]))
+    }
+}
+
+impl<const N: usize> Not for Wide<N> {
+    type Output = Self;
+
+    #[inline]
+    fn not(self) -> Self {
+        Wide(self.0.map(|w| !w))
+    }
+}
+
+impl<const N: usize> SimWord for Wide<N> {
+    const LANES: usize = 64 * N;
+
+    fn splat(value: bool) -> Self {
+        Wide([u64::splat(value); N])
+    }
+
+    fn lane(&self, i: usize) -> bool {
+        self.0[i / 64].lane(i % 64)
+    }
+
+    fn set_lane(&mut self, i: usize, value: bool) {
+        self.0[i / 64].set_lane(i % 64, value);
+    }
+
+    fn count_ones(&self) -> usize {
+        self.0.iter().map(SimWord::count_ones).sum()
+    }
+}
+
+/// Pack patterns into words, one pattern per lane
+///
+/// Lanes without a pattern repeat the first one. There must be at least one pattern, and at most
+/// as many as lanes.
+pub fn pack_lanes<W: SimWord>(patterns: &[Vec<bool>]) -> Vec<W> {
+    assert!(!patterns.is_empty() && patterns.len() <= W::LANES);
+    let mut ret = vec![W::splat(false); patterns[0].len()];
+    for lane in 0..W::LANES {
+        let p = patterns.get(lane).unwrap_or(&patterns[0]);
+        assert_eq!(p.len(), ret.len());
+        for (w, b) in ret.iter_mut().zip(p) {
+            w.set_lane(lane, *b);
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pack_lanes, SimWord, W256};
+
+    #[test]
+    fn test_lanes() {
+        let mut w = W256::splat(false);
+        w.set_lane(3, true);
+        w.set_lane(200, true);
+        assert!(w.lane(3) && w.lane(200) && !w.lane(4));
+        assert_eq!(w.count_ones(), 2);
+        assert_eq!((!w).count_ones(), 254);
+        assert_eq!((w ^ w), W256::splat(false));
+        assert!((w & !w).is_zero());
+        assert_eq!(w | !w, W256::splat(true));
+        w.set_lane(3, false);
+        assert_eq!(w.count_ones(), 1);
+    }
+
+    #[test]
+    fn test_pack() {
+        let patterns = vec![vec![true, false], vec![false, true], vec![true, true]];
+        let words: Vec<W256> = pack_lanes(&patterns);
+        assert!(words[0].lane(0) && !words[0].lane(1) && words[0].lane(2));
+        assert!(!words[1].lane(0) && words[1].lane(1) && words[1].lane(2));
+        // Unused lanes repeat the first pattern
+        assert!(words[0].lane(255) && !words[1].lane(255));
+        let words: Vec<u64> = pack_lanes(&patterns);
+        assert_eq!(words, vec![!0 ^ 2, 6]);
+    }
+}
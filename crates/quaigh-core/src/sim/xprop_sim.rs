@@ -0,0 +1,242 @@
+use volute::Lut;
+
+use crate::network::{BinaryType, NaryType, TernaryType};
+use crate::{Network, Signal};
+
+/// Dual-rail value of a signal, with one bit per lane on each rail
+///
+/// A bit is set in `can0` if the value may be 0 in this lane, and in `can1` if it may be 1.
+/// Unknown values (X) have both bits set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XValue {
+    pub can0: u64,
+    pub can1: u64,
+}
+
+impl XValue {
+    /// Unknown value in all lanes
+    pub fn unknown() -> XValue {
+        XValue { can0: !0, can1: !0 }
+    }
+
+    /// Known value in all lanes
+    pub fn known(value: u64) -> XValue {
+        XValue {
+            can0: !value,
+            can1: value,
+        }
+    }
+
+    fn invert(self) -> XValue {
+        XValue {
+            can0: self.can1,
+            can1: self.can0,
+        }
+    }
+
+    fn invert_if(self, inv: bool) -> XValue {
+        if inv {
+            self.invert()
+        } else {
+            self
+        }
+    }
+
+    fn and(self, o: XValue) -> XValue {
+        XValue {
+            can0: self.can0 | o.can0,
+            can1: self.can1 & o.can1,
+        }
+    }
+
+    fn xor(self, o: XValue) -> XValue {
+        XValue {
+            can0: (self.can0 & o.can0) | (self.can1 & o.can1),
+            can1: (self.can0 & o.can1) | (self.can1 & o.can0),
+        }
+    }
+
+    fn maj(self, b: XValue, c: XValue) -> XValue {
+        XValue {
+            can0: (b.can0 & c.can0) | (self.can0 & (b.can0 | c.can0)),
+            can1: (b.can1 & c.can1) | (self.can1 & (b.can1 | c.can1)),
+        }
+    }
+
+    /// Multiplexer: the value is exact if both data inputs are known and identical
+    fn mux(self, a: XValue, b: XValue) -> XValue {
+        XValue {
+            can0: (self.can1 & a.can0) | (self.can0 & b.can0),
+            can1: (self.can1 & a.can1) | (self.can0 & b.can1),
+        }
+    }
+}
+
+/// Structure for three-valued simulation, with unknown values propagated on two rails
+///
+/// As for [`SimpleSimulator`](super::simple_sim::SimpleSimulator), each bit of a 64b word is an
+/// independent lane. Flip-flops and latches start in an unknown state, until they are reset or
+/// loaded with a known value.
+#[derive(Clone, Debug)]
+pub struct XPropSimulator<'a> {
+    aig: &'a Network,
+    pub input_values: Vec<XValue>,
+    pub node_values: Vec<XValue>,
+    /// Values of the latches at the previous timestep
+    latch_values: Vec<XValue>,
+}
+
+impl<'a> XPropSimulator<'a> {
+    /// Build a simulator by capturing a network
+    pub fn from_aig(aig: &'a Network) -> XPropSimulator<'a> {
+        assert!(aig.is_topo_sorted());
+        XPropSimulator {
+            aig,
+            input_values: vec![XValue::unknown(); aig.nb_inputs()],
+            node_values: vec![XValue::unknown(); aig.nb_nodes()],
+            latch_values: vec![XValue::unknown(); aig.nb_nodes()],
+        }
+    }
+
+    /// Run the simulation
+    pub fn run(&mut self, input_values: &[Vec<XValue>]) -> Vec<Vec<XValue>> {
+        self.reset();
+        let mut ret = Vec::new();
+        for (i, v) in input_values.iter().enumerate() {
+            if i != 0 {
+                self.run_dff();
+            }
+            self.copy_inputs(v.as_slice());
+            self.run_comb();
+            ret.push(self.get_output_values());
+        }
+        ret
+    }
+
+    /// Reset the state of the simulator, with all values unknown
+    pub fn reset(&mut self) {
+        self.input_values = vec![XValue::unknown(); self.aig.nb_inputs()];
+        self.node_values = vec![XValue::unknown(); self.aig.nb_nodes()];
+        self.latch_values = vec![XValue::unknown(); self.aig.nb_nodes()];
+    }
+
+    // Get the value of a signal in the current state
+    fn get_value(&self, s: Signal) -> XValue {
+        if s.is_constant() {
+            XValue::known(0).invert_if(s == Signal::one())
+        } else if s.is_input() {
+            self.input_values[s.input() as usize].invert_if(s.is_inverted())
+        } else {
+            self.node_values[s.var() as usize].invert_if(s.is_inverted())
+        }
+    }
+
+    // Copy the values of the inputs to the internal state
+    pub fn copy_inputs(&mut self, inputs: &[XValue]) {
+        assert_eq!(inputs.len(), self.input_values.len());
+        self.input_values.copy_from_slice(inputs);
+    }
+
+    // Copy the values of the flip-flops for the next cycle
+    pub fn run_dff(&mut self) {
+        let mut next_values = self.node_values.clone();
+        for (i, next) in next_values.iter_mut().enumerate() {
+            if self.aig.gate_ref(i).is_latch() {
+                self.latch_values[i] = self.node_values[i];
+            }
+            if let crate::network::GateRef::Dff([d, en, res], _) = self.aig.gate_ref(i) {
+                let loaded = self
+                    .get_value(*en)
+                    .mux(self.get_value(*d), self.node_values[i]);
+                *next = loaded.and(self.get_value(*res).invert());
+            }
+        }
+        self.node_values = next_values;
+    }
+
+    /// Return the result of a single gate
+    pub fn run_gate(&self, i: usize) -> XValue {
+        use crate::network::GateRef::*;
+        match self.aig.gate_ref(i) {
+            Binary([a, b], tp) => {
+                let (va, vb) = (self.get_value(*a), self.get_value(*b));
+                match tp {
+                    BinaryType::And => va.and(vb),
+                    BinaryType::Xor => va.xor(vb),
+                }
+            }
+            Ternary([a, b, c], tp) => {
+                let va = self.get_value(*a);
+                let vb = self.get_value(*b);
+                let vc = self.get_value(*c);
+                match tp {
+                    TernaryType::And => va.and(vb).and(vc),
+                    TernaryType::Xor => va.xor(vb).xor(vc),
+                    TernaryType::Maj => va.maj(vb, vc),
+                    TernaryType::Mux => va.mux(vb, vc),
+                }
+            }
+            Dff(..) => self.node_values[i],
+            Latch([d, en, res]) => {
+                let loaded = self
+                    .get_value(*en)
+                    .mux(self.get_value(*d), self.latch_values[i]);
+                loaded.and(self.get_value(*res).invert())
+            }
+            Nary(v, tp) => match tp {
+                NaryType::And => self.compute_andn(v, false, false),
+                NaryType::Or => self.compute_andn(v, true, true),
+                NaryType::Nand => self.compute_andn(v, false, true),
+                NaryType::Nor => self.compute_andn(v, true, false),
+                NaryType::Xor => self.compute_xorn(v, false),
+                NaryType::Xnor => self.compute_xorn(v, true),
+            },
+            Buf(s) => self.get_value(*s),
+            Lut(inputs, lut) => self.compute_lut(lut, inputs),
+        }
+    }
+
+    /// Run the combinatorial part of the design
+    pub fn run_comb(&mut self) {
+        for i in 0..self.aig.nb_nodes() {
+            self.node_values[i] = self.run_gate(i);
+        }
+    }
+
+    fn compute_andn(&self, v: &[Signal], inv_in: bool, inv_out: bool) -> XValue {
+        v.iter()
+            .fold(XValue::known(!0), |acc, s| {
+                acc.and(self.get_value(*s).invert_if(inv_in))
+            })
+            .invert_if(inv_out)
+    }
+
+    fn compute_xorn(&self, v: &[Signal], inv_out: bool) -> XValue {
+        v.iter()
+            .fold(XValue::known(0), |acc, s| acc.xor(self.get_value(*s)))
+            .invert_if(inv_out)
+    }
+
+    /// The output may take a value if one of the minterms compatible with the inputs has it
+    fn compute_lut(&self, lut: &Lut, signals: &[Signal]) -> XValue {
+        let values: Vec<XValue> = signals.iter().map(|s| self.get_value(*s)).collect();
+        let mut ret = XValue { can0: 0, can1: 0 };
+        for m in 0..1usize << values.len() {
+            let possible = values.iter().enumerate().fold(!0, |acc, (i, v)| {
+                acc & if (m >> i) & 1 != 0 { v.can1 } else { v.can0 }
+            });
+            if lut.value(m) {
+                ret.can1 |= possible;
+            } else {
+                ret.can0 |= possible;
+            }
+        }
+        ret
+    }
+
+    fn get_output_values(&self) -> Vec<XValue> {
+        (0..self.aig.nb_outputs())
+            .map(|i| self.get_value(self.aig.output(i)))
+            .collect()
+    }
+}
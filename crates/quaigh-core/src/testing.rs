@@ -0,0 +1,557 @@
+//! Random networks and property checks, to test quaigh and the tools built on it
+//!
+//! [`RandomNetwork`] generates random networks with a configurable gate mix, depth and number of
+//! flip-flops. Properties are functions that return an error message when a network exposes a
+//! bug, such as [`check_roundtrip`]. [`check_property`] runs a property on many random networks
+//! and [`shrink`]s the first failing network to a small reproducer.
+//!
+//! ```
+//! # use quaigh_core::testing::{check_property, check_roundtrip, RandomNetwork};
+//! let generator = RandomNetwork {
+//!     nb_dffs: 2,
+//!     ..RandomNetwork::default()
+//! };
+//! check_property(&generator, 0..10, check_roundtrip).unwrap();
+//! ```
+
+use std::fmt;
+
+use rand::Rng;
+use volute::Lut;
+
+use crate::io::{
+    read_aiger, read_bench, read_blif, read_quaigh, write_aiger, write_bench, write_blif,
+    write_quaigh,
+};
+use crate::network::{ClockId, NaryType};
+use crate::sim::simulate_multi;
+use crate::util::rng::SeededRng;
+use crate::{Gate, Network, QuaighError, Signal};
+
+/// Number of timesteps simulated when comparing networks
+const NB_COMPARISON_STEPS: usize = 4;
+
+/// Relative frequency of each kind of gate in random networks
+///
+/// A kind of gate with weight 0 is never generated. At least one weight must be non-zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GateMix {
+    /// Weight of And2
+    pub and: u32,
+    /// Weight of Xor2
+    pub xor: u32,
+    /// Weight of And3
+    pub and3: u32,
+    /// Weight of Xor3
+    pub xor3: u32,
+    /// Weight of Mux
+    pub mux: u32,
+    /// Weight of Maj
+    pub maj: u32,
+    /// Weight of Buf
+    pub buf: u32,
+    /// Weight of N-ary gates, with 1 to 5 inputs
+    pub nary: u32,
+    /// Weight of 3-input Luts
+    pub lut: u32,
+}
+
+impl GateMix {
+    /// Only And2 gates, as in an And-Inverter Graph
+    pub fn aig() -> GateMix {
+        GateMix {
+            and: 1,
+            xor: 0,
+            and3: 0,
+            xor3: 0,
+            mux: 0,
+            maj: 0,
+            buf: 0,
+            nary: 0,
+            lut: 0,
+        }
+    }
+
+    /// Only the canonical gates: And, Xor, Mux, Maj and Lut
+    pub fn canonical() -> GateMix {
+        GateMix {
+            and: 1,
+            xor: 1,
+            and3: 0,
+            xor3: 0,
+            mux: 1,
+            maj: 1,
+            buf: 0,
+            nary: 0,
+            lut: 1,
+        }
+    }
+
+    /// Generate a random gate, with inputs taken from the given signals
+    fn random_gate(&self, rng: &mut SeededRng, signals: &[Signal]) -> Gate {
+        let weights = [
+            self.and, self.xor, self.and3, self.xor3, self.mux, self.maj, self.buf, self.nary,
+            self.lut,
+        ];
+        let total: u32 = weights.iter().sum();
+        assert!(
+            total > 0,
+            "At least one kind of gate must have a non-zero weight"
+        );
+        let mut r = rng.gen_range(0..total);
+        let mut kind = 0;
+        while r >= weights[kind] {
+            r -= weights[kind];
+            kind += 1;
+        }
+        let a = pick(rng, signals);
+        let b = pick(rng, signals);
+        let c = pick(rng, signals);
+        match kind {
+            0 => Gate::and(a, b),
+            1 => Gate::xor(a, b),
+            2 => Gate::and3(a, b, c),
+            3 => Gate::xor3(a, b, c),
+            4 => Gate::mux(a, b, c),
+            5 => Gate::maj(a, b, c),
+            6 => Gate::Buf(a),
+            7 => {
+                let v: Vec<Signal> = (0..rng.gen_range(1..6))
+                    .map(|_| pick(rng, signals))
+                    .collect();
+                let tp = [
+                    NaryType::And,
+                    NaryType::Or,
+                    NaryType::Nand,
+                    NaryType::Nor,
+                    NaryType::Xor,
+                    NaryType::Xnor,
+                ][rng.gen_range(0..6)];
+                Gate::Nary(v.into(), tp)
+            }
+            _ => {
+                let mut lut = Lut::zero(3);
+                for m in 0..8 {
+                    if rng.gen() {
+                        lut.set_bit(m);
+                    }
+                }
+                Gate::lut(&[a, b, c], lut)
+            }
+        }
+    }
+}
+
+impl Default for GateMix {
+    fn default() -> Self {
+        GateMix {
+            and: 1,
+            xor: 1,
+            and3: 1,
+            xor3: 1,
+            mux: 1,
+            maj: 1,
+            buf: 1,
+            nary: 1,
+            lut: 3,
+        }
+    }
+}
+
+/// Pick a random signal, possibly inverted, or a constant from time to time
+fn pick(rng: &mut SeededRng, signals: &[Signal]) -> Signal {
+    if signals.is_empty() || rng.gen_ratio(1, 20) {
+        Signal::from(rng.gen::<bool>())
+    } else {
+        signals[rng.gen_range(0..signals.len())] ^ rng.gen::<bool>()
+    }
+}
+
+/// Parameters of random network generation
+///
+/// Flip-flops have a random enable and reset half of the time. The generated networks are
+/// topologically sorted, with the flip-flops first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RandomNetwork {
+    /// Number of inputs
+    pub nb_inputs: usize,
+    /// Number of outputs
+    pub nb_outputs: usize,
+    /// Number of combinatorial gates
+    pub nb_gates: usize,
+    /// Number of flip-flops
+    pub nb_dffs: usize,
+    /// Maximum logic depth of the combinatorial gates, if any
+    pub max_depth: Option<usize>,
+    /// Relative frequency of each kind of gate
+    pub gate_mix: GateMix,
+}
+
+impl Default for RandomNetwork {
+    fn default() -> Self {
+        RandomNetwork {
+            nb_inputs: 8,
+            nb_outputs: 4,
+            nb_gates: 50,
+            nb_dffs: 0,
+            max_depth: None,
+            gate_mix: GateMix::default(),
+        }
+    }
+}
+
+impl RandomNetwork {
+    /// Generate a random network; the same seed always yields the same network
+    pub fn generate(&self, seed: u64) -> Network {
+        let mut rng = SeededRng::new(seed);
+        let max_depth = self.max_depth.unwrap_or(usize::MAX);
+        let mut ret = Network::new();
+        ret.add_inputs(self.nb_inputs);
+        // Flip-flops come first and are connected at the end, so they can use any signal
+        for _ in 0..self.nb_dffs {
+            ret.add(Gate::dff(Signal::zero(), Signal::one(), Signal::zero()));
+        }
+        let mut signals: Vec<Signal> = (0..self.nb_inputs)
+            .map(|i| ret.input(i))
+            .chain((0..self.nb_dffs).map(|i| ret.node(i)))
+            .collect();
+        let mut levels = vec![0; self.nb_dffs];
+        // Signals that can be used by a gate without exceeding the maximum depth
+        let mut shallow = if max_depth > 0 {
+            signals.clone()
+        } else {
+            Vec::new()
+        };
+        for _ in 0..self.nb_gates {
+            let g = self.gate_mix.random_gate(&mut rng, &shallow);
+            let level = 1 + g.vars().map(|v| levels[v as usize]).max().unwrap_or(0);
+            let s = ret.add(g);
+            levels.push(level);
+            signals.push(s);
+            if level < max_depth {
+                shallow.push(s);
+            }
+        }
+        for i in 0..self.nb_dffs {
+            let d = pick(&mut rng, &signals);
+            let en = if rng.gen() {
+                pick(&mut rng, &signals)
+            } else {
+                Signal::one()
+            };
+            let res = if rng.gen() {
+                pick(&mut rng, &signals)
+            } else {
+                Signal::zero()
+            };
+            ret.replace(i, Gate::dff(d, en, res));
+        }
+        for _ in 0..self.nb_outputs {
+            let o = pick(&mut rng, &signals);
+            ret.add_output(o);
+        }
+        ret.check();
+        ret
+    }
+}
+
+/// Mutate a network by inverting one input of a random combinatorial gate
+///
+/// The result is usually, but not always, functionally different from the original network.
+pub fn mutate(aig: &Network, seed: u64) -> Network {
+    let mut rng = SeededRng::new(seed);
+    let mut ret = aig.clone();
+    let candidates: Vec<usize> = (0..aig.nb_nodes())
+        .filter(|i| aig.gate_ref(*i).is_comb() && !aig.dependencies(*i).is_empty())
+        .collect();
+    if candidates.is_empty() {
+        return ret;
+    }
+    let i = candidates[rng.gen_range(0..candidates.len())];
+    let deps = aig.dependencies(i);
+    let target = deps[rng.gen_range(0..deps.len())];
+    let g = aig.gate(i).remap(|s| if *s == target { !*s } else { *s });
+    ret.replace(i, g);
+    ret
+}
+
+/// Compare two networks by random simulation over a few timesteps
+///
+/// Flip-flops start at zero. Returns a description of the first difference found.
+pub fn compare_by_simulation(a: &Network, b: &Network) -> Result<(), String> {
+    if a.nb_inputs() != b.nb_inputs() {
+        return Err(format!(
+            "Different number of inputs: {} vs {}",
+            a.nb_inputs(),
+            b.nb_inputs()
+        ));
+    }
+    if a.nb_outputs() != b.nb_outputs() {
+        return Err(format!(
+            "Different number of outputs: {} vs {}",
+            a.nb_outputs(),
+            b.nb_outputs()
+        ));
+    }
+    let mut rng = SeededRng::new(1);
+    let input_values: Vec<Vec<u64>> = (0..NB_COMPARISON_STEPS)
+        .map(|_| (0..a.nb_inputs()).map(|_| rng.gen()).collect())
+        .collect();
+    let va = simulate_multi(a, &input_values);
+    let vb = simulate_multi(b, &input_values);
+    for (step, (oa, ob)) in va.iter().zip(vb.iter()).enumerate() {
+        for (o, (wa, wb)) in oa.iter().zip(ob.iter()).enumerate() {
+            if wa != wb {
+                return Err(format!("Output {} differs at timestep {}", o, step));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Write a network in one format, read it back and compare them
+fn check_format<W, R>(aig: &Network, format: &str, write: W, read: R) -> Result<(), String>
+where
+    W: Fn(&mut Vec<u8>, &Network),
+    R: Fn(&[u8]) -> Result<Network, QuaighError>,
+{
+    let mut buf = Vec::new();
+    write(&mut buf, aig);
+    let reread = read(buf.as_slice())
+        .map_err(|e| format!("Unable to read back the {} file: {}", format, e))?;
+    compare_by_simulation(aig, &reread).map_err(|e| format!("{} round-trip: {}", format, e))
+}
+
+/// Write a network in each file format, read it back and compare them by simulation
+///
+/// The .bench, .blif and .qgh formats are always checked. AIGER is checked if all flip-flops
+/// are in the default clock domain, which is the only one it supports.
+pub fn check_roundtrip(aig: &Network) -> Result<(), String> {
+    check_format(aig, ".bench", write_bench, |r| read_bench(r))?;
+    check_format(aig, ".blif", write_blif, |r| read_blif(r))?;
+    check_format(aig, ".qgh", write_quaigh, |r| read_quaigh(r))?;
+    if aig.clock_domains().iter().all(|c| *c == ClockId::default()) {
+        check_format(
+            aig,
+            "AIGER",
+            |w, a| write_aiger(w, a, true),
+            |r| read_aiger(r),
+        )?;
+    }
+    Ok(())
+}
+
+/// Simpler replacements for a gate, to shrink a failing network
+fn simpler_gates(aig: &Network, i: usize) -> Vec<Gate> {
+    let g = aig.gate(i);
+    let mut ret = Vec::new();
+    if g != Gate::Buf(Signal::zero()) {
+        ret.push(Gate::Buf(Signal::zero()));
+    }
+    if !matches!(g, Gate::Buf(_)) {
+        // Only use earlier signals, to avoid creating combinatorial loops
+        for s in g.dependencies() {
+            if !s.is_var() || (s.var() as usize) < i {
+                ret.push(Gate::Buf(*s));
+            }
+        }
+    }
+    ret
+}
+
+/// Connect the users of buffers directly to their input
+fn bypass_buffers(aig: &Network) -> Network {
+    let bypass = |s: &Signal| -> Signal {
+        if !s.is_var() {
+            return *s;
+        }
+        match &aig.gate(s.var() as usize) {
+            Gate::Buf(t) => *t ^ s.is_inverted(),
+            _ => *s,
+        }
+    };
+    let mut ret = aig.clone();
+    for i in 0..aig.nb_nodes() {
+        ret.replace(i, aig.gate(i).remap(bypass));
+    }
+    for o in 0..aig.nb_outputs() {
+        ret.set_output(o, bypass(&aig.output(o)));
+    }
+    ret
+}
+
+/// Shrink a network while it still fails a check
+///
+/// Outputs are removed, gates are replaced by constants or by one of their inputs, buffers are
+/// bypassed and unused logic is removed, as long as `fails` still returns true. The inputs are
+/// kept unchanged.
+pub fn shrink<F: Fn(&Network) -> bool>(aig: &Network, fails: F) -> Network {
+    let mut best = aig.clone();
+    loop {
+        let mut progress = false;
+        let mut o = 0;
+        while o < best.nb_outputs() {
+            let mut candidate = best.clone();
+            candidate.remove_output(o);
+            if fails(&candidate) {
+                best = candidate;
+                progress = true;
+            } else {
+                o += 1;
+            }
+        }
+        for i in (0..best.nb_nodes()).rev() {
+            for g in simpler_gates(&best, i) {
+                let mut candidate = best.clone();
+                candidate.replace(i, g);
+                if fails(&candidate) {
+                    best = candidate;
+                    progress = true;
+                    break;
+                }
+            }
+        }
+        for mut candidate in [bypass_buffers(&best), best.clone()] {
+            candidate.cleanup();
+            if candidate.nb_nodes() < best.nb_nodes() && fails(&candidate) {
+                best = candidate;
+                progress = true;
+                break;
+            }
+        }
+        if !progress {
+            return best;
+        }
+    }
+}
+
+/// A random network that fails a property
+#[derive(Clone, Debug)]
+pub struct Failure {
+    /// Seed of the original failing network
+    pub seed: u64,
+    /// Shrunk failing network
+    pub network: Box<Network>,
+    /// Error returned by the property on the shrunk network
+    pub message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Property failed with seed {} on a network with {} nodes and {} outputs: {}",
+            self.seed,
+            self.network.nb_nodes(),
+            self.network.nb_outputs(),
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Check a property on random networks, one per seed
+///
+/// The first failing network is shrunk to a minimal reproducer, that is returned with the error.
+pub fn check_property<P, I>(generator: &RandomNetwork, seeds: I, property: P) -> Result<(), Failure>
+where
+    P: Fn(&Network) -> Result<(), String>,
+    I: IntoIterator<Item = u64>,
+{
+    for seed in seeds {
+        let aig = generator.generate(seed);
+        if property(&aig).is_ok() {
+            continue;
+        }
+        let network = shrink(&aig, |n| property(n).is_err());
+        let message = property(&network).unwrap_err();
+        return Err(Failure {
+            seed,
+            network: Box::new(network),
+            message,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_property, check_roundtrip, mutate, shrink, GateMix, RandomNetwork};
+    use crate::network::depth::depth;
+    use crate::Gate;
+
+    #[test]
+    fn test_generate() {
+        let generator = RandomNetwork {
+            nb_inputs: 5,
+            nb_outputs: 3,
+            nb_gates: 40,
+            nb_dffs: 4,
+            max_depth: Some(3),
+            gate_mix: GateMix::aig(),
+        };
+        for seed in 0..10 {
+            let aig = generator.generate(seed);
+            assert_eq!(aig.nb_inputs(), 5);
+            assert_eq!(aig.nb_outputs(), 3);
+            assert_eq!(aig.nb_nodes(), 44);
+            assert!(aig.is_topo_sorted());
+            assert!(depth(&aig) <= 3);
+            assert!((4..44).all(|i| matches!(&aig.gate(i), Gate::Binary(..))));
+            assert_eq!(
+                format!("{aig:?}"),
+                format!("{:?}", generator.generate(seed))
+            );
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let generator = RandomNetwork {
+            nb_dffs: 3,
+            ..RandomNetwork::default()
+        };
+        check_property(&generator, 0..20, check_roundtrip).unwrap();
+        let generator = RandomNetwork {
+            gate_mix: GateMix::canonical(),
+            ..RandomNetwork::default()
+        };
+        check_property(&generator, 0..20, check_roundtrip).unwrap();
+    }
+
+    #[test]
+    fn test_shrink() {
+        // Fails as soon as output 0 is connected to a gate: shrinks to a single gate and output
+        let generator = RandomNetwork {
+            nb_gates: 100,
+            ..RandomNetwork::default()
+        };
+        let property = |aig: &crate::Network| {
+            if aig.nb_outputs() > 0 && aig.output(0).is_var() {
+                Err("Output 0 is a gate".to_owned())
+            } else {
+                Ok(())
+            }
+        };
+        let err = check_property(&generator, 0..10, property).unwrap_err();
+        assert_eq!(err.network.nb_outputs(), 1);
+        assert_eq!(err.network.nb_nodes(), 1);
+        assert_eq!(err.message, "Output 0 is a gate");
+        let aig = generator.generate(err.seed);
+        assert_eq!(
+            format!("{:?}", shrink(&aig, |n| property(n).is_err())),
+            format!("{:?}", err.network)
+        );
+    }
+
+    #[test]
+    fn test_mutate() {
+        let aig = RandomNetwork::default().generate(3);
+        let mutated = mutate(&aig, 1);
+        let nb_diff = (0..aig.nb_nodes())
+            .filter(|i| aig.gate(*i) != mutated.gate(*i))
+            .count();
+        assert_eq!(nb_diff, 1);
+    }
+}
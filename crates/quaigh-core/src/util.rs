@@ -0,0 +1,4 @@
+//! Miscellaneous utilities
+
+pub mod format;
+pub mod rng;
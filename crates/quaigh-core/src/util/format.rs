@@ -0,0 +1,170 @@
+//! Human-readable formatting of counts, percentages and durations
+//!
+//! All user-facing reports go through these functions, so that they are formatted consistently.
+//! Humanization can be disabled globally for script consumers with [`set_raw_numbers`].
+//!
+//! ```
+//! use quaigh_core::util::format::{count, percentage};
+//! assert_eq!(count(393216), "393,216");
+//! assert_eq!(percentage(154201, 154880), "99.56%");
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+static RAW_NUMBERS: AtomicBool = AtomicBool::new(false);
+
+/// Disable or enable humanization of the numbers in reports
+pub fn set_raw_numbers(raw: bool) {
+    RAW_NUMBERS.store(raw, Ordering::Relaxed);
+}
+
+/// Returns whether humanization of the numbers is disabled
+pub fn raw_numbers() -> bool {
+    RAW_NUMBERS.load(Ordering::Relaxed)
+}
+
+fn count_impl(n: usize, raw: bool) -> String {
+    let digits = n.to_string();
+    if raw {
+        return digits;
+    }
+    let head = (digits.len() - 1) % 3 + 1;
+    let mut ret = digits[..head].to_string();
+    for group in digits.as_bytes()[head..].chunks(3) {
+        ret.push(',');
+        ret.extend(group.iter().map(|c| *c as char));
+    }
+    ret
+}
+
+fn compact_count_impl(n: usize, raw: bool) -> String {
+    if raw || n < 10_000 {
+        return count_impl(n, raw);
+    }
+    let suffixes = [(1e3, "k"), (1e6, "M"), (1e9, "G")];
+    for (i, (scale, suffix)) in suffixes.iter().enumerate() {
+        // Round before picking the precision and the suffix, as rounding may reach the next one
+        let v = n as f64 / scale;
+        let (rounded, precision) = if (v * 10.0).round() >= 1000.0 {
+            (v.round(), 0)
+        } else {
+            ((v * 10.0).round() / 10.0, 1)
+        };
+        if rounded < 1000.0 || i + 1 == suffixes.len() {
+            return format!("{:.*}{}", precision, rounded, suffix);
+        }
+    }
+    unreachable!()
+}
+
+fn percentage_impl(num: f64, den: f64, raw: bool) -> String {
+    let v = if den == 0.0 { 100.0 } else { 100.0 * num / den };
+    if raw {
+        format!("{}", v)
+    } else {
+        format!("{:.2}%", v)
+    }
+}
+
+fn duration_impl(d: Duration, raw: bool) -> String {
+    let secs = d.as_secs_f64();
+    if raw {
+        return format!("{:.3}", secs);
+    }
+    if secs < 1.0 {
+        format!("{}ms", d.as_millis())
+    } else if secs < 60.0 {
+        format!("{:.2}s", secs)
+    } else if secs < 3600.0 {
+        let s = d.as_secs();
+        format!("{}m{:02}s", s / 60, s % 60)
+    } else {
+        let s = d.as_secs();
+        format!("{}h{:02}m", s / 3600, (s / 60) % 60)
+    }
+}
+
+/// Format a count, with thousands separators
+pub fn count(n: usize) -> String {
+    count_impl(n, raw_numbers())
+}
+
+/// Format a count compactly, with k/M/G suffixes for large numbers
+pub fn compact_count(n: usize) -> String {
+    compact_count_impl(n, raw_numbers())
+}
+
+/// Format a ratio as a percentage, with two decimals
+///
+/// An empty ratio is considered to be 100%.
+pub fn percentage(num: usize, den: usize) -> String {
+    percentage_impl(num as f64, den as f64, raw_numbers())
+}
+
+/// Format a ratio of weights as a percentage, with two decimals
+///
+/// An empty ratio is considered to be 100%.
+pub fn weighted_percentage(num: f64, den: f64) -> String {
+    percentage_impl(num, den, raw_numbers())
+}
+
+/// Format a fraction, such as `154,201/154,880`
+pub fn fraction(num: usize, den: usize) -> String {
+    format!("{}/{}", count(num), count(den))
+}
+
+/// Format a duration, with a unit adapted to its magnitude
+pub fn duration(d: Duration) -> String {
+    duration_impl(d, raw_numbers())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{compact_count_impl, count_impl, duration_impl, percentage_impl};
+
+    #[test]
+    fn test_count() {
+        assert_eq!(count_impl(0, false), "0");
+        assert_eq!(count_impl(999, false), "999");
+        assert_eq!(count_impl(1000, false), "1,000");
+        assert_eq!(count_impl(154880, false), "154,880");
+        assert_eq!(count_impl(1234567, false), "1,234,567");
+        assert_eq!(count_impl(1234567, true), "1234567");
+    }
+
+    #[test]
+    fn test_compact_count() {
+        assert_eq!(compact_count_impl(9999, false), "9,999");
+        assert_eq!(compact_count_impl(12345, false), "12.3k");
+        assert_eq!(compact_count_impl(393216, false), "393k");
+        assert_eq!(compact_count_impl(2500000, false), "2.5M");
+        assert_eq!(compact_count_impl(3000000000, false), "3.0G");
+        assert_eq!(compact_count_impl(99_950, false), "100k");
+        assert_eq!(compact_count_impl(999_499, false), "999k");
+        assert_eq!(compact_count_impl(999_950, false), "1.0M");
+        assert_eq!(compact_count_impl(99_950_000, false), "100M");
+        assert_eq!(compact_count_impl(999_950_000, false), "1.0G");
+        assert_eq!(compact_count_impl(393216, true), "393216");
+    }
+
+    #[test]
+    fn test_percentage() {
+        assert_eq!(percentage_impl(154201.0, 154880.0, false), "99.56%");
+        assert_eq!(percentage_impl(1.0, 3.0, false), "33.33%");
+        assert_eq!(percentage_impl(0.0, 0.0, false), "100.00%");
+        assert_eq!(percentage_impl(1.0, 4.0, true), "25");
+        assert_eq!(percentage_impl(13.0, 13.5, false), "96.30%");
+    }
+
+    #[test]
+    fn test_duration() {
+        assert_eq!(duration_impl(Duration::from_millis(850), false), "850ms");
+        assert_eq!(duration_impl(Duration::from_millis(12340), false), "12.34s");
+        assert_eq!(duration_impl(Duration::from_secs(125), false), "2m05s");
+        assert_eq!(duration_impl(Duration::from_secs(3720), false), "1h02m");
+        assert_eq!(duration_impl(Duration::from_millis(12340), true), "12.340");
+    }
+}
@@ -0,0 +1,156 @@
+//! Deterministic random number generation
+//!
+//! All randomized algorithms take their random numbers from a [`SeededRng`], so that the results
+//! only depend on the seed. Unlike [`SmallRng`](rand::rngs::SmallRng), whose algorithm depends on
+//! the platform and may change between versions of `rand`, it produces the same sequence
+//! everywhere.
+//!
+//! ```
+//! use quaigh_core::util::rng::SeededRng;
+//! use rand::Rng;
+//!
+//! let mut a = SeededRng::new(42);
+//! let mut b = SeededRng::new(42);
+//! assert_eq!(a.gen::<u64>(), b.gen::<u64>());
+//! ```
+
+use rand::{Error, RngCore, SeedableRng};
+
+/// Seed used by the algorithms when none is given
+pub const DEFAULT_SEED: u64 = 1;
+
+/// Random number generator shared by the randomized algorithms
+///
+/// This is Xoshiro256++, seeded with SplitMix64. It implements [`RngCore`], so that all the
+/// methods of [`rand::Rng`] are available. Sub-tasks, for example on other threads, get their own
+/// independent generator with [`fork`](SeededRng::fork).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SeededRng {
+    state: [u64; 4],
+}
+
+/// Next value of a SplitMix64 generator
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl SeededRng {
+    /// Create a generator from a seed
+    pub fn new(seed: u64) -> SeededRng {
+        let mut s = seed;
+        SeededRng {
+            state: [
+                splitmix64(&mut s),
+                splitmix64(&mut s),
+                splitmix64(&mut s),
+                splitmix64(&mut s),
+            ],
+        }
+    }
+
+    /// Create an independent generator, seeded from this one
+    pub fn fork(&mut self) -> SeededRng {
+        SeededRng::new(self.next_u64())
+    }
+}
+
+impl Default for SeededRng {
+    fn default() -> Self {
+        SeededRng::new(DEFAULT_SEED)
+    }
+}
+
+impl RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let ret = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        ret
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let v = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&v[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for SeededRng {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        let mut state = [0; 4];
+        for (s, b) in state.iter_mut().zip(seed.chunks(8)) {
+            *s = u64::from_le_bytes(b.try_into().unwrap());
+        }
+        if state == [0; 4] {
+            // The all-zero state is a fixed point
+            return SeededRng::new(0);
+        }
+        SeededRng { state }
+    }
+
+    fn seed_from_u64(seed: u64) -> Self {
+        SeededRng::new(seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::seq::SliceRandom;
+    use rand::{Rng, RngCore};
+
+    use super::SeededRng;
+
+    #[test]
+    fn test_reference_values() {
+        // The sequence must never change, so that results are reproducible
+        let mut rng = SeededRng::new(1);
+        let values: Vec<u64> = (0..3).map(|_| rng.next_u64()).collect();
+        assert_eq!(
+            values,
+            [
+                0xCFC5_D07F_6F03_C29B,
+                0xBF42_4132_963F_E08D,
+                0x19A3_7D57_57AA_F520
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fork() {
+        let mut a = SeededRng::new(3);
+        let mut b = a.clone();
+        let mut fa = a.fork();
+        let mut fb = b.fork();
+        assert_eq!(fa.gen::<u64>(), fb.gen::<u64>());
+        assert_ne!(a.gen::<u64>(), fa.gen::<u64>());
+
+        let mut v: Vec<u32> = (0..100).collect();
+        v.shuffle(&mut a);
+        let mut w: Vec<u32> = (0..100).collect();
+        w.shuffle(&mut b);
+        assert_eq!(v, w);
+        assert_ne!(v, (0..100).collect::<Vec<_>>());
+    }
+}
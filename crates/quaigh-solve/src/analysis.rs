@@ -0,0 +1,185 @@
+//! Analysis of logic networks
+//!
+//! This extends the [core analysis module](quaigh_core::analysis) with Sat-based proofs.
+
+pub use quaigh_core::analysis::*;
+
+use rand::Rng;
+
+use crate::atpg::expose_dff;
+use crate::equiv::prove;
+use crate::network::NaryType;
+use crate::sim::simulate_comb_multi;
+use crate::util::rng::SeededRng;
+use crate::{Gate, Network, Signal};
+
+/// Number of 64b words of random simulation used to screen the outputs before Sat proofs
+const NB_SCREENING_WORDS: usize = 16;
+
+/// Append a network to another, with its inputs connected to the first inputs of the destination
+///
+/// Returns the outputs of the appended network.
+fn append(dest: &mut Network, src: &Network) -> Vec<Signal> {
+    assert!(src.nb_inputs() <= dest.nb_inputs());
+    let offset = dest.nb_nodes() as u32;
+    let t = |s: &Signal| -> Signal {
+        if s.is_var() {
+            Signal::from_var(offset + s.var()) ^ s.is_inverted()
+        } else {
+            *s
+        }
+    };
+    for i in 0..src.nb_nodes() {
+        dest.add(src.gate(i).remap(t));
+    }
+    (0..src.nb_outputs()).map(|o| t(&src.output(o))).collect()
+}
+
+/// Find the outputs that are constant, and prove it with a Sat solver
+///
+/// The constraints, if any, are given as a combinatorial network with the same inputs, whose
+/// outputs are all assumed to be 1: the outputs are only proven constant when the constraints hold.
+/// Flip-flops are treated as free inputs, so that an output is only reported if it is constant
+/// for any state.
+///
+/// Random simulation is used first, to avoid Sat calls for outputs that take both values.
+/// Returns the constant outputs with their values.
+pub fn constant_outputs(aig: &Network, constraints: Option<&Network>) -> Vec<(usize, bool)> {
+    let comb = if aig.is_comb() {
+        aig.clone()
+    } else {
+        expose_dff(aig)
+    };
+    let mut base = Network::new();
+    base.add_inputs(comb.nb_inputs());
+    let outputs = append(&mut base, &comb);
+    let assumption = match constraints {
+        Some(c) => {
+            assert!(c.is_comb(), "Constraints must be combinatorial");
+            assert_eq!(c.nb_inputs(), aig.nb_inputs());
+            let assumptions = append(&mut base, c);
+            base.add_canonical(Gate::Nary(assumptions.into(), NaryType::And))
+        }
+        None => Signal::one(),
+    };
+    base.add_output(assumption);
+    for o in &outputs {
+        base.add_output(*o);
+    }
+
+    // Values seen for each output when the constraints hold
+    let mut rng = SeededRng::new(1);
+    let mut seen = vec![(false, false); outputs.len()];
+    for _ in 0..NB_SCREENING_WORDS {
+        let inputs: Vec<u64> = (0..base.nb_inputs()).map(|_| rng.gen()).collect();
+        let values = simulate_comb_multi(&base, &inputs);
+        let valid = values[0];
+        for (s, v) in seen.iter_mut().zip(&values[1..]) {
+            s.0 |= !v & valid != 0;
+            s.1 |= v & valid != 0;
+        }
+    }
+
+    let mut ret = Vec::new();
+    for (o, (seen_zero, seen_one)) in seen.into_iter().enumerate() {
+        if seen_zero && seen_one {
+            continue;
+        }
+        let candidates: &[bool] = match (seen_zero, seen_one) {
+            (true, _) => &[false],
+            (_, true) => &[true],
+            // No valid pattern was found by simulation
+            _ => &[false, true],
+        };
+        for value in candidates {
+            let mut miter = Network::new();
+            miter.add_inputs(base.nb_inputs());
+            for i in 0..base.nb_nodes() {
+                miter.add(base.gate(i).clone());
+            }
+            let differs = outputs[o] ^ *value;
+            let x = miter.and(assumption, differs);
+            miter.add_output(x);
+            miter.cleanup();
+            miter.make_canonical();
+            if prove(&miter).is_none() {
+                ret.push((o, *value));
+                break;
+            }
+        }
+    }
+    ret
+}
+
+/// Replace the given outputs by constants and remove the logic that is not used anymore
+pub fn tie_outputs(aig: &mut Network, constants: &[(usize, bool)]) {
+    for (o, v) in constants {
+        aig.set_output(*o, Signal::from(*v));
+    }
+    aig.cleanup();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{constant_outputs, tie_outputs};
+    use crate::network::NaryType;
+    use crate::{Gate, Network, Signal};
+
+    /// Three request lines and an error flag that fires if two requests are active together
+    fn arbiter() -> Network {
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let r: Vec<Signal> = (0..3).map(|i| aig.input(i)).collect();
+        let a = aig.and(r[0], r[1]);
+        let b = aig.and(r[0], r[2]);
+        let c = aig.and(r[1], r[2]);
+        let err = aig.add(Gate::Nary([a, b, c].into(), NaryType::Or));
+        aig.add_output(err);
+        let any = aig.add(Gate::Nary(r.clone().into(), NaryType::Or));
+        aig.add_output(any);
+        // Always 0
+        let never = aig.and(r[0], !r[0]);
+        let never = aig.add(Gate::Buf(never));
+        aig.add_output(never);
+        aig
+    }
+
+    /// Constraint that the requests are one-hot
+    fn one_hot() -> Network {
+        let mut c = Network::new();
+        c.add_inputs(3);
+        let r: Vec<Signal> = (0..3).map(|i| c.input(i)).collect();
+        let x = c.add(Gate::xor3(r[0], r[1], r[2]));
+        let all = c.add(Gate::and3(r[0], r[1], r[2]));
+        c.add_output(x);
+        c.add_output(!all);
+        c
+    }
+
+    #[test]
+    fn test_constant_outputs() {
+        let aig = arbiter();
+        assert_eq!(constant_outputs(&aig, None), vec![(2, false)]);
+        assert_eq!(
+            constant_outputs(&aig, Some(&one_hot())),
+            vec![(0, false), (1, true), (2, false)]
+        );
+
+        let mut tied = aig.clone();
+        tie_outputs(&mut tied, &[(0, false)]);
+        assert_eq!(tied.output(0), Signal::zero());
+        assert!(tied.nb_nodes() < aig.nb_nodes());
+    }
+
+    #[test]
+    fn test_unsatisfiable_constraints() {
+        let aig = arbiter();
+        let mut c = Network::new();
+        c.add_inputs(3);
+        let x = c.and(c.input(0), !c.input(0));
+        let x = c.add(Gate::Buf(x));
+        c.add_output(x);
+        // Everything is constant when the constraints never hold
+        assert_eq!(constant_outputs(&aig, Some(&c)).len(), 3);
+    }
+}
@@ -0,0 +1,1286 @@
+//! Test pattern generation
+
+mod compaction;
+mod diagnosis;
+mod iddq;
+mod scan;
+mod solver;
+mod transition;
+
+pub use compaction::{find_cover, Compaction};
+pub use diagnosis::{diagnose, DiagnosisCandidate, FaultDictionary};
+pub use iddq::generate_iddq_test_patterns;
+pub use scan::{scan_insert, ScanChain};
+pub use transition::{generate_transition_test_patterns, LaunchOnCapture};
+
+use std::cmp::Reverse;
+use std::iter::zip;
+
+use kdam::{tqdm, BarExt};
+use rand::Rng;
+
+use crate::analysis::testability::Testability;
+use crate::atpg::iddq::add_activation_target;
+use crate::atpg::solver::{add_difference, add_faulty_cone, FaultSolver};
+use crate::sim::{
+    activates_faults_multi, detects_faults_cached, detects_faults_wide, fault_effects_multi,
+    pack_lanes, Fault, FaultWeights, SimCache, SimWord, W256,
+};
+use crate::util::format::{compact_count, count, fraction, percentage, weighted_percentage};
+use crate::util::rng::SeededRng;
+use crate::{Gate, Network, Signal};
+
+/// Expose flip_flops as inputs for ATPG
+///
+/// Flip-flop outputs are exposed are primary inputs. Flip-flop inputs, including
+/// enable and reset, become primary outputs.
+/// The new inputs and outputs are added after the original inputs, and their order
+/// matches the order of the flip flops.
+pub fn expose_dff(aig: &Network) -> Network {
+    let mut ret = Network::new();
+    ret.add_inputs(aig.nb_inputs());
+    for i in 0..aig.nb_outputs() {
+        ret.add_output(aig.output(i));
+    }
+    for i in 0..aig.nb_nodes() {
+        if let Gate::Dff([d, en, res], _) = &aig.gate(i) {
+            let new_input = ret.add_input();
+            ret.add(Gate::Buf(new_input));
+            ret.add_output(*d);
+            if !en.is_constant() {
+                ret.add_output(*en);
+            }
+            if !res.is_constant() {
+                ret.add_output(*res);
+            }
+        } else {
+            let g = aig.gate(i).clone();
+            ret.add(g);
+        }
+    }
+    ret.check();
+    ret
+}
+
+/// Add the logic detecting a stuck-at fault to a network starting with the nodes of `aig`
+///
+/// Only the fanout cone of the fault is duplicated with the fault injected. The returned signal
+/// is set when an output differs and the assumptions of the network hold.
+fn add_detection_target(ret: &mut Network, aig: &Network, fault: Fault, hold: Signal) -> Signal {
+    assert!(aig.is_comb());
+    assert!(!fault.is_transition());
+    let inputs: Vec<Signal> = (0..aig.nb_inputs()).map(|i| aig.input(i)).collect();
+    let nodes: Vec<Signal> = (0..aig.nb_nodes()).map(|i| aig.node(i)).collect();
+    let faulty = add_faulty_cone(
+        ret,
+        aig,
+        &inputs,
+        &nodes,
+        fault.gate(),
+        |ret, g| match fault {
+            Fault::OutputStuckAtFault { value, .. } => Signal::from(value),
+            Fault::InputStuckAtFault { input, value, .. } => {
+                ret.add(g.remap_with_ind(|s, i| if i == input { Signal::from(value) } else { *s }))
+            }
+            _ => unreachable!(),
+        },
+    );
+    let diff = add_difference(ret, aig, &inputs, &nodes, &faulty);
+    ret.and(diff, hold)
+}
+
+/// Generate random patterns with a given number of timesteps
+pub fn generate_random_seq_patterns(
+    nb_inputs: usize,
+    nb_timesteps: usize,
+    nb_patterns: usize,
+    seed: u64,
+) -> Vec<Vec<Vec<bool>>> {
+    let mut rng = SeededRng::new(seed);
+    let mut ret = Vec::new();
+    for _ in 0..nb_patterns {
+        let mut r1 = Vec::new();
+        for _ in 0..nb_timesteps {
+            let mut r2 = Vec::new();
+            for _ in 0..nb_inputs {
+                r2.push(rng.gen());
+            }
+            r1.push(r2);
+        }
+        ret.push(r1);
+    }
+    ret
+}
+
+/// Generate random combinatorial patterns
+pub fn generate_random_comb_patterns(
+    nb_inputs: usize,
+    nb_patterns: usize,
+    seed: u64,
+) -> Vec<Vec<bool>> {
+    let seq_patterns = generate_random_seq_patterns(nb_inputs, 1, nb_patterns, seed);
+    seq_patterns.iter().map(|p| p[0].clone()).collect()
+}
+
+/// Number of rounds of 64 random patterns used to find where undetected faults are visible
+const NB_OBSERVATION_ROUNDS: usize = 16;
+
+/// Number of pattern blocks whose simulation is cached during test pattern generation
+const SIM_CACHE_SIZE: usize = 16;
+
+/// Handling of the actual test pattern generation
+struct TestPatternGenerator<'a> {
+    aig: &'a Network,
+    /// Constant value of the inputs that are tied on the tester
+    ties: Vec<Option<bool>>,
+    faults: Vec<Fault>,
+    patterns: Vec<Vec<bool>>,
+    pattern_detections: Vec<Vec<bool>>,
+    detection: Vec<bool>,
+    /// Faults that are only undetectable because of tied inputs
+    tie_blocked: Vec<bool>,
+    /// Relative likelihood of the faults, if given
+    weights: Option<Vec<f64>>,
+    /// Controllability and observability of the nodes
+    testability: Testability,
+    /// Probability of each input to be 1 in biased random patterns
+    bias: Vec<f64>,
+    /// Launch and capture cycles, when generating patterns for transition faults
+    loc: Option<&'a LaunchOnCapture>,
+    /// Whether faults only need to be activated, for IDDQ testing
+    iddq: bool,
+    /// Compaction strategies in addition to the greedy compression
+    compaction: Compaction,
+    /// Simulation of the patterns that are checked repeatedly
+    cache: SimCache<'a>,
+    /// Sat solver shared by the faults, created on first use
+    sat: Option<FaultSolver>,
+    rng: SeededRng,
+}
+
+impl<'a> TestPatternGenerator<'a> {
+    pub fn nb_faults(&self) -> usize {
+        self.faults.len()
+    }
+
+    pub fn nb_patterns(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn nb_detected(&self) -> usize {
+        self.detection.iter().filter(|b| **b).count()
+    }
+
+    pub fn nb_tie_blocked(&self) -> usize {
+        self.tie_blocked.iter().filter(|b| **b).count()
+    }
+
+    /// Initialize the generator from a network and a seed
+    pub fn from(aig: &'a Network, faults: Vec<Fault>, seed: u64) -> TestPatternGenerator {
+        assert!(aig.is_topo_sorted());
+        let nb_faults = faults.len();
+        let testability = Testability::compute(aig);
+        let bias = testability.input_bias(aig);
+        TestPatternGenerator {
+            aig,
+            ties: vec![None; aig.nb_inputs()],
+            faults: faults,
+            patterns: Vec::new(),
+            pattern_detections: Vec::new(),
+            detection: vec![false; nb_faults],
+            tie_blocked: vec![false; nb_faults],
+            weights: None,
+            testability,
+            bias,
+            loc: None,
+            iddq: false,
+            compaction: Compaction::default(),
+            cache: SimCache::new(aig, SIM_CACHE_SIZE),
+            sat: None,
+            rng: SeededRng::new(seed),
+        }
+    }
+
+    /// Initialize the generator for two-cycle patterns on a network with exposed flip-flops
+    pub fn from_launch_on_capture(
+        loc: &'a LaunchOnCapture,
+        faults: Vec<Fault>,
+        seed: u64,
+    ) -> TestPatternGenerator<'a> {
+        let mut ret = TestPatternGenerator::from(loc.exposed(), faults, seed);
+        ret.loc = Some(loc);
+        ret.ties = vec![None; loc.nb_pattern_inputs()];
+        let capture_bias = ret.bias[..loc.nb_inputs()].to_vec();
+        ret.bias.extend(capture_bias);
+        ret
+    }
+
+    /// Only require faults to be activated, not propagated to an output, for IDDQ testing
+    pub fn set_iddq(&mut self) {
+        assert!(self.loc.is_none());
+        self.iddq = true;
+    }
+
+    /// Number of values in a pattern
+    fn nb_pattern_inputs(&self) -> usize {
+        match self.loc {
+            Some(loc) => loc.nb_pattern_inputs(),
+            None => self.aig.nb_inputs(),
+        }
+    }
+
+    /// Analyze which of a set of patterns detect the given faults
+    fn detects_faults_multi(&mut self, patterns: &[u64], faults: &[Fault]) -> Vec<u64> {
+        if self.iddq {
+            return activates_faults_multi(self.aig, patterns, faults);
+        }
+        match self.loc {
+            Some(loc) => loc.detects_faults_multi(patterns, faults),
+            None => detects_faults_cached(&mut self.cache, patterns, faults),
+        }
+    }
+
+    /// Returns whether a single pattern detects a fault
+    fn detects(&mut self, pattern: &[bool], fault: Fault) -> bool {
+        let multi: Vec<u64> = pattern.iter().map(|b| if *b { !0 } else { 0 }).collect();
+        self.detects_faults_multi(&multi, &[fault])[0] != 0
+    }
+
+    /// Signal set by the patterns detecting a fault, in the Sat solver shared by all faults
+    fn target(&mut self, fault: Fault) -> Signal {
+        let aig = self.aig;
+        let loc = self.loc;
+        let sat = self.sat.get_or_insert_with(|| match loc {
+            Some(loc) => FaultSolver::new(loc.unrolled().clone()),
+            None => FaultSolver::new(aig.clone()),
+        });
+        if self.iddq {
+            sat.target(fault, |ret, hold| {
+                add_activation_target(ret, aig, fault, hold)
+            })
+        } else if let Some(loc) = loc {
+            sat.target(fault, |ret, _| loc.add_detection_target(ret, fault))
+        } else {
+            sat.target(fault, |ret, hold| {
+                add_detection_target(ret, aig, fault, hold)
+            })
+        }
+    }
+
+    /// Find patterns setting the targets of several faults at once, with the tied inputs if required
+    fn solve_targets(&mut self, targets: &[Signal], with_ties: bool) -> Option<Vec<bool>> {
+        let ties = if with_ties {
+            self.ties.clone()
+        } else {
+            Vec::new()
+        };
+        self.sat.as_mut().unwrap().solve(targets, &ties)
+    }
+
+    /// Find a new test pattern for a specific fault using a SAT solver
+    fn find_pattern_detecting_fault(&mut self, fault: Fault, with_ties: bool) -> Option<Vec<bool>> {
+        if let Some(sat) = &mut self.sat {
+            sat.trim();
+        }
+        let target = self.target(fault);
+        let ret = self.solve_targets(&[target], with_ties);
+        if let Some(pattern) = &ret {
+            assert!(self.detects(pattern, fault));
+        }
+        ret
+    }
+
+    /// Tie some inputs to a constant value; patterns will never toggle them
+    pub fn set_ties(&mut self, ties: &[(usize, bool)]) {
+        for (i, b) in ties {
+            assert!(
+                *i < self.nb_pattern_inputs(),
+                "Tied input {} does not exist",
+                i
+            );
+            self.ties[*i] = Some(*b);
+        }
+    }
+
+    /// Weight the faults by their likelihood, for coverage reports and pattern compression
+    pub fn set_weights(&mut self, weights: &FaultWeights) {
+        self.weights = Some(weights.weights(self.aig, &self.faults));
+    }
+
+    /// Weight of the detected faults and total weight, if the faults are weighted
+    pub fn weighted_coverage(&self) -> Option<(f64, f64)> {
+        let weights = self.weights.as_ref()?;
+        let detected = zip(weights, &self.detection)
+            .filter(|(_, d)| **d)
+            .map(|(w, _)| w)
+            .sum();
+        Some((detected, weights.iter().sum()))
+    }
+
+    /// Returns whether some inputs are tied
+    fn has_ties(&self) -> bool {
+        self.ties.iter().any(|t| t.is_some())
+    }
+
+    /// Force the tied inputs of a multi-pattern to their value
+    fn apply_ties_multi(&self, patterns: &mut [u64]) {
+        for (b, t) in zip(patterns.iter_mut(), self.ties.iter()) {
+            if let Some(v) = t {
+                *b = if *v { !0 } else { 0 };
+            }
+        }
+    }
+
+    /// Extend a vector of boolean vectors with 64 elements at once
+    fn extend_vec(v: &mut Vec<Vec<bool>>, added: Vec<u64>) {
+        for i in 0..64 {
+            v.push(added.iter().map(|d| (d >> i) & 1 != 0).collect());
+        }
+    }
+
+    /// Obtain all faults, or only the ones that are not yet detected, and their index
+    pub fn get_faults(&self, check_already_detected: bool) -> (Vec<Fault>, Vec<usize>) {
+        let mut faults = Vec::new();
+        let mut indices = Vec::new();
+        for (i, f) in self.faults.iter().enumerate() {
+            if check_already_detected || !self.detection[i] {
+                faults.push(*f);
+                indices.push(i);
+            }
+        }
+        (faults, indices)
+    }
+
+    /// Add a single pattern and random variations to the current set
+    pub fn add_random_patterns_from(&mut self, pattern: Vec<bool>, check_already_detected: bool) {
+        let mut patterns = Vec::new();
+        let num_rounds = 4; // Generate mostly 0s, with 1/16 values being ones
+        for b in pattern {
+            let mut val = if b { !0 } else { 0 };
+            let mut change = !0;
+            for _ in 0..num_rounds {
+                change &= self.rng.gen::<u64>();
+            }
+            val ^= change;
+            val &= !1; // Ensure that the first pattern is the original one
+            patterns.push(val);
+        }
+        self.add_patterns(patterns, check_already_detected);
+    }
+
+    /// Add a new set of patterns to the current set
+    pub fn add_patterns(&mut self, mut patterns: Vec<u64>, check_already_detected: bool) {
+        self.apply_ties_multi(&mut patterns);
+        let (faults, indices) = self.get_faults(check_already_detected);
+        let detected = self.detects_faults_multi(&patterns, &faults);
+        let mut det = vec![0; self.nb_faults()];
+        for (i, d) in zip(indices, detected) {
+            self.detection[i] |= d != 0;
+            det[i] = d;
+        }
+        Self::extend_vec(&mut self.patterns, patterns);
+        Self::extend_vec(&mut self.pattern_detections, det);
+    }
+
+    /// Generate a random pattern and add it to the current set
+    pub fn add_random_patterns(&mut self, check_already_detected: bool) {
+        let pattern = (0..self.nb_pattern_inputs())
+            .map(|_| self.rng.gen::<u64>())
+            .collect();
+        self.add_patterns(pattern, check_already_detected);
+    }
+
+    /// Generate a random pattern biased towards the hard-to-control values, and add it to the current set
+    pub fn add_biased_random_patterns(&mut self, check_already_detected: bool) {
+        let pattern = self
+            .bias
+            .iter()
+            .map(|p| {
+                let mut val = 0u64;
+                for i in 0..64 {
+                    if self.rng.gen_bool(*p) {
+                        val |= 1 << i;
+                    }
+                }
+                val
+            })
+            .collect();
+        self.add_patterns(pattern, check_already_detected);
+    }
+
+    /// Check consistency
+    pub fn check(&self) {
+        assert_eq!(self.patterns.len(), self.pattern_detections.len());
+        for p in &self.patterns {
+            assert_eq!(p.len(), self.nb_pattern_inputs());
+        }
+        for p in &self.pattern_detections {
+            assert_eq!(p.len(), self.nb_faults());
+        }
+        assert_eq!(self.detection.len(), self.nb_faults());
+    }
+
+    /// Compress the existing patterns to keep as few as possible.
+    /// This is a minimum set cover problem.
+    /// At the moment we solve it with a simple greedy algorithm,
+    /// taking the pattern that detects the most new faults each time.
+    /// If the faults are weighted, the pattern that detects the largest weight of new faults is taken,
+    /// so that the first patterns give the best weighted coverage.
+    pub fn compress_patterns(&mut self) {
+        let mut progress =
+            tqdm!(total = 2 * self.nb_faults() * self.nb_patterns() + self.nb_detected());
+        progress.set_description("Compression progress");
+        progress
+            .set_bar_format("{desc}{percentage:3.0}%|{animation}| [{elapsed}<{remaining}{postfix}]")
+            .unwrap();
+        progress.set_postfix("patterns=-".to_string());
+        let mut remaining_to_detect = self.nb_detected();
+        let mut it = 0;
+
+        // Which patterns detect a given fault
+        let mut fault_to_patterns = Vec::new();
+        for f in 0..self.nb_faults() {
+            let mut patterns = Vec::new();
+            for p in 0..self.nb_patterns() {
+                if self.pattern_detections[p][f] {
+                    patterns.push(p);
+                }
+                it += 1;
+                if it % 256 == 0 {
+                    progress.update_to(it).unwrap();
+                }
+            }
+            fault_to_patterns.push(patterns);
+        }
+
+        // Which faults are detected by a given pattern
+        let mut pattern_to_faults = Vec::new();
+        for p in 0..self.nb_patterns() {
+            let mut faults = Vec::new();
+            for f in 0..self.nb_faults() {
+                if self.pattern_detections[p][f] {
+                    faults.push(f);
+                }
+                it += 1;
+                if it % 256 == 0 {
+                    progress.update_to(it).unwrap();
+                }
+            }
+            pattern_to_faults.push(faults);
+        }
+
+        // How many new faults each pattern detects
+        let mut nb_detected_by_pattern: Vec<_> =
+            pattern_to_faults.iter().map(|v| v.len()).collect();
+        // Weight of the new faults each pattern detects
+        let mut weight_detected_by_pattern: Option<Vec<f64>> = self.weights.as_ref().map(|w| {
+            pattern_to_faults
+                .iter()
+                .map(|v| v.iter().map(|f| w[*f]).sum())
+                .collect()
+        });
+        assert_eq!(fault_to_patterns.len(), self.nb_faults());
+        assert_eq!(pattern_to_faults.len(), self.nb_patterns());
+
+        let mut selected_patterns = Vec::new();
+        progress.update_to(it).unwrap();
+        while remaining_to_detect > 0 {
+            // Pick the pattern that detects the most faults, or the largest weight of faults
+            let best_pattern = match &weight_detected_by_pattern {
+                Some(weights) => (0..self.nb_patterns())
+                    .filter(|p| nb_detected_by_pattern[*p] > 0)
+                    .max_by(|a, b| {
+                        weights[*a]
+                            .total_cmp(&weights[*b])
+                            .then(nb_detected_by_pattern[*a].cmp(&nb_detected_by_pattern[*b]))
+                    })
+                    .unwrap(),
+                None => nb_detected_by_pattern
+                    .iter()
+                    .enumerate()
+                    .max_by(|(_, a), (_, b)| a.cmp(b))
+                    .map(|(index, _)| index)
+                    .unwrap(),
+            };
+            selected_patterns.push(best_pattern);
+            remaining_to_detect -= nb_detected_by_pattern[best_pattern];
+            progress.set_postfix(format!(
+                "patterns={}",
+                compact_count(selected_patterns.len())
+            ));
+            progress
+                .update(nb_detected_by_pattern[best_pattern])
+                .unwrap();
+
+            // Remove the faults detected by the pattern from consideration
+            assert!(nb_detected_by_pattern[best_pattern] > 0);
+            for f in &pattern_to_faults[best_pattern] {
+                for p in &fault_to_patterns[*f] {
+                    nb_detected_by_pattern[*p] -= 1;
+                    if let Some(weights) = &mut weight_detected_by_pattern {
+                        weights[*p] -= self.weights.as_ref().unwrap()[*f];
+                    }
+                }
+                // So we don't remove a fault twice
+                fault_to_patterns[*f].clear();
+            }
+            assert_eq!(nb_detected_by_pattern[best_pattern], 0);
+        }
+
+        let mut new_patterns = Vec::new();
+        let mut new_detections = Vec::new();
+        for p in selected_patterns {
+            new_patterns.push(self.patterns[p].clone());
+            new_detections.push(self.pattern_detections[p].clone());
+        }
+        self.patterns = new_patterns;
+        self.pattern_detections = new_detections;
+        println!();
+    }
+
+    pub fn detect_faults(&mut self) {
+        let mut progress = tqdm!(total = self.nb_faults());
+        progress.set_description("Detection progress");
+        progress
+            .set_bar_format("{desc}{percentage:3.0}%|{animation}| [{elapsed}<{remaining}{postfix}]")
+            .unwrap();
+        // Alternate uniform random patterns and patterns biased towards hard-to-control nodes
+        for round in 0.. {
+            let nb_detected_before = self.nb_detected();
+            if round % 2 == 0 {
+                self.add_random_patterns(true);
+            } else {
+                self.add_biased_random_patterns(true);
+            }
+            let nb_detected_after = self.nb_detected();
+            progress.set_postfix(format!(
+                "patterns={}, unobservable=-",
+                compact_count(self.nb_patterns())
+            ));
+            progress.update_to(self.nb_detected()).unwrap();
+            if nb_detected_after == self.nb_faults() {
+                break;
+            }
+            if ((nb_detected_after - nb_detected_before) as f64) < (0.01 * self.nb_faults() as f64)
+            {
+                break;
+            }
+        }
+        progress
+            .write(coverage_summary(
+                "Generated",
+                "random patterns",
+                self.nb_patterns(),
+                self.nb_detected(),
+                self.nb_faults(),
+            ))
+            .unwrap();
+        // Hardest faults first: their patterns are varied randomly and detect easier faults as well
+        let mut order: Vec<usize> = (0..self.nb_faults()).collect();
+        order.sort_by_key(|i| {
+            Reverse(
+                self.testability
+                    .fault_difficulty(self.aig, &self.faults[*i]),
+            )
+        });
+        let mut unobservable = 0;
+        for (pos, &i) in order.iter().enumerate() {
+            if self.detection[i] {
+                continue;
+            }
+            let p = self.find_pattern_detecting_fault(self.faults[i], true);
+            if let Some(mut pattern) = p {
+                if self.compaction.dynamic {
+                    pattern = self.extend_pattern(self.faults[i], pattern, &order[pos + 1..]);
+                }
+                self.add_random_patterns_from(pattern, false);
+            } else if self.has_ties()
+                && self
+                    .find_pattern_detecting_fault(self.faults[i], false)
+                    .is_some()
+            {
+                self.tie_blocked[i] = true;
+                unobservable += 1;
+            } else {
+                unobservable += 1;
+            }
+            progress.set_postfix(format!(
+                "patterns={}, unobservable={}",
+                compact_count(self.nb_patterns()),
+                compact_count(unobservable)
+            ));
+            progress
+                .update_to(self.nb_detected() + unobservable)
+                .unwrap();
+        }
+        progress
+            .write(coverage_summary(
+                "Generated",
+                "patterns total",
+                self.nb_patterns(),
+                self.nb_detected(),
+                self.nb_faults(),
+            ))
+            .unwrap();
+        if self.has_ties() {
+            progress
+                .write(undetected_summary(
+                    self.nb_tie_blocked(),
+                    self.nb_faults() - self.nb_detected() - self.nb_tie_blocked(),
+                ))
+                .unwrap();
+        }
+        println!();
+    }
+}
+
+/// Summary line for the network before test pattern generation
+fn network_summary(aig: &Network, nb_faults: usize, nb_unique_faults: usize) -> String {
+    format!(
+        "Analyzing network with {} inputs, {} outputs, {} gates, {} possible faults, {} unique faults",
+        count(aig.nb_inputs()),
+        count(aig.nb_outputs()),
+        count(aig.nb_nodes()),
+        count(nb_faults),
+        count(nb_unique_faults),
+    )
+}
+
+/// Summary line for the fault coverage of a set of patterns
+fn coverage_summary(
+    action: &str,
+    what: &str,
+    nb_patterns: usize,
+    nb_detected: usize,
+    nb_faults: usize,
+) -> String {
+    format!(
+        "{} {} {}, detecting {} faults ({} coverage)",
+        action,
+        count(nb_patterns),
+        what,
+        fraction(nb_detected, nb_faults),
+        percentage(nb_detected, nb_faults)
+    )
+}
+
+/// Summary line for the weighted fault coverage
+fn weighted_coverage_summary(detected: f64, total: f64) -> String {
+    format!(
+        "Weighted fault coverage: {} ({:.1}/{:.1} weight detected)",
+        weighted_percentage(detected, total),
+        detected,
+        total
+    )
+}
+
+/// Summary line for the faults that could not be detected
+fn undetected_summary(nb_tie_blocked: usize, nb_redundant: usize) -> String {
+    format!(
+        "Undetected faults: {} undetectable due to tied inputs, {} redundant",
+        count(nb_tie_blocked),
+        count(nb_redundant)
+    )
+}
+
+/// Generate combinatorial test patterns
+///
+/// This will generate random test patterns, then try to exercize the remaining faults
+/// using a SAT solver. The network needs to be combinatorial.
+///
+/// Inputs tied to a constant on the tester are given as (input, value) pairs: the patterns
+/// never toggle them, and faults that could only be detected by toggling them are reported separately.
+///
+/// With fault weights, the weighted coverage is reported as well, and the patterns are ordered
+/// to maximize the weighted coverage of the first patterns.
+///
+/// The patterns are compressed with a greedy algorithm, and further with the given compaction
+/// strategies.
+pub fn generate_comb_test_patterns(
+    aig: &Network,
+    seed: u64,
+    with_redundant_faults: bool,
+    ties: &[(usize, bool)],
+    weights: Option<&FaultWeights>,
+    compaction: Compaction,
+) -> Vec<Vec<bool>> {
+    assert!(aig.is_comb());
+    let faults = Fault::all(aig);
+    let unique_faults = Fault::all_unique(aig);
+
+    println!(
+        "{}",
+        network_summary(aig, faults.len(), unique_faults.len())
+    );
+
+    let mut gen = TestPatternGenerator::from(
+        aig,
+        if with_redundant_faults {
+            faults.clone()
+        } else {
+            unique_faults.clone()
+        },
+        seed,
+    );
+    gen.set_ties(ties);
+    gen.set_compaction(compaction);
+    if let Some(w) = weights {
+        gen.set_weights(w);
+    }
+    gen.detect_faults();
+    gen.check();
+    gen.compact_patterns();
+    gen.check();
+    println!(
+        "{}",
+        coverage_summary(
+            "Kept",
+            "patterns",
+            gen.nb_patterns(),
+            gen.nb_detected(),
+            gen.nb_faults()
+        )
+    );
+    if let Some((detected, total)) = gen.weighted_coverage() {
+        println!("{}", weighted_coverage_summary(detected, total));
+    }
+    println!("Simulation cache: {}", gen.cache.stats());
+    gen.patterns
+}
+
+/// Observation point suggested to improve the fault coverage
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ObservationPoint {
+    /// Node to expose as an additional output
+    pub node: usize,
+    /// Number of undetected faults that become detectable with this observation point
+    pub gain: usize,
+}
+
+/// Pack test patterns by groups of 64 for multi-pattern simulation
+///
+/// The last group is padded with copies of its first pattern.
+fn pack_patterns(patterns: &[Vec<bool>], nb_inputs: usize) -> Vec<Vec<u64>> {
+    let mut ret = Vec::new();
+    for chunk in patterns.chunks(64) {
+        let mut packed = vec![0u64; nb_inputs];
+        for j in 0..64 {
+            let p = chunk.get(j).unwrap_or(&chunk[0]);
+            for (v, b) in zip(packed.iter_mut(), p.iter()) {
+                *v |= (*b as u64) << j;
+            }
+        }
+        ret.push(packed);
+    }
+    ret
+}
+
+/// Suggest internal signals to expose as outputs to improve the coverage of test patterns
+///
+/// This is typically used after test pattern generation, to decide where to insert observation points
+/// in the design. Faults that are not detected by the patterns are simulated with the patterns and additional
+/// random patterns, to find the nodes where their effect is visible. Up to `nb_points` nodes are then
+/// selected greedily, each time picking the one that makes the most remaining faults detectable.
+/// The network needs to be combinatorial.
+pub fn suggest_observation_points(
+    aig: &Network,
+    patterns: &[Vec<bool>],
+    with_redundant_faults: bool,
+    ties: &[(usize, bool)],
+    nb_points: usize,
+    seed: u64,
+) -> Vec<ObservationPoint> {
+    assert!(aig.is_comb());
+    let faults = if with_redundant_faults {
+        Fault::all(aig)
+    } else {
+        Fault::all_unique(aig)
+    };
+    let mut gen = TestPatternGenerator::from(aig, faults, seed);
+    gen.set_ties(ties);
+    let mut packed = pack_patterns(patterns, aig.nb_inputs());
+    for p in &packed {
+        gen.add_patterns(p.clone(), false);
+    }
+    let (undetected, _) = gen.get_faults(false);
+
+    // Nodes where the effect of each undetected fault is visible
+    for _ in 0..NB_OBSERVATION_ROUNDS {
+        let mut p: Vec<u64> = (0..aig.nb_inputs()).map(|_| gen.rng.gen()).collect();
+        gen.apply_ties_multi(&mut p);
+        packed.push(p);
+    }
+    let mut fault_to_nodes = vec![Vec::new(); undetected.len()];
+    for p in &packed {
+        for (nodes, effects) in zip(
+            fault_to_nodes.iter_mut(),
+            fault_effects_multi(aig, p, &undetected),
+        ) {
+            nodes.extend(effects);
+        }
+    }
+    let mut node_to_faults = vec![Vec::new(); aig.nb_nodes()];
+    for (i, nodes) in fault_to_nodes.iter_mut().enumerate() {
+        nodes.sort();
+        nodes.dedup();
+        for n in nodes {
+            node_to_faults[*n].push(i);
+        }
+    }
+
+    // Greedy selection of the node that observes the most remaining faults
+    let mut observed = vec![false; undetected.len()];
+    let mut ret = Vec::new();
+    while ret.len() < nb_points {
+        let (node, gain) = node_to_faults
+            .iter()
+            .enumerate()
+            .map(|(n, f)| (n, f.iter().filter(|i| !observed[**i]).count()))
+            .max_by_key(|(n, gain)| (*gain, Reverse(*n)))
+            .unwrap_or((0, 0));
+        if gain == 0 {
+            break;
+        }
+        for i in &node_to_faults[node] {
+            observed[*i] = true;
+        }
+        ret.push(ObservationPoint { node, gain });
+    }
+    println!(
+        "{}",
+        observation_summary(aig, &ret, gen.nb_detected(), gen.nb_faults())
+    );
+    ret
+}
+
+/// Add observation points to a network as additional outputs
+pub fn add_observation_points(aig: &Network, points: &[ObservationPoint]) -> Network {
+    let mut ret = aig.clone();
+    for p in points {
+        ret.add_output(aig.node(p.node));
+    }
+    ret
+}
+
+/// Summary of the suggested observation points, with the expected coverage after each one is added
+fn observation_summary(
+    aig: &Network,
+    points: &[ObservationPoint],
+    nb_detected: usize,
+    nb_faults: usize,
+) -> String {
+    if points.is_empty() {
+        return "No observation point would improve the coverage".to_owned();
+    }
+    let mut ret = format!("Suggested {} observation points:", count(points.len()));
+    let mut detected = nb_detected;
+    for p in points {
+        detected += p.gain;
+        ret += &format!(
+            "\n  {}: +{} faults ({} coverage)",
+            aig.node(p.node),
+            count(p.gain),
+            percentage(detected, nb_faults)
+        );
+    }
+    ret
+}
+
+/// Analyze combinatorial test patterns
+///
+/// This will show the coverage obtained by these test patterns, weighted by the fault weights if any.
+/// The network needs to be combinatorial.
+pub fn report_comb_test_patterns<I: IntoIterator<Item = Vec<bool>>>(
+    aig: &Network,
+    patterns: I,
+    with_redundant_faults: bool,
+    weights: Option<&FaultWeights>,
+) {
+    assert!(aig.is_comb());
+    let faults = Fault::all(aig);
+    let unique_faults = Fault::all_unique(aig);
+
+    println!(
+        "{}",
+        network_summary(aig, faults.len(), unique_faults.len())
+    );
+
+    let faults = if with_redundant_faults {
+        faults
+    } else {
+        unique_faults
+    };
+    let mut grader = FaultGrader::new(aig, faults);
+    for pattern in tqdm!(patterns.into_iter()) {
+        grader.add_pattern(pattern);
+    }
+    let nb_patterns = grader.nb_patterns();
+    let detections = grader.finish();
+    let nb_detected = detections.iter().filter(|(_, d)| *d).count();
+
+    println!(
+        "{}",
+        coverage_summary(
+            "Analyzed",
+            "patterns",
+            nb_patterns,
+            nb_detected,
+            detections.len()
+        )
+    );
+    if let Some(w) = weights {
+        let faults: Vec<Fault> = detections.iter().map(|(f, _)| *f).collect();
+        let weights = w.weights(aig, &faults);
+        let detected = zip(&weights, &detections)
+            .filter(|(_, (_, d))| *d)
+            .map(|(w, _)| w)
+            .sum();
+        println!(
+            "{}",
+            weighted_coverage_summary(detected, weights.iter().sum())
+        );
+    }
+}
+
+/// Fault grading of a stream of patterns
+///
+/// Patterns are simulated 256 at a time, and only the detection status of the faults is kept,
+/// so that memory usage does not depend on the number of patterns. Faults that are already
+/// detected are not simulated again.
+pub struct FaultGrader<'a> {
+    aig: &'a Network,
+    faults: Vec<Fault>,
+    detection: Vec<bool>,
+    batch: Vec<Vec<bool>>,
+    nb_patterns: usize,
+}
+
+impl<'a> FaultGrader<'a> {
+    /// Initialize the grader for a combinatorial network and a set of faults
+    pub fn new(aig: &'a Network, faults: Vec<Fault>) -> FaultGrader<'a> {
+        assert!(aig.is_comb());
+        assert!(aig.is_topo_sorted());
+        let nb_faults = faults.len();
+        FaultGrader {
+            aig,
+            faults,
+            detection: vec![false; nb_faults],
+            batch: Vec::with_capacity(W256::LANES),
+            nb_patterns: 0,
+        }
+    }
+
+    /// Number of patterns added so far
+    pub fn nb_patterns(&self) -> usize {
+        self.nb_patterns
+    }
+
+    /// Add a pattern; patterns are simulated once 256 of them have been added
+    pub fn add_pattern(&mut self, pattern: Vec<bool>) {
+        assert_eq!(pattern.len(), self.aig.nb_inputs());
+        self.batch.push(pattern);
+        self.nb_patterns += 1;
+        if self.batch.len() == W256::LANES {
+            self.flush();
+        }
+    }
+
+    /// Simulate the patterns of the current batch
+    fn flush(&mut self) {
+        if self.batch.is_empty() {
+            return;
+        }
+        let words: Vec<W256> = pack_lanes(&self.batch);
+        let indices: Vec<usize> = (0..self.faults.len())
+            .filter(|i| !self.detection[*i])
+            .collect();
+        let faults: Vec<Fault> = indices.iter().map(|i| self.faults[*i]).collect();
+        let detected = detects_faults_wide(self.aig, &words, &faults);
+        for (i, d) in zip(indices, detected) {
+            self.detection[i] |= !d.is_zero();
+        }
+        self.batch.clear();
+    }
+
+    /// Simulate the remaining patterns, and return each fault with whether a pattern detects it
+    pub fn finish(mut self) -> Vec<(Fault, bool)> {
+        self.flush();
+        zip(self.faults, self.detection).collect()
+    }
+}
+
+/// Analyze which faults are detected by a set of test patterns
+///
+/// Returns each fault with whether one of the patterns detects it.
+pub fn analyze_fault_detection(
+    aig: &Network,
+    patterns: &[Vec<bool>],
+    with_redundant_faults: bool,
+) -> Vec<(Fault, bool)> {
+    assert!(aig.is_comb());
+    let faults = if with_redundant_faults {
+        Fault::all(aig)
+    } else {
+        Fault::all_unique(aig)
+    };
+    let mut grader = FaultGrader::new(aig, faults);
+    for pattern in patterns {
+        grader.add_pattern(pattern.clone());
+    }
+    grader.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        add_observation_points, analyze_fault_detection, coverage_summary,
+        generate_comb_test_patterns, network_summary, observation_summary,
+        suggest_observation_points, undetected_summary, weighted_coverage_summary, Compaction,
+        FaultGrader, ObservationPoint, TestPatternGenerator,
+    };
+    use std::iter::zip;
+
+    use crate::network::generators::adder;
+    use crate::network::NameTable;
+    use crate::sim::{detects_faults, Fault, FaultWeights};
+    use crate::Network;
+
+    impl TestPatternGenerator<'_> {
+        /// Add a single pattern to the current set, without random variations
+        fn add_single_pattern(&mut self, mut pattern: Vec<bool>, check_already_detected: bool) {
+            for (b, t) in zip(pattern.iter_mut(), self.ties.iter()) {
+                if let Some(v) = t {
+                    *b = *v;
+                }
+            }
+            let (faults, indices) = self.get_faults(check_already_detected);
+            let multi_pattern: Vec<u64> = pattern.iter().map(|b| if *b { !0 } else { 0 }).collect();
+            let detected = self.detects_faults_multi(&multi_pattern, &faults);
+            let mut det = vec![false; self.nb_faults()];
+            for (i, d) in zip(indices, detected) {
+                self.detection[i] |= d != 0;
+                det[i] = d != 0;
+            }
+            self.patterns.push(pattern);
+            self.pattern_detections.push(det);
+        }
+    }
+
+    #[test]
+    fn test_summaries() {
+        assert_eq!(
+            coverage_summary("Generated", "random patterns", 393216, 154201, 154880),
+            "Generated 393,216 random patterns, detecting 154,201/154,880 faults (99.56% coverage)"
+        );
+        assert_eq!(
+            coverage_summary("Kept", "patterns", 12, 0, 0),
+            "Kept 12 patterns, detecting 0/0 faults (100.00% coverage)"
+        );
+        assert_eq!(
+            network_summary(&adder::ripple_carry(4), 1500, 1200),
+            "Analyzing network with 8 inputs, 5 outputs, 8 gates, 1,500 possible faults, 1,200 unique faults"
+        );
+    }
+
+    #[test]
+    fn test_tied_inputs() {
+        // The cone of x is only observable when en is 1
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let en = aig.add_input();
+        let x = aig.and(a, b);
+        let y = aig.and(x, en);
+        let z = aig.xor(a, b);
+        aig.add_output(y);
+        aig.add_output(z);
+
+        let faults = Fault::all(&aig);
+        let mut gen = TestPatternGenerator::from(&aig, faults.clone(), 1);
+        gen.set_ties(&[(2, false)]);
+        gen.detect_faults();
+        gen.check();
+        for p in &gen.patterns {
+            assert!(!p[2]);
+        }
+        for (i, f) in faults.iter().enumerate() {
+            if f.gate() == 0 {
+                assert!(!gen.detection[i]);
+                assert!(gen.tie_blocked[i], "{} should be blocked by the tie", f);
+            }
+        }
+        // Without the tie, everything is detectable
+        assert_eq!(
+            gen.nb_detected() + gen.nb_tie_blocked(),
+            gen.nb_faults(),
+            "No fault should be classified as redundant"
+        );
+    }
+
+    #[test]
+    fn test_analyze_fault_detection() {
+        let aig = adder::ripple_carry(3);
+        let patterns =
+            generate_comb_test_patterns(&aig, 1, false, &[], None, Compaction::default());
+        let detections = analyze_fault_detection(&aig, &patterns, false);
+        assert_eq!(detections.len(), Fault::all_unique(&aig).len());
+
+        let detections = analyze_fault_detection(&aig, &patterns[..1], true);
+        assert_eq!(detections.len(), Fault::all(&aig).len());
+        let faults: Vec<Fault> = detections.iter().map(|(f, _)| *f).collect();
+        let expected = detects_faults(&aig, &patterns[0], &faults);
+        for ((_, d), e) in detections.iter().zip(expected) {
+            assert_eq!(*d, e);
+        }
+    }
+
+    #[test]
+    fn test_fault_grader() {
+        use rand::rngs::SmallRng;
+        use rand::{Rng, SeedableRng};
+
+        let aig = adder::ripple_carry(6);
+        let faults = Fault::all(&aig);
+        let mut rng = SmallRng::seed_from_u64(1);
+        // A partial last batch, and few patterns so that some faults stay undetected
+        for nb_patterns in [3, 130] {
+            let patterns: Vec<Vec<bool>> = (0..nb_patterns)
+                .map(|_| (0..aig.nb_inputs()).map(|_| rng.gen_ratio(1, 8)).collect())
+                .collect();
+            let mut grader = FaultGrader::new(&aig, faults.clone());
+            for p in patterns.iter().cloned() {
+                grader.add_pattern(p);
+            }
+            assert_eq!(grader.nb_patterns(), nb_patterns);
+            let detections = grader.finish();
+
+            let mut gen = TestPatternGenerator::from(&aig, faults.clone(), 0);
+            for p in &patterns {
+                gen.add_single_pattern(p.clone(), false);
+            }
+            let expected: Vec<(Fault, bool)> = faults.iter().copied().zip(gen.detection).collect();
+            assert_eq!(detections, expected);
+        }
+    }
+
+    #[test]
+    fn test_undetected_summary() {
+        assert_eq!(
+            undetected_summary(1200, 3),
+            "Undetected faults: 1,200 undetectable due to tied inputs, 3 redundant"
+        );
+    }
+
+    /// Network where the cone of x is masked by an always-false condition
+    fn masked_cone() -> (Network, Vec<usize>) {
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let c = aig.add_input();
+        let d = aig.add_input();
+        let e = aig.add_input();
+        let x0 = aig.and(a, b);
+        let x = aig.xor(x0, c);
+        let t = aig.and(c, d);
+        let u = aig.and(!c, e);
+        let w = aig.and(t, u);
+        let y = aig.and(x, w);
+        let z = aig.xor(d, e);
+        aig.add_output(y);
+        aig.add_output(z);
+        (aig, vec![x0.var() as usize, x.var() as usize])
+    }
+
+    #[test]
+    fn test_suggest_observation_points() {
+        let (aig, cone) = masked_cone();
+        let patterns = generate_comb_test_patterns(&aig, 1, true, &[], None, Compaction::default());
+        let points = suggest_observation_points(&aig, &patterns, true, &[], 1, 1);
+        assert_eq!(points.len(), 1);
+        assert!(cone.contains(&points[0].node));
+        assert!(points[0].gain > 0);
+
+        // Re-running test pattern generation achieves the predicted gain
+        let coverage = |aig: &Network| {
+            let mut gen = TestPatternGenerator::from(aig, Fault::all(aig), 1);
+            gen.detect_faults();
+            gen.nb_detected()
+        };
+        let observed = add_observation_points(&aig, &points);
+        assert_eq!(observed.nb_outputs(), aig.nb_outputs() + 1);
+        assert!(coverage(&observed) >= coverage(&aig) + points[0].gain);
+    }
+
+    #[test]
+    fn test_observation_summary() {
+        let aig = adder::ripple_carry(2);
+        let points = [
+            ObservationPoint { node: 1, gain: 12 },
+            ObservationPoint { node: 3, gain: 2 },
+        ];
+        assert_eq!(
+            observation_summary(&aig, &points, 80, 100),
+            "Suggested 2 observation points:\n  x1: +12 faults (92.00% coverage)\n  x3: +2 faults (94.00% coverage)"
+        );
+        assert_eq!(
+            observation_summary(&aig, &[], 80, 100),
+            "No observation point would improve the coverage"
+        );
+    }
+
+    #[test]
+    fn test_weighted_compression() {
+        // Two independent cones: x0 = a & b, and x2 = c & d & e
+        let mut aig = Network::new();
+        let i: Vec<_> = (0..5).map(|_| aig.add_input()).collect();
+        let x0 = aig.and(i[0], i[1]);
+        let x1 = aig.and(i[2], i[3]);
+        let x2 = aig.and(x1, i[4]);
+        aig.add_output(x0);
+        aig.add_output(x2);
+        let faults = Fault::all(&aig);
+
+        // The first pattern detects 4 faults, mostly in the first cone; the second detects 7
+        let patterns = [
+            vec![true, true, false, false, false],
+            vec![false, false, true, true, true],
+        ];
+        let first_kept = |weights: Option<&FaultWeights>| {
+            let mut gen = TestPatternGenerator::from(&aig, faults.clone(), 1);
+            if let Some(w) = weights {
+                gen.set_weights(w);
+            }
+            for p in &patterns {
+                gen.add_single_pattern(p.clone(), false);
+            }
+            gen.compress_patterns();
+            gen.check();
+            assert_eq!(gen.nb_patterns(), 2);
+            gen.patterns[0].clone()
+        };
+        assert_eq!(first_kept(None), patterns[1]);
+
+        // With the faults of the first cone ten times more likely, the first pattern is taken first
+        let names = NameTable::new();
+        let text: String = faults
+            .iter()
+            .filter(|f| f.gate() == 0)
+            .map(|f| format!("10 {}\n", f.describe(&aig, &names)))
+            .collect();
+        let weights = FaultWeights::parse(&text, &aig, &names).unwrap();
+        assert_eq!(first_kept(Some(&weights)), patterns[0]);
+
+        // 6 faults of weight 10 in the first cone, 12 faults of weight 1 in the second;
+        // the first pattern detects 3 of the first cone and the output stuck-at-1 of x2
+        let detections = analyze_fault_detection(&aig, &patterns[..1], true);
+        assert_eq!(weights.coverage(&aig, &detections), (31.0, 72.0));
+        let mut gen = TestPatternGenerator::from(&aig, faults.clone(), 1);
+        gen.set_weights(&weights);
+        gen.add_single_pattern(patterns[0].clone(), false);
+        assert_eq!(gen.weighted_coverage(), Some((31.0, 72.0)));
+        assert_eq!(
+            weighted_coverage_summary(31.0, 72.0),
+            "Weighted fault coverage: 43.06% (31.0/72.0 weight detected)"
+        );
+    }
+}
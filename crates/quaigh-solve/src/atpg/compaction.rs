@@ -0,0 +1,253 @@
+//! Test pattern compaction beyond the greedy set cover
+//!
+//! Two complementary strategies are available:
+//!     * dynamic compaction, where each pattern generated by the Sat solver is required to detect
+//!       additional undetected faults, as long as the solver finds a solution;
+//!     * exact compaction, where the minimum number of patterns covering all detected faults is
+//!       found with a Sat solver, starting from the greedy solution and decreasing it until the
+//!       problem becomes unsatisfiable.
+
+use std::cmp::Reverse;
+use std::iter::zip;
+
+use crate::atpg::TestPatternGenerator;
+use crate::equiv::prove;
+use crate::network::NaryType;
+use crate::sim::Fault;
+use crate::{Gate, Network, Signal};
+
+/// Pattern compaction strategies, in addition to the greedy compression
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Compaction {
+    /// Require each pattern generated by the Sat solver to detect several faults
+    pub dynamic: bool,
+    /// Find a minimum set of patterns with a Sat solver, after the greedy compression
+    pub exact: bool,
+}
+
+/// Number of additional faults tried for each pattern with dynamic compaction
+const NB_SECONDARY_FAULTS: usize = 8;
+
+/// Maximum number of distinct patterns for exact compaction
+const MAX_EXACT_PATTERNS: usize = 1024;
+
+/// Find at most `max_sets` sets covering all elements, each element being covered by the given sets
+///
+/// The cardinality constraint is encoded with a sequential counter, as a network solved with the
+/// Sat solver. Returns the selected sets, or None if no such cover exists.
+pub fn find_cover(covers: &[Vec<usize>], nb_sets: usize, max_sets: usize) -> Option<Vec<usize>> {
+    let mut aig = Network::new();
+    aig.add_inputs(nb_sets);
+    let mut constraints = Vec::new();
+    for c in covers {
+        let v: Vec<Signal> = c.iter().map(|i| aig.input(*i)).collect();
+        constraints.push(aig.add_canonical(Gate::Nary(v.into(), NaryType::Or)));
+    }
+    // at_least[j]: at least j + 1 of the sets seen so far are selected
+    let mut at_least = vec![Signal::zero(); max_sets + 1];
+    for i in 0..nb_sets {
+        let x = aig.input(i);
+        for j in (0..=max_sets).rev() {
+            let prev = if j == 0 {
+                Signal::one()
+            } else {
+                at_least[j - 1]
+            };
+            let inc = aig.and(x, prev);
+            at_least[j] = !aig.and(!at_least[j], !inc);
+        }
+    }
+    constraints.push(!at_least[max_sets]);
+    let o = aig.add_canonical(Gate::andn(&constraints));
+    aig.add_output(o);
+    let selection = prove(&aig)?;
+    Some((0..nb_sets).filter(|i| selection[*i]).collect())
+}
+
+impl<'a> TestPatternGenerator<'a> {
+    /// Set the compaction strategies used in addition to the greedy compression
+    pub fn set_compaction(&mut self, compaction: Compaction) {
+        self.compaction = compaction;
+    }
+
+    /// Extend a pattern found for a fault so that it detects other undetected faults
+    ///
+    /// The candidates are tried in order, and those that the pattern does not already detect are
+    /// added to the Sat problem one at a time, as long as it remains satisfiable.
+    pub fn extend_pattern(
+        &mut self,
+        fault: Fault,
+        mut pattern: Vec<bool>,
+        candidates: &[usize],
+    ) -> Vec<bool> {
+        let mut targets = vec![self.target(fault)];
+        let mut nb_tried = 0;
+        for i in candidates {
+            if nb_tried >= NB_SECONDARY_FAULTS {
+                break;
+            }
+            if self.detection[*i] || self.detects(&pattern, self.faults[*i]) {
+                continue;
+            }
+            nb_tried += 1;
+            targets.push(self.target(self.faults[*i]));
+            match self.solve_targets(&targets, true) {
+                Some(p) => pattern = p,
+                None => {
+                    targets.pop();
+                }
+            }
+        }
+        debug_assert!(self.detects(&pattern, fault));
+        pattern
+    }
+
+    /// Compress the patterns, with the greedy algorithm then with exact compaction if enabled
+    pub fn compact_patterns(&mut self) {
+        if !self.compaction.exact {
+            self.compress_patterns();
+            return;
+        }
+        // Patterns that only detect faults detected by another pattern are never needed
+        let mut order: Vec<usize> = (0..self.nb_patterns()).collect();
+        order.sort_by_cached_key(|p| {
+            Reverse(self.pattern_detections[*p].iter().filter(|d| **d).count())
+        });
+        let mut candidates: Vec<usize> = Vec::new();
+        for p in order {
+            let det = &self.pattern_detections[p];
+            let dominated = candidates
+                .iter()
+                .any(|q| zip(det, &self.pattern_detections[*q]).all(|(a, b)| !*a || *b));
+            if !dominated {
+                candidates.push(p);
+            }
+        }
+        let patterns: Vec<Vec<bool>> = candidates
+            .iter()
+            .map(|p| self.patterns[*p].clone())
+            .collect();
+        let detections: Vec<Vec<bool>> = candidates
+            .iter()
+            .map(|p| self.pattern_detections[*p].clone())
+            .collect();
+        self.compress_patterns();
+        if candidates.len() > MAX_EXACT_PATTERNS {
+            println!(
+                "Skipping exact compaction: {} useful patterns, at most {} supported",
+                candidates.len(),
+                MAX_EXACT_PATTERNS
+            );
+            return;
+        }
+
+        let covers: Vec<Vec<usize>> = (0..self.nb_faults())
+            .filter(|f| self.detection[*f])
+            .map(|f| {
+                (0..candidates.len())
+                    .filter(|p| detections[*p][f])
+                    .collect()
+            })
+            .collect();
+        let mut best = None;
+        let mut nb_patterns = self.nb_patterns();
+        while nb_patterns > 1 {
+            match find_cover(&covers, candidates.len(), nb_patterns - 1) {
+                Some(selection) => {
+                    nb_patterns = selection.len();
+                    best = Some(selection);
+                }
+                None => break,
+            }
+        }
+        if let Some(selection) = best {
+            println!("Exact compaction to {} patterns", selection.len());
+            self.patterns = selection.iter().map(|p| patterns[*p].clone()).collect();
+            self.pattern_detections = selection.iter().map(|p| detections[*p].clone()).collect();
+            // Order the patterns again
+            self.compress_patterns();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_cover, Compaction};
+    use crate::atpg::generate_comb_test_patterns;
+    use crate::network::generators::adder;
+    use crate::sim::{detects_faults, Fault};
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_find_cover() {
+        let sets = [
+            vec![0, 1],
+            vec![1, 2],
+            vec![2, 3],
+            vec![0, 3],
+            vec![0, 1, 2],
+        ];
+        let mut covers = vec![Vec::new(); 4];
+        for (i, s) in sets.iter().enumerate() {
+            for e in s {
+                covers[*e].push(i);
+            }
+        }
+        let selection = find_cover(&covers, sets.len(), 2).unwrap();
+        assert!(selection.len() <= 2);
+        for c in &covers {
+            assert!(c.iter().any(|i| selection.contains(i)));
+        }
+        assert_eq!(find_cover(&covers, sets.len(), 1), None);
+    }
+
+    /// Two wide And gates, whose faults are hard to detect with random patterns
+    fn wide_ands() -> Network {
+        let mut aig = Network::new();
+        for _ in 0..2 {
+            aig.add_inputs(12);
+            let v: Vec<Signal> = (aig.nb_inputs() - 12..aig.nb_inputs())
+                .map(|i| aig.input(i))
+                .collect();
+            let x = aig.add(Gate::andn(&v));
+            aig.add_output(x);
+        }
+        aig
+    }
+
+    /// Check that all faults detected by the reference patterns are detected
+    fn check_coverage(aig: &Network, reference: &[Vec<bool>], patterns: &[Vec<bool>]) {
+        for f in Fault::all_unique(aig) {
+            let detected = |patterns: &[Vec<bool>]| {
+                patterns.iter().any(|p| detects_faults(aig, p, &vec![f])[0])
+            };
+            assert_eq!(detected(reference), detected(patterns), "{}", f);
+        }
+    }
+
+    #[test]
+    fn test_dynamic_compaction() {
+        let aig = wide_ands();
+        let greedy = generate_comb_test_patterns(&aig, 1, false, &[], None, Compaction::default());
+        let dynamic = Compaction {
+            dynamic: true,
+            exact: false,
+        };
+        let compacted = generate_comb_test_patterns(&aig, 1, false, &[], None, dynamic);
+        assert!(compacted.len() < greedy.len());
+        check_coverage(&aig, &greedy, &compacted);
+    }
+
+    #[test]
+    fn test_exact_compaction() {
+        let aig = adder::ripple_carry(1);
+        let greedy = generate_comb_test_patterns(&aig, 1, true, &[], None, Compaction::default());
+        let exact = Compaction {
+            dynamic: false,
+            exact: true,
+        };
+        let minimum = generate_comb_test_patterns(&aig, 1, true, &[], None, exact);
+        assert!(minimum.len() <= greedy.len());
+        check_coverage(&aig, &greedy, &minimum);
+    }
+}
@@ -0,0 +1,217 @@
+//! Diagnosis of failing devices from their responses to test patterns
+
+use std::iter::zip;
+
+use crate::sim::{simulate_comb_multi, simulate_comb_multi_with_faults, Fault};
+use crate::Network;
+
+/// Pack up to 64 patterns per word, one word per value
+fn pack(values: &[Vec<bool>], nb_values: usize) -> Vec<Vec<u64>> {
+    values
+        .chunks(64)
+        .map(|chunk| {
+            let mut words = vec![0u64; nb_values];
+            for (lane, p) in chunk.iter().enumerate() {
+                for (w, b) in zip(&mut words, p) {
+                    *w |= (*b as u64) << lane;
+                }
+            }
+            words
+        })
+        .collect()
+}
+
+/// Fault dictionary: the output bits at which each fault makes a set of patterns fail
+///
+/// Failures are stored sparsely, as (batch of 64 patterns, output, failing lanes).
+pub struct FaultDictionary {
+    faults: Vec<Fault>,
+    nb_patterns: usize,
+    nb_outputs: usize,
+    /// Expected response of the fault-free network, per batch of 64 patterns
+    expected: Vec<Vec<u64>>,
+    /// Failing output bits of each fault
+    failures: Vec<Vec<(usize, usize, u64)>>,
+}
+
+/// Candidate fault for a diagnosis, with how well it matches the observed failures
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiagnosisCandidate {
+    /// The fault
+    pub fault: Fault,
+    /// Failing output bits that the fault explains
+    pub explained: usize,
+    /// Failing output bits that the fault does not explain
+    pub unexplained: usize,
+    /// Output bits where the fault predicts a failure that was not observed
+    pub mispredicted: usize,
+}
+
+impl DiagnosisCandidate {
+    /// Returns whether the fault explains all the failures, and only them
+    pub fn is_exact(&self) -> bool {
+        self.unexplained == 0 && self.mispredicted == 0
+    }
+}
+
+impl FaultDictionary {
+    /// Build the dictionary by fault simulation of the patterns on a combinatorial network
+    pub fn new(aig: &Network, patterns: &[Vec<bool>], faults: Vec<Fault>) -> FaultDictionary {
+        assert!(aig.is_comb());
+        let batches = pack(patterns, aig.nb_inputs());
+        let lane_masks: Vec<u64> = (0..batches.len())
+            .map(|b| {
+                let nb = (patterns.len() - 64 * b).min(64);
+                if nb == 64 {
+                    !0
+                } else {
+                    (1 << nb) - 1
+                }
+            })
+            .collect();
+        let expected: Vec<Vec<u64>> = batches
+            .iter()
+            .map(|b| simulate_comb_multi(aig, b))
+            .collect();
+        let mut failures = Vec::with_capacity(faults.len());
+        for f in &faults {
+            let mut fail = Vec::new();
+            for (b, words) in batches.iter().enumerate() {
+                let faulty = simulate_comb_multi_with_faults(aig, words, &[*f]);
+                for (o, (x, y)) in zip(&expected[b], &faulty).enumerate() {
+                    let diff = (x ^ y) & lane_masks[b];
+                    if diff != 0 {
+                        fail.push((b, o, diff));
+                    }
+                }
+            }
+            failures.push(fail);
+        }
+        FaultDictionary {
+            faults,
+            nb_patterns: patterns.len(),
+            nb_outputs: aig.nb_outputs(),
+            expected,
+            failures,
+        }
+    }
+
+    /// Number of patterns in the dictionary
+    pub fn nb_patterns(&self) -> usize {
+        self.nb_patterns
+    }
+
+    /// Rank the faults by how well they match the observed responses to the patterns
+    ///
+    /// Only faults that explain at least one failure are returned. Candidates are sorted by the
+    /// number of mismatches, unexplained failures and mispredictions, then by the number of
+    /// failures explained.
+    pub fn diagnose(&self, responses: &[Vec<bool>]) -> Result<Vec<DiagnosisCandidate>, String> {
+        if responses.len() != self.nb_patterns {
+            return Err(format!(
+                "Expected {} responses, got {}",
+                self.nb_patterns,
+                responses.len()
+            ));
+        }
+        if let Some(r) = responses.iter().find(|r| r.len() != self.nb_outputs) {
+            return Err(format!(
+                "Expected responses with {} outputs, got {}",
+                self.nb_outputs,
+                r.len()
+            ));
+        }
+        let observed = pack(responses, self.nb_outputs);
+        let observed_failures: Vec<Vec<u64>> = zip(&self.expected, &observed)
+            .map(|(e, o)| zip(e, o).map(|(x, y)| x ^ y).collect())
+            .collect();
+        let nb_failures: usize = observed_failures
+            .iter()
+            .flatten()
+            .map(|w| w.count_ones() as usize)
+            .sum();
+
+        let mut ret = Vec::new();
+        for (fault, fail) in zip(&self.faults, &self.failures) {
+            let mut explained = 0;
+            let mut mispredicted = 0;
+            for (b, o, diff) in fail {
+                let obs = observed_failures[*b][*o];
+                explained += (diff & obs).count_ones() as usize;
+                mispredicted += (diff & !obs).count_ones() as usize;
+            }
+            if explained == 0 {
+                continue;
+            }
+            ret.push(DiagnosisCandidate {
+                fault: *fault,
+                explained,
+                unexplained: nb_failures - explained,
+                mispredicted,
+            });
+        }
+        ret.sort_by_key(|c| (c.unexplained + c.mispredicted, nb_failures - c.explained));
+        Ok(ret)
+    }
+}
+
+/// Find the stuck-at faults compatible with the failures observed on a device
+///
+/// The responses are the output values observed for each pattern. See [`FaultDictionary::diagnose`]
+/// for the ranking of the candidates.
+pub fn diagnose(
+    aig: &Network,
+    patterns: &[Vec<bool>],
+    responses: &[Vec<bool>],
+    with_redundant_faults: bool,
+) -> Result<Vec<DiagnosisCandidate>, String> {
+    let faults = if with_redundant_faults {
+        Fault::all(aig)
+    } else {
+        Fault::all_unique(aig)
+    };
+    FaultDictionary::new(aig, patterns, faults).diagnose(responses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diagnose, FaultDictionary};
+    use crate::atpg::generate_random_comb_patterns;
+    use crate::network::generators::adder;
+    use crate::sim::{simulate_comb_with_faults, Fault};
+
+    #[test]
+    fn test_diagnose() {
+        let aig = adder::ripple_carry(4);
+        let patterns = generate_random_comb_patterns(aig.nb_inputs(), 100, 1);
+        let faults = Fault::all_unique(&aig);
+        let dictionary = FaultDictionary::new(&aig, &patterns, faults.clone());
+        assert_eq!(dictionary.nb_patterns(), 100);
+        for f in faults.iter().step_by(5) {
+            let responses: Vec<Vec<bool>> = patterns
+                .iter()
+                .map(|p| simulate_comb_with_faults(&aig, p, &vec![*f]))
+                .collect();
+            let candidates = dictionary.diagnose(&responses).unwrap();
+            assert!(candidates[0].is_exact());
+            assert!(candidates
+                .iter()
+                .take_while(|c| c.is_exact())
+                .any(|c| c.fault == *f));
+        }
+    }
+
+    #[test]
+    fn test_no_failure() {
+        let aig = adder::ripple_carry(2);
+        let patterns = generate_random_comb_patterns(aig.nb_inputs(), 10, 1);
+        let responses: Vec<Vec<bool>> = patterns
+            .iter()
+            .map(|p| simulate_comb_with_faults(&aig, p, &vec![]))
+            .collect();
+        assert!(diagnose(&aig, &patterns, &responses, false)
+            .unwrap()
+            .is_empty());
+        assert!(diagnose(&aig, &patterns, &responses[1..], false).is_err());
+    }
+}
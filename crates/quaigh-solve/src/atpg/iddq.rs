@@ -0,0 +1,155 @@
+//! Test pattern generation for IDDQ testing
+//!
+//! IDDQ testing measures the quiescent supply current after each pattern. A defect such as a short
+//! to a supply rail draws an abnormal current as soon as the faulty node is driven to the opposite
+//! value, whether or not the error reaches an output. Patterns only need to activate each fault,
+//! which requires much fewer patterns than stuck-at testing.
+
+use std::collections::HashSet;
+
+use crate::atpg::{
+    coverage_summary, network_summary, weighted_coverage_summary, Compaction, TestPatternGenerator,
+};
+use crate::sim::{Fault, FaultWeights};
+use crate::{Network, Signal};
+
+/// Add the logic activating a fault to a network starting with the nodes of `aig`
+///
+/// The returned signal is set when the site of the fault takes the value opposite to the stuck
+/// value, and the assumptions of the network hold.
+pub(super) fn add_activation_target(
+    ret: &mut Network,
+    aig: &Network,
+    fault: Fault,
+    hold: Signal,
+) -> Signal {
+    assert!(aig.is_comb());
+    assert!(!fault.is_transition());
+    ret.and(fault.site(aig) ^ fault.value(), hold)
+}
+
+/// Faults with a distinct activation condition
+///
+/// A fault on the input of a gate is activated exactly when the same fault on the output of its
+/// driver is, so only the first fault on each signal is kept. Inputs tied to a constant are not
+/// driven by a net, and are ignored.
+fn unique_iddq_faults(aig: &Network) -> Vec<Fault> {
+    let mut seen = HashSet::new();
+    Fault::all(aig)
+        .into_iter()
+        .filter(|f| {
+            let s = f.site(aig);
+            !s.is_constant() && seen.insert((s.without_inversion(), f.value() ^ s.is_inverted()))
+        })
+        .collect()
+}
+
+/// Generate combinatorial test patterns for IDDQ testing
+///
+/// Faults are detected as soon as they are activated, without propagating them to an output.
+/// Otherwise, this is similar to [`generate_comb_test_patterns`](super::generate_comb_test_patterns).
+pub fn generate_iddq_test_patterns(
+    aig: &Network,
+    seed: u64,
+    with_redundant_faults: bool,
+    ties: &[(usize, bool)],
+    weights: Option<&FaultWeights>,
+    compaction: Compaction,
+) -> Vec<Vec<bool>> {
+    assert!(aig.is_comb());
+    let faults = Fault::all(aig);
+    let unique_faults = unique_iddq_faults(aig);
+
+    println!(
+        "{}",
+        network_summary(aig, faults.len(), unique_faults.len())
+    );
+
+    let mut gen = TestPatternGenerator::from(
+        aig,
+        if with_redundant_faults {
+            faults
+        } else {
+            unique_faults
+        },
+        seed,
+    );
+    gen.set_iddq();
+    gen.set_ties(ties);
+    gen.set_compaction(compaction);
+    if let Some(w) = weights {
+        gen.set_weights(w);
+    }
+    gen.detect_faults();
+    gen.check();
+    gen.compact_patterns();
+    gen.check();
+    println!(
+        "{}",
+        coverage_summary(
+            "Kept",
+            "patterns",
+            gen.nb_patterns(),
+            gen.nb_detected(),
+            gen.nb_faults()
+        )
+    );
+    if let Some((detected, total)) = gen.weighted_coverage() {
+        println!("{}", weighted_coverage_summary(detected, total));
+    }
+    gen.patterns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_iddq_test_patterns, unique_iddq_faults};
+    use crate::atpg::{generate_comb_test_patterns, Compaction, TestPatternGenerator};
+    use crate::network::generators::adder;
+    use crate::sim::{activates_faults_multi, Fault};
+    use crate::{Gate, Network};
+
+    #[test]
+    fn test_adder() {
+        let aig = adder::ripple_carry(4);
+        let patterns = generate_iddq_test_patterns(&aig, 1, true, &[], None, Compaction::default());
+        let faults = Fault::all(&aig);
+        let mut activated = vec![false; faults.len()];
+        for p in &patterns {
+            let multi: Vec<u64> = p.iter().map(|b| if *b { !0 } else { 0 }).collect();
+            for (a, d) in activated
+                .iter_mut()
+                .zip(activates_faults_multi(&aig, &multi, &faults))
+            {
+                *a |= d != 0;
+            }
+        }
+        for (f, a) in faults.iter().zip(&activated) {
+            assert!(*a || f.site(&aig).is_constant(), "{}", f);
+        }
+        let stuck_at = generate_comb_test_patterns(&aig, 1, true, &[], None, Compaction::default());
+        assert!(patterns.len() < stuck_at.len());
+        assert!(unique_iddq_faults(&aig).len() < faults.len());
+    }
+
+    #[test]
+    fn test_tied() {
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let x = aig.add(Gate::and(a, b));
+        aig.add_output(x);
+        let fault = Fault::OutputStuckAtFault {
+            gate: 0,
+            value: false,
+        };
+        let mut gen = TestPatternGenerator::from(&aig, vec![fault], 0);
+        gen.set_iddq();
+        assert_eq!(
+            gen.find_pattern_detecting_fault(fault, true),
+            Some(vec![true, true])
+        );
+        gen.set_ties(&[(0, false)]);
+        assert_eq!(gen.find_pattern_detecting_fault(fault, true), None);
+        assert!(gen.find_pattern_detecting_fault(fault, false).is_some());
+    }
+}
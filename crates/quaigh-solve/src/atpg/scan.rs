@@ -0,0 +1,172 @@
+//! Insertion of a scan chain
+//!
+//! Each flip-flop is converted to a mux-scan flip-flop: when scan enable is set, it ignores its
+//! enable and reset and loads the previous flip-flop of the chain instead of its data. The chain
+//! follows the order of the flip-flops in the network, which is also the order in which they are
+//! exposed for test pattern generation: a pattern on the network with exposed flip-flops can
+//! then be applied by shifting its flip-flop values in.
+
+use std::fmt;
+
+use crate::{Gate, Network, Signal};
+
+/// Scan chain added to a network by [`scan_insert`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanChain {
+    /// Index of the scan enable input
+    pub scan_enable: usize,
+    /// Index of the scan input
+    pub scan_in: usize,
+    /// Index of the scan output
+    pub scan_out: usize,
+    /// Index of the flip-flops in the chain, starting next to the scan input
+    pub flops: Vec<usize>,
+}
+
+impl ScanChain {
+    /// Number of flip-flops in the chain
+    pub fn len(&self) -> usize {
+        self.flops.len()
+    }
+
+    /// Returns whether the chain has no flip-flop
+    pub fn is_empty(&self) -> bool {
+        self.flops.is_empty()
+    }
+
+    /// Input values to apply a pattern of the network with exposed flip-flops
+    ///
+    /// The pattern gives the primary inputs, followed by the value of each flip-flop. The
+    /// flip-flops are loaded with one shift cycle per flip-flop, then the capture cycle applies the
+    /// primary inputs, and the next state is unloaded with one shift cycle per flip-flop. In the
+    /// unload cycles, the scan output gives the next state of the last flip-flop first.
+    pub fn scan_test(&self, pattern: &[bool]) -> Vec<Vec<bool>> {
+        let nb_inputs = pattern.len() - self.len();
+        let (inputs, state) = pattern.split_at(nb_inputs);
+        let cycle = |scan_enable: bool, scan_in: bool| {
+            let mut ret = inputs.to_vec();
+            ret.resize(self.scan_enable.max(self.scan_in) + 1, false);
+            ret[self.scan_enable] = scan_enable;
+            ret[self.scan_in] = scan_in;
+            ret
+        };
+        let mut ret = Vec::new();
+        for b in state.iter().rev() {
+            ret.push(cycle(true, *b));
+        }
+        ret.push(cycle(false, false));
+        for _ in 0..self.len() {
+            ret.push(cycle(true, false));
+        }
+        ret
+    }
+}
+
+impl fmt::Display for ScanChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Scan chain of {} flip-flops: enable i{}, input i{}, output o{}",
+            self.len(),
+            self.scan_enable,
+            self.scan_in,
+            self.scan_out
+        )?;
+        for (i, ff) in self.flops.iter().enumerate() {
+            writeln!(f, "\t{}: x{}", i, ff)?;
+        }
+        Ok(())
+    }
+}
+
+/// Convert the flip-flops to mux-scan flip-flops, connected in a single scan chain
+///
+/// Scan enable and scan input ports are added after the existing inputs, and the scan output after
+/// the existing outputs. Flip-flops keep their index and clock. Returns the new network and the
+/// chain order.
+pub fn scan_insert(aig: &Network) -> (Network, ScanChain) {
+    let mut ret = aig.clone();
+    let scan_enable = ret.add_input();
+    let scan_in = ret.add_input();
+    let mut chain = ScanChain {
+        scan_enable: aig.nb_inputs(),
+        scan_in: aig.nb_inputs() + 1,
+        scan_out: aig.nb_outputs(),
+        flops: Vec::new(),
+    };
+    let mut prev = scan_in;
+    for i in 0..aig.nb_nodes() {
+        if let Gate::Dff([d, en, res], clock) = &aig.gate(i) {
+            let d = ret.add_canonical(Gate::mux(scan_enable, prev, *d));
+            let en = !ret.and(!*en, !scan_enable);
+            let res = ret.and(*res, !scan_enable);
+            ret.replace(i, Gate::Dff([d, en, res], *clock));
+            chain.flops.push(i);
+            prev = Signal::from_var(i as u32);
+        }
+    }
+    ret.add_output(prev);
+    ret.check();
+    (ret, chain)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::scan_insert;
+    use crate::atpg::{generate_random_seq_patterns, LaunchOnCapture};
+    use crate::network::generators::testcases;
+    use crate::sim::{simulate, simulate_comb};
+
+    #[test]
+    fn test_functional_mode() {
+        let aig = testcases::random_seq(3, 4, 20, 2, 1);
+        let (scanned, chain) = scan_insert(&aig);
+        assert_eq!(chain.len(), 4);
+        assert_eq!(scanned.nb_inputs(), 5);
+        assert_eq!(scanned.nb_outputs(), 3);
+        for mut p in generate_random_seq_patterns(3, 6, 8, 1) {
+            let expected = simulate(&aig, &p);
+            for v in &mut p {
+                v.push(false);
+                v.push(true);
+            }
+            let result: Vec<Vec<bool>> = simulate(&scanned, &p)
+                .into_iter()
+                .map(|mut v| {
+                    v.truncate(2);
+                    v
+                })
+                .collect();
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn test_scan_test() {
+        let aig = testcases::random_seq(3, 4, 20, 2, 2);
+        let (scanned, chain) = scan_insert(&aig);
+        let loc = LaunchOnCapture::new(&aig);
+        let mut rng = SmallRng::seed_from_u64(1);
+        for _ in 0..8 {
+            let pattern: Vec<bool> = (0..7).map(|_| rng.gen()).collect();
+            let vectors = chain.scan_test(&pattern);
+            assert_eq!(vectors.len(), 9);
+            let result = simulate(&scanned, &vectors);
+
+            // Outputs of the capture cycle
+            let expected = simulate_comb(loc.exposed(), &pattern);
+            assert_eq!(result[4][..2], expected[..2]);
+
+            // Next state shifted out, last flip-flop first
+            let mut capture = pattern.clone();
+            capture.extend(&pattern[..3]);
+            let next_state = &loc.cycles(&capture)[1][3..];
+            for (i, b) in next_state.iter().rev().enumerate() {
+                assert_eq!(result[5 + i][chain.scan_out], *b);
+            }
+        }
+    }
+}
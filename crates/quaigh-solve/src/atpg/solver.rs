@@ -0,0 +1,145 @@
+//! Sat solver shared by the faults during test pattern generation
+//!
+//! The fault-free network is encoded once in an [`IncrementalSolver`]. For each fault, the logic
+//! setting a target signal when the fault is detected is added to the same network: only the
+//! fanout cone of the fault is duplicated, and its encoding reuses the clauses of the fault-free
+//! logic. Queries are made with the targets and the tied inputs as assumptions, so that the
+//! clauses learned for a fault benefit the following ones.
+
+use std::collections::HashMap;
+
+use crate::equiv::IncrementalSolver;
+use crate::network::{NaryType, OutputKind};
+use crate::sim::Fault;
+use crate::{Gate, Network, Signal};
+
+/// Translate a signal of a network to a copy of it
+pub(super) fn translate(s: Signal, inputs: &[Signal], nodes: &[Signal]) -> Signal {
+    if s.is_input() {
+        inputs[s.input() as usize] ^ s.is_inverted()
+    } else if s.is_var() {
+        nodes[s.var() as usize] ^ s.is_inverted()
+    } else {
+        s
+    }
+}
+
+/// Add a copy of the fanout cone of a gate, and return the signal of each node in the copy
+///
+/// The signals of the inputs and nodes of `aig` in `ret` are given by `inputs` and `nodes`. The
+/// copy of the gate itself is created by `faulty`, from the gate with translated inputs. Nodes
+/// outside its fanout cone are not copied. The network must be topologically sorted.
+pub(super) fn add_faulty_cone(
+    ret: &mut Network,
+    aig: &Network,
+    inputs: &[Signal],
+    nodes: &[Signal],
+    gate: usize,
+    faulty: impl FnOnce(&mut Network, Gate) -> Signal,
+) -> Vec<Signal> {
+    let mut faulty_nodes = nodes.to_vec();
+    let mut changed = vec![false; aig.nb_nodes()];
+    let g = aig.gate(gate).remap(|s| translate(*s, inputs, nodes));
+    faulty_nodes[gate] = faulty(ret, g);
+    changed[gate] = faulty_nodes[gate] != nodes[gate];
+    for i in gate + 1..aig.nb_nodes() {
+        let g = aig.gate(i);
+        if g.vars().any(|v| changed[v as usize]) {
+            let g = g.remap(|s| translate(*s, inputs, &faulty_nodes));
+            faulty_nodes[i] = ret.add(g);
+            changed[i] = true;
+        }
+    }
+    faulty_nodes
+}
+
+/// Add the logic computing whether the outputs of two copies of a network differ
+///
+/// The outputs that are assumptions are not compared.
+pub(super) fn add_difference(
+    ret: &mut Network,
+    aig: &Network,
+    inputs: &[Signal],
+    nodes: &[Signal],
+    faulty_nodes: &[Signal],
+) -> Signal {
+    let mut diffs = Vec::new();
+    for o in 0..aig.nb_outputs() {
+        if aig.output_kind(o) == OutputKind::Assumption {
+            continue;
+        }
+        let a = translate(aig.output(o), inputs, nodes);
+        let b = translate(aig.output(o), inputs, faulty_nodes);
+        if a != b {
+            diffs.push(ret.xor(a, b));
+        }
+    }
+    ret.add_canonical(Gate::Nary(diffs.into(), NaryType::Or))
+}
+
+/// Incremental Sat solver on a fault-free network, extended with a target signal for each fault
+pub(super) struct FaultSolver {
+    solver: IncrementalSolver<'static>,
+    /// Fault-free network
+    base: Network,
+    /// Whether the assumptions of the fault-free network hold
+    hold: Signal,
+    /// Target signal of the faults added so far
+    targets: HashMap<Fault, Signal>,
+}
+
+impl FaultSolver {
+    /// Create a solver on a fault-free network
+    pub fn new(base: Network) -> FaultSolver {
+        let mut solver = IncrementalSolver::from_network(base.clone());
+        let hold = solver.extend(|aig| aig.assumptions_hold());
+        FaultSolver {
+            solver,
+            base,
+            hold,
+            targets: HashMap::new(),
+        }
+    }
+
+    /// Start over from the fault-free network once the logic added for the faults outgrows it
+    ///
+    /// This keeps the cost of each query bounded with backends that solve from scratch. The
+    /// target signals obtained before are invalidated.
+    pub fn trim(&mut self) {
+        if self.solver.network().nb_nodes() > 2 * self.base.nb_nodes() + 1 {
+            *self = FaultSolver::new(std::mem::take(&mut self.base));
+        }
+    }
+
+    /// Target signal of a fault, whose logic is created by `add` the first time
+    ///
+    /// `add` is given the network and whether its assumptions hold.
+    pub fn target(
+        &mut self,
+        fault: Fault,
+        add: impl FnOnce(&mut Network, Signal) -> Signal,
+    ) -> Signal {
+        if let Some(t) = self.targets.get(&fault) {
+            return *t;
+        }
+        let hold = self.hold;
+        let t = self.solver.extend(|aig| add(aig, hold));
+        self.targets.insert(fault, t);
+        t
+    }
+
+    /// Find a pattern setting all the targets, with the tied inputs at their value
+    pub fn solve(&mut self, targets: &[Signal], ties: &[Option<bool>]) -> Option<Vec<bool>> {
+        let mut assumptions = targets.to_vec();
+        for (i, t) in ties.iter().enumerate() {
+            if let Some(v) = t {
+                assumptions.push(self.solver.network().input(i) ^ !*v);
+            }
+        }
+        if self.solver.solve(&assumptions) {
+            Some(self.solver.input_values())
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! Test pattern generation for transition faults, with launch-on-capture patterns
+//!
+//! A transition fault is tested with two cycles. The flip-flops are scanned in and the primary inputs
+//! are applied in the launch cycle; the clock is pulsed so that the flip-flops capture their next
+//! state; new primary inputs are applied in the capture cycle, and the outputs and the next state of
+//! the flip-flops are observed. The state of the capture cycle is not controlled directly: it is
+//! the next state computed in the launch cycle.
+//!
+//! Patterns are generated on the network with exposed flip-flops. Each pattern is given as the
+//! primary inputs and flip-flop values of the launch cycle, followed by the primary inputs of the
+//! capture cycle.
+
+use crate::atpg::solver::{add_difference, add_faulty_cone, translate};
+use crate::atpg::{coverage_summary, expose_dff, network_summary, weighted_coverage_summary};
+use crate::atpg::{Compaction, TestPatternGenerator};
+use crate::sim::{detects_transition_faults_multi, simulate_comb, simulate_comb_multi};
+use crate::sim::{Fault, FaultWeights};
+use crate::{Gate, Network, Signal};
+
+/// Network with exposed flip-flops, unrolled over a launch and a capture cycle
+pub struct LaunchOnCapture {
+    /// Network with exposed flip-flops
+    exposed: Network,
+    /// Network computing the next state of the flip-flops from the exposed inputs
+    next_state: Network,
+    /// Number of primary inputs, before the exposed flip-flops
+    nb_inputs: usize,
+    /// Node index of each flip-flop, with its data, enable and reset
+    flops: Vec<(usize, [Signal; 3])>,
+    /// Fault-free network unrolled over the launch and capture cycles
+    unrolled: Network,
+    /// Signal of each node of the exposed network in the launch cycle of the unrolled network
+    launch_nodes: Vec<Signal>,
+    /// Signal of each input of the exposed network in the capture cycle of the unrolled network
+    capture_inputs: Vec<Signal>,
+    /// Signal of each node of the exposed network in the capture cycle of the unrolled network
+    capture_nodes: Vec<Signal>,
+}
+
+/// Value of the site of a transition fault in the capture cycle, from its launch and capture values
+fn delayed(aig: &mut Network, fault: &Fault, launch: Signal, capture: Signal) -> Signal {
+    if fault.value() {
+        !aig.and(!launch, !capture)
+    } else {
+        aig.and(launch, capture)
+    }
+}
+
+impl LaunchOnCapture {
+    /// Expose the flip-flops of a network, and compute their next state
+    pub fn new(aig: &Network) -> LaunchOnCapture {
+        let mut flops = Vec::new();
+        for i in 0..aig.nb_nodes() {
+            if let Gate::Dff(deps, _) = &aig.gate(i) {
+                flops.push((i, *deps));
+            }
+        }
+        let mut ret = LaunchOnCapture {
+            exposed: expose_dff(aig),
+            next_state: Network::new(),
+            nb_inputs: aig.nb_inputs(),
+            flops,
+            unrolled: Network::new(),
+            launch_nodes: Vec::new(),
+            capture_inputs: Vec::new(),
+            capture_nodes: Vec::new(),
+        };
+        let mut next_state = Network::new();
+        next_state.add_inputs(ret.exposed.nb_inputs());
+        let inputs: Vec<Signal> = (0..next_state.nb_inputs())
+            .map(|i| next_state.input(i))
+            .collect();
+        let nodes = ret.add_frame(&mut next_state, &inputs);
+        for s in ret.add_next_state(&mut next_state, &inputs, &nodes) {
+            next_state.add_output(s);
+        }
+        ret.next_state = next_state;
+        ret.unroll();
+        ret
+    }
+
+    /// Network with exposed flip-flops, on which the faults are defined
+    pub fn exposed(&self) -> &Network {
+        &self.exposed
+    }
+
+    /// Number of primary inputs, before the exposed flip-flops
+    pub fn nb_inputs(&self) -> usize {
+        self.nb_inputs
+    }
+
+    /// Number of values in a pattern: exposed inputs of the launch cycle, then primary inputs of
+    /// the capture cycle
+    pub fn nb_pattern_inputs(&self) -> usize {
+        self.exposed.nb_inputs() + self.nb_inputs
+    }
+
+    /// Fault-free network unrolled over the launch and capture cycles
+    ///
+    /// The outputs are the outputs of the exposed network in the capture cycle.
+    pub(super) fn unrolled(&self) -> &Network {
+        &self.unrolled
+    }
+
+    /// Add a copy of the exposed network, and return the signal of each node
+    fn add_frame(&self, aig: &mut Network, inputs: &[Signal]) -> Vec<Signal> {
+        let mut nodes = Vec::with_capacity(self.exposed.nb_nodes());
+        for i in 0..self.exposed.nb_nodes() {
+            let g = self
+                .exposed
+                .gate(i)
+                .remap(|s| translate(*s, inputs, &nodes));
+            nodes.push(aig.add(g));
+        }
+        nodes
+    }
+
+    /// Add the next state of the flip-flops, from a copy of the exposed network
+    fn add_next_state(
+        &self,
+        aig: &mut Network,
+        inputs: &[Signal],
+        nodes: &[Signal],
+    ) -> Vec<Signal> {
+        let mut ret = Vec::new();
+        for (i, [d, en, res]) in &self.flops {
+            let d = translate(*d, inputs, nodes);
+            let en = translate(*en, inputs, nodes);
+            let res = translate(*res, inputs, nodes);
+            let held = aig.add(Gate::mux(en, d, nodes[*i]));
+            ret.push(aig.and(!res, held));
+        }
+        ret
+    }
+
+    /// Unroll the fault-free network over the launch and capture cycles
+    fn unroll(&mut self) {
+        let mut ret = Network::new();
+        ret.add_inputs(self.nb_pattern_inputs());
+        let launch_inputs: Vec<Signal> = (0..self.exposed.nb_inputs())
+            .map(|i| ret.input(i))
+            .collect();
+        let launch_nodes = self.add_frame(&mut ret, &launch_inputs);
+        let mut capture_inputs: Vec<Signal> = (self.exposed.nb_inputs()..ret.nb_inputs())
+            .map(|i| ret.input(i))
+            .collect();
+        capture_inputs.extend(self.add_next_state(&mut ret, &launch_inputs, &launch_nodes));
+        let capture_nodes = self.add_frame(&mut ret, &capture_inputs);
+        for i in 0..self.exposed.nb_outputs() {
+            let s = translate(self.exposed.output(i), &capture_inputs, &capture_nodes);
+            ret.add_output(s);
+        }
+        ret.check();
+        self.unrolled = ret;
+        self.launch_nodes = launch_nodes;
+        self.capture_inputs = capture_inputs;
+        self.capture_nodes = capture_nodes;
+    }
+
+    /// Add the logic detecting a transition fault to a network starting with the unrolled network
+    ///
+    /// Only the capture cycle is faulty, and the launch cycle gives the previous value of the site.
+    /// The returned signal is set when an output of the capture cycle differs.
+    pub(super) fn add_detection_target(&self, ret: &mut Network, fault: Fault) -> Signal {
+        assert!(fault.is_transition());
+        let launch_inputs: Vec<Signal> = (0..self.exposed.nb_inputs())
+            .map(|i| ret.input(i))
+            .collect();
+        let site = fault.site(&self.exposed);
+        let launch = translate(site, &launch_inputs, &self.launch_nodes);
+        let capture = translate(site, &self.capture_inputs, &self.capture_nodes);
+        let faulty_nodes = add_faulty_cone(
+            ret,
+            &self.exposed,
+            &self.capture_inputs,
+            &self.capture_nodes,
+            fault.gate(),
+            |ret, g| {
+                let site = delayed(ret, &fault, launch, capture);
+                match fault.input() {
+                    Some(input) => {
+                        ret.add(g.remap_with_ind(|s, j| if j == input { site } else { *s }))
+                    }
+                    None => site,
+                }
+            },
+        );
+        add_difference(
+            ret,
+            &self.exposed,
+            &self.capture_inputs,
+            &self.capture_nodes,
+            &faulty_nodes,
+        )
+    }
+
+    /// Inputs of the exposed network in the launch and capture cycles, for 64 patterns at once
+    fn cycles_multi(&self, pattern: &[u64]) -> (Vec<u64>, Vec<u64>) {
+        let (launch, inputs) = pattern.split_at(self.exposed.nb_inputs());
+        let mut capture = inputs.to_vec();
+        capture.extend(simulate_comb_multi(&self.next_state, launch));
+        (launch.to_vec(), capture)
+    }
+
+    /// Inputs of the exposed network in the launch and capture cycles
+    pub fn cycles(&self, pattern: &[bool]) -> Vec<Vec<bool>> {
+        let (launch, inputs) = pattern.split_at(self.exposed.nb_inputs());
+        let mut capture = inputs.to_vec();
+        capture.extend(self.next_state(launch));
+        vec![launch.to_vec(), capture]
+    }
+
+    /// Next state of the flip-flops, from the inputs of the exposed network
+    pub fn next_state(&self, inputs: &[bool]) -> Vec<bool> {
+        simulate_comb(&self.next_state, &inputs.to_vec())
+    }
+
+    /// Analyze which of a set of patterns detect the given faults
+    pub fn detects_faults_multi(&self, pattern: &[u64], faults: &[Fault]) -> Vec<u64> {
+        let (launch, capture) = self.cycles_multi(pattern);
+        detects_transition_faults_multi(&self.exposed, &launch, &capture, faults)
+    }
+}
+
+/// Generate launch-on-capture test patterns for transition faults
+///
+/// The flip-flops of the network are exposed, and a slow-to-rise and a slow-to-fall fault are
+/// considered at each fault site of the exposed network. Each pattern has two timesteps, the
+/// launch and capture cycles, with values for all inputs of the exposed network: the flip-flop
+/// values of the capture cycle are computed from the launch cycle, and are given for reference.
+///
+/// Primary inputs tied to a constant are tied in both cycles.
+pub fn generate_transition_test_patterns(
+    aig: &Network,
+    seed: u64,
+    with_redundant_faults: bool,
+    ties: &[(usize, bool)],
+    weights: Option<&FaultWeights>,
+    compaction: Compaction,
+) -> Vec<Vec<Vec<bool>>> {
+    let loc = LaunchOnCapture::new(aig);
+    let faults = Fault::all_transition(loc.exposed());
+    let unique_faults = Fault::all_unique_transition(loc.exposed());
+
+    println!(
+        "{}",
+        network_summary(loc.exposed(), faults.len(), unique_faults.len())
+    );
+
+    let mut gen = TestPatternGenerator::from_launch_on_capture(
+        &loc,
+        if with_redundant_faults {
+            faults
+        } else {
+            unique_faults
+        },
+        seed,
+    );
+    let mut pattern_ties = ties.to_vec();
+    for (i, v) in ties {
+        if *i < loc.nb_inputs() {
+            pattern_ties.push((loc.exposed().nb_inputs() + i, *v));
+        }
+    }
+    gen.set_ties(&pattern_ties);
+    gen.set_compaction(compaction);
+    if let Some(w) = weights {
+        gen.set_weights(w);
+    }
+    gen.detect_faults();
+    gen.check();
+    gen.compact_patterns();
+    gen.check();
+    println!(
+        "{}",
+        coverage_summary(
+            "Kept",
+            "patterns",
+            gen.nb_patterns(),
+            gen.nb_detected(),
+            gen.nb_faults()
+        )
+    );
+    if let Some((detected, total)) = gen.weighted_coverage() {
+        println!("{}", weighted_coverage_summary(detected, total));
+    }
+    gen.patterns.iter().map(|p| loc.cycles(p)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::{generate_transition_test_patterns, LaunchOnCapture};
+    use crate::atpg::Compaction;
+    use crate::network::generators::{adder, testcases};
+    use crate::sim::{simulate_comb, simulate_with_faults, Fault};
+    use crate::Network;
+
+    /// Check whether a pattern detects a fault, by simulating both cycles of the exposed network
+    fn detects(loc: &LaunchOnCapture, pattern: &[bool], fault: Fault) -> bool {
+        let cycles = loc.cycles(pattern);
+        let faulty = simulate_with_faults(loc.exposed(), &cycles, &vec![fault]);
+        faulty[1] != simulate_comb(loc.exposed(), &cycles[1])
+    }
+
+    #[test]
+    fn test_detection() {
+        let aig = testcases::random_seq(4, 3, 20, 3, 1);
+        let loc = LaunchOnCapture::new(&aig);
+        assert_eq!(loc.nb_pattern_inputs(), 4 + 3 + 4);
+        let faults = Fault::all_transition(loc.exposed());
+        let mut rng = SmallRng::seed_from_u64(1);
+        let multi: Vec<u64> = (0..loc.nb_pattern_inputs()).map(|_| rng.gen()).collect();
+        let detected = loc.detects_faults_multi(&multi, &faults);
+        for lane in 0..8 {
+            let pattern: Vec<bool> = multi.iter().map(|w| (w >> lane) & 1 != 0).collect();
+            for (f, d) in faults.iter().zip(&detected) {
+                assert_eq!(detects(&loc, &pattern, *f), (d >> lane) & 1 != 0, "{}", f);
+            }
+        }
+    }
+
+    fn check_generation(aig: &Network) {
+        let loc = LaunchOnCapture::new(aig);
+        let patterns =
+            generate_transition_test_patterns(aig, 1, false, &[], None, Compaction::default());
+        let flat: Vec<Vec<bool>> = patterns
+            .iter()
+            .map(|p| {
+                assert_eq!(p.len(), 2);
+                let mut flat = p[0].clone();
+                flat.extend(&p[1][..aig.nb_inputs()]);
+                assert_eq!(loc.cycles(&flat), *p);
+                flat
+            })
+            .collect();
+        // Exhaustive search for the testable faults
+        let n = loc.nb_pattern_inputs();
+        assert!(n <= 12);
+        let all: Vec<Vec<bool>> = (0..1usize << n)
+            .map(|p| (0..n).map(|i| (p >> i) & 1 != 0).collect())
+            .collect();
+        for f in Fault::all_unique_transition(loc.exposed()) {
+            let detected = flat.iter().any(|p| detects(&loc, p, f));
+            let testable = all.iter().any(|p| detects(&loc, p, f));
+            assert_eq!(detected, testable, "{}", f);
+        }
+    }
+
+    #[test]
+    fn test_generation_comb() {
+        check_generation(&adder::ripple_carry(2));
+    }
+
+    #[test]
+    fn test_generation_seq() {
+        check_generation(&testcases::random_seq(3, 2, 8, 2, 3));
+        check_generation(&testcases::toggle_chain(3, true, true));
+    }
+}
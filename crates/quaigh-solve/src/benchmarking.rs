@@ -0,0 +1,455 @@
+//! Benchmarking of the optimization against stored baselines
+//!
+//! Each design of a suite is optimized with a fixed pipeline, and its area, depth and runtime are
+//! compared to a baseline recorded by a previous run. Baselines are stored as JSON:
+//! ```json
+//! {
+//!   "version": "0.0.6",
+//!   "pipeline": "share(64);xor-mux;dffe;share(64)",
+//!   "seed": 1,
+//!   "designs": {
+//!     "c17.bench": { "area": 24, "depth": 3, "runtime": 0.0003 }
+//!   }
+//! }
+//! ```
+//!
+//! Each design is optimized twice, to check that the results are deterministic: comparisons with
+//! a baseline are meaningless otherwise. The fastest of the two runs is recorded.
+
+pub mod perf;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::io::{read_aiger, read_bench, read_blif, read_verilog};
+use crate::network::area::AreaParameters;
+use crate::network::depth::depth;
+use crate::network::fingerprint::fingerprint;
+use crate::optim::Pipeline;
+use crate::util::format::{count, duration};
+use crate::Network;
+
+/// Runtime increase, in seconds, below which a design is never reported as slower
+///
+/// Runtimes of small designs are dominated by measurement noise.
+const RUNTIME_SLACK: f64 = 0.05;
+
+/// Metrics of an optimized design
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DesignMetrics {
+    /// Area of the optimized design
+    pub area: usize,
+    /// Logic depth of the optimized design
+    pub depth: usize,
+    /// Optimization runtime, in seconds
+    pub runtime: f64,
+}
+
+/// Metrics of all the designs of a suite, with the settings used to obtain them
+#[derive(Clone, Debug, PartialEq)]
+pub struct Baseline {
+    /// Version of quaigh that produced the metrics
+    pub version: String,
+    /// Pipeline used for the optimization, including its seed
+    pub pipeline: Pipeline,
+    /// Metrics of each design, by file name
+    pub designs: BTreeMap<String, DesignMetrics>,
+}
+
+impl Baseline {
+    /// Write the baseline as JSON
+    pub fn to_json(&self) -> String {
+        let designs: serde_json::Map<String, Value> = self
+            .designs
+            .iter()
+            .map(|(name, m)| {
+                let v = json!({"area": m.area, "depth": m.depth, "runtime": m.runtime});
+                (name.clone(), v)
+            })
+            .collect();
+        let baseline = json!({
+            "version": self.version,
+            "pipeline": self.pipeline.to_string(),
+            "seed": self.pipeline.seed,
+            "designs": designs,
+        });
+        serde_json::to_string_pretty(&baseline).unwrap() + "\n"
+    }
+
+    /// Read a baseline from JSON
+    pub fn from_json(s: &str) -> Result<Baseline, String> {
+        let v: Value = serde_json::from_str(s).map_err(|e| format!("Invalid JSON: {}", e))?;
+        let get_str = |key: &str| {
+            v[key]
+                .as_str()
+                .ok_or_else(|| format!("Missing or invalid {} in baseline", key))
+        };
+        let mut pipeline: Pipeline = get_str("pipeline")?.parse()?;
+        pipeline.seed = v["seed"].as_u64();
+        let mut designs = BTreeMap::new();
+        let entries = v["designs"]
+            .as_object()
+            .ok_or("Missing or invalid designs in baseline")?;
+        for (name, d) in entries {
+            let err = || format!("Invalid metrics for design {} in baseline", name);
+            let metrics = DesignMetrics {
+                area: d["area"].as_u64().ok_or_else(err)? as usize,
+                depth: d["depth"].as_u64().ok_or_else(err)? as usize,
+                runtime: d["runtime"].as_f64().ok_or_else(err)?,
+            };
+            designs.insert(name.clone(), metrics);
+        }
+        Ok(Baseline {
+            version: get_str("version")?.to_owned(),
+            pipeline,
+            designs,
+        })
+    }
+}
+
+/// Designs of a suite: the .bench, .blif, AIGER and Verilog files of the directory, sorted by name
+pub fn suite_designs(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?;
+    let mut ret: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.extension().is_some_and(|e| {
+                e == "bench" || e == "blif" || e == "aag" || e == "aig" || e == "v"
+            })
+        })
+        .collect();
+    ret.sort();
+    Ok(ret)
+}
+
+pub(crate) fn read_design(path: &Path) -> Result<Network, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let aig = if path.extension().is_some_and(|e| e == "blif") {
+        read_blif(data.as_slice())
+    } else if path.extension().is_some_and(|e| e == "aag" || e == "aig") {
+        read_aiger(data.as_slice())
+    } else if path.extension().is_some_and(|e| e == "v") {
+        read_verilog(data.as_slice())
+    } else {
+        read_bench(data.as_slice())
+    };
+    Ok(aig?)
+}
+
+/// Run the pipeline once, giving up after the time limit
+fn timed_run(
+    aig: &Network,
+    pipeline: &Pipeline,
+    time_limit: Option<Duration>,
+) -> Result<(Network, Duration), String> {
+    let (sender, receiver) = mpsc::channel();
+    let mut network = aig.clone();
+    let pipeline = pipeline.clone();
+    std::thread::spawn(move || {
+        let start = Instant::now();
+        pipeline.run(&mut network);
+        // The receiver is gone if the time limit was exceeded
+        let _ = sender.send((network, start.elapsed()));
+    });
+    match time_limit {
+        Some(limit) => receiver
+            .recv_timeout(limit)
+            .map_err(|_| format!("Exceeded the time limit of {}", duration(limit))),
+        None => receiver
+            .recv()
+            .map_err(|_| "Optimization failed".to_owned()),
+    }
+}
+
+/// Optimize a single design and collect its metrics
+///
+/// The design is optimized twice, and an error is returned if the results differ.
+pub fn run_design(
+    aig: &Network,
+    pipeline: &Pipeline,
+    params: &AreaParameters,
+    time_limit: Option<Duration>,
+) -> Result<DesignMetrics, String> {
+    let (first, t1) = timed_run(aig, pipeline, time_limit)?;
+    let (second, t2) = timed_run(aig, pipeline, time_limit)?;
+    if fingerprint(&first) != fingerprint(&second) {
+        return Err("Optimization is not deterministic".to_owned());
+    }
+    Ok(DesignMetrics {
+        area: params.area(&first),
+        depth: depth(&first),
+        runtime: t1.min(t2).as_secs_f64(),
+    })
+}
+
+/// Optimize all the designs of a suite and collect their metrics
+pub fn run_suite(
+    dir: &Path,
+    pipeline: &Pipeline,
+    params: &AreaParameters,
+    time_limit: Option<Duration>,
+) -> Result<Baseline, String> {
+    let mut designs = BTreeMap::new();
+    for path in suite_designs(dir)? {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let err = |e: String| format!("{}: {}", name, e);
+        let aig = read_design(&path).map_err(err)?;
+        let metrics = run_design(&aig, pipeline, params, time_limit).map_err(err)?;
+        designs.insert(name, metrics);
+    }
+    Ok(Baseline {
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+        pipeline: pipeline.clone(),
+        designs,
+    })
+}
+
+/// Outcome of the comparison of a design with its baseline
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Same area and depth, and no significant slowdown
+    Unchanged,
+    /// Better area or depth, and nothing worse
+    Improved,
+    /// Worse area, depth or runtime
+    Regressed,
+    /// Design absent from the baseline
+    New,
+    /// Design of the baseline absent from the suite
+    Missing,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Unchanged => write!(f, "unchanged"),
+            Status::Improved => write!(f, "improved"),
+            Status::Regressed => write!(f, "REGRESSED"),
+            Status::New => write!(f, "new"),
+            Status::Missing => write!(f, "missing"),
+        }
+    }
+}
+
+/// Comparison of a design with its baseline
+#[derive(Clone, Debug, PartialEq)]
+pub struct DesignComparison {
+    /// Name of the design
+    pub name: String,
+    /// Metrics in the baseline
+    pub baseline: Option<DesignMetrics>,
+    /// Metrics of the current run
+    pub current: Option<DesignMetrics>,
+    /// Outcome of the comparison
+    pub status: Status,
+}
+
+/// Comparison of a run with its baseline, displayed as a table
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    /// Comparison of each design, by name
+    pub designs: Vec<DesignComparison>,
+}
+
+fn compare_metrics(base: &DesignMetrics, cur: &DesignMetrics, runtime_tolerance: f64) -> Status {
+    let slower = cur.runtime > base.runtime * (1.0 + runtime_tolerance) + RUNTIME_SLACK;
+    if cur.area > base.area || cur.depth > base.depth || slower {
+        Status::Regressed
+    } else if cur.area < base.area || cur.depth < base.depth {
+        Status::Improved
+    } else {
+        Status::Unchanged
+    }
+}
+
+/// Compare a run with its baseline
+///
+/// A design is reported as regressed if its area or depth increased, or if its runtime increased by
+/// more than the given relative tolerance. Both runs must use the same pipeline and seed.
+pub fn compare(
+    baseline: &Baseline,
+    current: &Baseline,
+    runtime_tolerance: f64,
+) -> Result<Comparison, String> {
+    if baseline.pipeline != current.pipeline {
+        return Err(format!(
+            "The baseline was recorded with pipeline {} and seed {:?}, but the run uses {} and seed {:?}",
+            baseline.pipeline, baseline.pipeline.seed, current.pipeline, current.pipeline.seed
+        ));
+    }
+    let mut names: Vec<&String> = baseline
+        .designs
+        .keys()
+        .chain(current.designs.keys())
+        .collect();
+    names.sort();
+    names.dedup();
+    let designs = names
+        .into_iter()
+        .map(|name| {
+            let b = baseline.designs.get(name).copied();
+            let c = current.designs.get(name).copied();
+            let status = match (&b, &c) {
+                (Some(b), Some(c)) => compare_metrics(b, c, runtime_tolerance),
+                (None, _) => Status::New,
+                (_, None) => Status::Missing,
+            };
+            DesignComparison {
+                name: name.clone(),
+                baseline: b,
+                current: c,
+                status,
+            }
+        })
+        .collect();
+    Ok(Comparison { designs })
+}
+
+impl Comparison {
+    /// Number of designs with a given status
+    pub fn nb_with_status(&self, status: Status) -> usize {
+        self.designs.iter().filter(|d| d.status == status).count()
+    }
+}
+
+/// Format a metric with its change from the baseline
+fn with_delta(cur: Option<usize>, base: Option<usize>) -> String {
+    match (cur, base) {
+        (Some(c), Some(b)) if c != b => {
+            let sign = if c > b { "+" } else { "-" };
+            format!("{} ({}{})", count(c), sign, count(c.abs_diff(b)))
+        }
+        (Some(c), _) => count(c),
+        (None, Some(b)) => format!("({})", count(b)),
+        (None, None) => String::new(),
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name_width = self
+            .designs
+            .iter()
+            .map(|d| d.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(6);
+        writeln!(
+            f,
+            "  {:<name_width$}  {:>16}  {:>12}  {:>20}  Status",
+            "Design", "Area", "Depth", "Runtime"
+        )?;
+        for d in &self.designs {
+            let runtime = match (&d.current, &d.baseline) {
+                (Some(c), Some(b)) => format!(
+                    "{} ({})",
+                    duration(Duration::from_secs_f64(c.runtime)),
+                    duration(Duration::from_secs_f64(b.runtime))
+                ),
+                (Some(c), None) => duration(Duration::from_secs_f64(c.runtime)),
+                _ => String::new(),
+            };
+            writeln!(
+                f,
+                "  {:<name_width$}  {:>16}  {:>12}  {:>20}  {}",
+                d.name,
+                with_delta(d.current.map(|m| m.area), d.baseline.map(|m| m.area)),
+                with_delta(d.current.map(|m| m.depth), d.baseline.map(|m| m.depth)),
+                runtime,
+                d.status
+            )?;
+        }
+        write!(
+            f,
+            "{} designs: {} regressed, {} improved, {} unchanged, {} new, {} missing",
+            self.designs.len(),
+            self.nb_with_status(Status::Regressed),
+            self.nb_with_status(Status::Improved),
+            self.nb_with_status(Status::Unchanged),
+            self.nb_with_status(Status::New),
+            self.nb_with_status(Status::Missing)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use super::{compare, run_suite, Baseline, Status};
+    use crate::network::area::AreaParameters;
+    use crate::optim::Pipeline;
+
+    fn suite() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/bench-opt")
+    }
+
+    fn pipeline() -> Pipeline {
+        let mut p = Pipeline::from_effort(1);
+        p.seed = Some(1);
+        p
+    }
+
+    #[test]
+    fn test_suite() {
+        let params = AreaParameters::vlsi();
+        let run = run_suite(
+            &suite(),
+            &pipeline(),
+            &params,
+            Some(Duration::from_secs(60)),
+        )
+        .unwrap();
+        assert_eq!(
+            run.designs.keys().collect::<Vec<_>>(),
+            vec!["c17.bench", "lut4.bench", "s27.bench"]
+        );
+        // Luts have an area like the other gates
+        assert!(run.designs["lut4.bench"].area > 0);
+        let baseline = Baseline::from_json(&run.to_json()).unwrap();
+        assert_eq!(baseline, run);
+
+        // Identical metrics: nothing to report
+        let cmp = compare(&baseline, &run, 0.5).unwrap();
+        assert_eq!(cmp.nb_with_status(Status::Unchanged), 3);
+
+        // A baseline with a better area for c17 and a worse depth for s27
+        let mut synthetic = baseline.clone();
+        synthetic.designs.get_mut("c17.bench").unwrap().area -= 1;
+        synthetic.designs.get_mut("s27.bench").unwrap().depth += 1;
+        let cmp = compare(&synthetic, &run, 0.5).unwrap();
+        assert_eq!(cmp.designs[0].status, Status::Regressed);
+        assert_eq!(cmp.designs[2].status, Status::Improved);
+        let table = cmp.to_string();
+        assert!(table.contains("REGRESSED"), "{}", table);
+        assert!(
+            table.ends_with("3 designs: 1 regressed, 1 improved, 1 unchanged, 0 new, 0 missing")
+        );
+
+        // Much slower run
+        let mut slow = run.clone();
+        slow.designs.get_mut("s27.bench").unwrap().runtime = 10.0;
+        let cmp = compare(&baseline, &slow, 0.5).unwrap();
+        assert_eq!(cmp.designs[2].status, Status::Regressed);
+
+        // New and missing designs
+        let mut partial = baseline.clone();
+        partial.designs.remove("c17.bench");
+        let cmp = compare(&partial, &run, 0.5).unwrap();
+        assert_eq!(cmp.designs[0].status, Status::New);
+        let cmp = compare(&baseline, &partial, 0.5).unwrap();
+        assert_eq!(cmp.designs[0].status, Status::Missing);
+
+        // Different settings cannot be compared
+        let mut other = run.clone();
+        other.pipeline.seed = Some(2);
+        assert!(compare(&baseline, &other, 0.5).is_err());
+    }
+}
@@ -0,0 +1,411 @@
+//! Runtime budgets of the optimization passes
+//!
+//! A fixed set of synthetic designs is run through the optimization passes, and the runtime of
+//! each pass is compared to a budget. Budgets are stored in a text file that can be edited by hand,
+//! with one budget in milliseconds per line:
+//! ```text
+//!     # Calibration time of the machine the budgets were measured on
+//!     calibration 21.5
+//!     adder-1024 share(64) 40
+//!     adder-1024 balance(16) 25
+//! ```
+//!
+//! To make the budgets portable, the speed of the machine is measured with a calibration loop
+//! before the passes are timed, and the budgets are scaled by the ratio with the calibration time
+//! stored in the file.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use fxhash::FxHashMap;
+use rand::Rng;
+
+use crate::network::generators::{adder, multiplier, testcases};
+use crate::network::NaryType;
+use crate::optim::{Pass, Pipeline};
+use crate::util::format::duration;
+use crate::util::rng::SeededRng;
+use crate::{Gate, Network, Signal};
+
+/// A synthetic design used to time the passes
+#[derive(Clone, Debug)]
+pub struct Workload {
+    /// Name of the design, including its size
+    pub name: String,
+    /// The design
+    pub network: Network,
+}
+
+/// Wide And, Or and Xor gates over random signals
+fn wide_nary(nb_inputs: usize, nb_gates: usize, width: usize, seed: u64) -> Network {
+    let mut rng = SeededRng::new(seed);
+    let mut ret = Network::new();
+    ret.add_inputs(nb_inputs);
+    let mut signals: Vec<Signal> = (0..nb_inputs).map(|i| ret.input(i)).collect();
+    for i in 0..nb_gates {
+        let v: Vec<Signal> = (0..width)
+            .map(|_| signals[rng.gen_range(0..signals.len())] ^ rng.gen::<bool>())
+            .collect();
+        let tp = [NaryType::And, NaryType::Or, NaryType::Xor][i % 3];
+        signals.push(ret.add(Gate::Nary(v.into(), tp)));
+    }
+    for s in &signals[signals.len() - nb_gates.min(64)..] {
+        ret.add_output(*s);
+    }
+    ret
+}
+
+/// Balanced tree of Muxes, with one select per level
+fn mux_tree(depth: usize) -> Network {
+    let mut ret = Network::new();
+    let selects: Vec<Signal> = (0..depth).map(|_| ret.add_input()).collect();
+    let mut level: Vec<Signal> = (0..1 << depth).map(|_| ret.add_input()).collect();
+    for s in selects {
+        level = level
+            .chunks(2)
+            .map(|c| ret.add(Gate::mux(s, c[0], c[1])))
+            .collect();
+    }
+    ret.add_output(level[0]);
+    ret
+}
+
+/// The designs used to time the passes
+///
+/// The fast mode uses much smaller designs, to check the harness itself.
+pub fn workloads(fast: bool) -> Vec<Workload> {
+    let scale = if fast { 16 } else { 1 };
+    let mut ret = vec![
+        Workload {
+            name: format!("adder-{}", 1024 / scale),
+            network: adder::ripple_carry(1024 / scale),
+        },
+        Workload {
+            name: format!("multiplier-{}", 32 / scale.min(4)),
+            network: multiplier::array(32 / scale.min(4)),
+        },
+        Workload {
+            name: format!("nary-{}", 4096 / scale),
+            network: wide_nary(256 / scale, 4096 / scale, 16, 1),
+        },
+        Workload {
+            name: format!("mux-tree-{}", if fast { 6 } else { 12 }),
+            network: mux_tree(if fast { 6 } else { 12 }),
+        },
+    ];
+    for nb_gates in [1000, 10000] {
+        let nb_gates = nb_gates / scale;
+        ret.push(Workload {
+            name: format!("random-{}", nb_gates),
+            network: testcases::random_seq(64, nb_gates / 10, nb_gates, 64, 1),
+        });
+    }
+    ret
+}
+
+/// The passes that are timed, each design going through them in order
+pub fn perf_pipeline() -> Pipeline {
+    Pipeline {
+        seed: None,
+        passes: vec![
+            Pass::ShareLogic(64),
+            Pass::InferXorMux,
+            Pass::InferDffe,
+            Pass::Balance(16),
+        ],
+    }
+}
+
+/// Fixed amount of work, representative of the hashing and sorting done by the passes
+fn calibration_work() -> u64 {
+    let mut map = FxHashMap::default();
+    let mut x: u64 = 1;
+    for i in 0..200_000 {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        *map.entry(x % 50_000).or_insert(0u64) += i;
+    }
+    let mut v: Vec<u64> = map.into_values().collect();
+    v.sort();
+    v[v.len() / 2]
+}
+
+/// Measure the speed of the machine, as the time of the calibration loop in milliseconds
+pub fn calibrate() -> f64 {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(calibration_work());
+            start.elapsed().as_secs_f64() * 1000.0
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Runtime of a pass on a design
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassTiming {
+    /// Name of the design
+    pub workload: String,
+    /// The pass
+    pub pass: Pass,
+    /// Runtime in milliseconds
+    pub time: f64,
+}
+
+/// Time each pass of the pipeline on each design
+///
+/// The pipeline is run several times on each design, and the fastest runtime of each pass is kept.
+pub fn measure(workloads: &[Workload], pipeline: &Pipeline, repeats: usize) -> Vec<PassTiming> {
+    let mut ret = Vec::new();
+    for w in workloads {
+        let mut times = vec![f64::INFINITY; pipeline.passes.len()];
+        for _ in 0..repeats.max(1) {
+            let mut aig = w.network.clone();
+            let mut i = 0;
+            pipeline.run_with_hook(&mut aig, |_, _, t| {
+                times[i] = times[i].min(t.as_secs_f64() * 1000.0);
+                i += 1;
+            });
+        }
+        for (pass, time) in pipeline.passes.iter().zip(times) {
+            ret.push(PassTiming {
+                workload: w.name.clone(),
+                pass: *pass,
+                time,
+            });
+        }
+    }
+    ret
+}
+
+/// Runtime budgets of the passes
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Budgets {
+    /// Calibration time of the machine the budgets apply to, in milliseconds
+    pub calibration: f64,
+    /// Budget in milliseconds, by design and pass
+    pub budgets: BTreeMap<(String, String), f64>,
+}
+
+impl Budgets {
+    /// Budgets allowing the measured runtimes multiplied by a margin
+    ///
+    /// Budgets are at least one millisecond, since shorter runtimes are dominated by noise.
+    pub fn from_timings(timings: &[PassTiming], calibration: f64, margin: f64) -> Budgets {
+        let budgets = timings
+            .iter()
+            .map(|t| {
+                let key = (t.workload.clone(), t.pass.to_string());
+                (key, (t.time * margin).max(1.0).ceil())
+            })
+            .collect();
+        Budgets {
+            calibration,
+            budgets,
+        }
+    }
+
+    /// Parse budgets in the text format
+    pub fn parse(s: &str) -> Result<Budgets, String> {
+        let mut calibration = None;
+        let mut budgets = BTreeMap::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let err = |msg: &str| format!("Line {}: {}", i + 1, msg);
+            let time = |s: &str| -> Result<f64, String> {
+                match s.parse::<f64>() {
+                    Ok(t) if t > 0.0 => Ok(t),
+                    _ => Err(err(&format!("invalid time {}", s))),
+                }
+            };
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.as_slice() {
+                ["calibration", t] => calibration = Some(time(t)?),
+                [workload, pass, t] => {
+                    let pass: Pass = pass.parse().map_err(|e: String| err(&e))?;
+                    budgets.insert((workload.to_string(), pass.to_string()), time(t)?);
+                }
+                _ => return Err(err("expected a design, a pass and a time")),
+            }
+        }
+        Ok(Budgets {
+            calibration: calibration.ok_or("Missing calibration time")?,
+            budgets,
+        })
+    }
+}
+
+impl fmt::Display for Budgets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Runtime budgets of the passes, in milliseconds")?;
+        writeln!(f, "# Calibration time of the machine the budgets apply to")?;
+        writeln!(f, "calibration {}", self.calibration)?;
+        for ((workload, pass), t) in &self.budgets {
+            writeln!(f, "{} {} {}", workload, pass, t)?;
+        }
+        fmt::Result::Ok(())
+    }
+}
+
+/// Runtime of a pass compared to its budget
+#[derive(Clone, Debug, PartialEq)]
+pub struct PerfEntry {
+    /// Runtime of the pass
+    pub timing: PassTiming,
+    /// Budget in milliseconds, scaled to the speed of the machine
+    pub budget: Option<f64>,
+}
+
+impl PerfEntry {
+    /// Whether the pass ran within its budget, if it has one
+    pub fn within_budget(&self) -> Option<bool> {
+        self.budget.map(|b| self.timing.time <= b)
+    }
+}
+
+/// Comparison of the runtimes of the passes with their budgets, displayed as a table
+#[derive(Clone, Debug, PartialEq)]
+pub struct PerfReport {
+    /// Ratio between the calibration time of this machine and the one of the budgets
+    pub scale: f64,
+    /// Runtime of each pass on each design
+    pub entries: Vec<PerfEntry>,
+}
+
+impl PerfReport {
+    /// Number of passes that exceeded their budget
+    pub fn nb_over_budget(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| e.within_budget() == Some(false))
+            .count()
+    }
+
+    /// Number of passes without a budget
+    pub fn nb_without_budget(&self) -> usize {
+        self.entries.iter().filter(|e| e.budget.is_none()).count()
+    }
+}
+
+/// Compare the runtimes of the passes with their budgets
+///
+/// The budgets are scaled by the ratio between the calibration time measured on this machine and
+/// the one stored with the budgets.
+pub fn check_budgets(budgets: &Budgets, timings: &[PassTiming], calibration: f64) -> PerfReport {
+    let scale = calibration / budgets.calibration;
+    let entries = timings
+        .iter()
+        .map(|t| {
+            let key = (t.workload.clone(), t.pass.to_string());
+            PerfEntry {
+                timing: t.clone(),
+                budget: budgets.budgets.get(&key).map(|b| b * scale),
+            }
+        })
+        .collect();
+    PerfReport { scale, entries }
+}
+
+fn millis(t: f64) -> String {
+    duration(Duration::from_secs_f64(t / 1000.0))
+}
+
+impl fmt::Display for PerfReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name_width = self
+            .entries
+            .iter()
+            .map(|e| e.timing.workload.len())
+            .max()
+            .unwrap_or(0)
+            .max(6);
+        writeln!(
+            f,
+            "  {:<name_width$}  {:<12}  {:>10}  {:>10}  Status",
+            "Design", "Pass", "Time", "Budget"
+        )?;
+        for e in &self.entries {
+            let (budget, status) = match e.within_budget() {
+                Some(ok) => (
+                    millis(e.budget.unwrap()),
+                    if ok { "ok" } else { "OVER BUDGET" },
+                ),
+                None => (String::new(), "no budget"),
+            };
+            writeln!(
+                f,
+                "  {:<name_width$}  {:<12}  {:>10}  {:>10}  {}",
+                e.timing.workload,
+                e.timing.pass.to_string(),
+                millis(e.timing.time),
+                budget,
+                status
+            )?;
+        }
+        write!(
+            f,
+            "{} passes: {} over budget, {} without budget (budgets scaled by {:.2})",
+            self.entries.len(),
+            self.nb_over_budget(),
+            self.nb_without_budget(),
+            self.scale
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{calibrate, check_budgets, measure, perf_pipeline, workloads, Budgets};
+
+    #[test]
+    fn test_harness() {
+        let w = workloads(true);
+        let pipeline = perf_pipeline();
+        let timings = measure(&w, &pipeline, 1);
+        assert_eq!(timings.len(), w.len() * pipeline.passes.len());
+
+        let calibration = calibrate();
+        let budgets = Budgets::from_timings(&timings, calibration, 1000.0);
+        assert_eq!(Budgets::parse(&budgets.to_string()).unwrap(), budgets);
+
+        let report = check_budgets(&budgets, &timings, calibration);
+        assert_eq!(report.nb_over_budget(), 0);
+        assert_eq!(report.nb_without_budget(), 0);
+        let s = report.to_string();
+        let header: Vec<&str> = s.lines().next().unwrap().split_whitespace().collect();
+        assert_eq!(header, ["Design", "Pass", "Time", "Budget", "Status"]);
+        assert_eq!(s.lines().count(), timings.len() + 2);
+        assert!(s.ends_with("24 passes: 0 over budget, 0 without budget (budgets scaled by 1.00)"));
+
+        // Passes without a budget are reported but do not fail
+        let report = check_budgets(&Budgets::default(), &timings, calibration);
+        assert_eq!(report.nb_over_budget(), 0);
+        assert_eq!(report.nb_without_budget(), timings.len());
+    }
+
+    #[test]
+    fn test_parse_budgets() {
+        let b =
+            Budgets::parse("# Comment\ncalibration 20\nadder-64 share(64) 15 # Slow\n").unwrap();
+        assert_eq!(b.calibration, 20.0);
+        assert_eq!(
+            b.budgets[&("adder-64".to_owned(), "share(64)".to_owned())],
+            15.0
+        );
+        assert_eq!(
+            Budgets::parse("calibration 20\nadder-64 share 15\n").unwrap_err(),
+            "Line 2: Unknown pass share, expected share(N), balance(N), adders(N), window(N), xor-mux, dffe, dffr, dedup-ff, rewrite, mig-rewrite, xor-blocks, const-mult or fraig"
+        );
+        assert_eq!(
+            Budgets::parse("calibration 20\nadder-64 dffe -1\n").unwrap_err(),
+            "Line 2: invalid time -1"
+        );
+        assert!(Budgets::parse("adder-64 dffe 1\n").is_err());
+    }
+}
@@ -0,0 +1,1352 @@
+//! Equivalence checking
+
+mod backend;
+mod batch;
+mod black_box;
+mod by_output;
+mod cdcl;
+mod incremental;
+mod learn;
+mod normalize;
+mod query;
+mod witness;
+
+use std::collections::HashMap;
+use std::iter::zip;
+use std::time::Instant;
+
+use rand::Rng;
+use rustsat::types::Lit;
+use volute::Lut;
+
+use crate::network::{
+    BinaryType, ClockId, GateRef, GrowthLimitExceeded, NaryType, OutputKind, TernaryType,
+};
+use crate::sim::simulate_comb_multi;
+use crate::util::rng::SeededRng;
+use crate::{Gate, Network, Signal};
+
+pub use backend::{lit_value, new_solver, sat_backend, set_sat_backend, SatBackend, SatSolver};
+pub use batch::{parse_output_map, BatchEntry, BatchEquivRunner, BatchResult, BatchVerdict};
+pub use black_box::align_black_boxes;
+pub use by_output::{
+    check_equivalence_bounded_by_output, check_equivalence_by_output, output_differences,
+    OutputMismatch,
+};
+pub use cdcl::BuiltinSolver;
+pub use incremental::IncrementalSolver;
+pub use learn::LearnReport;
+pub use normalize::{normalize_miter, MiterReduction};
+pub use query::{query_signals, SignalDifference};
+pub use witness::WitnessBundle;
+
+// TODO: have clean clause builder object to encapsulate this part
+
+/// Add clauses for And-type n-ary function
+fn add_and_clauses(
+    clauses: &mut Vec<Vec<Signal>>,
+    v: &[Signal],
+    n: Signal,
+    inv_in: bool,
+    inv_out: bool,
+) {
+    for s in v.iter() {
+        clauses.push(vec![s ^ inv_in, !n ^ inv_out]);
+    }
+    let mut c = vec![n ^ inv_out];
+    for s in v.iter() {
+        c.push(!s ^ inv_in);
+    }
+    clauses.push(c);
+}
+
+/// Add clauses for Xor-type n-ary function
+fn add_xor_clauses(
+    clauses: &mut Vec<Vec<Signal>>,
+    var: &mut u32,
+    v: &[Signal],
+    n: Signal,
+    inv_out: bool,
+) {
+    if v.is_empty() {
+        clauses.push(vec![!n ^ inv_out]);
+    } else {
+        // Implement as a series of consecutive Xor
+        let mut a = v[0];
+        for i in 1..v.len() {
+            let b = v[i];
+            let v = Signal::from_var(*var);
+            *var += 1;
+            clauses.push(vec![a, b, !v]);
+            clauses.push(vec![!a, !b, !v]);
+            clauses.push(vec![!a, b, v]);
+            clauses.push(vec![a, !b, v]);
+            a = v;
+        }
+        // Final clauses to force n to the last result
+        clauses.push(vec![a, !n ^ inv_out]);
+        clauses.push(vec![!a, n ^ inv_out]);
+    }
+}
+
+/// Add clauses for Luts
+///
+/// This simply adds one clause for each entry of the LUT, so 64 clauses for a 6-input LUT
+fn add_lut_clauses(clauses: &mut Vec<Vec<Signal>>, v: &[Signal], n: Signal, lut: &Lut) {
+    for mask in 0..lut.num_bits() {
+        let val_out = lut.value(mask);
+        let mut clause = vec![!n ^ val_out];
+        for i in 0..lut.num_vars() {
+            let val_i = (mask >> i) & 1 != 0;
+            clause.push(v[i] ^ val_i);
+        }
+        clauses.push(clause);
+    }
+}
+
+/// Follow a chain of buffers to the signal it copies
+fn resolve_buf(aig: &Network, s: Signal) -> Signal {
+    let mut s = s;
+    while s.is_var() {
+        match aig.gate_ref(s.var() as usize) {
+            GateRef::Buf(x) => s = *x ^ s.is_inverted(),
+            _ => break,
+        }
+    }
+    s
+}
+
+/// Mark the nodes that are in the transitive fanin of an output
+fn output_cones(aig: &Network) -> Vec<bool> {
+    let mut used = vec![false; aig.nb_nodes()];
+    let mut stack: Vec<Signal> = (0..aig.nb_outputs()).map(|o| aig.output(o)).collect();
+    while let Some(s) = stack.pop() {
+        if !s.is_var() || used[s.var() as usize] {
+            continue;
+        }
+        used[s.var() as usize] = true;
+        stack.extend(aig.dependencies(s.var() as usize));
+    }
+    used
+}
+
+/// Add the clauses for a single gate
+///
+/// New variables, numbered from `var`, are created for the intermediate results of Xor gates.
+fn add_gate_clauses(ret: &mut Vec<Vec<Signal>>, var: &mut u32, n: Signal, g: GateRef) {
+    use GateRef::*;
+    match g {
+        Binary([a, b], BinaryType::And) => {
+            // 3 clauses, 7 literals
+            ret.push(vec![*a, !n]);
+            ret.push(vec![*b, !n]);
+            ret.push(vec![!a, !b, n]);
+        }
+        Binary([a, b], BinaryType::Xor) => {
+            // 4 clauses, 12 literals
+            ret.push(vec![*a, *b, !n]);
+            ret.push(vec![!a, !b, !n]);
+            ret.push(vec![!a, *b, n]);
+            ret.push(vec![*a, !b, n]);
+        }
+        Ternary([a, b, c], TernaryType::And) => {
+            // 4 clauses, 10 literals
+            ret.push(vec![*a, !n]);
+            ret.push(vec![*b, !n]);
+            ret.push(vec![*c, !n]);
+            ret.push(vec![!a, !b, !c, n]);
+        }
+        Ternary([a, b, c], TernaryType::Xor) => {
+            // 8 clauses, 24 literals, one new variable
+            let v = Signal::from_var(*var);
+            *var += 1;
+            // First Xor to new variable
+            ret.push(vec![*a, *b, !v]);
+            ret.push(vec![!a, !b, !v]);
+            ret.push(vec![!a, *b, v]);
+            ret.push(vec![*a, !b, v]);
+            // Second Xor to output
+            ret.push(vec![v, *c, !n]);
+            ret.push(vec![!v, !c, !n]);
+            ret.push(vec![!v, *c, n]);
+            ret.push(vec![v, !c, n]);
+        }
+        Ternary([s, a, b], TernaryType::Mux) => {
+            // 4 clauses, 12 literals + 2 redundant clauses
+            ret.push(vec![!s, !a, n]);
+            ret.push(vec![!s, *a, !n]);
+            ret.push(vec![*s, !b, n]);
+            ret.push(vec![*s, *b, !n]);
+            // Redundant but useful
+            ret.push(vec![*a, *b, !n]);
+            ret.push(vec![!a, !b, n]);
+        }
+        Ternary([a, b, c], TernaryType::Maj) => {
+            // 6 clauses, 18 literals
+            ret.push(vec![!a, !b, n]);
+            ret.push(vec![!b, !c, n]);
+            ret.push(vec![!a, !c, n]);
+            ret.push(vec![*a, *b, !n]);
+            ret.push(vec![*b, *c, !n]);
+            ret.push(vec![*a, *c, !n]);
+        }
+        Dff(..) | Latch(..) => panic!("Combinatorial network expected"),
+        Nary(v, tp) => match tp {
+            NaryType::And => add_and_clauses(ret, v, n, false, false),
+            NaryType::Or => add_and_clauses(ret, v, n, true, true),
+            NaryType::Nand => add_and_clauses(ret, v, n, false, true),
+            NaryType::Nor => add_and_clauses(ret, v, n, true, false),
+            NaryType::Xor => add_xor_clauses(ret, var, v, n, false),
+            NaryType::Xnor => add_xor_clauses(ret, var, v, n, true),
+        },
+        Buf(_) => {
+            // Replaced by its input in the fanouts
+        }
+        Lut(v, lut) => {
+            add_lut_clauses(ret, v, n, lut);
+        }
+    }
+}
+
+/// Add the clauses for a node of a network, with the buffers replaced by the signal they copy
+fn add_node_clauses(ret: &mut Vec<Vec<Signal>>, var: &mut u32, aig: &Network, i: usize) {
+    let start = ret.len();
+    add_gate_clauses(ret, var, aig.node(i), aig.gate_ref(i));
+    for c in &mut ret[start..] {
+        for s in c.iter_mut() {
+            // Intermediate variables are numbered after the nodes
+            if !s.is_var() || (s.var() as usize) < aig.nb_nodes() {
+                *s = resolve_buf(aig, *s);
+            }
+        }
+    }
+}
+
+/// Remove the constants from a set of clauses
+fn simplify_clauses(clauses: &mut Vec<Vec<Signal>>) {
+    // Filter out zeros (removed from the clause)
+    for c in clauses.iter_mut() {
+        c.retain(|s| *s != Signal::zero());
+        c.sort();
+        c.dedup();
+    }
+    // Filter out ones (clause removed)
+    clauses.retain(|c| c.iter().all(|s| *s != Signal::one()));
+}
+
+/// Export a combinatorial network to a CNF formula
+///
+/// Only the logic in the cone of the outputs is encoded, and buffers are replaced by the signal
+/// they copy, so that the network does not need to be cleaned up first.
+fn to_cnf(aig: &Network) -> Vec<Vec<Signal>> {
+    assert!(aig.is_comb());
+    let mut ret = Vec::<Vec<Signal>>::new();
+    let mut var = aig.nb_nodes() as u32;
+    for (i, used) in output_cones(aig).into_iter().enumerate() {
+        if !used {
+            continue;
+        }
+        add_node_clauses(&mut ret, &mut var, aig, i);
+    }
+    simplify_clauses(&mut ret);
+    ret
+}
+
+/// Copy the gates from one network to another and fill the existing translation table
+///
+/// Latches take their previous value from the translation of the previous step, if any, or
+/// start at 0.
+fn extend_aig_helper(
+    a: &mut Network,
+    b: &Network,
+    t: &mut HashMap<Signal, Signal>,
+    t_prev: Option<&HashMap<Signal, Signal>>,
+    same_inputs: bool,
+) {
+    assert!(b.is_topo_sorted());
+    assert!(!same_inputs || a.nb_inputs() == b.nb_inputs());
+
+    t.insert(Signal::zero(), Signal::zero());
+    t.insert(Signal::one(), Signal::one());
+    for i in 0..b.nb_inputs() {
+        let sa = if same_inputs {
+            a.input(i)
+        } else {
+            a.add_input()
+        };
+        let sb = b.input(i);
+        t.insert(sb, sa);
+        t.insert(!sb, !sa);
+    }
+    for i in 0..b.nb_nodes() {
+        let s = match b.gate_ref(i) {
+            GateRef::Dff(..) => continue,
+            GateRef::Latch([d, en, res]) => {
+                let prev = t_prev.map_or(Signal::zero(), |p| p[&b.node(i)]);
+                let mx = a.add_canonical(Gate::mux(t[en], t[d], prev));
+                a.and(mx, !t[res])
+            }
+            g => a.add(g.remap(|s| t[s])),
+        };
+        t.insert(b.node(i), s);
+        t.insert(!b.node(i), !s);
+    }
+}
+
+/// Copy the gates from one network to another and fill the translation table
+fn extend_aig(a: &mut Network, b: &Network) -> HashMap<Signal, Signal> {
+    let mut t = HashMap::<Signal, Signal>::new();
+    extend_aig_helper(a, b, &mut t, None, true);
+    t
+}
+
+/// Unroll a sequential network over a fixed number of steps, making a larger combinatorial networks
+///
+/// All flip-flops are updated at each step, whatever their clock domain, and transparent latches
+/// pass their inputs through within a step. The outputs of each step keep their role, so that the
+/// assumptions constrain every step.
+pub fn unroll(aig: &Network, nb_steps: usize) -> Network {
+    unroll_impl(aig, nb_steps, None)
+}
+
+/// Unroll a sequential network with several clock domains over a fixed number of steps
+///
+/// Between steps `i` and `i + 1`, only the flip-flops whose clock domain appears in `edges[i]` are
+/// updated, as in [`simulate_with_clocks`](crate::sim::simulate_with_clocks).
+pub fn unroll_with_clocks(aig: &Network, nb_steps: usize, edges: &[Vec<ClockId>]) -> Network {
+    assert!(edges.len() + 1 >= nb_steps);
+    unroll_impl(aig, nb_steps, Some(edges))
+}
+
+fn unroll_impl(aig: &Network, nb_steps: usize, edges: Option<&[Vec<ClockId>]>) -> Network {
+    use Gate::*;
+    let mut ret = Network::new();
+
+    let mut t_prev = HashMap::new();
+    for step in 0..nb_steps {
+        let mut t = HashMap::new();
+
+        // Convert flip-flops for this step
+        for i in 0..aig.nb_nodes() {
+            if let Dff([d, en, res], clock) = &aig.gate(i) {
+                let ff = aig.node(i);
+                let unroll_ff = if step == 0 {
+                    Signal::zero()
+                } else if edges.is_some_and(|e| !e[step - 1].contains(clock)) {
+                    t_prev[&ff]
+                } else {
+                    let mx = ret.add_canonical(Gate::mux(t_prev[en], t_prev[d], t_prev[&ff]));
+                    ret.and(mx, !t_prev[res])
+                };
+                t.insert(ff, unroll_ff);
+                t.insert(!ff, !unroll_ff);
+            }
+        }
+
+        // Convert inputs and nodes
+        let prev = if step == 0 { None } else { Some(&t_prev) };
+        extend_aig_helper(&mut ret, aig, &mut t, prev, false);
+
+        for o in 0..aig.nb_outputs() {
+            ret.add_output(t[&aig.output(o)]);
+            ret.set_output_kind(ret.nb_outputs() - 1, aig.output_kind(o));
+        }
+        std::mem::swap(&mut t, &mut t_prev);
+    }
+    assert_eq!(ret.nb_inputs(), aig.nb_inputs() * nb_steps);
+    assert_eq!(ret.nb_outputs(), aig.nb_outputs() * nb_steps);
+    ret
+}
+
+/// Upper bound on the number of nodes of a network unrolled with [`unroll`]
+pub fn unrolled_size(aig: &Network, nb_steps: usize) -> usize {
+    let nb_dff = (0..aig.nb_nodes())
+        .filter(|i| aig.gate(*i).is_dff())
+        .count();
+    let nb_latch = (0..aig.nb_nodes())
+        .filter(|i| aig.gate(*i).is_latch())
+        .count();
+    let nb_comb = aig.nb_nodes() - nb_dff - nb_latch;
+    // Each flip-flop becomes a Mux and an And after the first step, and each latch at every step
+    nb_steps
+        .saturating_mul(nb_comb + 2 * nb_latch)
+        .saturating_add(nb_steps.saturating_sub(1).saturating_mul(2 * nb_dff))
+}
+
+/// Unroll a sequential network as [`unroll`], failing before allocating anything if the result
+/// would have more than `max_nodes` nodes
+pub fn unroll_with_limit(
+    aig: &Network,
+    nb_steps: usize,
+    max_nodes: usize,
+) -> Result<Network, GrowthLimitExceeded> {
+    GrowthLimitExceeded::check("unroll", unrolled_size(aig, nb_steps), max_nodes)?;
+    Ok(unroll(aig, nb_steps))
+}
+
+/// Add the logic computing whether the assumptions of a network hold, translated in another network
+fn translated_assumptions(eq: &mut Network, a: &Network, ta: &HashMap<Signal, Signal>) -> Signal {
+    let assumptions: Vec<Signal> = a
+        .assumptions()
+        .into_iter()
+        .map(|o| ta[&a.output(o)])
+        .collect();
+    eq.add_canonical(Gate::andn(&assumptions))
+}
+
+/// Create a network with a single output, representing whether two combinatorial networks give different outputs
+///
+/// The outputs of `a` that are assumptions are not compared: the miter is only set for the input
+/// values where they are all 1.
+pub fn difference(a: &Network, b: &Network) -> Network {
+    assert!(a.is_comb() && b.is_comb());
+    assert_eq!(a.nb_inputs(), b.nb_inputs());
+    assert_eq!(a.nb_outputs(), b.nb_outputs());
+
+    let mut eq = Network::new();
+    eq.add_inputs(a.nb_inputs());
+    let ta = extend_aig(&mut eq, a);
+    let tb = extend_aig(&mut eq, b);
+
+    let mut outputs = Vec::new();
+    for i in 0..a.nb_outputs() {
+        if a.output_kind(i) == OutputKind::Assumption {
+            continue;
+        }
+        let sa = ta[&a.output(i)];
+        let sb = tb[&b.output(i)];
+        let o = eq.xor(sa, sb);
+        outputs.push(o);
+    }
+    let diff = eq.add_canonical(Gate::Nary(outputs.into(), NaryType::Or));
+    let hold = translated_assumptions(&mut eq, a, &ta);
+    let diff = eq.and(diff, hold);
+    eq.add_output(diff);
+    eq
+}
+
+/// Find an assignment of the inputs that sets the single output to 1
+///
+/// Returns the assignment, or None if no such assignment exists.
+pub fn prove(a: &Network) -> Option<Vec<bool>> {
+    prove_with_constraints(a, &[])
+}
+
+/// Find an assignment of the inputs that sets the single output to 1, with some inputs tied to constants
+///
+/// Each input may be constrained to a fixed value; inputs beyond the end of the constraints are free.
+/// Returns the assignment, or None if no such assignment exists.
+pub fn prove_with_constraints(a: &Network, constraints: &[Option<bool>]) -> Option<Vec<bool>> {
+    prove_with_clauses(a, constraints, Vec::new())
+}
+
+/// Find an assignment of the inputs that sets the single output to 1, after learning facts
+///
+/// Constant nodes and implications between pairs of nodes are found by random simulation,
+/// and the most promising ones are verified with small Sat queries. They are then added to the
+/// main Sat problem as unit and binary clauses. Statistics are accumulated in the report.
+pub fn prove_with_learning(a: &Network, report: &mut LearnReport) -> Option<Vec<bool>> {
+    let start = Instant::now();
+    let learned = if resolve_buf(a, a.output(0)).is_constant() {
+        Vec::new()
+    } else {
+        learn::learn_clauses(a, report)
+    };
+    report.learn_time += start.elapsed();
+    let start = Instant::now();
+    let ret = prove_with_clauses(a, &[], learned);
+    report.solve_time += start.elapsed();
+    ret
+}
+
+/// Find an assignment of the inputs that sets the single output to 1, with additional clauses
+pub(crate) fn prove_with_clauses(
+    a: &Network,
+    constraints: &[Option<bool>],
+    learned: Vec<Vec<Signal>>,
+) -> Option<Vec<bool>> {
+    assert_eq!(a.nb_outputs(), 1);
+    assert!(constraints.len() <= a.nb_inputs());
+
+    let mut clauses = to_cnf(a);
+    clauses.extend(learned);
+    let out = resolve_buf(a, a.output(0));
+
+    let mut all_lits: Vec<Signal> = clauses
+        .iter()
+        .flatten()
+        .map(|s| s.without_inversion())
+        .collect();
+    for i in 0..a.nb_inputs() {
+        all_lits.push(Signal::from_input(i as u32));
+    }
+    // The output may not appear in any clause
+    if !out.is_constant() {
+        all_lits.push(out.without_inversion());
+    }
+    all_lits.sort();
+    all_lits.dedup();
+    let mut t = HashMap::new();
+    let mut i: u32 = 0;
+    for s in all_lits {
+        t.insert(s, Lit::new(i, false));
+        t.insert(!s, Lit::new(i, true));
+        i += 1;
+    }
+
+    let mut solver = new_solver();
+    for c in clauses {
+        let cl: Vec<Lit> = c.iter().map(|s| t[s]).collect();
+        solver.add_clause(&cl);
+    }
+    if out == Signal::one() {
+        return Some(
+            (0..a.nb_inputs())
+                .map(|i| constraints.get(i).copied().flatten().unwrap_or(false))
+                .collect(),
+        );
+    } else if out == Signal::zero() {
+        return None;
+    }
+    solver.add_unit(t[&out]);
+    for (i, c) in constraints.iter().enumerate() {
+        if let Some(b) = c {
+            let inp = Signal::from_input(i as u32);
+            solver.add_unit(if *b { t[&inp] } else { t[&!inp] });
+        }
+    }
+
+    let sol = solver.solve()?;
+    Some(
+        (0..a.nb_inputs())
+            .map(|i| lit_value(&sol, t[&Signal::from_input(i as u32)]))
+            .collect(),
+    )
+}
+
+/// Number of words of 64 random patterns simulated before calling the Sat solver
+const NB_RANDOM_WORDS: usize = 64;
+
+/// Look for a counterexample to the equivalence of two combinatorial networks by random simulation
+///
+/// Simulates `nb_words` words of 64 random patterns on both networks, and returns the first
+/// pattern for which their outputs differ. This is much faster than the Sat solver when the
+/// networks differ on many patterns, but cannot prove equivalence. As for [`difference`], patterns
+/// that violate the assumptions of `a` are ignored.
+pub fn random_counterexample(
+    a: &Network,
+    b: &Network,
+    nb_words: usize,
+    seed: u64,
+) -> Option<Vec<bool>> {
+    assert!(a.is_comb() && b.is_comb());
+    assert_eq!(a.nb_inputs(), b.nb_inputs());
+    assert_eq!(a.nb_outputs(), b.nb_outputs());
+    let mut rng = SeededRng::new(seed);
+    for _ in 0..nb_words {
+        let inputs: Vec<u64> = (0..a.nb_inputs()).map(|_| rng.gen()).collect();
+        let values_a = simulate_comb_multi(a, &inputs);
+        let values_b = simulate_comb_multi(b, &inputs);
+        let mut diff = 0;
+        let mut hold = !0;
+        for (o, (x, y)) in zip(values_a, values_b).enumerate() {
+            if a.output_kind(o) == OutputKind::Assumption {
+                hold &= x;
+            } else {
+                diff |= x ^ y;
+            }
+        }
+        let diff = diff & hold;
+        if diff != 0 {
+            let lane = diff.trailing_zeros();
+            return Some(inputs.iter().map(|v| (v >> lane) & 1 != 0).collect());
+        }
+    }
+    None
+}
+
+/// Perform equivalence checking on two combinatorial networks
+///
+/// Random patterns are simulated first, so that a counterexample is usually found without
+/// calling the Sat solver when the networks are not equivalent.
+pub fn check_equivalence_comb(a: &Network, b: &Network, optimize: bool) -> Result<(), Vec<bool>> {
+    check_equivalence_comb_impl(a, b, optimize, None)
+}
+
+/// Perform equivalence checking on two combinatorial networks, optionally learning facts
+/// before the Sat solve as in [`prove_with_learning`]
+fn check_equivalence_comb_impl(
+    a: &Network,
+    b: &Network,
+    optimize: bool,
+    learn: Option<&mut LearnReport>,
+) -> Result<(), Vec<bool>> {
+    assert!(a.is_comb() && b.is_comb());
+    if let Some(v) = random_counterexample(a, b, NB_RANDOM_WORDS, 1) {
+        return Err(v);
+    }
+    let mut diff = difference(a, b);
+    if optimize {
+        normalize_miter(&mut diff);
+    }
+    let res = match learn {
+        Some(report) => prove_with_learning(&diff, report),
+        None => prove(&diff),
+    };
+    match res {
+        None => Ok(()),
+        Some(v) => Err(v),
+    }
+}
+
+/// Perform bounded equivalence checking on two sequential networks
+pub fn check_equivalence_bounded(
+    a: &Network,
+    b: &Network,
+    nb_steps: usize,
+    optimize: bool,
+) -> Result<(), Vec<Vec<bool>>> {
+    check_equivalence_bounded_impl(a, b, nb_steps, optimize, None)
+}
+
+/// Perform bounded equivalence checking on two sequential networks, learning constants and
+/// implications before the Sat solve as in [`prove_with_learning`]
+pub fn check_equivalence_bounded_with_learning(
+    a: &Network,
+    b: &Network,
+    nb_steps: usize,
+    optimize: bool,
+    report: &mut LearnReport,
+) -> Result<(), Vec<Vec<bool>>> {
+    check_equivalence_bounded_impl(a, b, nb_steps, optimize, Some(report))
+}
+
+fn check_equivalence_bounded_impl(
+    a: &Network,
+    b: &Network,
+    nb_steps: usize,
+    optimize: bool,
+    learn: Option<&mut LearnReport>,
+) -> Result<(), Vec<Vec<bool>>> {
+    assert_eq!(a.nb_inputs(), b.nb_inputs());
+    assert_eq!(a.nb_outputs(), b.nb_outputs());
+
+    let a_u = unroll(a, nb_steps);
+    let b_u = unroll(b, nb_steps);
+
+    let res = check_equivalence_comb_impl(&a_u, &b_u, optimize, learn);
+    match res {
+        Ok(()) => Ok(()),
+        Err(v) => {
+            assert_eq!(v.len(), a.nb_inputs() * nb_steps);
+            let mut assignment = Vec::<Vec<bool>>::new();
+            for step in 0..nb_steps {
+                let b = step * a.nb_inputs();
+                let e = (step + 1) * a.nb_inputs();
+                assignment.push(v[b..e].to_vec());
+            }
+            Err(assignment)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use volute::Lut;
+
+    use crate::equiv::unroll;
+    use crate::network::stats::stats;
+    use crate::network::{NaryType, OutputKind};
+    use crate::{Gate, Network, Signal};
+
+    use super::{
+        check_equivalence_bounded_with_learning, prove, random_counterexample, LearnReport,
+    };
+
+    /// Check equivalence with and without learning, and make sure that the verdicts agree
+    fn check_equivalence_comb(a: &Network, b: &Network, optimize: bool) -> Result<(), Vec<bool>> {
+        let res = super::check_equivalence_comb(a, b, optimize);
+        let mut report = LearnReport::default();
+        let learned = check_equivalence_bounded_with_learning(a, b, 1, optimize, &mut report);
+        assert_eq!(res.is_ok(), learned.is_ok());
+        res
+    }
+
+    #[test]
+    fn test_equiv_and() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let aa = a.and(l1, l2);
+        a.add_output(aa);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        let ab = b.and(l1, l2);
+        b.add_output(ab);
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+    }
+
+    #[test]
+    fn test_not_equiv_and_zero() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let aa = a.and(l1, l2);
+        a.add_output(aa);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        b.add_output(Signal::zero());
+        let res = check_equivalence_comb(&a, &b, false);
+        assert_eq!(res, Err(vec![true, true]));
+    }
+
+    #[test]
+    fn test_not_equiv_and_or() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let aa = a.and(l1, l2);
+        a.add_output(aa);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        let ab = !b.and(!l1, !l2);
+        b.add_output(ab);
+        let res = check_equivalence_comb(&a, &b, false);
+        assert_ne!(res, Ok(()));
+    }
+
+    #[test]
+    fn test_not_equiv_one_zero() {
+        let mut a = Network::new();
+        a.add_input();
+        a.add_input();
+        a.add_output(Signal::one());
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        b.add_output(Signal::zero());
+        let res = check_equivalence_comb(&a, &b, false);
+        assert_ne!(res, Ok(()));
+    }
+
+    #[test]
+    fn test_equiv_xor() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let a1 = a.and(l1, !l2);
+        let a2 = a.and(!l1, l2);
+        let ax = !a.and(!a1, !a2);
+        a.add_output(ax);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        let bx = b.xor(l1, l2);
+        b.add_output(bx);
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+    }
+
+    #[test]
+    fn test_equiv_mux() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let l3 = a.add_input();
+        let a1 = a.and(l1, l2);
+        let a2 = a.and(!l1, l3);
+        let ax = !a.and(!a1, !a2);
+        a.add_output(ax);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        b.add_input();
+        let bx = b.add_canonical(Gate::mux(l1, l2, l3));
+        b.add_output(bx);
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+    }
+
+    #[test]
+    fn test_equiv_maj() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let l3 = a.add_input();
+        let a1 = a.and(l1, l2);
+        let a2 = a.and(l1, l3);
+        let a3 = a.and(l2, l3);
+        let ax = !a.add(Gate::and3(!a1, !a2, !a3));
+        a.add_output(ax);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        b.add_input();
+        let bx = b.add(Gate::maj(l1, l2, l3));
+        b.add_output(bx);
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+    }
+
+    #[test]
+    fn test_equiv_and3() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let l3 = a.add_input();
+        let a1 = a.and(l1, l2);
+        let a2 = a.and(a1, l3);
+        a.add_output(a2);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        b.add_input();
+        let b2 = b.add(Gate::and3(l1, l2, l3));
+        b.add_output(b2);
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+    }
+
+    #[test]
+    fn test_equiv_xor3() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let l3 = a.add_input();
+        let a1 = a.xor(l1, l2);
+        let a2 = a.xor(a1, l3);
+        a.add_output(a2);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        b.add_input();
+        let b2 = b.add(Gate::xor3(l1, l2, l3));
+        b.add_output(b2);
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+    }
+
+    #[test]
+    fn test_equiv_andn() {
+        for nb in 0..8 {
+            let mut a = Network::new();
+            let mut ao = Signal::one();
+            for _ in 0..nb {
+                let inp = a.add_input();
+                ao = a.and(ao, inp);
+            }
+            a.add_output(ao);
+
+            let mut b = Network::new();
+            let mut v = Vec::new();
+            for _ in 0..nb {
+                v.push(b.add_input());
+            }
+            let bo = b.add(Gate::Nary(v.into(), NaryType::And));
+            b.add_output(bo);
+            check_equivalence_comb(&a, &b, false).unwrap();
+            check_equivalence_comb(&a, &b, true).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_equiv_xorn() {
+        for nb in 0..8 {
+            let mut a = Network::new();
+            let mut ao = Signal::zero();
+            for _ in 0..nb {
+                let inp = a.add_input();
+                ao = a.xor(ao, inp);
+            }
+            a.add_output(ao);
+
+            let mut b = Network::new();
+            let mut v = Vec::new();
+            for _ in 0..nb {
+                v.push(b.add_input());
+            }
+            let bo = b.add(Gate::Nary(v.into(), NaryType::Xor));
+            b.add_output(bo);
+            check_equivalence_comb(&a, &b, false).unwrap();
+            check_equivalence_comb(&a, &b, true).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_equiv_inputs() {
+        let mut a = Network::new();
+        let mut b = Network::new();
+        for _ in 0..3 {
+            let la = a.add_input();
+            a.add_output(la);
+            let lb = b.add_input();
+            b.add_output(lb);
+        }
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+    }
+
+    #[test]
+    fn test_not_equiv_inputs() {
+        let mut a = Network::new();
+        let mut b = Network::new();
+        for _ in 0..3 {
+            let la = a.add_input();
+            a.add_output(la);
+            let lb = b.add_input();
+            b.add_output(!lb);
+        }
+        let res = check_equivalence_comb(&a, &b, false);
+        assert_ne!(res, Ok(()));
+    }
+
+    #[test]
+    fn test_unused_inputs() {
+        let mut a = Network::new();
+        let mut b = Network::new();
+        for _ in 0..3 {
+            a.add_input();
+            b.add_input();
+        }
+        let l = Signal::from_input(0);
+        a.add_output(l);
+        b.add_output(!l);
+        let res = check_equivalence_comb(&a, &b, false);
+        assert_ne!(res, Ok(()));
+    }
+
+    #[test]
+    fn test_simple_unrolling() {
+        let mut a = Network::new();
+        let i0 = a.add_input();
+        let d = a.dff(i0, Signal::one(), Signal::zero());
+        a.add_output(d);
+
+        let nb_steps = 3;
+        let un = unroll(&a, nb_steps);
+        assert_eq!(un.nb_inputs(), nb_steps);
+        assert_eq!(un.nb_outputs(), nb_steps);
+        assert_eq!(un.nb_nodes(), 0);
+        assert_eq!(un.output(0), Signal::zero());
+        for i in 1..nb_steps {
+            assert_eq!(un.output(i), un.input(i - 1));
+        }
+    }
+
+    #[test]
+    fn test_clock_unrolling() {
+        use crate::network::ClockId;
+
+        let mut a = Network::new();
+        let i0 = a.add_input();
+        let (ca, cb) = (ClockId::rising(0), ClockId::rising(1));
+        let x = a.add(Gate::dff_with_clock(i0, Signal::one(), Signal::zero(), ca));
+        let y = a.add(Gate::dff_with_clock(i0, Signal::one(), Signal::zero(), cb));
+        a.add_output(x);
+        a.add_output(y);
+
+        let edges = vec![vec![ca], vec![cb], vec![ca, cb]];
+        let un = super::unroll_with_clocks(&a, 4, &edges);
+        assert_eq!(un.nb_inputs(), 4);
+        assert_eq!(un.nb_outputs(), 8);
+        let expected = [
+            Signal::zero(),
+            Signal::zero(),
+            un.input(0),
+            Signal::zero(),
+            un.input(0),
+            un.input(1),
+            un.input(2),
+            un.input(2),
+        ];
+        for (i, s) in expected.iter().enumerate() {
+            assert_eq!(un.output(i), *s);
+        }
+    }
+
+    #[test]
+    fn test_enable_unrolling() {
+        let mut a = Network::new();
+        let i0 = a.add_input();
+        let i1 = a.add_input();
+        let d = a.dff(i0, i1, Signal::zero());
+        a.add_output(d);
+
+        let nb_steps = 3;
+        let un = unroll(&a, nb_steps);
+        assert_eq!(un.nb_inputs(), 2 * nb_steps);
+        assert_eq!(un.nb_outputs(), nb_steps);
+        assert_eq!(un.nb_nodes(), nb_steps - 1);
+        let st = stats(&un);
+        assert_eq!(st.nb_mux, nb_steps - 2);
+        assert_eq!(st.nb_and, 1);
+        assert_eq!(un.output(0), Signal::zero());
+    }
+
+    #[test]
+    fn test_unroll_limit() {
+        use super::{unroll_with_limit, unrolled_size};
+        use crate::network::GrowthLimitExceeded;
+
+        let mut a = Network::new();
+        let i0 = a.add_input();
+        let i1 = a.add_input();
+        let d = a.dff(i0, i1, Signal::zero());
+        let x = a.and(d, i0);
+        a.add_output(x);
+
+        // 1 And per step, and a Mux and an And per flip-flop after the first step
+        let nb_steps = 4;
+        assert_eq!(unrolled_size(&a, nb_steps), 10);
+        let un = unroll_with_limit(&a, nb_steps, 10).unwrap();
+        assert!(un.nb_nodes() <= 10);
+        assert_eq!(
+            unroll_with_limit(&a, nb_steps, 9).unwrap_err(),
+            GrowthLimitExceeded {
+                pass: "unroll",
+                projected: 10,
+                limit: 9
+            }
+        );
+        assert!(unroll_with_limit(&a, usize::MAX, usize::MAX - 1).is_err());
+    }
+
+    /// Cross-check the flip-flop semantics of the simulator, the unrolling and the file formats
+    ///
+    /// All sequences of 4 cycles are tried, including enable and reset active simultaneously;
+    /// the reset must take priority.
+    #[test]
+    fn test_dff_priority() {
+        use crate::io::{read_bench, read_blif, write_bench, write_blif};
+        use crate::sim::simulate;
+
+        let mut a = Network::new();
+        let d = a.add_input();
+        let en = a.add_input();
+        let res = a.add_input();
+        let x0 = a.add(Gate::dff(d, en, res));
+        let x1 = a.add(Gate::dff(!d, !en, res));
+        let x2 = a.add(Gate::dff(x0, en, !res));
+        a.add_output(x0);
+        a.add_output(x1);
+        a.add_output(x2);
+
+        let mut bench = Vec::new();
+        write_bench(&mut bench, &a);
+        let from_bench = read_bench(bench.as_slice()).unwrap();
+        let mut blif = Vec::new();
+        write_blif(&mut blif, &a);
+        let from_blif = read_blif(blif.as_slice()).unwrap();
+
+        let nb_steps = 4;
+        let un = unroll(&a, nb_steps);
+        for seq in 0..(1usize << (3 * nb_steps)) {
+            let pattern: Vec<Vec<bool>> = (0..nb_steps)
+                .map(|t| (0..3).map(|i| (seq >> (3 * t + i)) & 1 != 0).collect())
+                .collect();
+
+            // Reference model: next = !res & (en ? d : prev)
+            let mut state = [false; 3];
+            let mut expected = Vec::new();
+            for v in &pattern {
+                expected.push(state.to_vec());
+                let [d, en, res] = [v[0], v[1], v[2]];
+                let next =
+                    |d: bool, en: bool, res: bool, prev: bool| !res && (if en { d } else { prev });
+                state = [
+                    next(d, en, res, state[0]),
+                    next(!d, !en, res, state[1]),
+                    next(state[0], en, !res, state[2]),
+                ];
+            }
+
+            assert_eq!(simulate(&a, &pattern), expected);
+            assert_eq!(simulate(&from_bench, &pattern), expected);
+            assert_eq!(simulate(&from_blif, &pattern), expected);
+            let flat = vec![pattern.concat()];
+            assert_eq!(simulate(&un, &flat)[0], expected.concat());
+        }
+    }
+
+    #[test]
+    fn test_latch() {
+        use crate::sim::simulate;
+
+        // A latch feeding another through a gate, in the same step
+        let mut a = Network::new();
+        let d = a.add_input();
+        let en = a.add_input();
+        let res = a.add_input();
+        let x0 = a.add(Gate::latch(d, en, res));
+        let x1 = a.and(x0, d);
+        let x2 = a.add(Gate::latch(x1, !en, Signal::zero()));
+        a.add_output(x0);
+        a.add_output(x2);
+        a.topo_sort();
+        assert!(a.has_latches());
+        let mut lowered = a.clone();
+        lowered.lower_latches();
+        assert!(!lowered.has_latches());
+
+        let nb_steps = 4;
+        let un = unroll(&a, nb_steps);
+        for seq in 0..(1usize << (3 * nb_steps)) {
+            let pattern: Vec<Vec<bool>> = (0..nb_steps)
+                .map(|t| (0..3).map(|i| (seq >> (3 * t + i)) & 1 != 0).collect())
+                .collect();
+
+            // Reference model: value = !res & (en ? d : prev)
+            let mut state = [false; 2];
+            let mut expected = Vec::new();
+            for v in &pattern {
+                let [d, en, res] = [v[0], v[1], v[2]];
+                let value =
+                    |d: bool, en: bool, res: bool, prev: bool| !res && (if en { d } else { prev });
+                let x0 = value(d, en, res, state[0]);
+                state = [x0, value(x0 && d, !en, false, state[1])];
+                expected.push(state.to_vec());
+            }
+
+            assert_eq!(simulate(&a, &pattern), expected);
+            assert_eq!(simulate(&lowered, &pattern), expected);
+            let flat = vec![pattern.concat()];
+            assert_eq!(simulate(&un, &flat)[0], expected.concat());
+        }
+    }
+
+    #[test]
+    fn test_random_counterexample() {
+        let mut a = Network::new();
+        let mut b = Network::new();
+        let mut c = Network::new();
+        for aig in [&mut a, &mut b, &mut c] {
+            aig.add_inputs(16);
+        }
+        let v: Vec<Signal> = (0..16).map(|i| a.input(i)).collect();
+        let x = a.add(Gate::andn(&v));
+        a.add_output(x);
+        b.add_output(Signal::zero());
+        let x = c.add(Gate::andn(&v[..2]));
+        c.add_output(x);
+
+        // Frequent difference, found by simulation
+        let cex = random_counterexample(&a, &c, 4, 1).unwrap();
+        assert!(cex[0] && cex[1]);
+        assert!(!cex.iter().all(|b| *b));
+
+        // Rare difference, only found by the Sat solver
+        assert_eq!(random_counterexample(&a, &b, 4, 1), None);
+        assert_eq!(check_equivalence_comb(&a, &b, false), Err(vec![true; 16]));
+        assert_eq!(random_counterexample(&a, &a, 4, 1), None);
+    }
+
+    #[test]
+    fn test_assumptions() {
+        // The outputs only differ when both inputs are 1, which the assumption excludes
+        let mut a = Network::new();
+        let mut b = Network::new();
+        for aig in [&mut a, &mut b] {
+            aig.add_inputs(2);
+        }
+        let x = a.and(a.input(0), a.input(1));
+        a.add_output(x);
+        a.add_assumption(!x);
+        b.add_output(Signal::zero());
+        b.add_output(Signal::one());
+        check_equivalence_comb(&a, &b, false).unwrap();
+        assert_eq!(random_counterexample(&a, &b, 4, 1), None);
+
+        a.set_output_kind(1, OutputKind::Output);
+        assert!(check_equivalence_comb(&a, &b, false).is_err());
+    }
+
+    #[test]
+    fn test_prove_and() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        // Add an unused input
+        a.add_input();
+        let aa = a.and(l1, l2);
+        a.add_output(aa);
+        let p = prove(&a).unwrap();
+        assert_eq!(p.len(), 3);
+        assert!(p[0]);
+        assert!(p[1]);
+    }
+
+    #[test]
+    fn test_equiv_lut_xor3() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let l3 = a.add_input();
+        let a1 = a.xor(l1, l2);
+        let a2 = a.xor(a1, l3);
+        a.add_output(a2);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        b.add_input();
+        let lut = Lut::nth_var(3, 0) ^ Lut::nth_var(3, 1) ^ Lut::nth_var(3, 2);
+        let b2 = b.add(Gate::lut(&[l1, l2, l3], lut));
+        b.add_output(b2);
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+    }
+
+    #[test]
+    fn test_equiv_lut_and3() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let l3 = a.add_input();
+        let a1 = a.and(l1, l2);
+        let a2 = a.and(a1, l3);
+        a.add_output(a2);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        b.add_input();
+        let lut = Lut::nth_var(3, 0) & Lut::nth_var(3, 1) & Lut::nth_var(3, 2);
+        let b2 = b.add(Gate::lut(&[l1, l2, l3], lut));
+        b.add_output(b2);
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+    }
+
+    #[test]
+    fn test_equiv_lut_andinv3() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let l3 = a.add_input();
+        let a2 = a.add(Gate::and3(!l1, !l2, l3));
+        a.add_output(a2);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        b.add_input();
+        let lut = !Lut::nth_var(3, 0) & !Lut::nth_var(3, 1) & Lut::nth_var(3, 2);
+        let b2 = b.add(Gate::lut(&[l1, l2, l3], lut));
+        b.add_output(b2);
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+    }
+
+    #[test]
+    fn test_equiv_lut_inv_inputs() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let l3 = a.add_input();
+        let a2 = a.add(Gate::and3(!l1, !l2, !l3));
+        a.add_output(a2);
+        let mut b = Network::new();
+        b.add_input();
+        b.add_input();
+        b.add_input();
+        let lut = Lut::nth_var(3, 0) & Lut::nth_var(3, 1) & Lut::nth_var(3, 2);
+        let b2 = b.add(Gate::lut(&[!l1, !l2, !l3], lut));
+        b.add_output(b2);
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+    }
+
+    #[test]
+    fn test_equiv_dead_logic() {
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let x = a.xor(l1, l2);
+        // Unused gates, including a large cone
+        let mut dead = a.and(x, l1);
+        for _ in 0..20 {
+            dead = a.xor(dead, l2);
+        }
+        a.add(Gate::Buf(dead));
+        a.add_output(x);
+        let mut b = Network::new();
+        b.add_inputs(2);
+        let x = b.xor(!l1, !l2);
+        b.add_output(x);
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+    }
+
+    #[test]
+    fn test_equiv_buf_chains() {
+        // Buf chains to a constant, an input and a gate
+        let mut a = Network::new();
+        let l1 = a.add_input();
+        let l2 = a.add_input();
+        let x = a.and(l1, l2);
+        for s in [Signal::one(), !l1, x] {
+            let mut b = s;
+            for _ in 0..5 {
+                b = a.add(Gate::Buf(!b));
+            }
+            a.add_output(b);
+        }
+        let mut b = Network::new();
+        b.add_inputs(2);
+        let x = b.and(l1, l2);
+        b.add_output(Signal::zero());
+        b.add_output(l1);
+        b.add_output(!x);
+        check_equivalence_comb(&a, &b, false).unwrap();
+        check_equivalence_comb(&a, &b, true).unwrap();
+
+        let mut c = b.clone();
+        c.add_output(l2);
+        let mut d = Network::new();
+        d.add_inputs(2);
+        for i in 0..3 {
+            d.add_output(b.output(i));
+        }
+        let x = d.and(l1, l2);
+        d.add_output(x);
+        // An output directly connected to an input
+        let mut e = a.clone();
+        e.add_output(l2);
+        assert!(check_equivalence_comb(&e, &c, false).is_ok());
+        assert!(check_equivalence_comb(&e, &d, false).is_err());
+    }
+
+    #[test]
+    fn test_prove_isolated_output() {
+        // Outputs that appear in no clause
+        for inverted in [false, true] {
+            let mut a = Network::new();
+            let l1 = a.add_input();
+            let l2 = a.add_input();
+            let unused = a.and(l1, l2);
+            a.add(Gate::Buf(unused));
+            let b = a.add(Gate::Buf(l2 ^ inverted));
+            let b = a.add(Gate::Buf(b));
+            a.add_output(b);
+            let p = prove(&a).unwrap();
+            assert_eq!(p[1], !inverted);
+        }
+    }
+
+    #[test]
+    fn test_learning() {
+        use super::{difference, prove_with_learning};
+        use crate::network::generators::adder;
+
+        // Miter between an adder and its canonical version, with a lot of reconvergence
+        let a = adder::ripple_carry(6);
+        let mut b = a.clone();
+        b.make_canonical();
+        let diff = difference(&a, &b);
+        let mut report = LearnReport::default();
+        assert_eq!(prove_with_learning(&diff, &mut report), None);
+        assert!(report.nb_implications + report.nb_constants > 0);
+        assert!(report.nb_checked <= report.nb_candidates);
+    }
+}
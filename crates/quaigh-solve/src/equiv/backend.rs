@@ -0,0 +1,337 @@
+//! Selection of the Sat solver used by all algorithms
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[cfg(feature = "cadical")]
+use rustsat::solvers::SolveIncremental;
+use rustsat::types::Lit;
+#[cfg(any(feature = "kissat", feature = "cadical"))]
+use rustsat::{
+    solvers::{Solve, SolverResult},
+    types::{Clause, TernaryVal},
+};
+
+use super::cdcl::BuiltinSolver;
+
+/// A Sat solver, used through a trait object so that the backend can be chosen at runtime
+pub trait SatSolver {
+    /// Add a clause, as a disjunction of literals
+    fn add_clause(&mut self, clause: &[Lit]);
+
+    /// Add a clause with a single literal
+    fn add_unit(&mut self, l: Lit) {
+        self.add_clause(&[l]);
+    }
+
+    /// Solve the problem
+    ///
+    /// Returns the value of each variable if it is satisfiable, and None otherwise. Variables that
+    /// appear in no clause may be missing from the end of the solution.
+    fn solve(&mut self) -> Option<Vec<bool>> {
+        self.solve_with_assumptions(&[])
+    }
+
+    /// Solve the problem with some literals forced to true, for this call only
+    ///
+    /// Clauses can be added between calls. The builtin solver and CaDiCaL keep what they learned
+    /// from one call to the next, while Kissat solves each call from scratch.
+    fn solve_with_assumptions(&mut self, assumptions: &[Lit]) -> Option<Vec<bool>>;
+}
+
+/// Value of a literal in a solution returned by [`SatSolver::solve`]
+///
+/// Variables missing from the solution are 0.
+pub fn lit_value(solution: &[bool], l: Lit) -> bool {
+    solution.get(l.vidx()).copied().unwrap_or(false) ^ l.is_neg()
+}
+
+/// Sat solvers available to quaigh
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SatBackend {
+    /// [Kissat](https://github.com/arminbiere/kissat), with the `kissat` feature
+    Kissat,
+    /// [CaDiCaL](https://github.com/arminbiere/cadical), with the `cadical` feature
+    Cadical,
+    /// Solver written in Rust, always available but much slower on hard problems
+    Builtin,
+}
+
+impl SatBackend {
+    /// All backends, available or not
+    pub const ALL: [SatBackend; 3] = [SatBackend::Kissat, SatBackend::Cadical, SatBackend::Builtin];
+
+    /// Whether the backend was enabled when building quaigh
+    pub fn is_available(&self) -> bool {
+        match self {
+            SatBackend::Kissat => cfg!(feature = "kissat"),
+            SatBackend::Cadical => cfg!(feature = "cadical"),
+            SatBackend::Builtin => true,
+        }
+    }
+
+    /// Create a new solver with this backend
+    ///
+    /// Panics if the backend is not available.
+    pub fn new_solver(&self) -> Box<dyn SatSolver> {
+        match self {
+            #[cfg(feature = "kissat")]
+            SatBackend::Kissat => Box::new(OneShotSolver::<rustsat_kissat::Kissat>::default()),
+            #[cfg(feature = "cadical")]
+            SatBackend::Cadical => {
+                Box::new(RustsatSolver::<rustsat_cadical::CaDiCaL<'static, 'static>>::default())
+            }
+            SatBackend::Builtin => Box::new(BuiltinSolver::default()),
+            #[allow(unreachable_patterns)]
+            _ => panic!("quaigh was built without the {} feature", self),
+        }
+    }
+}
+
+impl Default for SatBackend {
+    /// The fastest backend available
+    fn default() -> Self {
+        if cfg!(feature = "kissat") {
+            SatBackend::Kissat
+        } else if cfg!(feature = "cadical") {
+            SatBackend::Cadical
+        } else {
+            SatBackend::Builtin
+        }
+    }
+}
+
+impl fmt::Display for SatBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SatBackend::Kissat => write!(f, "kissat"),
+            SatBackend::Cadical => write!(f, "cadical"),
+            SatBackend::Builtin => write!(f, "builtin"),
+        }
+    }
+}
+
+impl FromStr for SatBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ret = match s.to_lowercase().as_str() {
+            "kissat" => SatBackend::Kissat,
+            "cadical" => SatBackend::Cadical,
+            "builtin" => SatBackend::Builtin,
+            _ => {
+                return Err(format!(
+                    "Unknown Sat backend {}, expected kissat, cadical or builtin",
+                    s
+                ))
+            }
+        };
+        if !ret.is_available() {
+            return Err(format!("quaigh was built without the {} feature", ret));
+        }
+        Ok(ret)
+    }
+}
+
+static SAT_BACKEND: AtomicU8 = AtomicU8::new(u8::MAX);
+
+/// Set the Sat solver used by all algorithms
+///
+/// Panics if the backend is not available.
+pub fn set_sat_backend(backend: SatBackend) {
+    assert!(
+        backend.is_available(),
+        "quaigh was built without the {} feature",
+        backend
+    );
+    SAT_BACKEND.store(backend as u8, Ordering::Relaxed);
+}
+
+/// Sat solver used by all algorithms
+pub fn sat_backend() -> SatBackend {
+    match SAT_BACKEND.load(Ordering::Relaxed) {
+        0 => SatBackend::Kissat,
+        1 => SatBackend::Cadical,
+        2 => SatBackend::Builtin,
+        _ => SatBackend::default(),
+    }
+}
+
+/// Create a new solver with the backend given by [`sat_backend`]
+pub fn new_solver() -> Box<dyn SatSolver> {
+    sat_backend().new_solver()
+}
+
+/// Solution of a [rustsat](https://docs.rs/rustsat) solver, on the variables seen so far
+#[cfg(any(feature = "kissat", feature = "cadical"))]
+fn rustsat_solution<S: Solve>(
+    solver: &S,
+    result: SolverResult,
+    nb_vars: usize,
+) -> Option<Vec<bool>> {
+    match result {
+        SolverResult::Sat => {
+            let sol = solver.full_solution().unwrap();
+            Some(
+                (0..nb_vars)
+                    .map(|v| sol.lit_value(Lit::new(v as u32, false)) == TernaryVal::True)
+                    .collect(),
+            )
+        }
+        SolverResult::Unsat => None,
+        SolverResult::Interrupted => panic!("Sat solver couldn't run to completion"),
+    }
+}
+
+/// Adapter for the incremental solvers of [rustsat](https://docs.rs/rustsat)
+#[cfg(feature = "cadical")]
+#[derive(Default)]
+struct RustsatSolver<S> {
+    solver: S,
+    nb_vars: usize,
+}
+
+#[cfg(feature = "cadical")]
+impl<S: SolveIncremental> SatSolver for RustsatSolver<S> {
+    fn add_clause(&mut self, clause: &[Lit]) {
+        for l in clause {
+            self.nb_vars = self.nb_vars.max(l.vidx() + 1);
+        }
+        self.solver
+            .add_clause(Clause::from_iter(clause.iter().copied()))
+            .unwrap();
+    }
+
+    fn solve_with_assumptions(&mut self, assumptions: &[Lit]) -> Option<Vec<bool>> {
+        for l in assumptions {
+            self.nb_vars = self.nb_vars.max(l.vidx() + 1);
+        }
+        let result = self.solver.solve_assumps(assumptions.to_vec()).unwrap();
+        rustsat_solution(&self.solver, result, self.nb_vars)
+    }
+}
+
+/// Adapter for the [rustsat](https://docs.rs/rustsat) solvers that can only solve once
+///
+/// The clauses are kept, and a new solver is created for each call, with the assumptions added
+/// as unit clauses.
+#[cfg(feature = "kissat")]
+#[derive(Default)]
+struct OneShotSolver<S> {
+    clauses: Vec<Vec<Lit>>,
+    nb_vars: usize,
+    _solver: std::marker::PhantomData<S>,
+}
+
+#[cfg(feature = "kissat")]
+impl<S: Solve + Default> SatSolver for OneShotSolver<S> {
+    fn add_clause(&mut self, clause: &[Lit]) {
+        for l in clause {
+            self.nb_vars = self.nb_vars.max(l.vidx() + 1);
+        }
+        self.clauses.push(clause.to_vec());
+    }
+
+    fn solve_with_assumptions(&mut self, assumptions: &[Lit]) -> Option<Vec<bool>> {
+        let mut solver = S::default();
+        for c in &self.clauses {
+            solver
+                .add_clause(Clause::from_iter(c.iter().copied()))
+                .unwrap();
+        }
+        for l in assumptions {
+            self.nb_vars = self.nb_vars.max(l.vidx() + 1);
+            solver.add_unit(*l).unwrap();
+        }
+        let result = solver.solve().unwrap();
+        rustsat_solution(&solver, result, self.nb_vars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::types::Lit;
+
+    use super::{lit_value, SatBackend};
+    use crate::util::rng::SeededRng;
+    use rand::Rng;
+
+    /// Check a random 3-Sat problem against exhaustive search
+    ///
+    /// The clauses are added in two batches, and each batch is solved under random assumptions.
+    fn check_random(backend: SatBackend, nb_vars: usize, nb_clauses: usize, seed: u64) {
+        let mut rng = SeededRng::new(seed);
+        let mut random_lit = || Lit::new(rng.gen_range(0..nb_vars as u32), rng.gen());
+        let clauses: Vec<Vec<Lit>> = (0..nb_clauses)
+            .map(|_| (0..3).map(|_| random_lit()).collect())
+            .collect();
+        let mut solver = backend.new_solver();
+        let mut nb_added = 0;
+        for nb in [nb_clauses / 2, nb_clauses] {
+            for c in &clauses[nb_added..nb] {
+                solver.add_clause(c);
+            }
+            nb_added = nb;
+            for nb_assumptions in 0..3 {
+                let assumptions: Vec<Lit> = (0..nb_assumptions).map(|_| random_lit()).collect();
+                let satisfies = |sol: &[bool]| {
+                    clauses[..nb]
+                        .iter()
+                        .all(|c| c.iter().any(|l| lit_value(sol, *l)))
+                        && assumptions.iter().all(|l| lit_value(sol, *l))
+                };
+                let expected = (0..1usize << nb_vars).any(|m| {
+                    let sol: Vec<bool> = (0..nb_vars).map(|v| m >> v & 1 != 0).collect();
+                    satisfies(&sol)
+                });
+                match solver.solve_with_assumptions(&assumptions) {
+                    Some(sol) => assert!(satisfies(&sol), "{} seed {}", backend, seed),
+                    None => assert!(!expected, "{} seed {}", backend, seed),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_backends() {
+        for backend in SatBackend::ALL {
+            if !backend.is_available() {
+                continue;
+            }
+            for seed in 0..50 {
+                check_random(backend, 10, 30 + seed as usize, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pigeonhole() {
+        // 5 pigeons do not fit in 4 holes
+        let var = |p: u32, h: u32| Lit::new(4 * p + h, false);
+        let mut solver = SatBackend::Builtin.new_solver();
+        for p in 0..5 {
+            solver.add_clause(&(0..4).map(|h| var(p, h)).collect::<Vec<_>>());
+        }
+        for h in 0..4 {
+            for p1 in 0..5 {
+                for p2 in p1 + 1..5 {
+                    solver.add_clause(&[!var(p1, h), !var(p2, h)]);
+                }
+            }
+        }
+        assert!(solver.solve().is_none());
+        solver.add_clause(&[]);
+        assert!(solver.solve().is_none());
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("Builtin".parse::<SatBackend>(), Ok(SatBackend::Builtin));
+        assert!("minisat".parse::<SatBackend>().is_err());
+        assert_eq!(
+            SatBackend::default().to_string().parse(),
+            Ok(SatBackend::default())
+        );
+    }
+}
@@ -0,0 +1,482 @@
+//! Equivalence checking of many pairs of designs, as in a regression flow
+//!
+//! A manifest lists the pairs of original and optimized netlists, one per line:
+//! ```text
+//!     original,optimized,cycles,output_map,time_limit
+//!     adder.bench,adder_opt.bench
+//!     counter.bench,counter_opt.bench,4
+//!     alu.bench,alu_opt.blif,1,alu.map,30
+//! ```
+//! The header line is optional, as are the number of cycles (default 1), the output map and the
+//! time limit in seconds. Paths are relative to the directory of the manifest.
+//!
+//! An output map gives, for each output of the original design, the corresponding output of the
+//! optimized design, as a pair of indices per line.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::benchmarking::read_design;
+use crate::equiv::{check_equivalence_comb, unroll_with_limit};
+use crate::io::write_patterns;
+use crate::network::DEFAULT_MAX_NODES;
+use crate::util::format::duration;
+use crate::Network;
+
+/// A pair of designs to compare
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchEntry {
+    /// Original design
+    pub original: PathBuf,
+    /// Optimized design
+    pub optimized: PathBuf,
+    /// Number of clock cycles considered
+    pub nb_cycles: usize,
+    /// Correspondence between the outputs of the two designs, if they are not in the same order
+    pub output_map: Option<PathBuf>,
+    /// Time limit for this pair, overriding the default of the runner
+    pub time_limit: Option<Duration>,
+}
+
+impl BatchEntry {
+    /// Parse a manifest, with paths relative to the given directory
+    pub fn parse_manifest(text: &str, dir: &Path) -> Result<Vec<BatchEntry>, String> {
+        let mut ret = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+            if ret.is_empty() && fields[0].eq_ignore_ascii_case("original") {
+                continue;
+            }
+            if fields.len() < 2 || fields.len() > 5 {
+                return Err(format!(
+                    "Line {}: expected between 2 and 5 fields, got {}",
+                    i + 1,
+                    fields.len()
+                ));
+            }
+            let field = |j: usize| fields.get(j).copied().filter(|f| !f.is_empty());
+            let nb_cycles = match field(2) {
+                Some(c) => c
+                    .parse()
+                    .map_err(|_| format!("Line {}: invalid number of cycles {}", i + 1, c))?,
+                None => 1,
+            };
+            let time_limit = match field(4) {
+                Some(t) => Some(
+                    t.parse()
+                        .ok()
+                        .and_then(|t| Duration::try_from_secs_f64(t).ok())
+                        .ok_or_else(|| format!("Line {}: invalid time limit {}", i + 1, t))?,
+                ),
+                None => None,
+            };
+            ret.push(BatchEntry {
+                original: dir.join(fields[0]),
+                optimized: dir.join(fields[1]),
+                nb_cycles,
+                output_map: field(3).map(|m| dir.join(m)),
+                time_limit,
+            });
+        }
+        Ok(ret)
+    }
+}
+
+/// Parse an output map, and return the output of the optimized design for each original output
+pub fn parse_output_map(text: &str, nb_outputs: usize) -> Result<Vec<usize>, String> {
+    let mut ret = vec![None; nb_outputs];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parsed: Vec<Option<usize>> = line.split_whitespace().map(|s| s.parse().ok()).collect();
+        let [Some(a), Some(b)] = parsed[..] else {
+            return Err(format!("Line {}: expected two output indices", i + 1));
+        };
+        if a >= nb_outputs || b >= nb_outputs {
+            return Err(format!("Line {}: output index out of range", i + 1));
+        }
+        if ret[a].replace(b).is_some() {
+            return Err(format!("Line {}: output {} is mapped twice", i + 1, a));
+        }
+    }
+    ret.into_iter()
+        .enumerate()
+        .map(|(a, b)| b.ok_or_else(|| format!("Output {} is not mapped", a)))
+        .collect()
+}
+
+/// Outcome of the comparison of a pair of designs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BatchVerdict {
+    /// The designs are equivalent
+    Equivalent,
+    /// The designs differ; the failing pattern is given for each cycle
+    Different(Vec<Vec<bool>>),
+    /// The check did not complete within the time limit
+    Unknown,
+    /// The designs could not be compared
+    Error(String),
+}
+
+impl BatchVerdict {
+    /// Return whether the pair passed the check
+    pub fn is_pass(&self) -> bool {
+        matches!(self, BatchVerdict::Equivalent)
+    }
+}
+
+impl fmt::Display for BatchVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchVerdict::Equivalent => write!(f, "equivalent"),
+            BatchVerdict::Different(_) => write!(f, "different"),
+            BatchVerdict::Unknown => write!(f, "unknown"),
+            BatchVerdict::Error(e) => write!(f, "error: {}", e),
+        }
+    }
+}
+
+/// Result of the comparison of a pair of designs
+#[derive(Clone, Debug)]
+pub struct BatchResult {
+    /// Index of the pair in the manifest
+    pub index: usize,
+    /// The pair of designs
+    pub entry: BatchEntry,
+    /// Outcome of the comparison
+    pub verdict: BatchVerdict,
+    /// Time spent on the comparison
+    pub time: Duration,
+    /// File where the failing pattern was written, if any
+    pub counterexample: Option<PathBuf>,
+}
+
+impl fmt::Display for BatchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>4}  {:<12} {:>8}  {} -> {}",
+            self.index,
+            match self.verdict {
+                BatchVerdict::Error(_) => "error".to_string(),
+                _ => self.verdict.to_string(),
+            },
+            duration(self.time),
+            self.entry.original.display(),
+            self.entry.optimized.display()
+        )?;
+        if let BatchVerdict::Error(e) = &self.verdict {
+            write!(f, ": {}", e)?;
+        }
+        if let Some(p) = &self.counterexample {
+            write!(f, " (counterexample in {})", p.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// An original design, unrolled once and shared by all pairs that use it
+struct Golden {
+    nb_inputs: usize,
+    nb_outputs: usize,
+    unrolled: Network,
+}
+
+/// Equivalence checker for many pairs of designs, run in parallel
+///
+/// Original designs that appear in several pairs are only read and unrolled once.
+pub struct BatchEquivRunner {
+    /// Number of pairs checked in parallel
+    pub nb_jobs: usize,
+    /// Default time limit for each pair
+    pub time_limit: Option<Duration>,
+    /// Whether to optimize the miter before the Sat solve
+    pub optimize: bool,
+    /// Directory where the failing patterns are written
+    pub counterexample_dir: Option<PathBuf>,
+    /// Maximum number of nodes of each unrolled design
+    pub max_nodes: usize,
+    goldens: Mutex<HashMap<(PathBuf, usize), Arc<Golden>>>,
+}
+
+impl Default for BatchEquivRunner {
+    fn default() -> Self {
+        BatchEquivRunner::new()
+    }
+}
+
+impl BatchEquivRunner {
+    /// Create a runner with a single job, no time limit and the default size limit
+    pub fn new() -> BatchEquivRunner {
+        BatchEquivRunner {
+            nb_jobs: 1,
+            time_limit: None,
+            optimize: true,
+            counterexample_dir: None,
+            max_nodes: DEFAULT_MAX_NODES,
+            goldens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Read and unroll an original design, or reuse it if it was already used
+    fn golden(&self, path: &Path, nb_cycles: usize) -> Result<Arc<Golden>, String> {
+        let key = (path.to_path_buf(), nb_cycles);
+        if let Some(g) = self.goldens.lock().unwrap().get(&key) {
+            return Ok(g.clone());
+        }
+        let aig = read_design(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let unrolled = unroll_with_limit(&aig, nb_cycles, self.max_nodes)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let g = Arc::new(Golden {
+            nb_inputs: aig.nb_inputs(),
+            nb_outputs: aig.nb_outputs(),
+            unrolled,
+        });
+        self.goldens.lock().unwrap().insert(key, g.clone());
+        Ok(g)
+    }
+
+    /// Read the optimized design, with its outputs in the order of the original design
+    fn revised(entry: &BatchEntry) -> Result<Network, String> {
+        let path = &entry.optimized;
+        let aig = read_design(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let Some(map_path) = &entry.output_map else {
+            return Ok(aig);
+        };
+        let text = std::fs::read_to_string(map_path)
+            .map_err(|e| format!("{}: {}", map_path.display(), e))?;
+        let map = parse_output_map(&text, aig.nb_outputs())
+            .map_err(|e| format!("{}: {}", map_path.display(), e))?;
+        let mut ret = aig.clone();
+        for (a, b) in map.into_iter().enumerate() {
+            ret.set_output(a, aig.output(b));
+        }
+        Ok(ret)
+    }
+
+    /// Compare a pair of designs
+    fn check(&self, entry: &BatchEntry) -> BatchVerdict {
+        let prepared = self
+            .golden(&entry.original, entry.nb_cycles)
+            .and_then(|golden| {
+                let revised = BatchEquivRunner::revised(entry)?;
+                if revised.nb_inputs() != golden.nb_inputs {
+                    return Err(format!(
+                        "different number of inputs: {} vs {}",
+                        golden.nb_inputs,
+                        revised.nb_inputs()
+                    ));
+                }
+                if revised.nb_outputs() != golden.nb_outputs {
+                    return Err(format!(
+                        "different number of outputs: {} vs {}",
+                        golden.nb_outputs,
+                        revised.nb_outputs()
+                    ));
+                }
+                let unrolled = unroll_with_limit(&revised, entry.nb_cycles, self.max_nodes)
+                    .map_err(|e| format!("{}: {}", entry.optimized.display(), e))?;
+                Ok((golden, unrolled))
+            });
+        let (golden, revised) = match prepared {
+            Ok(p) => p,
+            Err(e) => return BatchVerdict::Error(e),
+        };
+
+        let nb_inputs = golden.nb_inputs;
+        let (sender, receiver) = mpsc::channel();
+        let optimize = self.optimize;
+        std::thread::spawn(move || {
+            let res = check_equivalence_comb(&golden.unrolled, &revised, optimize);
+            // The receiver is gone if the time limit was exceeded
+            let _ = sender.send(res);
+        });
+        let res = match entry.time_limit.or(self.time_limit) {
+            Some(limit) => receiver.recv_timeout(limit).ok(),
+            None => receiver.recv().ok(),
+        };
+        match res {
+            None => BatchVerdict::Unknown,
+            Some(Ok(())) => BatchVerdict::Equivalent,
+            Some(Err(v)) => BatchVerdict::Different(
+                (0..entry.nb_cycles)
+                    .map(|c| v[c * nb_inputs..(c + 1) * nb_inputs].to_vec())
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Write the failing pattern of a pair to the counterexample directory
+    fn write_counterexample(
+        &self,
+        index: usize,
+        entry: &BatchEntry,
+        pattern: &[Vec<bool>],
+    ) -> Option<PathBuf> {
+        let dir = self.counterexample_dir.as_ref()?;
+        let stem = entry
+            .optimized
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let path = dir.join(format!("{}_{}.test", index, stem));
+        let mut f = File::create(&path).ok()?;
+        write_patterns(&mut f, &vec![pattern.to_vec()]);
+        Some(path)
+    }
+
+    /// Compare all pairs, calling the callback as soon as each result is available
+    ///
+    /// Returns the results in the order of the entries.
+    pub fn run<F: FnMut(&BatchResult)>(
+        &self,
+        entries: &[BatchEntry],
+        mut callback: F,
+    ) -> Vec<BatchResult> {
+        let next = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+        let mut ret = Vec::new();
+        std::thread::scope(|scope| {
+            for _ in 0..self.nb_jobs.clamp(1, entries.len().max(1)) {
+                let sender = sender.clone();
+                let next = &next;
+                scope.spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(entry) = entries.get(index) else {
+                        break;
+                    };
+                    let start = Instant::now();
+                    let verdict = self.check(entry);
+                    let time = start.elapsed();
+                    let counterexample = match &verdict {
+                        BatchVerdict::Different(p) => self.write_counterexample(index, entry, p),
+                        _ => None,
+                    };
+                    let _ = sender.send(BatchResult {
+                        index,
+                        entry: entry.clone(),
+                        verdict,
+                        time,
+                        counterexample,
+                    });
+                });
+            }
+            drop(sender);
+            for result in receiver {
+                callback(&result);
+                ret.push(result);
+            }
+        });
+        ret.sort_by_key(|r| r.index);
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{parse_output_map, BatchEntry, BatchEquivRunner, BatchVerdict};
+    use crate::io::{read_patterns, write_bench};
+    use crate::network::generators::{adder, multiplier};
+    use crate::sim::simulate;
+    use crate::Network;
+
+    fn write(path: &std::path::Path, aig: &Network) {
+        let mut f = std::fs::File::create(path).unwrap();
+        write_bench(&mut f, aig);
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let dir = std::path::Path::new("dir");
+        let text = "original,optimized,cycles,output_map,time_limit\n\
+            # comment\n\
+            a.bench, b.bench\n\
+            c.bench,d.bench,3,,0.5\n";
+        let entries = BatchEntry::parse_manifest(text, dir).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].optimized, dir.join("b.bench"));
+        assert_eq!(entries[0].nb_cycles, 1);
+        assert_eq!(entries[1].nb_cycles, 3);
+        assert_eq!(entries[1].output_map, None);
+        assert_eq!(entries[1].time_limit, Some(Duration::from_millis(500)));
+        assert!(BatchEntry::parse_manifest("a.bench\n", dir).is_err());
+        assert!(BatchEntry::parse_manifest("a.bench,b.bench,x\n", dir).is_err());
+
+        assert_eq!(parse_output_map("0 1\n1 0\n", 2), Ok(vec![1, 0]));
+        assert!(parse_output_map("0 1\n0 0\n", 2).is_err());
+        assert!(parse_output_map("0 1\n", 2).is_err());
+    }
+
+    #[test]
+    fn test_batch() {
+        let dir = std::env::temp_dir().join(format!("quaigh_batch_{}", std::process::id()));
+        let cex_dir = dir.join("cex");
+        std::fs::create_dir_all(&cex_dir).unwrap();
+
+        let a = adder::ripple_carry(4);
+        let mut a_opt = a.clone();
+        a_opt.make_canonical();
+        write(&dir.join("a.bench"), &a);
+        write(&dir.join("a_opt.bench"), &a_opt);
+        // Same design with two outputs swapped
+        let mut wrong = a_opt.clone();
+        wrong.set_output(0, a_opt.output(1));
+        wrong.set_output(1, a_opt.output(0));
+        write(&dir.join("a_wrong.bench"), &wrong);
+        std::fs::write(dir.join("swap.map"), "0 1\n1 0\n2 2\n3 3\n4 4\n").unwrap();
+        let m = multiplier::array(8);
+        let mut m_opt = m.clone();
+        m_opt.make_canonical();
+        write(&dir.join("m.bench"), &m);
+        write(&dir.join("m_opt.bench"), &m_opt);
+
+        let manifest = "original,optimized,cycles,output_map,time_limit\n\
+            a.bench,a_opt.bench\n\
+            a.bench,a_wrong.bench\n\
+            m.bench,m_opt.bench,1,,0\n\
+            a.bench,a_wrong.bench,1,swap.map\n";
+        let entries = BatchEntry::parse_manifest(manifest, &dir).unwrap();
+        let mut runner = BatchEquivRunner::new();
+        runner.nb_jobs = 2;
+        runner.counterexample_dir = Some(cex_dir.clone());
+        let mut nb_streamed = 0;
+        let results = runner.run(&entries, |_| nb_streamed += 1);
+        assert_eq!(nb_streamed, 4);
+        assert_eq!(results[0].verdict, BatchVerdict::Equivalent);
+        assert_eq!(results[2].verdict, BatchVerdict::Unknown);
+        assert_eq!(results[3].verdict, BatchVerdict::Equivalent);
+        assert_eq!(results.iter().filter(|r| r.verdict.is_pass()).count(), 2);
+
+        // The counterexample shows a difference between the designs
+        let BatchVerdict::Different(pattern) = &results[1].verdict else {
+            panic!("Designs should be different");
+        };
+        let path = results[1].counterexample.as_ref().unwrap();
+        let read = read_patterns(std::fs::File::open(path).unwrap()).unwrap();
+        assert_eq!(&read[0], pattern);
+        assert_ne!(simulate(&a, pattern), simulate(&wrong, pattern));
+
+        // Designs too large once unrolled are reported as errors
+        runner.max_nodes = 10;
+        let results = runner.run(&entries[2..3], |_| ());
+        let BatchVerdict::Error(msg) = &results[0].verdict else {
+            panic!("Unrolling should exceed the limit");
+        };
+        assert!(msg.contains("unroll would create"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
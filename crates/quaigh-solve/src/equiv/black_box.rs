@@ -0,0 +1,188 @@
+//! Equivalence checking of designs with black boxes
+//!
+//! Black boxes are cut out of the network by the readers: their outputs become inputs of the
+//! network, and their inputs become outputs. Identical black boxes, with the same path and model,
+//! are treated as equal: their outputs are shared between both designs, and their inputs must be
+//! driven by equivalent logic.
+
+use std::collections::HashMap;
+
+use crate::network::BlackBox;
+use crate::{Network, Signal};
+
+/// Pins of the black boxes, by path and port, with their index in the network
+fn black_box_pins(
+    boxes: &[BlackBox],
+    inputs: bool,
+) -> Result<HashMap<(&str, &str), usize>, String> {
+    let mut ret = HashMap::new();
+    for b in boxes {
+        let pins = if inputs { &b.outputs } else { &b.inputs };
+        for (port, index) in pins {
+            if ret
+                .insert((b.path.as_str(), port.as_str()), *index)
+                .is_some()
+            {
+                return Err(format!("Black box {} is defined twice", b.path));
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Order of the inputs or outputs of `b` matching those of `a`
+///
+/// Black box pins are matched by path and port, and the other pins are kept in order.
+fn match_pins(
+    nb_a: usize,
+    nb_b: usize,
+    pins_a: &HashMap<(&str, &str), usize>,
+    pins_b: &HashMap<(&str, &str), usize>,
+) -> Vec<usize> {
+    let a_to_key: HashMap<usize, (&str, &str)> = pins_a.iter().map(|(k, v)| (*v, *k)).collect();
+    let b_pins: Vec<usize> = pins_b.values().copied().collect();
+    let mut others = (0..nb_b).filter(|i| !b_pins.contains(i));
+    (0..nb_a)
+        .map(|i| match a_to_key.get(&i) {
+            Some(k) => pins_b[k],
+            None => others.next().unwrap(),
+        })
+        .collect()
+}
+
+/// Reorder the inputs and outputs of `b` so that its black box pins match those of `a`
+///
+/// Both designs must have the same black boxes, with the same paths, models and ports, and the
+/// same number of other inputs and outputs, which are kept in order. The returned network can be
+/// compared to `a` with any equivalence checking function.
+pub fn align_black_boxes(
+    a: &Network,
+    a_boxes: &[BlackBox],
+    b: &Network,
+    b_boxes: &[BlackBox],
+) -> Result<Network, String> {
+    let models_a: HashMap<&str, &BlackBox> = a_boxes.iter().map(|x| (x.path.as_str(), x)).collect();
+    for x in b_boxes {
+        match models_a.get(x.path.as_str()) {
+            None => return Err(format!("Black box {} is only in the second design", x.path)),
+            Some(y) if y.model != x.model => {
+                return Err(format!(
+                    "Black box {} is an instance of {} and {}",
+                    x.path, y.model, x.model
+                ))
+            }
+            _ => (),
+        }
+    }
+    if let Some(x) = a_boxes
+        .iter()
+        .find(|x| !b_boxes.iter().any(|y| y.path == x.path))
+    {
+        return Err(format!("Black box {} is only in the first design", x.path));
+    }
+    if a.nb_inputs() != b.nb_inputs() || a.nb_outputs() != b.nb_outputs() {
+        return Err("Designs have different interfaces".to_owned());
+    }
+    let inputs_a = black_box_pins(a_boxes, true)?;
+    let inputs_b = black_box_pins(b_boxes, true)?;
+    let outputs_a = black_box_pins(a_boxes, false)?;
+    let outputs_b = black_box_pins(b_boxes, false)?;
+    for (k, pins) in [(&inputs_a, &inputs_b), (&outputs_a, &outputs_b)] {
+        if let Some((path, port)) = k.keys().find(|key| !pins.contains_key(key)) {
+            return Err(format!(
+                "Black box {} has no port {} in both designs",
+                path, port
+            ));
+        }
+        if k.len() != pins.len() {
+            return Err("Black boxes have different ports".to_owned());
+        }
+    }
+
+    let input_order = match_pins(a.nb_inputs(), b.nb_inputs(), &inputs_a, &inputs_b);
+    let output_order = match_pins(a.nb_outputs(), b.nb_outputs(), &outputs_a, &outputs_b);
+    let mut input_map = vec![Signal::zero(); b.nb_inputs()];
+    let mut ret = Network::new();
+    ret.add_inputs(b.nb_inputs());
+    for (new, old) in input_order.iter().enumerate() {
+        input_map[*old] = ret.input(new);
+    }
+    let t = |s: &Signal| {
+        if s.is_input() {
+            input_map[s.input() as usize] ^ s.is_inverted()
+        } else {
+            *s
+        }
+    };
+    for i in 0..b.nb_nodes() {
+        ret.add(b.gate(i).remap(t));
+    }
+    for o in output_order {
+        ret.add_output(t(&b.output(o)));
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::align_black_boxes;
+    use crate::equiv::check_equivalence_comb;
+    use crate::io::read_blif_with_hierarchy;
+
+    const DESIGN: &str = ".model top
+.inputs a b
+.outputs y
+.subckt mult x=a z=b p=m
+.subckt mult x=b z=a p=n
+.names m n y
+11 1
+.end
+
+.model mult
+.inputs x z
+.outputs p
+.blackbox
+.end
+";
+
+    #[test]
+    fn test_black_box() {
+        let (a, _, ha) = read_blif_with_hierarchy(DESIGN.as_bytes()).unwrap();
+        // Two black box outputs as inputs, and two black box inputs as outputs for each
+        assert_eq!(a.nb_inputs(), 4);
+        assert_eq!(a.nb_outputs(), 5);
+        assert_eq!(ha.black_boxes().len(), 2);
+        assert_eq!(ha.black_boxes()[1].path, "mult_1");
+        assert_eq!(ha.black_boxes()[1].outputs, vec![("p".to_owned(), 3)]);
+        assert_eq!(
+            ha.black_boxes()[0].inputs,
+            vec![("x".to_owned(), 1), ("z".to_owned(), 2)]
+        );
+
+        // Same logic, with the black boxes instantiated in a different order
+        let swapped = DESIGN
+            .replace(".subckt mult x=a z=b p=m\n", "")
+            .replace("p=n\n", "p=n\n.subckt mult x=a z=b p=m\n")
+            .replace(".names m n y\n11 1", ".names n m y\n11 1");
+        let (b, _, hb) = read_blif_with_hierarchy(swapped.as_bytes()).unwrap();
+        assert_eq!(hb.black_boxes()[0].path, "mult_0");
+        let aligned = align_black_boxes(&a, ha.black_boxes(), &b, hb.black_boxes()).unwrap();
+        assert!(check_equivalence_comb(&a, &aligned, false).is_err());
+
+        // Same logic, with the same instance names
+        let same = DESIGN.replace(".names m n y\n11 1", ".names n m y\n11 1");
+        let (b, _, hb) = read_blif_with_hierarchy(same.as_bytes()).unwrap();
+        let aligned = align_black_boxes(&a, ha.black_boxes(), &b, hb.black_boxes()).unwrap();
+        assert!(check_equivalence_comb(&a, &aligned, false).is_ok());
+
+        // Different connections of a black box
+        let different = DESIGN.replace("x=b z=a", "x=a z=a");
+        let (b, _, hb) = read_blif_with_hierarchy(different.as_bytes()).unwrap();
+        let aligned = align_black_boxes(&a, ha.black_boxes(), &b, hb.black_boxes()).unwrap();
+        assert!(check_equivalence_comb(&a, &aligned, false).is_err());
+
+        let missing = DESIGN.replace(".subckt mult x=b z=a p=n\n", ".names n\n");
+        let (b, _, hb) = read_blif_with_hierarchy(missing.as_bytes()).unwrap();
+        assert!(align_black_boxes(&a, ha.black_boxes(), &b, hb.black_boxes()).is_err());
+    }
+}
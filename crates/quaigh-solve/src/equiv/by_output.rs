@@ -0,0 +1,211 @@
+//! Equivalence checking output by output
+//!
+//! A single miter reports one counterexample, which hides the other failing outputs, and its Sat
+//! problem contains the cones of all outputs at once. Here each output is checked separately with
+//! an [`IncrementalSolver`], so that the logic shared by the cones is only encoded once per
+//! solver. Random simulation finds most mismatches first, and the remaining outputs are split
+//! between several threads, each with its own solver.
+
+use rand::Rng;
+
+use super::{extend_aig, translated_assumptions, unroll, IncrementalSolver, NB_RANDOM_WORDS};
+use crate::network::OutputKind;
+use crate::sim::simulate_comb_multi;
+use crate::util::rng::SeededRng;
+use crate::{Network, Signal};
+
+/// Output where two networks differ, with a pattern exhibiting the difference
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputMismatch {
+    /// Index of the output
+    pub output: usize,
+    /// First cycle where the output differs, 0 for combinatorial networks
+    pub cycle: usize,
+    /// Values of the inputs at each cycle, up to the cycle where the output differs
+    pub pattern: Vec<Vec<bool>>,
+}
+
+/// Create a network with one output per output of two combinatorial networks, representing
+/// whether they differ
+///
+/// This is the same as [`difference`](super::difference), without merging the outputs. The
+/// outputs corresponding to the assumptions of `a` are constant 0.
+pub fn output_differences(a: &Network, b: &Network) -> Network {
+    assert!(a.is_comb() && b.is_comb());
+    assert_eq!(a.nb_inputs(), b.nb_inputs());
+    assert_eq!(a.nb_outputs(), b.nb_outputs());
+
+    let mut eq = Network::new();
+    eq.add_inputs(a.nb_inputs());
+    let ta = extend_aig(&mut eq, a);
+    let tb = extend_aig(&mut eq, b);
+    let hold = translated_assumptions(&mut eq, a, &ta);
+    for i in 0..a.nb_outputs() {
+        if a.output_kind(i) == OutputKind::Assumption {
+            eq.add_output(Signal::zero());
+            continue;
+        }
+        let o = eq.xor(ta[&a.output(i)], tb[&b.output(i)]);
+        let o = eq.and(o, hold);
+        eq.add_output(o);
+    }
+    eq
+}
+
+/// Perform equivalence checking on two combinatorial networks, output by output
+///
+/// Returns the outputs that differ, each with a counterexample, in order. The networks are
+/// equivalent if there is none.
+pub fn check_equivalence_by_output(
+    a: &Network,
+    b: &Network,
+    nb_jobs: usize,
+) -> Vec<OutputMismatch> {
+    check_equivalence_bounded_by_output(a, b, 1, nb_jobs)
+}
+
+/// Perform bounded equivalence checking on two sequential networks, output by output
+///
+/// Returns the outputs that differ in the first `nb_steps` cycles, each with the first cycle where
+/// it differs and a counterexample, in order.
+pub fn check_equivalence_bounded_by_output(
+    a: &Network,
+    b: &Network,
+    nb_steps: usize,
+    nb_jobs: usize,
+) -> Vec<OutputMismatch> {
+    assert_eq!(a.nb_inputs(), b.nb_inputs());
+    assert_eq!(a.nb_outputs(), b.nb_outputs());
+    let nb_inputs = a.nb_inputs();
+    let nb_outputs = a.nb_outputs();
+    let miter = output_differences(&unroll(a, nb_steps), &unroll(b, nb_steps));
+
+    // Outputs of the miter are ordered by cycle, then by output
+    let mismatch = |output: usize, cycle: usize, values: Vec<bool>| OutputMismatch {
+        output,
+        cycle,
+        pattern: (0..=cycle)
+            .map(|c| values[c * nb_inputs..(c + 1) * nb_inputs].to_vec())
+            .collect(),
+    };
+
+    // Random simulation first
+    let mut found: Vec<Option<OutputMismatch>> = vec![None; nb_outputs];
+    let mut rng = SeededRng::new(1);
+    for _ in 0..NB_RANDOM_WORDS {
+        let inputs: Vec<u64> = (0..miter.nb_inputs()).map(|_| rng.gen()).collect();
+        let diffs = simulate_comb_multi(&miter, &inputs);
+        for (o, f) in found.iter_mut().enumerate() {
+            let first = (0..nb_steps).find(|c| diffs[c * nb_outputs + o] != 0);
+            let Some(cycle) = first else {
+                continue;
+            };
+            if f.as_ref().is_some_and(|m| m.cycle <= cycle) {
+                continue;
+            }
+            let lane = diffs[cycle * nb_outputs + o].trailing_zeros();
+            let values = inputs.iter().map(|v| (v >> lane) & 1 != 0).collect();
+            *f = Some(mismatch(o, cycle, values));
+        }
+    }
+
+    // Then the Sat solver, for the cycles before the first difference found
+    let remaining: Vec<(usize, usize)> = found
+        .iter()
+        .enumerate()
+        .map(|(o, f)| (o, f.as_ref().map(|m| m.cycle).unwrap_or(nb_steps)))
+        .filter(|(_, end)| *end > 0)
+        .collect();
+    let check = |outputs: &[(usize, usize)]| -> Vec<OutputMismatch> {
+        let mut solver = IncrementalSolver::new(&miter);
+        let mut ret = Vec::new();
+        for (o, end) in outputs {
+            for cycle in 0..*end {
+                if solver.solve(&[miter.output(cycle * nb_outputs + o)]) {
+                    ret.push(mismatch(*o, cycle, solver.input_values()));
+                    break;
+                }
+            }
+        }
+        ret
+    };
+    let nb_jobs = nb_jobs.clamp(1, remaining.len().max(1));
+    let solved: Vec<OutputMismatch> = if nb_jobs == 1 {
+        check(&remaining)
+    } else {
+        let chunks: Vec<Vec<(usize, usize)>> = (0..nb_jobs)
+            .map(|j| remaining.iter().skip(j).step_by(nb_jobs).copied().collect())
+            .collect();
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = chunks.iter().map(|c| scope.spawn(|| check(c))).collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        })
+    };
+    for m in solved {
+        let o = m.output;
+        found[o] = Some(m);
+    }
+    found.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_equivalence_bounded_by_output, check_equivalence_by_output};
+    use crate::network::generators::adder;
+    use crate::sim::{simulate, simulate_comb};
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_comb() {
+        let a = adder::ripple_carry(4);
+        assert!(check_equivalence_by_output(&a, &a, 2).is_empty());
+
+        // Break two outputs, one of them on a single pattern
+        let mut b = a.clone();
+        b.set_output(1, !a.output(1));
+        let v: Vec<Signal> = (0..b.nb_inputs()).map(|i| b.input(i)).collect();
+        let rare = b.add(Gate::andn(&v));
+        let o = b.xor(a.output(3), rare);
+        b.set_output(3, o);
+
+        for nb_jobs in [1, 3] {
+            let mismatches = check_equivalence_by_output(&a, &b, nb_jobs);
+            let outputs: Vec<usize> = mismatches.iter().map(|m| m.output).collect();
+            assert_eq!(outputs, vec![1, 3]);
+            for m in &mismatches {
+                assert_eq!(m.cycle, 0);
+                assert_eq!(m.pattern.len(), 1);
+                let va = simulate_comb(&a, &m.pattern[0]);
+                let vb = simulate_comb(&b, &m.pattern[0]);
+                assert_ne!(va[m.output], vb[m.output]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bounded() {
+        // A shift register, whose second output is replaced by the first one
+        let mut a = Network::new();
+        let i = a.add_input();
+        let x = a.dff(i, Signal::one(), Signal::zero());
+        let y = a.dff(x, Signal::one(), Signal::zero());
+        a.add_output(x);
+        a.add_output(y);
+        let mut b = a.clone();
+        b.set_output(1, x);
+
+        assert!(check_equivalence_bounded_by_output(&a, &b, 1, 1).is_empty());
+        let mismatches = check_equivalence_bounded_by_output(&a, &b, 3, 1);
+        assert_eq!(mismatches.len(), 1);
+        let m = &mismatches[0];
+        assert_eq!((m.output, m.cycle), (1, 1));
+        assert_eq!(m.pattern.len(), 2);
+        assert_ne!(
+            simulate(&a, &m.pattern).last().unwrap()[1],
+            simulate(&b, &m.pattern).last().unwrap()[1]
+        );
+    }
+}
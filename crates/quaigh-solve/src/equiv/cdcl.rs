@@ -0,0 +1,572 @@
+//! Sat solver written in Rust, used when no native solver is available
+
+use rustsat::types::Lit;
+
+use super::backend::SatSolver;
+
+/// No reason for an assignment: decisions and units
+const NO_REASON: u32 = u32::MAX;
+
+/// Conflicts before the first restart, multiplied by the Luby sequence
+const RESTART_BASE: u64 = 100;
+
+/// A conflict-driven clause learning Sat solver, with watched literals, VSIDS and restarts
+///
+/// It has no native dependency, but is much slower than Kissat or CaDiCaL on hard problems.
+/// The solver is incremental: clauses may be added between calls, and the learned clauses,
+/// variable activities and saved phases are kept from one call to the next. Assumptions are
+/// decided first, one decision level each.
+///
+/// Literals are encoded as `2 * var + negated`.
+#[derive(Default)]
+pub struct BuiltinSolver {
+    search: Search,
+}
+
+impl BuiltinSolver {
+    /// Number of conflicts since the solver was created
+    pub fn nb_conflicts(&self) -> u64 {
+        self.search.nb_conflicts
+    }
+
+    /// Number of restarts since the solver was created
+    pub fn nb_restarts(&self) -> u64 {
+        self.search.nb_restarts
+    }
+}
+
+impl SatSolver for BuiltinSolver {
+    fn add_clause(&mut self, clause: &[Lit]) {
+        let c: Vec<u32> = clause.iter().map(|l| encode(*l)).collect();
+        self.search.add_clause(c);
+    }
+
+    fn solve_with_assumptions(&mut self, assumptions: &[Lit]) -> Option<Vec<bool>> {
+        let assumptions: Vec<u32> = assumptions.iter().map(|l| encode(*l)).collect();
+        self.search.run(&assumptions)
+    }
+}
+
+/// Encode a literal as `2 * var + negated`
+fn encode(l: Lit) -> u32 {
+    2 * l.vidx() as u32 + l.is_neg() as u32
+}
+
+/// Value of a literal: 1 if true, -1 if false, 0 if unassigned
+fn lit_value(assigns: &[i8], l: u32) -> i8 {
+    let v = assigns[l as usize / 2];
+    if l & 1 != 0 {
+        -v
+    } else {
+        v
+    }
+}
+
+/// Element of the Luby sequence 1, 1, 2, 1, 1, 2, 4, ...
+fn luby(mut i: u64) -> u64 {
+    let mut size = 1;
+    let mut seq = 0;
+    while size < i + 1 {
+        seq += 1;
+        size = 2 * size + 1;
+    }
+    while size - 1 != i {
+        size = (size - 1) / 2;
+        seq -= 1;
+        i %= size;
+    }
+    1 << seq
+}
+
+/// Binary heap of variables, ordered by decreasing activity
+#[derive(Default)]
+struct VarHeap {
+    heap: Vec<u32>,
+    /// Position of each variable in the heap, or None
+    pos: Vec<Option<usize>>,
+}
+
+impl VarHeap {
+    fn sift_up(&mut self, mut i: usize, activity: &[f64]) {
+        let v = self.heap[i];
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            let p = self.heap[parent];
+            if activity[p as usize] >= activity[v as usize] {
+                break;
+            }
+            self.heap[i] = p;
+            self.pos[p as usize] = Some(i);
+            i = parent;
+        }
+        self.heap[i] = v;
+        self.pos[v as usize] = Some(i);
+    }
+
+    fn sift_down(&mut self, mut i: usize, activity: &[f64]) {
+        let v = self.heap[i];
+        loop {
+            let mut child = 2 * i + 1;
+            if child >= self.heap.len() {
+                break;
+            }
+            if child + 1 < self.heap.len()
+                && activity[self.heap[child + 1] as usize] > activity[self.heap[child] as usize]
+            {
+                child += 1;
+            }
+            let c = self.heap[child];
+            if activity[c as usize] <= activity[v as usize] {
+                break;
+            }
+            self.heap[i] = c;
+            self.pos[c as usize] = Some(i);
+            i = child;
+        }
+        self.heap[i] = v;
+        self.pos[v as usize] = Some(i);
+    }
+
+    fn insert(&mut self, v: u32, activity: &[f64]) {
+        if self.pos.len() <= v as usize {
+            self.pos.resize(v as usize + 1, None);
+        }
+        if self.pos[v as usize].is_none() {
+            self.heap.push(v);
+            self.sift_up(self.heap.len() - 1, activity);
+        }
+    }
+
+    fn pop(&mut self, activity: &[f64]) -> Option<u32> {
+        let v = *self.heap.first()?;
+        let last = self.heap.pop().unwrap();
+        self.pos[v as usize] = None;
+        if !self.heap.is_empty() {
+            self.heap[0] = last;
+            self.sift_down(0, activity);
+        }
+        Some(v)
+    }
+
+    fn increased(&mut self, v: u32, activity: &[f64]) {
+        if let Some(i) = self.pos[v as usize] {
+            self.sift_up(i, activity);
+        }
+    }
+}
+
+/// State of the search, kept between calls
+struct Search {
+    /// Original and learned clauses; the literal implied by a clause is in first position
+    db: Vec<Vec<u32>>,
+    /// Clauses watching each literal, in their first two positions
+    watches: Vec<Vec<u32>>,
+    assigns: Vec<i8>,
+    level: Vec<u32>,
+    reason: Vec<u32>,
+    /// Saved polarity of each variable, used for the next decision
+    polarity: Vec<bool>,
+    activity: Vec<f64>,
+    var_inc: f64,
+    heap: VarHeap,
+    trail: Vec<u32>,
+    trail_lim: Vec<usize>,
+    qhead: usize,
+    seen: Vec<bool>,
+    /// False once the clauses are unsatisfiable without any assumption
+    ok: bool,
+    nb_conflicts: u64,
+    nb_restarts: u64,
+}
+
+impl Default for Search {
+    fn default() -> Search {
+        Search {
+            db: Vec::new(),
+            watches: Vec::new(),
+            assigns: Vec::new(),
+            level: Vec::new(),
+            reason: Vec::new(),
+            polarity: Vec::new(),
+            activity: Vec::new(),
+            var_inc: 1.0,
+            heap: VarHeap::default(),
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            qhead: 0,
+            seen: Vec::new(),
+            ok: true,
+            nb_conflicts: 0,
+            nb_restarts: 0,
+        }
+    }
+}
+
+impl Search {
+    fn nb_vars(&self) -> usize {
+        self.assigns.len()
+    }
+
+    /// Create the variables up to the one of this literal
+    fn reserve(&mut self, l: u32) {
+        let nb_vars = l as usize / 2 + 1;
+        for v in self.nb_vars()..nb_vars {
+            self.watches.push(Vec::new());
+            self.watches.push(Vec::new());
+            self.assigns.push(0);
+            self.level.push(0);
+            self.reason.push(NO_REASON);
+            self.polarity.push(false);
+            self.activity.push(0.0);
+            self.seen.push(false);
+            self.heap.insert(v as u32, &self.activity);
+        }
+    }
+
+    fn decision_level(&self) -> u32 {
+        self.trail_lim.len() as u32
+    }
+
+    fn enqueue(&mut self, l: u32, reason: u32) {
+        let v = l as usize / 2;
+        self.assigns[v] = if l & 1 != 0 { -1 } else { 1 };
+        self.level[v] = self.decision_level();
+        self.reason[v] = reason;
+        self.trail.push(l);
+    }
+
+    /// Open a new decision level with this literal
+    fn decide(&mut self, l: u32) {
+        self.trail_lim.push(self.trail.len());
+        self.enqueue(l, NO_REASON);
+    }
+
+    /// Add a clause with at least two literals, watching the first two
+    fn attach(&mut self, c: Vec<u32>) -> u32 {
+        let ci = self.db.len() as u32;
+        self.watches[c[0] as usize].push(ci);
+        self.watches[c[1] as usize].push(ci);
+        self.db.push(c);
+        ci
+    }
+
+    /// Add an original clause, simplified with the assignments of the first level
+    fn add_clause(&mut self, mut c: Vec<u32>) {
+        self.cancel_until(0);
+        for l in &c {
+            self.reserve(*l);
+        }
+        c.sort();
+        c.dedup();
+        if c.windows(2).any(|w| w[0] ^ 1 == w[1]) || c.iter().any(|l| self.value(*l) == 1) {
+            // Tautology or already satisfied
+            return;
+        }
+        c.retain(|l| self.value(*l) == 0);
+        match c.len() {
+            0 => self.ok = false,
+            1 => self.enqueue(c[0], NO_REASON),
+            _ => {
+                self.attach(c);
+            }
+        }
+    }
+
+    fn value(&self, l: u32) -> i8 {
+        lit_value(&self.assigns, l)
+    }
+
+    /// Propagate the assignments in the queue; returns a conflicting clause if any
+    fn propagate(&mut self) -> Option<u32> {
+        while self.qhead < self.trail.len() {
+            let false_lit = self.trail[self.qhead] ^ 1;
+            self.qhead += 1;
+            let mut ws = std::mem::take(&mut self.watches[false_lit as usize]);
+            let mut i = 0;
+            let mut j = 0;
+            let mut conflict = None;
+            while i < ws.len() {
+                let ci = ws[i];
+                i += 1;
+                let c = &mut self.db[ci as usize];
+                if c[0] == false_lit {
+                    c.swap(0, 1);
+                }
+                if lit_value(&self.assigns, c[0]) == 1 {
+                    ws[j] = ci;
+                    j += 1;
+                    continue;
+                }
+                if let Some(k) = (2..c.len()).find(|k| lit_value(&self.assigns, c[*k]) != -1) {
+                    c.swap(1, k);
+                    self.watches[c[1] as usize].push(ci);
+                    continue;
+                }
+                ws[j] = ci;
+                j += 1;
+                let first = c[0];
+                if lit_value(&self.assigns, first) == -1 {
+                    conflict = Some(ci);
+                    while i < ws.len() {
+                        ws[j] = ws[i];
+                        i += 1;
+                        j += 1;
+                    }
+                } else {
+                    self.enqueue(first, ci);
+                }
+            }
+            ws.truncate(j);
+            self.watches[false_lit as usize] = ws;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, v: usize) {
+        self.activity[v] += self.var_inc;
+        if self.activity[v] > 1e100 {
+            for a in &mut self.activity {
+                *a *= 1e-100;
+            }
+            self.var_inc *= 1e-100;
+        }
+        self.heap.increased(v as u32, &self.activity);
+    }
+
+    /// Learn a clause from a conflict, with the first unique implication point
+    ///
+    /// Returns the clause, with the asserting literal first, and the level to backtrack to.
+    fn analyze(&mut self, mut confl: u32) -> (Vec<u32>, u32) {
+        let mut learnt = vec![0];
+        let mut nb_pending = 0;
+        let mut p = None;
+        let mut index = self.trail.len();
+        loop {
+            // The first literal of a reason clause is the one it implied
+            let skip = usize::from(p.is_some());
+            for k in skip..self.db[confl as usize].len() {
+                let q = self.db[confl as usize][k];
+                let v = q as usize / 2;
+                if !self.seen[v] && self.level[v] > 0 {
+                    self.seen[v] = true;
+                    self.bump(v);
+                    if self.level[v] >= self.decision_level() {
+                        nb_pending += 1;
+                    } else {
+                        learnt.push(q);
+                    }
+                }
+            }
+            loop {
+                index -= 1;
+                if self.seen[self.trail[index] as usize / 2] {
+                    break;
+                }
+            }
+            let l = self.trail[index];
+            let v = l as usize / 2;
+            self.seen[v] = false;
+            nb_pending -= 1;
+            p = Some(l);
+            if nb_pending == 0 {
+                break;
+            }
+            confl = self.reason[v];
+        }
+        learnt[0] = p.unwrap() ^ 1;
+        for l in &learnt[1..] {
+            self.seen[*l as usize / 2] = false;
+        }
+
+        let mut bt_level = 0;
+        if learnt.len() > 1 {
+            let max_i = (1..learnt.len())
+                .max_by_key(|i| self.level[learnt[*i] as usize / 2])
+                .unwrap();
+            learnt.swap(1, max_i);
+            bt_level = self.level[learnt[1] as usize / 2];
+        }
+        (learnt, bt_level)
+    }
+
+    fn cancel_until(&mut self, level: u32) {
+        if self.decision_level() <= level {
+            return;
+        }
+        let start = self.trail_lim[level as usize];
+        for i in (start..self.trail.len()).rev() {
+            let l = self.trail[i];
+            let v = l as usize / 2;
+            self.polarity[v] = l & 1 == 0;
+            self.assigns[v] = 0;
+            self.reason[v] = NO_REASON;
+            self.heap.insert(v as u32, &self.activity);
+        }
+        self.trail.truncate(start);
+        self.trail_lim.truncate(level as usize);
+        self.qhead = self.trail.len();
+    }
+
+    /// Run the search with the given assumptions
+    ///
+    /// Returns the value of each variable if the problem is satisfiable. The solver is back to
+    /// the first level afterwards, so that clauses can be added.
+    fn run(&mut self, assumptions: &[u32]) -> Option<Vec<bool>> {
+        for l in assumptions {
+            self.reserve(*l);
+        }
+        self.cancel_until(0);
+        let ret = self.search(assumptions);
+        self.cancel_until(0);
+        ret
+    }
+
+    fn search(&mut self, assumptions: &[u32]) -> Option<Vec<bool>> {
+        if !self.ok {
+            return None;
+        }
+        let mut nb_restarts = 0;
+        let mut nb_conflicts = 0;
+        loop {
+            if let Some(confl) = self.propagate() {
+                nb_conflicts += 1;
+                self.nb_conflicts += 1;
+                if self.decision_level() == 0 {
+                    self.ok = false;
+                    return None;
+                }
+                let (learnt, bt_level) = self.analyze(confl);
+                self.cancel_until(bt_level);
+                if learnt.len() == 1 {
+                    self.enqueue(learnt[0], NO_REASON);
+                } else {
+                    let l = learnt[0];
+                    let ci = self.attach(learnt);
+                    self.enqueue(l, ci);
+                }
+                self.var_inc /= 0.95;
+            } else if nb_conflicts >= RESTART_BASE * luby(nb_restarts) {
+                nb_restarts += 1;
+                self.nb_restarts += 1;
+                nb_conflicts = 0;
+                self.cancel_until(0);
+            } else if let Some(&l) = assumptions.get(self.decision_level() as usize) {
+                match self.value(l) {
+                    // Already implied: open an empty level to keep one level per assumption
+                    1 => self.trail_lim.push(self.trail.len()),
+                    -1 => return None,
+                    _ => self.decide(l),
+                }
+            } else {
+                let Some(v) = std::iter::from_fn(|| self.heap.pop(&self.activity))
+                    .find(|v| self.assigns[*v as usize] == 0)
+                else {
+                    return Some(self.assigns.iter().map(|a| *a == 1).collect());
+                };
+                self.decide(2 * v + !self.polarity[v as usize] as u32);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustsat::types::Lit;
+
+    use super::{luby, BuiltinSolver, Search};
+    use crate::equiv::SatSolver;
+
+    #[test]
+    fn test_luby() {
+        let seq: Vec<u64> = (0..15).map(luby).collect();
+        assert_eq!(seq, [1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn test_analyze() {
+        // Variables a, b, c, d, e are 0 to 4, and their negation is the next odd number
+        let mut search = Search::default();
+        for c in [vec![1, 2], vec![5, 6], vec![3, 7, 8], vec![7, 9]] {
+            search.add_clause(c);
+        }
+        search.decide(0);
+        assert_eq!(search.propagate(), None);
+        assert_eq!(search.trail, [0, 2]);
+        search.decide(4);
+        // c implies d, then b and d imply e, which conflicts with d
+        let confl = search.propagate().unwrap();
+        assert_eq!(search.trail, [0, 2, 4, 6, 8]);
+        let (learnt, bt_level) = search.analyze(confl);
+        // d is the first unique implication point, and b comes from the first level
+        assert_eq!(learnt, [7, 3]);
+        assert_eq!(bt_level, 1);
+        assert!(search.seen.iter().all(|s| !s));
+
+        search.cancel_until(bt_level);
+        assert_eq!(search.trail, [0, 2]);
+        let ci = search.attach(learnt);
+        search.enqueue(7, ci);
+        assert_eq!(search.propagate(), None);
+        // Not d implies not c
+        assert_eq!(search.trail, [0, 2, 7, 5]);
+    }
+
+    /// Clauses stating that n + 1 pigeons fit in n holes, enabled by a selector literal
+    fn add_pigeonhole(solver: &mut BuiltinSolver, n: u32, selector: Lit) {
+        let var = |p: u32, h: u32| Lit::new(1 + n * p + h, false);
+        for p in 0..n + 1 {
+            let mut c: Vec<Lit> = (0..n).map(|h| var(p, h)).collect();
+            c.push(!selector);
+            solver.add_clause(&c);
+        }
+        for h in 0..n {
+            for p1 in 0..n + 1 {
+                for p2 in p1 + 1..n + 1 {
+                    solver.add_clause(&[!var(p1, h), !var(p2, h), !selector]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_restarts() {
+        let mut solver = BuiltinSolver::default();
+        let selector = Lit::new(0, false);
+        add_pigeonhole(&mut solver, 6, selector);
+        assert!(solver.solve_with_assumptions(&[selector]).is_none());
+        assert!(solver.nb_restarts() > 0);
+        // The learned clauses are kept through restarts, and now refute the selector directly
+        let nb_conflicts = solver.nb_conflicts();
+        assert!(solver.solve_with_assumptions(&[selector]).is_none());
+        assert_eq!(solver.nb_conflicts(), nb_conflicts);
+        // Without the assumption, the problem is satisfiable
+        let sol = solver.solve().unwrap();
+        assert!(!sol[0]);
+    }
+
+    #[test]
+    fn test_assumptions() {
+        let a = Lit::new(0, false);
+        let b = Lit::new(1, false);
+        let c = Lit::new(2, false);
+        let mut solver = BuiltinSolver::default();
+        solver.add_clause(&[!a, b]);
+        solver.add_clause(&[!b, c]);
+        let sol = solver.solve_with_assumptions(&[a]).unwrap();
+        assert_eq!(sol, [true, true, true]);
+        assert!(solver.solve_with_assumptions(&[a, !c]).is_none());
+        // Assumptions only hold for a single call
+        let sol = solver.solve_with_assumptions(&[!c]).unwrap();
+        assert_eq!(sol, [false, false, false]);
+        assert!(solver.solve_with_assumptions(&[c, !c]).is_none());
+        solver.add_clause(&[!c]);
+        assert!(solver.solve_with_assumptions(&[a]).is_none());
+        assert!(solver.solve().is_some());
+        solver.add_clause(&[a]);
+        assert!(solver.solve().is_none());
+        assert!(solver.solve_with_assumptions(&[!a]).is_none());
+    }
+}
@@ -0,0 +1,377 @@
+//! Incremental Sat solving on a network
+//!
+//! Many closely-related queries are made on the same network during equivalence checking, test
+//! pattern generation or Sat sweeping. The solver encodes the cones of the signals on demand,
+//! so that the logic is only translated once, and each query is expressed as a set of
+//! assumptions on signals.
+//!
+//! A single Sat solver is kept for all queries, and the assumptions are passed to it directly:
+//! with the builtin solver and CaDiCaL, the clauses learned by a query are reused by the
+//! following ones. When a query fails, the negation of its assumptions is implied by the
+//! network, and is added as a clause as well: for example, once two signals are proven
+//! equivalent, the equivalence is available to all later queries, whatever the backend.
+//!
+//! The network may be extended between queries, for example with a faulty copy of some logic,
+//! and the new logic is encoded as soon as a query uses it.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use rustsat::types::Lit;
+
+use super::{add_node_clauses, lit_value, new_solver, resolve_buf, simplify_clauses, SatSolver};
+use crate::network::GateRef;
+use crate::{Network, Signal};
+
+/// Sat solver on the signals of a network, with queries under assumptions
+///
+/// Flip-flops are treated as free variables.
+///
+/// ```
+/// # use quaigh_solve::{Gate, Network};
+/// # use quaigh_solve::equiv::IncrementalSolver;
+/// let mut aig = Network::new();
+/// aig.add_inputs(2);
+/// let (a, b) = (aig.input(0), aig.input(1));
+/// let x = aig.add(Gate::and(a, b));
+/// let mut solver = IncrementalSolver::new(&aig);
+/// assert!(solver.solve(&[x]));
+/// assert!(solver.value(a) && solver.value(b));
+/// assert!(!solver.solve(&[x, !a]));
+/// ```
+pub struct IncrementalSolver<'a> {
+    aig: Cow<'a, Network>,
+    solver: Box<dyn SatSolver>,
+    /// Nodes whose clauses have been added
+    encoded: Vec<bool>,
+    /// Number of Sat variables used so far
+    nb_vars: u32,
+    /// Translation of the signals to Sat literals
+    lits: HashMap<Signal, Lit>,
+    /// Number of clauses of the encoded logic, including the learned clauses
+    nb_clauses: usize,
+    /// Solution of the last satisfiable query
+    solution: Option<Vec<bool>>,
+    /// Number of queries
+    nb_calls: usize,
+}
+
+impl<'a> IncrementalSolver<'a> {
+    /// Create a solver for a network
+    pub fn new(aig: &'a Network) -> Self {
+        IncrementalSolver::from_cow(Cow::Borrowed(aig))
+    }
+
+    fn from_cow(aig: Cow<'a, Network>) -> Self {
+        IncrementalSolver {
+            encoded: vec![false; aig.nb_nodes()],
+            aig,
+            solver: new_solver(),
+            nb_vars: 0,
+            lits: HashMap::new(),
+            nb_clauses: 0,
+            solution: None,
+            nb_calls: 0,
+        }
+    }
+
+    /// Network on which the queries are made
+    pub fn network(&self) -> &Network {
+        &self.aig
+    }
+
+    /// Add logic to the network, and return the result of the closure
+    ///
+    /// The closure may only add nodes: the existing nodes may already be encoded, and must not be
+    /// modified. The network is copied the first time it is extended, if it was borrowed.
+    pub fn extend<R>(&mut self, f: impl FnOnce(&mut Network) -> R) -> R {
+        let nb_nodes = self.aig.nb_nodes();
+        let ret = f(self.aig.to_mut());
+        assert!(self.aig.nb_nodes() >= nb_nodes);
+        self.encoded.resize(self.aig.nb_nodes(), false);
+        ret
+    }
+
+    /// Number of queries made so far
+    pub fn nb_calls(&self) -> usize {
+        self.nb_calls
+    }
+
+    /// Number of clauses, including the learned clauses
+    pub fn nb_clauses(&self) -> usize {
+        self.nb_clauses
+    }
+
+    /// Create a new Sat variable
+    fn new_lit(&mut self) -> Lit {
+        let l = Lit::new(self.nb_vars, false);
+        self.nb_vars += 1;
+        l
+    }
+
+    /// Sat literal for a non-constant signal, created if necessary
+    fn lit(&mut self, s: Signal) -> Lit {
+        assert!(!s.is_constant());
+        let pos = s.without_inversion();
+        let l = match self.lits.get(&pos) {
+            Some(l) => *l,
+            None => {
+                let l = self.new_lit();
+                self.lits.insert(pos, l);
+                l
+            }
+        };
+        if s.is_inverted() {
+            !l
+        } else {
+            l
+        }
+    }
+
+    /// Add the clauses for the cone of a signal
+    fn encode(&mut self, s: Signal) {
+        let mut stack = Vec::new();
+        if s.is_var() {
+            stack.push(s.var() as usize);
+        }
+        let mut clauses = Vec::new();
+        // Intermediate results of the gates are numbered after the nodes
+        let mut next_var = self.aig.nb_nodes() as u32;
+        while let Some(i) = stack.pop() {
+            if self.encoded[i] {
+                continue;
+            }
+            self.encoded[i] = true;
+            let g = self.aig.gate_ref(i);
+            for d in g.dependencies() {
+                if d.is_var() && !self.encoded[d.var() as usize] {
+                    stack.push(d.var() as usize);
+                }
+            }
+            if g.is_comb() && !matches!(g, GateRef::Buf(_)) {
+                add_node_clauses(&mut clauses, &mut next_var, &self.aig, i);
+            }
+        }
+        self.add_clauses(clauses);
+    }
+
+    /// Add clauses on signals, whose cones must already be encoded
+    ///
+    /// Variables beyond the nodes of the network are intermediate results, local to these clauses.
+    fn add_clauses(&mut self, mut clauses: Vec<Vec<Signal>>) {
+        simplify_clauses(&mut clauses);
+        let nb_nodes = self.aig.nb_nodes() as u32;
+        let mut intermediates = HashMap::new();
+        for c in clauses {
+            let c: Vec<Lit> = c
+                .iter()
+                .map(|s| {
+                    if s.is_var() && s.var() >= nb_nodes {
+                        let l = *intermediates
+                            .entry(s.without_inversion())
+                            .or_insert_with(|| self.new_lit());
+                        if s.is_inverted() {
+                            !l
+                        } else {
+                            l
+                        }
+                    } else {
+                        self.lit(*s)
+                    }
+                })
+                .collect();
+            self.solver.add_clause(&c);
+            self.nb_clauses += 1;
+        }
+    }
+
+    /// Add a clause on signals of the network, that all following queries must satisfy
+    pub fn add_clause(&mut self, clause: &[Signal]) {
+        let clause: Vec<Signal> = clause.iter().map(|s| resolve_buf(&self.aig, *s)).collect();
+        for s in &clause {
+            self.encode(*s);
+        }
+        self.add_clauses(vec![clause]);
+    }
+
+    /// Find an assignment where all the assumptions are 1
+    ///
+    /// Returns true if such an assignment exists; the values of the signals are then available
+    /// with [`IncrementalSolver::value`]. Otherwise, the negation of the assumptions is learned.
+    pub fn solve(&mut self, assumptions: &[Signal]) -> bool {
+        self.nb_calls += 1;
+        self.solution = None;
+        let assumptions: Vec<Signal> = assumptions
+            .iter()
+            .map(|s| resolve_buf(&self.aig, *s))
+            .filter(|s| *s != Signal::one())
+            .collect();
+        if assumptions.contains(&Signal::zero()) {
+            return false;
+        }
+        for s in &assumptions {
+            self.encode(*s);
+        }
+        let lits: Vec<Lit> = assumptions.iter().map(|s| self.lit(*s)).collect();
+        self.solution = self.solver.solve_with_assumptions(&lits);
+        if self.solution.is_none() {
+            self.add_clauses(vec![assumptions.iter().map(|s| !*s).collect()]);
+        }
+        self.solution.is_some()
+    }
+
+    /// Value of a signal in the assignment found by the last query
+    ///
+    /// Signals outside the cones of the queries are not constrained, and take the value 0 if
+    /// they are inputs or flip-flops.
+    pub fn value(&self, s: Signal) -> bool {
+        let sol = self
+            .solution
+            .as_ref()
+            .expect("The last query should be satisfiable");
+        let r = resolve_buf(&self.aig, s);
+        if r.is_constant() {
+            return r == Signal::one();
+        }
+        match self.lits.get(&r.without_inversion()) {
+            Some(l) => lit_value(sol, *l) ^ r.is_inverted(),
+            None => {
+                if !r.is_input() {
+                    let i = r.var() as usize;
+                    assert!(
+                        !self.aig.gate(i).is_comb() || self.encoded[i],
+                        "Signal outside of the encoded cones"
+                    );
+                }
+                r.is_inverted()
+            }
+        }
+    }
+
+    /// Values of the primary inputs in the assignment found by the last query
+    pub fn input_values(&self) -> Vec<bool> {
+        (0..self.aig.nb_inputs())
+            .map(|i| self.value(self.aig.input(i)))
+            .collect()
+    }
+
+    /// Check whether two signals are equivalent
+    ///
+    /// If they are, the equivalence is learned for the following queries. Otherwise, the
+    /// assignment where they differ is available with [`IncrementalSolver::value`].
+    pub fn equivalent(&mut self, a: Signal, b: Signal) -> bool {
+        !self.solve(&[a, !b]) && !self.solve(&[!a, b])
+    }
+}
+
+impl IncrementalSolver<'static> {
+    /// Create a solver owning its network, for example to extend it between queries
+    pub fn from_network(aig: Network) -> Self {
+        IncrementalSolver::from_cow(Cow::Owned(aig))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IncrementalSolver;
+    use crate::network::generators::adder;
+    use crate::optim::lut_map;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_queries() {
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        let x = aig.add(Gate::and3(a, b, c));
+        let t = aig.add(Gate::and(a, b));
+        let y = aig.add(Gate::and(t, c));
+        let z = aig.add(Gate::Buf(!y));
+        let ff = aig.add(Gate::dff(x, a, Signal::zero()));
+        let w = aig.add(Gate::and(ff, !c));
+
+        let mut solver = IncrementalSolver::new(&aig);
+        assert!(solver.equivalent(x, y));
+        assert!(solver.equivalent(!x, z));
+        assert!(!solver.equivalent(x, t));
+        assert!(solver.value(a) && solver.value(b) && !solver.value(c));
+        assert_eq!(solver.value(x), !solver.value(z));
+
+        // Flip-flops are free variables
+        assert!(solver.solve(&[w, a]));
+        assert!(solver.value(ff) && !solver.value(c));
+        assert!(!solver.solve(&[w, c]));
+
+        // Constants
+        assert!(solver.solve(&[Signal::one()]));
+        assert!(!solver.solve(&[a, Signal::zero()]));
+
+        // Permanent clauses
+        solver.add_clause(&[!a]);
+        assert!(!solver.solve(&[t]));
+        assert!(solver.solve(&[b]));
+        assert!(!solver.value(a));
+        assert_eq!(solver.nb_calls(), 12);
+    }
+
+    #[test]
+    fn test_unencoded_inputs() {
+        // Inputs outside the cones of the queries read as 0
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        let x = aig.add(Gate::and(a, !b));
+        let mut solver = IncrementalSolver::new(&aig);
+        assert!(solver.solve(&[x]));
+        assert!(!solver.value(c));
+        assert!(solver.value(!c));
+        assert_eq!(solver.input_values(), [true, false, false]);
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut aig = Network::new();
+        aig.add_inputs(2);
+        let (a, b) = (aig.input(0), aig.input(1));
+        let x = aig.add(Gate::xor(a, b));
+        let mut solver = IncrementalSolver::new(&aig);
+        assert!(solver.solve(&[x, a]));
+        // Logic added after the first queries, with a Xor3 that needs an intermediate variable
+        let (y, z) = solver.extend(|aig| {
+            let t = aig.add(Gate::and(a, b));
+            let y = aig.add(Gate::xor3(a, b, t));
+            let z = aig.add(Gate::Buf(!x));
+            (y, z)
+        });
+        assert!(solver.network().nb_nodes() > aig.nb_nodes());
+        // y is a | b, and z is the negation of a ^ b
+        assert!(!solver.equivalent(y, !z));
+        assert!(solver.value(a) && solver.value(b));
+        assert!(!solver.solve(&[y, !a, !b]));
+        assert!(!solver.solve(&[y, z, !a]));
+        assert!(solver.solve(&[y, z]));
+        assert!(solver.value(a) && solver.value(b) && !solver.value(x));
+
+        let mut solver = IncrementalSolver::from_network(aig.clone());
+        assert!(!solver.solve(&[x, a, b]));
+        assert_eq!(solver.nb_calls(), 1);
+    }
+
+    #[test]
+    fn test_adders() {
+        // The outputs of an adder and of its mapping to 3-input Luts are equivalent
+        let a = adder::ripple_carry(4);
+        let b = lut_map(&a, 3);
+        let mut aig = a.clone();
+        let inputs: Vec<Signal> = (0..aig.nb_inputs()).map(|i| aig.input(i)).collect();
+        let outputs = aig.insert_network(&b, &inputs).unwrap();
+        let mut solver = IncrementalSolver::new(&aig);
+        for (i, o) in outputs.iter().enumerate() {
+            assert!(solver.equivalent(aig.output(i), *o));
+        }
+        assert!(!solver.equivalent(aig.output(0), aig.output(1)));
+        let v = solver.input_values();
+        assert_ne!(solver.value(aig.output(0)), solver.value(aig.output(1)));
+        assert_eq!(v.len(), aig.nb_inputs());
+    }
+}
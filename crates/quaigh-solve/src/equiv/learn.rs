@@ -0,0 +1,175 @@
+//! Learning of constants and implications before the main Sat solve
+//!
+//! Random simulation gives candidate constant nodes, and candidate implications between pairs of
+//! nodes that hold for all simulated patterns. A bounded number of the most promising candidates
+//! are verified with small Sat queries on their cones, and the verified facts are given to the
+//! main solve as unit and binary clauses.
+
+use std::fmt;
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::equiv::{output_cones, prove};
+use crate::sim::simulate_nodes_multi;
+use crate::util::format::duration;
+use crate::util::rng::SeededRng;
+use crate::{Gate, Network, Signal};
+
+/// Number of 64b words of random simulation used to find the candidates
+const NB_SIMULATION_WORDS: usize = 8;
+
+/// Maximum number of nodes between which implications are searched, as pairs are quadratic
+const MAX_IMPLICATION_NODES: usize = 512;
+
+/// Maximum number of candidates verified with the Sat solver
+const MAX_CHECKS: usize = 64;
+
+/// Statistics on the facts learned before a Sat solve
+#[derive(Clone, Debug, Default)]
+pub struct LearnReport {
+    /// Number of candidate facts found by simulation
+    pub nb_candidates: usize,
+    /// Number of candidates verified with the Sat solver
+    pub nb_checked: usize,
+    /// Number of constant nodes added as unit clauses
+    pub nb_constants: usize,
+    /// Number of implications added as binary clauses
+    pub nb_implications: usize,
+    /// Time spent on simulation and verification
+    pub learn_time: Duration,
+    /// Time spent in the main Sat solve
+    pub solve_time: Duration,
+}
+
+impl fmt::Display for LearnReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Learned {} constants and {} implications ({} candidates, {} checked) in {}",
+            self.nb_constants,
+            self.nb_implications,
+            self.nb_candidates,
+            self.nb_checked,
+            duration(self.learn_time)
+        )?;
+        write!(f, "Main solve took {}", duration(self.solve_time))
+    }
+}
+
+/// Return whether a signal can never be 1
+fn never_true(aig: &Network, s: Signal) -> bool {
+    let mut m = aig.clone();
+    m.set_output(0, s);
+    prove(&m).is_none()
+}
+
+/// Return whether the gate of a node uses another node directly
+///
+/// Such implications are already part of the encoding of the gate.
+fn uses(aig: &Network, i: usize, j: usize) -> bool {
+    aig.gate(i)
+        .dependencies()
+        .iter()
+        .any(|s| s.is_var() && s.var() as usize == j)
+}
+
+/// Find constants and implications between the nodes of a single-output network
+///
+/// Returns the verified facts as clauses on the nodes of the network.
+pub(crate) fn learn_clauses(aig: &Network, report: &mut LearnReport) -> Vec<Vec<Signal>> {
+    assert!(aig.is_comb());
+    assert_eq!(aig.nb_outputs(), 1);
+    let out = aig.output(0);
+
+    // Only nodes that appear in the encoding are considered
+    let nodes: Vec<usize> = output_cones(aig)
+        .into_iter()
+        .enumerate()
+        .filter(|(i, used)| {
+            *used
+                && !matches!(&aig.gate(*i), Gate::Buf(_))
+                && out != aig.node(*i).without_inversion()
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    let mut rng = SeededRng::new(1);
+    let mut values = vec![Vec::new(); aig.nb_nodes()];
+    for _ in 0..NB_SIMULATION_WORDS {
+        let inputs: Vec<u64> = (0..aig.nb_inputs()).map(|_| rng.gen()).collect();
+        for (v, x) in values.iter_mut().zip(simulate_nodes_multi(aig, &inputs)) {
+            v.push(x);
+        }
+    }
+    let count = |i: usize| -> u32 { values[i].iter().map(|x| x.count_ones()).sum() };
+    let total = (64 * NB_SIMULATION_WORDS) as u32;
+
+    // Candidates as clauses, with a score: constants first, then implications that were
+    // exercised by the most patterns
+    let mut candidates: Vec<(u32, Vec<Signal>)> = Vec::new();
+    let mut varying = Vec::new();
+    for i in nodes {
+        let ones = count(i);
+        if ones == 0 || ones == total {
+            candidates.push((u32::MAX, vec![aig.node(i) ^ (ones == 0)]));
+        } else {
+            varying.push(i);
+        }
+    }
+    varying.truncate(MAX_IMPLICATION_NODES);
+    for (k, i) in varying.iter().enumerate() {
+        for j in &varying[k + 1..] {
+            if uses(aig, *i, *j) || uses(aig, *j, *i) {
+                continue;
+            }
+            for (inv_a, inv_b) in [(false, false), (false, true), (true, false), (true, true)] {
+                let (va, vb) = (&values[*i], &values[*j]);
+                let mask_a = if inv_a { !0 } else { 0 };
+                let mask_b = if inv_b { !0 } else { 0 };
+                // a implies b if a & !b never happens
+                let holds = va
+                    .iter()
+                    .zip(vb)
+                    .all(|(x, y)| (x ^ mask_a) & !(y ^ mask_b) == 0);
+                if !holds {
+                    continue;
+                }
+                let a = aig.node(*i) ^ inv_a;
+                let b = aig.node(*j) ^ inv_b;
+                let support: u32 = va
+                    .iter()
+                    .zip(vb)
+                    .map(|(x, y)| ((x ^ mask_a) | !(y ^ mask_b)).count_ones())
+                    .sum();
+                candidates.push((support, vec![!a, b]));
+            }
+        }
+    }
+    report.nb_candidates += candidates.len();
+
+    candidates.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    let mut ret = Vec::new();
+    for (_, clause) in candidates.into_iter().take(MAX_CHECKS) {
+        report.nb_checked += 1;
+        let verified = match clause.as_slice() {
+            [a] => never_true(aig, !*a),
+            [a, b] => {
+                let mut m = aig.clone();
+                let x = m.and(!*a, !*b);
+                never_true(&m, x)
+            }
+            _ => unreachable!(),
+        };
+        if !verified {
+            continue;
+        }
+        if clause.len() == 1 {
+            report.nb_constants += 1;
+        } else {
+            report.nb_implications += 1;
+        }
+        ret.push(clause);
+    }
+    ret
+}
@@ -0,0 +1,206 @@
+//! Normalization of miters before the Sat solve
+//!
+//! Deduplication already merges the gates of the two sides when their operands are the same
+//! after remapping, whatever the order of the nodes in the original designs. It cannot merge
+//! And and Xor chains that are associated differently on each side, such as `(a & b) & c` and
+//! `a & (b & c)`. Chains whose intermediate nodes have no other use are flattened into a single
+//! n-ary gate, with sorted operands, so that the two sides are merged by the next deduplication.
+
+use std::fmt;
+
+use crate::{Gate, Network, Signal};
+
+/// Size of a miter before and after normalization
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MiterReduction {
+    /// Number of nodes of the miter before normalization
+    pub nb_nodes_before: usize,
+    /// Number of nodes of the miter after normalization
+    pub nb_nodes_after: usize,
+}
+
+impl fmt::Display for MiterReduction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Miter reduced from {} to {} nodes",
+            self.nb_nodes_before, self.nb_nodes_after
+        )
+    }
+}
+
+/// Translate a signal of the original miter to the new network
+fn translate(s: &Signal, translation: &[Signal]) -> Signal {
+    if s.is_var() {
+        translation[s.var() as usize] ^ s.is_inverted()
+    } else {
+        *s
+    }
+}
+
+/// Flatten a single And or Xor gate, given the flattened operands of the previous nodes
+///
+/// Returns the operands of the flattened gate, in the new network. Inverted operands can only
+/// be expanded for Xor gates, where the inversion is kept as a constant operand.
+fn flatten_operands(
+    g: &Gate,
+    translation: &[Signal],
+    flat: &[Option<(bool, Vec<Signal>)>],
+    fanouts: &[usize],
+) -> Vec<Signal> {
+    let is_xor = g.is_xor();
+    let mut ret = Vec::new();
+    for s in g.dependencies() {
+        if s.is_var() {
+            let v = s.var() as usize;
+            if fanouts[v] == 1 && (is_xor || !s.is_inverted()) {
+                if let Some((x, ops)) = &flat[v] {
+                    if *x == is_xor {
+                        ret.extend(ops);
+                        if s.is_inverted() {
+                            ret.push(Signal::one());
+                        }
+                        continue;
+                    }
+                }
+            }
+        }
+        ret.push(translate(s, translation));
+    }
+    ret
+}
+
+/// Normalize a combinatorial miter, so that more logic is shared between its two sides
+///
+/// The miter is made canonical, And and Xor chains without intermediate fanout are flattened,
+/// and the result is deduplicated again. Returns the number of nodes before and after.
+pub fn normalize_miter(miter: &mut Network) -> MiterReduction {
+    assert!(miter.is_comb());
+    let nb_nodes_before = miter.nb_nodes();
+    miter.make_canonical();
+    miter.cleanup();
+
+    let mut fanouts = vec![0; miter.nb_nodes()];
+    for i in 0..miter.nb_nodes() {
+        for v in miter.gate(i).vars() {
+            fanouts[v as usize] += 1;
+        }
+    }
+    for o in 0..miter.nb_outputs() {
+        let s = miter.output(o);
+        if s.is_var() {
+            fanouts[s.var() as usize] += 1;
+        }
+    }
+
+    let mut ret = Network::new();
+    ret.add_inputs(miter.nb_inputs());
+    let mut translation = Vec::with_capacity(miter.nb_nodes());
+    let mut flat = Vec::with_capacity(miter.nb_nodes());
+    for i in 0..miter.nb_nodes() {
+        let g = miter.gate(i);
+        if g.is_and() || g.is_xor() {
+            let ops = flatten_operands(&g, &translation, &flat, &fanouts);
+            let gate = if g.is_xor() {
+                Gate::xorn(&ops)
+            } else {
+                Gate::andn(&ops)
+            };
+            translation.push(ret.add_canonical(gate));
+            flat.push(Some((g.is_xor(), ops)));
+        } else {
+            translation.push(ret.add_canonical(g.remap(|s| translate(s, &translation))));
+            flat.push(None);
+        }
+    }
+    for o in 0..miter.nb_outputs() {
+        ret.add_output(translate(&miter.output(o), &translation));
+    }
+    ret.make_canonical();
+    ret.cleanup();
+    *miter = ret;
+    MiterReduction {
+        nb_nodes_before,
+        nb_nodes_after: miter.nb_nodes(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize_miter;
+    use crate::equiv::difference;
+    use crate::network::generators::adder;
+    use crate::sim::simulate_comb;
+    use crate::{Network, Signal};
+
+    #[test]
+    fn test_shuffled_adders() {
+        let a = adder::ripple_carry(8);
+        let mut b = a.clone();
+        b.shuffle(7);
+        let mut diff = difference(&a, &b);
+        let reduction = normalize_miter(&mut diff);
+        assert_eq!(diff.output(0), Signal::zero());
+        assert_eq!(reduction.nb_nodes_after, 0);
+        assert!(reduction.nb_nodes_before > 0);
+    }
+
+    #[test]
+    fn test_association() {
+        // ((a & b) & c) & !d against a & (b & (c & !d)), and the same with Xor
+        let mut a = Network::new();
+        let mut b = Network::new();
+        a.add_inputs(4);
+        b.add_inputs(4);
+        let i: Vec<Signal> = (0..4).map(|k| a.input(k)).collect();
+        let x = a.and(i[0], i[1]);
+        let x = a.and(x, i[2]);
+        let x = a.and(x, !i[3]);
+        a.add_output(x);
+        let y = b.and(i[2], !i[3]);
+        let y = b.and(i[1], y);
+        let y = b.and(i[0], y);
+        b.add_output(y);
+        let x = a.xor(i[0], !i[1]);
+        let x = a.xor(x, i[2]);
+        let x = a.xor(!x, i[3]);
+        a.add_output(x);
+        let y = b.xor(i[3], i[2]);
+        let y = b.xor(!i[1], y);
+        let y = b.xor(i[0], !y);
+        b.add_output(y);
+
+        let mut diff = difference(&a, &b);
+        diff.make_canonical();
+        diff.cleanup();
+        assert_ne!(diff.output(0), Signal::zero());
+        normalize_miter(&mut diff);
+        assert_eq!(diff.output(0), Signal::zero());
+    }
+
+    #[test]
+    fn test_function_preserved() {
+        // Chains with fanout and inversions must keep the same function
+        let mut a = Network::new();
+        a.add_inputs(5);
+        let i: Vec<Signal> = (0..5).map(|k| a.input(k)).collect();
+        let x = a.and(i[0], i[1]);
+        let y = a.and(x, !i[2]);
+        let z = a.and(!y, i[3]);
+        let w = a.xor(y, i[4]);
+        let w = a.xor(!w, x);
+        let o = a.and(z, w);
+        a.add_output(o);
+        a.add_output(y);
+        let mut b = a.clone();
+        normalize_miter(&mut b);
+        for k in 0..32 {
+            let pattern: Vec<bool> = (0..5).map(|j| (k >> j) & 1 != 0).collect();
+            assert_eq!(
+                simulate_comb(&a, &pattern),
+                simulate_comb(&b, &pattern),
+                "Mismatch for pattern {pattern:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Queries on internal signals, for interactive debugging
+//!
+//! A query asks whether two internal signals, of the same network or of two networks with the
+//! same inputs, can take different values. Only the cones of the two signals are encoded, and
+//! flip-flops are treated as free variables.
+
+use std::collections::HashMap;
+
+use crate::equiv::prove_with_constraints;
+use crate::sim::simulate_comb;
+use crate::{Gate, Network, Signal};
+
+/// An assignment for which two signals take different values
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignalDifference {
+    /// Values of the primary inputs
+    pub inputs: Vec<bool>,
+    /// Values of the flip-flops in the cone of each signal, by node index
+    ///
+    /// Flip-flops of the same network are shared between both signals, and only listed once.
+    pub state: [Vec<(usize, bool)>; 2],
+    /// Values of the two signals
+    pub values: [bool; 2],
+}
+
+/// Copy the cone of a signal, with flip-flops replaced by new inputs
+///
+/// The translation of the nodes already copied is kept in `t`, and the new inputs created for the
+/// flip-flops are recorded in `state`.
+fn copy_cone(
+    dest: &mut Network,
+    src: &Network,
+    s: Signal,
+    t: &mut HashMap<u32, Signal>,
+    state: &mut Vec<(usize, Signal)>,
+) -> Signal {
+    let translate = |t: &HashMap<u32, Signal>, s: &Signal| -> Signal {
+        // Inputs are shared, and have the same index in both networks
+        if s.is_var() {
+            t[&s.var()] ^ s.is_inverted()
+        } else {
+            *s
+        }
+    };
+    let mut stack = Vec::new();
+    if s.is_var() {
+        stack.push((s.var(), false));
+    }
+    while let Some((v, expanded)) = stack.pop() {
+        if t.contains_key(&v) {
+            continue;
+        }
+        let g = src.gate(v as usize);
+        if let Gate::Dff(..) = g {
+            let x = dest.add_input();
+            state.push((v as usize, x));
+            t.insert(v, x);
+        } else if expanded {
+            let x = dest.add(g.remap(|s| translate(t, s)));
+            t.insert(v, x);
+        } else {
+            stack.push((v, true));
+            for d in g.dependencies() {
+                if d.is_var() && !t.contains_key(&d.var()) {
+                    stack.push((d.var(), false));
+                }
+            }
+        }
+    }
+    translate(t, &s)
+}
+
+/// Find an assignment for which two signals take different values
+///
+/// The second signal belongs to the network `b`, or to the network `a` if `b` is None. Both
+/// networks must have the same inputs. Each primary input may be constrained to a fixed value;
+/// inputs beyond the end of the constraints are free. Flip-flops are free variables.
+///
+/// Returns the assignment, or None if the signals never differ under the constraints.
+pub fn query_signals(
+    a: &Network,
+    sa: Signal,
+    b: Option<&Network>,
+    sb: Signal,
+    constraints: &[Option<bool>],
+) -> Option<SignalDifference> {
+    assert!(b.is_none_or(|b| b.nb_inputs() == a.nb_inputs()));
+    assert!(constraints.len() <= a.nb_inputs());
+    let nb_inputs = a.nb_inputs();
+
+    let mut cones = Network::new();
+    cones.add_inputs(nb_inputs);
+    let mut t = HashMap::new();
+    let mut state_a = Vec::new();
+    let xa = copy_cone(&mut cones, a, sa, &mut t, &mut state_a);
+    let mut state_b = Vec::new();
+    let xb = match b {
+        Some(b) => copy_cone(&mut cones, b, sb, &mut HashMap::new(), &mut state_b),
+        None => copy_cone(&mut cones, a, sb, &mut t, &mut state_a),
+    };
+
+    let mut miter = cones.clone();
+    let x = miter.xor(xa, xb);
+    miter.add_output(x);
+    let assignment = prove_with_constraints(&miter, constraints)?;
+
+    cones.add_output(xa);
+    cones.add_output(xb);
+    let values = simulate_comb(&cones, &assignment);
+    let value = |x: Signal| assignment[x.input() as usize];
+    Some(SignalDifference {
+        inputs: assignment[..nb_inputs].to_vec(),
+        state: [
+            state_a.iter().map(|(i, x)| (*i, value(*x))).collect(),
+            state_b.iter().map(|(i, x)| (*i, value(*x))).collect(),
+        ],
+        values: [values[0], values[1]],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::query_signals;
+    use crate::network::generators::adder;
+    use crate::{Gate, Network};
+
+    #[test]
+    fn test_same_design() {
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (i0, i1, i2) = (aig.input(0), aig.input(1), aig.input(2));
+        let a = aig.add(Gate::and3(i0, i1, i2));
+        let x = aig.and(i0, i1);
+        let b = aig.and(x, i2);
+        let c = aig.add(Gate::and3(i0, i1, !i2));
+        let ff = aig.dff(c, i0, i1);
+        let d = aig.and(ff, a);
+        aig.add_output(d);
+
+        // Provably equal
+        assert_eq!(query_signals(&aig, a, None, b, &[]), None);
+
+        // Provably different
+        let diff = query_signals(&aig, a, None, c, &[]).unwrap();
+        assert_eq!(diff.values, [diff.inputs[2], !diff.inputs[2]]);
+        assert!(diff.inputs[0] && diff.inputs[1]);
+
+        // Equal under assumptions
+        assert_eq!(query_signals(&aig, a, None, c, &[Some(false)]), None);
+
+        // Flip-flops are free, but shared between the two signals
+        let diff = query_signals(&aig, a, None, d, &[Some(true), Some(true), Some(true)]).unwrap();
+        assert_eq!(diff.state[0], vec![(ff.var() as usize, false)]);
+        assert!(diff.state[1].is_empty());
+        assert_eq!(query_signals(&aig, d, None, d, &[]), None);
+    }
+
+    #[test]
+    fn test_cross_design() {
+        let a = adder::ripple_carry(4);
+        let mut b = a.clone();
+        b.make_canonical();
+        // The carry out is equal, but not to the first sum bit
+        let diff = query_signals(&a, a.output(4), Some(&b), b.output(0), &[]).unwrap();
+        assert_ne!(diff.values[0], diff.values[1]);
+        assert_eq!(
+            query_signals(&a, a.output(4), Some(&b), b.output(4), &[]),
+            None
+        );
+    }
+}
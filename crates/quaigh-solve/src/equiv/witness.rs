@@ -0,0 +1,279 @@
+//! Replayable witnesses for failed equivalence checks
+//!
+//! A witness bundle is a tar archive with everything needed to replay a failure later:
+//! * canonicalized snapshots of both networks (`network1.bench`, `network2.bench`),
+//! * the counterexample (`counterexample.test`),
+//! * the output values of each network on the counterexample (`outputs1.test`, `outputs2.test`),
+//! * a JSON manifest with the version, options and verdict (`manifest.json`),
+//! * the command line to replay it (`replay.sh`).
+
+use std::io::{Read, Write};
+
+use serde_json::json;
+
+use crate::io::tar::{read_tar, write_tar};
+use crate::io::{read_bench, read_patterns, write_bench, write_patterns};
+use crate::network::fingerprint::fingerprint;
+use crate::sim::simulate;
+use crate::Network;
+
+/// Verdict recorded for a failed equivalence check
+const NOT_EQUIVALENT: &str = "not-equivalent";
+
+/// Everything needed to replay a failed equivalence check
+#[derive(Clone, Debug)]
+pub struct WitnessBundle {
+    /// Canonicalized snapshot of the first network
+    pub network1: Network,
+    /// Canonicalized snapshot of the second network
+    pub network2: Network,
+    /// Original file names of the networks
+    pub names: [String; 2],
+    /// Counterexample, with the values of the inputs at each timestep
+    pub counterexample: Vec<Vec<bool>>,
+    /// Outputs of the first network on the counterexample
+    pub outputs1: Vec<Vec<bool>>,
+    /// Outputs of the second network on the counterexample
+    pub outputs2: Vec<Vec<bool>>,
+    /// Number of clock cycles considered by the check
+    pub num_cycles: usize,
+    /// Whether the check only used the Sat solver
+    pub sat_only: bool,
+    /// Version of quaigh that produced the witness
+    pub version: String,
+}
+
+fn to_string(data: &[u8]) -> Result<&str, String> {
+    std::str::from_utf8(data).map_err(|_| "Invalid UTF-8 in witness bundle".to_owned())
+}
+
+/// Read a pattern file containing a single pattern
+fn read_single_pattern(data: &[u8]) -> Result<Vec<Vec<bool>>, String> {
+    let mut patterns = read_patterns(data)?;
+    if patterns.len() != 1 {
+        return Err(format!("Expected a single pattern, got {}", patterns.len()));
+    }
+    Ok(patterns.remove(0))
+}
+
+impl WitnessBundle {
+    /// Create a witness from the two networks and the counterexample returned by the equivalence check
+    pub fn new(
+        a: &Network,
+        b: &Network,
+        names: [String; 2],
+        counterexample: Vec<Vec<bool>>,
+        num_cycles: usize,
+        sat_only: bool,
+    ) -> WitnessBundle {
+        let mut network1 = a.clone();
+        network1.make_canonical();
+        let mut network2 = b.clone();
+        network2.make_canonical();
+        let outputs1 = simulate(&network1, &counterexample);
+        let outputs2 = simulate(&network2, &counterexample);
+        WitnessBundle {
+            network1,
+            network2,
+            names,
+            counterexample,
+            outputs1,
+            outputs2,
+            num_cycles,
+            sat_only,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// JSON manifest of the bundle
+    fn manifest(&self) -> String {
+        let manifest = json!({
+            "quaigh_version": self.version,
+            "command": "check-equivalence",
+            "files": self.names,
+            "fingerprints": [
+                format!("{:#018x}", fingerprint(&self.network1)),
+                format!("{:#018x}", fingerprint(&self.network2)),
+            ],
+            "options": {
+                "num_cycles": self.num_cycles,
+                "sat_only": self.sat_only,
+            },
+            "verdict": NOT_EQUIVALENT,
+        });
+        serde_json::to_string_pretty(&manifest).unwrap()
+    }
+
+    /// Write the bundle as a tar archive
+    pub fn write<W: Write>(&self, w: &mut W, bundle_name: &str) {
+        let mut network1 = Vec::new();
+        write_bench(&mut network1, &self.network1);
+        let mut network2 = Vec::new();
+        write_bench(&mut network2, &self.network2);
+        let mut counterexample = Vec::new();
+        write_patterns(&mut counterexample, &vec![self.counterexample.clone()]);
+        let mut outputs1 = Vec::new();
+        write_patterns(&mut outputs1, &vec![self.outputs1.clone()]);
+        let mut outputs2 = Vec::new();
+        write_patterns(&mut outputs2, &vec![self.outputs2.clone()]);
+        let manifest = self.manifest();
+        let replay = format!("#!/bin/sh\nquaigh replay-witness {}\n", bundle_name);
+        write_tar(
+            w,
+            &[
+                ("manifest.json", manifest.as_bytes()),
+                ("network1.bench", &network1),
+                ("network2.bench", &network2),
+                ("counterexample.test", &counterexample),
+                ("outputs1.test", &outputs1),
+                ("outputs2.test", &outputs2),
+                ("replay.sh", replay.as_bytes()),
+            ],
+        );
+    }
+
+    /// Read a bundle from a tar archive
+    pub fn read<R: Read>(mut r: R) -> Result<WitnessBundle, String> {
+        let mut data = Vec::new();
+        r.read_to_end(&mut data).map_err(|e| e.to_string())?;
+        let files = read_tar(&data)?;
+        let get = |name: &str| -> Result<&[u8], String> {
+            files
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, c)| c.as_slice())
+                .ok_or_else(|| format!("Missing {} in witness bundle", name))
+        };
+        let manifest: serde_json::Value = serde_json::from_str(to_string(get("manifest.json")?)?)
+            .map_err(|e| format!("Invalid manifest: {}", e))?;
+        if manifest["verdict"] != NOT_EQUIVALENT {
+            return Err(format!(
+                "Unknown verdict {} in manifest",
+                manifest["verdict"]
+            ));
+        }
+        let names = [
+            manifest["files"][0].as_str().unwrap_or("").to_string(),
+            manifest["files"][1].as_str().unwrap_or("").to_string(),
+        ];
+        Ok(WitnessBundle {
+            network1: read_bench(get("network1.bench")?)?,
+            network2: read_bench(get("network2.bench")?)?,
+            names,
+            counterexample: read_single_pattern(get("counterexample.test")?)?,
+            outputs1: read_single_pattern(get("outputs1.test")?)?,
+            outputs2: read_single_pattern(get("outputs2.test")?)?,
+            num_cycles: manifest["options"]["num_cycles"]
+                .as_u64()
+                .ok_or("Missing number of cycles in manifest")? as usize,
+            sat_only: manifest["options"]["sat_only"].as_bool().unwrap_or(false),
+            version: manifest["quaigh_version"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+        })
+    }
+
+    /// Replay the simulation of the counterexample
+    ///
+    /// Returns an error if the recorded verdict is not confirmed: either the networks give the same outputs
+    /// on the counterexample, or their outputs do not match the recorded values.
+    pub fn replay(&self) -> Result<(), String> {
+        let nb_inputs = self.network1.nb_inputs();
+        if self.network2.nb_inputs() != nb_inputs {
+            return Err("The networks have different numbers of inputs".to_owned());
+        }
+        for (i, step) in self.counterexample.iter().enumerate() {
+            if step.len() != nb_inputs {
+                return Err(format!(
+                    "Counterexample has {} inputs at timestep {}, expected {}",
+                    step.len(),
+                    i,
+                    nb_inputs
+                ));
+            }
+        }
+        let outputs1 = simulate(&self.network1, &self.counterexample);
+        let outputs2 = simulate(&self.network2, &self.counterexample);
+        if outputs1 != self.outputs1 {
+            return Err("Outputs of the first network do not match the recorded values".to_owned());
+        }
+        if outputs2 != self.outputs2 {
+            return Err(
+                "Outputs of the second network do not match the recorded values".to_owned(),
+            );
+        }
+        if outputs1 == outputs2 {
+            return Err("The networks give the same outputs on the counterexample".to_owned());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::WitnessBundle;
+    use crate::equiv::check_equivalence_bounded;
+    use crate::io::tar::{read_tar, write_tar};
+    use crate::Network;
+
+    fn failing_pair() -> (Network, Network) {
+        let mut a = Network::new();
+        let i0 = a.add_input();
+        let i1 = a.add_input();
+        let x = a.and(i0, i1);
+        a.add_output(x);
+        let mut b = Network::new();
+        b.add_inputs(2);
+        let x = !b.and(!i0, !i1);
+        b.add_output(x);
+        (a, b)
+    }
+
+    fn make_bundle() -> WitnessBundle {
+        let (a, b) = failing_pair();
+        let cex = check_equivalence_bounded(&a, &b, 1, true).unwrap_err();
+        WitnessBundle::new(&a, &b, ["a.bench".into(), "b.bench".into()], cex, 1, false)
+    }
+
+    #[test]
+    fn test_replay() {
+        let bundle = make_bundle();
+        let dir = std::env::temp_dir().join(format!("quaigh_witness_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("witness.tar");
+        bundle.write(&mut File::create(&path).unwrap(), "witness.tar");
+        let read = WitnessBundle::read(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(read.counterexample, bundle.counterexample);
+        assert_eq!(read.names, bundle.names);
+        assert_eq!(read.num_cycles, 1);
+        read.replay().unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_pattern() {
+        let bundle = make_bundle();
+        let mut data = Vec::new();
+        bundle.write(&mut data, "witness.tar");
+
+        // Replace the counterexample by a pattern where both networks agree
+        let files = read_tar(&data).unwrap();
+        let files: Vec<(&str, &[u8])> = files
+            .iter()
+            .map(|(n, c)| {
+                if n == "counterexample.test" {
+                    (n.as_str(), b"1: 11\n".as_slice())
+                } else {
+                    (n.as_str(), c.as_slice())
+                }
+            })
+            .collect();
+        let mut corrupted = Vec::new();
+        write_tar(&mut corrupted, &files);
+        let read = WitnessBundle::read(corrupted.as_slice()).unwrap();
+        assert!(read.replay().is_err());
+    }
+}
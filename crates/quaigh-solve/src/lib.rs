@@ -0,0 +1,18 @@
+//! Sat-based algorithms of quaigh: equivalence checking, test pattern generation and optimization
+//!
+//! By default, this crate relies on [Kissat](https://github.com/arminbiere/kissat), which requires a C compiler to build.
+//! Without the `kissat` feature, it uses a slower Sat solver written in Rust: see [`equiv::SatBackend`].
+//! Most users should depend on [quaigh](https://docs.rs/quaigh), which re-exports everything.
+
+#![warn(missing_docs)]
+
+pub mod analysis;
+pub mod atpg;
+pub mod benchmarking;
+pub mod equiv;
+pub mod mc;
+pub mod optim;
+pub mod testing;
+
+pub use quaigh_core::{error, io, network, sim, util};
+pub use quaigh_core::{Gate, Network, QuaighError, Signal};
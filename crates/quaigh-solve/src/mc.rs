@@ -0,0 +1,452 @@
+//! Model checking of safety properties
+//!
+//! A property is a signal of a sequential network that must be 1 at every clock cycle, starting
+//! from the initial state where all flip-flops are 0. Unlike bounded equivalence checking, the
+//! property is proved for any number of cycles. In networks with several clock domains, all
+//! flip-flops are updated at each cycle.
+//!
+//! The check uses property directed reachability (IC3/PDR). A sequence of frames
+//! over-approximates the states reachable in a bounded number of cycles. States from which the
+//! property may fail are blocked in the frames by clauses, that are generalized and propagated to
+//! later frames. The property is proved when two consecutive frames are identical, and a
+//! counterexample is found when a chain of states leads back to the initial state.
+//!
+//! ```
+//! # use quaigh_solve::{Gate, Network};
+//! use quaigh_solve::mc::{check_invariant, CheckResult};
+//!
+//! // A flip-flop that stays at 0
+//! let mut aig = Network::new();
+//! let i = aig.add_input();
+//! let q = aig.add(Gate::dff(i, i, i));
+//! assert_eq!(check_invariant(&aig, !q, 10), CheckResult::Proved);
+//! ```
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::equiv::prove_with_clauses;
+use crate::{Gate, Network, Signal};
+
+/// Result of the check of a safety property
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckResult {
+    /// The property holds in all reachable states
+    Proved,
+    /// The property fails: values of the inputs at each cycle, the property being 0 at the last one
+    Failed(Vec<Vec<bool>>),
+    /// The property could not be proved or disproved with the maximum number of frames
+    Unknown,
+}
+
+/// A conjunction of values of the flip-flops, sorted by flip-flop
+type Cube = Vec<(usize, bool)>;
+
+/// Whether a cube contains the initial state, where all flip-flops are 0
+fn contains_init(cube: &Cube) -> bool {
+    cube.iter().all(|(_, b)| !b)
+}
+
+/// Combinational transition relation of a sequential network
+///
+/// The inputs are the primary inputs followed by the current state of the flip-flops.
+struct Transition {
+    net: Network,
+    nb_inputs: usize,
+    next: Vec<Signal>,
+    bad: Signal,
+}
+
+impl Transition {
+    fn new(aig: &Network, property: Signal) -> Transition {
+        let mut aig = aig.clone();
+        aig.add_output(property);
+        aig.lower_latches();
+        aig.topo_sort();
+        let property = aig.output(aig.nb_outputs() - 1);
+
+        let dffs: Vec<usize> = (0..aig.nb_nodes())
+            .filter(|i| matches!(&aig.gate(*i), Gate::Dff(..)))
+            .collect();
+        let mut net = Network::new();
+        net.add_inputs(aig.nb_inputs() + dffs.len());
+        let mut t = vec![Signal::zero(); aig.nb_nodes()];
+        for (j, i) in dffs.iter().enumerate() {
+            t[*i] = net.input(aig.nb_inputs() + j);
+        }
+        let translate = |t: &Vec<Signal>, s: &Signal| {
+            if s.is_var() {
+                t[s.var() as usize] ^ s.is_inverted()
+            } else {
+                *s
+            }
+        };
+        for i in 0..aig.nb_nodes() {
+            let g = aig.gate(i);
+            if g.is_comb() {
+                t[i] = net.add(g.remap(|s| translate(&t, s)));
+            }
+        }
+        let mut next = Vec::new();
+        for i in dffs {
+            let Gate::Dff([d, en, res], _) = &aig.gate(i) else {
+                unreachable!()
+            };
+            let (d, en, res) = (translate(&t, d), translate(&t, en), translate(&t, res));
+            let q = t[i];
+            let m = net.add_canonical(Gate::mux(en, d, q));
+            next.push(net.and(m, !res));
+        }
+        let bad = !translate(&t, &property);
+        Transition {
+            net,
+            nb_inputs: aig.nb_inputs(),
+            next,
+            bad,
+        }
+    }
+
+    /// Signal of the current value of a flip-flop
+    fn state(&self, j: usize) -> Signal {
+        self.net.input(self.nb_inputs + j)
+    }
+
+    /// Find values of the inputs and of the state such that the target is 1
+    ///
+    /// The state satisfies the clauses of the frame, or is the initial state if there is no frame,
+    /// and is not in the blocked cube.
+    fn solve(
+        &self,
+        target: &[Signal],
+        frame: Option<&[&Cube]>,
+        blocked: Option<&Cube>,
+    ) -> Option<(Vec<bool>, Cube)> {
+        let mut q = self.net.clone();
+        let out = q.add_canonical(Gate::andn(target));
+        q.add_output(out);
+        let mut clauses: Vec<Vec<Signal>> = Vec::new();
+        for c in frame.unwrap_or_default().iter().copied().chain(blocked) {
+            clauses.push(c.iter().map(|(j, b)| self.state(*j) ^ *b).collect());
+        }
+        let mut constraints = vec![None; self.nb_inputs];
+        if frame.is_none() {
+            constraints.resize(self.nb_inputs + self.next.len(), Some(false));
+        }
+        let values = prove_with_clauses(&q, &constraints, clauses)?;
+        let state = (0..self.next.len())
+            .map(|j| (j, values[self.nb_inputs + j]))
+            .collect();
+        Some((values[..self.nb_inputs].to_vec(), state))
+    }
+
+    /// Signals representing the cube in the next state
+    fn next_cube(&self, cube: &Cube) -> Vec<Signal> {
+        cube.iter().map(|(j, b)| self.next[*j] ^ !*b).collect()
+    }
+}
+
+/// Frames of the reachability analysis
+///
+/// Clauses are stored in the last frame where they hold, and each frame implies the previous ones.
+struct Frames {
+    blocked: Vec<Vec<Cube>>,
+}
+
+impl Frames {
+    /// Cubes blocked in a frame; there is no frame for the initial state
+    fn get(&self, level: usize) -> Option<Vec<&Cube>> {
+        if level == 0 {
+            return None;
+        }
+        Some(self.blocked[level..].iter().flatten().collect())
+    }
+
+    /// Whether a cube is already blocked in a frame
+    fn is_blocked(&self, cube: &Cube, level: usize) -> bool {
+        self.blocked[level..]
+            .iter()
+            .flatten()
+            .any(|c| c.iter().all(|l| cube.contains(l)))
+    }
+}
+
+/// A state that reaches a failure of the property
+struct Obligation {
+    cube: Cube,
+    level: usize,
+    /// Inputs leading from this state to the state of the parent, or to the failure
+    inputs: Vec<bool>,
+    parent: Option<usize>,
+}
+
+/// Property directed reachability
+struct Pdr {
+    tr: Transition,
+    frames: Frames,
+}
+
+impl Pdr {
+    /// Whether a cube cannot be reached in one step from the previous frame
+    fn is_inductive(&self, cube: &Cube, level: usize) -> bool {
+        if cube.is_empty() || contains_init(cube) {
+            return false;
+        }
+        let target = self.tr.next_cube(cube);
+        let frame = self.frames.get(level - 1);
+        self.tr
+            .solve(&target, frame.as_deref(), Some(cube))
+            .is_none()
+    }
+
+    /// Remove literals from a blocked cube, as long as it stays blocked
+    fn generalize(&self, cube: &Cube, level: usize) -> Cube {
+        let mut ret = cube.clone();
+        let mut i = 0;
+        while i < ret.len() {
+            let mut c = ret.clone();
+            c.remove(i);
+            if self.is_inductive(&c, level) {
+                ret = c;
+            } else {
+                i += 1;
+            }
+        }
+        ret
+    }
+
+    /// Block a state where the property fails, or return the counterexample
+    fn block(&mut self, root: Obligation) -> Option<Vec<Vec<bool>>> {
+        let mut obligations = vec![root];
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((obligations[0].level, 0)));
+        while let Some(Reverse((level, idx))) = queue.pop() {
+            let cube = obligations[idx].cube.clone();
+            if contains_init(&cube) {
+                return Some(trace(&obligations, idx, None));
+            }
+            if self.frames.is_blocked(&cube, level) {
+                continue;
+            }
+            let target = self.tr.next_cube(&cube);
+            let frame = self.frames.get(level - 1);
+            match self.tr.solve(&target, frame.as_deref(), Some(&cube)) {
+                Some((inputs, state)) => {
+                    if level == 1 {
+                        return Some(trace(&obligations, idx, Some(inputs)));
+                    }
+                    obligations.push(Obligation {
+                        cube: state,
+                        level: level - 1,
+                        inputs,
+                        parent: Some(idx),
+                    });
+                    queue.push(Reverse((level - 1, obligations.len() - 1)));
+                    queue.push(Reverse((level, idx)));
+                }
+                None => {
+                    let c = self.generalize(&cube, level);
+                    self.frames.blocked[level].push(c);
+                }
+            }
+        }
+        None
+    }
+
+    /// Move the clauses that hold in the next frame, and return whether a frame became inductive
+    fn propagate(&mut self, k: usize) -> bool {
+        self.frames.blocked.push(Vec::new());
+        for level in 1..=k {
+            let cubes = std::mem::take(&mut self.frames.blocked[level]);
+            for c in cubes {
+                if self.is_inductive(&c, level + 1) {
+                    self.frames.blocked[level + 1].push(c);
+                } else {
+                    self.frames.blocked[level].push(c);
+                }
+            }
+            if self.frames.blocked[level].is_empty() {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// Inputs of the counterexample ending with an obligation
+fn trace(obligations: &[Obligation], idx: usize, first: Option<Vec<bool>>) -> Vec<Vec<bool>> {
+    let mut ret: Vec<Vec<bool>> = first.into_iter().collect();
+    let mut cur = Some(idx);
+    while let Some(i) = cur {
+        ret.push(obligations[i].inputs.clone());
+        cur = obligations[i].parent;
+    }
+    ret
+}
+
+/// Check that a signal is 1 in every reachable state of a network
+///
+/// Flip-flops start at 0. If the network has assumptions, the property only needs to hold as long
+/// as they held at every cycle: counterexamples satisfy the assumptions up to the last cycle.
+/// Returns [`CheckResult::Unknown`] if no conclusion is reached with `max_frames` frames.
+pub fn check_invariant(aig: &Network, property: Signal, max_frames: usize) -> CheckResult {
+    if aig.has_assumptions() {
+        let mut constrained = aig.clone();
+        let held = constrained.assumptions_held();
+        let property = !constrained.and(held, !property);
+        check_invariant_impl(&constrained, property, max_frames)
+    } else {
+        check_invariant_impl(aig, property, max_frames)
+    }
+}
+
+/// Check that all assertions of a network hold in every reachable state, under its assumptions
+///
+/// See [`check_invariant`].
+pub fn check_assertions(aig: &Network, max_frames: usize) -> CheckResult {
+    let mut aig = aig.clone();
+    let assertions: Vec<Signal> = aig
+        .assertions()
+        .into_iter()
+        .map(|o| aig.output(o))
+        .collect();
+    let property = aig.add_canonical(Gate::andn(&assertions));
+    check_invariant(&aig, property, max_frames)
+}
+
+fn check_invariant_impl(aig: &Network, property: Signal, max_frames: usize) -> CheckResult {
+    let tr = Transition::new(aig, property);
+    if let Some((inputs, _)) = tr.solve(&[tr.bad], None, None) {
+        return CheckResult::Failed(vec![inputs]);
+    }
+    let mut pdr = Pdr {
+        tr,
+        frames: Frames {
+            blocked: vec![Vec::new(), Vec::new()],
+        },
+    };
+    for k in 1..=max_frames {
+        loop {
+            let frame = pdr.frames.get(k);
+            let Some((inputs, state)) = pdr.tr.solve(&[pdr.tr.bad], frame.as_deref(), None) else {
+                break;
+            };
+            let root = Obligation {
+                cube: state,
+                level: k,
+                inputs,
+                parent: None,
+            };
+            if let Some(t) = pdr.block(root) {
+                return CheckResult::Failed(t);
+            }
+        }
+        if pdr.propagate(k) {
+            return CheckResult::Proved;
+        }
+    }
+    CheckResult::Unknown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_assertions, check_invariant, CheckResult};
+    use crate::sim::simulate;
+    use crate::{Gate, Network, Signal};
+
+    /// A counter on some bits, incremented at each cycle, with its bits as outputs
+    fn counter(nb_bits: usize) -> Network {
+        let mut aig = Network::new();
+        aig.add_input();
+        let bits: Vec<Signal> = (0..nb_bits)
+            .map(|_| aig.add(Gate::dff(Signal::zero(), Signal::one(), Signal::zero())))
+            .collect();
+        let mut carry = Signal::one();
+        for (i, b) in bits.iter().enumerate() {
+            let d = aig.xor(*b, carry);
+            aig.replace(i, Gate::dff(d, Signal::one(), Signal::zero()));
+            carry = aig.and(*b, carry);
+        }
+        for b in bits {
+            aig.add_output(b);
+        }
+        aig
+    }
+
+    #[test]
+    fn test_combinational() {
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let x = aig.add(Gate::and(a, b));
+        let implied = aig.add(Gate::and(x, !a));
+        assert_eq!(check_invariant(&aig, !implied, 4), CheckResult::Proved);
+        let CheckResult::Failed(t) = check_invariant(&aig, !x, 4) else {
+            panic!()
+        };
+        assert_eq!(t, vec![vec![true, true]]);
+    }
+
+    #[test]
+    fn test_counter() {
+        // The counter reaches all values: it is never 5 only if it has not enough cycles
+        let aig = counter(3);
+        let mut prop_aig = aig.clone();
+        let five = prop_aig.add(Gate::andn(&[aig.output(0), !aig.output(1), aig.output(2)]));
+        let CheckResult::Failed(t) = check_invariant(&prop_aig, !five, 10) else {
+            panic!()
+        };
+        assert_eq!(t.len(), 6);
+        let values = simulate(&aig, &t);
+        assert_eq!(values[5], vec![true, false, true]);
+    }
+
+    #[test]
+    fn test_strengthening() {
+        // A counter that goes back to 0 after 5 never reaches 7, but 6 must be excluded too
+        let mut aig = counter(3);
+        let b: Vec<Signal> = (0..3).map(|i| aig.output(i)).collect();
+        let five = aig.add(Gate::andn(&[b[0], !b[1], b[2]]));
+        for i in 0..3 {
+            let Gate::Dff([d, en, _], _) = aig.gate(i) else {
+                panic!()
+            };
+            aig.replace(i, Gate::dff(d, en, five));
+        }
+        let seven = aig.add(Gate::andn(&b));
+        assert_eq!(check_invariant(&aig, !seven, 10), CheckResult::Proved);
+        assert!(matches!(
+            check_invariant(&aig, !five, 10),
+            CheckResult::Failed(_)
+        ));
+        assert_eq!(check_invariant(&aig, !five, 2), CheckResult::Unknown);
+    }
+
+    #[test]
+    fn test_invariant() {
+        // Two flip-flops loaded with the same value are always equal
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let en = aig.add_input();
+        let q0 = aig.add(Gate::dff(a, en, Signal::zero()));
+        let q1 = aig.add(Gate::dff(!a, en, Signal::zero()));
+        let x = aig.add(Gate::and(q0, q1));
+        assert_eq!(check_invariant(&aig, !x, 10), CheckResult::Proved);
+    }
+
+    #[test]
+    fn test_assumptions() {
+        // A flip-flop that is only set if the input is 1
+        let mut aig = Network::new();
+        let a = aig.add_input();
+        let q = aig.add(Gate::dff(a, Signal::one(), Signal::zero()));
+        aig.add_assertion(!q);
+        let CheckResult::Failed(t) = check_assertions(&aig, 4) else {
+            panic!()
+        };
+        assert_eq!(t.len(), 2);
+
+        // The assumption must hold at every cycle, up to the failing one
+        aig.add_assumption(!a);
+        assert_eq!(check_assertions(&aig, 4), CheckResult::Proved);
+    }
+}
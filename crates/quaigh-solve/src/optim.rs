@@ -0,0 +1,53 @@
+//! Optimization of logic networks
+
+#[cfg(feature = "abc")]
+mod abc;
+mod adders;
+mod balance;
+mod cell_map;
+mod const_mult;
+mod dont_care;
+mod exact;
+mod explore;
+mod fraig;
+mod incremental;
+mod infer_gates;
+mod linear;
+mod lut_map;
+mod mig_rewrite;
+mod parallel;
+mod pipeline;
+mod registers;
+mod resub;
+mod rewrite;
+mod share_logic;
+mod share_mux;
+mod window_resynth;
+mod words;
+
+#[cfg(feature = "abc")]
+pub use abc::{run_abc, run_abc_with_binary, DEFAULT_ABC_BINARY};
+pub use adders::resynthesize_adders;
+pub use balance::balance;
+pub use cell_map::{cell_map, CellLibrary};
+pub use const_mult::{csd_digits, reduce_constant_multipliers, ConstantMultiplier};
+pub use dont_care::{optimize_dont_cares, optimize_dont_cares_with_rng};
+pub use exact::exact_synthesis;
+pub use explore::{explore, pareto_front, suffixed_path, ExplorationPoint, ParetoTable};
+pub use fraig::{fraig, fraig_with_rng};
+pub use incremental::{optimize_incremental, IncrementalReport};
+pub use infer_gates::{infer_dffe, infer_dffr, infer_xor_mux};
+pub use linear::optimize_xor_blocks;
+pub use lut_map::{lut_map, MAX_LUT_SIZE, MIN_LUT_SIZE};
+pub use mig_rewrite::mig_rewrite;
+pub use parallel::optimize_parallel;
+pub use pipeline::{Pass, Pipeline};
+pub use registers::dedup_registers;
+pub use resub::{resubstitute, resubstitute_with_rng};
+pub use rewrite::rewrite;
+pub use share_logic::{
+    flatten_nary, flatten_nary_with_limit, flattened_size, share_logic,
+    share_logic_with_constraints,
+};
+pub use share_mux::share_mux;
+pub use window_resynth::{window_resynth, window_resynth_with_rng, MAX_WINDOW_INPUTS};
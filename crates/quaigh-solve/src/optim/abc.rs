@@ -0,0 +1,165 @@
+//! Optimization with the external [ABC](https://github.com/berkeley-abc/abc) tool
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::io::{read_aiger, write_aiger};
+use crate::network::ClockId;
+use crate::{Network, QuaighError};
+
+/// Name of the ABC executable, looked up in the PATH
+pub const DEFAULT_ABC_BINARY: &str = "abc";
+
+/// Counter to give a distinct exchange directory to each call in the process
+static NB_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+/// Run an ABC script on a network, such as `"dc2; rewrite"`
+///
+/// See [`run_abc_with_binary`].
+pub fn run_abc(aig: &mut Network, script: &str) -> Result<(), QuaighError> {
+    run_abc_with_binary(aig, script, Path::new(DEFAULT_ABC_BINARY))
+}
+
+/// Run an ABC script on a network, with a given ABC executable
+///
+/// The network is exchanged through binary AIGER files: ABC reads it, converts it to an And-Inverter
+/// Graph with `strash`, runs the script and writes the result back. As for
+/// [`write_aiger`], all flip-flops must be in the default clock domain, and their enable and reset
+/// are implemented in logic. The inputs and outputs are kept in the same order.
+pub fn run_abc_with_binary(
+    aig: &mut Network,
+    script: &str,
+    binary: &Path,
+) -> Result<(), QuaighError> {
+    if aig.clock_domains().iter().any(|c| *c != ClockId::default()) {
+        return Err(QuaighError::Unsupported(
+            "ABC only supports flip-flops on the rising edge of a single clock".to_owned(),
+        ));
+    }
+    let dir = std::env::temp_dir().join(format!(
+        "quaigh_abc_{}_{}",
+        std::process::id(),
+        NB_CALLS.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir_all(&dir).map_err(|e| QuaighError::io(&dir, e))?;
+    let ret = run_in_dir(aig, script, binary, &dir);
+    let _ = fs::remove_dir_all(&dir);
+    *aig = ret?;
+    Ok(())
+}
+
+/// Write the network, run ABC and read the result in an exchange directory
+fn run_in_dir(
+    aig: &Network,
+    script: &str,
+    binary: &Path,
+    dir: &Path,
+) -> Result<Network, QuaighError> {
+    let input: PathBuf = dir.join("input.aig");
+    let output: PathBuf = dir.join("output.aig");
+    let mut data = Vec::new();
+    write_aiger(&mut data, aig, true);
+    fs::write(&input, data).map_err(|e| QuaighError::io(&input, e))?;
+
+    let command = format!(
+        "read_aiger {}; strash; {}; write_aiger {}",
+        input.display(),
+        script.trim().trim_end_matches(';'),
+        output.display()
+    );
+    let res = Command::new(binary)
+        .arg("-c")
+        .arg(&command)
+        .output()
+        .map_err(|e| QuaighError::External {
+            program: binary.display().to_string(),
+            message: e.to_string(),
+        })?;
+    if !res.status.success() || !output.exists() {
+        let log = [&res.stdout, &res.stderr]
+            .iter()
+            .map(|l| String::from_utf8_lossy(l).trim().to_owned())
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        return Err(QuaighError::External {
+            program: binary.display().to_string(),
+            message: format!("script \"{}\" did not produce a network: {}", script, log),
+        });
+    }
+
+    let data = fs::read(&output).map_err(|e| QuaighError::io(&output, e))?;
+    let ret = read_aiger(data.as_slice()).map_err(|e| e.with_file(&output))?;
+    if ret.nb_inputs() != aig.nb_inputs() || ret.nb_outputs() != aig.nb_outputs() {
+        return Err(QuaighError::External {
+            program: binary.display().to_string(),
+            message: format!(
+                "the interface changed from {} inputs and {} outputs to {} inputs and {} outputs",
+                aig.nb_inputs(),
+                aig.nb_outputs(),
+                ret.nb_inputs(),
+                ret.nb_outputs()
+            ),
+        });
+    }
+    Ok(ret)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+
+    use super::run_abc_with_binary;
+    use crate::equiv::check_equivalence_bounded;
+    use crate::network::generators::testcases::random_seq;
+    use crate::QuaighError;
+
+    /// Write an executable script that stands in for ABC
+    fn fake_abc(dir: &Path, name: &str, body: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_exchange() {
+        let dir = std::env::temp_dir().join(format!("quaigh_abc_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // Copy the input to the output, as a script that does not change anything
+        let copy = fake_abc(
+            &dir,
+            "copy",
+            "in=$(echo \"$2\" | sed 's/^read_aiger \\([^;]*\\);.*/\\1/')\n\
+             out=$(echo \"$2\" | sed 's/.*write_aiger \\(.*\\)$/\\1/')\n\
+             cp \"$in\" \"$out\"",
+        );
+        let fail = fake_abc(
+            &dir,
+            "fail",
+            "echo \"** cmd error: unknown command\"\nexit 1",
+        );
+
+        let aig = random_seq(4, 2, 10, 3, 1);
+        let mut res = aig.clone();
+        run_abc_with_binary(&mut res, "dc2; rewrite;", &copy).unwrap();
+        assert_eq!(res.nb_inputs(), 4);
+        assert_eq!(res.nb_outputs(), 3);
+        check_equivalence_bounded(&aig, &res, 2, false).unwrap();
+
+        let mut res = aig.clone();
+        let err = run_abc_with_binary(&mut res, "foo", &fail).unwrap_err();
+        assert!(matches!(err, QuaighError::External { .. }), "{:?}", err);
+        assert!(err.to_string().contains("unknown command"), "{}", err);
+        assert_eq!(format!("{res:?}"), format!("{aig:?}"));
+
+        let missing = dir.join("missing");
+        let err = run_abc_with_binary(&mut res, "dc2", &missing).unwrap_err();
+        assert!(matches!(err, QuaighError::External { .. }), "{:?}", err);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
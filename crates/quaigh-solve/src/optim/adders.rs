@@ -0,0 +1,263 @@
+//! Recognition of ripple-carry adders, and resynthesis as carry-lookahead adders
+//!
+//! A full adder is a Maj and a Xor3 gate with the same inputs. Full adders where the carry of
+//! one is an input of the next, and is not used elsewhere, form a ripple-carry adder of two
+//! words with a carry in. Its depth is linear in the number of bits, and the adder is rebuilt
+//! with a parallel prefix structure to obtain a logarithmic depth.
+
+use std::collections::HashMap;
+
+use crate::network::generators::adder::add_carry_lookahead;
+use crate::network::matcher::Matcher;
+use crate::{Gate, Network, Signal};
+
+fn maj_pattern() -> Network {
+    let mut pattern = Network::new();
+    pattern.add_inputs(3);
+    let o = pattern.add(Gate::maj(
+        pattern.input(0),
+        pattern.input(1),
+        pattern.input(2),
+    ));
+    pattern.add_output(o);
+    pattern
+}
+
+fn xor3_pattern() -> Network {
+    let mut pattern = Network::new();
+    pattern.add_inputs(3);
+    let o = pattern.add(Gate::xor3(
+        pattern.input(0),
+        pattern.input(1),
+        pattern.input(2),
+    ));
+    pattern.add_output(o);
+    pattern
+}
+
+/// Inputs of a 3-input gate without their inversion, sorted, and the parity of the inversions
+fn normalized_inputs(v: &[Signal]) -> ([Signal; 3], bool) {
+    let mut key = [
+        v[0].without_inversion(),
+        v[1].without_inversion(),
+        v[2].without_inversion(),
+    ];
+    key.sort();
+    let parity = v.iter().fold(false, |p, s| p ^ s.is_inverted());
+    (key, parity)
+}
+
+/// A full adder found in the network
+struct FullAdder {
+    /// Inputs of the full adder
+    inputs: Vec<Signal>,
+    /// Maj node computing the carry
+    carry: usize,
+    /// Xor3 node computing the sum, up to an inversion
+    sum: usize,
+    /// Whether the Xor3 node computes the complement of the sum
+    sum_inverted: bool,
+}
+
+/// Find the full adders of the network
+fn find_full_adders(aig: &Network) -> Vec<FullAdder> {
+    let xor3 = xor3_pattern();
+    let mut matcher = Matcher::from_pattern(&xor3);
+    let mut xors = HashMap::new();
+    for i in 0..aig.nb_nodes() {
+        if let Some(v) = matcher.matches(aig, i) {
+            xors.insert(normalized_inputs(&v).0, (i, normalized_inputs(&v).1));
+        }
+    }
+
+    let maj = maj_pattern();
+    let mut matcher = Matcher::from_pattern(&maj);
+    let mut ret = Vec::new();
+    for i in 0..aig.nb_nodes() {
+        if let Some(v) = matcher.matches(aig, i) {
+            let (key, parity) = normalized_inputs(&v);
+            if let Some((x, xor_parity)) = xors.get(&key) {
+                ret.push(FullAdder {
+                    inputs: v,
+                    carry: i,
+                    sum: *x,
+                    sum_inverted: parity ^ xor_parity,
+                });
+            }
+        }
+    }
+    ret
+}
+
+/// Number of uses of each node, by gates and outputs
+fn count_uses(aig: &Network) -> Vec<usize> {
+    let mut uses = vec![0; aig.nb_nodes()];
+    for i in 0..aig.nb_nodes() {
+        for v in aig.gate(i).vars() {
+            uses[v as usize] += 1;
+        }
+    }
+    for o in 0..aig.nb_outputs() {
+        let s = aig.output(o);
+        if s.is_var() {
+            uses[s.var() as usize] += 1;
+        }
+    }
+    uses
+}
+
+/// Find the chains of full adders, from the least significant bit, with the carry in of each
+fn find_chains(aig: &Network, adders: &[FullAdder]) -> Vec<Vec<(usize, Signal)>> {
+    let uses = count_uses(aig);
+    let by_carry: HashMap<usize, usize> = adders
+        .iter()
+        .enumerate()
+        .map(|(k, fa)| (fa.carry, k))
+        .collect();
+    let mut pred: Vec<Option<(usize, Signal)>> = vec![None; adders.len()];
+    let mut succ: Vec<Option<usize>> = vec![None; adders.len()];
+    for (k, fa) in adders.iter().enumerate() {
+        // The carry in must be the carry of another full adder, only used by this one
+        for s in &fa.inputs {
+            if !s.is_var() {
+                continue;
+            }
+            let Some(&p) = by_carry.get(&(s.var() as usize)) else {
+                continue;
+            };
+            if uses[s.var() as usize] == 2 && succ[p].is_none() {
+                pred[k] = Some((p, *s));
+                succ[p] = Some(k);
+                break;
+            }
+        }
+    }
+
+    let mut chains = Vec::new();
+    for (start, fa) in adders.iter().enumerate() {
+        if pred[start].is_some() {
+            continue;
+        }
+        let mut chain = vec![(start, *fa.inputs.last().unwrap())];
+        let mut k = start;
+        while let Some(next) = succ[k] {
+            chain.push((next, pred[next].unwrap().1));
+            k = next;
+        }
+        chains.push(chain);
+    }
+    chains
+}
+
+/// Replace ripple-carry adders by carry-lookahead adders to reduce logic depth
+///
+/// Only chains of at least `min_len` full adders are replaced. Returns the number of adders
+/// replaced.
+pub fn resynthesize_adders(aig: &mut Network, min_len: usize) -> usize {
+    aig.make_canonical();
+    let adders = find_full_adders(aig);
+    let chains = find_chains(aig, &adders);
+    let mut nb_replaced = 0;
+    for chain in chains {
+        if chain.len() < min_len.max(1) {
+            continue;
+        }
+        // The carry of a full adder may be used inverted by the next one: by self-duality, this
+        // is a full adder of the inverted operands, whose outputs are inverted
+        let mut a = Vec::new();
+        let mut b = Vec::new();
+        let mut flips = Vec::new();
+        let mut carry_flipped = false;
+        for (pos, (k, carry_in)) in chain.iter().enumerate() {
+            let flip = pos > 0 && (carry_flipped ^ carry_in.is_inverted());
+            let mut operands = adders[*k].inputs.clone();
+            let i = operands.iter().position(|s| s == carry_in).unwrap();
+            operands.remove(i);
+            a.push(operands[0] ^ flip);
+            b.push(operands[1] ^ flip);
+            flips.push(flip);
+            carry_flipped = flip;
+        }
+        let (sum, carry_out) = add_carry_lookahead(aig, &a, &b, chain[0].1);
+        for (((k, _), s), flip) in chain.iter().zip(sum).zip(flips) {
+            let fa = &adders[*k];
+            aig.replace(fa.sum, Gate::Buf(s ^ fa.sum_inverted ^ flip));
+        }
+        let last = &adders[chain.last().unwrap().0];
+        aig.replace(last.carry, Gate::Buf(carry_out ^ carry_flipped));
+        nb_replaced += 1;
+    }
+    aig.topo_sort();
+    aig.make_canonical();
+    aig.cleanup();
+    nb_replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resynthesize_adders;
+    use crate::equiv::check_equivalence_comb;
+    use crate::network::depth::depth;
+    use crate::network::generators::{adder, multiplier};
+    use crate::sim::simulate_comb_multi;
+    use crate::{Gate, Network};
+
+    #[test]
+    fn test_ripple_carry() {
+        let orig = adder::ripple_carry(16);
+        let mut aig = orig.clone();
+        assert_eq!(resynthesize_adders(&mut aig, 4), 1);
+        assert!(depth(&aig) <= 12);
+        let mut x = 1u64;
+        for _ in 0..16 {
+            let v: Vec<u64> = (0..orig.nb_inputs())
+                .map(|_| {
+                    x = x
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    x
+                })
+                .collect();
+            assert_eq!(
+                simulate_comb_multi(&orig, &v),
+                simulate_comb_multi(&aig, &v)
+            );
+        }
+
+        let orig = adder::ripple_carry(6);
+        let mut aig = orig.clone();
+        assert_eq!(resynthesize_adders(&mut aig, 4), 1);
+        check_equivalence_comb(&orig, &aig, false).unwrap();
+
+        // Too short to be replaced
+        let mut aig = orig.clone();
+        assert_eq!(resynthesize_adders(&mut aig, 8), 0);
+        assert_eq!(depth(&aig), depth(&orig));
+    }
+
+    #[test]
+    fn test_inverted() {
+        // Adder with inverted operands and an inverted sum
+        let mut aig = Network::new();
+        aig.add_inputs(8);
+        let mut c = aig.input(0);
+        for i in 0..3 {
+            let (a, b) = (aig.input(2 * i + 1), !aig.input(2 * i + 2));
+            let s = aig.add(Gate::xor3(a, b, c));
+            c = aig.add(Gate::maj(a, b, c));
+            aig.add_output(!s);
+        }
+        aig.add_output(c);
+        let orig = aig.clone();
+        assert_eq!(resynthesize_adders(&mut aig, 3), 1);
+        check_equivalence_comb(&orig, &aig, false).unwrap();
+    }
+
+    #[test]
+    fn test_multiplier() {
+        let orig = multiplier::array(4);
+        let mut aig = orig.clone();
+        resynthesize_adders(&mut aig, 2);
+        check_equivalence_comb(&orig, &aig, false).unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+//! Depth-oriented decomposition of N-input And and Xor gates
+//!
+//! Contrary to logic sharing, this pass decomposes each gate independently,
+//! always combining the two shallowest inputs first to minimize logic depth.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::network::depth::DelayParameters;
+use crate::optim::share_logic::flatten_nary;
+use crate::{Gate, Network, Signal};
+
+/// Arrival time of a signal, given the arrival times of the nodes and inputs
+fn signal_time(time: &[usize], input_time: &[usize], s: Signal) -> usize {
+    if s.is_input() {
+        input_time[s.input() as usize]
+    } else if s.is_var() {
+        time[s.var() as usize]
+    } else {
+        0
+    }
+}
+
+/// Build a tree of binary gates from the inputs, combining the earliest first
+fn build_tree<G: Fn(Signal, Signal) -> Gate>(
+    aig: &mut Network,
+    time: &mut Vec<usize>,
+    input_time: &[usize],
+    inputs: &[Signal],
+    builder: G,
+    delay: usize,
+) -> Signal {
+    let mut heap: BinaryHeap<_> = inputs
+        .iter()
+        .map(|s| Reverse((signal_time(time, input_time, *s), *s)))
+        .collect();
+    while heap.len() > 1 {
+        let Reverse((ta, a)) = heap.pop().unwrap();
+        let Reverse((tb, b)) = heap.pop().unwrap();
+        let s = aig.add(builder(a, b));
+        let t = std::cmp::max(ta, tb) + delay;
+        time.push(t);
+        heap.push(Reverse((t, s)));
+    }
+    heap.pop().unwrap().0 .1
+}
+
+/// Rebuild the selected N-input And and Xor gates of a flattened network as trees of 2-input
+/// gates, minimizing their arrival time
+///
+/// The new gates are added at the end of the network, which is not topologically sorted anymore.
+pub(crate) fn rebalance<F: Fn(usize) -> bool>(
+    aig: &mut Network,
+    delays: &DelayParameters,
+    input_time: &[usize],
+    select: F,
+) {
+    let nb_nodes = aig.nb_nodes();
+    let mut time = vec![0; nb_nodes];
+    for i in 0..nb_nodes {
+        let g = aig.gate(i).clone();
+        let max_input = g
+            .dependencies()
+            .iter()
+            .map(|s| signal_time(&time, input_time, *s))
+            .max()
+            .unwrap_or(0);
+        if !g.is_comb() {
+            time[i] = 0;
+        } else if g.dependencies().len() > 2 && (g.is_and() || g.is_xor()) && select(i) {
+            let s = if g.is_and() {
+                build_tree(
+                    aig,
+                    &mut time,
+                    input_time,
+                    g.dependencies(),
+                    Gate::and,
+                    delays.and,
+                )
+            } else {
+                build_tree(
+                    aig,
+                    &mut time,
+                    input_time,
+                    g.dependencies(),
+                    Gate::xor,
+                    delays.xor,
+                )
+            };
+            aig.replace(i, Gate::Buf(s));
+            time[i] = signal_time(&time, input_time, s);
+        } else if g.is_buf_like() {
+            time[i] = max_input;
+        } else {
+            time[i] = max_input + delays.gate_delay(&g);
+        }
+    }
+}
+
+/// Rebalance And and Xor gates to reduce logic depth
+///
+/// Gates are first flattened, then rebuilt as balanced trees of 2-input gates.
+/// This usually gives a shallower but larger network than [`share_logic`](super::share_logic).
+pub fn balance(aig: &mut Network, flattening_limit: usize) {
+    let mut ret = flatten_nary(aig, flattening_limit);
+    let input_time = vec![0; ret.nb_inputs()];
+    rebalance(&mut ret, &DelayParameters::unit(), &input_time, |_| true);
+    ret.topo_sort();
+    ret.make_canonical();
+    *aig = ret;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::balance;
+    use crate::equiv::check_equivalence_comb;
+    use crate::network::depth::depth;
+    use crate::Network;
+
+    #[test]
+    fn test_balance_chain() {
+        let mut aig = Network::new();
+        let mut x = aig.add_input();
+        for _ in 0..7 {
+            let i = aig.add_input();
+            x = aig.and(x, i);
+        }
+        aig.add_output(x);
+        let orig = aig.clone();
+        assert_eq!(depth(&aig), 7);
+        balance(&mut aig, 64);
+        assert_eq!(depth(&aig), 3);
+        assert_eq!(aig.nb_nodes(), 7);
+        check_equivalence_comb(&orig, &aig, false).unwrap();
+    }
+}
@@ -0,0 +1,554 @@
+//! Mapping of a network to generic cell libraries
+//!
+//! Generic libraries contain a few standard cells and need no Liberty file. They are meant for
+//! teaching, and for comparisons with academic flows that report results in two-input Nand or
+//! Nor gates. The network is first decomposed into two-input And gates, then each of them is
+//! covered by cells of the library. Cells are represented by ordinary gates:
+//!   * NAND2 and NOR2 are 2-input Nand and Nor gates;
+//!   * INV is a Not gate, or a Nand or Nor gate with both inputs tied together in the libraries
+//!     that only have one type of cell;
+//!   * AOI21, OAI21, AOI22 and OAI22 are Luts.
+//!
+//! Flip-flops and latches are kept as is. No signal of the mapped network is inverted, except at
+//! the input of Not gates.
+
+use volute::Lut;
+
+use crate::network::{BinaryType, NaryType, TernaryType};
+use crate::{Gate, Network, Signal};
+
+/// Generic target library for [`cell_map`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellLibrary {
+    /// Two-input Nand gates only
+    Nand2,
+    /// Two-input Nor gates only
+    Nor2,
+    /// Inverters, two-input Nand and Nor gates, and And-Or-Invert and Or-And-Invert gates
+    AoiOai,
+}
+
+/// Names of the And-Or-Invert and Or-And-Invert cells
+const COMPLEX_CELLS: [&str; 4] = ["AOI21", "OAI21", "AOI22", "OAI22"];
+
+/// Function of an And-Or-Invert or Or-And-Invert cell
+type CellFunction = fn(&[bool]) -> bool;
+
+/// Truth table of an And-Or-Invert or Or-And-Invert cell
+fn complex_cell_lut(name: &str) -> Lut {
+    let (n, f): (usize, CellFunction) = match name {
+        "AOI21" => (3, |v| !(v[0] && v[1] || v[2])),
+        "OAI21" => (3, |v| !((v[0] || v[1]) && v[2])),
+        "AOI22" => (4, |v| !(v[0] && v[1] || v[2] && v[3])),
+        "OAI22" => (4, |v| !((v[0] || v[1]) && (v[2] || v[3]))),
+        _ => panic!("Unknown cell {}", name),
+    };
+    let mut ret = Lut::zero(n);
+    for m in 0..1 << n {
+        let v: Vec<bool> = (0..n).map(|i| (m >> i) & 1 != 0).collect();
+        if f(&v) {
+            ret.set_bit(m);
+        }
+    }
+    ret
+}
+
+impl CellLibrary {
+    /// Names of the cells of the library
+    pub fn cells(&self) -> &'static [&'static str] {
+        match self {
+            CellLibrary::Nand2 => &["INV", "NAND2"],
+            CellLibrary::Nor2 => &["INV", "NOR2"],
+            CellLibrary::AoiOai => &["INV", "NAND2", "NOR2", "AOI21", "OAI21", "AOI22", "OAI22"],
+        }
+    }
+
+    /// Cell of the library implemented by a gate, if any
+    pub fn cell_of(&self, g: &Gate) -> Option<&'static str> {
+        let uninverted = |s: &[Signal]| s.iter().all(|s| !s.is_inverted());
+        let name = match (self, g) {
+            (CellLibrary::Nand2, Gate::Nary(v, NaryType::Nand))
+            | (CellLibrary::Nor2, Gate::Nary(v, NaryType::Nor))
+                if v.len() == 2 && uninverted(v) =>
+            {
+                if v[0] == v[1] {
+                    "INV"
+                } else if *self == CellLibrary::Nand2 {
+                    "NAND2"
+                } else {
+                    "NOR2"
+                }
+            }
+            (CellLibrary::AoiOai, Gate::Buf(s)) if s.is_inverted() && !s.is_constant() => "INV",
+            (CellLibrary::AoiOai, Gate::Nary(v, tp)) if v.len() == 2 && uninverted(v) => match tp {
+                NaryType::Nand => "NAND2",
+                NaryType::Nor => "NOR2",
+                _ => return None,
+            },
+            (CellLibrary::AoiOai, Gate::Lut(lut)) if uninverted(&lut.inputs) => COMPLEX_CELLS
+                .iter()
+                .find(|name| complex_cell_lut(name) == lut.lut)?,
+            _ => return None,
+        };
+        Some(name)
+    }
+
+    /// Number of instances of each cell of the library in a network, in the order of
+    /// [`cells`](CellLibrary::cells)
+    pub fn cell_counts(&self, aig: &Network) -> Vec<(&'static str, usize)> {
+        let mut ret: Vec<(&'static str, usize)> = self.cells().iter().map(|c| (*c, 0)).collect();
+        for (_, g) in aig.iter_comb() {
+            if let Some(name) = self.cell_of(&g) {
+                ret.iter_mut().find(|(c, _)| *c == name).unwrap().1 += 1;
+            }
+        }
+        ret
+    }
+
+    /// Returns whether all combinatorial gates of the network are cells of the library
+    pub fn is_mapped(&self, aig: &Network) -> bool {
+        aig.iter_comb().all(|(_, g)| self.cell_of(&g).is_some())
+    }
+
+    /// Add an inverter
+    fn inv(&self, aig: &mut Network, s: Signal) -> Signal {
+        match self {
+            CellLibrary::Nand2 => aig.add(Gate::Nary([s, s].into(), NaryType::Nand)),
+            CellLibrary::Nor2 => aig.add(Gate::Nary([s, s].into(), NaryType::Nor)),
+            CellLibrary::AoiOai => aig.add(Gate::Buf(!s)),
+        }
+    }
+}
+
+/// Translate a signal to the new network
+fn translate(s: &Signal, translation: &[Signal]) -> Signal {
+    if s.is_var() {
+        translation[s.var() as usize] ^ s.is_inverted()
+    } else {
+        *s
+    }
+}
+
+fn and_all(aig: &mut Network, v: &[Signal]) -> Signal {
+    match v {
+        [] => Signal::one(),
+        [a] => *a,
+        _ => {
+            let (a, b) = v.split_at(v.len() / 2);
+            let a = and_all(aig, a);
+            let b = and_all(aig, b);
+            aig.and(a, b)
+        }
+    }
+}
+
+fn or2(aig: &mut Network, a: Signal, b: Signal) -> Signal {
+    !aig.and(!a, !b)
+}
+
+fn mux(aig: &mut Network, s: Signal, a: Signal, b: Signal) -> Signal {
+    let x = aig.and(s, a);
+    let y = aig.and(!s, b);
+    or2(aig, x, y)
+}
+
+fn xor_all(aig: &mut Network, v: &[Signal]) -> Signal {
+    match v {
+        [] => Signal::zero(),
+        [a] => *a,
+        _ => {
+            let (a, b) = v.split_at(v.len() / 2);
+            let a = xor_all(aig, a);
+            let b = xor_all(aig, b);
+            mux(aig, a, !b, b)
+        }
+    }
+}
+
+/// Add a Lut, by Shannon expansion on its last inputs
+fn add_lut(aig: &mut Network, inputs: &[Signal], lut: &Lut) -> Signal {
+    let n = inputs.len();
+    if n == 0 {
+        return Signal::zero() ^ lut.value(0);
+    }
+    let mut f0 = Lut::zero(n - 1);
+    let mut f1 = Lut::zero(n - 1);
+    for m in 0..1 << (n - 1) {
+        if lut.value(m) {
+            f0.set_bit(m);
+        }
+        if lut.value(m | 1 << (n - 1)) {
+            f1.set_bit(m);
+        }
+    }
+    let a = add_lut(aig, &inputs[..n - 1], &f1);
+    let b = add_lut(aig, &inputs[..n - 1], &f0);
+    mux(aig, inputs[n - 1], a, b)
+}
+
+/// Copy the network, so that all combinatorial gates are 2-input And gates
+fn decompose(aig: &Network) -> Network {
+    let mut ret = Network::new();
+    ret.add_inputs(aig.nb_inputs());
+    let mut translation = Vec::with_capacity(aig.nb_nodes());
+    for (_, g) in aig.iter_gates() {
+        if g.is_dff() {
+            // Flip-flops may use later nodes, and are filled once all nodes are translated
+            translation.push(ret.add(Gate::Buf(Signal::zero())));
+            continue;
+        }
+        let v: Vec<Signal> = g
+            .dependencies()
+            .iter()
+            .map(|s| translate(s, &translation))
+            .collect();
+        let inv: Vec<Signal> = v.iter().map(|s| !s).collect();
+        let s = match g {
+            Gate::Dff(..) => unreachable!(),
+            Gate::Latch(_) => ret.add(g.remap(|s| translate(s, &translation))),
+            Gate::Binary(_, BinaryType::And) | Gate::Ternary(_, TernaryType::And) => {
+                and_all(&mut ret, &v)
+            }
+            Gate::Binary(_, BinaryType::Xor) | Gate::Ternary(_, TernaryType::Xor) => {
+                xor_all(&mut ret, &v)
+            }
+            Gate::Ternary(_, TernaryType::Mux) => mux(&mut ret, v[0], v[1], v[2]),
+            Gate::Ternary(_, TernaryType::Maj) => {
+                let ab = ret.and(v[0], v[1]);
+                let a_or_b = or2(&mut ret, v[0], v[1]);
+                let c = ret.and(v[2], a_or_b);
+                or2(&mut ret, ab, c)
+            }
+            Gate::Nary(_, tp) => match tp {
+                NaryType::And => and_all(&mut ret, &v),
+                NaryType::Nand => !and_all(&mut ret, &v),
+                NaryType::Or => !and_all(&mut ret, &inv),
+                NaryType::Nor => and_all(&mut ret, &inv),
+                NaryType::Xor => xor_all(&mut ret, &v),
+                NaryType::Xnor => !xor_all(&mut ret, &v),
+            },
+            Gate::Buf(_) => v[0],
+            Gate::Lut(lut) => add_lut(&mut ret, &v, &lut.lut),
+        };
+        translation.push(s);
+    }
+    for (i, g) in aig.iter_dffs() {
+        let node = translation[i.var() as usize].var() as usize;
+        ret.replace(node, g.remap(|s| translate(s, &translation)));
+    }
+    for o in 0..aig.nb_outputs() {
+        ret.add_output(translate(&aig.output(o), &translation));
+        ret.set_output_kind(o, aig.output_kind(o));
+    }
+    ret.deduplicate();
+    ret.cleanup();
+    ret
+}
+
+/// Polarities in which each signal of the decomposed network is needed
+///
+/// Index 0 is the signal itself, and index 1 its complement.
+struct Demand {
+    nodes: Vec<[bool; 2]>,
+    inputs: Vec<[bool; 2]>,
+}
+
+impl Demand {
+    fn add(&mut self, s: Signal) {
+        let pol = s.is_inverted() as usize;
+        if s.is_input() {
+            self.inputs[s.input() as usize][pol] = true;
+        } else if s.is_var() {
+            self.nodes[s.var() as usize][pol] = true;
+        }
+    }
+}
+
+/// Covering of a network of 2-input And gates by the cells of a library
+struct Mapper<'a> {
+    aig: &'a Network,
+    library: CellLibrary,
+    /// Whether each node is merged into the complex cell of its single fanout
+    absorbed: Vec<bool>,
+    /// Mapped signals for each node, then for each input
+    nodes: Vec<[Signal; 2]>,
+    inputs: Vec<[Signal; 2]>,
+}
+
+impl<'a> Mapper<'a> {
+    fn new(aig: &'a Network, library: CellLibrary) -> Self {
+        let mut refs = vec![0; aig.nb_nodes()];
+        for (_, g) in aig.iter_gates() {
+            for v in g.vars() {
+                refs[v as usize] += 1;
+            }
+        }
+        for o in aig.outputs() {
+            if o.is_var() {
+                refs[o.var() as usize] += 1;
+            }
+        }
+        let absorbed = aig
+            .iter_gates()
+            .map(|(s, g)| {
+                library == CellLibrary::AoiOai && g.is_comb() && refs[s.var() as usize] == 1
+            })
+            .collect();
+        Mapper {
+            aig,
+            library,
+            absorbed,
+            nodes: vec![[Signal::zero(); 2]; aig.nb_nodes()],
+            inputs: vec![[Signal::zero(); 2]; aig.nb_inputs()],
+        }
+    }
+
+    /// Inputs of a node, that must be a 2-input And gate
+    fn and_inputs(&self, s: Signal) -> [Signal; 2] {
+        match &self.aig.gate(s.var() as usize) {
+            Gate::Binary([a, b], BinaryType::And) => [*a, *b],
+            g => panic!("Gate {} should have been decomposed", g),
+        }
+    }
+
+    /// Returns whether a signal is the complement of a node merged into its fanout
+    fn is_absorbed(&self, s: Signal) -> bool {
+        s.is_var() && s.is_inverted() && self.absorbed[s.var() as usize]
+    }
+
+    /// Signals used by the cell implementing a polarity of an And gate
+    ///
+    /// For the And-Or-Invert library, the complement is a Nand or Or-And-Invert cell, and the
+    /// node itself a Nor or And-Or-Invert cell, or an inverter if both are needed.
+    fn cell_inputs(&self, s: Signal, negative: bool, both: bool) -> Vec<Signal> {
+        let [x, y] = self.and_inputs(s);
+        match self.library {
+            CellLibrary::Nand2 => {
+                if negative {
+                    vec![x, y]
+                } else {
+                    vec![!s]
+                }
+            }
+            CellLibrary::Nor2 => {
+                if negative {
+                    vec![s]
+                } else {
+                    vec![!x, !y]
+                }
+            }
+            CellLibrary::AoiOai => {
+                if !negative && both {
+                    return vec![!s];
+                }
+                // Absorbed And gates come first, as the And or Or part of the complex cell
+                let mut ret = Vec::new();
+                for z in [x, y] {
+                    if self.is_absorbed(z) {
+                        let [p, q] = self.and_inputs(!z);
+                        if negative {
+                            ret.extend([!p, !q]);
+                        } else {
+                            ret.extend([p, q]);
+                        }
+                    }
+                }
+                for z in [x, y] {
+                    if !self.is_absorbed(z) {
+                        ret.push(if negative { z } else { !z });
+                    }
+                }
+                ret
+            }
+        }
+    }
+
+    /// Polarities needed for each signal, from the outputs and the sequential elements
+    fn demand(&self) -> Demand {
+        let mut d = Demand {
+            nodes: vec![[false; 2]; self.aig.nb_nodes()],
+            inputs: vec![[false; 2]; self.aig.nb_inputs()],
+        };
+        for o in self.aig.outputs() {
+            d.add(o);
+        }
+        for (_, g) in self.aig.iter_gates() {
+            if !g.is_comb() {
+                for s in g.dependencies() {
+                    d.add(*s);
+                }
+            }
+        }
+        for (s, g) in self.aig.iter_gates().rev() {
+            let i = s.var() as usize;
+            if !g.is_comb() {
+                continue;
+            }
+            // The inverter of the Nand2 and Nor2 libraries uses the other polarity
+            let order = match self.library {
+                CellLibrary::Nor2 => [true, false],
+                _ => [false, true],
+            };
+            for negative in order {
+                if d.nodes[i][negative as usize] {
+                    let both = d.nodes[i][0] && d.nodes[i][1];
+                    for t in self.cell_inputs(s, negative, both) {
+                        d.add(t);
+                    }
+                }
+            }
+        }
+        d
+    }
+
+    /// Mapped signal
+    fn get(&self, s: Signal) -> Signal {
+        let pol = s.is_inverted() as usize;
+        if s.is_input() {
+            self.inputs[s.input() as usize][pol]
+        } else if s.is_var() {
+            self.nodes[s.var() as usize][pol]
+        } else {
+            s
+        }
+    }
+
+    /// Add the cell implementing a polarity of an And gate
+    fn add_cell(&self, ret: &mut Network, s: Signal, negative: bool, both: bool) -> Signal {
+        let v: Vec<Signal> = self
+            .cell_inputs(s, negative, both)
+            .into_iter()
+            .map(|t| self.get(t))
+            .collect();
+        let lib = self.library;
+        match (lib, negative, v.len()) {
+            (CellLibrary::Nand2, false, _) | (CellLibrary::Nor2, true, _) => lib.inv(ret, v[0]),
+            (CellLibrary::AoiOai, false, 1) => lib.inv(ret, v[0]),
+            (_, true, 2) => ret.add(Gate::Nary(v.into(), NaryType::Nand)),
+            (_, false, 2) => ret.add(Gate::Nary(v.into(), NaryType::Nor)),
+            (_, true, 3) => ret.add(Gate::lut(&v, complex_cell_lut("OAI21"))),
+            (_, false, 3) => ret.add(Gate::lut(&v, complex_cell_lut("AOI21"))),
+            (_, true, 4) => ret.add(Gate::lut(&v, complex_cell_lut("OAI22"))),
+            (_, false, 4) => ret.add(Gate::lut(&v, complex_cell_lut("AOI22"))),
+            _ => unreachable!(),
+        }
+    }
+
+    fn run(&mut self) -> Network {
+        let d = self.demand();
+        let lib = self.library;
+        let mut ret = Network::new();
+        ret.add_inputs(self.aig.nb_inputs());
+        for i in 0..self.aig.nb_inputs() {
+            let s = ret.input(i);
+            let n = if d.inputs[i][1] {
+                lib.inv(&mut ret, s)
+            } else {
+                !s
+            };
+            self.inputs[i] = [s, n];
+        }
+        for (s, g) in self.aig.iter_gates() {
+            let i = s.var() as usize;
+            let [pos, neg] = d.nodes[i];
+            if !g.is_comb() {
+                let s = if g.is_dff() {
+                    // Flip-flops may use later nodes, and are filled once all nodes are mapped
+                    ret.add(Gate::Buf(Signal::zero()))
+                } else {
+                    ret.add(g.remap(|t| self.get(*t)))
+                };
+                let n = if neg { lib.inv(&mut ret, s) } else { !s };
+                self.nodes[i] = [s, n];
+                continue;
+            }
+            let both = pos && neg;
+            let order = match lib {
+                CellLibrary::Nor2 => [false, true],
+                _ => [true, false],
+            };
+            for negative in order {
+                if d.nodes[i][negative as usize] {
+                    let t = self.add_cell(&mut ret, s, negative, both);
+                    self.nodes[i][negative as usize] = t;
+                }
+            }
+        }
+        for (s, g) in self.aig.iter_dffs() {
+            let node = self.nodes[s.var() as usize][0].var() as usize;
+            ret.replace(node, g.remap(|t| self.get(*t)));
+        }
+        for (o, s) in self.aig.outputs().enumerate() {
+            ret.add_output(self.get(s));
+            ret.set_output_kind(o, self.aig.output_kind(o));
+        }
+        ret
+    }
+}
+
+/// Map a network to a generic cell library
+///
+/// The result only uses the cells of the library for its combinatorial logic, as given by
+/// [`CellLibrary::cell_of`], and no signal is inverted except at the input of Not gates.
+/// Flip-flops and latches are kept as is, and constants are used directly.
+pub fn cell_map(aig: &Network, library: CellLibrary) -> Network {
+    let mut canonical = aig.clone();
+    canonical.topo_sort();
+    canonical.make_canonical();
+    canonical.cleanup();
+    let decomposed = decompose(&canonical);
+    Mapper::new(&decomposed, library).run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cell_map, CellLibrary};
+    use crate::equiv::{check_equivalence_bounded, check_equivalence_comb};
+    use crate::network::generators::adder;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_comb() {
+        let aig = adder::ripple_carry(4);
+        for library in [CellLibrary::Nand2, CellLibrary::Nor2, CellLibrary::AoiOai] {
+            let mapped = cell_map(&aig, library);
+            mapped.check();
+            assert!(library.is_mapped(&mapped), "{:?}", library);
+            assert!(mapped
+                .outputs()
+                .all(|s| !s.is_inverted() || s.is_constant()));
+            let nb_cells: usize = library.cell_counts(&mapped).iter().map(|c| c.1).sum();
+            assert_eq!(nb_cells, mapped.nb_nodes());
+            assert!(check_equivalence_comb(&aig, &mapped, false).is_ok());
+        }
+        let nand = cell_map(&aig, CellLibrary::Nand2).nb_nodes();
+        let aoi = cell_map(&aig, CellLibrary::AoiOai).nb_nodes();
+        assert!(aoi < nand);
+    }
+
+    #[test]
+    fn test_seq() {
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        let x = aig.add(Gate::mux(a, b, !c));
+        let q = aig.add(Gate::dff(!x, !a, Signal::zero()));
+        let y = aig.add(Gate::maj(q, !b, c));
+        let l = aig.add(Gate::latch(!y, b, Signal::zero()));
+        aig.add_output(!q);
+        aig.add_output(y);
+        aig.add_output(l);
+        aig.add_output(!a);
+        for library in [CellLibrary::Nand2, CellLibrary::Nor2, CellLibrary::AoiOai] {
+            let mapped = cell_map(&aig, library);
+            mapped.check();
+            assert!(library.is_mapped(&mapped), "{:?}", library);
+            for (_, g) in mapped.iter_gates() {
+                if !g.is_comb() {
+                    assert!(g.dependencies().iter().all(|s| !s.is_inverted()));
+                }
+            }
+            assert!(check_equivalence_bounded(&aig, &mapped, 3, false).is_ok());
+        }
+    }
+}
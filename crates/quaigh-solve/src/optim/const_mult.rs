@@ -0,0 +1,393 @@
+//! Strength reduction of multipliers by a constant
+//!
+//! A full multiplier whose second operand is tied to a constant is still large after constant
+//! propagation. This pass recognizes such multipliers functionally and rebuilds them as a
+//! sequence of shifts and additions, using the canonical signed digit representation of the
+//! constant: for example, a multiplication by 255 becomes `(x << 8) - x`.
+
+use std::fmt;
+
+use rand::Rng;
+
+use crate::equiv::check_equivalence_comb;
+use crate::network::area::AreaParameters;
+use crate::optim::words::{add, shift_left, sub};
+use crate::sim::simulate_comb_multi;
+use crate::util::rng::SeededRng;
+use crate::{Gate, Network, Signal};
+
+/// Number of 64b words of random simulation used to recognize a multiplier
+const NB_SIMULATION_WORDS: usize = 4;
+
+/// A multiplier by a constant that was replaced by shifts and additions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstantMultiplier {
+    /// Primary inputs of the operand, least significant bit first
+    pub inputs: Vec<usize>,
+    /// Primary outputs of the product, least significant bit first
+    pub outputs: Vec<usize>,
+    /// Value of the constant
+    pub constant: u64,
+    /// Signed digits of the constant, least significant first
+    pub digits: Vec<i8>,
+    /// Area of the multiplier before the replacement
+    pub area_before: usize,
+    /// Area of the shift-add network
+    pub area_after: usize,
+}
+
+impl fmt::Display for ConstantMultiplier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let terms: Vec<String> = self
+            .digits
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| **d != 0)
+            .map(|(i, d)| format!("{}(x << {})", if *d > 0 { "+" } else { "-" }, i))
+            .collect();
+        write!(
+            f,
+            "Multiplier by {} ({} bits to {} bits) replaced by {}; area {} -> {}",
+            self.constant,
+            self.inputs.len(),
+            self.outputs.len(),
+            terms.join(" "),
+            self.area_before,
+            self.area_after
+        )
+    }
+}
+
+/// Canonical signed digit representation of a constant, least significant digit first
+///
+/// No two consecutive digits are non-zero, which minimizes the number of additions.
+pub fn csd_digits(c: u64) -> Vec<i8> {
+    let mut c = c as u128;
+    let mut ret = Vec::new();
+    while c != 0 {
+        if c & 1 == 0 {
+            ret.push(0);
+        } else if c & 2 == 0 {
+            ret.push(1);
+            c -= 1;
+        } else {
+            ret.push(-1);
+            c += 1;
+        }
+        c >>= 1;
+    }
+    ret
+}
+
+/// A candidate multiplier: a contiguous range of outputs and the inputs they depend on
+struct Candidate {
+    /// Primary inputs, in the order of the candidate's inputs
+    inputs: Vec<usize>,
+    /// Primary outputs, in the order of the candidate's outputs
+    outputs: Vec<usize>,
+    /// The extracted network
+    network: Network,
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Group the outputs that share logic or inputs into candidate multipliers
+///
+/// Each group must be a contiguous range of outputs, extended with the constant zero outputs
+/// just below it, that correspond to the trailing zeros of the constant.
+fn find_candidates(aig: &Network) -> Vec<Candidate> {
+    let nb_inputs = aig.nb_inputs();
+    let index = |s: Signal| -> Option<usize> {
+        if s.is_input() {
+            Some(s.input() as usize)
+        } else if s.is_var() {
+            Some(nb_inputs + s.var() as usize)
+        } else {
+            None
+        }
+    };
+    let mut parent: Vec<usize> = (0..nb_inputs + aig.nb_nodes()).collect();
+    for i in 0..aig.nb_nodes() {
+        for s in aig.dependencies(i) {
+            if let Some(j) = index(*s) {
+                let a = find_root(&mut parent, nb_inputs + i);
+                let b = find_root(&mut parent, j);
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+    for o in 0..aig.nb_outputs() {
+        let Some(j) = index(aig.output(o)) else {
+            continue;
+        };
+        let root = find_root(&mut parent, j);
+        match groups.iter_mut().find(|(r, _)| *r == root) {
+            Some((_, outputs)) => outputs.push(o),
+            None => groups.push((root, vec![o])),
+        }
+    }
+
+    let mut ret = Vec::new();
+    for (root, group) in groups {
+        let mut lo = group[0];
+        let hi = *group.last().unwrap();
+        while lo > 0 && aig.output(lo - 1) == Signal::zero() {
+            lo -= 1;
+        }
+        let contiguous = (lo..=hi).all(|o| group.contains(&o) || aig.output(o) == Signal::zero());
+        if !contiguous {
+            continue;
+        }
+        let inputs: Vec<usize> = (0..nb_inputs)
+            .filter(|i| find_root(&mut parent, *i) == root)
+            .collect();
+        let nodes: Vec<usize> = (0..aig.nb_nodes())
+            .filter(|i| find_root(&mut parent, nb_inputs + i) == root)
+            .collect();
+
+        let mut network = Network::new();
+        network.add_inputs(inputs.len());
+        let mut translation = vec![Signal::zero(); aig.nb_nodes()];
+        for (j, i) in nodes.iter().enumerate() {
+            translation[*i] = Signal::from_var(j as u32);
+        }
+        let t = |s: &Signal| -> Signal {
+            if s.is_input() {
+                let j = inputs.binary_search(&(s.input() as usize)).unwrap();
+                Signal::from_input(j as u32) ^ s.is_inverted()
+            } else if s.is_var() {
+                translation[s.var() as usize] ^ s.is_inverted()
+            } else {
+                *s
+            }
+        };
+        for i in &nodes {
+            network.add(aig.gate(*i).remap(t));
+        }
+        for o in lo..=hi {
+            network.add_output(t(&aig.output(o)));
+        }
+        ret.push(Candidate {
+            inputs,
+            outputs: (lo..=hi).collect(),
+            network,
+        });
+    }
+    ret
+}
+
+/// Gather the bits of a lane into an integer, least significant bit first
+fn lane_value(words: &[u64], lane: usize) -> u64 {
+    words
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (i, w)| acc | (((w >> lane) & 1) << i))
+}
+
+/// Find the constant by which a combinatorial network multiplies its inputs, if any
+///
+/// The inputs and outputs are read as unsigned integers, least significant bit first, and the
+/// product is taken modulo the number of outputs. The result is obtained by random simulation
+/// and is not proven.
+fn find_constant(aig: &Network) -> Option<u64> {
+    let (n, w) = (aig.nb_inputs(), aig.nb_outputs());
+    if !(2..=64).contains(&n) || !(2..=64).contains(&w) || !aig.is_comb() {
+        return None;
+    }
+    let mask = if w == 64 { u64::MAX } else { (1 << w) - 1 };
+    let mut rng = SeededRng::new(1);
+    let mut constant = None;
+    for _ in 0..NB_SIMULATION_WORDS {
+        let mut inputs: Vec<u64> = (0..n).map(|_| rng.gen()).collect();
+        // Lane 0 multiplies zero and lane 1 multiplies one
+        for (i, v) in inputs.iter_mut().enumerate() {
+            *v &= !3;
+            if i == 0 {
+                *v |= 2;
+            }
+        }
+        let outputs = simulate_comb_multi(aig, &inputs);
+        let c = *constant.get_or_insert(lane_value(&outputs, 1));
+        for lane in 0..64 {
+            let x = lane_value(&inputs, lane);
+            if lane_value(&outputs, lane) != x.wrapping_mul(c) & mask {
+                return None;
+            }
+        }
+    }
+    constant
+}
+
+/// Build a shift-add network multiplying its inputs by a constant
+fn build_shift_add(nb_inputs: usize, width: usize, digits: &[i8]) -> Network {
+    let mut ret = Network::new();
+    ret.add_inputs(nb_inputs);
+    let x: Vec<Signal> = (0..nb_inputs).map(|i| ret.input(i)).collect();
+    let terms = |sign: i8| -> Vec<Vec<Signal>> {
+        digits
+            .iter()
+            .enumerate()
+            .filter(|(_, d)| **d == sign)
+            .map(|(i, _)| shift_left(&x, i, width))
+            .collect()
+    };
+    let mut acc: Option<Vec<Signal>> = None;
+    for t in terms(1) {
+        acc = Some(match acc {
+            Some(a) => add(&mut ret, &a, &t, Signal::zero()),
+            None => t,
+        });
+    }
+    let mut acc = acc.unwrap_or_else(|| vec![Signal::zero(); width]);
+    for t in terms(-1) {
+        acc = sub(&mut ret, &acc, &t);
+    }
+    for s in acc {
+        ret.add_output(s);
+    }
+    ret.cleanup();
+    ret.make_canonical();
+    ret
+}
+
+/// Replace multipliers by a constant with shift-add networks
+///
+/// Each group of outputs that share logic or inputs is checked with random simulation: if it computes the
+/// product of its inputs by a constant, a shift-add network is built from the canonical signed
+/// digit representation of the constant. It replaces the original logic if it is proven
+/// equivalent with a Sat solver and is smaller. Groups containing Luts are skipped, as their area
+/// is not modeled. Returns the replacements made.
+pub fn reduce_constant_multipliers(aig: &mut Network) -> Vec<ConstantMultiplier> {
+    aig.make_canonical();
+    aig.cleanup();
+    aig.topo_sort();
+    let params = AreaParameters::vlsi();
+    let mut ret = Vec::new();
+    for c in find_candidates(aig) {
+        if (0..c.network.nb_nodes()).any(|i| matches!(&c.network.gate(i), Gate::Lut(_))) {
+            continue;
+        }
+        let Some(constant) = find_constant(&c.network) else {
+            continue;
+        };
+        let digits = csd_digits(constant);
+        let replacement = build_shift_add(c.inputs.len(), c.outputs.len(), &digits);
+        let area_before = params.area(&c.network);
+        let area_after = params.area(&replacement);
+        if area_after >= area_before
+            || check_equivalence_comb(&c.network, &replacement, false).is_err()
+        {
+            continue;
+        }
+
+        // Splice the replacement; the original logic is removed by the cleanup
+        let binding: Vec<Signal> = c.inputs.iter().map(|i| aig.input(*i)).collect();
+        let outputs = aig.insert_network(&replacement, &binding).unwrap();
+        for (o, s) in c.outputs.iter().zip(outputs) {
+            aig.set_output(*o, s);
+        }
+        ret.push(ConstantMultiplier {
+            inputs: c.inputs,
+            outputs: c.outputs,
+            constant,
+            digits,
+            area_before,
+            area_after,
+        });
+    }
+    if !ret.is_empty() {
+        aig.cleanup();
+        aig.make_canonical();
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{csd_digits, reduce_constant_multipliers};
+    use crate::equiv::check_equivalence_comb;
+    use crate::network::area::AreaParameters;
+    use crate::network::generators::multiplier;
+    use crate::optim::lut_map;
+    use crate::{Network, Signal};
+
+    /// An array multiplier whose second operand is tied to a constant
+    fn constant_multiplier(len: usize, c: u64) -> Network {
+        let mul = multiplier::array(len);
+        let mut ret = Network::new();
+        ret.add_inputs(len);
+        let t = |s: &Signal| -> Signal {
+            if !s.is_input() {
+                return *s;
+            }
+            let i = s.input() as usize;
+            let v = if i < len {
+                Signal::from_input(i as u32)
+            } else {
+                Signal::from((c >> (i - len)) & 1 != 0)
+            };
+            v ^ s.is_inverted()
+        };
+        for i in 0..mul.nb_nodes() {
+            ret.add(mul.gate(i).remap(t));
+        }
+        for o in 0..mul.nb_outputs() {
+            ret.add_output(t(&mul.output(o)));
+        }
+        ret
+    }
+
+    #[test]
+    fn test_csd() {
+        assert_eq!(csd_digits(10), vec![0, 1, 0, 1]);
+        assert_eq!(csd_digits(255), vec![-1, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(csd_digits(7), vec![-1, 0, 0, 1]);
+        for c in 0..1000u64 {
+            let v: i64 = csd_digits(c)
+                .iter()
+                .enumerate()
+                .map(|(i, d)| (*d as i64) << i)
+                .sum();
+            assert_eq!(v, c as i64);
+        }
+        assert_eq!(csd_digits(u64::MAX).len(), 65);
+    }
+
+    #[test]
+    fn test_reduce() {
+        let params = AreaParameters::vlsi();
+        for c in [10, 255] {
+            let orig = constant_multiplier(8, c);
+            let mut folded = orig.clone();
+            folded.make_canonical();
+            folded.cleanup();
+            let mut aig = orig.clone();
+            let report = reduce_constant_multipliers(&mut aig);
+            assert_eq!(report.len(), 1);
+            assert_eq!(report[0].constant, c);
+            assert_eq!(report[0].inputs, (0..8).collect::<Vec<_>>());
+            assert_eq!(report[0].outputs[0], 0);
+            assert!(params.area(&aig) < params.area(&folded));
+            assert!(check_equivalence_comb(&orig, &aig, false).is_ok());
+        }
+
+        // A general multiplier is not reduced
+        let mut aig = multiplier::array(4);
+        assert!(reduce_constant_multipliers(&mut aig).is_empty());
+    }
+
+    #[test]
+    fn test_luts() {
+        // Luts have no area in the model, so mapped multipliers are left alone
+        let mut aig = lut_map(&constant_multiplier(6, 10), 3);
+        assert!(reduce_constant_multipliers(&mut aig).is_empty());
+    }
+}
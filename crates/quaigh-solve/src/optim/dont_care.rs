@@ -0,0 +1,248 @@
+//! Simplification of nodes with don't cares
+//!
+//! A node may be replaced by a constant or by one of its inputs if the outputs of the network
+//! only change for input values where they are don't cares: either because the change is not
+//! observable at the outputs, or because of an external don't care network. Candidates are
+//! filtered by random simulation, then verified with a Sat solver on the whole network.
+//!
+//! Flip-flops are treated as free variables, and their inputs must be preserved exactly.
+
+use rand::Rng;
+
+use crate::equiv::prove;
+use crate::network::NaryType;
+use crate::sim::simulate_comb_multi;
+use crate::util::rng::SeededRng;
+use crate::{Gate, Network, Signal};
+
+/// Number of 64b words of random simulation used to filter the candidates
+const NB_WORDS: usize = 8;
+
+/// Combinational view of a network
+///
+/// Flip-flops become additional inputs, and their inputs become additional outputs.
+fn comb_view(aig: &Network) -> Network {
+    comb_view_with_translation(aig).0
+}
+
+/// Combinational view of a network, with the signal of the view corresponding to each node
+pub(crate) fn comb_view_with_translation(aig: &Network) -> (Network, Vec<Signal>) {
+    let dffs: Vec<usize> = (0..aig.nb_nodes())
+        .filter(|i| !aig.gate(*i).is_comb())
+        .collect();
+    let mut ret = Network::new();
+    ret.add_inputs(aig.nb_inputs() + dffs.len());
+    let mut t: Vec<Signal> = vec![Signal::zero(); aig.nb_nodes()];
+    for (j, i) in dffs.iter().enumerate() {
+        t[*i] = ret.input(aig.nb_inputs() + j);
+    }
+    let translate = |t: &Vec<Signal>, s: &Signal| {
+        if s.is_var() {
+            t[s.var() as usize] ^ s.is_inverted()
+        } else {
+            *s
+        }
+    };
+    for i in 0..aig.nb_nodes() {
+        let g = aig.gate(i);
+        if g.is_comb() {
+            t[i] = ret.add(g.remap(|s| translate(&t, s)));
+        }
+    }
+    for o in 0..aig.nb_outputs() {
+        ret.add_output(translate(&t, &aig.output(o)));
+    }
+    for i in dffs {
+        for s in aig.dependencies(i) {
+            ret.add_output(translate(&t, s));
+        }
+    }
+    (ret, t)
+}
+
+/// Don't care network for the combinational view
+///
+/// The external don't cares apply to the primary outputs. Flip-flop inputs are never don't cares.
+pub(crate) fn dont_care_view(view: &Network, nb_inputs: usize, exdc: Option<&Network>) -> Network {
+    let mut ret = Network::new();
+    ret.add_inputs(view.nb_inputs());
+    let inputs: Vec<Signal> = (0..nb_inputs).map(|i| ret.input(i)).collect();
+    let mut outputs = match exdc {
+        Some(exdc) => ret.insert_network(exdc, &inputs).unwrap(),
+        None => Vec::new(),
+    };
+    outputs.resize(view.nb_outputs(), Signal::zero());
+    for o in outputs {
+        ret.add_output(o);
+    }
+    ret
+}
+
+/// Check with a Sat solver that two combinational views agree whenever the outputs are not don't
+/// cares, and return a counterexample otherwise
+pub(crate) fn find_difference(a: &Network, b: &Network, dc: &Network) -> Option<Vec<bool>> {
+    let mut miter = Network::new();
+    miter.add_inputs(a.nb_inputs());
+    let inputs: Vec<Signal> = (0..a.nb_inputs()).map(|i| miter.input(i)).collect();
+    let oa = miter.insert_network(a, &inputs).unwrap();
+    let ob = miter.insert_network(b, &inputs).unwrap();
+    let odc = miter.insert_network(dc, &inputs).unwrap();
+    let mut diffs = Vec::new();
+    for ((sa, sb), sdc) in oa.iter().zip(ob.iter()).zip(odc.iter()) {
+        let x = miter.xor(*sa, *sb);
+        diffs.push(miter.and(x, !*sdc));
+    }
+    let out = miter.add_canonical(Gate::Nary(diffs.into(), NaryType::Or));
+    miter.add_output(out);
+    prove(&miter)
+}
+
+/// Check with simulation that two combinational views agree whenever the outputs are not don't
+/// cares
+fn same_on_patterns(a: &Network, b: &Network, dc: &Network, patterns: &[Vec<u64>]) -> bool {
+    patterns.iter().all(|p| {
+        let va = simulate_comb_multi(a, p);
+        let vb = simulate_comb_multi(b, p);
+        let vdc = simulate_comb_multi(dc, p);
+        (0..va.len()).all(|i| (va[i] ^ vb[i]) & !vdc[i] == 0)
+    })
+}
+
+/// Simplify the nodes of a network using its don't cares
+///
+/// Each node is replaced, if possible, by a constant or by one of its inputs, possibly inverted,
+/// as long as the outputs of the network only change where they are don't cares. Optionally,
+/// an external don't care network gives the input values where each output does not matter: it
+/// must be combinational, with the same number of inputs and outputs as the network.
+pub fn optimize_dont_cares(aig: &mut Network, exdc: Option<&Network>) {
+    optimize_dont_cares_with_rng(aig, exdc, &mut SeededRng::default());
+}
+
+/// Simplify the nodes of a network using its don't cares, with the simulation patterns taken
+/// from a given generator
+pub fn optimize_dont_cares_with_rng(
+    aig: &mut Network,
+    exdc: Option<&Network>,
+    rng: &mut SeededRng,
+) {
+    if let Some(exdc) = exdc {
+        assert!(exdc.is_comb());
+        assert_eq!(exdc.nb_inputs(), aig.nb_inputs());
+        assert_eq!(exdc.nb_outputs(), aig.nb_outputs());
+    }
+    aig.topo_sort();
+    aig.make_canonical();
+    aig.cleanup();
+
+    let mut view = comb_view(aig);
+    let dc = dont_care_view(&view, aig.nb_inputs(), exdc);
+    let mut patterns: Vec<Vec<u64>> = (0..NB_WORDS)
+        .map(|_| (0..view.nb_inputs()).map(|_| rng.gen()).collect())
+        .collect();
+
+    // Nodes are visited from the outputs, where observability don't cares are the largest
+    for n in (0..aig.nb_nodes()).rev() {
+        let g = aig.gate(n);
+        if !g.is_comb() || matches!(g, Gate::Buf(_)) {
+            continue;
+        }
+        let mut candidates = vec![Signal::zero(), Signal::one()];
+        for s in g.dependencies() {
+            candidates.push(*s);
+            candidates.push(!*s);
+        }
+        for c in candidates {
+            let mut modified = aig.clone();
+            modified.replace(n, Gate::Buf(c));
+            let modified_view = comb_view(&modified);
+            if !same_on_patterns(&view, &modified_view, &dc, &patterns) {
+                continue;
+            }
+            match find_difference(&view, &modified_view, &dc) {
+                None => {
+                    *aig = modified;
+                    view = modified_view;
+                    break;
+                }
+                Some(assignment) => {
+                    // Add the counterexample to the patterns, in the first bit of a new word
+                    let word = assignment
+                        .iter()
+                        .map(|b| (rng.gen::<u64>() & !1) | *b as u64)
+                        .collect();
+                    patterns.push(word);
+                }
+            }
+        }
+    }
+
+    aig.topo_sort();
+    aig.make_canonical();
+    aig.cleanup();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::optimize_dont_cares;
+    use crate::sim::simulate_comb;
+    use crate::{Gate, Network};
+
+    #[test]
+    fn test_observability() {
+        // The Xor is only observed when a & b is 1, where it is 0
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        let x = aig.add(Gate::xor(a, b));
+        let y = aig.add(Gate::and(a, b));
+        let z = aig.add(Gate::and(y, !x));
+        let o = aig.add(Gate::and(z, c));
+        aig.add_output(o);
+        let mut opt = aig.clone();
+        optimize_dont_cares(&mut opt, None);
+        opt.check();
+        assert_eq!(opt.nb_nodes(), 2);
+        for p in 0..8 {
+            let v: Vec<bool> = (0..3).map(|i| p >> i & 1 != 0).collect();
+            assert_eq!(simulate_comb(&aig, &v), simulate_comb(&opt, &v));
+        }
+    }
+
+    #[test]
+    fn test_external() {
+        // The output is a don't care when a is 0, so a & b can be replaced by b
+        let mut aig = Network::new();
+        aig.add_inputs(2);
+        let (a, b) = (aig.input(0), aig.input(1));
+        let x = aig.add(Gate::and(a, b));
+        aig.add_output(x);
+        let mut exdc = Network::new();
+        exdc.add_inputs(2);
+        exdc.add_output(!exdc.input(0));
+        let mut opt = aig.clone();
+        optimize_dont_cares(&mut opt, Some(&exdc));
+        assert_eq!(opt.nb_nodes(), 0);
+        assert_eq!(opt.output(0), opt.input(1));
+
+        // Without the external don't cares, nothing changes
+        let mut opt = aig.clone();
+        optimize_dont_cares(&mut opt, None);
+        assert_eq!(opt.nb_nodes(), 1);
+    }
+
+    #[test]
+    fn test_sequential() {
+        // The inputs of the flip-flops are preserved
+        let mut aig = Network::new();
+        aig.add_inputs(2);
+        let (a, b) = (aig.input(0), aig.input(1));
+        let x = aig.add(Gate::and(a, b));
+        let q = aig.add(Gate::dff(x, b, a));
+        let y = aig.add(Gate::and(q, a));
+        aig.add_output(y);
+        let mut opt = aig.clone();
+        optimize_dont_cares(&mut opt, None);
+        opt.check();
+        assert_eq!(opt.nb_nodes(), 3);
+    }
+}
@@ -0,0 +1,297 @@
+//! Exact synthesis of small functions with a Sat solver
+//!
+//! A function given by its truth table is implemented with the smallest number of 2-input gates,
+//! by asking a Sat solver whether a circuit of `k` gates exists for increasing values of `k`.
+//! The encoding follows Knuth and Kojevnikov et al.: each gate selects two operands among the
+//! inputs and the previous gates, and computes a function that is 0 when both operands are 0.
+//! Any other function is obtained by inverting the output. Rows of the truth table that are
+//! don't cares are simply left out of the formula.
+
+use rustsat::types::Lit;
+
+use crate::equiv::{lit_value, new_solver};
+use crate::{Gate, Network, Signal};
+
+/// Value of an operand of a gate for a row of the truth table
+#[derive(Clone, Copy)]
+enum Value {
+    Const(bool),
+    Var(Lit),
+}
+
+impl Value {
+    /// Literal that is true if the value is not `b`, or None if this is always false
+    fn differs(&self, b: bool) -> Option<Option<Lit>> {
+        match self {
+            Value::Const(c) => {
+                if *c != b {
+                    None
+                } else {
+                    Some(None)
+                }
+            }
+            Value::Var(l) => Some(Some(if b { !*l } else { *l })),
+        }
+    }
+}
+
+/// Sat encoding of a circuit of a given number of gates
+struct Encoding {
+    nb_inputs: usize,
+    nb_gates: usize,
+    rows: Vec<usize>,
+    nb_vars: u32,
+    clauses: Vec<Vec<Lit>>,
+    /// Operand pairs and selection literal of each gate
+    selection: Vec<Vec<(usize, usize, Lit)>>,
+    /// Function literals of each gate, for operand values 01, 10 and 11
+    function: Vec<[Lit; 3]>,
+    /// Value of each gate for each row
+    values: Vec<Vec<Lit>>,
+}
+
+impl Encoding {
+    fn new_lit(&mut self) -> Lit {
+        self.nb_vars += 1;
+        Lit::new(self.nb_vars - 1, false)
+    }
+
+    fn new(nb_inputs: usize, nb_gates: usize, rows: Vec<usize>) -> Encoding {
+        let mut ret = Encoding {
+            nb_inputs,
+            nb_gates,
+            rows,
+            nb_vars: 0,
+            clauses: Vec::new(),
+            selection: Vec::new(),
+            function: Vec::new(),
+            values: Vec::new(),
+        };
+        for i in 0..nb_gates {
+            let mut sel = Vec::new();
+            for a in 0..nb_inputs + i {
+                for b in a + 1..nb_inputs + i {
+                    let l = ret.new_lit();
+                    sel.push((a, b, l));
+                }
+            }
+            ret.selection.push(sel);
+            let f = [ret.new_lit(), ret.new_lit(), ret.new_lit()];
+            ret.function.push(f);
+            let v = (0..ret.rows.len()).map(|_| ret.new_lit()).collect();
+            ret.values.push(v);
+        }
+        for i in 0..nb_gates {
+            ret.encode_gate(i);
+        }
+        ret.break_symmetries();
+        ret
+    }
+
+    /// Value of an input or gate for a row
+    fn value(&self, operand: usize, row: usize) -> Value {
+        if operand < self.nb_inputs {
+            Value::Const(self.rows[row] >> operand & 1 != 0)
+        } else {
+            Value::Var(self.values[operand - self.nb_inputs][row])
+        }
+    }
+
+    /// Add a clause, simplifying constant literals
+    fn add_clause(&mut self, lits: &[Option<Option<Lit>>]) {
+        let mut cl = Vec::new();
+        for l in lits {
+            match l {
+                None => return,
+                Some(None) => (),
+                Some(Some(l)) => cl.push(*l),
+            }
+        }
+        self.clauses.push(cl);
+    }
+
+    fn encode_gate(&mut self, i: usize) {
+        let sel = self.selection[i].clone();
+        self.clauses.push(sel.iter().map(|(_, _, l)| *l).collect());
+        let f = self.function[i];
+        for (a, b, s) in sel {
+            for row in 0..self.rows.len() {
+                let va = self.value(a, row);
+                let vb = self.value(b, row);
+                let x = self.values[i][row];
+                for (j, (ba, bb)) in [(false, false), (false, true), (true, false), (true, true)]
+                    .into_iter()
+                    .enumerate()
+                {
+                    let prefix = [Some(Some(!s)), va.differs(ba), vb.differs(bb)];
+                    if j == 0 {
+                        self.add_clause(&[prefix[0], prefix[1], prefix[2], Some(Some(!x))]);
+                    } else {
+                        let fl = f[j - 1];
+                        self.add_clause(&[
+                            prefix[0],
+                            prefix[1],
+                            prefix[2],
+                            Some(Some(x)),
+                            Some(Some(!fl)),
+                        ]);
+                        self.add_clause(&[
+                            prefix[0],
+                            prefix[1],
+                            prefix[2],
+                            Some(Some(!x)),
+                            Some(Some(fl)),
+                        ]);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Forbid trivial gates, and require every gate but the last to be used
+    fn break_symmetries(&mut self) {
+        for i in 0..self.nb_gates {
+            let [f01, f10, f11] = self.function[i];
+            self.clauses.push(vec![f01, f10, f11]);
+            self.clauses.push(vec![f01, !f10, !f11]);
+            self.clauses.push(vec![!f01, f10, !f11]);
+        }
+        for i in 0..self.nb_gates.saturating_sub(1) {
+            let op = self.nb_inputs + i;
+            let users = self.selection[i + 1..]
+                .iter()
+                .flatten()
+                .filter(|(a, b, _)| *a == op || *b == op)
+                .map(|(_, _, l)| *l);
+            self.clauses.push(users.collect());
+        }
+    }
+
+    /// Build the circuit from a solution
+    fn decode(&self, sol: &[bool], inverted: bool) -> Network {
+        let is_true = |l: Lit| lit_value(sol, l);
+        let mut ret = Network::new();
+        ret.add_inputs(self.nb_inputs);
+        let mut signals: Vec<Signal> = (0..self.nb_inputs).map(|i| ret.input(i)).collect();
+        for i in 0..self.nb_gates {
+            let (a, b, _) = *self.selection[i]
+                .iter()
+                .find(|(_, _, l)| is_true(*l))
+                .unwrap();
+            let (a, b) = (signals[a], signals[b]);
+            let f = self.function[i].map(is_true);
+            let g = match f {
+                [false, false, true] => Gate::and(a, b),
+                [false, true, false] => Gate::and(a, !b),
+                [true, false, false] => Gate::and(!a, b),
+                [true, true, false] => Gate::xor(a, b),
+                [true, true, true] => Gate::Buf(!ret.add(Gate::and(!a, !b))),
+                _ => unreachable!(),
+            };
+            signals.push(ret.add(g));
+        }
+        let out = signals.last().copied().unwrap();
+        ret.add_output(out ^ inverted);
+        ret.make_canonical();
+        ret.cleanup();
+        ret
+    }
+}
+
+/// Implementation of a function with no gate, if it is a constant or an input
+fn trivial_implementation(nb_inputs: usize, function: &[bool], care: &[bool]) -> Option<Signal> {
+    let rows: Vec<usize> = (0..function.len()).filter(|r| care[*r]).collect();
+    let mut candidates = vec![Signal::zero(), Signal::one()];
+    for i in 0..nb_inputs {
+        candidates.push(Signal::from_input(i as u32));
+        candidates.push(!Signal::from_input(i as u32));
+    }
+    candidates.into_iter().find(|s| {
+        rows.iter().all(|r| {
+            let v = if s.is_constant() {
+                *s == Signal::one()
+            } else {
+                (r >> s.input() & 1 != 0) ^ s.is_inverted()
+            };
+            v == function[*r]
+        })
+    })
+}
+
+/// Find a circuit with the smallest number of 2-input And and Xor gates for a function
+///
+/// The function is given by its truth table, the first input being the least significant bit of
+/// the row index. Rows where `care` is false are don't cares. Returns a network with one output,
+/// or None if more than `max_gates` gates are required.
+///
+/// The size of the formula grows with the number of rows and gates, so that this is only
+/// practical for functions of up to about 10 inputs and a handful of gates.
+pub fn exact_synthesis(function: &[bool], care: &[bool], max_gates: usize) -> Option<Network> {
+    assert!(function.len().is_power_of_two());
+    assert_eq!(function.len(), care.len());
+    let nb_inputs = function.len().trailing_zeros() as usize;
+    if let Some(s) = trivial_implementation(nb_inputs, function, care) {
+        let mut ret = Network::new();
+        ret.add_inputs(nb_inputs);
+        ret.add_output(s);
+        return Some(ret);
+    }
+
+    // Normal circuits are 0 when all inputs are 0, so the output is inverted if needed
+    let inverted = care[0] && function[0];
+    let rows: Vec<usize> = (1..function.len()).filter(|r| care[*r]).collect();
+    for nb_gates in 1..=max_gates {
+        let enc = Encoding::new(nb_inputs, nb_gates, rows.clone());
+        let mut solver = new_solver();
+        for c in &enc.clauses {
+            solver.add_clause(c);
+        }
+        let out = enc.values[nb_gates - 1].clone();
+        for (row, l) in rows.iter().zip(out) {
+            let v = function[*row] ^ inverted;
+            solver.add_unit(if v { l } else { !l });
+        }
+        if let Some(sol) = solver.solve() {
+            return Some(enc.decode(&sol, inverted));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::exact_synthesis;
+    use crate::sim::simulate_comb;
+
+    fn check(function: &[bool], care: &[bool], nb_gates: usize) {
+        let aig = exact_synthesis(function, care, nb_gates).unwrap();
+        assert!(aig.nb_nodes() <= nb_gates);
+        assert!(exact_synthesis(function, care, nb_gates - 1).is_none());
+        for row in 0..function.len() {
+            let v: Vec<bool> = (0..aig.nb_inputs()).map(|i| row >> i & 1 != 0).collect();
+            if care[row] {
+                assert_eq!(simulate_comb(&aig, &v)[0], function[row]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_exact() {
+        let tt = |f: u32, n: usize| -> Vec<bool> { (0..1 << n).map(|r| f >> r & 1 != 0).collect() };
+        // Majority of 3 takes 4 gates
+        check(&tt(0xE8, 3), &[true; 8], 4);
+        // Xor of 3 takes 2 gates
+        check(&tt(0x96, 3), &[true; 8], 2);
+        // Nand of 2 takes 1 gate
+        check(&tt(0x7, 2), &[true; 4], 1);
+        // Mux takes 3 gates
+        check(&tt(0xCA, 3), &[true; 8], 3);
+        // Majority is an Or if the third input is always 1
+        let care: Vec<bool> = (0..8).map(|r| r & 4 != 0).collect();
+        check(&tt(0xE8, 3), &care, 1);
+
+        // Constants and inputs take no gate
+        let aig = exact_synthesis(&tt(0xC, 2), &[true; 4], 0).unwrap();
+        assert_eq!(aig.output(0), aig.input(1));
+    }
+}
@@ -0,0 +1,325 @@
+//! Exploration of the area/depth tradeoff across optimization pipelines
+//!
+//! Different pass orderings trade area against depth. This runs several randomized
+//! pipelines and keeps the results that are not dominated by another one.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::network::area::AreaParameters;
+use crate::network::depth::depth;
+use crate::network::GrowthLimitExceeded;
+use crate::optim::Pipeline;
+use crate::util::format::count;
+use crate::util::rng::SeededRng;
+use crate::Network;
+
+/// Result of one exploration run
+#[derive(Clone, Debug)]
+pub struct ExplorationPoint {
+    /// Pipeline used to obtain the network
+    pub pipeline: Pipeline,
+    /// Area of the optimized network
+    pub area: usize,
+    /// Logic depth of the optimized network
+    pub depth: usize,
+    /// Optimized network
+    pub network: Network,
+}
+
+impl ExplorationPoint {
+    /// Returns whether this point is at least as good as the other in all objectives, and strictly better in one
+    pub fn dominates(&self, other: &ExplorationPoint) -> bool {
+        self.area <= other.area
+            && self.depth <= other.depth
+            && (self.area < other.area || self.depth < other.depth)
+    }
+}
+
+/// Pipeline to use for a given exploration run
+///
+/// The first run always uses the default pipeline, so that exploration is never worse than plain optimization.
+fn exploration_pipeline(effort: u64, seed: u64, run: usize) -> Pipeline {
+    if run == 0 {
+        Pipeline::from_effort(effort)
+    } else {
+        let mut rng = SeededRng::new(seed.wrapping_add(run as u64));
+        Pipeline::random(effort, &mut rng)
+    }
+}
+
+/// Run a single exploration point, failing if a pass would grow the network larger than `max_nodes`
+fn run_point(
+    aig: &Network,
+    pipeline: Pipeline,
+    params: &AreaParameters,
+    max_nodes: usize,
+) -> Result<ExplorationPoint, GrowthLimitExceeded> {
+    let mut network = aig.clone();
+    pipeline.try_run(&mut network, max_nodes)?;
+    Ok(ExplorationPoint {
+        pipeline,
+        area: params.area(&network),
+        depth: depth(&network),
+        network,
+    })
+}
+
+/// Keep only the non-dominated points, sorted by increasing area
+///
+/// Points with identical area and depth are only kept once.
+pub fn pareto_front(points: Vec<ExplorationPoint>) -> Vec<ExplorationPoint> {
+    let mut ret: Vec<ExplorationPoint> = Vec::new();
+    for p in points {
+        let redundant = ret
+            .iter()
+            .any(|q| q.dominates(&p) || (q.area == p.area && q.depth == p.depth));
+        if !redundant {
+            ret.retain(|q| !p.dominates(q));
+            ret.push(p);
+        }
+    }
+    ret.sort_by_key(|p| (p.area, p.depth));
+    ret
+}
+
+/// Explore randomized optimization pipelines and return the Pareto front of area versus depth
+///
+/// The runs are distributed over the given number of threads. The result only depends on the seed,
+/// not on the number of threads. Runs where a pass would grow the network larger than `max_nodes`
+/// are skipped, and an error is returned if all runs are skipped.
+pub fn explore(
+    aig: &Network,
+    nb_runs: usize,
+    effort: u64,
+    seed: u64,
+    params: &AreaParameters,
+    nb_jobs: usize,
+    max_nodes: usize,
+) -> Result<Vec<ExplorationPoint>, GrowthLimitExceeded> {
+    let pipelines: Vec<Pipeline> = (0..nb_runs)
+        .map(|i| exploration_pipeline(effort, seed, i))
+        .collect();
+    let nb_jobs = nb_jobs.clamp(1, std::cmp::max(nb_runs, 1));
+    let results: Vec<Result<ExplorationPoint, GrowthLimitExceeded>> = if nb_jobs == 1 {
+        pipelines
+            .into_iter()
+            .map(|p| run_point(aig, p, params, max_nodes))
+            .collect()
+    } else {
+        let chunk_size = nb_runs.div_ceil(nb_jobs);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = pipelines
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|p| run_point(aig, p.clone(), params, max_nodes))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        })
+    };
+    let mut points = Vec::new();
+    let mut error = None;
+    for r in results {
+        match r {
+            Ok(p) => points.push(p),
+            Err(e) => {
+                error.get_or_insert(e);
+            }
+        }
+    }
+    match error {
+        Some(e) if points.is_empty() => Err(e),
+        _ => Ok(pareto_front(points)),
+    }
+}
+
+/// Path of the output file for a point of the Pareto front, obtained by suffixing the file stem
+///
+/// For example, `optimized.bench` becomes `optimized_2.bench` for the third point.
+pub fn suffixed_path(path: &Path, index: usize) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, index, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, index),
+    };
+    path.with_file_name(name)
+}
+
+/// Table of the points of a Pareto front, for display
+pub struct ParetoTable<'a> {
+    /// Points of the front
+    pub points: &'a [ExplorationPoint],
+    /// Output files for each point
+    pub files: &'a [PathBuf],
+}
+
+impl fmt::Display for ParetoTable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Pareto front ({} points):", self.points.len())?;
+        writeln!(
+            f,
+            "  {:>8}  {:>6}  {:<24}  Pipeline",
+            "Area", "Depth", "File"
+        )?;
+        for (p, file) in self.points.iter().zip(self.files.iter()) {
+            let seed = match p.pipeline.seed {
+                Some(s) => format!("seed {}: ", s),
+                None => String::new(),
+            };
+            writeln!(
+                f,
+                "  {:>8}  {:>6}  {:<24}  {}{}",
+                count(p.area),
+                count(p.depth),
+                file.display(),
+                seed,
+                p.pipeline
+            )?;
+        }
+        fmt::Result::Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use volute::Lut;
+
+    use super::{explore, suffixed_path, ExplorationPoint, ParetoTable};
+    use crate::equiv::check_equivalence_comb;
+    use crate::io::{read_network_file, write_network_file};
+    use crate::network::area::AreaParameters;
+    use crate::network::generators::adder;
+    use crate::network::DEFAULT_MAX_NODES;
+    use crate::optim::Pipeline;
+    use crate::{Gate, Network, Signal};
+
+    fn test_design() -> Network {
+        let mut aig = adder::ripple_carry(3);
+        // Add a wide And and a Mux made of And gates, to give the passes something to do
+        let inputs: Vec<Signal> = (0..aig.nb_inputs()).map(|i| aig.input(i)).collect();
+        let mut x = inputs[0];
+        for s in &inputs[1..] {
+            x = aig.and(x, *s);
+        }
+        aig.add_output(x);
+        let a = aig.and(inputs[0], inputs[1]);
+        let b = aig.and(!inputs[0], inputs[2]);
+        let m = aig.and(!a, !b);
+        aig.add_output(!m);
+        aig
+    }
+
+    #[test]
+    fn test_explore() {
+        let aig = test_design();
+        let front = explore(&aig, 4, 1, 1, &AreaParameters::vlsi(), 2, DEFAULT_MAX_NODES).unwrap();
+        assert!(!front.is_empty());
+        for p in &front {
+            for q in &front {
+                assert!(!q.dominates(p));
+            }
+        }
+        let dir = std::env::temp_dir().join(format!("quaigh_explore_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("optimized.bench");
+        for (i, p) in front.iter().enumerate() {
+            let path = suffixed_path(&base, i);
+            write_network_file(&path, &p.network).unwrap();
+            let written = read_network_file(&path).unwrap();
+            check_equivalence_comb(&aig, &written, false).unwrap();
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_explore_deterministic() {
+        let aig = test_design();
+        let params = AreaParameters::vlsi();
+        let a = explore(&aig, 4, 1, 3, &params, 1, DEFAULT_MAX_NODES).unwrap();
+        let b = explore(&aig, 4, 1, 3, &params, 3, DEFAULT_MAX_NODES).unwrap();
+        assert_eq!(a.len(), b.len());
+        for (p, q) in a.iter().zip(b.iter()) {
+            assert_eq!(p.pipeline, q.pipeline);
+            assert_eq!((p.area, p.depth), (q.area, q.depth));
+        }
+    }
+
+    #[test]
+    fn test_explore_lut() {
+        let mut aig = test_design();
+        let inputs: Vec<Signal> = (0..4).map(|i| aig.input(i)).collect();
+        let l = aig.add(Gate::lut(&inputs, Lut::nth_var(4, 1) ^ Lut::nth_var(4, 3)));
+        aig.add_output(l);
+        let front = explore(&aig, 2, 1, 1, &AreaParameters::vlsi(), 1, DEFAULT_MAX_NODES).unwrap();
+        assert!(!front.is_empty());
+        for p in &front {
+            check_equivalence_comb(&aig, &p.network, false).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_explore_max_nodes() {
+        let aig = test_design();
+        let err = explore(&aig, 2, 1, 1, &AreaParameters::vlsi(), 1, 1).unwrap_err();
+        assert_eq!(err.limit, 1);
+    }
+
+    #[test]
+    fn test_suffixed_path() {
+        assert_eq!(
+            suffixed_path(&PathBuf::from("dir/out.bench"), 3),
+            PathBuf::from("dir/out_3.bench")
+        );
+        assert_eq!(
+            suffixed_path(&PathBuf::from("out"), 0),
+            PathBuf::from("out_0")
+        );
+    }
+
+    #[test]
+    fn test_pareto_table() {
+        let points = vec![
+            ExplorationPoint {
+                pipeline: Pipeline::from_effort(1),
+                area: 1234,
+                depth: 12,
+                network: Network::new(),
+            },
+            ExplorationPoint {
+                pipeline: Pipeline {
+                    seed: Some(5),
+                    passes: Vec::new(),
+                },
+                area: 2000,
+                depth: 8,
+                network: Network::new(),
+            },
+        ];
+        let files = vec![PathBuf::from("out_0.bench"), PathBuf::from("out_1.bench")];
+        let table = ParetoTable {
+            points: &points,
+            files: &files,
+        };
+        assert_eq!(
+            table.to_string(),
+            "Pareto front (2 points):\n\
+            \x20     Area   Depth  File                      Pipeline\n\
+            \x20    1,234      12  out_0.bench               share(64);fraig;xor-mux;dffe;dedup-ff;share(64);rewrite\n\
+            \x20    2,000       8  out_1.bench               seed 5: \n"
+        );
+    }
+}
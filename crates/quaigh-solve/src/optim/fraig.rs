@@ -0,0 +1,275 @@
+//! Sat sweeping, or fraiging
+//!
+//! Random simulation splits the signals of the network into candidate classes, that have the same
+//! value, or opposite values, for all simulated patterns. Nodes are visited in topological order
+//! and merged with the first signal of their class if the Sat solver proves them equivalent.
+//! Otherwise, the counterexample is added to the simulation patterns, which refines the classes.
+//! A single incremental solver is used for all the queries, so that proven equivalences help
+//! the following ones.
+//!
+//! Flip-flops are treated as free variables, so that only combinational equivalences are found.
+
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::equiv::IncrementalSolver;
+use crate::optim::lut_map::eval_gate;
+use crate::util::rng::SeededRng;
+use crate::{Gate, Network, Signal};
+
+/// Number of 64b words of random simulation used to build the classes
+const NB_WORDS: usize = 4;
+
+/// Maximum number of counterexamples added to the simulation
+const MAX_COUNTEREXAMPLES: usize = 256;
+
+/// Simulation values of the inputs and nodes of a network
+struct Signatures {
+    inputs: Vec<Vec<u64>>,
+    nodes: Vec<Vec<u64>>,
+    nb_words: usize,
+    rng: SeededRng,
+}
+
+impl Signatures {
+    fn new(aig: &Network, rng: SeededRng) -> Self {
+        let mut ret = Signatures {
+            inputs: vec![Vec::new(); aig.nb_inputs()],
+            nodes: vec![Vec::new(); aig.nb_nodes()],
+            nb_words: 0,
+            rng,
+        };
+        for _ in 0..NB_WORDS {
+            ret.add_word(aig, &HashMap::new());
+        }
+        ret
+    }
+
+    /// Simulate a new word of random patterns, with some values of the first pattern imposed
+    fn add_word(&mut self, aig: &Network, imposed: &HashMap<Signal, bool>) {
+        let value = |rng: &mut SeededRng, s: Signal| {
+            let w: u64 = rng.gen();
+            match imposed.get(&s) {
+                Some(b) => (w & !1) | *b as u64,
+                None => w,
+            }
+        };
+        for i in 0..aig.nb_inputs() {
+            let w = value(&mut self.rng, aig.input(i));
+            self.inputs[i].push(w);
+        }
+        for i in 0..aig.nb_nodes() {
+            let g = aig.gate(i);
+            let w = if g.is_comb() {
+                let v: Vec<u64> = g
+                    .dependencies()
+                    .iter()
+                    .map(|s| self.word(*s, self.nb_words))
+                    .collect();
+                eval_gate(&g, &v)
+            } else {
+                value(&mut self.rng, aig.node(i))
+            };
+            self.nodes[i].push(w);
+        }
+        self.nb_words += 1;
+    }
+
+    /// Simulate a counterexample returned by the Sat solver
+    fn add_counterexample(&mut self, aig: &Network, solver: &IncrementalSolver) {
+        let mut imposed = HashMap::new();
+        for i in 0..aig.nb_inputs() {
+            imposed.insert(aig.input(i), solver.value(aig.input(i)));
+        }
+        for i in 0..aig.nb_nodes() {
+            if !aig.gate(i).is_comb() {
+                imposed.insert(aig.node(i), solver.value(aig.node(i)));
+            }
+        }
+        self.add_word(aig, &imposed);
+    }
+
+    /// Simulation value of a signal for one word
+    fn word(&self, s: Signal, w: usize) -> u64 {
+        let v = if s.is_input() {
+            self.inputs[s.input() as usize][w]
+        } else if s.is_var() {
+            self.nodes[s.var() as usize][w]
+        } else {
+            0
+        };
+        if s.is_inverted() {
+            !v
+        } else {
+            v
+        }
+    }
+
+    /// Key of the class of a signal, and whether the signal is inverted compared to the class
+    ///
+    /// Classes are normalized so that the first simulated value is 0.
+    fn class(&self, s: Signal) -> (Vec<u64>, bool) {
+        let inv = self.word(s, 0) & 1 != 0;
+        let key = (0..self.nb_words).map(|w| self.word(s ^ inv, w)).collect();
+        (key, inv)
+    }
+}
+
+/// Merge the nodes of a network that are proven equivalent, up to an inversion
+///
+/// Combinational nodes are merged with earlier nodes, inputs, flip-flops or constants. Flip-flops
+/// are treated as free variables, and are not merged.
+pub fn fraig(aig: &mut Network) {
+    fraig_with_rng(aig, &mut SeededRng::default());
+}
+
+/// Merge the nodes of a network that are proven equivalent, with the random patterns taken from
+/// a given generator
+pub fn fraig_with_rng(aig: &mut Network, rng: &mut SeededRng) {
+    aig.topo_sort();
+    aig.make_canonical();
+    aig.cleanup();
+
+    // Queries are made on the original network, whose nodes have the same functions
+    let orig = aig.clone();
+    let mut solver = IncrementalSolver::new(&orig);
+    let mut sigs = Signatures::new(aig, rng.fork());
+    let mut nb_counterexamples = 0;
+    let candidates = |aig: &Network, n: usize| -> Vec<Signal> {
+        let mut ret = vec![Signal::zero()];
+        ret.extend((0..aig.nb_inputs()).map(|i| aig.input(i)));
+        ret.extend(
+            (0..n)
+                .filter(|i| !matches!(&aig.gate(*i), Gate::Buf(_)))
+                .map(|i| aig.node(i)),
+        );
+        ret
+    };
+    let build_classes = |sigs: &Signatures, signals: Vec<Signal>| {
+        let mut ret: HashMap<Vec<u64>, Signal> = HashMap::new();
+        for s in signals {
+            let (key, inv) = sigs.class(s);
+            ret.entry(key).or_insert(s ^ inv);
+        }
+        ret
+    };
+    let mut classes = build_classes(&sigs, candidates(aig, 0));
+
+    for n in 0..aig.nb_nodes() {
+        let s = aig.node(n);
+        loop {
+            let (key, inv) = sigs.class(s);
+            let r = match classes.get(&key) {
+                Some(r) if aig.gate(n).is_comb() => *r,
+                _ => {
+                    classes.entry(key).or_insert(s ^ inv);
+                    break;
+                }
+            };
+            let r = r ^ inv;
+            if solver.equivalent(s, r) {
+                aig.replace(n, Gate::Buf(r));
+                break;
+            }
+            if nb_counterexamples >= MAX_COUNTEREXAMPLES {
+                break;
+            }
+            nb_counterexamples += 1;
+            sigs.add_counterexample(aig, &solver);
+            classes = build_classes(&sigs, candidates(aig, n));
+        }
+    }
+
+    aig.topo_sort();
+    aig.make_canonical();
+    aig.cleanup();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fraig;
+    use crate::equiv::check_equivalence_comb;
+    use crate::network::generators::adder;
+    use crate::optim::lut_map;
+    use crate::testing::{GateMix, RandomNetwork};
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_merge() {
+        // Two Xor functions with different structures, and a constant node
+        let mut aig = Network::new();
+        aig.add_inputs(2);
+        let (a, b) = (aig.input(0), aig.input(1));
+        let x = aig.add(Gate::xor(a, b));
+        let t0 = aig.add(Gate::and(a, !b));
+        let t1 = aig.add(Gate::and(!a, b));
+        let y = aig.add(Gate::and(!t0, !t1));
+        let z = aig.add(Gate::and(t0, t1));
+        aig.add_output(x);
+        aig.add_output(y);
+        aig.add_output(z);
+        let mut opt = aig.clone();
+        fraig(&mut opt);
+        opt.check();
+        assert_eq!(opt.nb_nodes(), 1);
+        assert_eq!(opt.output(1), !opt.output(0));
+        assert!(opt.output(2).is_constant());
+        assert_eq!(check_equivalence_comb(&aig, &opt, false), Ok(()));
+    }
+
+    #[test]
+    fn test_unused_inputs() {
+        // Counterexamples read inputs outside the cones encoded so far
+        let aig = RandomNetwork {
+            nb_gates: 40,
+            gate_mix: GateMix::aig(),
+            ..RandomNetwork::default()
+        }
+        .generate(40);
+        let mut opt = aig.clone();
+        fraig(&mut opt);
+        opt.check();
+        assert_eq!(check_equivalence_comb(&aig, &opt, false), Ok(()));
+    }
+
+    #[test]
+    fn test_adders() {
+        // An adder and its mapping to 3-input Luts, with the same inputs
+        let adder = adder::ripple_carry(6);
+        let mapped = lut_map(&adder, 3);
+        let mut aig = adder.clone();
+        let inputs: Vec<Signal> = (0..aig.nb_inputs()).map(|i| aig.input(i)).collect();
+        let outputs = aig.insert_network(&mapped, &inputs).unwrap();
+        for o in outputs {
+            aig.add_output(o);
+        }
+        let mut opt = aig.clone();
+        fraig(&mut opt);
+        assert_eq!(opt.nb_nodes(), adder.nb_nodes());
+        for i in 0..adder.nb_outputs() {
+            assert_eq!(opt.output(i), opt.output(i + adder.nb_outputs()));
+        }
+    }
+
+    #[test]
+    fn test_sequential() {
+        // Equivalent nodes behind flip-flops are merged, but not the flip-flops themselves
+        let mut aig = Network::new();
+        aig.add_inputs(2);
+        let (a, b) = (aig.input(0), aig.input(1));
+        let q0 = aig.add(Gate::dff(a, b, Signal::zero()));
+        let q1 = aig.add(Gate::dff(b, a, Signal::zero()));
+        let t0 = aig.add(Gate::and(q0, !q1));
+        let t1 = aig.add(Gate::and(!q0, q1));
+        let x = aig.add(Gate::xor(q0, q1));
+        let y = aig.add(Gate::and(!t0, !t1));
+        aig.add_output(x);
+        aig.add_output(y);
+        let mut opt = aig.clone();
+        fraig(&mut opt);
+        opt.check();
+        assert_eq!(opt.nb_nodes(), 3);
+        assert_eq!(opt.output(0), !opt.output(1));
+    }
+}
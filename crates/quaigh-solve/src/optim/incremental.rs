@@ -0,0 +1,322 @@
+//! Incremental optimization, reusing the results of previous runs from an on-disk cache
+//!
+//! The network is split into independent components, each made of a group of outputs and their
+//! transitive fanin. Each component is identified by its [fingerprint](crate::network::fingerprint),
+//! so that components untouched by an edit are not optimized again.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::equiv::check_equivalence_bounded;
+use crate::io::{read_bench, write_bench};
+use crate::network::fingerprint::fingerprint;
+use crate::optim::Pipeline;
+use crate::{Network, Signal};
+
+/// Number of clock cycles used to verify cached sequential components
+const VERIFY_CYCLES: usize = 8;
+
+/// Statistics of an incremental optimization run
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IncrementalReport {
+    /// Number of independent components in the network
+    pub nb_components: usize,
+    /// Number of components reused from the cache
+    pub nb_cached: usize,
+    /// Number of components optimized by the pipeline
+    pub nb_optimized: usize,
+    /// Number of cached components rejected by verification
+    pub nb_rejected: usize,
+}
+
+impl fmt::Display for IncrementalReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Incremental optimization: {} components, {} reused from cache, {} optimized",
+            self.nb_components, self.nb_cached, self.nb_optimized
+        )?;
+        if self.nb_rejected != 0 {
+            write!(f, ", {} cached results rejected", self.nb_rejected)?;
+        }
+        Ok(())
+    }
+}
+
+/// A component of the network, extracted as a standalone network
+pub(crate) struct Component {
+    /// Primary inputs used by the component, in the order of the component's inputs
+    pub inputs: Vec<usize>,
+    /// Primary outputs implemented by the component, in the order of the component's outputs
+    pub outputs: Vec<usize>,
+    /// The extracted network
+    pub network: Network,
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Split the network into components that share no gate
+///
+/// Outputs that are constants or primary inputs are not part of any component.
+pub(crate) fn split_components(aig: &Network) -> Vec<Component> {
+    assert!(aig.is_topo_sorted());
+    let mut parent: Vec<usize> = (0..aig.nb_nodes()).collect();
+    for i in 0..aig.nb_nodes() {
+        for v in aig.gate(i).vars() {
+            let a = find_root(&mut parent, i);
+            let b = find_root(&mut parent, v as usize);
+            parent[a.max(b)] = a.min(b);
+        }
+    }
+
+    // Group the outputs by component, in order of their first output
+    let mut root_to_comp = vec![usize::MAX; aig.nb_nodes()];
+    let mut comp_outputs: Vec<Vec<usize>> = Vec::new();
+    for o in 0..aig.nb_outputs() {
+        let s = aig.output(o);
+        if !s.is_var() {
+            continue;
+        }
+        let root = find_root(&mut parent, s.var() as usize);
+        if root_to_comp[root] == usize::MAX {
+            root_to_comp[root] = comp_outputs.len();
+            comp_outputs.push(Vec::new());
+        }
+        comp_outputs[root_to_comp[root]].push(o);
+    }
+
+    let mut comp_nodes: Vec<Vec<usize>> = vec![Vec::new(); comp_outputs.len()];
+    for i in 0..aig.nb_nodes() {
+        let c = root_to_comp[find_root(&mut parent, i)];
+        if c != usize::MAX {
+            comp_nodes[c].push(i);
+        }
+    }
+
+    let mut ret = Vec::new();
+    for (outputs, nodes) in comp_outputs.into_iter().zip(comp_nodes) {
+        let mut inputs: Vec<usize> = nodes
+            .iter()
+            .flat_map(|i| aig.dependencies(*i))
+            .filter(|s| s.is_input())
+            .map(|s| s.input() as usize)
+            .collect();
+        inputs.sort();
+        inputs.dedup();
+
+        let mut network = Network::new();
+        network.add_inputs(inputs.len());
+        let mut translation = vec![Signal::zero(); aig.nb_nodes()];
+        for (j, i) in nodes.iter().enumerate() {
+            translation[*i] = Signal::from_var(j as u32);
+        }
+        let t = |s: &Signal| -> Signal {
+            if s.is_input() {
+                let j = inputs.binary_search(&(s.input() as usize)).unwrap();
+                Signal::from_input(j as u32) ^ s.is_inverted()
+            } else if s.is_var() {
+                translation[s.var() as usize] ^ s.is_inverted()
+            } else {
+                *s
+            }
+        };
+        for i in &nodes {
+            network.add(aig.gate(*i).remap(t));
+        }
+        for o in &outputs {
+            network.add_output(t(&aig.output(*o)));
+        }
+        ret.push(Component {
+            inputs,
+            outputs,
+            network,
+        });
+    }
+    ret
+}
+
+/// Directory of the cache used for a given pipeline
+fn pipeline_dir(cache_dir: &Path, pipeline: &Pipeline) -> PathBuf {
+    let mut name: String = pipeline
+        .to_string()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if let Some(s) = pipeline.seed {
+        name.push_str(&format!("_seed{}", s));
+    }
+    cache_dir.join(name)
+}
+
+/// Read a cached result, checking that it is compatible with the component
+fn read_cached(path: &Path, component: &Network) -> Option<Network> {
+    let data = fs::read(path).ok()?;
+    let aig = read_bench(data.as_slice()).ok()?;
+    if aig.nb_inputs() != component.nb_inputs() || aig.nb_outputs() != component.nb_outputs() {
+        return None;
+    }
+    Some(aig)
+}
+
+/// Check that a cached result is equivalent to the component
+fn verify_cached(cached: &Network, component: &Network) -> bool {
+    let nb_steps = if component.is_comb() && cached.is_comb() {
+        1
+    } else {
+        VERIFY_CYCLES
+    };
+    check_equivalence_bounded(component, cached, nb_steps, true).is_ok()
+}
+
+/// Optimize a network, reusing the optimized components stored in the cache directory
+///
+/// The network is split into independent components, each identified by its fingerprint.
+/// Components found in the cache are reused, the others are optimized with the pipeline and
+/// stored in the cache. The components are then put back together, followed by a light
+/// global pass of deduplication and cleanup.
+///
+/// With `verify`, cached components are checked for equivalence before being reused, with a bounded
+/// number of cycles for sequential components, and optimized again if the check fails.
+pub fn optimize_incremental(
+    aig: &mut Network,
+    pipeline: &Pipeline,
+    cache_dir: &Path,
+    verify: bool,
+) -> Result<IncrementalReport, String> {
+    aig.topo_sort();
+    let components = split_components(aig);
+    let dir = pipeline_dir(cache_dir, pipeline);
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create cache directory: {}", e))?;
+
+    let mut report = IncrementalReport {
+        nb_components: components.len(),
+        ..Default::default()
+    };
+    let mut ret = Network::new();
+    ret.add_inputs(aig.nb_inputs());
+    let mut outputs: Vec<Signal> = (0..aig.nb_outputs()).map(|o| aig.output(o)).collect();
+    for c in &components {
+        let path = dir.join(format!("{:016x}.bench", fingerprint(&c.network)));
+        let mut optimized = read_cached(&path, &c.network);
+        if verify {
+            if let Some(cached) = &optimized {
+                if !verify_cached(cached, &c.network) {
+                    report.nb_rejected += 1;
+                    optimized = None;
+                }
+            }
+        }
+        let optimized = match optimized {
+            Some(cached) => {
+                report.nb_cached += 1;
+                cached
+            }
+            None => {
+                let mut opt = c.network.clone();
+                pipeline.run(&mut opt);
+                let mut buf = Vec::new();
+                write_bench(&mut buf, &opt);
+                fs::write(&path, buf).map_err(|e| format!("Cannot write to cache: {}", e))?;
+                report.nb_optimized += 1;
+                opt
+            }
+        };
+
+        // Splice the component back; its nodes are appended in order
+        let binding: Vec<Signal> = c.inputs.iter().map(|i| ret.input(*i)).collect();
+        let spliced = ret
+            .insert_network(&optimized, &binding)
+            .map_err(|e| format!("Cannot splice cached component: {}", e))?;
+        for (o, s) in c.outputs.iter().zip(spliced) {
+            outputs[*o] = s;
+        }
+    }
+    for o in outputs {
+        ret.add_output(o);
+    }
+    ret.topo_sort();
+    ret.deduplicate();
+    ret.cleanup();
+    *aig = ret;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{optimize_incremental, split_components};
+    use crate::equiv::check_equivalence_comb;
+    use crate::optim::Pipeline;
+    use crate::{Gate, Network};
+
+    /// Two independent cones, the first one with some redundant logic
+    fn two_cones(edit: bool) -> Network {
+        let mut aig = Network::new();
+        aig.add_inputs(6);
+        let i: Vec<_> = (0..6).map(|j| aig.input(j)).collect();
+        let a = aig.add(Gate::and(i[0], i[1]));
+        let b = aig.add(Gate::and(i[1], i[0]));
+        let c = aig.add(Gate::xor(a, b));
+        let d = aig.add(Gate::xor(c, i[2]));
+        aig.add_output(d);
+        let e = aig.add(Gate::and3(i[3], i[4], i[5]));
+        let f = if edit {
+            aig.add(Gate::and(e, !i[3]))
+        } else {
+            aig.add(Gate::xor(e, i[3]))
+        };
+        aig.add_output(f);
+        aig.add_output(i[4]);
+        aig
+    }
+
+    #[test]
+    fn test_split_components() {
+        let comps = split_components(&two_cones(false));
+        assert_eq!(comps.len(), 2);
+        assert_eq!(comps[0].inputs, vec![0, 1, 2]);
+        assert_eq!(comps[0].outputs, vec![0]);
+        assert_eq!(comps[0].network.nb_nodes(), 4);
+        assert_eq!(comps[1].inputs, vec![3, 4, 5]);
+        assert_eq!(comps[1].outputs, vec![1]);
+        assert_eq!(comps[1].network.nb_nodes(), 2);
+    }
+
+    #[test]
+    fn test_incremental() {
+        let dir = std::env::temp_dir().join(format!("quaigh-incremental-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let pipeline = Pipeline::from_effort(1);
+
+        let mut reference = two_cones(false);
+        pipeline.run(&mut reference);
+
+        let mut aig = two_cones(false);
+        let report = optimize_incremental(&mut aig, &pipeline, &dir, false).unwrap();
+        assert_eq!((report.nb_cached, report.nb_optimized), (0, 2));
+        assert!(check_equivalence_comb(&aig, &two_cones(false), false).is_ok());
+        assert_eq!(aig.nb_nodes(), reference.nb_nodes());
+
+        // Same design again: everything comes from the cache
+        let mut aig = two_cones(false);
+        let report = optimize_incremental(&mut aig, &pipeline, &dir, true).unwrap();
+        assert_eq!((report.nb_cached, report.nb_optimized), (2, 0));
+        assert!(check_equivalence_comb(&aig, &two_cones(false), false).is_ok());
+
+        // Edit in the second cone only
+        let mut aig = two_cones(true);
+        let report = optimize_incremental(&mut aig, &pipeline, &dir, true).unwrap();
+        assert_eq!(report.nb_components, 2);
+        assert_eq!((report.nb_cached, report.nb_optimized), (1, 1));
+        assert_eq!(report.nb_rejected, 0);
+        assert!(check_equivalence_comb(&aig, &two_cones(true), false).is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+//! Infer Xor and Mux gates from And gates, and flip-flop enables and resets from their logic
+
+use std::collections::HashMap;
+
+use crate::network::matcher::Matcher;
+use crate::{Gate, Network, Signal};
+
+fn mux_pattern() -> Network {
+    let mut pattern = Network::new();
+    let s = pattern.add_input();
+    let a = pattern.add_input();
+    let b = pattern.add_input();
+    let x0 = pattern.add(Gate::and(s, !a));
+    let x1 = pattern.add(Gate::and(!s, !b));
+    let o = pattern.add(Gate::and(!x0, !x1));
+    pattern.add_output(o);
+    pattern
+}
+
+/// Rebuild Xor and Mux gates from And gates
+pub fn infer_xor_mux(aig: &mut Network) {
+    let mut ret = aig.clone();
+
+    let pattern = mux_pattern();
+    let mut matcher = Matcher::from_pattern(&pattern);
+    for i in 0..ret.nb_nodes() {
+        if let Some(v) = matcher.matches(&ret, i) {
+            ret.replace(i, Gate::mux(v[0], v[1], v[2]));
+        }
+    }
+    ret.cleanup();
+    ret.make_canonical();
+    *aig = ret;
+}
+
+fn dffe_pattern() -> Network {
+    let mut pattern = Network::new();
+    let d = pattern.add_input();
+    let en = pattern.add_input();
+    let var = Signal::from_var(1);
+    let mx = pattern.add(Gate::mux(en, d, var));
+    let q = pattern.add(Gate::dff(mx, Signal::one(), Signal::zero()));
+    pattern.add_output(q);
+    assert_eq!(q, var);
+    pattern
+}
+
+/// Rebuild Dffe from Mux gates
+pub fn infer_dffe(aig: &mut Network) {
+    let mut ret = aig.clone();
+
+    let pattern = dffe_pattern();
+    let mut matcher = Matcher::from_pattern(&pattern);
+    for i in 0..ret.nb_nodes() {
+        if let Some(v) = matcher.matches(&ret, i) {
+            let clock = ret.gate(i).clock().unwrap();
+            ret.replace(i, Gate::dff_with_clock(v[0], v[1], Signal::zero(), clock));
+        }
+    }
+    ret.cleanup();
+    ret.make_canonical();
+    *aig = ret;
+}
+
+/// Inputs of an And gate of any arity driving a flip-flop, if any
+fn and_inputs(aig: &Network, s: Signal) -> Option<&[Signal]> {
+    if !s.is_var() || s.is_inverted() {
+        return None;
+    }
+    let i = s.var() as usize;
+    if aig.gate(i).is_and() {
+        Some(aig.dependencies(i))
+    } else {
+        None
+    }
+}
+
+/// Fold synchronous resets into the reset pin of the flip-flops
+///
+/// A flip-flop without enable whose data is an And gate, such as `d & !r`, has its next value
+/// forced to 0 by each input of the And: it becomes `Dff(d, res=r)`. Among the inputs of the And,
+/// the reset is the one shared by the most flip-flops. A signal that only resets a single
+/// flip-flop is only folded if it is a primary input. Resets that are already present are
+/// combined with the new one.
+pub fn infer_dffr(aig: &mut Network) {
+    let mut ret = aig.clone();
+
+    let candidates: Vec<usize> = (0..ret.nb_nodes())
+        .filter(|i| match &ret.gate(*i) {
+            Gate::Dff([d, en, _], _) => *en == Signal::one() && and_inputs(&ret, *d).is_some(),
+            _ => false,
+        })
+        .collect();
+    let mut count: HashMap<Signal, usize> = HashMap::new();
+    for i in &candidates {
+        let Gate::Dff([d, _, _], _) = &ret.gate(*i) else {
+            unreachable!()
+        };
+        for s in and_inputs(&ret, *d).unwrap() {
+            *count.entry(*s).or_insert(0) += 1;
+        }
+    }
+
+    for i in candidates {
+        let Gate::Dff([d, _, res], clock) = ret.gate(i) else {
+            unreachable!()
+        };
+        let inputs = and_inputs(&ret, d).unwrap().to_vec();
+        let best = inputs
+            .iter()
+            .copied()
+            .filter(|s| count[s] >= 2 || s.is_input())
+            .max_by_key(|s| (count[s], s.is_input(), !s.raw()));
+        let Some(reset) = best else {
+            continue;
+        };
+        let rest: Vec<Signal> = inputs.into_iter().filter(|s| *s != reset).collect();
+        let data = ret.add_canonical(Gate::andn(&rest));
+        let res = !ret.and(!res, reset);
+        ret.replace(i, Gate::dff_with_clock(data, Signal::one(), res, clock));
+    }
+    ret.cleanup();
+    ret.make_canonical();
+    *aig = ret;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::infer_dffr;
+    use crate::equiv::check_equivalence_bounded;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_infer_dffr() {
+        let mut aig = Network::new();
+        let rst = aig.add_input();
+        let a = aig.add_input();
+        let b = aig.add_input();
+        let x = aig.and(a, b);
+        for d in [a, !b, x] {
+            let g = aig.add(Gate::andn(&[d, !rst]));
+            let q = aig.add(Gate::dff(g, Signal::one(), Signal::zero()));
+            aig.add_output(q);
+        }
+        aig.topo_sort();
+
+        let mut inferred = aig.clone();
+        infer_dffr(&mut inferred);
+        let resets = (0..inferred.nb_nodes())
+            .filter(|i| matches!(&inferred.gate(*i), Gate::Dff([_, _, r], _) if *r == rst))
+            .count();
+        assert_eq!(resets, 3);
+        check_equivalence_bounded(&aig, &inferred, 3, false).unwrap();
+
+        let mut expanded = inferred.clone();
+        expanded.expand_resets();
+        assert!((0..expanded.nb_nodes()).all(|i| match &expanded.gate(i) {
+            Gate::Dff([_, _, r], _) => *r == Signal::zero(),
+            _ => true,
+        }));
+        check_equivalence_bounded(&aig, &expanded, 3, false).unwrap();
+    }
+}
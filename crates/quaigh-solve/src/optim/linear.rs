@@ -0,0 +1,413 @@
+//! Resynthesis of Xor clusters as linear systems
+//!
+//! Xor gates connected together or through common inputs form a linear block: each of its
+//! outputs is the parity of some of its inputs, that is a linear function over GF(2). The block is rebuilt from scratch:
+//!   * Gaussian elimination finds the outputs that are cheaper to obtain from other outputs than
+//!     from the inputs, such as `a ^ b` given `a ^ c` and `b ^ c`;
+//!   * the other outputs are built by repeatedly extracting the pair of signals that appears in
+//!     the most outputs, breaking ties towards the shallowest pair;
+//!   * the remaining Xors are built as balanced trees.
+//!
+//! The block is replaced only if this reduces the number of 2-input Xor gates.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::network::NaryType;
+use crate::{Gate, Network, Signal};
+
+/// Maximum number of inputs or outputs of a block to be resynthesized
+const MAX_BLOCK_SIZE: usize = 256;
+
+/// Set of variables over GF(2), as a bitset
+type Row = Vec<u64>;
+
+fn row_get(r: &Row, i: usize) -> bool {
+    (r[i / 64] >> (i % 64)) & 1 != 0
+}
+
+fn row_flip(r: &mut Row, i: usize) {
+    r[i / 64] ^= 1 << (i % 64);
+}
+
+fn row_xor(r: &mut Row, o: &Row) {
+    for (a, b) in r.iter_mut().zip(o.iter()) {
+        *a ^= b;
+    }
+}
+
+fn row_weight(r: &Row) -> usize {
+    r.iter().map(|b| b.count_ones() as usize).sum()
+}
+
+fn row_bits(r: &Row, len: usize) -> Vec<usize> {
+    (0..len).filter(|i| row_get(r, *i)).collect()
+}
+
+/// Find the parent of an element in a union-find structure
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut r = i;
+    while parent[r] != r {
+        r = parent[r];
+    }
+    let mut j = i;
+    while parent[j] != r {
+        let next = parent[j];
+        parent[j] = r;
+        j = next;
+    }
+    r
+}
+
+/// Xor gates being built, with the level of each signal for depth-aware construction
+struct Builder<'a> {
+    aig: &'a mut Network,
+    signals: Vec<Signal>,
+    levels: Vec<usize>,
+    nb_gates: usize,
+}
+
+impl Builder<'_> {
+    /// Add a new 2-input Xor of two columns, and return the new column
+    fn xor(&mut self, a: usize, b: usize) -> usize {
+        let s = self.aig.add(Gate::xor(self.signals[a], self.signals[b]));
+        self.signals.push(s);
+        self.levels.push(self.levels[a].max(self.levels[b]) + 1);
+        self.nb_gates += 1;
+        self.signals.len() - 1
+    }
+
+    /// Build a balanced Xor tree of the columns, combining the shallowest first
+    fn tree(&mut self, cols: &[usize]) -> Option<usize> {
+        let mut heap: BinaryHeap<_> = cols
+            .iter()
+            .map(|c| Reverse((self.levels[*c], *c)))
+            .collect();
+        while heap.len() > 1 {
+            let Reverse((_, a)) = heap.pop().unwrap();
+            let Reverse((_, b)) = heap.pop().unwrap();
+            let c = self.xor(a, b);
+            heap.push(Reverse((self.levels[c], c)));
+        }
+        heap.pop().map(|Reverse((_, c))| c)
+    }
+
+    /// Build a set of parity functions, sharing the pairs that appear in several of them
+    fn share_pairs(&mut self, mut rows: Vec<Vec<usize>>) -> Vec<Option<usize>> {
+        loop {
+            let mut counts: HashMap<(usize, usize), usize> = HashMap::new();
+            for r in &rows {
+                for (k, a) in r.iter().enumerate() {
+                    for b in &r[k + 1..] {
+                        *counts.entry((*a, *b)).or_default() += 1;
+                    }
+                }
+            }
+            let best = counts
+                .into_iter()
+                .filter(|(_, c)| *c >= 2)
+                .max_by_key(|((a, b), c)| {
+                    (
+                        *c,
+                        Reverse(self.levels[*a].max(self.levels[*b])),
+                        Reverse((*a, *b)),
+                    )
+                });
+            let Some(((a, b), _)) = best else {
+                break;
+            };
+            let c = self.xor(a, b);
+            for r in &mut rows {
+                if r.contains(&a) && r.contains(&b) {
+                    r.retain(|x| *x != a && *x != b);
+                    r.push(c);
+                }
+            }
+        }
+        rows.iter().map(|r| self.tree(r)).collect()
+    }
+}
+
+/// A block of connected Xor gates
+#[derive(Default)]
+struct Block {
+    /// Signals used by the block but not computed in it, without inversion
+    leaves: Vec<Signal>,
+    /// Nodes of the block used outside of it
+    outputs: Vec<usize>,
+    /// Number of 2-input Xor gates in the block
+    cost: usize,
+}
+
+/// Find the blocks of Xor gates connected by Xor gates or by shared leaves
+fn find_blocks(aig: &Network) -> Vec<Block> {
+    let n = aig.nb_nodes();
+    let is_xor = |s: &Signal| s.is_var() && aig.gate(s.var() as usize).is_xor_like();
+    let mut parent: Vec<usize> = (0..n).collect();
+    let mut used_outside = vec![false; n];
+    let mut leaf_user: HashMap<Signal, usize> = HashMap::new();
+    for i in 0..n {
+        let g = aig.gate(i);
+        for s in g.dependencies() {
+            if g.is_xor_like() {
+                // Xor gates are connected through Xor gates and through their leaves
+                let j = if is_xor(s) {
+                    s.var() as usize
+                } else if !s.is_constant() {
+                    *leaf_user.entry(s.without_inversion()).or_insert(i)
+                } else {
+                    continue;
+                };
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                parent[ri] = rj;
+            } else if is_xor(s) {
+                used_outside[s.var() as usize] = true;
+            }
+        }
+    }
+    for o in 0..aig.nb_outputs() {
+        if is_xor(&aig.output(o)) {
+            used_outside[aig.output(o).var() as usize] = true;
+        }
+    }
+
+    let mut block_index = HashMap::new();
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut block_leaves = HashSet::new();
+    for (i, used) in used_outside.iter().enumerate() {
+        let g = aig.gate(i);
+        if !g.is_xor_like() {
+            continue;
+        }
+        let root = find(&mut parent, i);
+        let b = *block_index.entry(root).or_insert_with(|| {
+            blocks.push(Block::default());
+            blocks.len() - 1
+        });
+        let block = &mut blocks[b];
+        block.cost += g.dependencies().len() - 1;
+        for s in g.dependencies() {
+            if !is_xor(s) && !s.is_constant() && block_leaves.insert((b, s.without_inversion())) {
+                block.leaves.push(s.without_inversion());
+            }
+        }
+        if *used {
+            block.outputs.push(i);
+        }
+    }
+    blocks
+}
+
+/// Parity function of each output of a block, over its leaves, with its inversion
+fn block_functions(aig: &Network, block: &Block) -> Vec<(Row, bool)> {
+    let words = block.leaves.len().div_ceil(64);
+    let leaf_index: HashMap<Signal, usize> = block
+        .leaves
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (*s, i))
+        .collect();
+    let mut functions: HashMap<usize, (Row, bool)> = HashMap::new();
+    let mut stack: Vec<usize> = block.outputs.clone();
+    while let Some(i) = stack.pop() {
+        if functions.contains_key(&i) {
+            continue;
+        }
+        let g = aig.gate(i);
+        let pending: Vec<usize> = g
+            .dependencies()
+            .iter()
+            .filter(|s| !leaf_index.contains_key(&s.without_inversion()) && s.is_var())
+            .map(|s| s.var() as usize)
+            .filter(|j| !functions.contains_key(j))
+            .collect();
+        if !pending.is_empty() {
+            stack.push(i);
+            stack.extend(pending);
+            continue;
+        }
+        let mut row = vec![0; words];
+        let mut inv = matches!(g, Gate::Nary(_, NaryType::Xnor));
+        for s in g.dependencies() {
+            inv ^= s.is_inverted();
+            if s.is_constant() {
+                continue;
+            }
+            if let Some(l) = leaf_index.get(&s.without_inversion()) {
+                row_flip(&mut row, *l);
+            } else {
+                let (r, v) = &functions[&(s.var() as usize)];
+                row_xor(&mut row, r);
+                inv ^= v;
+            }
+        }
+        functions.insert(i, (row, inv));
+    }
+    block.outputs.iter().map(|o| functions[o].clone()).collect()
+}
+
+/// Rebuild a block, returning the new signal of each output, if it saves gates
+fn resynthesize_block(aig: &mut Network, block: &Block) -> Option<Vec<Signal>> {
+    let functions = block_functions(aig, block);
+    let nb_leaves = block.leaves.len();
+    let nb_outputs = functions.len();
+
+    // Gaussian elimination, with the lightest outputs first
+    let mut order: Vec<usize> = (0..nb_outputs).collect();
+    order.sort_by_key(|o| row_weight(&functions[*o].0));
+    let mut basis: Vec<(usize, Row, Row)> = Vec::new();
+    let mut derived: Vec<Option<Vec<usize>>> = vec![None; nb_outputs];
+    for &o in &order {
+        let mut row = functions[o].0.clone();
+        let mut comb = vec![0; nb_outputs.div_ceil(64)];
+        row_flip(&mut comb, o);
+        for (pivot, r, c) in &basis {
+            if row_get(&row, *pivot) {
+                row_xor(&mut row, r);
+                row_xor(&mut comb, c);
+            }
+        }
+        if let Some(pivot) = (0..nb_leaves).find(|i| row_get(&row, *i)) {
+            basis.push((pivot, row, comb));
+        } else {
+            row_flip(&mut comb, o);
+            let others = row_bits(&comb, nb_outputs);
+            if others.len() < row_weight(&functions[o].0) {
+                derived[o] = Some(others);
+            }
+        }
+    }
+
+    // Build the outputs that are not derived from other outputs
+    let mut builder = Builder {
+        aig,
+        signals: block.leaves.clone(),
+        levels: vec![0; nb_leaves],
+        nb_gates: 0,
+    };
+    let direct: Vec<usize> = (0..nb_outputs).filter(|o| derived[*o].is_none()).collect();
+    let rows = direct
+        .iter()
+        .map(|o| row_bits(&functions[*o].0, nb_leaves))
+        .collect();
+    let mut columns: Vec<Option<usize>> = vec![None; nb_outputs];
+    for (o, c) in direct.iter().zip(builder.share_pairs(rows)) {
+        columns[*o] = c;
+    }
+
+    // Build the derived outputs, ignoring the inversions until the end
+    for o in 0..nb_outputs {
+        if let Some(others) = &derived[o] {
+            let cols: Vec<usize> = others.iter().filter_map(|j| columns[*j]).collect();
+            columns[o] = builder.tree(&cols);
+        }
+    }
+    let nb_gates = builder.nb_gates;
+    let signals = builder.signals;
+
+    if nb_gates >= block.cost {
+        // Not worth it: the new gates are removed by cleanup afterwards
+        return None;
+    }
+    let mut ret = Vec::new();
+    for o in 0..nb_outputs {
+        let mut inv = functions[o].1;
+        if let Some(others) = &derived[o] {
+            for j in others {
+                inv ^= functions[*j].1;
+            }
+        }
+        let s = columns[o].map(|c| signals[c]).unwrap_or(Signal::zero());
+        ret.push(s ^ inv);
+    }
+    Some(ret)
+}
+
+/// Resynthesize connected Xor gates with Gaussian elimination to reduce the number of Xor gates
+///
+/// This handles parity and CRC circuits, where the same parities are shared across many outputs
+/// in ways that [`flatten_nary`](super::flatten_nary) and [`share_logic`](super::share_logic)
+/// do not find. Returns the number of 2-input Xor gates saved.
+pub fn optimize_xor_blocks(aig: &mut Network) -> usize {
+    aig.make_canonical();
+    let mut saved = 0;
+    for block in find_blocks(aig) {
+        if block.leaves.len() > MAX_BLOCK_SIZE || block.outputs.len() > MAX_BLOCK_SIZE {
+            continue;
+        }
+        let before = aig.nb_nodes();
+        if let Some(signals) = resynthesize_block(aig, &block) {
+            saved += block.cost - (aig.nb_nodes() - before);
+            for (o, s) in block.outputs.iter().zip(signals) {
+                aig.replace(*o, Gate::Buf(s));
+            }
+        }
+    }
+    aig.topo_sort();
+    aig.make_canonical();
+    aig.cleanup();
+    saved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::optimize_xor_blocks;
+    use crate::equiv::check_equivalence_comb;
+    use crate::network::TernaryType;
+    use crate::{Gate, Network, Signal};
+
+    fn parity(aig: &mut Network, v: &[Signal]) -> Signal {
+        let mut x = v[0];
+        for s in &v[1..] {
+            x = aig.xor(x, *s);
+        }
+        x
+    }
+
+    #[test]
+    fn test_shared_pairs() {
+        let mut aig = Network::new();
+        aig.add_inputs(4);
+        let i: Vec<Signal> = (0..4).map(|k| aig.input(k)).collect();
+        let o1 = parity(&mut aig, &[i[0], i[1], i[2]]);
+        let o2 = parity(&mut aig, &[i[2], i[1], i[3]]);
+        let o3 = parity(&mut aig, &[i[0], !i[3]]);
+        aig.add_output(o1);
+        aig.add_output(o2);
+        aig.add_output(o3);
+        let orig = aig.clone();
+        // o2 is o1 ^ o3
+        assert_eq!(optimize_xor_blocks(&mut aig), 1);
+        assert_eq!(aig.nb_nodes(), 4);
+        check_equivalence_comb(&orig, &aig, false).unwrap();
+    }
+
+    #[test]
+    fn test_derived() {
+        let mut aig = Network::new();
+        aig.add_inputs(5);
+        let i: Vec<Signal> = (0..5).map(|k| aig.input(k)).collect();
+        let o1 = parity(&mut aig, &[i[0], i[1], i[2], i[3]]);
+        let o2 = parity(&mut aig, &[i[4], i[3], i[2], i[1], i[0]]);
+        let x = aig.add(Gate::and(o1, o2));
+        aig.add_output(!o2);
+        aig.add_output(x);
+        let orig = aig.clone();
+        optimize_xor_blocks(&mut aig);
+        assert_eq!(aig.nb_nodes(), 5);
+        check_equivalence_comb(&orig, &aig, false).unwrap();
+    }
+
+    #[test]
+    fn test_no_change() {
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let i: Vec<Signal> = (0..3).map(|k| aig.input(k)).collect();
+        let x = aig.add(Gate::Ternary([i[0], i[1], i[2]], TernaryType::Xor));
+        aig.add_output(x);
+        let orig = aig.clone();
+        assert_eq!(optimize_xor_blocks(&mut aig), 0);
+        assert_eq!(aig.nb_nodes(), 1);
+        check_equivalence_comb(&orig, &aig, false).unwrap();
+    }
+}
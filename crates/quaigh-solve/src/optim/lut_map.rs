@@ -0,0 +1,558 @@
+//! Mapping of a network to k-input Luts, for FPGAs
+//!
+//! The mapper keeps a few priority cuts of at most k leaves for each node. A first pass selects
+//! the cut of minimum depth for each node. A second pass recovers area: it selects the cut of
+//! minimum area flow whose depth does not exceed the time required by the first mapping, so
+//! that the depth is preserved. The cover is then built from the outputs and the flip-flop
+//! inputs, with one Lut for each selected cut.
+
+use std::collections::HashMap;
+
+use volute::Lut;
+
+use crate::network::{BinaryType, NaryType, TernaryType};
+use crate::{Gate, Network, Signal};
+
+/// Smallest Lut size supported by the mapper, so that Mux and Maj gates fit in a single Lut
+pub const MIN_LUT_SIZE: usize = 3;
+
+/// Largest Lut size supported by the mapper, so that truth tables fit in a 64-bit word
+pub const MAX_LUT_SIZE: usize = 6;
+
+/// Number of cuts kept for each node
+const NB_CUTS: usize = 8;
+
+/// Truth tables of the variables of a 6-input function
+pub(super) const VAR_WORDS: [u64; 6] = [
+    0xAAAA_AAAA_AAAA_AAAA,
+    0xCCCC_CCCC_CCCC_CCCC,
+    0xF0F0_F0F0_F0F0_F0F0,
+    0xFF00_FF00_FF00_FF00,
+    0xFFFF_0000_FFFF_0000,
+    0xFFFF_FFFF_0000_0000,
+];
+
+/// Translate a signal to the new network
+fn translate(s: &Signal, translation: &[Signal]) -> Signal {
+    if s.is_var() {
+        translation[s.var() as usize] ^ s.is_inverted()
+    } else {
+        *s
+    }
+}
+
+/// Add an And or Xor tree for a gate with too many inputs
+fn add_tree(aig: &mut Network, ops: &[Signal], is_xor: bool) -> Signal {
+    if ops.len() == 1 {
+        return ops[0];
+    }
+    let (a, b) = ops.split_at(ops.len() / 2);
+    let a = add_tree(aig, a, is_xor);
+    let b = add_tree(aig, b, is_xor);
+    aig.add(if is_xor {
+        Gate::xor(a, b)
+    } else {
+        Gate::and(a, b)
+    })
+}
+
+/// Add a Lut with too many inputs, by Shannon expansion on its last inputs
+fn add_lut(aig: &mut Network, inputs: &[Signal], lut: &Lut, k: usize) -> Signal {
+    let n = inputs.len();
+    if n <= k {
+        return aig.add(Gate::lut(inputs, lut.clone()));
+    }
+    let mut f0 = Lut::zero(n - 1);
+    let mut f1 = Lut::zero(n - 1);
+    for m in 0..1 << (n - 1) {
+        if lut.value(m) {
+            f0.set_bit(m);
+        }
+        if lut.value(m | 1 << (n - 1)) {
+            f1.set_bit(m);
+        }
+    }
+    let a = add_lut(aig, &inputs[..n - 1], &f1, k);
+    let b = add_lut(aig, &inputs[..n - 1], &f0, k);
+    aig.add(Gate::mux(inputs[n - 1], a, b))
+}
+
+/// Copy the network, so that all combinatorial gates have at most k inputs
+fn decompose(aig: &Network, k: usize) -> Network {
+    let mut ret = Network::new();
+    ret.add_inputs(aig.nb_inputs());
+    let mut translation = Vec::with_capacity(aig.nb_nodes());
+    for i in 0..aig.nb_nodes() {
+        let g = aig.gate(i);
+        let s = if !g.is_comb() {
+            // Flip-flops may use later nodes, and are filled once all nodes are translated
+            ret.add(Gate::Buf(Signal::zero()))
+        } else if g.dependencies().len() <= k {
+            ret.add(g.remap(|s| translate(s, &translation)))
+        } else {
+            let ops: Vec<Signal> = g
+                .dependencies()
+                .iter()
+                .map(|s| translate(s, &translation))
+                .collect();
+            match g {
+                Gate::Nary(_, NaryType::And) => add_tree(&mut ret, &ops, false),
+                Gate::Nary(_, NaryType::Xor) => add_tree(&mut ret, &ops, true),
+                Gate::Lut(lut) => add_lut(&mut ret, &ops, &lut.lut, k),
+                _ => panic!("Gate {} should have been made canonical", g),
+            }
+        };
+        translation.push(s);
+    }
+    for i in 0..aig.nb_nodes() {
+        let g = aig.gate(i);
+        if !g.is_comb() {
+            let node = translation[i].var() as usize;
+            ret.replace(node, g.remap(|s| translate(s, &translation)));
+        }
+    }
+    for o in 0..aig.nb_outputs() {
+        ret.add_output(translate(&aig.output(o), &translation));
+    }
+    ret
+}
+
+/// Evaluate a gate on 64-bit words
+pub(super) fn eval_gate(g: &Gate, v: &[u64]) -> u64 {
+    match g {
+        Gate::Binary(_, BinaryType::And) => v[0] & v[1],
+        Gate::Binary(_, BinaryType::Xor) => v[0] ^ v[1],
+        Gate::Ternary(_, TernaryType::And) => v[0] & v[1] & v[2],
+        Gate::Ternary(_, TernaryType::Xor) => v[0] ^ v[1] ^ v[2],
+        Gate::Ternary(_, TernaryType::Maj) => (v[0] & v[1]) | (v[2] & (v[0] | v[1])),
+        Gate::Ternary(_, TernaryType::Mux) => (v[0] & v[1]) | (!v[0] & v[2]),
+        Gate::Nary(_, t) => {
+            let and = v.iter().fold(!0, |a, b| a & b);
+            let or = v.iter().fold(0, |a, b| a | b);
+            let xor = v.iter().fold(0, |a, b| a ^ b);
+            match t {
+                NaryType::And => and,
+                NaryType::Nand => !and,
+                NaryType::Or => or,
+                NaryType::Nor => !or,
+                NaryType::Xor => xor,
+                NaryType::Xnor => !xor,
+            }
+        }
+        Gate::Buf(_) => v[0],
+        Gate::Lut(lut) => {
+            let mut ret = 0;
+            for lane in 0..64 {
+                let mut m = 0;
+                for (j, w) in v.iter().enumerate() {
+                    m |= (((w >> lane) & 1) as usize) << j;
+                }
+                if lut.lut.value(m) {
+                    ret |= 1 << lane;
+                }
+            }
+            ret
+        }
+        Gate::Dff(..) | Gate::Latch(..) => unreachable!(),
+    }
+}
+
+/// Cut enumeration and selection
+struct Mapper<'a> {
+    aig: &'a Network,
+    k: usize,
+    /// Priority cuts of each node, the selected one first; leaves are nodes, then inputs
+    cuts: Vec<Vec<Vec<u32>>>,
+    /// Depth of each node in the current mapping
+    arrival: Vec<u32>,
+    /// Area flow of each node in the current mapping
+    area_flow: Vec<f64>,
+    /// Estimated number of references of each node
+    refs: Vec<f64>,
+}
+
+impl<'a> Mapper<'a> {
+    fn new(aig: &'a Network, k: usize) -> Self {
+        let n = aig.nb_nodes();
+        let mut refs = vec![0.0; n];
+        for i in 0..n {
+            for v in aig.gate(i).vars() {
+                refs[v as usize] += 1.0;
+            }
+        }
+        for o in 0..aig.nb_outputs() {
+            if aig.output(o).is_var() {
+                refs[aig.output(o).var() as usize] += 1.0;
+            }
+        }
+        Mapper {
+            aig,
+            k,
+            cuts: vec![Vec::new(); n],
+            arrival: vec![0; n],
+            area_flow: vec![0.0; n],
+            refs,
+        }
+    }
+
+    /// Leaf corresponding to a signal, if not constant
+    fn leaf(&self, s: &Signal) -> Option<u32> {
+        if s.is_input() {
+            Some(self.aig.nb_nodes() as u32 + s.input())
+        } else if s.is_var() {
+            Some(s.var())
+        } else {
+            None
+        }
+    }
+
+    /// Whether a leaf is a combinatorial node, that could be part of a Lut
+    fn is_comb_node(&self, l: u32) -> bool {
+        (l as usize) < self.aig.nb_nodes() && self.aig.gate(l as usize).is_comb()
+    }
+
+    fn cut_arrival(&self, cut: &[u32]) -> u32 {
+        1 + cut
+            .iter()
+            .filter(|l| self.is_comb_node(**l))
+            .map(|l| self.arrival[*l as usize])
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn cut_area_flow(&self, cut: &[u32]) -> f64 {
+        1.0 + cut
+            .iter()
+            .filter(|l| self.is_comb_node(**l))
+            .map(|l| self.area_flow[*l as usize] / self.refs[*l as usize].max(1.0))
+            .sum::<f64>()
+    }
+
+    /// All cuts of a node obtained by merging the cuts of its fanins
+    fn enumerate(&self, i: usize) -> Vec<Vec<u32>> {
+        let mut ret: Vec<Vec<u32>> = vec![Vec::new()];
+        for l in self
+            .aig
+            .gate(i)
+            .dependencies()
+            .iter()
+            .filter_map(|s| self.leaf(s))
+        {
+            let mut fanin_cuts = vec![vec![l]];
+            if self.is_comb_node(l) {
+                fanin_cuts.extend(self.cuts[l as usize].iter().cloned());
+            }
+            let mut next = Vec::new();
+            for c in &ret {
+                for f in &fanin_cuts {
+                    let mut m = c.clone();
+                    m.extend(f);
+                    m.sort();
+                    m.dedup();
+                    if m.len() <= self.k {
+                        next.push(m);
+                    }
+                }
+            }
+            next.sort();
+            next.dedup();
+            ret = next;
+        }
+        ret
+    }
+
+    /// Select the cuts of a node
+    ///
+    /// With a required time, the cut of minimum area flow that meets it is selected. Otherwise,
+    /// the cut of minimum depth is selected. The forced cut is always a candidate.
+    fn select(&mut self, i: usize, required: Option<u32>, forced: Option<&Vec<u32>>) {
+        let mut candidates = self.enumerate(i);
+        if let Some(c) = forced {
+            candidates.push(c.clone());
+        }
+        // Remove dominated cuts
+        candidates.sort_by_key(|c| c.len());
+        candidates.dedup();
+        let mut kept: Vec<Vec<u32>> = Vec::new();
+        for c in candidates {
+            if !kept.iter().any(|k| k.iter().all(|l| c.contains(l))) {
+                kept.push(c);
+            }
+        }
+        let mut scored: Vec<(u32, f64, Vec<u32>)> = kept
+            .into_iter()
+            .map(|c| (self.cut_arrival(&c), self.cut_area_flow(&c), c))
+            .collect();
+        match required {
+            Some(r) => scored.sort_by(|a, b| {
+                (a.0 > r)
+                    .cmp(&(b.0 > r))
+                    .then(a.1.total_cmp(&b.1))
+                    .then(a.0.cmp(&b.0))
+                    .then(a.2.len().cmp(&b.2.len()))
+            }),
+            None => scored.sort_by(|a, b| {
+                a.0.cmp(&b.0)
+                    .then(a.1.total_cmp(&b.1))
+                    .then(a.2.len().cmp(&b.2.len()))
+            }),
+        }
+        scored.truncate(NB_CUTS);
+        self.arrival[i] = scored[0].0;
+        self.area_flow[i] = scored[0].1;
+        self.cuts[i] = scored.into_iter().map(|c| c.2).collect();
+    }
+
+    /// Roots of the cover: outputs and flip-flop inputs
+    fn roots(&self) -> Vec<u32> {
+        let mut ret = Vec::new();
+        for o in 0..self.aig.nb_outputs() {
+            ret.extend(self.leaf(&self.aig.output(o)));
+        }
+        for i in 0..self.aig.nb_nodes() {
+            if !self.aig.gate(i).is_comb() {
+                ret.extend(
+                    self.aig
+                        .gate(i)
+                        .dependencies()
+                        .iter()
+                        .filter_map(|s| self.leaf(s)),
+                );
+            }
+        }
+        ret.retain(|l| self.is_comb_node(*l));
+        ret
+    }
+
+    /// Nodes of the cover, with the number of times they are used as a leaf or a root
+    fn cover(&self) -> Vec<usize> {
+        let mut used = vec![0; self.aig.nb_nodes()];
+        for l in self.roots() {
+            used[l as usize] += 1;
+        }
+        for i in (0..self.aig.nb_nodes()).rev() {
+            if used[i] > 0 && self.aig.gate(i).is_comb() {
+                for l in &self.cuts[i][0] {
+                    if self.is_comb_node(*l) {
+                        used[*l as usize] += 1;
+                    }
+                }
+            }
+        }
+        used
+    }
+
+    /// Depth-oriented mapping followed by area recovery
+    fn run(&mut self) {
+        let n = self.aig.nb_nodes();
+        for i in 0..n {
+            if self.aig.gate(i).is_comb() {
+                self.select(i, None, None);
+            }
+        }
+
+        // Required times of the nodes of the depth-oriented cover
+        let roots = self.roots();
+        let depth = roots.iter().map(|l| self.arrival[*l as usize]).max();
+        let mut required: Vec<Option<u32>> = vec![None; n];
+        for l in roots {
+            required[l as usize] = depth;
+        }
+        for i in (0..n).rev() {
+            if let Some(r) = required[i] {
+                for l in &self.cuts[i][0] {
+                    if self.is_comb_node(*l) {
+                        let req = &mut required[*l as usize];
+                        *req = Some(req.map_or(r - 1, |x| x.min(r - 1)));
+                    }
+                }
+            }
+        }
+        self.refs = self.cover().into_iter().map(|u| u as f64).collect();
+
+        // Area recovery, keeping the previous cut as a candidate so that the depth is met
+        for (i, req) in required.into_iter().enumerate() {
+            if self.aig.gate(i).is_comb() {
+                let previous = self.cuts[i][0].clone();
+                self.select(i, req, Some(&previous));
+            }
+        }
+    }
+
+    /// Truth table of a node as a function of the leaves of a cut
+    fn truth_table(&self, i: usize, cut: &[u32]) -> u64 {
+        let mut values: HashMap<u32, u64> = HashMap::new();
+        for (j, l) in cut.iter().enumerate() {
+            values.insert(*l, VAR_WORDS[j]);
+        }
+        self.eval(i as u32, &mut values)
+    }
+
+    fn eval(&self, l: u32, values: &mut HashMap<u32, u64>) -> u64 {
+        if let Some(v) = values.get(&l) {
+            return *v;
+        }
+        let g = self.aig.gate(l as usize);
+        let mut v = Vec::new();
+        for s in g.dependencies() {
+            let w = match self.leaf(s) {
+                None => 0,
+                Some(l) => self.eval(l, values),
+            };
+            v.push(if s.is_inverted() { !w } else { w });
+        }
+        let ret = eval_gate(&g, &v);
+        values.insert(l, ret);
+        ret
+    }
+}
+
+/// Map a network to Luts of at most `k` inputs
+///
+/// The depth of the mapping is minimized first, then its number of Luts. All combinatorial
+/// gates of the result are Luts; flip-flops are kept as is. `k` must be between
+/// [`MIN_LUT_SIZE`] and [`MAX_LUT_SIZE`].
+pub fn lut_map(aig: &Network, k: usize) -> Network {
+    assert!(
+        (MIN_LUT_SIZE..=MAX_LUT_SIZE).contains(&k),
+        "Lut size must be between {} and {}",
+        MIN_LUT_SIZE,
+        MAX_LUT_SIZE
+    );
+    let mut canonical = aig.clone();
+    canonical.topo_sort();
+    canonical.make_canonical();
+    canonical.cleanup();
+    let aig = decompose(&canonical, k);
+
+    let mut mapper = Mapper::new(&aig, k);
+    mapper.run();
+    let used = mapper.cover();
+
+    let n = aig.nb_nodes();
+    let mut ret = Network::new();
+    ret.add_inputs(aig.nb_inputs());
+    let mut translation = vec![Signal::zero(); n];
+    let leaf_signal = |l: u32, translation: &[Signal]| {
+        if l as usize >= n {
+            Signal::from_input(l - n as u32)
+        } else {
+            translation[l as usize]
+        }
+    };
+    for i in 0..n {
+        let g = aig.gate(i);
+        if !g.is_comb() {
+            translation[i] = ret.add(Gate::Buf(Signal::zero()));
+        } else if used[i] > 0 {
+            let cut = &mapper.cuts[i][0];
+            let tt = mapper.truth_table(i, cut);
+            let mut lut = Lut::zero(cut.len());
+            for m in 0..1 << cut.len() {
+                if (tt >> m) & 1 != 0 {
+                    lut.set_bit(m);
+                }
+            }
+            let inputs: Vec<Signal> = cut.iter().map(|l| leaf_signal(*l, &translation)).collect();
+            translation[i] = ret.add(Gate::lut(&inputs, lut));
+        }
+    }
+    let t = |s: &Signal, translation: &[Signal]| match mapper.leaf(s) {
+        None => *s,
+        Some(l) => leaf_signal(l, translation) ^ s.is_inverted(),
+    };
+    for i in 0..n {
+        let g = aig.gate(i);
+        if !g.is_comb() {
+            let node = translation[i].var() as usize;
+            ret.replace(node, g.remap(|s| t(s, &translation)));
+        }
+    }
+    for o in 0..aig.nb_outputs() {
+        ret.add_output(t(&aig.output(o), &translation));
+    }
+    ret.cleanup();
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::lut_map;
+    use crate::network::depth::depth;
+    use crate::network::generators::adder;
+    use crate::network::stats::stats;
+    use crate::sim::simulate;
+    use crate::{Gate, Network, Signal};
+
+    fn check_mapping(aig: &Network, mapped: &Network, k: usize) {
+        mapped.check();
+        for i in 0..mapped.nb_nodes() {
+            match &mapped.gate(i) {
+                Gate::Lut(lut) => assert!(lut.inputs.len() <= k),
+                Gate::Dff(..) => (),
+                g => panic!("Unexpected gate {}", g),
+            }
+        }
+        let nb_inputs = aig.nb_inputs();
+        for seed in 0..64u64 {
+            let pattern: Vec<Vec<bool>> = (0..4u64)
+                .map(|t| {
+                    (0..nb_inputs as u64)
+                        .map(|j| (seed.wrapping_mul(0x9e37_79b9) >> ((j + 7 * t) % 61)) & 1 != 0)
+                        .collect()
+                })
+                .collect();
+            assert_eq!(simulate(aig, &pattern), simulate(mapped, &pattern));
+        }
+    }
+
+    #[test]
+    fn test_adder() {
+        let aig = adder::ripple_carry(8);
+        for k in 3..=6 {
+            let mapped = lut_map(&aig, k);
+            check_mapping(&aig, &mapped, k);
+        }
+    }
+
+    #[test]
+    fn test_wide_gates() {
+        // A 12-input And and Xor, and a 4-input Lut mapped to 3-input Luts
+        let mut aig = Network::new();
+        aig.add_inputs(12);
+        let v: Vec<Signal> = (0..12).map(|i| aig.input(i)).collect();
+        let a = aig.add(Gate::andn(&v));
+        let x = aig.add(Gate::xorn(&v));
+        let lut =
+            !volute::Lut::nth_var(4, 0) ^ (volute::Lut::nth_var(4, 1) & volute::Lut::nth_var(4, 3));
+        let l = aig.add(Gate::lut(&v[..4], lut));
+        aig.add_output(a);
+        aig.add_output(!x);
+        aig.add_output(l);
+        let mapped = lut_map(&aig, 3);
+        check_mapping(&aig, &mapped, 3);
+        let mapped = lut_map(&aig, 6);
+        check_mapping(&aig, &mapped, 6);
+        // 12-input functions need three 6-input Luts each, with depth 2
+        assert!(mapped.nb_nodes() <= 7);
+        assert_eq!(depth(&mapped), 2);
+    }
+
+    #[test]
+    fn test_sequential() {
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let [a, b, c] = [0, 1, 2].map(|i| aig.input(i));
+        let q = aig.add(Gate::dff(Signal::zero(), Signal::one(), Signal::zero()));
+        let x = aig.and(a, q);
+        let y = aig.xor(x, b);
+        let z = aig.add(Gate::mux(c, y, !x));
+        aig.replace(0, Gate::dff(z, Signal::one(), !a));
+        aig.add_output(q);
+        aig.add_output(!z);
+        let mapped = lut_map(&aig, 4);
+        check_mapping(&aig, &mapped, 4);
+        assert_eq!(stats(&mapped).nb_lut, 1);
+        assert_eq!(mapped.nb_nodes(), 2);
+    }
+}
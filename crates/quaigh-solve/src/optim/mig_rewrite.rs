@@ -0,0 +1,229 @@
+//! Algebraic rewriting of majority gates
+//!
+//! Majority-inverter graphs have a complete algebraic axiomatization. This pass applies the rules
+//! that reduce the number of gates, viewing 2-input And gates as majority gates with a constant
+//! input (`a & b = Maj(0, a, b)`) and using self-duality (`!Maj(a, b, c) = Maj(!a, !b, !c)`):
+//!   * majority: `Maj(x, x, y) = x` and `Maj(x, !x, y) = y`, applied by normalization;
+//!   * relevance: `Maj(x, y, z) = Maj(x, y, z[x/!y])`, applied when it simplifies `z` to an
+//!     existing signal, which subsumes the simplifications obtained by associativity;
+//!   * distributivity, from right to left:
+//!     `Maj(Maj(x, y, u), Maj(x, y, v), z) = Maj(x, y, Maj(u, v, z))`, applied when the two inner
+//!     gates are not used elsewhere.
+
+use crate::network::{BinaryType, Normalization, TernaryType};
+use crate::{Gate, Network, Signal};
+
+/// Maximum number of rewriting rounds
+const MAX_ROUNDS: usize = 8;
+
+/// Inputs of a signal seen as a majority gate, if it is a Maj or a 2-input And
+fn maj_view(aig: &Network, s: Signal) -> Option<[Signal; 3]> {
+    if !s.is_var() {
+        return None;
+    }
+    let v = match &aig.gate(s.var() as usize) {
+        Gate::Ternary(v, TernaryType::Maj) => *v,
+        Gate::Binary([a, b], BinaryType::And) => [Signal::zero(), *a, *b],
+        Gate::Buf(b) => return maj_view(aig, *b ^ s.is_inverted()),
+        _ => return None,
+    };
+    Some(v.map(|x| x ^ s.is_inverted()))
+}
+
+/// Simplify `Maj(x, y, z)` by substituting `!y` for `x` in `z`, if this yields an existing signal
+fn relevance(aig: &mut Network, x: Signal, y: Signal, z: Signal) -> Option<Signal> {
+    let inner = maj_view(aig, z)?;
+    if !inner
+        .iter()
+        .any(|s| s.without_inversion() == x.without_inversion())
+    {
+        return None;
+    }
+    let substituted = inner.map(|s| {
+        if s == x {
+            !y
+        } else if s == !x {
+            y
+        } else {
+            s
+        }
+    });
+    match Gate::maj(substituted[0], substituted[1], substituted[2]).make_canonical() {
+        Normalization::Copy(s) => Some(aig.add_canonical(Gate::maj(x, y, s))),
+        _ => None,
+    }
+}
+
+/// Factor out the two inputs shared by two single-use majority gates
+fn distributivity(
+    aig: &mut Network,
+    uses: &[usize],
+    a: Signal,
+    b: Signal,
+    z: Signal,
+) -> Option<Signal> {
+    if !a.is_var() || !b.is_var() || a.var() == b.var() {
+        return None;
+    }
+    if uses[a.var() as usize] != 1 || uses[b.var() as usize] != 1 {
+        return None;
+    }
+    let ia = maj_view(aig, a)?;
+    let ib = maj_view(aig, b)?;
+    let shared: Vec<Signal> = ia.iter().copied().filter(|s| ib.contains(s)).collect();
+    if shared.len() < 2 {
+        return None;
+    }
+    let (x, y) = (shared[0], shared[1]);
+    let u = *ia.iter().find(|s| **s != x && **s != y).unwrap_or(&x);
+    let v = *ib.iter().find(|s| **s != x && **s != y).unwrap_or(&x);
+    let inner = aig.add_canonical(Gate::maj(u, v, z));
+    Some(aig.add_canonical(Gate::maj(x, y, inner)))
+}
+
+/// Try to rewrite a majority gate, returning the signal that replaces it
+fn rewrite_node(aig: &mut Network, uses: &[usize], i: usize) -> Option<Signal> {
+    if matches!(&aig.gate(i), Gate::Buf(_)) {
+        return None;
+    }
+    let v = maj_view(aig, aig.node(i))?;
+    for k in 0..3 {
+        let (x, y, z) = (v[(k + 1) % 3], v[(k + 2) % 3], v[k]);
+        if let Some(s) = relevance(aig, x, y, z) {
+            return Some(s);
+        }
+        if let Some(s) = relevance(aig, y, x, z) {
+            return Some(s);
+        }
+    }
+    for k in 0..3 {
+        let (a, b, z) = (v[(k + 1) % 3], v[(k + 2) % 3], v[k]);
+        if let Some(s) = distributivity(aig, uses, a, b, z) {
+            return Some(s);
+        }
+    }
+    None
+}
+
+/// Number of uses of each node, by gates and outputs
+fn count_uses(aig: &Network) -> Vec<usize> {
+    let mut uses = vec![0; aig.nb_nodes()];
+    for i in 0..aig.nb_nodes() {
+        for v in aig.gate(i).vars() {
+            uses[v as usize] += 1;
+        }
+    }
+    for o in 0..aig.nb_outputs() {
+        let s = aig.output(o);
+        if s.is_var() {
+            uses[s.var() as usize] += 1;
+        }
+    }
+    uses
+}
+
+/// Optimize majority gates with algebraic rewriting
+///
+/// And gates are rewritten together with majority gates, so that arithmetic circuits are optimized
+/// at the Maj level. Rules are only applied when they do not increase the number of gates.
+pub fn mig_rewrite(aig: &mut Network) {
+    aig.make_canonical();
+    aig.cleanup();
+    for _ in 0..MAX_ROUNDS {
+        let uses = count_uses(aig);
+        let nb_nodes = aig.nb_nodes();
+        let mut changed = false;
+        for i in 0..nb_nodes {
+            if let Some(s) = rewrite_node(aig, &uses, i) {
+                if s != aig.node(i) {
+                    aig.replace(i, Gate::Buf(s));
+                    changed = true;
+                }
+            }
+        }
+        aig.topo_sort();
+        aig.make_canonical();
+        aig.cleanup();
+        if !changed || aig.nb_nodes() >= nb_nodes {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mig_rewrite;
+    use crate::equiv::check_equivalence_comb;
+    use crate::network::generators::adder;
+    use crate::{Gate, Network};
+
+    #[test]
+    fn test_relevance() {
+        // Maj(x, y, Maj(!x, y, z)) = y
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (x, y, z) = (aig.input(0), aig.input(1), aig.input(2));
+        let inner = aig.add(Gate::maj(!x, y, z));
+        let outer = aig.add(Gate::maj(x, y, inner));
+        aig.add_output(outer);
+        let orig = aig.clone();
+        mig_rewrite(&mut aig);
+        assert_eq!(aig.nb_nodes(), 0);
+        assert_eq!(aig.output(0), y);
+        check_equivalence_comb(&orig, &aig, false).unwrap();
+    }
+
+    #[test]
+    fn test_associativity() {
+        // Maj(x, u, Maj(x, u, z)) = Maj(x, u, z)
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (x, u, z) = (aig.input(0), aig.input(1), aig.input(2));
+        let inner = aig.add(Gate::maj(x, u, z));
+        let outer = aig.add(Gate::maj(x, u, inner));
+        aig.add_output(outer);
+        aig.add_output(inner);
+        let orig = aig.clone();
+        mig_rewrite(&mut aig);
+        assert_eq!(aig.nb_nodes(), 1);
+        check_equivalence_comb(&orig, &aig, false).unwrap();
+    }
+
+    #[test]
+    fn test_distributivity() {
+        // Maj(Maj(x, y, u), Maj(x, y, v), z) = Maj(x, y, Maj(u, v, z))
+        let mut aig = Network::new();
+        aig.add_inputs(5);
+        let (x, y, u, v, z) = (
+            aig.input(0),
+            aig.input(1),
+            aig.input(2),
+            aig.input(3),
+            aig.input(4),
+        );
+        let a = aig.add(Gate::maj(x, y, u));
+        let b = aig.add(Gate::maj(!x, !y, v));
+        let c = aig.add(Gate::maj(a, !b, z));
+        aig.add_output(c);
+        let orig = aig.clone();
+        mig_rewrite(&mut aig);
+        assert_eq!(aig.nb_nodes(), 2);
+        check_equivalence_comb(&orig, &aig, false).unwrap();
+
+        // Not applied if the inner gates are used elsewhere
+        let mut aig = orig.clone();
+        aig.add_output(a);
+        mig_rewrite(&mut aig);
+        assert_eq!(aig.nb_nodes(), 3);
+    }
+
+    #[test]
+    fn test_adder() {
+        let orig = adder::ripple_carry(4);
+        let mut aig = orig.clone();
+        mig_rewrite(&mut aig);
+        assert!(aig.nb_nodes() <= orig.nb_nodes());
+        aig.check();
+        check_equivalence_comb(&orig, &aig, false).unwrap();
+    }
+}
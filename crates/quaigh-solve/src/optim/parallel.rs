@@ -0,0 +1,160 @@
+//! Optimization of the independent parts of a network in parallel threads
+//!
+//! The network is split into groups of outputs, and the cone of each group is optimized
+//! separately. Components that share no gate are optimized as a whole. Large combinatorial
+//! components are split further into cones of consecutive outputs: the logic they share is
+//! duplicated in each cone, and merged again by deduplication once the cones are put back
+//! together. Sequential components are never split, so that flip-flops are not duplicated.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::network::GrowthLimitExceeded;
+use crate::optim::incremental::split_components;
+use crate::optim::Pipeline;
+use crate::{Network, Signal};
+
+/// Number of gates above which a combinatorial component is split into several cones
+const MAX_PART_SIZE: usize = 4096;
+
+/// Groups of outputs optimized separately
+///
+/// Outputs that are constants or primary inputs are not part of any group.
+fn partition_outputs(aig: &Network, max_part_size: usize) -> Vec<Vec<usize>> {
+    let mut ret = Vec::new();
+    for c in split_components(aig) {
+        let size = c.network.nb_nodes();
+        if !c.network.is_comb() || size <= max_part_size {
+            ret.push(c.outputs);
+            continue;
+        }
+        let nb_parts = size.div_ceil(max_part_size).min(c.outputs.len());
+        let chunk_size = c.outputs.len().div_ceil(nb_parts);
+        ret.extend(c.outputs.chunks(chunk_size).map(|o| o.to_vec()));
+    }
+    ret
+}
+
+/// Optimize a network with a pipeline, using several threads
+///
+/// The network is split into parts that are optimized independently, then put back together,
+/// followed by a light global pass of deduplication and cleanup. The largest parts are handled
+/// first, and the result does not depend on the number of threads. The growth limit applies to
+/// each part separately. Returns the number of parts.
+pub fn optimize_parallel(
+    aig: &mut Network,
+    pipeline: &Pipeline,
+    nb_jobs: usize,
+    max_nodes: usize,
+) -> Result<usize, GrowthLimitExceeded> {
+    optimize_parallel_with_size(aig, pipeline, nb_jobs, max_nodes, MAX_PART_SIZE)
+}
+
+fn optimize_parallel_with_size(
+    aig: &mut Network,
+    pipeline: &Pipeline,
+    nb_jobs: usize,
+    max_nodes: usize,
+    max_part_size: usize,
+) -> Result<usize, GrowthLimitExceeded> {
+    aig.topo_sort();
+    let mut parts: Vec<_> = partition_outputs(aig, max_part_size)
+        .iter()
+        .map(|outputs| aig.extract_cone(outputs))
+        .collect();
+    parts.sort_by_key(|(cone, _)| std::cmp::Reverse(cone.nb_nodes()));
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<Network, GrowthLimitExceeded>>>> =
+        Mutex::new(vec![None; parts.len()]);
+    let nb_jobs = nb_jobs.clamp(1, std::cmp::max(parts.len(), 1));
+    std::thread::scope(|scope| {
+        for _ in 0..nb_jobs {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some((cone, _)) = parts.get(i) else {
+                    break;
+                };
+                let mut opt = cone.clone();
+                let res = pipeline.try_run(&mut opt, max_nodes).map(|_| opt);
+                results.lock().unwrap()[i] = Some(res);
+            });
+        }
+    });
+
+    let mut ret = Network::new();
+    ret.add_inputs(aig.nb_inputs());
+    let mut outputs: Vec<Signal> = (0..aig.nb_outputs()).map(|o| aig.output(o)).collect();
+    for ((_, cone), res) in parts.iter().zip(results.into_inner().unwrap()) {
+        let optimized = res.unwrap()?;
+        let binding: Vec<Signal> = cone.inputs.iter().map(|i| ret.input(*i)).collect();
+        let spliced = ret.insert_network(&optimized, &binding).unwrap();
+        for (o, s) in cone.outputs.iter().zip(spliced) {
+            outputs[*o] = s;
+        }
+    }
+    for (o, s) in outputs.into_iter().enumerate() {
+        ret.add_output(s);
+        ret.set_output_kind(o, aig.output_kind(o));
+    }
+    ret.topo_sort();
+    ret.deduplicate();
+    ret.cleanup();
+    *aig = ret;
+    Ok(parts.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{optimize_parallel, optimize_parallel_with_size, partition_outputs};
+    use crate::equiv::{check_equivalence_bounded, check_equivalence_comb};
+    use crate::network::generators::adder;
+    use crate::network::DEFAULT_MAX_NODES;
+    use crate::optim::Pipeline;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_partition() {
+        let aig = adder::ripple_carry(8);
+        let nb_components = partition_outputs(&aig, 1000).len();
+        let parts = partition_outputs(&aig, 10);
+        assert!(parts.len() > nb_components);
+        let mut outputs: Vec<usize> = parts.concat();
+        outputs.sort();
+        assert_eq!(outputs, (0..aig.nb_outputs()).collect::<Vec<_>>());
+
+        // Sequential components are kept whole
+        let mut seq = aig.clone();
+        let outputs: Vec<Signal> = (0..seq.nb_outputs()).map(|o| seq.output(o)).collect();
+        let x = seq.add(Gate::xorn(&outputs));
+        let q = seq.add(Gate::dff(x, Signal::one(), Signal::zero()));
+        seq.add_output(q);
+        seq.topo_sort();
+        assert_eq!(partition_outputs(&seq, 10).len(), 1);
+    }
+
+    #[test]
+    fn test_parallel() {
+        let aig = adder::ripple_carry(8);
+        let pipeline = Pipeline::from_effort(1);
+        let mut one = aig.clone();
+        optimize_parallel_with_size(&mut one, &pipeline, 1, DEFAULT_MAX_NODES, 10).unwrap();
+        let mut four = aig.clone();
+        let nb_parts =
+            optimize_parallel_with_size(&mut four, &pipeline, 4, DEFAULT_MAX_NODES, 10).unwrap();
+        assert!(nb_parts > 1);
+        four.check();
+        assert!(check_equivalence_comb(&aig, &four, false).is_ok());
+        assert_eq!(one.to_string(), four.to_string());
+
+        let mut seq = Network::new();
+        seq.add_inputs(2);
+        let x = seq.add(Gate::and(seq.input(0), seq.input(1)));
+        let q = seq.add(Gate::dff(x, Signal::one(), Signal::zero()));
+        seq.add_output(q);
+        seq.add_output(seq.input(1));
+        let mut opt = seq.clone();
+        optimize_parallel(&mut opt, &pipeline, 2, DEFAULT_MAX_NODES).unwrap();
+        assert!(check_equivalence_bounded(&seq, &opt, 3, false).is_ok());
+    }
+}
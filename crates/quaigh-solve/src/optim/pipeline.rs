@@ -0,0 +1,375 @@
+//! Sequences of optimization passes
+//!
+//! ```
+//! # use quaigh_solve::Network;
+//! # let mut aig = Network::new();
+//! use quaigh_solve::optim::Pipeline;
+//!
+//! // The pipeline used by the optimization command
+//! let pipeline = Pipeline::from_effort(1);
+//! println!("Running {}", pipeline);
+//! pipeline.run(&mut aig);
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::network::GrowthLimitExceeded;
+use crate::optim::share_logic::flattened_size;
+use crate::optim::{
+    balance, dedup_registers, fraig_with_rng, infer_dffe, infer_dffr, infer_xor_mux, mig_rewrite,
+    optimize_xor_blocks, reduce_constant_multipliers, resynthesize_adders, rewrite, share_logic,
+    window_resynth_with_rng, MAX_WINDOW_INPUTS,
+};
+use crate::util::rng::{SeededRng, DEFAULT_SEED};
+use crate::Network;
+
+/// Maximum number of gates of the implementations found by window resynthesis
+const WINDOW_MAX_GATES: usize = 6;
+
+/// A single optimization pass
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pass {
+    /// Logic sharing between And and Xor gates, with the given flattening limit
+    ShareLogic(usize),
+    /// Depth-oriented rebalancing of And and Xor gates, with the given flattening limit
+    Balance(usize),
+    /// Inference of Xor and Mux gates
+    InferXorMux,
+    /// Inference of flip-flop enables
+    InferDffe,
+    /// Inference of flip-flop synchronous resets
+    InferDffr,
+    /// Merging of equivalent flip-flops
+    DedupRegisters,
+    /// Rewriting of 4-input cuts with optimized structures
+    Rewrite,
+    /// Algebraic rewriting of majority gates
+    MigRewrite,
+    /// Resynthesis of Xor clusters with Gaussian elimination
+    XorBlocks,
+    /// Replacement of ripple-carry adders of at least the given length by carry-lookahead adders
+    Adders(usize),
+    /// Strength reduction of multipliers by a constant to shifts and additions
+    ConstMult,
+    /// Merging of equivalent nodes proven with a Sat solver
+    Fraig,
+    /// Exact resynthesis of windows with at most the given number of inputs
+    WindowResynth(usize),
+}
+
+impl Pass {
+    /// Run the pass on a network
+    pub fn run(&self, aig: &mut Network) {
+        self.run_with_rng(aig, &mut SeededRng::default());
+    }
+
+    /// Run the pass on a network, with the random numbers taken from a given generator
+    pub fn run_with_rng(&self, aig: &mut Network, rng: &mut SeededRng) {
+        match self {
+            Pass::ShareLogic(limit) => share_logic(aig, *limit),
+            Pass::Balance(limit) => balance(aig, *limit),
+            Pass::InferXorMux => infer_xor_mux(aig),
+            Pass::InferDffe => infer_dffe(aig),
+            Pass::InferDffr => infer_dffr(aig),
+            Pass::DedupRegisters => {
+                dedup_registers(aig);
+            }
+            Pass::Rewrite => rewrite(aig),
+            Pass::MigRewrite => mig_rewrite(aig),
+            Pass::XorBlocks => {
+                optimize_xor_blocks(aig);
+            }
+            Pass::Adders(min_len) => {
+                resynthesize_adders(aig, *min_len);
+            }
+            Pass::ConstMult => {
+                for m in reduce_constant_multipliers(aig) {
+                    println!("{}", m);
+                }
+            }
+            Pass::Fraig => fraig_with_rng(aig, rng),
+            Pass::WindowResynth(max_inputs) => {
+                window_resynth_with_rng(aig, *max_inputs, WINDOW_MAX_GATES, rng)
+            }
+        }
+    }
+
+    /// Run the pass on a network, failing before modifying it if the network would grow larger
+    /// than `max_nodes`
+    pub fn try_run(
+        &self,
+        aig: &mut Network,
+        max_nodes: usize,
+        rng: &mut SeededRng,
+    ) -> Result<(), GrowthLimitExceeded> {
+        match self {
+            Pass::ShareLogic(limit) | Pass::Balance(limit) => {
+                GrowthLimitExceeded::check("flatten", flattened_size(aig, *limit), max_nodes)?;
+            }
+            Pass::InferXorMux
+            | Pass::InferDffe
+            | Pass::InferDffr
+            | Pass::DedupRegisters
+            | Pass::Rewrite
+            | Pass::MigRewrite
+            | Pass::XorBlocks
+            | Pass::Adders(_)
+            | Pass::ConstMult
+            | Pass::Fraig
+            | Pass::WindowResynth(_) => (),
+        }
+        self.run_with_rng(aig, rng);
+        Ok(())
+    }
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pass::ShareLogic(limit) => write!(f, "share({})", limit),
+            Pass::Balance(limit) => write!(f, "balance({})", limit),
+            Pass::InferXorMux => write!(f, "xor-mux"),
+            Pass::InferDffe => write!(f, "dffe"),
+            Pass::InferDffr => write!(f, "dffr"),
+            Pass::DedupRegisters => write!(f, "dedup-ff"),
+            Pass::Rewrite => write!(f, "rewrite"),
+            Pass::MigRewrite => write!(f, "mig-rewrite"),
+            Pass::XorBlocks => write!(f, "xor-blocks"),
+            Pass::Adders(min_len) => write!(f, "adders({})", min_len),
+            Pass::ConstMult => write!(f, "const-mult"),
+            Pass::Fraig => write!(f, "fraig"),
+            Pass::WindowResynth(max_inputs) => write!(f, "window({})", max_inputs),
+        }
+    }
+}
+
+impl FromStr for Pass {
+    type Err = String;
+
+    /// Parse a pass, in the same format as its display: `share(64)`, `balance(8)`, `adders(8)`,
+    /// `window(6)`, `xor-mux`, `dffe`, `dffr`, `dedup-ff`, `rewrite`, `mig-rewrite`, `xor-blocks`,
+    /// `const-mult` or `fraig`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some((name, arg)) = s.strip_suffix(')').and_then(|s| s.split_once('(')) {
+            let limit = arg
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid limit {} for pass {}", arg, name))?;
+            match name.trim() {
+                "share" => return Ok(Pass::ShareLogic(limit)),
+                "balance" => return Ok(Pass::Balance(limit)),
+                "adders" => return Ok(Pass::Adders(limit)),
+                "window" if limit <= MAX_WINDOW_INPUTS => return Ok(Pass::WindowResynth(limit)),
+                "window" => {
+                    return Err(format!(
+                        "Windows are limited to {} inputs",
+                        MAX_WINDOW_INPUTS
+                    ))
+                }
+                _ => (),
+            }
+        }
+        match s {
+            "xor-mux" => Ok(Pass::InferXorMux),
+            "dffe" => Ok(Pass::InferDffe),
+            "dffr" => Ok(Pass::InferDffr),
+            "dedup-ff" => Ok(Pass::DedupRegisters),
+            "rewrite" => Ok(Pass::Rewrite),
+            "mig-rewrite" => Ok(Pass::MigRewrite),
+            "xor-blocks" => Ok(Pass::XorBlocks),
+            "const-mult" => Ok(Pass::ConstMult),
+            "fraig" => Ok(Pass::Fraig),
+            _ => Err(format!(
+                "Unknown pass {}, expected share(N), balance(N), adders(N), window(N), xor-mux, dffe, \
+                 dffr, dedup-ff, rewrite, mig-rewrite, xor-blocks, const-mult or fraig",
+                s
+            )),
+        }
+    }
+}
+
+/// A sequence of optimization passes
+///
+/// The network is shuffled with the seed, if any, then cleaned up and made canonical before the passes are run.
+/// Shuffling changes the order in which gates are visited, and breaks ties differently in the passes.
+/// The randomized passes draw from a generator initialized with the seed, or with
+/// [`DEFAULT_SEED`], so that the result only depends on the seed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    /// Seed used to shuffle the network beforehand and to initialize the random generator
+    pub seed: Option<u64>,
+    /// Passes to run, in order
+    pub passes: Vec<Pass>,
+}
+
+impl Pipeline {
+    /// The default pipeline for a given effort level
+    pub fn from_effort(effort: u64) -> Pipeline {
+        let mut passes = vec![Pass::ShareLogic(64)];
+        for _ in 0..effort {
+            passes.push(Pass::Fraig);
+            passes.push(Pass::InferXorMux);
+            passes.push(Pass::InferDffe);
+            passes.push(Pass::DedupRegisters);
+            passes.push(Pass::ShareLogic(64));
+            passes.push(Pass::Rewrite);
+        }
+        Pipeline { seed: None, passes }
+    }
+
+    /// A random variation of the pipeline for a given effort level
+    ///
+    /// Passes are picked and ordered randomly, the network is shuffled with a random seed,
+    /// and depth-oriented rebalancing is enabled or not at random.
+    pub fn random<R: Rng>(effort: u64, rng: &mut R) -> Pipeline {
+        let limits = [8, 16, 64];
+        let mut passes = Vec::new();
+        for _ in 0..effort {
+            passes.push(Pass::Fraig);
+            passes.push(Pass::InferXorMux);
+            passes.push(Pass::InferDffe);
+            passes.push(Pass::DedupRegisters);
+            passes.push(Pass::ShareLogic(*limits.choose(rng).unwrap()));
+            passes.push(Pass::Rewrite);
+        }
+        passes.shuffle(rng);
+        passes.insert(0, Pass::ShareLogic(*limits.choose(rng).unwrap()));
+        if rng.gen() {
+            passes.push(Pass::Balance(*limits.choose(rng).unwrap()));
+        }
+        Pipeline {
+            seed: Some(rng.gen()),
+            passes,
+        }
+    }
+
+    /// Run the pipeline on a network
+    pub fn run(&self, aig: &mut Network) {
+        self.run_with_hook(aig, |_, _, _| ());
+    }
+
+    /// Run the pipeline on a network, failing if a pass would grow it larger than `max_nodes`
+    ///
+    /// On failure, the network is left unchanged.
+    pub fn try_run(&self, aig: &mut Network, max_nodes: usize) -> Result<(), GrowthLimitExceeded> {
+        let mut rng = self.rng();
+        let mut ret = aig.clone();
+        if let Some(s) = self.seed {
+            ret.shuffle(s);
+        }
+        ret.cleanup();
+        ret.make_canonical();
+        for p in &self.passes {
+            p.try_run(&mut ret, max_nodes, &mut rng)?;
+        }
+        *aig = ret;
+        Ok(())
+    }
+
+    /// Run the pipeline on a network, calling the hook after each pass with its runtime
+    pub fn run_with_hook<F: FnMut(&Pass, &Network, Duration)>(
+        &self,
+        aig: &mut Network,
+        mut hook: F,
+    ) {
+        let mut rng = self.rng();
+        if let Some(s) = self.seed {
+            aig.shuffle(s);
+        }
+        aig.cleanup();
+        aig.make_canonical();
+        for p in &self.passes {
+            let start = Instant::now();
+            p.run_with_rng(aig, &mut rng);
+            hook(p, aig, start.elapsed());
+        }
+    }
+
+    /// Random generator used by the passes
+    fn rng(&self) -> SeededRng {
+        SeededRng::new(self.seed.unwrap_or(DEFAULT_SEED))
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let passes: Vec<String> = self.passes.iter().map(|p| p.to_string()).collect();
+        write!(f, "{}", passes.join(";"))
+    }
+}
+
+impl FromStr for Pipeline {
+    type Err = String;
+
+    /// Parse a sequence of passes separated by semicolons, as displayed; the seed is not set
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let passes = s
+            .split(';')
+            .filter(|p| !p.trim().is_empty())
+            .map(Pass::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Pipeline { seed: None, passes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pass, Pipeline};
+    use crate::network::fingerprint::fingerprint;
+    use crate::network::{generators, DEFAULT_MAX_NODES};
+
+    #[test]
+    fn test_parse() {
+        let mut p = Pipeline::from_effort(2);
+        p.passes.push(Pass::Balance(8));
+        assert_eq!(p.to_string().parse::<Pipeline>(), Ok(p));
+        assert_eq!("share( 16 )".parse::<Pass>(), Ok(Pass::ShareLogic(16)));
+        assert!("share(x)".parse::<Pass>().is_err());
+        assert_eq!("rewrite".parse::<Pass>(), Ok(Pass::Rewrite));
+        assert_eq!("fraig".parse::<Pass>(), Ok(Pass::Fraig));
+        assert_eq!("dffr".parse::<Pass>(), Ok(Pass::InferDffr));
+        assert_eq!("dedup-ff".parse::<Pass>(), Ok(Pass::DedupRegisters));
+        assert_eq!("mig-rewrite".parse::<Pass>(), Ok(Pass::MigRewrite));
+        assert_eq!("xor-blocks".parse::<Pass>(), Ok(Pass::XorBlocks));
+        assert_eq!("const-mult".parse::<Pass>(), Ok(Pass::ConstMult));
+        assert_eq!("adders(8)".parse::<Pass>(), Ok(Pass::Adders(8)));
+        assert_eq!("window(6)".parse::<Pass>(), Ok(Pass::WindowResynth(6)));
+        assert!("window(16)".parse::<Pass>().is_err());
+        assert!("refactor".parse::<Pass>().is_err());
+        assert!("dffe(2)".parse::<Pass>().is_err());
+    }
+
+    #[test]
+    fn test_growth_limit() {
+        let aig = generators::adder::ripple_carry(8);
+        let pipeline = Pipeline::from_effort(1);
+        let mut copy = aig.clone();
+        let err = pipeline.try_run(&mut copy, 4).unwrap_err();
+        assert_eq!(err.pass, "flatten");
+        assert_eq!(err.limit, 4);
+        assert_eq!(copy.nb_nodes(), aig.nb_nodes());
+        assert_eq!(fingerprint(&copy), fingerprint(&aig));
+        pipeline.try_run(&mut copy, DEFAULT_MAX_NODES).unwrap();
+        let mut reference = aig.clone();
+        pipeline.run(&mut reference);
+        assert_eq!(fingerprint(&copy), fingerprint(&reference));
+    }
+
+    #[test]
+    fn test_seed() {
+        let aig = generators::testcases::random_seq(8, 4, 100, 4, 3);
+        let mut pipeline = Pipeline::from_effort(1);
+        pipeline.seed = Some(5);
+        let mut a = aig.clone();
+        pipeline.run(&mut a);
+        let mut b = aig.clone();
+        pipeline.try_run(&mut b, DEFAULT_MAX_NODES).unwrap();
+        assert_eq!(a.to_string(), b.to_string());
+    }
+}
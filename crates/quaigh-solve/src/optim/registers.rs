@@ -0,0 +1,125 @@
+//! Merging of equivalent flip-flops
+//!
+//! Structural hashing only merges flip-flops whose inputs are already identical, one level of
+//! flip-flops at a time, and never merges flip-flops that depend on themselves. Here, flip-flops
+//! are first assumed equivalent, and the assumption is refined until it is consistent: two
+//! flip-flops stay equivalent if their data, enable and reset are identical once equivalent
+//! flip-flops are merged. Since all flip-flops start at 0, the flip-flops that remain equivalent
+//! have the same value at every cycle.
+
+use fxhash::FxHashMap;
+
+use crate::{Gate, Network, Signal};
+
+/// Apply a translation returned by a network transformation to a signal
+fn translate(s: Signal, t: &[Signal]) -> Signal {
+    if s.is_var() {
+        t[s.var() as usize] ^ s.is_inverted()
+    } else {
+        s
+    }
+}
+
+/// Number of flip-flops in the network
+fn nb_dff(aig: &Network) -> usize {
+    (0..aig.nb_nodes())
+        .filter(|i| !aig.gate(*i).is_comb())
+        .count()
+}
+
+/// Merge each flip-flop into the representative of its class, and return the translation
+fn merge(aig: &mut Network, repr: &[usize]) -> Vec<Signal> {
+    for (i, r) in repr.iter().enumerate() {
+        if *r != i {
+            aig.replace(i, Gate::Buf(aig.node(*r)));
+        }
+    }
+    let sorted = aig.topo_sort();
+    let canonical = aig.make_canonical();
+    sorted.iter().map(|s| translate(*s, &canonical)).collect()
+}
+
+/// Merge flip-flops with the same data, enable, reset and clock, until convergence
+///
+/// Flip-flops that only depend on each other, such as identical toggle chains, are merged as well.
+/// Returns the number of flip-flops removed.
+pub fn dedup_registers(aig: &mut Network) -> usize {
+    let nb_dff_before = nb_dff(aig);
+    aig.topo_sort();
+    aig.make_canonical();
+
+    // Class representative of each node; combinatorial nodes are their own representative
+    let mut repr: Vec<usize> = (0..aig.nb_nodes()).collect();
+    let dffs: Vec<usize> = (0..aig.nb_nodes())
+        .filter(|i| !aig.gate(*i).is_comb())
+        .collect();
+    if let Some(first) = dffs.first() {
+        for i in &dffs {
+            repr[*i] = *first;
+        }
+    }
+    loop {
+        let mut merged = aig.clone();
+        let t = merge(&mut merged, &repr);
+        let mut classes: FxHashMap<(usize, Gate), usize> = FxHashMap::default();
+        let mut next_repr = repr.clone();
+        for i in &dffs {
+            let g = aig.gate(*i).remap(|s| translate(*s, &t));
+            next_repr[*i] = *classes.entry((repr[*i], g)).or_insert(*i);
+        }
+        if next_repr == repr {
+            break;
+        }
+        repr = next_repr;
+    }
+
+    merge(aig, &repr);
+    aig.cleanup();
+    nb_dff_before - nb_dff(aig)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dedup_registers, nb_dff};
+    use crate::network::generators::testcases;
+    use crate::sim::simulate;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_ff_tree() {
+        for depth in [1, 3, 5] {
+            for (has_en, has_res) in [(false, false), (true, false), (true, true)] {
+                let mut aig = testcases::ff_tree(depth, has_en, has_res, 2);
+                assert_eq!(dedup_registers(&mut aig), (1 << (depth + 1)) - 2 - depth);
+                assert_eq!(aig.nb_nodes(), depth);
+            }
+        }
+    }
+
+    #[test]
+    fn test_toggle() {
+        // Two identical toggle flip-flops, each depending on itself
+        let mut aig = Network::new();
+        let en = aig.add_input();
+        let mut outputs = Vec::new();
+        for _ in 0..2 {
+            outputs.push(aig.add(Gate::dff(Signal::zero(), en, Signal::zero())));
+        }
+        // A third one toggling on another enable
+        let en2 = aig.add_input();
+        outputs.push(aig.add(Gate::dff(Signal::zero(), en2, Signal::zero())));
+        for ff in outputs {
+            let g = aig
+                .gate(ff.var() as usize)
+                .remap_with_ind(|s, i| if i == 0 { !ff } else { *s });
+            aig.replace(ff.var() as usize, g);
+            aig.add_output(ff);
+        }
+
+        let orig = aig.clone();
+        assert_eq!(dedup_registers(&mut aig), 1);
+        assert_eq!(nb_dff(&aig), 2);
+        let pattern = vec![vec![true, false], vec![true, true], vec![false, true]];
+        assert_eq!(simulate(&orig, &pattern), simulate(&aig, &pattern));
+    }
+}
@@ -0,0 +1,470 @@
+//! Boolean resubstitution
+//!
+//! Each node is re-expressed with signals that already exist in its transitive fanin, the
+//! divisors, using no new gate, a single new And or Xor gate, or two new gates. Candidates are
+//! found by random simulation, where flip-flops take random values, and verified with a Sat query
+//! before the node is replaced. A candidate is only used if it creates fewer gates than it frees.
+
+use std::collections::{HashMap, HashSet};
+
+use rand::Rng;
+
+use crate::equiv::query_signals;
+use crate::network::FanoutView;
+use crate::optim::lut_map::eval_gate;
+use crate::util::rng::SeededRng;
+use crate::{Gate, Network, Signal};
+
+/// Number of 64b words of random simulation used to find the candidates
+const NB_WORDS: usize = 8;
+
+/// Number of levels of the transitive fanin searched for divisors
+const MAX_LEVELS: usize = 4;
+
+/// Maximum number of divisors for candidates with zero or one new gate
+const MAX_DIVISORS: usize = 40;
+
+/// Maximum number of divisors for candidates with two new gates
+const MAX_DIVISORS_TWO_GATES: usize = 16;
+
+/// Maximum number of Sat queries for each node
+const MAX_SAT_CHECKS: usize = 4;
+
+/// Expression of a node with its divisors
+#[derive(Clone, Debug)]
+enum Candidate {
+    /// An existing signal
+    Signal(Signal),
+    /// A new 2-input gate, with an optional output inversion
+    Gate(Gate, bool),
+    /// A new 2-input gate, whose output is combined with a third divisor by a second And gate
+    TwoGates(Gate, bool, Signal, bool),
+}
+
+/// Network being optimized, with simulation signatures and reference counts
+struct Resubstitution {
+    aig: Network,
+    input_sigs: Vec<[u64; NB_WORDS]>,
+    sigs: Vec<[u64; NB_WORDS]>,
+    refs: Vec<usize>,
+    fanouts: FanoutView,
+}
+
+impl Resubstitution {
+    fn new(aig: Network, rng: &mut SeededRng) -> Self {
+        let input_sigs: Vec<[u64; NB_WORDS]> = (0..aig.nb_inputs()).map(|_| rng.gen()).collect();
+        let fanouts = FanoutView::new(&aig);
+        let mut ret = Resubstitution {
+            aig,
+            input_sigs,
+            sigs: Vec::new(),
+            refs: Vec::new(),
+            fanouts,
+        };
+        for i in 0..ret.aig.nb_nodes() {
+            let sig = if ret.aig.gate(i).is_comb() {
+                ret.eval(&ret.aig.gate(i))
+            } else {
+                rng.gen()
+            };
+            ret.sigs.push(sig);
+            ret.refs.push(0);
+        }
+        for i in 0..ret.aig.nb_nodes() {
+            ret.reference_deps(i);
+        }
+        for o in 0..ret.aig.nb_outputs() {
+            let s = ret.aig.output(o);
+            if s.is_var() {
+                ret.refs[s.var() as usize] += 1;
+            }
+        }
+        ret
+    }
+
+    /// Simulation value of a signal
+    fn value(&self, s: Signal, w: usize) -> u64 {
+        let v = if s.is_constant() {
+            0
+        } else if s.is_input() {
+            self.input_sigs[s.input() as usize][w]
+        } else {
+            self.sigs[s.var() as usize][w]
+        };
+        if s.is_inverted() {
+            !v
+        } else {
+            v
+        }
+    }
+
+    /// Simulation values of a gate
+    fn eval(&self, g: &Gate) -> [u64; NB_WORDS] {
+        let mut ret = [0; NB_WORDS];
+        for (w, r) in ret.iter_mut().enumerate() {
+            let v: Vec<u64> = g.dependencies().iter().map(|s| self.value(*s, w)).collect();
+            *r = eval_gate(g, &v);
+        }
+        ret
+    }
+
+    /// Follow buffers to the signal they copy
+    fn resolve(&self, mut s: Signal) -> Signal {
+        while s.is_var() {
+            match &self.aig.gate(s.var() as usize) {
+                Gate::Buf(x) => s = *x ^ s.is_inverted(),
+                _ => break,
+            }
+        }
+        s
+    }
+
+    /// Add a gate, with its simulation values and references
+    fn add(&mut self, g: Gate) -> Signal {
+        let nb = self.aig.nb_nodes();
+        let s = self.aig.add_canonical(g);
+        if self.aig.nb_nodes() > nb {
+            let g = self.aig.gate(nb).clone();
+            self.sigs.push(self.eval(&g));
+            self.refs.push(0);
+            self.fanouts.add_new_nodes(&self.aig);
+            self.reference_deps(nb);
+        }
+        s
+    }
+
+    /// Add the references of the dependencies of a node
+    fn reference_deps(&mut self, n: usize) {
+        for s in self.aig.dependencies(n) {
+            if s.is_var() {
+                self.refs[s.var() as usize] += 1;
+            }
+        }
+    }
+
+    /// Remove a reference to a signal, and to the gates that become unused
+    fn deref(&mut self, s: Signal) {
+        if !s.is_var() {
+            return;
+        }
+        let mut stack = vec![s.var() as usize];
+        while let Some(v) = stack.pop() {
+            self.refs[v] -= 1;
+            if self.refs[v] == 0 && self.aig.gate(v).is_comb() {
+                stack.extend(self.aig.gate(v).vars().map(|x| x as usize));
+            }
+        }
+    }
+
+    /// Maximum fanout-free cone of a node, and its number of gates
+    fn mffc(&self, n: usize) -> (HashSet<usize>, usize) {
+        let mut ret = HashSet::from([n]);
+        let mut derefs: HashMap<usize, usize> = HashMap::new();
+        let mut stack = vec![n];
+        while let Some(i) = stack.pop() {
+            for v in self.aig.gate(i).vars() {
+                let v = v as usize;
+                let d = derefs.entry(v).or_insert(0);
+                *d += 1;
+                if *d == self.refs[v] && self.aig.gate(v).is_comb() {
+                    ret.insert(v);
+                    stack.push(v);
+                }
+            }
+        }
+        let size = ret
+            .iter()
+            .filter(|i| !matches!(&self.aig.gate(**i), Gate::Buf(_)))
+            .count();
+        (ret, size)
+    }
+
+    /// Signals that remain if a node is replaced: its transitive fanin, and the nodes that only
+    /// depend on it
+    fn divisors(&self, n: usize, mffc: &HashSet<usize>) -> Vec<Signal> {
+        let mut ret = Vec::new();
+        let mut seen = HashSet::new();
+        let mut frontier = vec![n];
+        for _ in 0..MAX_LEVELS {
+            let mut next = Vec::new();
+            for i in frontier {
+                for s in self.aig.dependencies(i) {
+                    let l = self.resolve(s.without_inversion()).without_inversion();
+                    if l.is_constant() || !seen.insert(l) {
+                        continue;
+                    }
+                    let is_comb = l.is_var() && self.aig.gate(l.var() as usize).is_comb();
+                    if is_comb {
+                        next.push(l.var() as usize);
+                    }
+                    if !(l.is_var() && mffc.contains(&(l.var() as usize))) {
+                        ret.push(l);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        ret.truncate(MAX_DIVISORS);
+        let mut i = 0;
+        while i < ret.len() && ret.len() < MAX_DIVISORS {
+            for &f in self.fanouts.fanouts(ret[i]) {
+                let g = self.aig.gate(f);
+                let l = Signal::from_var(f as u32);
+                if mffc.contains(&f)
+                    || self.refs[f] == 0
+                    || !g.is_comb()
+                    || matches!(g, Gate::Buf(_))
+                    || seen.contains(&l)
+                {
+                    continue;
+                }
+                let in_window = g.dependencies().iter().all(|s| {
+                    s.is_constant() || seen.contains(&self.resolve(*s).without_inversion())
+                });
+                if in_window {
+                    seen.insert(l);
+                    ret.push(l);
+                }
+            }
+            i += 1;
+        }
+        ret
+    }
+
+    /// Whether simulation values match those of the node
+    fn matches<F: Fn(usize) -> u64>(&self, n: usize, f: F) -> bool {
+        (0..NB_WORDS).all(|w| f(w) == self.sigs[n][w])
+    }
+
+    /// Candidates found by simulation, by increasing number of new gates
+    fn candidates(&self, n: usize, divisors: &[Signal], size: usize) -> Vec<(usize, Candidate)> {
+        let mut ret = Vec::new();
+        let pols = |s: Signal| [s, !s];
+        for c in [Signal::zero(), Signal::one()] {
+            if self.matches(n, |w| self.value(c, w)) {
+                ret.push((0, Candidate::Signal(c)));
+            }
+        }
+        for d in divisors {
+            for a in pols(*d) {
+                if self.matches(n, |w| self.value(a, w)) {
+                    ret.push((0, Candidate::Signal(a)));
+                }
+            }
+        }
+        if !ret.is_empty() || size < 2 {
+            return ret;
+        }
+        for (i, d1) in divisors.iter().enumerate() {
+            for d2 in &divisors[i + 1..] {
+                for a in pols(*d1) {
+                    for b in pols(*d2) {
+                        let and = |w| self.value(a, w) & self.value(b, w);
+                        if self.matches(n, and) {
+                            ret.push((1, Candidate::Gate(Gate::and(a, b), false)));
+                        } else if self.matches(n, |w| !and(w)) {
+                            ret.push((1, Candidate::Gate(Gate::and(a, b), true)));
+                        }
+                    }
+                }
+                let xor = |w| self.value(*d1, w) ^ self.value(*d2, w);
+                if self.matches(n, xor) {
+                    ret.push((1, Candidate::Gate(Gate::xor(*d1, *d2), false)));
+                } else if self.matches(n, |w| !xor(w)) {
+                    ret.push((1, Candidate::Gate(Gate::xor(*d1, *d2), true)));
+                }
+            }
+        }
+        if !ret.is_empty() || size < 3 {
+            return ret;
+        }
+        let divisors = &divisors[..divisors.len().min(MAX_DIVISORS_TWO_GATES)];
+        for (i, d1) in divisors.iter().enumerate() {
+            for (j, d2) in divisors.iter().enumerate().skip(i + 1) {
+                let mut inner = vec![Gate::xor(*d1, *d2)];
+                for a in pols(*d1) {
+                    for b in pols(*d2) {
+                        inner.push(Gate::and(a, b));
+                    }
+                }
+                for g in inner {
+                    let sig = self.eval(&g);
+                    for (k, d3) in divisors.iter().enumerate() {
+                        if k == i || k == j {
+                            continue;
+                        }
+                        for inv in [false, true] {
+                            for c in pols(*d3) {
+                                let v = |w: usize| {
+                                    let x = if inv { !sig[w] } else { sig[w] };
+                                    x & self.value(c, w)
+                                };
+                                if self.matches(n, v) {
+                                    ret.push((2, Candidate::TwoGates(g.clone(), inv, c, false)));
+                                } else if self.matches(n, |w| !v(w)) {
+                                    ret.push((2, Candidate::TwoGates(g.clone(), inv, c, true)));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    /// Add the gates of a candidate
+    fn build(&mut self, c: &Candidate) -> Signal {
+        match c {
+            Candidate::Signal(s) => *s,
+            Candidate::Gate(g, inv) => self.add(g.clone()) ^ *inv,
+            Candidate::TwoGates(g, inner_inv, c, inv) => {
+                let x = self.add(g.clone());
+                self.add(Gate::and(x ^ *inner_inv, *c)) ^ *inv
+            }
+        }
+    }
+
+    /// Try to replace a node, and return whether it was replaced
+    fn resubstitute(&mut self, n: usize) -> bool {
+        let (mffc, size) = self.mffc(n);
+        let divisors = self.divisors(n, &mffc);
+        let candidates = self.candidates(n, &divisors, size);
+        for (nb_gates, c) in candidates.into_iter().take(MAX_SAT_CHECKS) {
+            if nb_gates >= size {
+                continue;
+            }
+            let s = self.build(&c);
+            if s.is_var() {
+                self.refs[s.var() as usize] += 1;
+            }
+            if query_signals(&self.aig, self.aig.node(n), None, s, &[]).is_none() {
+                let old = self.aig.gate(n).clone();
+                self.fanouts.replace(&mut self.aig, n, Gate::Buf(s));
+                for d in old.dependencies() {
+                    self.deref(*d);
+                }
+                return true;
+            }
+            self.deref(s);
+        }
+        false
+    }
+}
+
+/// Replace nodes by simpler expressions of the signals of their transitive fanin
+///
+/// Candidates use no new gate, one new gate or two new gates. They are found by simulation and
+/// verified with a Sat solver, and the number of nodes of the network never increases.
+pub fn resubstitute(aig: &mut Network) {
+    resubstitute_with_rng(aig, &mut SeededRng::default());
+}
+
+/// Replace nodes by simpler expressions, with the simulation patterns taken from a given
+/// generator
+pub fn resubstitute_with_rng(aig: &mut Network, rng: &mut SeededRng) {
+    let mut work = aig.clone();
+    work.topo_sort();
+    work.make_canonical();
+    work.cleanup();
+    let nb_nodes = work.nb_nodes();
+    let mut r = Resubstitution::new(work, rng);
+    for n in 0..nb_nodes {
+        let g = r.aig.gate(n);
+        if g.is_comb() && !matches!(g, Gate::Buf(_)) && r.refs[n] > 0 {
+            r.resubstitute(n);
+        }
+    }
+    let mut ret = r.aig;
+    ret.topo_sort();
+    ret.make_canonical();
+    ret.cleanup();
+    *aig = ret;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resubstitute;
+    use crate::network::generators::adder;
+    use crate::sim::simulate;
+    use crate::{Gate, Network};
+
+    fn check_equivalent(a: &Network, b: &Network) {
+        b.check();
+        for seed in 0..64u64 {
+            let pattern: Vec<Vec<bool>> = (0..3u64)
+                .map(|t| {
+                    (0..a.nb_inputs() as u64)
+                        .map(|j| (seed.wrapping_mul(0x9e37_79b9) >> ((j + 5 * t) % 61)) & 1 != 0)
+                        .collect()
+                })
+                .collect();
+            assert_eq!(simulate(a, &pattern), simulate(b, &pattern));
+        }
+    }
+
+    #[test]
+    fn test_copy() {
+        // An Xor built with And gates is replaced by an existing Xor
+        let mut aig = Network::new();
+        aig.add_inputs(2);
+        let (a, b) = (aig.input(0), aig.input(1));
+        let x = aig.add(Gate::xor(a, b));
+        let t0 = aig.add(Gate::and(a, !b));
+        let t1 = aig.add(Gate::and(!a, b));
+        let y = aig.add(Gate::and(!t0, !t1));
+        aig.add_output(x);
+        aig.add_output(!y);
+        let mut opt = aig.clone();
+        resubstitute(&mut opt);
+        check_equivalent(&aig, &opt);
+        assert_eq!(opt.nb_nodes(), 1);
+    }
+
+    #[test]
+    fn test_one_gate() {
+        // (a & c) & b is rebuilt from a & b
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        let x = aig.add(Gate::and(a, b));
+        let t = aig.add(Gate::and(a, c));
+        let y = aig.add(Gate::and(t, b));
+        aig.add_output(x);
+        aig.add_output(y);
+        let mut opt = aig.clone();
+        resubstitute(&mut opt);
+        check_equivalent(&aig, &opt);
+        assert_eq!(opt.nb_nodes(), 2);
+    }
+
+    #[test]
+    fn test_no_increase() {
+        let mut aig = adder::ripple_carry(8);
+        aig.make_canonical();
+        let nb = aig.nb_nodes();
+        let mut opt = aig.clone();
+        resubstitute(&mut opt);
+        check_equivalent(&aig, &opt);
+        assert!(opt.nb_nodes() <= nb);
+    }
+
+    #[test]
+    fn test_sequential() {
+        // The register output is a divisor, and is free in the equivalence check
+        let mut aig = Network::new();
+        aig.add_inputs(2);
+        let (a, b) = (aig.input(0), aig.input(1));
+        let q = aig.add(Gate::dff(a, b, a));
+        let q = aig.node(q.var() as usize);
+        let x = aig.add(Gate::and(q, b));
+        let t = aig.add(Gate::and(q, a));
+        let y = aig.add(Gate::and(t, b));
+        aig.add_output(x);
+        aig.add_output(y);
+        let mut opt = aig.clone();
+        resubstitute(&mut opt);
+        check_equivalent(&aig, &opt);
+        assert_eq!(opt.nb_nodes(), 3);
+    }
+}
@@ -0,0 +1,679 @@
+//! DAG-aware rewriting of And and Xor gates with 4-input cuts
+//!
+//! For each node, the cuts of at most 4 leaves are enumerated through 2-input gates, and the
+//! function of the node is computed for each cut. Functions are grouped by NPN class (up to
+//! permutation and negation of the inputs and of the output), and an optimized structure of And
+//! and Xor gates is computed once for each class. The node is replaced by the structure if this
+//! saves gates, taking into account the gates that are freed by the replacement and the gates
+//! of the structure that already exist in the network.
+
+use std::collections::{HashMap, HashSet};
+
+use fxhash::FxHashMap;
+use itertools::Itertools;
+
+use crate::network::Normalization;
+use crate::optim::lut_map::{eval_gate, VAR_WORDS};
+use crate::{Gate, Network, Signal};
+
+/// Maximum number of cuts kept for each node
+const NB_CUTS: usize = 8;
+
+/// Truth tables of the 4 variables
+const VARS: [u16; 4] = [0xAAAA, 0xCCCC, 0xF0F0, 0xFF00];
+
+/// Cofactor of a 4-input function with respect to a variable
+fn cofactor(f: u16, v: usize, value: bool) -> u16 {
+    let s = 1 << v;
+    if value {
+        let c = f & VARS[v];
+        c | (c >> s)
+    } else {
+        let c = f & !VARS[v];
+        c | (c << s)
+    }
+}
+
+/// Variables of a 4-input function, as a bitmask
+fn support(f: u16) -> usize {
+    (0..4)
+        .filter(|v| cofactor(f, *v, false) != cofactor(f, *v, true))
+        .fold(0, |m, v| m | 1 << v)
+}
+
+/// Existential quantification of a set of variables
+fn exists(f: u16, vars: usize) -> u16 {
+    (0..4)
+        .filter(|v| vars >> v & 1 != 0)
+        .fold(f, |f, v| cofactor(f, v, false) | cofactor(f, v, true))
+}
+
+/// Cofactor of a set of variables, all set to zero
+fn cofactor_zero(f: u16, vars: usize) -> u16 {
+    (0..4)
+        .filter(|v| vars >> v & 1 != 0)
+        .fold(f, |f, v| cofactor(f, v, false))
+}
+
+/// NPN transformation of a 4-input function
+///
+/// The transformed function is `g(x) = out ^ f(z)` with `z[perm[j]] = x[j] ^ neg[j]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct NpnTransform {
+    perm: [usize; 4],
+    neg: usize,
+    out: bool,
+}
+
+impl NpnTransform {
+    fn apply(&self, f: u16) -> u16 {
+        let mut ret = 0;
+        for x in 0..16 {
+            let mut z = 0;
+            for j in 0..4 {
+                z |= ((x >> j & 1) ^ (self.neg >> j & 1)) << self.perm[j];
+            }
+            if (f >> z & 1 != 0) ^ self.out {
+                ret |= 1 << x;
+            }
+        }
+        ret
+    }
+}
+
+/// Representative of the NPN class of a 4-input function, with the transformation to obtain it
+fn npn_canonical(f: u16) -> (u16, NpnTransform) {
+    let mut best: Option<(u16, NpnTransform)> = None;
+    for perm in (0..4).permutations(4) {
+        for neg in 0..16 {
+            for out in [false, true] {
+                let t = NpnTransform {
+                    perm: [perm[0], perm[1], perm[2], perm[3]],
+                    neg,
+                    out,
+                };
+                let g = t.apply(f);
+                if best.is_none_or(|b| g < b.0) {
+                    best = Some((g, t));
+                }
+            }
+        }
+    }
+    best.unwrap()
+}
+
+/// Decomposition of a 4-input function, whose sub-functions have a smaller support
+#[derive(Clone, Copy, Debug)]
+enum Decomposition {
+    Const,
+    Var(usize),
+    And(u16, u16),
+    Xor(u16, u16),
+    /// `v ? f1 : f0`, with 3 And gates
+    Mux(usize, u16, u16),
+    /// `f0 ^ (v & (f0 ^ f1))`, with an And and a Xor gate
+    XorMux(usize, u16, u16),
+}
+
+/// Structure of And and Xor gates implementing a 4-input function
+///
+/// Operands are literals, `2 * index + inversion`, with index 0 the constant zero, 1 to 4 the
+/// inputs, and the gates afterwards.
+#[derive(Clone, Debug, Default)]
+struct Structure {
+    gates: Vec<(bool, u32, u32)>,
+    output: u32,
+}
+
+/// Optimized structures for the NPN classes of 4-input functions, computed on demand
+#[derive(Default)]
+struct Library {
+    npn: HashMap<u16, (u16, NpnTransform)>,
+    structures: HashMap<u16, Structure>,
+    /// Cost, decomposition and output inversion of each function
+    best: HashMap<u16, (u32, Decomposition, bool)>,
+}
+
+impl Library {
+    /// Number of gates of the best decomposition found for a function
+    fn cost(&mut self, f: u16) -> u32 {
+        if let Some(b) = self.best.get(&f) {
+            return b.0;
+        }
+        let sup = support(f);
+        let ret = if sup == 0 {
+            (0, Decomposition::Const, f != 0)
+        } else if sup.count_ones() == 1 {
+            let v = sup.trailing_zeros() as usize;
+            (0, Decomposition::Var(v), f != VARS[v])
+        } else {
+            let mut cands = Vec::new();
+            let low = sup & sup.wrapping_neg();
+            for a in 1..sup {
+                // Disjoint-support decompositions, each partition considered once
+                if a & !sup != 0 || a & low == 0 {
+                    continue;
+                }
+                let b = sup & !a;
+                for (target, inv) in [(f, false), (!f, true)] {
+                    let g = exists(target, b);
+                    let h = exists(target, a);
+                    if g & h == target {
+                        cands.push((
+                            1 + self.cost(g) + self.cost(h),
+                            Decomposition::And(g, h),
+                            inv,
+                        ));
+                    }
+                }
+                // With c = f(0), g = f(A, 0) and h = f(0, B) ^ c
+                let g = cofactor_zero(f, b);
+                let c = if f & 1 != 0 { !0 } else { 0 };
+                let h = cofactor_zero(f, a) ^ c;
+                if g ^ h == f {
+                    cands.push((
+                        1 + self.cost(g) + self.cost(h),
+                        Decomposition::Xor(g, h),
+                        false,
+                    ));
+                }
+            }
+            for v in (0..4).filter(|v| sup >> v & 1 != 0) {
+                let f0 = cofactor(f, v, false);
+                let f1 = cofactor(f, v, true);
+                cands.push((
+                    3 + self.cost(f0) + self.cost(f1),
+                    Decomposition::Mux(v, f1, f0),
+                    false,
+                ));
+                cands.push((
+                    2 + self.cost(f0) + self.cost(f0 ^ f1),
+                    Decomposition::XorMux(v, f0, f0 ^ f1),
+                    false,
+                ));
+            }
+            cands.into_iter().min_by_key(|c| c.0).unwrap()
+        };
+        self.best.insert(f, ret);
+        ret.0
+    }
+
+    /// Add the gates of a function to a structure, and return its literal
+    fn emit(&mut self, f: u16, s: &mut Structure, lits: &mut HashMap<u16, u32>) -> u32 {
+        if let Some(l) = lits.get(&f) {
+            return *l;
+        }
+        if let Some(l) = lits.get(&!f) {
+            return *l ^ 1;
+        }
+        self.cost(f);
+        let (_, d, inv) = self.best[&f];
+        let gate = |s: &mut Structure, is_xor: bool, a: u32, b: u32| -> u32 {
+            s.gates.push((is_xor, a, b));
+            2 * (4 + s.gates.len() as u32)
+        };
+        let lit = match d {
+            Decomposition::Const => 0,
+            Decomposition::Var(v) => 2 * (v as u32 + 1),
+            Decomposition::And(g, h) => {
+                let a = self.emit(g, s, lits);
+                let b = self.emit(h, s, lits);
+                gate(s, false, a, b)
+            }
+            Decomposition::Xor(g, h) => {
+                let a = self.emit(g, s, lits);
+                let b = self.emit(h, s, lits);
+                gate(s, true, a, b)
+            }
+            Decomposition::Mux(v, f1, f0) => {
+                let x = 2 * (v as u32 + 1);
+                let a = self.emit(f1, s, lits);
+                let b = self.emit(f0, s, lits);
+                let t1 = gate(s, false, x, a);
+                let t0 = gate(s, false, x ^ 1, b);
+                gate(s, false, t1 ^ 1, t0 ^ 1) ^ 1
+            }
+            Decomposition::XorMux(v, f0, d) => {
+                let x = 2 * (v as u32 + 1);
+                let a = self.emit(f0, s, lits);
+                let b = self.emit(d, s, lits);
+                let t = gate(s, false, x, b);
+                gate(s, true, a, t)
+            }
+        } ^ inv as u32;
+        lits.insert(f, lit);
+        lit
+    }
+
+    /// Structure of a function, and the literals to connect to its inputs and output
+    fn structure(&mut self, f: u16) -> (Structure, NpnTransform) {
+        let (repr, t) = *self.npn.entry(f).or_insert_with(|| npn_canonical(f));
+        if !self.structures.contains_key(&repr) {
+            let mut s = Structure::default();
+            let mut lits = HashMap::new();
+            s.output = self.emit(repr, &mut s, &mut lits);
+            self.structures.insert(repr, s);
+        }
+        (self.structures[&repr].clone(), t)
+    }
+}
+
+/// Network being rebuilt, with structural hashing
+struct Builder {
+    aig: Network,
+    strash: FxHashMap<Gate, Signal>,
+}
+
+impl Builder {
+    /// Find an existing gate, or return the normalized gate to create
+    fn find(&self, g: Gate) -> Result<Signal, (Gate, bool)> {
+        match g.make_canonical() {
+            Normalization::Copy(s) => Ok(s),
+            Normalization::Node(g, inv) => match self.strash.get(&g) {
+                Some(s) => Ok(*s ^ inv),
+                None => Err((g, inv)),
+            },
+        }
+    }
+
+    fn add(&mut self, g: Gate) -> Signal {
+        match self.find(g) {
+            Ok(s) => s,
+            Err((g, inv)) => {
+                let s = self.aig.add(g.clone());
+                self.strash.insert(g, s);
+                s ^ inv
+            }
+        }
+    }
+
+    /// Number of gates that a structure would create, given the gates that would be freed
+    fn count_new(&self, s: &Structure, inputs: &[Signal; 4], freed: &HashSet<u32>) -> usize {
+        let mut sigs: Vec<Option<Signal>> = vec![Some(Signal::zero())];
+        sigs.extend(inputs.iter().map(|s| Some(*s)));
+        let lit =
+            |sigs: &Vec<Option<Signal>>, l: u32| sigs[l as usize / 2].map(|s| s ^ (l & 1 != 0));
+        let mut ret = 0;
+        for (is_xor, a, b) in &s.gates {
+            let res = match (lit(&sigs, *a), lit(&sigs, *b)) {
+                (Some(a), Some(b)) => {
+                    let g = if *is_xor {
+                        Gate::xor(a, b)
+                    } else {
+                        Gate::and(a, b)
+                    };
+                    match self.find(g) {
+                        Ok(s) if !s.is_var() || !freed.contains(&s.var()) => Some(s),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            if res.is_none() {
+                ret += 1;
+            }
+            sigs.push(res);
+        }
+        ret
+    }
+
+    /// Add the gates of a structure
+    fn instantiate(&mut self, s: &Structure, inputs: &[Signal; 4]) -> Signal {
+        let mut sigs = vec![Signal::zero()];
+        sigs.extend(inputs);
+        let lit = |sigs: &Vec<Signal>, l: u32| sigs[l as usize / 2] ^ (l & 1 != 0);
+        for (is_xor, a, b) in &s.gates {
+            let (a, b) = (lit(&sigs, *a), lit(&sigs, *b));
+            let g = if *is_xor {
+                Gate::xor(a, b)
+            } else {
+                Gate::and(a, b)
+            };
+            let res = self.add(g);
+            sigs.push(res);
+        }
+        lit(&sigs, s.output)
+    }
+}
+
+/// Whether a node is a 2-input gate that can be rewritten
+fn is_rewritable(aig: &Network, i: usize) -> bool {
+    matches!(&aig.gate(i), Gate::Binary(_, _))
+}
+
+/// Enumerate the cuts of at most 4 leaves of each node, through 2-input gates
+fn enumerate_cuts(aig: &Network) -> Vec<Vec<Vec<Signal>>> {
+    let mut cuts: Vec<Vec<Vec<Signal>>> = vec![Vec::new(); aig.nb_nodes()];
+    for i in 0..aig.nb_nodes() {
+        if !is_rewritable(aig, i) {
+            continue;
+        }
+        let mut ret: Vec<Vec<Signal>> = vec![Vec::new()];
+        for s in aig.dependencies(i) {
+            if s.is_constant() {
+                continue;
+            }
+            let l = s.without_inversion();
+            let mut fanin_cuts = vec![vec![l]];
+            if l.is_var() {
+                fanin_cuts.extend(cuts[l.var() as usize].iter().cloned());
+            }
+            let mut next = Vec::new();
+            for c in &ret {
+                for f in &fanin_cuts {
+                    let mut m = c.clone();
+                    m.extend(f);
+                    m.sort();
+                    m.dedup();
+                    if m.len() <= 4 {
+                        next.push(m);
+                    }
+                }
+            }
+            ret = next;
+        }
+        ret.sort_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+        ret.dedup();
+        let mut kept: Vec<Vec<Signal>> = Vec::new();
+        for c in ret {
+            if kept.len() < NB_CUTS && !kept.iter().any(|k| k.iter().all(|l| c.contains(l))) {
+                kept.push(c);
+            }
+        }
+        cuts[i] = kept;
+    }
+    cuts
+}
+
+/// Truth table of a node as a function of the leaves of a cut
+fn truth_table(aig: &Network, i: usize, cut: &[Signal]) -> u16 {
+    fn eval(aig: &Network, s: Signal, values: &mut HashMap<Signal, u64>) -> u64 {
+        if s.is_constant() {
+            return if s == Signal::one() { !0 } else { 0 };
+        }
+        let inv = if s.is_inverted() { !0 } else { 0 };
+        let s = s.without_inversion();
+        if let Some(v) = values.get(&s) {
+            return *v ^ inv;
+        }
+        let g = aig.gate(s.var() as usize);
+        let v: Vec<u64> = g
+            .dependencies()
+            .iter()
+            .map(|d| eval(aig, *d, values))
+            .collect();
+        let ret = eval_gate(&g, &v);
+        values.insert(s, ret);
+        ret ^ inv
+    }
+    let mut values = HashMap::new();
+    for (j, l) in cut.iter().enumerate() {
+        values.insert(*l, VAR_WORDS[j]);
+    }
+    eval(aig, aig.node(i), &mut values) as u16
+}
+
+/// Maximum fanout-free cone of a node, bounded by the leaves of a cut
+fn mffc(aig: &Network, i: usize, cut: &[Signal], refs: &[usize]) -> Vec<usize> {
+    let mut ret = vec![i];
+    let mut derefs: HashMap<usize, usize> = HashMap::new();
+    let mut to_visit = vec![i];
+    while let Some(n) = to_visit.pop() {
+        for s in aig.dependencies(n) {
+            if !s.is_var() || cut.contains(&s.without_inversion()) {
+                continue;
+            }
+            let v = s.var() as usize;
+            let d = derefs.entry(v).or_insert(0);
+            *d += 1;
+            if *d == refs[v] {
+                ret.push(v);
+                to_visit.push(v);
+            }
+        }
+    }
+    ret
+}
+
+/// Rewrite the And and Xor gates of the network with optimized structures for 4-input cuts
+///
+/// Each node is replaced by the structure that saves the most gates, if any. The network is
+/// left unchanged if the rewriting does not reduce its number of nodes.
+pub fn rewrite(aig: &mut Network) {
+    let mut orig = aig.clone();
+    orig.topo_sort();
+    orig.make_canonical();
+    orig.cleanup();
+
+    let cuts = enumerate_cuts(&orig);
+    let mut refs = vec![0; orig.nb_nodes()];
+    for i in 0..orig.nb_nodes() {
+        for v in orig.gate(i).vars() {
+            refs[v as usize] += 1;
+        }
+    }
+    for o in 0..orig.nb_outputs() {
+        if orig.output(o).is_var() {
+            refs[orig.output(o).var() as usize] += 1;
+        }
+    }
+
+    let mut lib = Library::default();
+    let mut b = Builder {
+        aig: Network::new(),
+        strash: FxHashMap::with_capacity_and_hasher(orig.nb_nodes(), Default::default()),
+    };
+    b.aig.add_inputs(orig.nb_inputs());
+    let mut translation: Vec<Signal> = Vec::with_capacity(orig.nb_nodes());
+    let t = |s: &Signal, translation: &[Signal]| {
+        if s.is_var() {
+            translation[s.var() as usize] ^ s.is_inverted()
+        } else {
+            *s
+        }
+    };
+    for (i, node_cuts) in cuts.iter().enumerate() {
+        let g = orig.gate(i);
+        if !g.is_comb() {
+            // Flip-flops may use later nodes, and are filled once all nodes are translated
+            translation.push(b.aig.add(Gate::Buf(Signal::zero())));
+            continue;
+        }
+        let mut best: Option<(usize, Structure, [Signal; 4])> = None;
+        for cut in node_cuts {
+            let f = truth_table(&orig, i, cut);
+            let (s, tr) = lib.structure(f);
+            let cone = mffc(&orig, i, cut, &refs);
+            let freed: HashSet<u32> = cone[1..]
+                .iter()
+                .filter(|n| translation[**n].is_var())
+                .map(|n| translation[*n].var())
+                .collect();
+            let mut inputs = [Signal::zero(); 4];
+            for (j, input) in inputs.iter_mut().enumerate() {
+                let leaf = cut.get(tr.perm[j]).copied().unwrap_or(Signal::zero());
+                *input = t(&leaf, &translation) ^ (tr.neg >> j & 1 != 0);
+            }
+            let nb_new = b.count_new(&s, &inputs, &freed);
+            let gain = cone.len().saturating_sub(nb_new);
+            if gain > 0 && best.as_ref().is_none_or(|x| gain > x.0) {
+                let mut s = s;
+                s.output ^= tr.out as u32;
+                best = Some((gain, s, inputs));
+            }
+        }
+        let s = match best {
+            Some((_, s, inputs)) => b.instantiate(&s, &inputs),
+            None => b.add(g.remap(|s| t(s, &translation))),
+        };
+        translation.push(s);
+    }
+    for i in 0..orig.nb_nodes() {
+        let g = orig.gate(i);
+        if !g.is_comb() {
+            let node = translation[i].var() as usize;
+            b.aig.replace(node, g.remap(|s| t(s, &translation)));
+        }
+    }
+    for o in 0..orig.nb_outputs() {
+        b.aig.add_output(t(&orig.output(o), &translation));
+    }
+    let mut ret = b.aig;
+    ret.cleanup();
+    if ret.nb_nodes() < orig.nb_nodes() {
+        *aig = ret;
+    } else {
+        *aig = orig;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{npn_canonical, rewrite, Library};
+    use crate::network::generators::{adder, multiplier};
+    use crate::sim::simulate;
+    use crate::{Gate, Network, Signal};
+
+    fn check_equivalent(a: &Network, b: &Network) {
+        b.check();
+        for seed in 0..64u64 {
+            let pattern: Vec<Vec<bool>> = (0..3u64)
+                .map(|t| {
+                    (0..a.nb_inputs() as u64)
+                        .map(|j| (seed.wrapping_mul(0x9e37_79b9) >> ((j + 5 * t) % 61)) & 1 != 0)
+                        .collect()
+                })
+                .collect();
+            assert_eq!(simulate(a, &pattern), simulate(b, &pattern));
+        }
+    }
+
+    #[test]
+    fn test_npn() {
+        // And functions with any polarity are in the same class, and Xor functions too
+        let (and, _) = npn_canonical(0x8888);
+        assert_eq!(npn_canonical(0x2222).0, and);
+        assert_eq!(npn_canonical(0xA0A0).0, and);
+        assert_eq!(npn_canonical(!0x8888).0, and);
+        let (xor, _) = npn_canonical(0x6666);
+        assert_eq!(npn_canonical(0x9999).0, xor);
+        assert_ne!(and, xor);
+        for f in [0x8888u16, 0x1234, 0xCAFE, 0x6996] {
+            let (repr, t) = npn_canonical(f);
+            assert_eq!(t.apply(f), repr);
+        }
+    }
+
+    #[test]
+    fn test_structures() {
+        // Check the structures of all functions of 3 variables and a few of 4 variables
+        let mut lib = Library::default();
+        let funcs: Vec<u16> = (0..256u16)
+            .map(|f| f | f << 8)
+            .chain([0x1234, 0xCAFE, 0x6996, 0x8001, 0x7FFE])
+            .collect();
+        for f in funcs {
+            let (s, t) = lib.structure(f);
+            let mut aig = Network::new();
+            aig.add_inputs(4);
+            let mut sigs: Vec<Signal> = vec![Signal::zero()];
+            for j in 0..4 {
+                sigs.push(aig.input(t.perm[j]) ^ (t.neg >> j & 1 != 0));
+            }
+            for (is_xor, a, b) in &s.gates {
+                let (a, b) = (
+                    sigs[*a as usize / 2] ^ (a & 1 != 0),
+                    sigs[*b as usize / 2] ^ (b & 1 != 0),
+                );
+                let g = if *is_xor {
+                    Gate::xor(a, b)
+                } else {
+                    Gate::and(a, b)
+                };
+                sigs.push(aig.add_canonical(g));
+            }
+            let out = sigs[s.output as usize / 2] ^ (s.output & 1 != 0) ^ t.out;
+            aig.add_output(out);
+            for x in 0..16 {
+                let v: Vec<bool> = (0..4).map(|j| x >> j & 1 != 0).collect();
+                assert_eq!(
+                    simulate(&aig, &vec![v])[0][0],
+                    f >> x & 1 != 0,
+                    "Wrong structure for {:04x}",
+                    f
+                );
+            }
+        }
+        // Known optimal costs
+        assert_eq!(lib.cost(0x8888), 1);
+        assert_eq!(lib.cost(0x6666), 1);
+        assert_eq!(lib.cost(0x6996), 3);
+        assert_eq!(lib.cost(0xCACA), 3);
+    }
+
+    #[test]
+    fn test_redundant() {
+        // (a & b) | (a & c) | (a & !b & !c), which is just a
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let [a, b, c] = [0, 1, 2].map(|i| aig.input(i));
+        let x = aig.and(a, b);
+        let y = aig.and(a, c);
+        let z = aig.and(!b, !c);
+        let z = aig.and(a, z);
+        let o = aig.and(!x, !y);
+        let o = aig.and(o, !z);
+        aig.add_output(!o);
+        let mut opt = aig.clone();
+        rewrite(&mut opt);
+        check_equivalent(&aig, &opt);
+        assert_eq!(opt.nb_nodes(), 0);
+        assert_eq!(opt.output(0), a);
+    }
+
+    #[test]
+    fn test_factor() {
+        // (a & b) ^ (a & c) is factored as a & (b ^ c)
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let [a, b, c] = [0, 1, 2].map(|i| aig.input(i));
+        let x = aig.and(a, b);
+        let y = aig.and(a, c);
+        let o = aig.xor(x, y);
+        aig.add_output(o);
+        let mut opt = aig.clone();
+        rewrite(&mut opt);
+        check_equivalent(&aig, &opt);
+        assert_eq!(opt.nb_nodes(), 2);
+    }
+
+    #[test]
+    fn test_no_increase() {
+        for aig in [adder::ripple_carry(8), multiplier::array(4)] {
+            let mut opt = aig.clone();
+            rewrite(&mut opt);
+            check_equivalent(&aig, &opt);
+            let mut reference = aig.clone();
+            reference.make_canonical();
+            reference.cleanup();
+            assert!(opt.nb_nodes() <= reference.nb_nodes());
+        }
+    }
+
+    #[test]
+    fn test_sequential() {
+        let mut aig = Network::new();
+        aig.add_inputs(2);
+        let [a, b] = [0, 1].map(|i| aig.input(i));
+        let q = aig.add(Gate::dff(Signal::zero(), Signal::one(), Signal::zero()));
+        let x = aig.and(a, q);
+        let y = aig.and(a, b);
+        let o = aig.xor(x, y);
+        aig.replace(0, Gate::dff(o, Signal::one(), Signal::zero()));
+        aig.add_output(q);
+        let mut opt = aig.clone();
+        rewrite(&mut opt);
+        check_equivalent(&aig, &opt);
+        assert_eq!(opt.nb_nodes(), 3);
+    }
+}
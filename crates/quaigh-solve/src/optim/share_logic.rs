@@ -9,7 +9,11 @@ use std::iter::zip;
 use fxhash::{FxHashMap, FxHashSet};
 use itertools::Itertools;
 
-use crate::network::NaryType;
+use crate::network::depth::DelayParameters;
+use crate::network::timing::{Slacks, TimingConstraints};
+use crate::network::{GrowthLimitExceeded, NaryType};
+use crate::optim::balance::rebalance;
+use crate::optim::share_mux;
 use crate::{Gate, Network, Signal};
 
 /// Helper functions to merge N-input gates, to specialize by And/Xor
@@ -29,7 +33,7 @@ fn merge_dependencies<F: Fn(&Gate) -> bool>(
         } else {
             let prev_g = aig.gate(s.var() as usize);
             let prev_deps = prev_g.dependencies();
-            if pred(prev_g) && ret.len() + prev_deps.len() + remaining <= max_size {
+            if pred(&prev_g) && ret.len() + prev_deps.len() + remaining <= max_size {
                 ret.extend(prev_deps);
             } else {
                 ret.push(*s);
@@ -51,7 +55,7 @@ pub fn flatten_nary(aig: &Network, max_size: usize) -> Network {
             ret.replace(
                 i,
                 Gate::Nary(
-                    merge_dependencies(&ret, ret.gate(i), max_size, |t| t.is_and()),
+                    merge_dependencies(&ret, &ret.gate(i), max_size, |t| t.is_and()).into(),
                     NaryType::And,
                 ),
             );
@@ -59,7 +63,7 @@ pub fn flatten_nary(aig: &Network, max_size: usize) -> Network {
             ret.replace(
                 i,
                 Gate::Nary(
-                    merge_dependencies(&ret, ret.gate(i), max_size, |t| t.is_xor()),
+                    merge_dependencies(&ret, &ret.gate(i), max_size, |t| t.is_xor()).into(),
                     NaryType::Xor,
                 ),
             );
@@ -70,6 +74,57 @@ pub fn flatten_nary(aig: &Network, max_size: usize) -> Network {
     ret
 }
 
+/// Upper bound on the size of a network flattened with [`flatten_nary`], once decomposed
+///
+/// Flattening does not add nodes, but the total number of gate inputs may grow quadratically.
+/// The size is counted as the number of 2-input gates needed to decompose the flattened gates,
+/// before unused gates are removed.
+pub fn flattened_size(aig: &Network, max_size: usize) -> usize {
+    let mut sizes: Vec<usize> = (0..aig.nb_nodes())
+        .map(|i| aig.dependencies(i).len())
+        .collect();
+    let mut ret: usize = 0;
+    for i in 0..aig.nb_nodes() {
+        let g = aig.gate(i);
+        if !g.is_and() && !g.is_xor() {
+            ret = ret.saturating_add(1);
+            continue;
+        }
+        let v = g.dependencies();
+        let mut size: usize = 0;
+        let mut remaining = v.len();
+        for s in v.iter() {
+            remaining -= 1;
+            if !s.is_var() || s.is_inverted() {
+                size += 1;
+                continue;
+            }
+            let j = s.var() as usize;
+            let prev_g = aig.gate(j);
+            let same = (g.is_and() && prev_g.is_and()) || (g.is_xor() && prev_g.is_xor());
+            if same && size + sizes[j] + remaining <= max_size {
+                size = size.saturating_add(sizes[j]);
+            } else {
+                size += 1;
+            }
+        }
+        sizes[i] = size;
+        ret = ret.saturating_add(std::cmp::max(size, 2) - 1);
+    }
+    ret
+}
+
+/// Flatten And and Xor gates as [`flatten_nary`], failing before allocating anything if the
+/// result would be larger than `max_nodes`, as computed by [`flattened_size`]
+pub fn flatten_nary_with_limit(
+    aig: &Network,
+    max_size: usize,
+    max_nodes: usize,
+) -> Result<Network, GrowthLimitExceeded> {
+    GrowthLimitExceeded::check("flatten", flattened_size(aig, max_size), max_nodes)?;
+    Ok(flatten_nary(aig, max_size))
+}
+
 /// Datastructure representing the factorization process
 struct Factoring {
     /// Gates left to factor
@@ -303,7 +358,7 @@ fn factor_gates<F: Fn(&Gate) -> bool, G: Fn(Signal, Signal) -> Gate>(
     let mut gates = Vec::new();
     for i in 0..aig.nb_nodes() {
         let g = aig.gate(i);
-        if pred(g) && g.dependencies().len() > 1 {
+        if pred(&g) && g.dependencies().len() > 1 {
             gates.push(g.dependencies().into());
             inds.push(i);
         }
@@ -338,16 +393,99 @@ pub fn factor_nary(aig: &Network) -> Network {
 
 /// Share logic between N-ary gates
 ///
-/// Reorganizes logic into N-input gates, then creates trees of 2-input gates that share as much logic as possible
+/// Reorganizes logic into N-input gates, then creates trees of 2-input gates that share as much logic as possible.
+/// Equivalent Mux gates are shared afterwards.
 pub fn share_logic(aig: &mut Network, flattening_limit: usize) {
     *aig = flatten_nary(&aig, flattening_limit);
     *aig = factor_nary(&aig);
+    share_mux(aig);
+}
+
+/// Delay of a gate once decomposed into a balanced tree of 2-input gates
+fn decomposed_delay(delays: &DelayParameters, g: &Gate) -> usize {
+    let n = g.dependencies().len();
+    if n <= 2 || !(g.is_and() || g.is_xor()) {
+        return delays.gate_delay(g);
+    }
+    let depth = n.next_power_of_two().trailing_zeros() as usize;
+    if g.is_and() {
+        depth * delays.and
+    } else {
+        depth * delays.xor
+    }
+}
+
+/// Decompose a flattened network, balancing the selected gates and sharing logic between the others
+fn share_logic_except<F: Fn(usize) -> bool>(
+    flat: &Network,
+    delays: &DelayParameters,
+    input_time: &[usize],
+    select: F,
+) -> Network {
+    let mut ret = flat.clone();
+    rebalance(&mut ret, delays, input_time, select);
+    ret.topo_sort();
+    ret.make_canonical();
+    let mut ret = factor_nary(&ret);
+    share_mux(&mut ret);
+    ret
+}
+
+/// Share logic between N-ary gates, except on the paths that violate the timing constraints
+///
+/// As [`share_logic`], but the slacks are computed after flattening, assuming that each gate will
+/// be decomposed as a balanced tree. Gates whose slack is below a margin are rebuilt to minimize
+/// their arrival time as in [`balance`](super::balance), taking the arrival time of the inputs into
+/// account, and logic is shared between the others only. Since sharing lengthens the paths, the
+/// margin starts at zero and is doubled until the result meets the constraints, or is as good as
+/// the balanced estimate. If the constraints are met with sharing everywhere, this is the same as
+/// [`share_logic`].
+pub fn share_logic_with_constraints(
+    aig: &mut Network,
+    flattening_limit: usize,
+    delays: &DelayParameters,
+    constraints: &TimingConstraints,
+) {
+    let flat = flatten_nary(aig, flattening_limit);
+    let gate_delays: Vec<usize> = (0..flat.nb_nodes())
+        .map(|i| decomposed_delay(delays, &flat.gate(i)))
+        .collect();
+    let slacks = Slacks::compute(&flat, &gate_delays, constraints);
+    let Some(target) = slacks.worst_slack() else {
+        share_logic(aig, flattening_limit);
+        return;
+    };
+    let target = target.min(0);
+    let max_slack = (0..flat.nb_nodes())
+        .filter_map(|i| slacks.slack(i))
+        .max()
+        .unwrap_or(0);
+    let mut margin = 0;
+    loop {
+        let ret = share_logic_except(&flat, delays, &constraints.input_arrival, |i| {
+            slacks.slack(i).is_some_and(|s| s < margin)
+        });
+        let achieved = Slacks::compute(&ret, &delays.gate_delays(&ret), constraints)
+            .worst_slack()
+            .unwrap_or(0);
+        if achieved >= target || margin > max_slack {
+            *aig = ret;
+            return;
+        }
+        margin = std::cmp::max(2 * margin, 1);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{factor_nary, flatten_nary};
-    use crate::network::NaryType;
+    use super::{
+        factor_nary, flatten_nary, flatten_nary_with_limit, flattened_size, share_logic,
+        share_logic_with_constraints,
+    };
+    use crate::equiv::check_equivalence_comb;
+    use crate::network::depth::{depth, DelayParameters};
+    use crate::network::timing::{Slacks, TimingConstraints};
+    use crate::network::{GrowthLimitExceeded, NaryType};
     use crate::{Gate, Network, Signal};
 
     #[test]
@@ -367,7 +505,7 @@ mod tests {
         assert_eq!(aig.nb_nodes(), 1);
         assert_eq!(
             aig.gate(0),
-            &Gate::Nary(Box::new([i4, !i2, i1, i0]), NaryType::And)
+            Gate::Nary([i4, !i2, i1, i0].into(), NaryType::And)
         );
     }
 
@@ -386,10 +524,34 @@ mod tests {
         aig.add_output(x3);
         aig = flatten_nary(&aig, 64);
         assert_eq!(aig.nb_nodes(), 1);
-        assert_eq!(aig.gate(0), &Gate::xor3(i4, i2, i1));
+        assert_eq!(aig.gate(0), Gate::xor3(i4, i2, i1));
         assert_eq!(aig.output(0), !Signal::from_var(0));
     }
 
+    #[test]
+    fn test_flatten_limit() {
+        // A chain of And gates, whose flattening is quadratic before cleanup
+        let mut aig = Network::new();
+        let mut x = aig.add_input();
+        for _ in 0..100 {
+            let i = aig.add_input();
+            x = aig.and(x, i);
+        }
+        aig.add_output(x);
+        assert_eq!(flattened_size(&aig, usize::MAX), 5050);
+        assert!(flattened_size(&aig, 8) < 800);
+        assert_eq!(
+            flatten_nary_with_limit(&aig, usize::MAX, 5000).unwrap_err(),
+            GrowthLimitExceeded {
+                pass: "flatten",
+                projected: 5050,
+                limit: 5000
+            }
+        );
+        let flat = flatten_nary_with_limit(&aig, usize::MAX, 5050).unwrap();
+        assert_eq!(flat.nb_nodes(), 1);
+    }
+
     #[test]
     fn test_share_and() {
         let mut aig = Network::new();
@@ -398,15 +560,55 @@ mod tests {
         let i2 = aig.add_input();
         let i3 = aig.add_input();
         let i4 = aig.add_input();
-        let x0 = aig.add(Gate::Nary(Box::new([i0, i1, i2]), NaryType::And));
-        let x1 = aig.add(Gate::Nary(Box::new([i0, i1, i2, i3]), NaryType::And));
-        let x2 = aig.add(Gate::Nary(Box::new([i1, i2, i4]), NaryType::And));
+        let x0 = aig.add(Gate::Nary([i0, i1, i2].into(), NaryType::And));
+        let x1 = aig.add(Gate::Nary([i0, i1, i2, i3].into(), NaryType::And));
+        let x2 = aig.add(Gate::Nary([i1, i2, i4].into(), NaryType::And));
         aig.add_output(x0);
         aig.add_output(x1);
         aig.add_output(x2);
         aig = factor_nary(&aig);
         assert_eq!(aig.nb_nodes(), 4);
         // Check that the first gate is the most shared
-        assert_eq!(aig.gate(0), &Gate::and(i2, i1));
+        assert_eq!(aig.gate(0), Gate::and(i2, i1));
+    }
+
+    #[test]
+    fn test_share_with_constraints() {
+        // Two large And gates sharing most of their inputs, which sharing turns into chains
+        let mut aig = Network::new();
+        aig.add_inputs(9);
+        let shared: Vec<Signal> = (0..7).map(|i| aig.input(i)).collect();
+        for i in 7..9 {
+            let mut v = shared.clone();
+            v.push(aig.input(i));
+            let x = aig.add(Gate::Nary(v.into(), NaryType::And));
+            aig.add_output(x);
+        }
+        let mut shared_aig = aig.clone();
+        share_logic(&mut shared_aig, 64);
+        assert!(depth(&shared_aig) > 3);
+
+        // Without violation, logic is shared as usual
+        let relaxed = TimingConstraints::with_clock_period(&aig, 10);
+        let mut relaxed_aig = aig.clone();
+        share_logic_with_constraints(&mut relaxed_aig, 64, &DelayParameters::unit(), &relaxed);
+        assert_eq!(relaxed_aig.nb_nodes(), shared_aig.nb_nodes());
+
+        // With a tight clock period, the critical gates are balanced
+        let tight = TimingConstraints::with_clock_period(&aig, 3);
+        let mut tight_aig = aig.clone();
+        share_logic_with_constraints(&mut tight_aig, 64, &DelayParameters::unit(), &tight);
+        assert_eq!(depth(&tight_aig), 3);
+        check_equivalence_comb(&aig, &tight_aig, false).unwrap();
+
+        // A late input is combined last, so that the constraints are still met
+        let mut late = TimingConstraints::with_clock_period(&aig, 4);
+        late.input_arrival[8] = 1;
+        let mut late_aig = aig.clone();
+        share_logic_with_constraints(&mut late_aig, 64, &DelayParameters::unit(), &late);
+        let delays = DelayParameters::unit().gate_delays(&late_aig);
+        let slacks = Slacks::compute(&late_aig, &delays, &late);
+        assert_eq!(slacks.worst_slack(), Some(0));
+        check_equivalence_comb(&aig, &late_aig, false).unwrap();
     }
 }
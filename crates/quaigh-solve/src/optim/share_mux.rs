@@ -0,0 +1,196 @@
+//! Logic sharing between Mux gates
+//!
+//! Canonicalization already merges muxes that are identical up to the polarity of their select,
+//! but muxes whose data inputs are equivalent without being the same node are kept separate.
+//! This pass groups muxes by select signal, finds candidate equivalences with random simulation,
+//! proves them with a Sat solver, and replaces the redundant muxes by a copy of a representative.
+
+use fxhash::FxHashMap;
+use rand::Rng;
+
+use crate::atpg::expose_dff;
+use crate::equiv::prove;
+use crate::network::TernaryType;
+use crate::sim::simulate_nodes_multi;
+use crate::util::rng::SeededRng;
+use crate::{Gate, Network, Signal};
+
+/// Number of 64b words of random simulation used for the signatures
+const NB_SIGNATURE_WORDS: usize = 4;
+
+/// Compute the simulation signature of each node with random inputs
+///
+/// The network must be combinatorial.
+fn node_signatures(aig: &Network, seed: u64) -> Vec<[u64; NB_SIGNATURE_WORDS]> {
+    let mut rng = SeededRng::new(seed);
+    let mut ret = vec![[0; NB_SIGNATURE_WORDS]; aig.nb_nodes()];
+    for w in 0..NB_SIGNATURE_WORDS {
+        let inputs: Vec<u64> = (0..aig.nb_inputs()).map(|_| rng.gen()).collect();
+        for (r, v) in ret.iter_mut().zip(simulate_nodes_multi(aig, &inputs)) {
+            r[w] = v;
+        }
+    }
+    ret
+}
+
+/// Normalize a signature so that complemented nodes get the same one, and return whether it was inverted
+fn normalize_signature(sig: [u64; NB_SIGNATURE_WORDS]) -> ([u64; NB_SIGNATURE_WORDS], bool) {
+    if sig[0] & 1 != 0 {
+        (sig.map(|w| !w), true)
+    } else {
+        (sig, false)
+    }
+}
+
+/// Prove that two signals of a combinatorial network are always equal
+fn prove_equal(aig: &Network, a: Signal, b: Signal) -> bool {
+    let mut miter = Network::new();
+    miter.add_inputs(aig.nb_inputs());
+    for i in 0..aig.nb_nodes() {
+        miter.add(aig.gate(i).clone());
+    }
+    let x = miter.xor(a, b);
+    miter.add_output(x);
+    miter.make_canonical();
+    miter.cleanup();
+    prove(&miter).is_none()
+}
+
+/// Share Mux gates that are equivalent up to a complement
+///
+/// Muxes with the same select signal are compared using random simulation, and candidate
+/// equivalences are proven with a Sat solver. Redundant muxes are replaced by a possibly inverted
+/// copy of the first equivalent mux, and removed.
+pub fn share_mux(aig: &mut Network) {
+    aig.topo_sort();
+    // Flip-flops are treated as free inputs, which keeps the node indices unchanged
+    let comb = if aig.is_comb() {
+        aig.clone()
+    } else {
+        expose_dff(aig)
+    };
+    let signatures = node_signatures(&comb, 1);
+
+    let mut representatives =
+        FxHashMap::<(Signal, [u64; NB_SIGNATURE_WORDS]), Vec<Signal>>::default();
+    let mut changed = false;
+    for (i, sig) in signatures.iter().enumerate() {
+        let Gate::Ternary([s, _, _], TernaryType::Mux) = &aig.gate(i) else {
+            continue;
+        };
+        let (sig, inv) = normalize_signature(*sig);
+        let node = aig.node(i) ^ inv;
+        let candidates = representatives
+            .entry((s.without_inversion(), sig))
+            .or_default();
+        if let Some(rep) = candidates
+            .iter()
+            .find(|rep| prove_equal(&comb, **rep, node))
+        {
+            aig.replace(i, Gate::Buf(*rep ^ inv));
+            changed = true;
+        } else {
+            candidates.push(node);
+        }
+    }
+    if changed {
+        aig.make_canonical();
+        aig.cleanup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::share_mux;
+    use crate::equiv::{check_equivalence_bounded, check_equivalence_comb};
+    use crate::network::TernaryType;
+    use crate::{Gate, Network};
+
+    fn nb_muxes(aig: &Network) -> usize {
+        (0..aig.nb_nodes())
+            .filter(|i| matches!(&aig.gate(*i), Gate::Ternary(_, TernaryType::Mux)))
+            .count()
+    }
+
+    #[test]
+    fn test_share_disguised_muxes() {
+        let mut aig = Network::new();
+        let s = aig.add_input();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let i2 = aig.add_input();
+
+        // Two structurally different versions of the same Xor
+        let x = aig.xor(i0, i1);
+        let a = aig.and(i0, !i1);
+        let b = aig.and(!i0, i1);
+        let y = !aig.and(!a, !b);
+
+        // Muxes that are the same function in disguise
+        let m0 = aig.add(Gate::mux(s, x, i2));
+        let m1 = !aig.add(Gate::mux(s, !y, !i2));
+        let m2 = aig.add(Gate::mux(!s, i2, y));
+        let m3 = !aig.add(Gate::mux(!s, !i2, !x));
+        // A mux with the same select that is different
+        let m4 = aig.add(Gate::mux(s, x, !i2));
+        for m in [m0, m1, m2, m3, m4] {
+            aig.add_output(m);
+        }
+        let mut opt = aig.clone();
+        opt.make_canonical();
+        assert_eq!(nb_muxes(&opt), 3);
+
+        share_mux(&mut opt);
+        assert_eq!(nb_muxes(&opt), 2);
+        assert_eq!(opt.output(0), opt.output(1));
+        assert_eq!(opt.output(0), opt.output(2));
+        assert_eq!(opt.output(0), opt.output(3));
+        assert_ne!(opt.output(0), opt.output(4));
+        check_equivalence_comb(&aig, &opt, false).unwrap();
+    }
+
+    #[test]
+    fn test_share_complement_muxes() {
+        let mut aig = Network::new();
+        let s = aig.add_input();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let x = aig.xor(i0, i1);
+        let a = aig.and(i0, !i1);
+        let b = aig.and(!i0, i1);
+        let y = !aig.and(!a, !b);
+        let m0 = aig.add(Gate::mux(s, x, i0));
+        // Complement of the first mux, with different data inputs
+        let m1 = aig.add(Gate::mux(s, !y, !i0));
+        aig.add_output(m0);
+        aig.add_output(m1);
+        let mut opt = aig.clone();
+        opt.make_canonical();
+        share_mux(&mut opt);
+        assert_eq!(nb_muxes(&opt), 1);
+        assert_eq!(opt.output(0), !opt.output(1));
+        check_equivalence_comb(&aig, &opt, false).unwrap();
+    }
+
+    #[test]
+    fn test_share_sequential_muxes() {
+        let mut aig = Network::new();
+        let s = aig.add_input();
+        let i0 = aig.add_input();
+        let i1 = aig.add_input();
+        let d = aig.dff(i0, i1, s);
+        let x = aig.xor(d, i1);
+        let a = aig.and(d, !i1);
+        let b = aig.and(!d, i1);
+        let y = !aig.and(!a, !b);
+        let m0 = aig.add(Gate::mux(s, x, d));
+        let m1 = aig.add(Gate::mux(s, y, d));
+        aig.add_output(m0);
+        aig.add_output(m1);
+        let mut opt = aig.clone();
+        opt.make_canonical();
+        share_mux(&mut opt);
+        assert_eq!(nb_muxes(&opt), 1);
+        check_equivalence_bounded(&aig, &opt, 3, false).unwrap();
+    }
+}
@@ -0,0 +1,320 @@
+//! Resynthesis of small windows with exact synthesis
+//!
+//! For each node, a window of at most 10 inputs is extracted around it. Its function is computed
+//! exhaustively, and the input values that never appear in random simulation of the whole
+//! network are treated as don't cares. The window is replaced by the smallest implementation
+//! found by [`exact_synthesis`] if this saves gates: only the gates that are not used outside of
+//! the window are freed. Replacements that rely on don't cares are verified with a Sat solver
+//! on the whole network. The number of gates tried by exact synthesis is limited for windows
+//! with many care rows, as proving that no smaller circuit exists quickly becomes expensive.
+//!
+//! Flip-flops are treated as free variables, and their inputs must be preserved exactly.
+
+use rand::Rng;
+
+use crate::network::TernaryType;
+use crate::network::Window;
+use crate::optim::dont_care::{comb_view_with_translation, dont_care_view, find_difference};
+use crate::optim::exact_synthesis;
+use crate::sim::{simulate_comb_multi, simulate_nodes_multi};
+use crate::util::rng::SeededRng;
+use crate::{Gate, Network, Signal};
+
+/// Maximum number of inputs of the windows
+pub const MAX_WINDOW_INPUTS: usize = 10;
+
+/// Maximum number of gates in a window
+const MAX_WINDOW_SIZE: usize = 32;
+
+/// Number of 64b words of random simulation used to compute the don't cares
+const NB_WORDS: usize = 16;
+
+/// Number of counterexamples added before giving up on a window
+const MAX_RETRIES: usize = 4;
+
+/// Bound on the work of exact synthesis on a window, as the number of care rows times 4 to the
+/// power of the number of gates
+const MAX_SYNTHESIS_EFFORT: usize = 1 << 15;
+
+/// Number of 2-input gates to implement a gate, if it can be resynthesized
+fn gate_cost(g: &Gate) -> Option<usize> {
+    match g {
+        Gate::Binary(..) => Some(1),
+        Gate::Ternary(_, TernaryType::And | TernaryType::Xor) => Some(2),
+        Gate::Ternary(_, TernaryType::Mux) => Some(3),
+        Gate::Ternary(_, TernaryType::Maj) => Some(4),
+        Gate::Nary(v, _) => Some(v.len().saturating_sub(1)),
+        Gate::Buf(_) => Some(0),
+        _ => None,
+    }
+}
+
+/// Number of uses of each node by gates and outputs
+fn reference_counts(aig: &Network) -> Vec<usize> {
+    let mut ret = vec![0; aig.nb_nodes()];
+    let mut add = |s: &Signal| {
+        if s.is_var() {
+            ret[s.var() as usize] += 1;
+        }
+    };
+    for i in 0..aig.nb_nodes() {
+        aig.dependencies(i).iter().for_each(&mut add);
+    }
+    for o in 0..aig.nb_outputs() {
+        add(&aig.output(o));
+    }
+    ret
+}
+
+/// Number of 2-input gates freed by replacing the root of a window, if they can all be
+/// resynthesized
+fn freed_cost(aig: &Network, window: &Window, refs: &mut [usize]) -> Option<usize> {
+    let mut stack = vec![*window.roots.first().unwrap()];
+    let mut cost = 0;
+    while let Some(i) = stack.pop() {
+        let g = aig.gate(i);
+        cost += gate_cost(&g)?;
+        for s in g.dependencies() {
+            if !s.is_var() {
+                continue;
+            }
+            let j = s.var() as usize;
+            refs[j] -= 1;
+            if refs[j] == 0 && window.nodes.binary_search(&j).is_ok() {
+                stack.push(j);
+            }
+        }
+    }
+    Some(cost)
+}
+
+/// Simulation of the combinational view of a network, to find the values that may appear in it
+struct Patterns {
+    patterns: Vec<Vec<u64>>,
+    view: Network,
+    translation: Vec<Signal>,
+    values: Vec<Vec<u64>>,
+}
+
+impl Patterns {
+    fn new(aig: &Network, patterns: Vec<Vec<u64>>) -> Patterns {
+        let (view, translation) = comb_view_with_translation(aig);
+        let values = patterns
+            .iter()
+            .map(|p| simulate_nodes_multi(&view, p))
+            .collect();
+        Patterns {
+            patterns,
+            view,
+            translation,
+            values,
+        }
+    }
+
+    /// Value of a signal of the network for a word of patterns
+    fn value(&self, word: usize, s: Signal) -> u64 {
+        let t = if s.is_var() {
+            self.translation[s.var() as usize]
+        } else {
+            s.without_inversion()
+        };
+        let v = if t.is_input() {
+            self.patterns[word][t.input() as usize]
+        } else if t.is_var() {
+            self.values[word][t.var() as usize]
+        } else {
+            0
+        };
+        if t.is_inverted() ^ s.is_inverted() {
+            !v
+        } else {
+            v
+        }
+    }
+
+    /// Rows of the window's truth table that appear in simulation
+    fn care_set(&self, window: &Window) -> Vec<bool> {
+        let mut ret = vec![false; 1 << window.inputs.len()];
+        for word in 0..self.patterns.len() {
+            let values: Vec<u64> = window.inputs.iter().map(|s| self.value(word, *s)).collect();
+            for bit in 0..64 {
+                let row = values
+                    .iter()
+                    .enumerate()
+                    .fold(0, |r, (j, v)| r | ((v >> bit & 1) as usize) << j);
+                ret[row] = true;
+            }
+        }
+        ret
+    }
+}
+
+/// Complete truth table of a single-output network
+fn truth_table(w: &Network) -> Vec<bool> {
+    let nb_rows = 1 << w.nb_inputs();
+    let mut ret = Vec::with_capacity(nb_rows);
+    for start in (0..nb_rows).step_by(64) {
+        let inputs: Vec<u64> = (0..w.nb_inputs())
+            .map(|j| {
+                (0..64)
+                    .filter(|b| (start + b) >> j & 1 != 0)
+                    .fold(0, |v, b| v | 1 << b)
+            })
+            .collect();
+        let out = simulate_comb_multi(w, &inputs)[0];
+        ret.extend((0..64.min(nb_rows)).map(|b| out >> b & 1 != 0));
+    }
+    ret
+}
+
+/// Maximum number of gates for exact synthesis on a window with a given number of care rows
+fn synthesis_gate_limit(nb_care: usize) -> usize {
+    let mut ret = 0;
+    while nb_care.max(1) << (2 * (ret + 1)) <= MAX_SYNTHESIS_EFFORT {
+        ret += 1;
+    }
+    ret
+}
+
+/// Resynthesize windows of a network with exact synthesis
+///
+/// Windows have at most `max_inputs` inputs, up to [`MAX_WINDOW_INPUTS`], and are replaced by
+/// implementations of at most `max_gates` 2-input gates. The cost of exact synthesis grows very
+/// quickly with both.
+pub fn window_resynth(aig: &mut Network, max_inputs: usize, max_gates: usize) {
+    window_resynth_with_rng(aig, max_inputs, max_gates, &mut SeededRng::default());
+}
+
+/// Resynthesize windows of a network, with the simulation patterns taken from a given generator
+pub fn window_resynth_with_rng(
+    aig: &mut Network,
+    max_inputs: usize,
+    max_gates: usize,
+    rng: &mut SeededRng,
+) {
+    assert!(max_inputs <= MAX_WINDOW_INPUTS);
+    aig.topo_sort();
+    aig.make_canonical();
+    aig.cleanup();
+
+    let (view, _) = comb_view_with_translation(aig);
+    let patterns: Vec<Vec<u64>> = (0..NB_WORDS)
+        .map(|_| (0..view.nb_inputs()).map(|_| rng.gen()).collect())
+        .collect();
+    let mut sim = Patterns::new(aig, patterns);
+
+    let mut i = aig.nb_nodes();
+    while i > 0 && aig.nb_nodes() > 0 {
+        i = i.min(aig.nb_nodes()) - 1;
+        if !aig.gate(i).is_comb() || matches!(&aig.gate(i), Gate::Buf(_)) {
+            continue;
+        }
+        let (w, window) = aig.extract_window(&[i], max_inputs, MAX_WINDOW_SIZE);
+        if w.nb_inputs() > max_inputs {
+            continue;
+        }
+        let Some(cost) = freed_cost(aig, &window, &mut reference_counts(aig)) else {
+            continue;
+        };
+        if cost == 0 {
+            continue;
+        }
+        let function = truth_table(&w);
+        for _ in 0..MAX_RETRIES {
+            let care = sim.care_set(&window);
+            let nb_care = care.iter().filter(|c| **c).count();
+            let limit = max_gates.min(cost - 1).min(synthesis_gate_limit(nb_care));
+            let Some(replacement) = exact_synthesis(&function, &care, limit) else {
+                break;
+            };
+            let mut modified = aig.clone();
+            if modified.stitch_back(&window, &replacement).is_err() {
+                break;
+            }
+            modified.topo_sort();
+            if !care.iter().all(|c| *c) {
+                let (modified_view, _) = comb_view_with_translation(&modified);
+                let dc = dont_care_view(&sim.view, 0, None);
+                if let Some(assignment) = find_difference(&sim.view, &modified_view, &dc) {
+                    // Add the counterexample to the patterns, in the first bit of a new word
+                    let mut patterns = sim.patterns.clone();
+                    let word = assignment
+                        .iter()
+                        .map(|b| (rng.gen::<u64>() & !1) | *b as u64)
+                        .collect();
+                    patterns.push(word);
+                    sim = Patterns::new(aig, patterns);
+                    continue;
+                }
+            }
+            modified.make_canonical();
+            modified.cleanup();
+            *aig = modified;
+            sim = Patterns::new(aig, sim.patterns);
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::window_resynth;
+    use crate::equiv::check_equivalence_bounded;
+    use crate::{Gate, Network, Signal};
+
+    #[test]
+    fn test_irregular_cone() {
+        // Majority written with 5 gates
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        let x = aig.add(Gate::and(a, b));
+        let y = aig.add(Gate::and(a, c));
+        let z = aig.add(Gate::and(b, c));
+        let o1 = aig.add(Gate::and(!x, !y));
+        let o2 = aig.add(Gate::and(o1, !z));
+        aig.add_output(!o2);
+        let mut opt = aig.clone();
+        window_resynth(&mut opt, 6, 4);
+        opt.check();
+        assert_eq!(opt.nb_nodes(), 4);
+        check_equivalence_bounded(&aig, &opt, 1, false).unwrap();
+    }
+
+    #[test]
+    fn test_constant_output() {
+        // The whole network simplifies to a constant
+        let mut aig = Network::new();
+        aig.add_inputs(8);
+        let x0 = aig.add(Gate::and(!aig.input(5), aig.input(4)));
+        let x1 = aig.add(Gate::and(x0, !aig.input(4)));
+        aig.add_output(!x1);
+        let mut opt = aig.clone();
+        window_resynth(&mut opt, 6, 6);
+        opt.check();
+        assert_eq!(opt.nb_nodes(), 0);
+        assert_eq!(opt.output(0), Signal::one());
+        check_equivalence_bounded(&aig, &opt, 1, false).unwrap();
+    }
+
+    #[test]
+    fn test_dont_cares() {
+        // x and y are never both 1, which is only visible with don't cares in windows of 2
+        // inputs: the data of the flip-flop is 0
+        let mut aig = Network::new();
+        aig.add_inputs(3);
+        let (a, b, c) = (aig.input(0), aig.input(1), aig.input(2));
+        let x = aig.add(Gate::and(a, b));
+        let y = aig.add(Gate::and(!a, !b));
+        let z = aig.add(Gate::and(x, y));
+        let q = aig.add(Gate::dff(z, c, Signal::zero()));
+        let o = aig.add(Gate::xor(q, c));
+        aig.add_output(o);
+        let mut opt = aig.clone();
+        window_resynth(&mut opt, 2, 4);
+        opt.check();
+        assert_eq!(opt.nb_nodes(), 2);
+        assert!(matches!(&opt.gate(0), Gate::Dff([d, _, _], _) if *d == Signal::zero()));
+        check_equivalence_bounded(&aig, &opt, 2, false).unwrap();
+    }
+}
@@ -0,0 +1,34 @@
+//! Arithmetic on words of signals, least significant bit first
+
+use crate::{Gate, Network, Signal};
+
+/// Shift a word to the left, keeping the same width
+pub fn shift_left(x: &[Signal], shift: usize, width: usize) -> Vec<Signal> {
+    (0..width)
+        .map(|i| {
+            if i >= shift && i - shift < x.len() {
+                x[i - shift]
+            } else {
+                Signal::zero()
+            }
+        })
+        .collect()
+}
+
+/// Ripple-carry addition of two words of the same width, modulo the width
+pub fn add(aig: &mut Network, a: &[Signal], b: &[Signal], carry_in: Signal) -> Vec<Signal> {
+    assert_eq!(a.len(), b.len());
+    let mut c = carry_in;
+    let mut ret = Vec::new();
+    for (x, y) in a.iter().zip(b) {
+        ret.push(aig.add_canonical(Gate::xor3(*x, *y, c)));
+        c = aig.add_canonical(Gate::maj(*x, *y, c));
+    }
+    ret
+}
+
+/// Ripple-carry subtraction of two words of the same width, modulo the width
+pub fn sub(aig: &mut Network, a: &[Signal], b: &[Signal]) -> Vec<Signal> {
+    let not_b: Vec<Signal> = b.iter().map(|s| !*s).collect();
+    add(aig, a, &not_b, Signal::one())
+}
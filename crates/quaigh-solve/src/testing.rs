@@ -0,0 +1,149 @@
+//! Random networks and property checks, to test quaigh and the tools built on it
+//!
+//! This extends the [core testing module](quaigh_core::testing) with Sat-based properties.
+//!
+//! ```
+//! # use quaigh_solve::testing::{check_optimization, check_property, RandomNetwork};
+//! let generator = RandomNetwork::default();
+//! check_property(&generator, 0..5, |aig| check_optimization(aig, 1)).unwrap();
+//! ```
+
+pub use quaigh_core::testing::*;
+
+use crate::equiv::{
+    check_equivalence_bounded, check_equivalence_comb, difference, prove, random_counterexample,
+};
+use crate::optim::Pipeline;
+use crate::sim::simulate_comb;
+use crate::Network;
+
+/// Number of timesteps checked for sequential networks
+const NB_EQUIV_STEPS: usize = 3;
+
+/// Number of 64b words of random patterns used to double-check Sat proofs
+const NB_RANDOM_WORDS: usize = 16;
+
+/// Format a pattern as a string of 0 and 1
+fn pattern_string(pattern: &[bool]) -> String {
+    pattern.iter().map(|b| if *b { '1' } else { '0' }).collect()
+}
+
+/// Check that a transformation preserves the function of a network, with a Sat solver
+///
+/// Sequential networks are compared on their first timesteps, with flip-flops starting at zero.
+pub fn check_transform_equivalence<F: Fn(&mut Network)>(
+    aig: &Network,
+    transform: F,
+) -> Result<(), String> {
+    let mut transformed = aig.clone();
+    transform(&mut transformed);
+    if transformed.nb_inputs() != aig.nb_inputs() || transformed.nb_outputs() != aig.nb_outputs() {
+        return Err(format!(
+            "Interface changed from {} inputs and {} outputs to {} inputs and {} outputs",
+            aig.nb_inputs(),
+            aig.nb_outputs(),
+            transformed.nb_inputs(),
+            transformed.nb_outputs()
+        ));
+    }
+    if aig.is_comb() && transformed.is_comb() {
+        check_equivalence_comb(aig, &transformed, false)
+            .map_err(|p| format!("Not equivalent for inputs {}", pattern_string(&p)))
+    } else {
+        check_equivalence_bounded(aig, &transformed, NB_EQUIV_STEPS, false).map_err(|p| {
+            let steps: Vec<String> = p.iter().map(|v| pattern_string(v)).collect();
+            format!("Not equivalent for input sequence {}", steps.join(" "))
+        })
+    }
+}
+
+/// Check that optimization with a given effort preserves the function of a network
+pub fn check_optimization(aig: &Network, effort: u64) -> Result<(), String> {
+    check_transform_equivalence(aig, |a| Pipeline::from_effort(effort).run(a))
+}
+
+/// Check that the Sat solver and the simulator agree on the equivalence of two combinatorial
+/// networks
+///
+/// A Sat counterexample must give different outputs in simulation, and random simulation must not
+/// find a difference between networks that the Sat solver proves equivalent.
+pub fn check_sat_vs_simulation(a: &Network, b: &Network) -> Result<(), String> {
+    assert!(a.is_comb() && b.is_comb());
+    match prove(&difference(a, b)) {
+        Some(p) => {
+            if simulate_comb(a, &p) == simulate_comb(b, &p) {
+                Err(format!(
+                    "The Sat counterexample {} gives the same outputs in simulation",
+                    pattern_string(&p)
+                ))
+            } else {
+                Ok(())
+            }
+        }
+        None => match random_counterexample(a, b, NB_RANDOM_WORDS, 1) {
+            Some(p) => Err(format!(
+                "Proved equivalent by the Sat solver, but simulation differs for inputs {}",
+                pattern_string(&p)
+            )),
+            None => Ok(()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_optimization, check_property, check_sat_vs_simulation, check_transform_equivalence,
+        mutate, RandomNetwork,
+    };
+    use crate::network::NaryType;
+    use crate::Gate;
+
+    #[test]
+    fn test_optimization() {
+        let generator = RandomNetwork {
+            nb_gates: 30,
+            ..RandomNetwork::default()
+        };
+        check_property(&generator, 0..10, |aig| check_optimization(aig, 1)).unwrap();
+        let generator = RandomNetwork {
+            nb_inputs: 4,
+            nb_outputs: 2,
+            nb_gates: 10,
+            nb_dffs: 2,
+            ..RandomNetwork::default()
+        };
+        check_property(&generator, 0..5, |aig| check_optimization(aig, 1)).unwrap();
+    }
+
+    #[test]
+    fn test_sat_vs_simulation() {
+        let generator = RandomNetwork::default();
+        check_property(&generator, 0..10, |aig| {
+            check_sat_vs_simulation(aig, aig)?;
+            check_sat_vs_simulation(aig, &mutate(aig, 0))
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_broken_transform() {
+        // Replacing Nand gates by And gates is caught, and shrinks to a single gate
+        let generator = RandomNetwork::default();
+        let transform = |aig: &mut crate::Network| {
+            for i in 0..aig.nb_nodes() {
+                if let Gate::Nary(v, NaryType::Nand) = &aig.gate(i) {
+                    let g = Gate::Nary(v.clone(), NaryType::And);
+                    aig.replace(i, g);
+                }
+            }
+        };
+        let err = check_property(&generator, 0..20, |aig| {
+            check_transform_equivalence(aig, transform)
+        })
+        .unwrap_err();
+        assert!(err.message.starts_with("Not equivalent"), "{}", err);
+        assert_eq!(err.network.nb_outputs(), 1);
+        assert_eq!(err.network.nb_nodes(), 1);
+    }
+}
@@ -44,7 +44,7 @@
           ];
       };
       self = {
-        quaigh = rustPkgs.workspace.quaigh {};
+        quaigh = rustPkgs.workspace.quaigh-cli {};
         default = self.quaigh;
       };
     in
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use quaigh::fuzz_targets::{canonicalize_roundtrip, Error};
+
+fuzz_target!(|data: &[u8]| {
+    if let Err(Error::Mismatch(msg)) = canonicalize_roundtrip(data) {
+        panic!("{}", msg);
+    }
+});
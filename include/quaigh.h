@@ -0,0 +1,67 @@
+/*
+ * C API of quaigh, built with the quaigh_capi feature:
+ *     cargo rustc --release --features quaigh_capi --crate-type cdylib
+ *
+ * Networks and test patterns are opaque pointers, freed with quaigh_network_free and
+ * quaigh_patterns_free. Signals are 32b integers: 0 and 1 are the constants, and a signal is
+ * inverted by flipping its lowest bit.
+ *
+ * On error, functions returning a signal return -1, functions returning a pointer return NULL and
+ * functions returning an int return a negative value. quaigh_last_error describes the error.
+ */
+
+#ifndef QUAIGH_H
+#define QUAIGH_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define QUAIGH_ZERO ((uint32_t)0)
+#define QUAIGH_ONE ((uint32_t)1)
+#define QUAIGH_NOT(s) ((uint32_t)(s) ^ 1u)
+
+typedef struct Network QuaighNetwork;
+typedef struct TestPatterns QuaighPatterns;
+
+/* Description of the last error in this thread, or NULL */
+const char *quaigh_last_error(void);
+
+/* Networks */
+QuaighNetwork *quaigh_network_new(void);
+QuaighNetwork *quaigh_network_read(const char *path);
+int quaigh_network_write(const QuaighNetwork *net, const char *path);
+void quaigh_network_free(QuaighNetwork *net);
+size_t quaigh_network_nb_inputs(const QuaighNetwork *net);
+size_t quaigh_network_nb_outputs(const QuaighNetwork *net);
+size_t quaigh_network_nb_nodes(const QuaighNetwork *net);
+int64_t quaigh_network_output(const QuaighNetwork *net, size_t i);
+
+/* Construction */
+int64_t quaigh_network_add_input(QuaighNetwork *net);
+int quaigh_network_add_output(QuaighNetwork *net, uint32_t s);
+int64_t quaigh_network_add_and(QuaighNetwork *net, uint32_t a, uint32_t b);
+int64_t quaigh_network_add_xor(QuaighNetwork *net, uint32_t a, uint32_t b);
+int64_t quaigh_network_add_mux(QuaighNetwork *net, uint32_t s, uint32_t a, uint32_t b);
+int64_t quaigh_network_add_maj(QuaighNetwork *net, uint32_t a, uint32_t b, uint32_t c);
+int64_t quaigh_network_add_lut(QuaighNetwork *net, const uint32_t *inputs, size_t nb_inputs,
+                               const char *truth_table);
+int64_t quaigh_network_add_dff(QuaighNetwork *net, uint32_t d, uint32_t en, uint32_t res);
+int quaigh_network_set_dff_data(QuaighNetwork *net, uint32_t dff, uint32_t d);
+
+/* Algorithms */
+int quaigh_network_optimize(QuaighNetwork *net, uint64_t effort);
+int quaigh_network_simulate(const QuaighNetwork *net, const uint8_t *inputs, uint8_t *outputs);
+QuaighPatterns *quaigh_atpg(const QuaighNetwork *net, uint64_t seed);
+size_t quaigh_patterns_count(const QuaighPatterns *patterns);
+int quaigh_patterns_value(const QuaighPatterns *patterns, size_t pattern, size_t input);
+void quaigh_patterns_free(QuaighPatterns *patterns);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* QUAIGH_H */
@@ -0,0 +1,445 @@
+//! C API, to embed quaigh in other tools such as Yosys passes, or to use it from Python with ctypes
+//!
+//! The API is enabled by the `quaigh_capi` feature, and declared in `include/quaigh.h`. To build a
+//! shared library:
+//! ```bash
+//! cargo rustc --release --features quaigh_capi --crate-type cdylib
+//! ```
+//!
+//! Networks and test patterns are opaque pointers, that must be freed by [`quaigh_network_free`]
+//! and [`quaigh_patterns_free`]. Signals are 32b integers, as given by [`Signal::raw`]: 0 and 1 are
+//! the constants, and a signal is inverted by flipping its lowest bit.
+//!
+//! Functions that return a signal return -1 on error, functions that return a pointer return
+//! null, and functions that return an int return a negative value. The description of the last
+//! error of the thread is then given by [`quaigh_last_error`].
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+
+use volute::Lut;
+
+use crate::atpg::{generate_comb_test_patterns, Compaction};
+use crate::io::{read_network_file, write_network_file};
+use crate::optim::Pipeline;
+use crate::sim::simulate_comb;
+use crate::{Gate, Network, Signal};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Record the last error of the thread
+fn set_error(message: impl Into<String>) {
+    let message = message.into().replace('\0', " ");
+    LAST_ERROR.with(|e| *e.borrow_mut() = CString::new(message).ok());
+}
+
+/// Run a function, recording its error or panic as the last error
+fn run<T>(f: impl FnOnce() -> Result<T, String>) -> Option<T> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(r)) => Some(r),
+        Ok(Err(e)) => {
+            set_error(e);
+            None
+        }
+        Err(p) => {
+            let message = if let Some(s) = p.downcast_ref::<&str>() {
+                s.to_string()
+            } else if let Some(s) = p.downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "internal error".to_owned()
+            };
+            set_error(message);
+            None
+        }
+    }
+}
+
+/// Convert a nullable network pointer to a reference
+unsafe fn network<'a>(net: *const Network) -> Result<&'a Network, String> {
+    net.as_ref().ok_or_else(|| "Null network".to_owned())
+}
+
+/// Convert a nullable network pointer to a mutable reference
+unsafe fn network_mut<'a>(net: *mut Network) -> Result<&'a mut Network, String> {
+    net.as_mut().ok_or_else(|| "Null network".to_owned())
+}
+
+/// Convert a C string to a path
+unsafe fn to_path(p: *const c_char) -> Result<PathBuf, String> {
+    if p.is_null() {
+        return Err("Null path".to_owned());
+    }
+    let s = CStr::from_ptr(p)
+        .to_str()
+        .map_err(|_| "Path is not valid UTF-8".to_owned())?;
+    Ok(PathBuf::from(s))
+}
+
+/// Check that signals can be used in a network
+fn signals(net: &Network, raw: &[u32]) -> Result<Vec<Signal>, String> {
+    raw.iter()
+        .map(|r| {
+            let s = Signal::from_raw(*r);
+            if net.is_valid(s) {
+                Ok(s)
+            } else {
+                Err(format!("Invalid signal {}", r))
+            }
+        })
+        .collect()
+}
+
+/// Add a gate to a network, and return its signal
+unsafe fn add_gate(
+    net: *mut Network,
+    raw: &[u32],
+    gate: impl FnOnce(&[Signal]) -> Result<Gate, String>,
+) -> i64 {
+    run(|| {
+        let net = network_mut(net)?;
+        let g = gate(&signals(net, raw)?)?;
+        Ok(net.add(g).raw() as i64)
+    })
+    .unwrap_or(-1)
+}
+
+/// Description of the last error in this thread, or null if there was none
+///
+/// The string is owned by quaigh, and valid until the next call that fails.
+#[no_mangle]
+pub extern "C" fn quaigh_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| match &*e.borrow() {
+        Some(s) => s.as_ptr(),
+        None => std::ptr::null(),
+    })
+}
+
+/// Create an empty network
+#[no_mangle]
+pub extern "C" fn quaigh_network_new() -> *mut Network {
+    Box::into_raw(Box::new(Network::new()))
+}
+
+/// Read a network from a file, in any format supported by quaigh; returns null on error
+///
+/// # Safety
+/// `path` must be a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_read(path: *const c_char) -> *mut Network {
+    run(|| {
+        let aig = read_network_file(&to_path(path)?).map_err(|e| e.to_string())?;
+        Ok(Box::into_raw(Box::new(aig)))
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Write a network to a file, whose format is given by its extension
+///
+/// # Safety
+/// `net` must be a network created by quaigh, and `path` a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_write(net: *const Network, path: *const c_char) -> c_int {
+    run(|| {
+        write_network_file(&to_path(path)?, network(net)?).map_err(|e| e.to_string())?;
+        Ok(0)
+    })
+    .unwrap_or(-1)
+}
+
+/// Free a network; null is ignored
+///
+/// # Safety
+/// `net` must be a network created by quaigh, and not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_free(net: *mut Network) {
+    if !net.is_null() {
+        drop(Box::from_raw(net));
+    }
+}
+
+/// Number of inputs of a network
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_nb_inputs(net: *const Network) -> usize {
+    network(net).map(|n| n.nb_inputs()).unwrap_or(0)
+}
+
+/// Number of outputs of a network
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_nb_outputs(net: *const Network) -> usize {
+    network(net).map(|n| n.nb_outputs()).unwrap_or(0)
+}
+
+/// Number of gates and flip-flops of a network
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_nb_nodes(net: *const Network) -> usize {
+    network(net).map(|n| n.nb_nodes()).unwrap_or(0)
+}
+
+/// Signal of an output, or -1 if it does not exist
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_output(net: *const Network, i: usize) -> i64 {
+    run(|| {
+        let net = network(net)?;
+        if i >= net.nb_outputs() {
+            return Err(format!("Invalid output {}", i));
+        }
+        Ok(net.output(i).raw() as i64)
+    })
+    .unwrap_or(-1)
+}
+
+/// Add an input to a network, and return its signal
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_add_input(net: *mut Network) -> i64 {
+    run(|| Ok(network_mut(net)?.add_input().raw() as i64)).unwrap_or(-1)
+}
+
+/// Add an output to a network; returns a negative value if the signal is invalid
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_add_output(net: *mut Network, s: u32) -> c_int {
+    run(|| {
+        let net = network_mut(net)?;
+        let s = signals(net, &[s])?[0];
+        net.add_output(s);
+        Ok(0)
+    })
+    .unwrap_or(-1)
+}
+
+/// Add a 2-input And gate
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_add_and(net: *mut Network, a: u32, b: u32) -> i64 {
+    add_gate(net, &[a, b], |s| Ok(Gate::and(s[0], s[1])))
+}
+
+/// Add a 2-input Xor gate
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_add_xor(net: *mut Network, a: u32, b: u32) -> i64 {
+    add_gate(net, &[a, b], |s| Ok(Gate::xor(s[0], s[1])))
+}
+
+/// Add a Mux gate, returning `a` if `s` is 1 and `b` otherwise
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_add_mux(net: *mut Network, s: u32, a: u32, b: u32) -> i64 {
+    add_gate(net, &[s, a, b], |s| Ok(Gate::mux(s[0], s[1], s[2])))
+}
+
+/// Add a Majority gate
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_add_maj(net: *mut Network, a: u32, b: u32, c: u32) -> i64 {
+    add_gate(net, &[a, b, c], |s| Ok(Gate::maj(s[0], s[1], s[2])))
+}
+
+/// Add a Lut, with its truth table as an hexadecimal string
+///
+/// # Safety
+/// `net` must be a network created by quaigh, `inputs` an array of `nb_inputs` signals and
+/// `truth_table` a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_add_lut(
+    net: *mut Network,
+    inputs: *const u32,
+    nb_inputs: usize,
+    truth_table: *const c_char,
+) -> i64 {
+    if inputs.is_null() || truth_table.is_null() {
+        set_error("Null inputs or truth table");
+        return -1;
+    }
+    let raw = std::slice::from_raw_parts(inputs, nb_inputs);
+    let hex = CStr::from_ptr(truth_table).to_string_lossy();
+    add_gate(net, raw, |s| {
+        let lut = Lut::from_hex_string(s.len(), &hex)
+            .map_err(|_| format!("Invalid truth table {} for {} inputs", hex, s.len()))?;
+        Ok(Gate::lut(s, lut))
+    })
+}
+
+/// Add a flip-flop, with its data, enable and reset; its value is 0 initially
+///
+/// The data may use signals that are added later: use [`quaigh_network_set_dff_data`] to connect
+/// it once they are created.
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_add_dff(
+    net: *mut Network,
+    d: u32,
+    en: u32,
+    res: u32,
+) -> i64 {
+    add_gate(net, &[d, en, res], |s| Ok(Gate::dff(s[0], s[1], s[2])))
+}
+
+/// Change the data of a flip-flop, given by its signal
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_set_dff_data(net: *mut Network, dff: u32, d: u32) -> c_int {
+    run(|| {
+        let net = network_mut(net)?;
+        let s = signals(net, &[dff, d])?;
+        if !s[0].is_var() || s[0].is_inverted() || !net.gate(s[0].var() as usize).is_dff() {
+            return Err(format!("Signal {} is not a flip-flop", dff));
+        }
+        let i = s[0].var() as usize;
+        let g = match &net.gate(i) {
+            Gate::Dff([_, en, res], clock) => Gate::Dff([s[1], *en, *res], *clock),
+            _ => unreachable!(),
+        };
+        net.replace(i, g);
+        Ok(0)
+    })
+    .unwrap_or(-1)
+}
+
+/// Optimize a network, with an effort level as in the `opt` command (1 by default)
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_optimize(net: *mut Network, effort: u64) -> c_int {
+    run(|| {
+        let net = network_mut(net)?;
+        net.try_topo_sort().map_err(|e| e.to_string())?;
+        Pipeline::from_effort(effort).run(net);
+        Ok(0)
+    })
+    .unwrap_or(-1)
+}
+
+/// Simulate a combinatorial network, with one byte per input and output, 0 or 1
+///
+/// # Safety
+/// `net` must be a network created by quaigh, `inputs` an array of [`quaigh_network_nb_inputs`]
+/// bytes and `outputs` an array of [`quaigh_network_nb_outputs`] bytes.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_network_simulate(
+    net: *const Network,
+    inputs: *const u8,
+    outputs: *mut u8,
+) -> c_int {
+    run(|| {
+        let net = network(net)?;
+        if !net.is_comb() {
+            return Err("Simulation requires a combinatorial network".to_owned());
+        }
+        if inputs.is_null() || outputs.is_null() {
+            return Err("Null inputs or outputs".to_owned());
+        }
+        let values: Vec<bool> = std::slice::from_raw_parts(inputs, net.nb_inputs())
+            .iter()
+            .map(|v| *v != 0)
+            .collect();
+        let res = simulate_comb(net, &values);
+        let out = std::slice::from_raw_parts_mut(outputs, net.nb_outputs());
+        for (o, v) in out.iter_mut().zip(res) {
+            *o = v as u8;
+        }
+        Ok(0)
+    })
+    .unwrap_or(-1)
+}
+
+/// Test patterns generated by [`quaigh_atpg`]
+pub struct TestPatterns(Vec<Vec<bool>>);
+
+/// Generate test patterns for a combinatorial network, that detect all detectable stuck-at faults
+///
+/// Returns null on error.
+///
+/// # Safety
+/// `net` must be a network created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_atpg(net: *const Network, seed: u64) -> *mut TestPatterns {
+    run(|| {
+        let net = network(net)?;
+        if !net.is_comb() {
+            return Err("Test pattern generation requires a combinatorial network".to_owned());
+        }
+        let patterns =
+            generate_comb_test_patterns(net, seed, false, &[], None, Compaction::default());
+        Ok(Box::into_raw(Box::new(TestPatterns(patterns))))
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Number of test patterns
+///
+/// # Safety
+/// `patterns` must be test patterns created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_patterns_count(patterns: *const TestPatterns) -> usize {
+    patterns.as_ref().map(|p| p.0.len()).unwrap_or(0)
+}
+
+/// Value of an input in a test pattern: 0 or 1, or -1 if out of range
+///
+/// # Safety
+/// `patterns` must be test patterns created by quaigh.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_patterns_value(
+    patterns: *const TestPatterns,
+    pattern: usize,
+    input: usize,
+) -> c_int {
+    match patterns
+        .as_ref()
+        .and_then(|p| p.0.get(pattern))
+        .and_then(|p| p.get(input))
+    {
+        Some(v) => *v as c_int,
+        None => {
+            set_error(format!("Invalid pattern {} or input {}", pattern, input));
+            -1
+        }
+    }
+}
+
+/// Free test patterns; null is ignored
+///
+/// # Safety
+/// `patterns` must be test patterns created by quaigh, and not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn quaigh_patterns_free(patterns: *mut TestPatterns) {
+    if !patterns.is_null() {
+        drop(Box::from_raw(patterns));
+    }
+}
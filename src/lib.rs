@@ -7,7 +7,7 @@
 //!
 //! Quaigh provides a command line tool, that can be installed using
 //! [Cargo](https://doc.rust-lang.org/cargo/getting-started/installation.html):
-//! `cargo install quaigh`.
+//! `cargo install quaigh-cli`.
 //!
 //! To show available commands:
 //! ```bash
@@ -32,10 +32,17 @@
 //! ```bash
 //! quaigh opt mydesign.bench -o optimized.bench
 //! ```
+//! When built with the `abc` feature, an [ABC](https://github.com/berkeley-abc/abc) script can be run after
+//! the passes, through an AIGER file exchange: `quaigh opt mydesign.bench -o optimized.bench --abc "dc2; rewrite"`.
 //!
 //! Quaigh supports a subset of the [Blif](https://course.ece.cmu.edu/~ee760/760docs/blif.pdf) file format, as well
 //! as the simple Bench file format used by ISCAS benchmarks. Benchmarks can be downloaded
 //! [here](https://github.com/Coloquinte/moosic-yosys-plugin/releases/download/iscas_benchmarks/benchmarks.tar.xz).
+//! To embed quaigh in other tools, such as Yosys passes or Python scripts with ctypes, the `quaigh_capi` feature
+//! exposes a C API, declared in `include/quaigh.h`.
+//! For experiments in notebooks, the `python` feature provides Python bindings, built with [maturin](https://www.maturin.rs).
+//! For teaching demos, the `wasm` feature provides WebAssembly bindings to read, simplify and simulate networks in a browser,
+//! without the default features.
 //! More features will be added over time, such as technology mapping, operator optimization, ...
 //! The complete documentation is available on [docs.rs](https://docs.rs/crate/quaigh/latest).
 //!
@@ -45,7 +52,7 @@
 //! Inverters are implicit, occupying just one bit in [`Signal`](https://docs.rs/quaigh/latest/quaigh/network/struct.Signal.html).
 //! It supports many kinds of logic, and all can coexist in the same circuit:
 //! *   Complex gates such as Xor, Mux and Maj3 are all first class citizens;
-//! *   Flip-flops with enable and reset are represented directly.
+//! *   Flip-flops with enable and reset are represented directly, with their clock domain and edge.
 //!
 //! In most logic optimization libraries ([ABC](https://github.com/berkeley-abc/abc), [Mockturtle](https://github.com/lsils/mockturtle), ...),
 //! there are many different ways to represent logic, with separate datastructures: AIG, MIG, LUT, ...
@@ -78,16 +85,31 @@
 //! [simulation](https://docs.rs/quaigh/latest/quaigh/sim/index.html) (including fault simulation) and
 //! [test pattern generation](https://docs.rs/quaigh/latest/quaigh/atpg/index.html).
 //! For optimization and equivalence checking, Quaigh relies on other packages as much as possible:
-//! *   [Kissat](https://github.com/arminbiere/kissat) (using [rustsat](https://docs.rs/rustsat/)) as a Sat solver,
+//! *   [Kissat](https://github.com/arminbiere/kissat) or [CaDiCaL](https://github.com/arminbiere/cadical) (using [rustsat](https://docs.rs/rustsat/)) as a Sat solver,
+//!     selected with `--sat-backend`. A slower solver written in Rust is always available, to build without a C compiler;
 //! *   [Highs](https://github.com/ERGO-Code/HiGHS) (using [good_lp](https://docs.rs/good_lp/)) as an optimization solver.
+//!
+//! The code is split in several crates, all re-exported by `quaigh`:
+//! *   `quaigh-core` contains the datastructures, simulation and file formats, and does not require a C compiler;
+//! *   `quaigh-solve` contains equivalence checking, test pattern generation and optimization;
+//! *   `quaigh-cli` contains the command line tool.
+//!
+//! To use only the core datastructures, disable the default `solve` feature of `quaigh`.
+//! To build without a C compiler, disable the default `kissat` feature and enable `solve` alone.
 
 #![warn(missing_docs)]
 
-pub mod atpg;
-pub mod equiv;
-pub mod io;
-pub mod network;
-pub mod optim;
-pub mod sim;
+pub use quaigh_core::{error, fuzz_targets, io, network, sim, util};
+pub use quaigh_core::{Gate, Network, QuaighError, Signal};
+
+#[cfg(not(feature = "solve"))]
+pub use quaigh_core::{analysis, testing};
+#[cfg(feature = "solve")]
+pub use quaigh_solve::{analysis, atpg, benchmarking, equiv, mc, optim, testing};
 
-pub use network::{Gate, Network, Signal};
+#[cfg(feature = "quaigh_capi")]
+pub mod capi;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,436 @@
+//! Python bindings, to script experiments with quaigh in notebooks
+//!
+//! The bindings are enabled by the `python` feature. The Python module is built with
+//! [maturin](https://www.maturin.rs), using the `pyproject.toml` at the root of the repository:
+//! ```bash
+//! maturin develop --release
+//! ```
+//!
+//! ```python
+//! import quaigh
+//!
+//! aig = quaigh.Network.read("mydesign.bench")
+//! patterns = quaigh.generate_test_patterns(aig)
+//! coverage = [quaigh.fault_coverage(aig, patterns[:i]) for i in range(len(patterns) + 1)]
+//! ```
+//!
+//! Signals are immutable values, inverted with `~`. Functions that take a network expect the
+//! number of inputs and outputs to match, and raise `ValueError` otherwise.
+
+use std::path::PathBuf;
+
+use pyo3::exceptions::{PyIOError, PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use volute::Lut;
+
+use crate::atpg::{analyze_fault_detection, generate_comb_test_patterns, Compaction};
+use crate::equiv::{check_equivalence_bounded, check_equivalence_comb};
+use crate::io::{read_network_file, write_network_file};
+use crate::optim::Pipeline;
+use crate::sim::{simulate, simulate_comb};
+use crate::{Gate, Network, QuaighError, Signal};
+
+/// Convert a quaigh error to the corresponding Python exception
+fn to_py_err(e: QuaighError) -> PyErr {
+    match e {
+        QuaighError::Io { .. } => PyIOError::new_err(e.to_string()),
+        _ => PyValueError::new_err(e.to_string()),
+    }
+}
+
+/// Check that input values have one value per input of the network
+fn check_nb_inputs(aig: &Network, values: &[bool]) -> PyResult<()> {
+    if values.len() != aig.nb_inputs() {
+        return Err(PyValueError::new_err(format!(
+            "Expected {} input values, got {}",
+            aig.nb_inputs(),
+            values.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Check that two networks have the same interface
+fn check_same_interface(a: &Network, b: &Network) -> PyResult<()> {
+    if a.nb_inputs() != b.nb_inputs() || a.nb_outputs() != b.nb_outputs() {
+        return Err(PyValueError::new_err(format!(
+            "Networks have different interfaces: {} inputs and {} outputs vs {} inputs and {} outputs",
+            a.nb_inputs(),
+            a.nb_outputs(),
+            b.nb_inputs(),
+            b.nb_outputs()
+        )));
+    }
+    Ok(())
+}
+
+/// Check that a network is combinatorial
+fn check_comb(aig: &Network, what: &str) -> PyResult<()> {
+    if !aig.is_comb() {
+        return Err(PyValueError::new_err(format!(
+            "{} requires a combinatorial network",
+            what
+        )));
+    }
+    Ok(())
+}
+
+/// A signal in a network: a constant, an input or a gate, possibly inverted
+#[pyclass(name = "Signal", module = "quaigh", frozen, eq, hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PySignal(pub Signal);
+
+#[pymethods]
+impl PySignal {
+    /// Constant zero
+    #[staticmethod]
+    fn zero() -> PySignal {
+        PySignal(Signal::zero())
+    }
+
+    /// Constant one
+    #[staticmethod]
+    fn one() -> PySignal {
+        PySignal(Signal::one())
+    }
+
+    fn is_constant(&self) -> bool {
+        self.0.is_constant()
+    }
+
+    fn is_input(&self) -> bool {
+        self.0.is_input()
+    }
+
+    fn is_inverted(&self) -> bool {
+        self.0.is_inverted()
+    }
+
+    fn __invert__(&self) -> PySignal {
+        PySignal(!self.0)
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Signal({})", self.0)
+    }
+}
+
+/// Convert Python signals to quaigh signals
+fn to_signals(signals: &[PySignal]) -> Vec<Signal> {
+    signals.iter().map(|s| s.0).collect()
+}
+
+/// A logic gate, to be added to a network
+#[pyclass(name = "Gate", module = "quaigh", frozen, eq, hash)]
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PyGate(pub Gate);
+
+#[pymethods]
+impl PyGate {
+    /// 2-input And gate
+    #[staticmethod]
+    fn and_(a: PySignal, b: PySignal) -> PyGate {
+        PyGate(Gate::and(a.0, b.0))
+    }
+
+    /// 2-input Xor gate
+    #[staticmethod]
+    fn xor(a: PySignal, b: PySignal) -> PyGate {
+        PyGate(Gate::xor(a.0, b.0))
+    }
+
+    /// 3-input And gate
+    #[staticmethod]
+    fn and3(a: PySignal, b: PySignal, c: PySignal) -> PyGate {
+        PyGate(Gate::and3(a.0, b.0, c.0))
+    }
+
+    /// 3-input Xor gate
+    #[staticmethod]
+    fn xor3(a: PySignal, b: PySignal, c: PySignal) -> PyGate {
+        PyGate(Gate::xor3(a.0, b.0, c.0))
+    }
+
+    /// Mux gate, returning `a` if `s` is 1 and `b` otherwise
+    #[staticmethod]
+    fn mux(s: PySignal, a: PySignal, b: PySignal) -> PyGate {
+        PyGate(Gate::mux(s.0, a.0, b.0))
+    }
+
+    /// Majority gate
+    #[staticmethod]
+    fn maj(a: PySignal, b: PySignal, c: PySignal) -> PyGate {
+        PyGate(Gate::maj(a.0, b.0, c.0))
+    }
+
+    /// Buffer
+    #[staticmethod]
+    fn buf(a: PySignal) -> PyGate {
+        PyGate(Gate::Buf(a.0))
+    }
+
+    /// Lut, with its truth table as an hexadecimal string
+    #[staticmethod]
+    fn lut(inputs: Vec<PySignal>, truth_table: &str) -> PyResult<PyGate> {
+        let lut = Lut::from_hex_string(inputs.len(), truth_table).map_err(|_| {
+            PyValueError::new_err(format!(
+                "Invalid truth table {} for {} inputs",
+                truth_table,
+                inputs.len()
+            ))
+        })?;
+        Ok(PyGate(Gate::lut(&to_signals(&inputs), lut)))
+    }
+
+    /// Flip-flop, with its data, enable and reset; its value is 0 initially
+    #[staticmethod]
+    #[pyo3(signature = (d, en = PySignal(Signal::one()), res = PySignal(Signal::zero())))]
+    fn dff(d: PySignal, en: PySignal, res: PySignal) -> PyGate {
+        PyGate(Gate::dff(d.0, en.0, res.0))
+    }
+
+    /// Signals used by the gate
+    fn dependencies(&self) -> Vec<PySignal> {
+        self.0.dependencies().iter().map(|s| PySignal(*s)).collect()
+    }
+
+    fn is_comb(&self) -> bool {
+        self.0.is_comb()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Gate({})", self.0)
+    }
+}
+
+/// A logic network, with its inputs, outputs and gates
+#[pyclass(name = "Network", module = "quaigh")]
+#[derive(Clone, Default)]
+pub struct PyNetwork(pub Network);
+
+impl PyNetwork {
+    /// Check that the signals used by a gate exist in the network
+    fn check_gate(&self, gate: &Gate) -> PyResult<()> {
+        for s in gate.dependencies() {
+            if !self.0.is_valid(*s) {
+                return Err(PyValueError::new_err(format!("Invalid signal {}", s)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a gate index exists in the network
+    fn check_node(&self, i: usize) -> PyResult<()> {
+        if i >= self.0.nb_nodes() {
+            return Err(PyIndexError::new_err(format!("Invalid gate {}", i)));
+        }
+        Ok(())
+    }
+}
+
+#[pymethods]
+impl PyNetwork {
+    /// Create an empty network
+    #[new]
+    fn new() -> PyNetwork {
+        PyNetwork(Network::new())
+    }
+
+    /// Read a network from a file, in any format supported by quaigh
+    #[staticmethod]
+    fn read(path: PathBuf) -> PyResult<PyNetwork> {
+        Ok(PyNetwork(read_network_file(&path).map_err(to_py_err)?))
+    }
+
+    /// Write a network to a file, whose format is given by its extension
+    fn write(&self, path: PathBuf) -> PyResult<()> {
+        write_network_file(&path, &self.0).map_err(to_py_err)
+    }
+
+    fn nb_inputs(&self) -> usize {
+        self.0.nb_inputs()
+    }
+
+    fn nb_outputs(&self) -> usize {
+        self.0.nb_outputs()
+    }
+
+    /// Number of gates and flip-flops
+    fn nb_nodes(&self) -> usize {
+        self.0.nb_nodes()
+    }
+
+    fn is_comb(&self) -> bool {
+        self.0.is_comb()
+    }
+
+    fn input(&self, i: usize) -> PyResult<PySignal> {
+        if i >= self.0.nb_inputs() {
+            return Err(PyIndexError::new_err(format!("Invalid input {}", i)));
+        }
+        Ok(PySignal(self.0.input(i)))
+    }
+
+    fn output(&self, i: usize) -> PyResult<PySignal> {
+        if i >= self.0.nb_outputs() {
+            return Err(PyIndexError::new_err(format!("Invalid output {}", i)));
+        }
+        Ok(PySignal(self.0.output(i)))
+    }
+
+    fn gate(&self, i: usize) -> PyResult<PyGate> {
+        self.check_node(i)?;
+        Ok(PyGate(self.0.gate(i).clone()))
+    }
+
+    /// Signal of a gate or flip-flop
+    fn node(&self, i: usize) -> PyResult<PySignal> {
+        self.check_node(i)?;
+        Ok(PySignal(self.0.node(i)))
+    }
+
+    fn add_input(&mut self) -> PySignal {
+        PySignal(self.0.add_input())
+    }
+
+    fn add_output(&mut self, s: PySignal) -> PyResult<()> {
+        if !self.0.is_valid(s.0) {
+            return Err(PyValueError::new_err(format!("Invalid signal {}", s.0)));
+        }
+        self.0.add_output(s.0);
+        Ok(())
+    }
+
+    /// Add a gate, and return its signal
+    fn add(&mut self, gate: PyGate) -> PyResult<PySignal> {
+        self.check_gate(&gate.0)?;
+        Ok(PySignal(self.0.add(gate.0)))
+    }
+
+    /// Replace a gate, for example to connect a flip-flop once its data is created
+    fn replace(&mut self, i: usize, gate: PyGate) -> PyResult<PySignal> {
+        self.check_node(i)?;
+        self.check_gate(&gate.0)?;
+        Ok(PySignal(self.0.replace(i, gate.0)))
+    }
+
+    /// Optimize the network, with an effort level as in the `opt` command
+    #[pyo3(signature = (effort = 1))]
+    fn optimize(&mut self, effort: u64) -> PyResult<()> {
+        self.0.try_topo_sort().map_err(to_py_err)?;
+        Pipeline::from_effort(effort).run(&mut self.0);
+        Ok(())
+    }
+
+    fn copy(&self) -> PyNetwork {
+        self.clone()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Network({} inputs, {} outputs, {} nodes)",
+            self.0.nb_inputs(),
+            self.0.nb_outputs(),
+            self.0.nb_nodes()
+        )
+    }
+}
+
+/// Simulate a network over several timesteps, with one list of input values per timestep
+#[pyfunction(name = "simulate")]
+fn py_simulate(aig: &PyNetwork, inputs: Vec<Vec<bool>>) -> PyResult<Vec<Vec<bool>>> {
+    for v in &inputs {
+        check_nb_inputs(&aig.0, v)?;
+    }
+    Ok(simulate(&aig.0, &inputs))
+}
+
+/// Simulate a combinatorial network
+#[pyfunction(name = "simulate_comb")]
+fn py_simulate_comb(aig: &PyNetwork, inputs: Vec<bool>) -> PyResult<Vec<bool>> {
+    check_comb(&aig.0, "Combinatorial simulation")?;
+    check_nb_inputs(&aig.0, &inputs)?;
+    Ok(simulate_comb(&aig.0, &inputs))
+}
+
+/// Check the equivalence of two combinatorial networks
+///
+/// Returns None if they are equivalent, or input values for which their outputs differ.
+#[pyfunction(name = "check_equivalence_comb")]
+fn py_check_equivalence_comb(a: &PyNetwork, b: &PyNetwork) -> PyResult<Option<Vec<bool>>> {
+    check_comb(&a.0, "Combinatorial equivalence")?;
+    check_comb(&b.0, "Combinatorial equivalence")?;
+    check_same_interface(&a.0, &b.0)?;
+    Ok(check_equivalence_comb(&a.0, &b.0, false).err())
+}
+
+/// Check the equivalence of two sequential networks over a number of timesteps
+///
+/// Returns None if they are equivalent, or input values for each timestep for which their outputs
+/// differ.
+#[pyfunction(name = "check_equivalence_bounded")]
+fn py_check_equivalence_bounded(
+    a: &PyNetwork,
+    b: &PyNetwork,
+    nb_steps: usize,
+) -> PyResult<Option<Vec<Vec<bool>>>> {
+    check_same_interface(&a.0, &b.0)?;
+    Ok(check_equivalence_bounded(&a.0, &b.0, nb_steps, false).err())
+}
+
+/// Generate test patterns for a combinatorial network, that detect all detectable stuck-at faults
+#[pyfunction(name = "generate_test_patterns")]
+#[pyo3(signature = (aig, seed = 1))]
+fn py_generate_test_patterns(aig: &PyNetwork, seed: u64) -> PyResult<Vec<Vec<bool>>> {
+    check_comb(&aig.0, "Test pattern generation")?;
+    Ok(generate_comb_test_patterns(
+        &aig.0,
+        seed,
+        false,
+        &[],
+        None,
+        Compaction::default(),
+    ))
+}
+
+/// Fraction of the stuck-at faults of a combinatorial network detected by test patterns
+#[pyfunction(name = "fault_coverage")]
+fn py_fault_coverage(aig: &PyNetwork, patterns: Vec<Vec<bool>>) -> PyResult<f64> {
+    check_comb(&aig.0, "Fault coverage")?;
+    for p in &patterns {
+        check_nb_inputs(&aig.0, p)?;
+    }
+    let detection = analyze_fault_detection(&aig.0, &patterns, false);
+    if detection.is_empty() {
+        return Ok(1.0);
+    }
+    let nb_detected = detection.iter().filter(|(_, d)| *d).count();
+    Ok(nb_detected as f64 / detection.len() as f64)
+}
+
+/// The `quaigh` Python module
+#[pymodule]
+pub fn quaigh(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PySignal>()?;
+    m.add_class::<PyGate>()?;
+    m.add_class::<PyNetwork>()?;
+    m.add_function(wrap_pyfunction!(py_simulate, m)?)?;
+    m.add_function(wrap_pyfunction!(py_simulate_comb, m)?)?;
+    m.add_function(wrap_pyfunction!(py_check_equivalence_comb, m)?)?;
+    m.add_function(wrap_pyfunction!(py_check_equivalence_bounded, m)?)?;
+    m.add_function(wrap_pyfunction!(py_generate_test_patterns, m)?)?;
+    m.add_function(wrap_pyfunction!(py_fault_coverage, m)?)?;
+    Ok(())
+}
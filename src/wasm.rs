@@ -0,0 +1,154 @@
+//! WebAssembly bindings, to run quaigh in a browser for teaching demos
+//!
+//! The bindings are enabled by the `wasm` feature. The default `kissat` feature requires a C
+//! compiler, so default features are disabled to build with
+//! [wasm-pack](https://rustwasm.github.io/wasm-pack/):
+//! ```bash
+//! wasm-pack build --target web -- --no-default-features --features wasm
+//! ```
+//!
+//! There is no filesystem in the browser: networks and patterns are exchanged as the content of
+//! the files, with the format given by its usual extension.
+//!
+//! ```javascript
+//! import init, { Network } from "./pkg/quaigh.js";
+//!
+//! await init();
+//! const aig = Network.fromText("INPUT(a)\nINPUT(b)\nOUTPUT(x)\nx = XOR(a, b)\n", "bench");
+//! aig.simplify();
+//! console.log(aig.simulate("01\n11\n"));
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::io::{read_network, read_patterns, write_network, write_patterns};
+use crate::network::stats::stats;
+use crate::sim::{simulate, simulate_comb};
+use crate::Network;
+
+/// A logic network, usable from Javascript
+#[wasm_bindgen(js_name = Network)]
+#[derive(Clone, Default)]
+pub struct WasmNetwork(Network);
+
+#[wasm_bindgen(js_class = Network)]
+impl WasmNetwork {
+    /// Create an empty network
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WasmNetwork {
+        WasmNetwork(Network::new())
+    }
+
+    /// Read a network from the content of a file, in any format supported by quaigh
+    pub fn read(data: &[u8], format: &str) -> Result<WasmNetwork, String> {
+        Ok(WasmNetwork(
+            read_network(data, format).map_err(|e| e.to_string())?,
+        ))
+    }
+
+    /// Read a network from the content of a text file, such as .bench or .blif
+    #[wasm_bindgen(js_name = fromText)]
+    pub fn from_text(text: &str, format: &str) -> Result<WasmNetwork, String> {
+        WasmNetwork::read(text.as_bytes(), format)
+    }
+
+    /// Write the network to the content of a file, in any format supported by quaigh
+    pub fn write(&self, format: &str) -> Result<Vec<u8>, String> {
+        write_network(&self.0, format).map_err(|e| e.to_string())
+    }
+
+    /// Write the network to the content of a text file, such as .bench or .blif
+    #[wasm_bindgen(js_name = toText)]
+    pub fn to_text(&self, format: &str) -> Result<String, String> {
+        String::from_utf8(self.write(format)?)
+            .map_err(|_| format!("Format {} is not a text format", format))
+    }
+
+    /// Number of inputs
+    #[wasm_bindgen(js_name = nbInputs)]
+    pub fn nb_inputs(&self) -> usize {
+        self.0.nb_inputs()
+    }
+
+    /// Number of outputs
+    #[wasm_bindgen(js_name = nbOutputs)]
+    pub fn nb_outputs(&self) -> usize {
+        self.0.nb_outputs()
+    }
+
+    /// Number of gates and flip-flops
+    #[wasm_bindgen(js_name = nbNodes)]
+    pub fn nb_nodes(&self) -> usize {
+        self.0.nb_nodes()
+    }
+
+    /// Whether the network is combinatorial
+    #[wasm_bindgen(js_name = isComb)]
+    pub fn is_comb(&self) -> bool {
+        self.0.is_comb()
+    }
+
+    /// Statistics on the network, as shown by `quaigh show`
+    pub fn stats(&self) -> String {
+        stats(&self.0).to_string()
+    }
+
+    /// Simplify the network: propagate constants, merge duplicate gates and remove unused logic
+    ///
+    /// Optimizations that require a Sat solver are not available.
+    pub fn simplify(&mut self) -> Result<(), String> {
+        self.0.try_topo_sort().map_err(|e| e.to_string())?;
+        self.0.make_canonical();
+        self.0.cleanup();
+        Ok(())
+    }
+
+    /// Simulate a combinatorial network, with one value per input, 0 or 1
+    #[wasm_bindgen(js_name = simulateComb)]
+    pub fn simulate_comb(&self, inputs: &[u8]) -> Result<Vec<u8>, String> {
+        if !self.0.is_comb() {
+            return Err("Simulation requires a combinatorial network".to_owned());
+        }
+        let values: Vec<bool> = inputs.iter().map(|v| *v != 0).collect();
+        self.check_nb_inputs(&values)?;
+        Ok(simulate_comb(&self.0, &values)
+            .into_iter()
+            .map(|v| v as u8)
+            .collect())
+    }
+
+    /// Simulate patterns, given as the content of a pattern file, and return the output values in
+    /// the same format
+    pub fn simulate(&self, patterns: &str) -> Result<String, String> {
+        let patterns = read_patterns(patterns.as_bytes())?;
+        for p in &patterns {
+            for v in p {
+                self.check_nb_inputs(v)?;
+            }
+        }
+        let outputs: Vec<Vec<Vec<bool>>> = patterns.iter().map(|p| simulate(&self.0, p)).collect();
+        let mut ret = Vec::new();
+        write_patterns(&mut ret, &outputs);
+        Ok(String::from_utf8(ret).unwrap())
+    }
+
+    /// Representation of the network, with one line per gate
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+impl WasmNetwork {
+    /// Check that input values have one value per input of the network
+    fn check_nb_inputs(&self, values: &[bool]) -> Result<(), String> {
+        if values.len() != self.0.nb_inputs() {
+            return Err(format!(
+                "Expected {} input values, got {}",
+                self.0.nb_inputs(),
+                values.len()
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+//! Check the C API, as it would be used from C or Python
+#![cfg(feature = "quaigh_capi")]
+
+use std::ffi::{CStr, CString};
+
+use quaigh::capi::*;
+
+fn last_error() -> String {
+    let e = quaigh_last_error();
+    assert!(!e.is_null());
+    unsafe { CStr::from_ptr(e) }.to_string_lossy().into_owned()
+}
+
+#[test]
+fn test_capi() {
+    unsafe {
+        // Full adder
+        let net = quaigh_network_new();
+        let a = quaigh_network_add_input(net) as u32;
+        let b = quaigh_network_add_input(net) as u32;
+        let c = quaigh_network_add_input(net) as u32;
+        let carry = quaigh_network_add_maj(net, a, b, c) as u32;
+        let t = quaigh_network_add_xor(net, a, b) as u32;
+        let sum = quaigh_network_add_lut(net, [t, c].as_ptr(), 2, c"6".as_ptr()) as u32;
+        assert_eq!(quaigh_network_add_output(net, carry), 0);
+        assert_eq!(quaigh_network_add_output(net, sum ^ 1), 0);
+        assert_eq!(quaigh_network_nb_inputs(net), 3);
+        assert_eq!(quaigh_network_nb_outputs(net), 2);
+        assert_eq!(quaigh_network_output(net, 1), (sum ^ 1) as i64);
+
+        let mut outputs = [0u8; 2];
+        assert_eq!(
+            quaigh_network_simulate(net, [1, 1, 0].as_ptr(), outputs.as_mut_ptr()),
+            0
+        );
+        assert_eq!(outputs, [1, 1]);
+        assert_eq!(quaigh_network_optimize(net, 1), 0);
+        assert_eq!(
+            quaigh_network_simulate(net, [1, 0, 0].as_ptr(), outputs.as_mut_ptr()),
+            0
+        );
+        assert_eq!(outputs, [0, 0]);
+
+        let patterns = quaigh_atpg(net, 1);
+        assert!(!patterns.is_null());
+        assert!(quaigh_patterns_count(patterns) > 0);
+        assert!(quaigh_patterns_value(patterns, 0, 2) >= 0);
+        assert_eq!(quaigh_patterns_value(patterns, 0, 3), -1);
+        quaigh_patterns_free(patterns);
+
+        // Errors
+        assert_eq!(quaigh_network_add_and(net, a, 1000), -1);
+        assert_eq!(last_error(), "Invalid signal 1000");
+        assert_eq!(quaigh_network_output(net, 2), -1);
+        let lut = quaigh_network_add_lut(net, [a].as_ptr(), 1, c"zz".as_ptr());
+        assert_eq!(lut, -1);
+        assert!(last_error().starts_with("Invalid truth table"));
+        let path = CString::new("missing.bench").unwrap();
+        assert!(quaigh_network_read(path.as_ptr()).is_null());
+        assert!(last_error().contains("missing.bench"));
+        quaigh_network_free(net);
+    }
+}
+
+#[test]
+fn test_capi_dff() {
+    unsafe {
+        // Toggle flip-flop, whose data is connected after its creation
+        let net = quaigh_network_new();
+        let en = quaigh_network_add_input(net) as u32;
+        let ff = quaigh_network_add_dff(net, 0, 1, 0) as u32;
+        let d = quaigh_network_add_xor(net, ff, en) as u32;
+        assert_eq!(quaigh_network_set_dff_data(net, ff, d), 0);
+        assert_eq!(quaigh_network_set_dff_data(net, d, d), -1);
+        assert_eq!(quaigh_network_add_output(net, ff), 0);
+        let mut outputs = [0u8; 1];
+        assert_eq!(
+            quaigh_network_simulate(net, [1].as_ptr(), outputs.as_mut_ptr()),
+            -1
+        );
+        assert!(quaigh_atpg(net, 1).is_null());
+
+        let dir = std::env::temp_dir().join(format!("quaigh_capi_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = CString::new(dir.join("toggle.bench").to_str().unwrap()).unwrap();
+        assert_eq!(quaigh_network_write(net, path.as_ptr()), 0);
+        let read = quaigh_network_read(path.as_ptr());
+        assert!(!read.is_null());
+        assert_eq!(quaigh_network_nb_nodes(read), 2);
+        quaigh_network_free(read);
+        quaigh_network_free(net);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
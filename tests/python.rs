@@ -0,0 +1,91 @@
+//! Check the Python bindings, as they would be used from a script
+#![cfg(feature = "python")]
+
+use pyo3::prelude::*;
+use pyo3::{py_run, wrap_pymodule};
+
+/// Run a Python script with the quaigh module available
+fn run_python(script: &str) {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let quaigh = wrap_pymodule!(quaigh::python::quaigh)(py);
+        py_run!(py, quaigh, script);
+    });
+}
+
+#[test]
+fn test_python_comb() {
+    run_python(
+        r#"
+# Full adder
+aig = quaigh.Network()
+a, b, c = aig.add_input(), aig.add_input(), aig.add_input()
+s = aig.add(quaigh.Gate.xor3(a, b, c))
+carry = aig.add(quaigh.Gate.maj(a, b, c))
+aig.add_output(s)
+aig.add_output(~carry)
+assert (aig.nb_inputs(), aig.nb_outputs(), aig.nb_nodes()) == (3, 2, 2)
+assert aig.output(1) == ~carry and aig.output(1).is_inverted()
+assert aig.gate(0).dependencies() == [a, b, c]
+assert quaigh.simulate_comb(aig, [True, True, False]) == [False, False]
+
+opt = aig.copy()
+opt.optimize()
+assert quaigh.check_equivalence_comb(aig, opt) is None
+wrong = quaigh.Network()
+for i in range(3):
+    wrong.add_input()
+wrong.add_output(wrong.add(quaigh.Gate.xor(wrong.input(0), wrong.input(1))))
+wrong.add_output(quaigh.Signal.one())
+cex = quaigh.check_equivalence_comb(aig, wrong)
+assert quaigh.simulate_comb(aig, cex) != quaigh.simulate_comb(wrong, cex)
+
+patterns = quaigh.generate_test_patterns(aig)
+assert quaigh.fault_coverage(aig, []) == 0.0
+assert quaigh.fault_coverage(aig, patterns) == 1.0
+
+big = quaigh.Network()
+x = big.add_input()
+for i in range(5):
+    x = big.add(quaigh.Gate.buf(x))
+try:
+    aig.add(quaigh.Gate.and_(a, big.node(4)))
+    assert False
+except ValueError:
+    pass
+try:
+    quaigh.Network.read("missing.bench")
+    assert False
+except IOError as e:
+    assert "missing.bench" in str(e)
+try:
+    quaigh.Gate.lut([a], "zz")
+    assert False
+except ValueError as e:
+    assert "Invalid truth table" in str(e)
+"#,
+    );
+}
+
+#[test]
+fn test_python_seq() {
+    run_python(
+        r#"
+# Toggle flip-flop, whose data is connected after its creation
+aig = quaigh.Network()
+en = aig.add_input()
+ff = aig.add(quaigh.Gate.dff(quaigh.Signal.zero()))
+d = aig.add(quaigh.Gate.xor(ff, en))
+aig.replace(0, quaigh.Gate.dff(d))
+aig.add_output(ff)
+assert not aig.is_comb()
+assert quaigh.simulate(aig, [[True], [False], [True], [True]]) == [[False], [True], [True], [False]]
+assert quaigh.check_equivalence_bounded(aig, aig.copy(), 3) is None
+try:
+    quaigh.simulate_comb(aig, [True])
+    assert False
+except ValueError:
+    pass
+"#,
+    );
+}
@@ -0,0 +1,50 @@
+//! Check that the public API is still available through the quaigh crate
+//!
+//! The modules are defined in quaigh-core and quaigh-solve, and existing users should not notice.
+
+use quaigh::io::{read_bench, write_bench};
+use quaigh::network::stats::stats;
+use quaigh::sim::simulate_comb;
+use quaigh::{Gate, Network, Signal};
+
+fn full_adder() -> Network {
+    let mut net = Network::new();
+    let i0 = net.add_input();
+    let i1 = net.add_input();
+    let i2 = net.add_input();
+    let carry = net.add(Gate::maj(i0, i1, i2));
+    let out = net.add(Gate::xor3(i0, i1, i2));
+    net.add_output(carry);
+    net.add_output(out);
+    net
+}
+
+#[test]
+fn test_core_reexports() {
+    let net = full_adder();
+    assert_eq!(
+        simulate_comb(&net, &vec![true, true, false]),
+        vec![true, false]
+    );
+    let mut buf = Vec::new();
+    write_bench(&mut buf, &net);
+    let read = read_bench(buf.as_slice()).unwrap();
+    assert_eq!(stats(&read).nb_inputs, 3);
+    assert_eq!(read.nb_outputs(), 2);
+    assert!(read.output(0) != Signal::zero());
+}
+
+#[cfg(feature = "solve")]
+#[test]
+fn test_solve_reexports() {
+    use quaigh::atpg::{generate_comb_test_patterns, Compaction};
+    use quaigh::equiv::check_equivalence_comb;
+    use quaigh::optim::Pipeline;
+
+    let net = full_adder();
+    let mut opt = net.clone();
+    Pipeline::from_effort(1).run(&mut opt);
+    check_equivalence_comb(&net, &opt, false).unwrap();
+    let patterns = generate_comb_test_patterns(&net, 1, false, &[], None, Compaction::default());
+    assert!(!patterns.is_empty());
+}
@@ -0,0 +1,44 @@
+//! Check the WebAssembly bindings, without a browser
+#![cfg(feature = "wasm")]
+
+use quaigh::wasm::WasmNetwork;
+
+const ADDER: &str = "INPUT(a)
+INPUT(b)
+INPUT(c)
+OUTPUT(s)
+OUTPUT(co)
+t = XOR(a, b)
+s = XOR(t, c)
+u = AND(a, b)
+v = AND(t, c)
+w = AND(a, b)
+co = OR(u, v, w)
+";
+
+#[test]
+fn test_wasm() {
+    let mut aig = WasmNetwork::from_text(ADDER, "bench").unwrap();
+    assert_eq!(aig.nb_inputs(), 3);
+    assert_eq!(aig.nb_outputs(), 2);
+    assert!(aig.is_comb());
+    let nb_nodes = aig.nb_nodes();
+    aig.simplify().unwrap();
+    assert!(aig.nb_nodes() < nb_nodes);
+    assert_eq!(aig.simulate_comb(&[1, 1, 0]).unwrap(), vec![0, 1]);
+    let outputs = aig.simulate("011\n2: 111 100\n").unwrap();
+    assert!(outputs.ends_with("1: 01\n2: 11 10\n"), "{}", outputs);
+    assert!(aig.stats().contains("Inputs"));
+
+    let data = aig.write("aig").unwrap();
+    let copy = WasmNetwork::read(&data, "aig").unwrap();
+    for i in 0..8 {
+        let inputs = [i & 1, (i >> 1) & 1, (i >> 2) & 1];
+        assert_eq!(copy.simulate_comb(&inputs), aig.simulate_comb(&inputs));
+    }
+
+    assert!(WasmNetwork::from_text("x = FOO(a)", "bench").is_err());
+    assert!(WasmNetwork::from_text(ADDER, "xyz").is_err());
+    assert!(aig.simulate_comb(&[1]).is_err());
+    assert!(aig.simulate("01\n").is_err());
+}